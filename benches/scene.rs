@@ -0,0 +1,188 @@
+use std::collections::HashMap;
+
+use criterion::criterion_group;
+use criterion::criterion_main;
+use criterion::BatchSize;
+use criterion::Criterion;
+use glam::Vec3;
+use pulse::systems;
+use pulse::LocalTransform;
+use pulse::Node;
+use pulse::Scene;
+use pulse::Visibility;
+use pulse::WorldTransform;
+
+const NODES: usize = 10_000;
+const STATIC_NODES: usize = 50_000;
+const MOVING_NODES: usize = 50;
+
+fn scene_with_transforms() -> Scene {
+    let mut scene = Scene::new();
+    for i in 0..NODES {
+        let node = scene.spawn_with(LocalTransform::IDENTITY);
+        if i % 2 == 0 {
+            scene.add(node, Visibility::Visible);
+        }
+    }
+    systems::compute_world_transform(&mut scene);
+    scene
+}
+
+/// Returns a propagated scene of groups of ten nested nodes that is mostly static.
+fn static_scene() -> (Scene, Vec<Node>) {
+    let mut scene = Scene::new();
+    let mut nodes = Vec::with_capacity(STATIC_NODES);
+    for i in 0..STATIC_NODES {
+        let node = scene.spawn_with(LocalTransform::from_position(Vec3::X));
+        if i % 10 != 0 {
+            scene.set_parent(node, nodes[i - 1]);
+        }
+        nodes.push(node);
+    }
+
+    systems::compute_world_transform(&mut scene);
+    scene.advance_change_tick();
+    systems::compute_world_transform(&mut scene);
+    scene.advance_change_tick();
+    (scene, nodes)
+}
+
+fn query(c: &mut Criterion) {
+    let scene = scene_with_transforms();
+
+    c.bench_function("query_one_component", |b| {
+        b.iter(|| scene.query::<(LocalTransform,)>().count())
+    });
+
+    c.bench_function("query_two_components", |b| {
+        b.iter(|| scene.query::<(LocalTransform, Visibility)>().count())
+    });
+
+    c.bench_function("query_local_and_world_transforms", |b| {
+        b.iter(|| scene.query::<(LocalTransform, WorldTransform)>().count())
+    });
+
+    // Queries looked up the other components per node through the scene before they resolved each
+    // component's table once.
+    c.bench_function("query_local_and_world_transforms_get_per_node", |b| {
+        b.iter(|| {
+            scene
+                .query::<(LocalTransform,)>()
+                .filter_map(|(node, transform)| {
+                    Some((transform, scene.get::<WorldTransform>(node)?))
+                })
+                .count()
+        })
+    });
+
+    // Components were stored in a hash map per type before they were stored in sparse sets.
+    let local = scene
+        .query::<(LocalTransform,)>()
+        .map(|(node, transform)| (node, *transform))
+        .collect::<HashMap<_, _>>();
+    let world = scene
+        .query::<(WorldTransform,)>()
+        .map(|(node, transform)| (node, *transform))
+        .collect::<HashMap<_, _>>();
+    c.bench_function("query_local_and_world_transforms_hash_maps", |b| {
+        b.iter(|| {
+            local
+                .iter()
+                .filter_map(|(node, transform)| Some((transform, world.get(node)?)))
+                .count()
+        })
+    });
+
+    c.bench_function("get_per_node", |b| {
+        let nodes = scene
+            .query::<(LocalTransform,)>()
+            .map(|(node, _)| node)
+            .collect::<Vec<_>>();
+        b.iter(|| {
+            nodes
+                .iter()
+                .filter(|node| scene.get::<Visibility>(**node).is_some())
+                .count()
+        })
+    });
+}
+
+fn spawn(c: &mut Criterion) {
+    c.bench_function("spawn_with_two_components", |b| {
+        b.iter_batched(
+            Scene::new,
+            |mut scene| {
+                for _ in 0..NODES {
+                    scene.spawn_with((LocalTransform::IDENTITY, Visibility::Visible));
+                }
+                scene
+            },
+            BatchSize::LargeInput,
+        )
+    });
+
+    c.bench_function("spawn_batch_with_two_components", |b| {
+        b.iter_batched(
+            Scene::new,
+            |mut scene| {
+                let nodes = scene.spawn_batch(NODES);
+                scene.add_batch(nodes.iter().map(|node| (*node, LocalTransform::IDENTITY)));
+                scene.add_batch(nodes.iter().map(|node| (*node, Visibility::Visible)));
+                scene
+            },
+            BatchSize::LargeInput,
+        )
+    });
+}
+
+fn transform_propagation(c: &mut Criterion) {
+    c.bench_function("compute_world_transform", |b| {
+        b.iter_batched_ref(
+            scene_with_transforms,
+            systems::compute_world_transform,
+            BatchSize::LargeInput,
+        )
+    });
+}
+
+fn incremental_transform_propagation(c: &mut Criterion) {
+    let (mut scene, nodes) = static_scene();
+
+    c.bench_function("compute_world_transform_static", |b| {
+        b.iter(|| {
+            systems::compute_world_transform(&mut scene);
+            scene.advance_change_tick();
+        })
+    });
+
+    c.bench_function("compute_world_transform_few_moving", |b| {
+        b.iter(|| {
+            for node in nodes.iter().step_by(STATIC_NODES / MOVING_NODES) {
+                scene.get_mut::<LocalTransform>(*node).unwrap().position.y += 1.0;
+            }
+            systems::compute_world_transform(&mut scene);
+            scene.advance_change_tick();
+            scene.clear_events();
+        })
+    });
+
+    c.bench_function("compute_world_transform_all_moving", |b| {
+        b.iter(|| {
+            for node in &nodes {
+                scene.get_mut::<LocalTransform>(*node).unwrap().position.y += 1.0;
+            }
+            systems::compute_world_transform(&mut scene);
+            scene.advance_change_tick();
+            scene.clear_events();
+        })
+    });
+}
+
+criterion_group!(
+    benches,
+    query,
+    spawn,
+    transform_propagation,
+    incremental_transform_propagation
+);
+criterion_main!(benches);
@@ -0,0 +1,26 @@
+//! Packs the files of an asset directory into an asset pack, e.g.
+//! `cargo run --example pack -- assets assets.pack`.
+
+use std::env;
+use std::process::ExitCode;
+
+use pulse::assets::pack;
+
+fn main() -> ExitCode {
+    let arguments = env::args().skip(1).collect::<Vec<_>>();
+    let [directory, pack] = arguments.as_slice() else {
+        eprintln!("Usage: pack <directory> <pack>");
+        return ExitCode::FAILURE;
+    };
+
+    match pack::build(directory, pack) {
+        Ok(count) => {
+            println!("Packed {count} files from {directory} into {pack}");
+            ExitCode::SUCCESS
+        }
+        Err(error) => {
+            eprintln!("Failed to pack {directory}: {error}");
+            ExitCode::FAILURE
+        }
+    }
+}
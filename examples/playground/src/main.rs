@@ -1,5 +1,18 @@
+use glam::UVec2;
+use glam::Vec3;
+use pulse::render::bloom::Bloom;
+use pulse::render::environment::EnvironmentLight;
+use pulse::render::image::HdrImage;
+use pulse::render::light::DirectionalLight;
+use pulse::render::light::PointLight;
+use pulse::render::material::Material;
+use pulse::render::material::StandardMaterial;
+use pulse::render::mesh::Mesh;
+use pulse::render::ClearColor;
+use pulse::render::Color;
 use pulse::Application;
 use pulse::ApplicationState;
+use pulse::Camera;
 use pulse::Event;
 use pulse::LocalTransform;
 use pulse::Scene;
@@ -14,9 +27,71 @@ impl Playground {
     fn new() -> Self {
         let mut scene = Scene::new();
 
-        let node = scene.spawn();
-        scene.add(node, Visibility::Visible);
-        scene.add(node, LocalTransform::IDENTITY);
+        scene.spawn_with((Visibility::Visible, LocalTransform::IDENTITY));
+        scene.spawn_with((
+            Camera::perspective(60f32.to_radians(), 16.0 / 9.0, 0.1, 100.0),
+            Bloom::default(),
+            LocalTransform::from_position(Vec3::new(0.0, 2.0, 6.0)).looking_at(Vec3::ZERO, Vec3::Y),
+        ));
+        let primitives = [
+            (Mesh::cube(1.0), Color::srgb(0.8, 0.2, 0.2), 0.0, 0.6),
+            (
+                Mesh::sphere(0.5, 32, 16),
+                Color::srgb(1.0, 0.77, 0.34),
+                1.0,
+                0.3,
+            ),
+            (
+                Mesh::capsule(0.4, 0.6, 32, 8),
+                Color::srgb(0.2, 0.4, 0.8),
+                0.0,
+                0.2,
+            ),
+        ];
+        for (index, (mesh, base_color, metallic, roughness)) in primitives.into_iter().enumerate() {
+            let x = index as f32 * 1.5 - 1.5;
+            let material = Material::new(StandardMaterial {
+                base_color,
+                metallic,
+                roughness,
+                ..StandardMaterial::default()
+            });
+            scene.spawn_with((
+                mesh,
+                material,
+                Visibility::Visible,
+                LocalTransform::from_position(Vec3::new(x, 0.5, 0.0)),
+            ));
+        }
+        scene.spawn_with((
+            Mesh::plane(10.0),
+            Material::new(StandardMaterial {
+                base_color: Color::srgb(0.5, 0.5, 0.5),
+                roughness: 0.9,
+                ..StandardMaterial::default()
+            }),
+            Visibility::Visible,
+            LocalTransform::IDENTITY,
+        ));
+        scene.spawn_with((
+            DirectionalLight {
+                shadows: true,
+                ..DirectionalLight::default()
+            },
+            LocalTransform::from_position(Vec3::new(2.0, 4.0, 3.0)).looking_at(Vec3::ZERO, Vec3::Y),
+        ));
+        scene.spawn_with((
+            PointLight {
+                color: Color::srgb(1.0, 0.6, 0.3),
+                ..PointLight::default()
+            },
+            LocalTransform::from_position(Vec3::new(-2.0, 1.5, 1.5)),
+        ));
+        scene.insert_resource(ClearColor(Color::srgb(0.1, 0.1, 0.15)));
+        scene.insert_resource(EnvironmentLight {
+            intensity: 0.5,
+            ..EnvironmentLight::new(sky())
+        });
 
         Self {
             state: ApplicationState::Running,
@@ -35,10 +110,8 @@ impl Application for Playground {
     }
 
     fn handle_event(&mut self, event: Event) {
-        match event {
-            Event::CloseRequested => {
-                self.state = ApplicationState::Finished;
-            }
+        if event == Event::CloseRequested {
+            self.state = ApplicationState::Finished;
         }
     }
 
@@ -47,8 +120,31 @@ impl Application for Playground {
     fn scene(&self) -> &Scene {
         &self.scene
     }
+
+    fn scene_mut(&mut self) -> &mut Scene {
+        &mut self.scene
+    }
+}
+
+/// Returns an equirectangular sky fading from blue at the top to white at the horizon, above
+/// brown ground.
+fn sky() -> HdrImage {
+    let size = UVec2::new(64, 32);
+    let pixels = (0..size.y)
+        .flat_map(|y| {
+            let height = 1.0 - (y as f32 + 0.5) / size.y as f32 * 2.0;
+            let color = if height > 0.0 {
+                Vec3::ONE.lerp(Vec3::new(0.3, 0.5, 1.0), height.sqrt())
+            } else {
+                Vec3::new(0.3, 0.2, 0.1)
+            };
+            (0..size.x).map(move |_| color.to_array())
+        })
+        .collect();
+
+    HdrImage::new(size, pixels).unwrap()
 }
 
-fn main() {
-    Playground::new().run();
+fn main() -> Result<(), pulse::Error> {
+    Playground::new().run()
 }
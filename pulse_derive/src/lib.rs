@@ -0,0 +1,199 @@
+#![warn(missing_docs)]
+
+//! # Pulse Derive
+//!
+//! Derive macros for the Pulse game engine. Use the re-exports from the `pulse` crate instead of
+//! depending on this crate directly.
+
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::parse_macro_input;
+use syn::Data;
+use syn::DeriveInput;
+use syn::Fields;
+use syn::Index;
+
+/// Derives `pulse::Component` for the type.
+#[proc_macro_derive(Component)]
+pub fn derive_component(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let name = &input.ident;
+
+    let mut generics = input.generics.clone();
+    for param in generics.type_params_mut() {
+        param.bounds.push(syn::parse_quote!(::pulse::Component));
+    }
+    let (impl_generics, type_generics, where_clause) = generics.split_for_impl();
+
+    quote! {
+        impl #impl_generics ::pulse::Component for #name #type_generics #where_clause {}
+    }
+    .into()
+}
+
+/// Derives `pulse::Tag` for the type.
+#[proc_macro_derive(Tag)]
+pub fn derive_tag(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let name = &input.ident;
+
+    let mut generics = input.generics.clone();
+    for param in generics.type_params_mut() {
+        param.bounds.push(syn::parse_quote!('static));
+    }
+    let (impl_generics, type_generics, where_clause) = generics.split_for_impl();
+
+    quote! {
+        impl #impl_generics ::pulse::Tag for #name #type_generics #where_clause {}
+    }
+    .into()
+}
+
+/// Derives `pulse::Relationship` for the type.
+#[proc_macro_derive(Relationship)]
+pub fn derive_relationship(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let name = &input.ident;
+
+    let mut generics = input.generics.clone();
+    for param in generics.type_params_mut() {
+        param.bounds.push(syn::parse_quote!('static));
+    }
+    let (impl_generics, type_generics, where_clause) = generics.split_for_impl();
+
+    quote! {
+        impl #impl_generics ::pulse::Relationship for #name #type_generics #where_clause {}
+    }
+    .into()
+}
+
+/// Derives `pulse::Bundle` for a struct whose fields are all bundles or components.
+#[proc_macro_derive(Bundle)]
+pub fn derive_bundle(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let name = &input.ident;
+
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => fields
+                .named
+                .iter()
+                .map(|field| {
+                    let ident = field.ident.as_ref().unwrap();
+                    quote!(#ident)
+                })
+                .collect::<Vec<_>>(),
+            Fields::Unnamed(fields) => (0..fields.unnamed.len())
+                .map(|index| {
+                    let index = Index::from(index);
+                    quote!(#index)
+                })
+                .collect(),
+            Fields::Unit => Vec::new(),
+        },
+        _ => {
+            return syn::Error::new_spanned(&input.ident, "Bundle can only be derived for structs")
+                .to_compile_error()
+                .into();
+        }
+    };
+
+    let mut generics = input.generics.clone();
+    for param in generics.type_params_mut() {
+        param.bounds.push(syn::parse_quote!(::pulse::Bundle));
+    }
+    let (impl_generics, type_generics, where_clause) = generics.split_for_impl();
+
+    quote! {
+        impl #impl_generics ::pulse::Bundle for #name #type_generics #where_clause {
+            fn add_to(self, scene: &mut ::pulse::Scene, node: ::pulse::Node) {
+                #(::pulse::Bundle::add_to(self.#fields, scene, node);)*
+            }
+        }
+    }
+    .into()
+}
+
+/// Derives `pulse::Reflect` for the type. Named struct fields are exposed by name, tuple struct
+/// fields by index, and enums are treated as opaque values.
+#[proc_macro_derive(Reflect)]
+pub fn derive_reflect(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let name = &input.ident;
+
+    let (names, members) = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => fields
+                .named
+                .iter()
+                .map(|field| {
+                    let ident = field.ident.as_ref().unwrap();
+                    (ident.to_string(), quote!(#ident))
+                })
+                .unzip(),
+            Fields::Unnamed(fields) => (0..fields.unnamed.len())
+                .map(|index| {
+                    let member = Index::from(index);
+                    (index.to_string(), quote!(#member))
+                })
+                .unzip(),
+            Fields::Unit => (Vec::new(), Vec::new()),
+        },
+        _ => (Vec::new(), Vec::new()),
+    };
+
+    let mut generics = input.generics.clone();
+    for param in generics.type_params_mut() {
+        param.bounds.push(syn::parse_quote!(::pulse::Reflect));
+        param.bounds.push(syn::parse_quote!(::std::clone::Clone));
+    }
+    let (impl_generics, type_generics, where_clause) = generics.split_for_impl();
+
+    quote! {
+        impl #impl_generics ::pulse::Reflect for #name #type_generics #where_clause {
+            fn type_name(&self) -> &'static str {
+                ::std::any::type_name::<Self>()
+            }
+
+            fn field_names(&self) -> &'static [&'static str] {
+                &[#(#names),*]
+            }
+
+            fn field(&self, name: &str) -> ::std::option::Option<&dyn ::pulse::Reflect> {
+                match name {
+                    #(#names => ::std::option::Option::Some(&self.#members),)*
+                    _ => ::std::option::Option::None,
+                }
+            }
+
+            fn field_mut(
+                &mut self,
+                name: &str,
+            ) -> ::std::option::Option<&mut dyn ::pulse::Reflect> {
+                match name {
+                    #(#names => ::std::option::Option::Some(&mut self.#members),)*
+                    _ => ::std::option::Option::None,
+                }
+            }
+
+            fn apply(&mut self, value: &dyn ::pulse::Reflect) -> bool {
+                match value.as_any().downcast_ref::<Self>() {
+                    ::std::option::Option::Some(value) => {
+                        *self = ::std::clone::Clone::clone(value);
+                        true
+                    }
+                    ::std::option::Option::None => false,
+                }
+            }
+
+            fn as_any(&self) -> &dyn ::std::any::Any {
+                self
+            }
+
+            fn as_any_mut(&mut self) -> &mut dyn ::std::any::Any {
+                self
+            }
+        }
+    }
+    .into()
+}
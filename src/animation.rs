@@ -0,0 +1,376 @@
+//! # Animation
+//!
+//! Keyframe animation of node transforms and morph weights, e.g. imported from glTF files. An
+//! [AnimationClip] holds the keyframes of the animated properties of its target nodes, and an
+//! [AnimationPlayer] component plays a clip, advanced and applied by [crate::systems::animate].
+
+use std::ops::Add;
+use std::ops::Mul;
+use std::sync::Arc;
+
+use glam::Quat;
+use glam::Vec3;
+
+use crate::render::morph::MorphWeights;
+use crate::Component;
+use crate::LocalTransform;
+use crate::Node;
+use crate::Scene;
+
+/// # Interpolation
+///
+/// How the values of an [AnimationChannel] are interpolated between keyframes, following the glTF
+/// conventions.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum Interpolation {
+    /// The value of the previous keyframe is held until the next keyframe.
+    Step,
+    /// Values are interpolated linearly, and rotations spherically.
+    #[default]
+    Linear,
+    /// Values are interpolated by a cubic Hermite spline. Each keyframe has an in-tangent, a
+    /// value, and an out-tangent, in that order.
+    CubicSpline,
+}
+
+/// # Keyframes
+///
+/// Values of an animated property at the times of an [AnimationChannel].
+#[derive(Clone, Debug, PartialEq)]
+pub enum Keyframes {
+    /// Positions of the node's [LocalTransform].
+    Translation(Vec<Vec3>),
+    /// Rotations of the node's [LocalTransform].
+    Rotation(Vec<Quat>),
+    /// Scales of the node's [LocalTransform].
+    Scale(Vec<Vec3>),
+    /// Weights of the node's [MorphWeights], one for each morph target at each keyframe.
+    Weights(Vec<f32>),
+}
+
+/// # Animation Channel
+///
+/// Keyframes of a property of the target node.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AnimationChannel {
+    /// Node whose property is animated.
+    pub target: Node,
+    /// Increasing times of the keyframes in seconds.
+    pub times: Vec<f32>,
+    /// Values of the property at the keyframes.
+    pub keyframes: Keyframes,
+    /// How the values are interpolated between keyframes.
+    pub interpolation: Interpolation,
+}
+
+impl AnimationChannel {
+    /// Sets the target's property to its value at the time. Times before the first or after the
+    /// last keyframe hold the value of that keyframe.
+    pub fn apply(&self, scene: &mut Scene, time: f32) {
+        let target = self.target;
+        if !scene.contains(target) {
+            return;
+        }
+
+        let mut transform = scene
+            .get::<LocalTransform>(target)
+            .copied()
+            .unwrap_or_default();
+        match &self.keyframes {
+            Keyframes::Translation(values) => {
+                transform.position = self.sample(values, 1, time, Vec3::lerp)[0];
+            }
+            Keyframes::Rotation(values) => {
+                transform.rotation = self.sample(values, 1, time, Quat::slerp)[0].normalize();
+            }
+            Keyframes::Scale(values) => {
+                transform.scale = self.sample(values, 1, time, Vec3::lerp)[0];
+            }
+            Keyframes::Weights(values) => {
+                let values_per_key = match self.interpolation {
+                    Interpolation::CubicSpline => 3,
+                    Interpolation::Step | Interpolation::Linear => 1,
+                };
+                let width = values.len() / (self.times.len() * values_per_key).max(1);
+                let weights = self.sample(values, width, time, |a, b, s| a + (b - a) * s);
+                if !weights.is_empty()
+                    && scene.get::<MorphWeights>(target).map(|w| &w.0) != Some(&weights)
+                {
+                    scene.set_or_add(target, MorphWeights(weights));
+                }
+                return;
+            }
+        }
+
+        if scene.get::<LocalTransform>(target) != Some(&transform) {
+            scene.set_or_add(target, transform);
+        }
+    }
+
+    /// Returns the `width` values at the time, mixing the values of neighbouring keyframes with
+    /// `mix` for linear interpolation.
+    fn sample<T>(
+        &self,
+        values: &[T],
+        width: usize,
+        time: f32,
+        mix: impl Fn(T, T, f32) -> T,
+    ) -> Vec<T>
+    where
+        T: Copy + Add<Output = T> + Mul<f32, Output = T>,
+    {
+        let keys = self.times.len();
+        let cubic = self.interpolation == Interpolation::CubicSpline;
+        let stride = if cubic { width * 3 } else { width };
+        if keys == 0 || width == 0 || values.len() < keys * stride {
+            return Vec::new();
+        }
+
+        // Offsets of the in-tangent, value, and out-tangent of each keyframe.
+        let (tangent_in, value, tangent_out) = if cubic {
+            (0, width, width * 2)
+        } else {
+            (0, 0, 0)
+        };
+        let at = |key: usize, offset: usize, index: usize| values[key * stride + offset + index];
+
+        let next = self.times.partition_point(|key_time| *key_time <= time);
+        if next == 0 || next == keys {
+            let key = next.min(keys - 1);
+            return (0..width).map(|index| at(key, value, index)).collect();
+        }
+
+        let key = next - 1;
+        let duration = self.times[next] - self.times[key];
+        let s = if duration > 0.0 {
+            (time - self.times[key]) / duration
+        } else {
+            0.0
+        };
+        (0..width)
+            .map(|index| match self.interpolation {
+                Interpolation::Step => at(key, value, index),
+                Interpolation::Linear => mix(at(key, value, index), at(next, value, index), s),
+                Interpolation::CubicSpline => {
+                    let (s2, s3) = (s * s, s * s * s);
+                    at(key, value, index) * (2.0 * s3 - 3.0 * s2 + 1.0)
+                        + at(key, tangent_out, index) * (duration * (s3 - 2.0 * s2 + s))
+                        + at(next, value, index) * (-2.0 * s3 + 3.0 * s2)
+                        + at(next, tangent_in, index) * (duration * (s3 - s2))
+                }
+            })
+            .collect()
+    }
+}
+
+/// # Animation Clip
+///
+/// Named set of [AnimationChannel]s played together, lasting until the last keyframe of its
+/// channels. The channels are shared between clones, and clips are equal if they share the same
+/// channels.
+///
+/// ```
+/// # use glam::Vec3;
+/// # use pulse::animation::AnimationChannel;
+/// # use pulse::animation::AnimationClip;
+/// # use pulse::animation::AnimationPlayer;
+/// # use pulse::animation::Interpolation;
+/// # use pulse::animation::Keyframes;
+/// # use pulse::LocalTransform;
+/// # use pulse::Scene;
+/// let mut scene = Scene::new();
+/// let node = scene.spawn_with(LocalTransform::default());
+///
+/// let bounce = AnimationClip::new(
+///     "bounce",
+///     vec![AnimationChannel {
+///         target: node,
+///         times: vec![0.0, 0.5, 1.0],
+///         keyframes: Keyframes::Translation(vec![Vec3::ZERO, Vec3::Y, Vec3::ZERO]),
+///         interpolation: Interpolation::Linear,
+///     }],
+/// );
+/// bounce.apply(&mut scene, 0.25);
+/// assert_eq!(scene.get::<LocalTransform>(node).unwrap().position, Vec3::Y * 0.5);
+///
+/// scene.set_or_add(node, AnimationPlayer::new(bounce));
+/// ```
+#[derive(Clone, Debug)]
+pub struct AnimationClip {
+    data: Arc<ClipData>,
+}
+
+#[derive(Debug)]
+struct ClipData {
+    name: String,
+    channels: Vec<AnimationChannel>,
+    duration: f32,
+}
+
+impl AnimationClip {
+    /// Returns the clip with the name and channels.
+    pub fn new(name: impl Into<String>, channels: Vec<AnimationChannel>) -> Self {
+        let duration = channels
+            .iter()
+            .filter_map(|channel| channel.times.last().copied())
+            .fold(0.0, f32::max);
+        Self {
+            data: Arc::new(ClipData {
+                name: name.into(),
+                channels,
+                duration,
+            }),
+        }
+    }
+
+    /// Returns the name of the clip.
+    pub fn name(&self) -> &str {
+        &self.data.name
+    }
+
+    /// Returns the channels of the clip.
+    pub fn channels(&self) -> &[AnimationChannel] {
+        &self.data.channels
+    }
+
+    /// Returns the time of the last keyframe in seconds.
+    pub fn duration(&self) -> f32 {
+        self.data.duration
+    }
+
+    /// Sets the properties of the clip's targets to their values at the time.
+    pub fn apply(&self, scene: &mut Scene, time: f32) {
+        for channel in self.channels() {
+            channel.apply(scene, time);
+        }
+    }
+}
+
+impl PartialEq for AnimationClip {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.data, &other.data)
+    }
+}
+
+/// # Animation Player
+///
+/// Component playing the clip, advanced by the frame's delta time and applied to the clip's
+/// targets by [crate::systems::animate].
+#[derive(Clone, Debug, PartialEq, Component)]
+pub struct AnimationPlayer {
+    /// Clip being played.
+    pub clip: AnimationClip,
+    /// Current time in the clip in seconds.
+    pub time: f32,
+    /// Factor of the playback speed, negative to play backwards.
+    pub speed: f32,
+    /// Whether the clip starts over when it ends, or holds its last frame.
+    pub looping: bool,
+    /// Whether the time advances. Paused clips are still applied.
+    pub playing: bool,
+}
+
+impl AnimationPlayer {
+    /// Returns a player of the clip looping it at normal speed from the start.
+    pub fn new(clip: AnimationClip) -> Self {
+        Self {
+            clip,
+            time: 0.0,
+            speed: 1.0,
+            looping: true,
+            playing: true,
+        }
+    }
+
+    /// Advances the time by the delta in seconds times the speed, wrapping or clamping it to the
+    /// clip's duration.
+    pub fn advance(&mut self, delta: f32) {
+        if !self.playing {
+            return;
+        }
+
+        let duration = self.clip.duration();
+        let time = self.time + delta * self.speed;
+        self.time = if self.looping && duration > 0.0 {
+            time.rem_euclid(duration)
+        } else {
+            time.clamp(0.0, duration)
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn channel(
+        target: Node,
+        keyframes: Keyframes,
+        interpolation: Interpolation,
+    ) -> AnimationChannel {
+        AnimationChannel {
+            target,
+            times: vec![1.0, 2.0],
+            keyframes,
+            interpolation,
+        }
+    }
+
+    #[test]
+    fn channels_interpolate_keyframes() {
+        let mut scene = Scene::new();
+        let node = scene.spawn();
+        let translation = Keyframes::Translation(vec![Vec3::ZERO, Vec3::X]);
+        let rotation = Keyframes::Rotation(vec![Quat::IDENTITY, Quat::from_rotation_y(1.0)]);
+        // In-tangent, value, and out-tangent of two weights at each keyframe.
+        let weights = Keyframes::Weights(vec![
+            0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0,
+        ]);
+
+        channel(node, translation.clone(), Interpolation::Linear).apply(&mut scene, 1.25);
+        let transform = *scene.get::<LocalTransform>(node).unwrap();
+        assert_eq!(transform.position, Vec3::new(0.25, 0.0, 0.0));
+        channel(node, translation, Interpolation::Step).apply(&mut scene, 1.75);
+        assert_eq!(
+            scene.get::<LocalTransform>(node).unwrap().position,
+            Vec3::ZERO
+        );
+        channel(node, rotation, Interpolation::Linear).apply(&mut scene, 3.0);
+        let rotation = scene.get::<LocalTransform>(node).unwrap().rotation;
+        assert!(rotation.abs_diff_eq(Quat::from_rotation_y(1.0), 1e-6));
+
+        channel(node, weights, Interpolation::CubicSpline).apply(&mut scene, 1.5);
+        assert_eq!(
+            scene.get::<MorphWeights>(node),
+            Some(&MorphWeights(vec![0.5, 0.5]))
+        );
+    }
+
+    #[test]
+    fn player_wraps_or_clamps_time() {
+        let clip = AnimationClip::new(
+            "scale",
+            vec![channel(
+                Scene::new().spawn(),
+                Keyframes::Scale(vec![Vec3::ONE; 2]),
+                Interpolation::Linear,
+            )],
+        );
+        let mut player = AnimationPlayer::new(clip);
+        assert_eq!(player.clip.duration(), 2.0);
+
+        player.advance(2.5);
+        assert_eq!(player.time, 0.5);
+        player.speed = -1.0;
+        player.advance(1.0);
+        assert_eq!(player.time, 1.5);
+
+        player.looping = false;
+        player.speed = 1.0;
+        player.advance(1.0);
+        assert_eq!(player.time, 2.0);
+        player.playing = false;
+        player.advance(-1.0);
+        assert_eq!(player.time, 2.0);
+    }
+}
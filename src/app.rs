@@ -1,12 +1,58 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use glam::UVec2;
+use glam::Vec2;
+use winit::dpi::PhysicalPosition;
+use winit::dpi::PhysicalSize;
+use winit::event::DeviceEvent;
+use winit::event::ElementState;
+use winit::event::Ime;
 use winit::event::WindowEvent;
 use winit::event_loop::ControlFlow;
 use winit::event_loop::EventLoop;
+use winit::event_loop::EventLoopWindowTarget;
+use winit::keyboard::PhysicalKey;
+#[cfg(all(target_arch = "wasm32", feature = "web"))]
+use winit::platform::web::EventLoopExtWebSys;
+#[cfg(all(target_arch = "wasm32", feature = "web"))]
+use winit::platform::web::WindowBuilderExtWebSys;
+use winit::window::Fullscreen;
 use winit::window::WindowBuilder;
 
+use crate::assets::AssetServer;
 use crate::components::WorldTransform;
-use crate::systems;
+use crate::input;
+#[cfg(feature = "gamepad")]
+use crate::input::gamepad::Gamepad;
+#[cfg(feature = "gamepad")]
+use crate::input::gamepad::GamepadAxis;
+#[cfg(feature = "gamepad")]
+use crate::input::gamepad::GamepadButton;
+#[cfg(feature = "gamepad")]
+use crate::input::gamepad::GamepadContext;
+use crate::input::Input;
+use crate::input::Key;
+use crate::input::MouseButton;
+use crate::input::MouseScrollUnit;
+use crate::render::Renderer;
+use crate::systems::Stages;
+use crate::time::Instant;
+use crate::AppBuilder;
 use crate::ComputedVisibility;
+use crate::Cursor;
+use crate::CursorGrab;
+use crate::Error;
+use crate::FramePacing;
+use crate::FrameWait;
+use crate::Monitor;
 use crate::Scene;
+use crate::Time;
+use crate::UserAttention;
+use crate::VideoMode;
+use crate::Window;
+use crate::WindowMode;
 
 /// # Application
 ///
@@ -20,22 +66,97 @@ pub trait Application: Sized {
     /// called.
     fn state(&self) -> ApplicationState;
 
-    /// Handles the incoming event.
+    /// Called once when the application starts, after the window and the scene's
+    /// [crate::render::Renderer] resource were created and the builder's startup hooks were run,
+    /// e.g. to create GPU resources against the window's surface.
+    fn startup(&mut self) {}
+
+    /// Called once before the application exits, e.g. to save its state.
+    fn shutdown(&mut self) {}
+
+    /// Handles the incoming event. The scene's [Input] and [Window] resources are already updated
+    /// from the event.
     fn handle_event(&mut self, event: Event);
 
-    /// Updates the application for the current frame.
+    /// Updates the application for the current frame. The frame timing is available as the
+    /// scene's [Time] resource.
     fn update(&mut self);
 
+    /// Adds the application's plugins, systems, resources, and hooks to the builder before the
+    /// application runs. Called before [Application::build_stages].
+    fn build(&mut self, _app: &mut AppBuilder) {}
+
+    /// Adds the application's systems to the stages run on the scene after every
+    /// [Application::update]. The [crate::systems::Stage::Render] schedule already contains the
+    /// built-in systems labelled [crate::systems::VISIBILITY] and [crate::systems::TRANSFORM] to
+    /// order against.
+    fn build_stages(&mut self, _stages: &mut Stages) {}
+
     /// Returns a reference to the application's scene.
     fn scene(&self) -> &Scene;
 
-    /// Runs the application.
-    fn run(self) {
-        run_application(self);
+    /// Returns a mutable reference to the application's scene.
+    fn scene_mut(&mut self) -> &mut Scene;
+
+    /// Runs the application until it's finished.
+    ///
+    /// On the web with the `web` feature, the application window is appended to the document body
+    /// as a canvas, frames are paced by `requestAnimationFrame`, and this returns immediately while
+    /// the application keeps running in the browser's event loop.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the application's systems can't be ordered, or the event loop, window,
+    /// or renderer can't be created, e.g. because there's no display server or graphics adapter.
+    fn run(self) -> Result<(), Error>
+    where
+        Self: 'static,
+    {
+        run_application(self)
+    }
+
+    /// Runs the application without a window or event loop, e.g. for tests and dedicated servers,
+    /// until it's finished or has run for [Headless::frames]. Returns the application so its final
+    /// state can be inspected. The scene has an [Input] resource without any input but no
+    /// [Window] resource.
+    ///
+    /// Frames are uncapped unless the scene has a [FramePacing] resource, in which case the runner
+    /// sleeps between frames to keep the tick rate, with [FramePacing::Vsync] limited to
+    /// [FramePacing::DEFAULT_REFRESH_RATE].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the application's systems can't be ordered.
+    fn run_headless(self, headless: Headless) -> Result<Self, Error> {
+        run_headless(self, headless)
     }
 }
 
+/// # Headless
+///
+/// Settings for running an application without a window through [Application::run_headless].
+///
+/// ```
+/// # use std::time::Duration;
+/// # use pulse::Headless;
+/// let headless = Headless {
+///     frames: Some(60),
+///     delta: Some(Duration::from_secs(1) / 60),
+/// };
+/// ```
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct Headless {
+    /// Number of frames to run, or `None` to run until the application is finished.
+    pub frames: Option<u64>,
+    /// Fixed duration of every frame for deterministic runs, or `None` to measure the actual
+    /// duration.
+    pub delta: Option<Duration>,
+}
+
 /// # Application State
+///
+/// Whether the application keeps running. Game states like menus and pause screens are managed
+/// with [crate::States] instead.
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum ApplicationState {
     /// Application is running.
@@ -45,59 +166,743 @@ pub enum ApplicationState {
 }
 
 /// # Event
-#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum Event {
     /// Application window requested to close.
     CloseRequested,
+    /// Application was suspended, e.g. when an Android app is sent to the background. GPU surfaces
+    /// must be dropped when this is received, as the native window may be destroyed.
+    Suspended,
+    /// Application was resumed, including once when the application starts. GPU surfaces must be
+    /// (re)created when this is received rather than before the first event.
+    Resumed,
+    /// Application window gained or lost keyboard focus.
+    Focused(bool),
+    /// Application window was resized.
+    Resized {
+        /// New inner width of the window in physical pixels.
+        width: u32,
+        /// New inner height of the window in physical pixels.
+        height: u32,
+    },
+    /// Scale factor of the application window changed, e.g. when the window was moved to a display
+    /// with a different DPI. The window is usually resized as well.
+    ScaleFactorChanged {
+        /// New ratio of physical to logical pixels.
+        scale_factor: f64,
+    },
+    /// File is being dragged over the application window. A [Event::FileDropped] or
+    /// [Event::FileHoverCancelled] event follows. Dragging multiple files sends an event for each.
+    FileHovered(PathBuf),
+    /// File was dropped onto the application window.
+    FileDropped(PathBuf),
+    /// Files dragged over the application window left the window without being dropped.
+    FileHoverCancelled,
+    /// Text was entered while the window was focused, either by pressing a key or by committing an
+    /// IME composition. Takes the keyboard layout into account, unlike [Event::KeyPressed], and
+    /// excludes control characters such as backspace.
+    TextInput(String),
+    /// IME was enabled. Only sent after IME was allowed through [Window::set_ime_allowed].
+    ImeEnabled,
+    /// IME composition text changed. The text isn't entered yet and should be displayed in place
+    /// of the text cursor until an [Event::TextInput] commits it. Empty text clears the
+    /// composition.
+    ImePreedit {
+        /// Text being composed.
+        text: String,
+        /// Byte range of the cursor within the text, or `None` to hide the cursor.
+        cursor: Option<(usize, usize)>,
+    },
+    /// IME was disabled.
+    ImeDisabled,
+    /// Keyboard key was pressed while the window was focused.
+    KeyPressed {
+        /// Pressed key.
+        key: Key,
+        /// Whether the event was repeated by the operating system while the key is held down.
+        repeat: bool,
+    },
+    /// Keyboard key was released while the window was focused.
+    KeyReleased {
+        /// Released key.
+        key: Key,
+    },
+    /// Cursor moved within the application window.
+    MouseMoved {
+        /// Position of the cursor in physical pixels relative to the top-left corner of the
+        /// window.
+        position: Vec2,
+        /// Distance the cursor moved since the previous event, or zero for the first event.
+        delta: Vec2,
+    },
+    /// Mouse moved, reported by the device without cursor acceleration or clamping to the window,
+    /// e.g. for first-person camera controls.
+    MouseMotion {
+        /// Distance the mouse moved in device-specific units.
+        delta: Vec2,
+    },
+    /// Mouse button was pressed while the window was focused.
+    MouseButtonPressed {
+        /// Pressed button.
+        button: MouseButton,
+    },
+    /// Mouse button was released while the window was focused.
+    MouseButtonReleased {
+        /// Released button.
+        button: MouseButton,
+    },
+    /// Mouse wheel or touchpad scrolled.
+    MouseWheel {
+        /// Scrolled distance, positive for scrolling right and up.
+        delta: Vec2,
+        /// Unit of the distance.
+        unit: MouseScrollUnit,
+    },
+    /// Gamepad was connected, including the gamepads connected when the application starts.
+    #[cfg(feature = "gamepad")]
+    GamepadConnected {
+        /// Connected gamepad.
+        gamepad: Gamepad,
+    },
+    /// Gamepad was disconnected.
+    #[cfg(feature = "gamepad")]
+    GamepadDisconnected {
+        /// Disconnected gamepad.
+        gamepad: Gamepad,
+    },
+    /// Gamepad button was pressed.
+    #[cfg(feature = "gamepad")]
+    GamepadButtonPressed {
+        /// Gamepad the button belongs to.
+        gamepad: Gamepad,
+        /// Pressed button.
+        button: GamepadButton,
+    },
+    /// Gamepad button was released.
+    #[cfg(feature = "gamepad")]
+    GamepadButtonReleased {
+        /// Gamepad the button belongs to.
+        gamepad: Gamepad,
+        /// Released button.
+        button: GamepadButton,
+    },
+    /// How far an analog gamepad button is pressed changed.
+    #[cfg(feature = "gamepad")]
+    GamepadButtonChanged {
+        /// Gamepad the button belongs to.
+        gamepad: Gamepad,
+        /// Changed button.
+        button: GamepadButton,
+        /// How far the button is pressed from 0 to 1.
+        value: f32,
+    },
+    /// Gamepad axis changed.
+    #[cfg(feature = "gamepad")]
+    GamepadAxisChanged {
+        /// Gamepad the axis belongs to.
+        gamepad: Gamepad,
+        /// Changed axis.
+        axis: GamepadAxis,
+        /// Raw value of the axis from -1 to 1, without the dead zone applied.
+        value: f32,
+    },
 }
 
-fn run_application(mut app: impl Application) {
-    let event_loop = EventLoop::new().unwrap();
+fn run_application(mut app: impl 'static + Application) -> Result<(), Error> {
+    let mut builder = build_app(&mut app)?;
+    let mut last_frame = Instant::now();
+    let mut cursor_position = None::<Vec2>;
+    #[cfg(feature = "gamepad")]
+    let mut gamepads = GamepadContext::new();
+
+    let event_loop = EventLoop::new()?;
     let mut window_title = app.title().to_string();
-    let window = WindowBuilder::new()
-        .with_title(&window_title)
-        .build(&event_loop)
-        .unwrap();
-
-    event_loop.set_control_flow(ControlFlow::Poll);
-    event_loop
-        .run(|event, event_loop_window_target| {
-            match event {
-                winit::event::Event::WindowEvent { event, .. } => match event {
-                    WindowEvent::CloseRequested => {
-                        app.handle_event(Event::CloseRequested);
+    let window = WindowBuilder::new().with_title(&window_title);
+    #[cfg(all(target_arch = "wasm32", feature = "web"))]
+    let window = window.with_append(true);
+    let window = Arc::new(window.build(&event_loop)?);
+    let mut applied_cursor = Cursor::default();
+    let mut ime_allowed = false;
+    let size = window.inner_size();
+    let mut state = Window::new(UVec2::new(size.width, size.height), window.scale_factor());
+    update_monitors(&window, &mut state);
+    state.handle_event(&Event::Focused(window.has_focus()));
+    let mut applied_mode = state.mode();
+    app.scene_mut().insert_resource(state);
+
+    // Adapters and devices can only be requested asynchronously on the web, so startup is deferred
+    // to the first event after the renderer was created.
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let renderer = pollster::block_on(Renderer::new(Arc::clone(&window)))?;
+        app.scene_mut().insert_resource(renderer);
+        builder.run_startup(app.scene_mut());
+        app.startup();
+    }
+    #[cfg(target_arch = "wasm32")]
+    let pending_renderer = {
+        let pending = std::rc::Rc::new(std::cell::RefCell::new(None));
+        let renderer = Renderer::new(Arc::clone(&window));
+        let result = std::rc::Rc::clone(&pending);
+        wasm_bindgen_futures::spawn_local(async move {
+            *result.borrow_mut() = Some(renderer.await);
+        });
+        pending
+    };
+    #[cfg(target_arch = "wasm32")]
+    let mut started = false;
+
+    let event_handler = move |event, event_loop_window_target: &EventLoopWindowTarget<()>| {
+        #[cfg(target_arch = "wasm32")]
+        if !started {
+            let Some(renderer) = pending_renderer.borrow_mut().take() else {
+                return;
+            };
+            match renderer {
+                Ok(renderer) => {
+                    app.scene_mut().insert_resource(renderer);
+                }
+                Err(error) => {
+                    println!("Failed to create renderer: {error}");
+                    event_loop_window_target.exit();
+                    return;
+                }
+            }
+            builder.run_startup(app.scene_mut());
+            app.startup();
+            started = true;
+        }
+
+        match event {
+            winit::event::Event::WindowEvent {
+                event: WindowEvent::RedrawRequested,
+                ..
+            } => {
+                #[cfg(feature = "gamepad")]
+                if let Some(input) = app.scene_mut().get_resource_mut::<Input>() {
+                    for event in gamepads.poll(input) {
+                        dispatch_event(&mut app, &mut builder, event);
                     }
-                    _ => {}
-                },
-                winit::event::Event::AboutToWait => {
-                    app.update();
+                }
 
-                    let scene = app.scene();
-                    systems::compute_visibility(scene);
-                    systems::compute_world_transform(scene);
+                let now = Instant::now();
+                run_frame(&mut app, &mut builder, now - last_frame);
+                last_frame = now;
 
-                    for event in scene.events::<ComputedVisibility>().iter() {
-                        println!("Computed Visibility: {event:?}");
+                let scene = app.scene_mut();
+                if let Some(state) = scene.get_resource_mut::<Window>() {
+                    apply_cursor(&window, state, &mut applied_cursor);
+                    apply_ime(&window, state, &mut ime_allowed);
+                    apply_mode(&window, state, &mut applied_mode);
+                    apply_window_requests(&window, state);
+                }
+                if let Some(mut renderer) = scene.remove_resource::<Renderer>() {
+                    renderer.render(scene);
+                    scene.insert_resource(renderer.stats().clone());
+                    scene.insert_resource(renderer);
+                }
+                if let Some(input) = scene.get_resource_mut::<Input>() {
+                    #[cfg(feature = "gamepad")]
+                    gamepads.play_rumbles(input);
+                    input.end_frame();
+                }
+
+                let title = app.title();
+                if title != window_title {
+                    window_title = title.to_string();
+                    window.set_title(&window_title);
+                }
+            }
+            winit::event::Event::WindowEvent { event, .. } => {
+                if let WindowEvent::Moved(_) = event {
+                    if let Some(state) = app.scene_mut().get_resource_mut::<Window>() {
+                        update_monitors(&window, state);
                     }
+                }
 
-                    for event in scene.events::<WorldTransform>().iter() {
-                        println!("World Transform: {event:?}");
+                if event == WindowEvent::Focused(false) {
+                    if let Some(input) = app.scene_mut().get_resource_mut::<Input>() {
+                        input.release_all();
                     }
+                }
+
+                let text_input = text_input(&event);
+                if let Some(event) = translate_window_event(event, &mut cursor_position) {
+                    dispatch_event(&mut app, &mut builder, event);
+                }
+                if let Some(event) = text_input {
+                    dispatch_event(&mut app, &mut builder, event);
+                }
+            }
+            winit::event::Event::Suspended => {
+                if let Some(input) = app.scene_mut().get_resource_mut::<Input>() {
+                    input.release_all();
+                }
 
-                    scene.clear_events();
+                dispatch_event(&mut app, &mut builder, Event::Suspended);
+            }
+            winit::event::Event::Resumed => dispatch_event(&mut app, &mut builder, Event::Resumed),
+            winit::event::Event::DeviceEvent {
+                event: DeviceEvent::MouseMotion { delta: (x, y) },
+                ..
+            } => {
+                let delta = Vec2::new(x as f32, y as f32);
+                dispatch_event(&mut app, &mut builder, Event::MouseMotion { delta });
+            }
+            winit::event::Event::AboutToWait => {
+                let pacing = app
+                    .scene()
+                    .get_resource::<FramePacing>()
+                    .copied()
+                    .unwrap_or_default();
+                let control_flow = pace_frame(&window, pacing, last_frame);
+                event_loop_window_target.set_control_flow(control_flow);
+            }
+            winit::event::Event::LoopExiting => app.shutdown(),
+            _ => {}
+        }
 
-                    let title = app.title();
-                    if title != &window_title {
-                        window_title = title.to_string();
-                        window.set_title(&window_title);
-                    }
+        if app.state() == ApplicationState::Finished {
+            event_loop_window_target.exit();
+        }
+    };
+
+    #[cfg(all(target_arch = "wasm32", feature = "web"))]
+    event_loop.spawn(event_handler);
+    #[cfg(not(all(target_arch = "wasm32", feature = "web")))]
+    event_loop.run(event_handler)?;
+
+    Ok(())
+}
+
+fn run_headless<A: Application>(mut app: A, headless: Headless) -> Result<A, Error> {
+    let mut builder = build_app(&mut app)?;
+    builder.run_startup(app.scene_mut());
+    app.startup();
+    let mut last_frame = Instant::now();
+    let mut frames = 0;
+
+    while app.state() == ApplicationState::Running
+        && headless.frames.is_none_or(|limit| frames < limit)
+    {
+        let now = Instant::now();
+        run_frame(
+            &mut app,
+            &mut builder,
+            headless.delta.unwrap_or(now - last_frame),
+        );
+        last_frame = now;
+        frames += 1;
+
+        if let Some(input) = app.scene_mut().get_resource_mut::<Input>() {
+            input.end_frame();
+        }
+
+        let frame_duration = app
+            .scene()
+            .get_resource::<FramePacing>()
+            .and_then(|pacing| pacing.frame_duration(None));
+        if let Some(frame_duration) = frame_duration {
+            std::thread::sleep((now + frame_duration).saturating_duration_since(Instant::now()));
+        }
+    }
+
+    app.shutdown();
+    Ok(app)
+}
+
+/// Returns the builder with the application's plugins and systems along with the built-in
+/// systems, and inserts the [Input] and [AssetServer] resources if the scene has none yet.
+fn build_app(app: &mut impl Application) -> Result<AppBuilder, Error> {
+    let mut builder = AppBuilder::new();
+    app.build(&mut builder);
+    app.build_stages(builder.stages_mut());
+    builder.build()?;
+
+    if !app.scene().contains_resource::<Input>() {
+        app.scene_mut().insert_resource(Input::new());
+    }
+    if !app.scene().contains_resource::<AssetServer>() {
+        app.scene_mut().insert_resource(AssetServer::default());
+    }
+
+    Ok(builder)
+}
+
+/// Updates the frame timing, the application, and the scene's systems for a frame that took the
+/// given time.
+fn run_frame(app: &mut impl Application, builder: &mut AppBuilder, elapsed: Duration) {
+    let scene = app.scene_mut();
+    if !scene.contains_resource::<Time>() {
+        scene.insert_resource(Time::new());
+    }
+    let time = scene.get_resource_mut::<Time>().unwrap();
+    time.update(elapsed);
+    let delta = time.delta();
+
+    app.update();
+
+    let scene = app.scene_mut();
+    builder.stages_mut().run(scene, delta);
+    builder.end_frame(scene);
+
+    for event in scene.events::<ComputedVisibility>() {
+        println!("Computed Visibility: {event:?}");
+    }
+
+    for event in scene.events::<WorldTransform>() {
+        println!("World Transform: {event:?}");
+    }
+
+    scene.clear_events();
+    scene.advance_change_tick();
+}
+
+/// Updates the scene's [Input], [Window], and [Renderer] resources from the event and passes the
+/// event to the event handlers and the application.
+fn dispatch_event(app: &mut impl Application, builder: &mut AppBuilder, event: Event) {
+    let scene = app.scene_mut();
+    if let Some(input) = scene.get_resource_mut::<Input>() {
+        input.handle_event(&event);
+    }
+    if let Some(window) = scene.get_resource_mut::<Window>() {
+        window.handle_event(&event);
+    }
+    if let Some(renderer) = scene.get_resource_mut::<Renderer>() {
+        match event {
+            Event::Resized { width, height } => renderer.resize(UVec2::new(width, height)),
+            Event::Suspended => renderer.suspend(),
+            Event::Resumed => renderer.resume(),
+            _ => {}
+        }
+    }
+    builder.handle_event(scene, &event);
+
+    app.handle_event(event);
+}
+
+/// Updates the available monitors of the [Window] resource and the monitor the window is on.
+fn update_monitors(window: &winit::window::Window, state: &mut Window) {
+    let handles: Vec<_> = window.available_monitors().collect();
+    let current_monitor = window
+        .current_monitor()
+        .and_then(|current| handles.iter().position(|handle| *handle == current));
+    let monitors = handles.iter().map(Monitor::from_winit).collect();
+    state.set_monitors(monitors, current_monitor);
+}
+
+/// Applies the mode of the [Window] resource if it changed since it was last applied.
+fn apply_mode(window: &winit::window::Window, state: &Window, applied: &mut WindowMode) {
+    let mode = state.mode();
+    if mode == *applied {
+        return;
+    }
+
+    *applied = mode;
+    let monitor = |index: usize| window.available_monitors().nth(index);
+    let fullscreen = match mode {
+        WindowMode::Windowed => None,
+        WindowMode::BorderlessFullscreen { monitor: index } => {
+            Some(Fullscreen::Borderless(index.and_then(monitor)))
+        }
+        WindowMode::Fullscreen {
+            monitor: index,
+            video_mode,
+        } => {
+            let handle = monitor(index).and_then(|monitor| {
+                monitor
+                    .video_modes()
+                    .find(|handle| VideoMode::from_winit(handle) == video_mode)
+            });
+            match handle {
+                Some(handle) => Some(Fullscreen::Exclusive(handle)),
+                None => {
+                    println!("Failed to set fullscreen: unsupported video mode {video_mode:?}");
+                    return;
                 }
-                _ => {}
             }
+        }
+    };
+
+    window.set_fullscreen(fullscreen);
+}
+
+/// Applies the icon, attention, minimized, and maximized requests of the [Window] resource.
+fn apply_window_requests(window: &winit::window::Window, state: &mut Window) {
+    if let Some(icon) = state.take_icon_request() {
+        let icon = icon.map(|icon| icon.to_winit());
+        if let Some(None) = icon {
+            println!("Failed to set window icon: invalid image size");
+        } else {
+            window.set_window_icon(icon.flatten());
+        }
+    }
+
+    if let Some(attention) = state.take_attention_request() {
+        window.request_user_attention(attention.map(UserAttention::to_winit));
+    }
 
-            if app.state() == ApplicationState::Finished {
-                event_loop_window_target.exit();
+    if let Some(minimized) = state.take_minimized_request() {
+        window.set_minimized(minimized);
+    }
+
+    if let Some(maximized) = state.take_maximized_request() {
+        window.set_maximized(maximized);
+    }
+}
+
+/// Applies the IME settings of the [Window] resource that changed since they were last applied.
+fn apply_ime(window: &winit::window::Window, state: &mut Window, allowed: &mut bool) {
+    if state.is_ime_allowed() != *allowed {
+        *allowed = state.is_ime_allowed();
+        window.set_ime_allowed(*allowed);
+    }
+
+    if let Some((position, size)) = state.take_ime_cursor_area_request() {
+        window.set_ime_cursor_area(
+            PhysicalPosition::new(position.x as f64, position.y as f64),
+            PhysicalSize::new(size.x as f64, size.y as f64),
+        );
+    }
+}
+
+/// Requests a redraw of the window to run the next frame if it's due according to the pacing, and
+/// returns how long the event loop should wait otherwise.
+fn pace_frame(
+    window: &winit::window::Window,
+    pacing: FramePacing,
+    last_frame: Instant,
+) -> ControlFlow {
+    /// Time before the next frame at which [FrameWait::Spin] stops sleeping.
+    const SPIN_MARGIN: Duration = Duration::from_millis(2);
+
+    // Redraws on the web are already paced to the display by requestAnimationFrame.
+    let frame_duration =
+        if pacing == FramePacing::Vsync && cfg!(all(target_arch = "wasm32", feature = "web")) {
+            None
+        } else {
+            let refresh_rate = window
+                .current_monitor()
+                .and_then(|monitor| monitor.refresh_rate_millihertz())
+                .map(|millihertz| f64::from(millihertz) / 1000.0);
+            pacing.frame_duration(refresh_rate)
+        };
+
+    if let Some(frame_duration) = frame_duration {
+        let next_frame = last_frame + frame_duration;
+        let spin = matches!(
+            pacing,
+            FramePacing::Limited {
+                wait: FrameWait::Spin,
+                ..
+            }
+        );
+        if spin && next_frame.saturating_duration_since(Instant::now()) <= SPIN_MARGIN {
+            while Instant::now() < next_frame {
+                std::hint::spin_loop();
             }
-        })
-        .unwrap();
+        } else if spin {
+            return ControlFlow::WaitUntil(next_frame - SPIN_MARGIN);
+        } else if Instant::now() < next_frame {
+            return ControlFlow::WaitUntil(next_frame);
+        }
+    }
+
+    window.request_redraw();
+    ControlFlow::Wait
+}
+
+/// Applies the cursor settings of the [Window] resource that changed since they were last applied.
+fn apply_cursor(window: &winit::window::Window, state: &mut Window, applied: &mut Cursor) {
+    let cursor = state.cursor();
+    if cursor.grab != applied.grab {
+        let fallback = match cursor.grab {
+            CursorGrab::None => CursorGrab::None,
+            CursorGrab::Confined => CursorGrab::Locked,
+            CursorGrab::Locked => CursorGrab::Confined,
+        };
+        if let Err(error) = window
+            .set_cursor_grab(cursor.grab.to_winit())
+            .or_else(|_| window.set_cursor_grab(fallback.to_winit()))
+        {
+            println!("Failed to grab cursor: {error}");
+        }
+    }
+
+    if cursor.visible != applied.visible {
+        window.set_cursor_visible(cursor.visible);
+    }
+
+    if cursor.icon != applied.icon {
+        window.set_cursor_icon(cursor.icon.to_winit());
+    }
+
+    if let Some(position) = state.take_cursor_position_request() {
+        let position = PhysicalPosition::new(position.x as f64, position.y as f64);
+        if let Err(error) = window.set_cursor_position(position) {
+            println!("Failed to set cursor position: {error}");
+        }
+    }
+
+    *applied = cursor;
+}
+
+/// Returns the [Event::TextInput] for the text entered by a key press, or `None` if the event isn't
+/// a key press or the key doesn't enter any printable text.
+fn text_input(event: &WindowEvent) -> Option<Event> {
+    let WindowEvent::KeyboardInput { event, .. } = event else {
+        return None;
+    };
+    if event.state != ElementState::Pressed {
+        return None;
+    }
+
+    let text: String = event
+        .text
+        .as_ref()?
+        .chars()
+        .filter(|c| !c.is_control())
+        .collect();
+    (!text.is_empty()).then_some(Event::TextInput(text))
+}
+
+/// Returns the event for the winit window event, or `None` if the event isn't reported.
+fn translate_window_event(event: WindowEvent, cursor_position: &mut Option<Vec2>) -> Option<Event> {
+    match event {
+        WindowEvent::CloseRequested => Some(Event::CloseRequested),
+        WindowEvent::Focused(focused) => Some(Event::Focused(focused)),
+        WindowEvent::HoveredFile(path) => Some(Event::FileHovered(path)),
+        WindowEvent::DroppedFile(path) => Some(Event::FileDropped(path)),
+        WindowEvent::HoveredFileCancelled => Some(Event::FileHoverCancelled),
+        WindowEvent::Ime(Ime::Enabled) => Some(Event::ImeEnabled),
+        WindowEvent::Ime(Ime::Preedit(text, cursor)) => Some(Event::ImePreedit { text, cursor }),
+        WindowEvent::Ime(Ime::Commit(text)) => Some(Event::TextInput(text)),
+        WindowEvent::Ime(Ime::Disabled) => Some(Event::ImeDisabled),
+        WindowEvent::Resized(size) => Some(Event::Resized {
+            width: size.width,
+            height: size.height,
+        }),
+        WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
+            Some(Event::ScaleFactorChanged { scale_factor })
+        }
+        WindowEvent::KeyboardInput { event, .. } => {
+            let PhysicalKey::Code(code) = event.physical_key else {
+                return None;
+            };
+            let key = Key::from_winit(code)?;
+            Some(match event.state {
+                ElementState::Pressed => Event::KeyPressed {
+                    key,
+                    repeat: event.repeat,
+                },
+                ElementState::Released => Event::KeyReleased { key },
+            })
+        }
+        WindowEvent::CursorMoved { position, .. } => {
+            let position = Vec2::new(position.x as f32, position.y as f32);
+            let delta = cursor_position.map_or(Vec2::ZERO, |previous| position - previous);
+            *cursor_position = Some(position);
+            Some(Event::MouseMoved { position, delta })
+        }
+        WindowEvent::MouseInput { state, button, .. } => {
+            let button = MouseButton::from_winit(button);
+            Some(match state {
+                ElementState::Pressed => Event::MouseButtonPressed { button },
+                ElementState::Released => Event::MouseButtonReleased { button },
+            })
+        }
+        WindowEvent::MouseWheel { delta, .. } => {
+            let (delta, unit) = input::scroll_from_winit(delta);
+            Some(Event::MouseWheel { delta, unit })
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Counter {
+        scene: Scene,
+        updates: u32,
+        finish_after: u32,
+        lifecycle: Vec<&'static str>,
+    }
+
+    impl Application for Counter {
+        fn title(&self) -> &str {
+            "Counter"
+        }
+
+        fn state(&self) -> ApplicationState {
+            if self.updates >= self.finish_after {
+                ApplicationState::Finished
+            } else {
+                ApplicationState::Running
+            }
+        }
+
+        fn startup(&mut self) {
+            self.lifecycle.push("startup");
+        }
+
+        fn shutdown(&mut self) {
+            self.lifecycle.push("shutdown");
+        }
+
+        fn handle_event(&mut self, _event: Event) {}
+
+        fn update(&mut self) {
+            assert_eq!(self.lifecycle, ["startup"]);
+            self.updates += 1;
+        }
+
+        fn scene(&self) -> &Scene {
+            &self.scene
+        }
+
+        fn scene_mut(&mut self) -> &mut Scene {
+            &mut self.scene
+        }
+    }
+
+    #[test]
+    fn run_headless_runs_given_frames() {
+        let app = Counter {
+            scene: Scene::new(),
+            updates: 0,
+            finish_after: u32::MAX,
+            lifecycle: Vec::new(),
+        };
+
+        let app = app
+            .run_headless(Headless {
+                frames: Some(3),
+                delta: Some(Duration::from_millis(10)),
+            })
+            .unwrap();
+
+        let time = app.scene().get_resource::<Time>().unwrap();
+        assert_eq!(app.updates, 3);
+        assert_eq!(time.frame_count(), 3);
+        assert_eq!(time.elapsed(), Duration::from_millis(30));
+        assert!(app.scene().contains_resource::<Input>());
+        assert!(!app.scene().contains_resource::<Window>());
+    }
+
+    #[test]
+    fn run_headless_stops_when_finished() {
+        let app = Counter {
+            scene: Scene::new(),
+            updates: 0,
+            finish_after: 5,
+            lifecycle: Vec::new(),
+        };
+
+        let app = app.run_headless(Headless::default()).unwrap();
+
+        assert_eq!(app.updates, 5);
+        assert_eq!(app.lifecycle, ["startup", "shutdown"]);
+    }
 }
@@ -0,0 +1,1699 @@
+//! # Assets
+//!
+//! Data loaded from files, e.g. fonts, images, scenes, and shaders. An [AssetServer] resource loads
+//! assets relative to its root directory in the background with the [AssetLoader] registered for
+//! their extension, and [crate::systems::update_assets] moves them into the scene's [Assets]
+//! resource of their type once they're loaded. Assets are referenced by [Handle]s and removed once
+//! the last strong handle is dropped. Loaders can load other assets as dependencies with
+//! [LoadContext::load], and an asset is only reported as loaded once its dependencies are.
+//! Loaders can also process their assets into bytes that the server caches on disk, so unchanged
+//! assets load faster the next time. Shipped games can load their assets from [pack::AssetPack]s
+//! instead of loose files.
+//!
+//! ```no_run
+//! # use pulse::assets::AssetServer;
+//! # use pulse::assets::Assets;
+//! # use pulse::assets::LoadState;
+//! # use pulse::render::text::Font;
+//! # use pulse::systems;
+//! # use pulse::Scene;
+//! let mut scene = Scene::new();
+//! scene.insert_resource(AssetServer::new("assets"));
+//!
+//! let server = scene.get_resource_mut::<AssetServer>().unwrap();
+//! let font = server.load::<Font>("fonts/inter.ttf");
+//!
+//! systems::update_assets(&mut scene);
+//! let server = scene.get_resource::<AssetServer>().unwrap();
+//! if let LoadState::Loaded = server.load_state(&font) {
+//!     let fonts = scene.get_resource::<Assets<Font>>().unwrap();
+//!     assert!(fonts.get(&font).is_some());
+//! }
+//! ```
+
+pub(crate) mod cache;
+pub mod gltf;
+pub mod obj;
+pub mod pack;
+pub mod ply;
+
+use std::any::Any;
+use std::any::TypeId;
+use std::collections::HashMap;
+#[cfg(feature = "hot-reload")]
+use std::collections::HashSet;
+use std::fmt;
+use std::hash::Hash;
+use std::hash::Hasher;
+use std::io;
+use std::marker::PhantomData;
+use std::panic;
+use std::panic::AssertUnwindSafe;
+use std::path::Component as PathComponent;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::Weak;
+#[cfg(not(target_arch = "wasm32"))]
+use std::thread;
+
+use nohash::IntMap;
+use nohash::IntSet;
+#[cfg(feature = "hot-reload")]
+use notify::Watcher as _;
+
+use crate::assets::gltf::GltfLoader;
+use crate::assets::obj::ObjLoader;
+use crate::assets::pack::AssetPack;
+use crate::assets::ply::PlyLoader;
+use crate::render::image::HdrImageLoader;
+use crate::render::image::ImageLoader;
+use crate::render::shader_material::MaterialShaderLoader;
+use crate::render::shader_material::ShaderMaterialLoader;
+use crate::render::text::FontLoader;
+use crate::Component;
+use crate::Events;
+use crate::Scene;
+
+/// Index of the next asset, shared by all asset types so indices are never reused.
+static NEXT_INDEX: AtomicU64 = AtomicU64::new(0);
+
+/// Maximum number of threads loading assets in the background.
+#[cfg(not(target_arch = "wasm32"))]
+const MAX_LOAD_THREADS: usize = 4;
+
+type LoaderError = Box<dyn std::error::Error + Send + Sync>;
+type ApplyFn = fn(&mut Scene, u64, Option<Box<dyn Any + Send + Sync>>);
+type CleanupFn = fn(&mut Scene);
+type LoadJob = Box<dyn FnOnce() + Send>;
+type HandlePaths = Mutex<HashMap<(PathBuf, TypeId), Weak<HandleData>>>;
+
+/// # Asset
+///
+/// Type that can be stored in [Assets]. Implemented for all thread-safe types.
+pub trait Asset: 'static + Send + Sync {}
+
+impl<T: 'static + Send + Sync> Asset for T {}
+
+/// # Asset Id
+///
+/// Identifier of an asset of type `T`, valid while any strong [Handle] to the asset exists.
+pub struct AssetId<T> {
+    index: u64,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T> AssetId<T> {
+    const fn new(index: u64) -> Self {
+        Self {
+            index,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<T> Clone for AssetId<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for AssetId<T> {}
+
+impl<T> PartialEq for AssetId<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.index == other.index
+    }
+}
+
+impl<T> Eq for AssetId<T> {}
+
+impl<T> Hash for AssetId<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.index.hash(state);
+    }
+}
+
+impl<T> fmt::Debug for AssetId<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("AssetId").field(&self.index).finish()
+    }
+}
+
+impl<T> From<&Handle<T>> for AssetId<T> {
+    fn from(handle: &Handle<T>) -> Self {
+        handle.id()
+    }
+}
+
+impl<T> From<&WeakHandle<T>> for AssetId<T> {
+    fn from(handle: &WeakHandle<T>) -> Self {
+        handle.id()
+    }
+}
+
+/// Data shared by the strong handles of an asset.
+#[derive(Debug)]
+struct HandleData {
+    index: u64,
+}
+
+/// # Handle
+///
+/// Strong reference to an asset of type `T` that keeps the asset in its [Assets] resource. The
+/// asset is removed once all strong handles were dropped. Handles are equal if they refer to the
+/// same asset. As a component, [crate::systems::update_asset_components] keeps the node's `T`
+/// component in sync with the asset.
+pub struct Handle<T> {
+    data: Arc<HandleData>,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Handle<T> {
+    /// Returns a handle to a new asset.
+    fn new() -> Self {
+        Self::from_data(Arc::new(HandleData {
+            index: NEXT_INDEX.fetch_add(1, Ordering::Relaxed),
+        }))
+    }
+
+    fn from_data(data: Arc<HandleData>) -> Self {
+        Self {
+            data,
+            marker: PhantomData,
+        }
+    }
+
+    /// Returns the identifier of the asset.
+    pub fn id(&self) -> AssetId<T> {
+        AssetId::new(self.data.index)
+    }
+
+    /// Returns a weak handle to the asset, which doesn't keep the asset alive.
+    pub fn downgrade(&self) -> WeakHandle<T> {
+        WeakHandle {
+            data: Arc::downgrade(&self.data),
+            index: self.data.index,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        Self::from_data(Arc::clone(&self.data))
+    }
+}
+
+impl<T> PartialEq for Handle<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.data.index == other.data.index
+    }
+}
+
+impl<T> Eq for Handle<T> {}
+
+impl<T> Hash for Handle<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.data.index.hash(state);
+    }
+}
+
+impl<T: Asset> Component for Handle<T> {}
+
+impl<T> fmt::Debug for Handle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Handle").field(&self.data.index).finish()
+    }
+}
+
+/// # Weak Handle
+///
+/// Weak reference to an asset of type `T` that doesn't keep the asset alive, returned by
+/// [Handle::downgrade].
+pub struct WeakHandle<T> {
+    data: Weak<HandleData>,
+    index: u64,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T> WeakHandle<T> {
+    /// Returns the identifier of the asset.
+    pub fn id(&self) -> AssetId<T> {
+        AssetId::new(self.index)
+    }
+
+    /// Returns a strong handle to the asset, or `None` if all strong handles were dropped.
+    pub fn upgrade(&self) -> Option<Handle<T>> {
+        self.data.upgrade().map(Handle::from_data)
+    }
+}
+
+impl<T> Clone for WeakHandle<T> {
+    fn clone(&self) -> Self {
+        Self {
+            data: Weak::clone(&self.data),
+            index: self.index,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<T> PartialEq for WeakHandle<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.index == other.index
+    }
+}
+
+impl<T> Eq for WeakHandle<T> {}
+
+impl<T> Hash for WeakHandle<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.index.hash(state);
+    }
+}
+
+impl<T> fmt::Debug for WeakHandle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("WeakHandle").field(&self.index).finish()
+    }
+}
+
+/// Asset stored in [Assets] with a weak reference to its handles.
+struct AssetEntry<T> {
+    asset: T,
+    handle: Weak<HandleData>,
+}
+
+/// # Assets
+///
+/// Scene resource storing the assets of type `T` by their [Handle]s.
+///
+/// ```
+/// # use pulse::assets::Assets;
+/// let mut names = Assets::new();
+/// let handle = names.add(String::from("Pulse"));
+/// assert_eq!(names.get(&handle).unwrap(), "Pulse");
+///
+/// drop(handle);
+/// assert_eq!(names.remove_unused().len(), 1);
+/// assert!(names.is_empty());
+/// ```
+pub struct Assets<T> {
+    assets: IntMap<u64, AssetEntry<T>>,
+}
+
+impl<T: Asset> Assets<T> {
+    /// Returns the store without any assets.
+    pub fn new() -> Self {
+        Self {
+            assets: IntMap::default(),
+        }
+    }
+
+    /// Adds the asset and returns a handle to it.
+    pub fn add(&mut self, asset: T) -> Handle<T> {
+        let handle = Handle::new();
+        self.insert(&handle, asset);
+        handle
+    }
+
+    /// Inserts the asset for the handle and returns the asset it replaced, if any.
+    pub fn insert(&mut self, handle: &Handle<T>, asset: T) -> Option<T> {
+        let entry = AssetEntry {
+            asset,
+            handle: Arc::downgrade(&handle.data),
+        };
+        self.assets
+            .insert(handle.data.index, entry)
+            .map(|entry| entry.asset)
+    }
+
+    /// Returns a reference to the asset, if it exists.
+    pub fn get(&self, id: impl Into<AssetId<T>>) -> Option<&T> {
+        self.assets.get(&id.into().index).map(|entry| &entry.asset)
+    }
+
+    /// Returns a mutable reference to the asset, if it exists.
+    pub fn get_mut(&mut self, id: impl Into<AssetId<T>>) -> Option<&mut T> {
+        self.assets
+            .get_mut(&id.into().index)
+            .map(|entry| &mut entry.asset)
+    }
+
+    /// Returns true if the asset exists.
+    pub fn contains(&self, id: impl Into<AssetId<T>>) -> bool {
+        self.assets.contains_key(&id.into().index)
+    }
+
+    /// Removes and returns the asset, if it exists. Its handles stay valid but refer to no asset.
+    pub fn remove(&mut self, id: impl Into<AssetId<T>>) -> Option<T> {
+        self.assets
+            .remove(&id.into().index)
+            .map(|entry| entry.asset)
+    }
+
+    /// Removes the assets without strong handles and returns their identifiers.
+    pub fn remove_unused(&mut self) -> Vec<AssetId<T>> {
+        let mut removed = Vec::new();
+        self.assets.retain(|index, entry| {
+            let used = entry.handle.strong_count() > 0;
+            if !used {
+                removed.push(AssetId::new(*index));
+            }
+            used
+        });
+        removed
+    }
+
+    /// Returns the number of assets.
+    pub fn len(&self) -> usize {
+        self.assets.len()
+    }
+
+    /// Returns true if there are no assets.
+    pub fn is_empty(&self) -> bool {
+        self.assets.is_empty()
+    }
+
+    /// Returns the identifiers and references of the assets in arbitrary order.
+    pub fn iter(&self) -> impl Iterator<Item = (AssetId<T>, &T)> {
+        self.assets
+            .iter()
+            .map(|(index, entry)| (AssetId::new(*index), &entry.asset))
+    }
+}
+
+impl<T: Asset> Default for Assets<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// # Asset Event
+///
+/// Event sent by [crate::systems::update_assets] for assets of type `T`, received through the
+/// [Events] resource registered with [crate::AppBuilder::add_event].
+pub enum AssetEvent<T> {
+    /// The asset was loaded and added to its [Assets] resource.
+    Loaded(AssetId<T>),
+    /// The asset couldn't be loaded. The error is returned by [AssetServer::load_state].
+    Failed(AssetId<T>),
+    /// The asset was reloaded and replaced the previous asset in its [Assets] resource.
+    Modified(AssetId<T>),
+    /// The asset was removed from its [Assets] resource because all of its strong handles were
+    /// dropped.
+    Removed(AssetId<T>),
+}
+
+impl<T> AssetEvent<T> {
+    /// Returns the identifier of the asset the event is about.
+    pub fn id(&self) -> AssetId<T> {
+        match self {
+            Self::Loaded(id) | Self::Failed(id) | Self::Modified(id) | Self::Removed(id) => *id,
+        }
+    }
+}
+
+impl<T> Clone for AssetEvent<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for AssetEvent<T> {}
+
+impl<T> PartialEq for AssetEvent<T> {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Loaded(a), Self::Loaded(b))
+            | (Self::Failed(a), Self::Failed(b))
+            | (Self::Modified(a), Self::Modified(b))
+            | (Self::Removed(a), Self::Removed(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl<T> Eq for AssetEvent<T> {}
+
+impl<T> fmt::Debug for AssetEvent<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Loaded(id) => f.debug_tuple("Loaded").field(id).finish(),
+            Self::Failed(id) => f.debug_tuple("Failed").field(id).finish(),
+            Self::Modified(id) => f.debug_tuple("Modified").field(id).finish(),
+            Self::Removed(id) => f.debug_tuple("Removed").field(id).finish(),
+        }
+    }
+}
+
+/// # Load State
+///
+/// State of an asset loaded by an [AssetServer].
+#[derive(Clone, Debug)]
+pub enum LoadState {
+    /// The asset wasn't loaded by the server or all of its strong handles were dropped.
+    NotLoaded,
+    /// The asset is being loaded and isn't in its [Assets] resource yet.
+    Loading,
+    /// The asset was loaded and added to its [Assets] resource.
+    Loaded,
+    /// The asset couldn't be loaded.
+    Failed(AssetError),
+}
+
+/// # Asset Error
+///
+/// Error of an asset that couldn't be loaded, returned by [AssetServer::load_state].
+#[derive(Clone, Debug)]
+pub enum AssetError {
+    /// No loader for the asset type is registered for the path's extension.
+    NoLoader(PathBuf),
+    /// The asset's file couldn't be read.
+    Io(Arc<io::Error>),
+    /// The loader failed to load the asset from its file.
+    Loader(Arc<dyn std::error::Error + Send + Sync>),
+    /// A dependency of the asset at the path relative to the root couldn't be loaded.
+    Dependency(PathBuf, Box<AssetError>),
+}
+
+impl fmt::Display for AssetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoLoader(path) => write!(f, "no asset loader for {}", path.display()),
+            Self::Io(error) => write!(f, "failed to read asset: {error}"),
+            Self::Loader(error) => write!(f, "failed to load asset: {error}"),
+            Self::Dependency(path, error) => {
+                write!(f, "failed to load dependency {}: {error}", path.display())
+            }
+        }
+    }
+}
+
+impl std::error::Error for AssetError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::NoLoader(_) => None,
+            Self::Io(error) => Some(error.as_ref()),
+            Self::Loader(error) => Some(error.as_ref()),
+            Self::Dependency(_, error) => Some(error.as_ref()),
+        }
+    }
+}
+
+/// # Load Context
+///
+/// File of an asset passed to [AssetLoader::load], recording the files the loader reads and the
+/// assets it loads as the asset's dependencies.
+pub struct LoadContext<'a> {
+    path: &'a Path,
+    root: &'a Path,
+    packs: &'a [Arc<AssetPack>],
+    bytes: Vec<u8>,
+    paths: &'a HandlePaths,
+    dependencies: Mutex<Dependencies>,
+}
+
+impl LoadContext<'_> {
+    /// Returns the path of the asset relative to the [AssetServer]'s root.
+    pub fn path(&self) -> &Path {
+        self.path
+    }
+
+    /// Returns the content of the asset's file.
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Reads the file at the path relative to the asset's file, e.g. a shader referenced by a
+    /// material, from the server's packs or root directory. With the `hot-reload` feature, the
+    /// asset is reloaded when the file changes, even if it couldn't be read.
+    pub fn read(&self, path: impl AsRef<Path>) -> io::Result<Vec<u8>> {
+        let file = self.relative_path(path.as_ref());
+        let bytes = read_file(self.root, self.packs, &file);
+        let mut dependencies = self.dependencies.lock().unwrap();
+        if !dependencies.files.contains(&file) {
+            dependencies.files.push(file);
+        }
+        bytes
+    }
+
+    /// Loads the asset of type `T` from the file at the path relative to the asset's file as a
+    /// dependency, e.g. a texture referenced by a model, and returns a handle to it like
+    /// [AssetServer::load]. The asset is only reported as loaded once its dependencies are, and
+    /// fails if any of them fails.
+    pub fn load<T: Asset>(&self, path: impl AsRef<Path>) -> Handle<T> {
+        let path = self.relative_path(path.as_ref());
+        let (handle, request) = request::<T>(self.paths, path);
+        let mut dependencies = self.dependencies.lock().unwrap();
+        if !dependencies.assets.contains(&handle.data.index) {
+            dependencies.assets.push(handle.data.index);
+        }
+        dependencies.requests.extend(request);
+        handle
+    }
+
+    /// Returns the path relative to the asset's file as a path relative to the [AssetServer]'s
+    /// root.
+    fn relative_path(&self, path: &Path) -> PathBuf {
+        normalize(&self.path.parent().unwrap_or(Path::new("")).join(path))
+    }
+}
+
+/// Files read and assets loaded by a loader, and the requests to load the assets that weren't
+/// loaded yet.
+#[derive(Default)]
+struct Dependencies {
+    files: Vec<PathBuf>,
+    assets: Vec<u64>,
+    requests: Vec<LoadRequest>,
+}
+
+/// Request to load an asset whose handle was just created.
+struct LoadRequest {
+    path: PathBuf,
+    asset_type: TypeId,
+    handle: Arc<HandleData>,
+    apply: ApplyFn,
+    cleanup: CleanupFn,
+}
+
+/// Returns the handle of the asset of type `T` at the path, along with the request to load it if
+/// it isn't loaded or being loaded yet.
+fn request<T: Asset>(paths: &HandlePaths, path: PathBuf) -> (Handle<T>, Option<LoadRequest>) {
+    let mut paths = paths.lock().unwrap();
+    let key = (path, TypeId::of::<T>());
+    if let Some(data) = paths.get(&key).and_then(Weak::upgrade) {
+        return (Handle::from_data(data), None);
+    }
+
+    let handle = Handle::<T>::new();
+    paths.insert(key.clone(), Arc::downgrade(&handle.data));
+    let request = LoadRequest {
+        path: key.0,
+        asset_type: key.1,
+        handle: Arc::clone(&handle.data),
+        apply: apply_loaded::<T>,
+        cleanup: remove_unused_assets::<T>,
+    };
+    (handle, Some(request))
+}
+
+/// Returns the path without `.` components and with `..` components removing their parent, so
+/// different paths to the same file refer to the same asset.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            PathComponent::CurDir => {}
+            PathComponent::ParentDir if normalized.file_name().is_some() => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+/// Reads the file at the path relative to the root from the last of the packs that has it, or else
+/// from the root directory.
+fn read_file(root: &Path, packs: &[Arc<AssetPack>], path: &Path) -> io::Result<Vec<u8>> {
+    match packs.iter().rev().find(|pack| pack.contains(path)) {
+        Some(pack) => pack.read(path),
+        None => std::fs::read(root.join(path)),
+    }
+}
+
+/// # Asset Loader
+///
+/// Loads assets of a type from files with the loader's extensions, registered with
+/// [AssetServer::add_loader].
+///
+/// ```
+/// # use pulse::assets::AssetLoader;
+/// # use pulse::assets::LoadContext;
+/// struct TextLoader;
+///
+/// impl AssetLoader for TextLoader {
+///     type Asset = String;
+///
+///     fn extensions(&self) -> &[&str] {
+///         &["txt"]
+///     }
+///
+///     fn load(
+///         &self,
+///         context: &LoadContext,
+///     ) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+///         Ok(String::from_utf8(context.bytes().to_vec())?)
+///     }
+/// }
+/// ```
+pub trait AssetLoader: 'static + Send + Sync {
+    /// Type of the loaded assets.
+    type Asset: Asset;
+
+    /// Returns the extensions of the files loaded by the loader without the leading dot, e.g.
+    /// `"png"` or `"material.ron"`.
+    fn extensions(&self) -> &[&str];
+
+    /// Loads the asset from its file.
+    fn load(&self, context: &LoadContext) -> Result<Self::Asset, LoaderError>;
+
+    /// Returns the loaded asset processed into bytes that are faster to load, e.g. decoded pixels,
+    /// which the [AssetServer] caches and loads with [AssetLoader::load_processed] while the
+    /// asset's files don't change. Returns none by default, so assets aren't cached.
+    fn process(&self, _asset: &Self::Asset) -> Option<Vec<u8>> {
+        None
+    }
+
+    /// Loads the asset from the bytes returned by [AssetLoader::process].
+    fn load_processed(&self, _bytes: &[u8]) -> Result<Self::Asset, LoaderError> {
+        Err("asset loader doesn't process assets".into())
+    }
+
+    /// Returns the loader's settings that affect the processed assets, so changing them
+    /// invalidates the cached assets. Returns no settings by default.
+    fn settings(&self) -> Vec<u8> {
+        Vec::new()
+    }
+}
+
+/// [AssetLoader] with its asset type erased, to store loaders of different types.
+trait ErasedLoader: Send + Sync {
+    fn asset_type(&self) -> TypeId;
+
+    fn extensions(&self) -> &[&str];
+
+    fn load(&self, context: &LoadContext) -> Result<Box<dyn Any + Send + Sync>, LoaderError>;
+
+    fn name(&self) -> &'static str;
+
+    fn process(&self, asset: &(dyn Any + Send + Sync)) -> Option<Vec<u8>>;
+
+    fn load_processed(&self, bytes: &[u8]) -> Result<Box<dyn Any + Send + Sync>, LoaderError>;
+
+    fn settings(&self) -> Vec<u8>;
+}
+
+impl<L: AssetLoader> ErasedLoader for L {
+    fn asset_type(&self) -> TypeId {
+        TypeId::of::<L::Asset>()
+    }
+
+    fn extensions(&self) -> &[&str] {
+        AssetLoader::extensions(self)
+    }
+
+    fn load(&self, context: &LoadContext) -> Result<Box<dyn Any + Send + Sync>, LoaderError> {
+        AssetLoader::load(self, context).map(|asset| Box::new(asset) as Box<dyn Any + Send + Sync>)
+    }
+
+    fn name(&self) -> &'static str {
+        std::any::type_name::<L>()
+    }
+
+    fn process(&self, asset: &(dyn Any + Send + Sync)) -> Option<Vec<u8>> {
+        AssetLoader::process(self, asset.downcast_ref()?)
+    }
+
+    fn load_processed(&self, bytes: &[u8]) -> Result<Box<dyn Any + Send + Sync>, LoaderError> {
+        AssetLoader::load_processed(self, bytes)
+            .map(|asset| Box::new(asset) as Box<dyn Any + Send + Sync>)
+    }
+
+    fn settings(&self) -> Vec<u8> {
+        AssetLoader::settings(self)
+    }
+}
+
+/// State of an asset loaded by the server, with its loader to reload it and the files and assets
+/// it depends on. The asset is stale if its file changed while it was being loaded, so it's loaded
+/// again once the current load finished.
+struct LoadEntry {
+    path: PathBuf,
+    handle: Weak<HandleData>,
+    state: LoadState,
+    loader: Option<Arc<dyn ErasedLoader>>,
+    apply: ApplyFn,
+    files: Vec<PathBuf>,
+    dependencies: Vec<u64>,
+    loading: bool,
+    stale: bool,
+}
+
+/// Watches the files in an [AssetServer]'s root directory and collects the paths of the files that
+/// were created or modified.
+#[cfg(feature = "hot-reload")]
+struct AssetWatcher {
+    _watcher: notify::RecommendedWatcher,
+    root: PathBuf,
+    changed: Arc<Mutex<HashSet<PathBuf>>>,
+}
+
+#[cfg(feature = "hot-reload")]
+impl AssetWatcher {
+    fn new(root: &Path) -> notify::Result<Self> {
+        let root = root.canonicalize().map_err(notify::Error::io)?;
+        let changed = Arc::new(Mutex::new(HashSet::new()));
+        let paths = Arc::clone(&changed);
+        let mut watcher =
+            notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+                if let Ok(event) = event {
+                    if event.kind.is_create() || event.kind.is_modify() {
+                        paths.lock().unwrap().extend(event.paths);
+                    }
+                }
+            })?;
+        watcher.watch(&root, notify::RecursiveMode::Recursive)?;
+        Ok(Self {
+            _watcher: watcher,
+            root,
+            changed,
+        })
+    }
+
+    /// Returns the paths relative to the root of the files changed since the previous call.
+    fn changed(&self) -> Vec<PathBuf> {
+        self.changed
+            .lock()
+            .unwrap()
+            .drain()
+            .filter_map(|path| Some(path.strip_prefix(&self.root).ok()?.to_path_buf()))
+            .collect()
+    }
+}
+
+/// Threads running the load jobs of an [AssetServer], spawned when the first job is run. On the
+/// web, jobs are run as tasks on the main thread instead.
+struct LoadPool {
+    #[cfg(not(target_arch = "wasm32"))]
+    sender: Option<mpsc::Sender<LoadJob>>,
+}
+
+impl LoadPool {
+    const fn new() -> Self {
+        Self {
+            #[cfg(not(target_arch = "wasm32"))]
+            sender: None,
+        }
+    }
+
+    /// Runs the job on one of the pool's threads.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn run(&mut self, job: LoadJob) {
+        let sender = self.sender.get_or_insert_with(|| {
+            let (sender, receiver) = mpsc::channel::<LoadJob>();
+            let receiver = Arc::new(Mutex::new(receiver));
+            let threads = thread::available_parallelism()
+                .map_or(1, |threads| threads.get())
+                .min(MAX_LOAD_THREADS);
+            for index in 0..threads {
+                let receiver = Arc::clone(&receiver);
+                thread::Builder::new()
+                    .name(format!("pulse-assets-{index}"))
+                    .spawn(move || {
+                        // The threads exit once the server and its sender are dropped.
+                        while let Ok(job) = receiver.lock().unwrap().recv() {
+                            job();
+                        }
+                    })
+                    .expect("failed to spawn asset loading thread");
+            }
+            sender
+        });
+        sender.send(job).unwrap();
+    }
+
+    /// Runs the job as a task on the main thread.
+    #[cfg(target_arch = "wasm32")]
+    fn run(&mut self, job: LoadJob) {
+        wasm_bindgen_futures::spawn_local(async move { job() });
+    }
+}
+
+/// Asset that finished loading but wasn't added to its [Assets] resource yet, with the
+/// dependencies its loader recorded.
+pub(crate) struct LoadedAsset {
+    index: u64,
+    result: Result<Box<dyn Any + Send + Sync>, AssetError>,
+    apply: ApplyFn,
+    dependencies: Dependencies,
+}
+
+/// # Asset Server
+///
+/// Scene resource loading assets from files relative to its root directory with the
+/// [AssetLoader]s registered for their extensions. Assets are loaded on a pool of background
+/// threads, or as tasks on the web, so loading doesn't block the frame. Loaded assets are added to
+/// the scene's [Assets] resources by [crate::systems::update_assets], which also sends their
+/// [AssetEvent]s and removes the loaded assets whose strong handles were all dropped.
+///
+/// Loaders load the assets their asset depends on with [LoadContext::load], e.g. the textures of a
+/// model. The server tracks the dependencies of each asset, and keeps an asset loading until all
+/// of its dependencies and their own dependencies are loaded. The asset fails with
+/// [AssetError::Dependency] if any of them fails.
+///
+/// With the `hot-reload` feature, the server watches the files in its root directory and reloads
+/// the assets whose files, or files read by their loaders with [LoadContext::read], change.
+/// Reloaded assets replace the previous ones in their [Assets] resource, keeping their handles,
+/// and are reported with [AssetEvent::Modified]. Assets that fail to reload keep the previous
+/// asset and are reported with [AssetEvent::Failed].
+///
+/// Packs mounted with [AssetServer::mount] are read-only sources of files relative to the root,
+/// taking precedence over the files in the root directory, e.g. to ship a game with its assets in
+/// a single [AssetPack] built by [pack::build].
+///
+/// Assets whose loaders implement [AssetLoader::process] are cached in the server's cache
+/// directory, `.pulse_cache` in the root directory by default, keyed by a hash of their loader and
+/// the path and content of their file. Cached assets are loaded from the cache until any of the
+/// files their loader read changes. Assets that load other assets aren't cached.
+///
+/// The server has loaders for fonts (`ttf`, `otf`), glTF scenes (`gltf`, `glb`), images (`png`,
+/// `jpg`, `jpeg`, `ktx2`), HDR images (`hdr`, `exr`), material shaders (`wgsl`), OBJ models
+/// (`obj`), PLY meshes (`ply`), and shader materials (`material.ron`).
+pub struct AssetServer {
+    root: PathBuf,
+    packs: Vec<Arc<AssetPack>>,
+    cache: Option<PathBuf>,
+    loaders: Vec<Arc<dyn ErasedLoader>>,
+    paths: Arc<HandlePaths>,
+    entries: IntMap<u64, LoadEntry>,
+    loaded: Arc<Mutex<Vec<LoadedAsset>>>,
+    waiting: Vec<LoadedAsset>,
+    cleanups: HashMap<TypeId, CleanupFn>,
+    pool: LoadPool,
+    #[cfg(feature = "hot-reload")]
+    watcher: Option<AssetWatcher>,
+}
+
+impl AssetServer {
+    /// Returns the server loading assets relative to the root directory with the built-in
+    /// loaders.
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        let root = root.into();
+        let cache = (!cfg!(target_arch = "wasm32")).then(|| root.join(".pulse_cache"));
+        #[cfg(feature = "hot-reload")]
+        let watcher = AssetWatcher::new(&root)
+            .map_err(|error| println!("Failed to watch assets: {error}"))
+            .ok();
+        let mut server = Self {
+            root,
+            packs: Vec::new(),
+            cache,
+            loaders: Vec::new(),
+            paths: Arc::new(Mutex::new(HashMap::new())),
+            entries: IntMap::default(),
+            loaded: Arc::new(Mutex::new(Vec::new())),
+            waiting: Vec::new(),
+            cleanups: HashMap::new(),
+            pool: LoadPool::new(),
+            #[cfg(feature = "hot-reload")]
+            watcher,
+        };
+        server
+            .add_loader(FontLoader)
+            .add_loader(GltfLoader)
+            .add_loader(HdrImageLoader)
+            .add_loader(ImageLoader::default())
+            .add_loader(MaterialShaderLoader)
+            .add_loader(ObjLoader)
+            .add_loader(PlyLoader)
+            .add_loader(ShaderMaterialLoader);
+        server
+    }
+
+    /// Returns the directory assets are loaded from.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Mounts the pack, so files relative to the root are read from the pack if it has them.
+    /// Packs mounted later take precedence over earlier packs. Affects the assets loaded
+    /// afterwards.
+    pub fn mount(&mut self, pack: AssetPack) -> &mut Self {
+        self.packs.push(Arc::new(pack));
+        self
+    }
+
+    /// Returns the directory processed assets are cached in, if they're cached.
+    pub fn cache(&self) -> Option<&Path> {
+        self.cache.as_deref()
+    }
+
+    /// Sets the directory processed assets are cached in, or disables the cache if none. Affects
+    /// the assets loaded afterwards.
+    pub fn set_cache(&mut self, cache: Option<PathBuf>) -> &mut Self {
+        self.cache = cache;
+        self
+    }
+
+    /// Registers the loader for its extensions. Loaders registered later take precedence over
+    /// earlier loaders of the same asset type and extension.
+    pub fn add_loader(&mut self, loader: impl AssetLoader) -> &mut Self {
+        self.loaders.push(Arc::new(loader));
+        self
+    }
+
+    /// Loads the asset of type `T` from the file at the path relative to the root and returns a
+    /// handle to it immediately. Returns the existing handle if the asset is already loaded or
+    /// being loaded. The asset is loaded in the background and added to its [Assets] resource by
+    /// the first [crate::systems::update_assets] after it and its dependencies finished loading.
+    pub fn load<T: Asset>(&mut self, path: impl AsRef<Path>) -> Handle<T> {
+        let (handle, request) = request::<T>(&self.paths, normalize(path.as_ref()));
+        if let Some(request) = request {
+            self.start_load(request);
+        }
+        handle
+    }
+
+    /// Returns the load state of the asset.
+    pub fn load_state<T>(&self, id: impl Into<AssetId<T>>) -> LoadState {
+        self.entries
+            .get(&id.into().index)
+            .filter(|entry| entry.handle.strong_count() > 0)
+            .map_or(LoadState::NotLoaded, |entry| entry.state.clone())
+    }
+
+    /// Returns true if any asset is still being loaded, e.g. to show a loading screen until all
+    /// assets are loaded.
+    pub fn is_loading(&self) -> bool {
+        self.entries.values().any(|entry| {
+            matches!(entry.state, LoadState::Loading) && entry.handle.strong_count() > 0
+        })
+    }
+
+    /// Returns true if the asset was loaded and added to its [Assets] resource.
+    pub fn is_loaded<T>(&self, id: impl Into<AssetId<T>>) -> bool {
+        matches!(self.load_state(id), LoadState::Loaded)
+    }
+
+    /// Returns the path the asset was loaded from relative to the root, if it was loaded by the
+    /// server.
+    pub fn path<T>(&self, id: impl Into<AssetId<T>>) -> Option<&Path> {
+        self.entries
+            .get(&id.into().index)
+            .map(|entry| entry.path.as_path())
+    }
+
+    /// Returns the paths relative to the root of the files read and the assets loaded by the
+    /// asset's loader, or none if the asset didn't finish loading yet.
+    pub fn dependencies<T>(&self, id: impl Into<AssetId<T>>) -> Vec<&Path> {
+        let Some(entry) = self.entries.get(&id.into().index) else {
+            return Vec::new();
+        };
+        let assets = entry
+            .dependencies
+            .iter()
+            .filter_map(|index| self.entries.get(index))
+            .map(|dependency| dependency.path.as_path());
+        entry
+            .files
+            .iter()
+            .map(PathBuf::as_path)
+            .chain(assets)
+            .collect()
+    }
+
+    /// Adds the entry of the requested asset and loads it with the loader for its type and path,
+    /// or fails it if there's no such loader.
+    fn start_load(&mut self, request: LoadRequest) {
+        let index = request.handle.index;
+        let loader = match self.loader(request.asset_type, &request.path) {
+            Ok(loader) => {
+                self.spawn_load(index, &request.path, Arc::clone(&loader), request.apply);
+                Some(loader)
+            }
+            Err(error) => {
+                self.loaded.lock().unwrap().push(LoadedAsset {
+                    index,
+                    result: Err(error),
+                    apply: request.apply,
+                    dependencies: Dependencies::default(),
+                });
+                None
+            }
+        };
+
+        self.entries.insert(
+            index,
+            LoadEntry {
+                path: request.path,
+                handle: Arc::downgrade(&request.handle),
+                state: LoadState::Loading,
+                loader,
+                apply: request.apply,
+                files: Vec::new(),
+                dependencies: Vec::new(),
+                loading: true,
+                stale: false,
+            },
+        );
+        self.cleanups.insert(request.asset_type, request.cleanup);
+    }
+
+    /// Loads the asset from the file at the path relative to the root with the loader on the
+    /// pool.
+    fn spawn_load(
+        &mut self,
+        index: u64,
+        path: &Path,
+        loader: Arc<dyn ErasedLoader>,
+        apply: ApplyFn,
+    ) {
+        let root = self.root.clone();
+        let packs = self.packs.clone();
+        let cache = self.cache.clone();
+        let path = path.to_path_buf();
+        let paths = Arc::clone(&self.paths);
+        let loaded = Arc::clone(&self.loaded);
+        self.pool.run(Box::new(move || {
+            let (result, dependencies) = read(
+                loader.as_ref(),
+                &root,
+                &packs,
+                &path,
+                &paths,
+                cache.as_deref(),
+            );
+            let asset = LoadedAsset {
+                index,
+                result,
+                apply,
+                dependencies,
+            };
+            loaded.lock().unwrap().push(asset);
+        }));
+    }
+
+    /// Reloads the assets whose files changed since the previous call, or marks them as stale if
+    /// they're still being loaded.
+    #[cfg(feature = "hot-reload")]
+    fn reload_changed(&mut self) {
+        let Some(watcher) = &self.watcher else {
+            return;
+        };
+
+        let mut reloads = Vec::new();
+        for path in watcher.changed() {
+            let entries = self.entries.iter_mut().filter(|(_, entry)| {
+                (entry.path == path || entry.files.contains(&path))
+                    && entry.handle.strong_count() > 0
+                    && entry.loader.is_some()
+            });
+            for (index, entry) in entries {
+                if entry.loading {
+                    entry.stale = true;
+                } else {
+                    entry.loading = true;
+                    reloads.push((*index, entry.path.clone()));
+                }
+            }
+        }
+        self.spawn_reloads(reloads);
+    }
+
+    /// Loads the assets again from their files with the loaders they were loaded with.
+    fn spawn_reloads(&mut self, reloads: Vec<(u64, PathBuf)>) {
+        for (index, path) in reloads {
+            let entry = &self.entries[&index];
+            if let Some(loader) = entry.loader.clone() {
+                let apply = entry.apply;
+                self.spawn_load(index, &path, loader, apply);
+            }
+        }
+    }
+
+    /// Returns the latest loader of assets of the type registered for the path's extension.
+    fn loader(&self, asset_type: TypeId, path: &Path) -> Result<Arc<dyn ErasedLoader>, AssetError> {
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        self.loaders
+            .iter()
+            .rev()
+            .filter(|loader| loader.asset_type() == asset_type)
+            .find(|loader| {
+                loader.extensions().iter().any(|extension| {
+                    name.len() > extension.len() + 1
+                        && name.ends_with(extension)
+                        && name[..name.len() - extension.len()].ends_with('.')
+                })
+            })
+            .cloned()
+            .ok_or_else(|| AssetError::NoLoader(path.to_path_buf()))
+    }
+
+    /// Returns the state of the asset whose file finished loading given its dependencies: failed
+    /// if any of them or their own dependencies failed, loaded once all of them are loaded, or
+    /// none while any of them is still loading.
+    fn dependency_state(&self, index: u64) -> Option<LoadState> {
+        let mut visited = IntSet::from_iter([index]);
+        let mut stack = self.entries[&index].dependencies.clone();
+        while let Some(dependency) = stack.pop() {
+            if !visited.insert(dependency) {
+                continue;
+            }
+            let Some(entry) = self.entries.get(&dependency) else {
+                continue;
+            };
+            if entry.loading {
+                return None;
+            }
+            if let LoadState::Failed(error) = &entry.state {
+                let error = AssetError::Dependency(entry.path.clone(), Box::new(error.clone()));
+                return Some(LoadState::Failed(error));
+            }
+            stack.extend(&entry.dependencies);
+        }
+        Some(LoadState::Loaded)
+    }
+
+    /// Reloads the assets whose files changed, starts loading the dependencies of the assets that
+    /// finished loading, updates the load states of the assets whose dependencies are ready,
+    /// forgets the assets whose strong handles were all dropped, and returns the ready assets
+    /// along with the functions removing the unused assets of each loaded type.
+    pub(crate) fn take_loaded(&mut self) -> (Vec<LoadedAsset>, Vec<CleanupFn>) {
+        #[cfg(feature = "hot-reload")]
+        self.reload_changed();
+
+        let loaded = std::mem::take(&mut *self.loaded.lock().unwrap());
+        let mut reloads = Vec::new();
+        for mut asset in loaded {
+            let Some(entry) = self.entries.get_mut(&asset.index) else {
+                continue;
+            };
+            if entry.stale {
+                entry.stale = false;
+                reloads.push((asset.index, entry.path.clone()));
+                continue;
+            }
+
+            entry.loading = false;
+            let dependencies = std::mem::take(&mut asset.dependencies);
+            match &asset.result {
+                Ok(_) => {
+                    if let LoadState::Failed(_) = entry.state {
+                        entry.state = LoadState::Loading;
+                    }
+                    entry.files = dependencies.files;
+                    entry.dependencies = dependencies.assets;
+                    for request in dependencies.requests {
+                        self.start_load(request);
+                    }
+                }
+                Err(error) => entry.state = LoadState::Failed(error.clone()),
+            }
+            self.waiting.push(asset);
+        }
+        self.spawn_reloads(reloads);
+
+        let mut ready = Vec::new();
+        for mut asset in std::mem::take(&mut self.waiting) {
+            let state = match &asset.result {
+                Ok(_) if self.entries.contains_key(&asset.index) => {
+                    self.dependency_state(asset.index)
+                }
+                Ok(_) => continue,
+                Err(error) => Some(LoadState::Failed(error.clone())),
+            };
+            let Some(state) = state else {
+                self.waiting.push(asset);
+                continue;
+            };
+
+            if let LoadState::Failed(error) = &state {
+                asset.result = Err(error.clone());
+            }
+            let Some(entry) = self.entries.get_mut(&asset.index) else {
+                continue;
+            };
+            entry.state = state;
+            if entry.handle.strong_count() > 0 {
+                ready.push(asset);
+            }
+        }
+
+        self.entries
+            .retain(|_, entry| entry.handle.strong_count() > 0);
+        self.paths
+            .lock()
+            .unwrap()
+            .retain(|_, handle| handle.strong_count() > 0);
+        (ready, self.cleanups.values().copied().collect())
+    }
+}
+
+impl Default for AssetServer {
+    /// Returns the server loading assets from the `assets` directory.
+    fn default() -> Self {
+        Self::new("assets")
+    }
+}
+
+/// Returns the asset loaded from the file at the path relative to the root or in the packs with the
+/// loader, or the error if it couldn't be loaded, along with the dependencies the loader recorded.
+/// The asset is loaded from and processed into the cache directory, if any.
+fn read(
+    loader: &dyn ErasedLoader,
+    root: &Path,
+    packs: &[Arc<AssetPack>],
+    path: &Path,
+    paths: &HandlePaths,
+    cache: Option<&Path>,
+) -> (Result<Box<dyn Any + Send + Sync>, AssetError>, Dependencies) {
+    let read = |file: &Path| read_file(root, packs, file);
+    let bytes = match read(path) {
+        Ok(bytes) => bytes,
+        Err(error) => {
+            return (
+                Err(AssetError::Io(Arc::new(error))),
+                Dependencies::default(),
+            )
+        }
+    };
+    let cache = cache.map(|directory| {
+        let key = cache::key(loader.name(), &loader.settings(), path, &bytes);
+        (directory, key)
+    });
+    if let Some((directory, key)) = cache {
+        let cached = cache::load(directory, key, read)
+            .and_then(|(files, processed)| Some((files, loader.load_processed(&processed).ok()?)));
+        if let Some((files, asset)) = cached {
+            let dependencies = Dependencies {
+                files,
+                ..Dependencies::default()
+            };
+            return (Ok(asset), dependencies);
+        }
+    }
+
+    let context = LoadContext {
+        path,
+        root,
+        packs,
+        bytes,
+        paths,
+        dependencies: Mutex::new(Dependencies::default()),
+    };
+    // A panicking loader fails its asset instead of taking down the loading thread.
+    let result = panic::catch_unwind(AssertUnwindSafe(|| loader.load(&context)))
+        .unwrap_or_else(|_| Err("asset loader panicked".into()))
+        .map_err(|error| AssetError::Loader(Arc::from(error)));
+    let dependencies = context.dependencies.into_inner().unwrap();
+    // Assets loading other assets aren't cached, since the other assets would be loaded from
+    // their files anyway.
+    if let (Some((directory, key)), Ok(asset)) = (cache, &result) {
+        if dependencies.assets.is_empty() {
+            if let Some(processed) = loader.process(asset.as_ref()) {
+                if let Err(error) =
+                    cache::store(directory, key, read, &dependencies.files, &processed)
+                {
+                    println!("Failed to cache {}: {error}", path.display());
+                }
+            }
+        }
+    }
+    (result, dependencies)
+}
+
+impl LoadedAsset {
+    /// Adds the asset to its [Assets] resource and sends its event.
+    pub(crate) fn apply(self, scene: &mut Scene) {
+        (self.apply)(scene, self.index, self.result.ok());
+    }
+}
+
+/// Adds the loaded asset of type `T` to its [Assets] resource, replacing the previous asset if it
+/// was reloaded, or sends [AssetEvent::Failed] if there's no asset.
+fn apply_loaded<T: Asset>(
+    scene: &mut Scene,
+    index: u64,
+    asset: Option<Box<dyn Any + Send + Sync>>,
+) {
+    let id = AssetId::new(index);
+    let event = match asset.map(|asset| asset.downcast::<T>()) {
+        Some(Ok(asset)) => {
+            let data = scene
+                .get_resource::<AssetServer>()
+                .and_then(|server| server.entries.get(&index))
+                .and_then(|entry| entry.handle.upgrade());
+            let Some(data) = data else {
+                return;
+            };
+            if !scene.contains_resource::<Assets<T>>() {
+                scene.insert_resource(Assets::<T>::new());
+            }
+            let assets = scene.get_resource_mut::<Assets<T>>().unwrap();
+            match assets.insert(&Handle::from_data(data), *asset) {
+                Some(_) => AssetEvent::Modified(id),
+                None => AssetEvent::Loaded(id),
+            }
+        }
+        _ => AssetEvent::Failed(id),
+    };
+
+    if let Some(events) = scene.get_resource_mut::<Events<AssetEvent<T>>>() {
+        events.send(event);
+    }
+}
+
+/// Removes the assets of type `T` without strong handles and sends [AssetEvent::Removed] for them.
+fn remove_unused_assets<T: Asset>(scene: &mut Scene) {
+    let Some(assets) = scene.get_resource_mut::<Assets<T>>() else {
+        return;
+    };
+    let removed = assets.remove_unused();
+    if let Some(events) = scene.get_resource_mut::<Events<AssetEvent<T>>>() {
+        for id in removed {
+            events.send(AssetEvent::Removed(id));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::systems;
+
+    struct TextLoader;
+
+    impl AssetLoader for TextLoader {
+        type Asset = String;
+
+        fn extensions(&self) -> &[&str] {
+            &["txt"]
+        }
+
+        fn load(&self, context: &LoadContext) -> Result<String, LoaderError> {
+            Ok(String::from_utf8(context.bytes().to_vec())?)
+        }
+    }
+
+    /// Updates the assets until the server finished loading them.
+    fn update_until_loaded(scene: &mut Scene) {
+        loop {
+            systems::update_assets(scene);
+            if !scene.get_resource::<AssetServer>().unwrap().is_loading() {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+    }
+
+    fn scene(name: &str) -> (Scene, PathBuf) {
+        let directory = std::env::temp_dir().join(format!("pulse-{name}-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        std::fs::write(directory.join("greeting.txt"), "hello").unwrap();
+        std::fs::write(directory.join("full.bin"), [1, 2, 3]).unwrap();
+        std::fs::write(directory.join("empty.bin"), []).unwrap();
+
+        let mut server = AssetServer::new(&directory);
+        server.add_loader(TextLoader);
+        let mut scene = Scene::new();
+        scene.insert_resource(server);
+        scene.insert_resource(Events::<AssetEvent<String>>::new());
+        (scene, directory)
+    }
+
+    #[test]
+    fn handles_keep_assets_until_dropped() {
+        let mut assets = Assets::new();
+        let handle = assets.add(1);
+        let weak = handle.downgrade();
+        let clone = handle.clone();
+
+        drop(handle);
+        assert!(assets.remove_unused().is_empty());
+        assert_eq!(weak.upgrade(), Some(clone.clone()));
+        assert_eq!(assets.get(&weak), Some(&1));
+
+        drop(clone);
+        assert_eq!(assets.remove_unused(), [weak.id()]);
+        assert_eq!(weak.upgrade(), None);
+        assert!(!assets.contains(&weak));
+    }
+
+    #[test]
+    fn server_loads_assets_into_store() {
+        let (mut scene, directory) = scene("load");
+        let server = scene.get_resource_mut::<AssetServer>().unwrap();
+        let greeting = server.load::<String>("greeting.txt");
+        let missing = server.load::<String>("missing.txt");
+        let unknown = server.load::<u32>("greeting.txt");
+        assert_eq!(server.load::<String>("greeting.txt"), greeting);
+        assert!(matches!(server.load_state(&greeting), LoadState::Loading));
+
+        update_until_loaded(&mut scene);
+        let server = scene.get_resource::<AssetServer>().unwrap();
+        assert!(server.is_loaded(&greeting));
+        assert!(matches!(
+            server.load_state(&missing),
+            LoadState::Failed(AssetError::Io(_))
+        ));
+        assert!(matches!(
+            server.load_state(&unknown),
+            LoadState::Failed(AssetError::NoLoader(_))
+        ));
+        assert_eq!(server.path(&greeting), Some(Path::new("greeting.txt")));
+        let assets = scene.get_resource::<Assets<String>>().unwrap();
+        assert_eq!(assets.get(&greeting).unwrap(), "hello");
+        let events = scene.get_resource::<Events<AssetEvent<String>>>().unwrap();
+        assert_eq!(events.len(), 2);
+        assert!(events
+            .iter()
+            .any(|event| *event == AssetEvent::Loaded(greeting.id())));
+        assert!(events
+            .iter()
+            .any(|event| *event == AssetEvent::Failed(missing.id())));
+
+        let id = greeting.id();
+        drop(greeting);
+        systems::update_assets(&mut scene);
+        let server = scene.get_resource::<AssetServer>().unwrap();
+        assert!(matches!(server.load_state(id), LoadState::NotLoaded));
+        assert!(scene.get_resource::<Assets<String>>().unwrap().is_empty());
+        let events = scene.get_resource::<Events<AssetEvent<String>>>().unwrap();
+        assert!(events.iter().any(|event| *event == AssetEvent::Removed(id)));
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    /// Loader that waits for a signal before loading, or panics if the file is empty.
+    struct BlockingLoader(Mutex<std::sync::mpsc::Receiver<()>>);
+
+    impl AssetLoader for BlockingLoader {
+        type Asset = Vec<u8>;
+
+        fn extensions(&self) -> &[&str] {
+            &["bin"]
+        }
+
+        fn load(&self, context: &LoadContext) -> Result<Vec<u8>, LoaderError> {
+            self.0.lock().unwrap().recv().unwrap();
+            assert!(!context.bytes().is_empty());
+            Ok(context.bytes().to_vec())
+        }
+    }
+
+    #[test]
+    fn load_returns_before_asset_is_loaded() {
+        let (mut scene, directory) = scene("background");
+        let (signal, receiver) = std::sync::mpsc::channel();
+        let server = scene.get_resource_mut::<AssetServer>().unwrap();
+        server.add_loader(BlockingLoader(Mutex::new(receiver)));
+        let full = server.load::<Vec<u8>>("full.bin");
+        let empty = server.load::<Vec<u8>>("empty.bin");
+
+        systems::update_assets(&mut scene);
+        let server = scene.get_resource::<AssetServer>().unwrap();
+        assert!(server.is_loading());
+        assert!(matches!(server.load_state(&full), LoadState::Loading));
+
+        signal.send(()).unwrap();
+        signal.send(()).unwrap();
+        update_until_loaded(&mut scene);
+        let server = scene.get_resource::<AssetServer>().unwrap();
+        assert!(matches!(
+            server.load_state(&empty),
+            LoadState::Failed(AssetError::Loader(_))
+        ));
+        let assets = scene.get_resource::<Assets<Vec<u8>>>().unwrap();
+        assert_eq!(assets.get(&full), Some(&vec![1, 2, 3]));
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    /// Loader of lists of files loaded as dependencies with the [BlockingLoader].
+    struct ListLoader;
+
+    impl AssetLoader for ListLoader {
+        type Asset = Vec<Handle<Vec<u8>>>;
+
+        fn extensions(&self) -> &[&str] {
+            &["list"]
+        }
+
+        fn load(&self, context: &LoadContext) -> Result<Vec<Handle<Vec<u8>>>, LoaderError> {
+            let list = String::from_utf8(context.bytes().to_vec())?;
+            Ok(list.lines().map(|path| context.load(path)).collect())
+        }
+    }
+
+    #[test]
+    fn assets_are_loaded_once_dependencies_are() {
+        let (mut scene, directory) = scene("dependencies");
+        std::fs::create_dir_all(directory.join("lists")).unwrap();
+        std::fs::write(
+            directory.join("lists/full.list"),
+            "../full.bin\n.././full.bin",
+        )
+        .unwrap();
+        std::fs::write(directory.join("lists/missing.list"), "missing.bin").unwrap();
+        let (signal, receiver) = std::sync::mpsc::channel();
+        let server = scene.get_resource_mut::<AssetServer>().unwrap();
+        server
+            .add_loader(BlockingLoader(Mutex::new(receiver)))
+            .add_loader(ListLoader);
+        let full = server.load::<Vec<Handle<Vec<u8>>>>("lists/full.list");
+        let missing = server.load::<Vec<Handle<Vec<u8>>>>("lists/missing.list");
+
+        // The list waits for its dependency, which waits for the signal.
+        loop {
+            systems::update_assets(&mut scene);
+            let server = scene.get_resource::<AssetServer>().unwrap();
+            if !server.dependencies(&full).is_empty() {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+        let server = scene.get_resource_mut::<AssetServer>().unwrap();
+        assert_eq!(server.dependencies(&full), [Path::new("full.bin")]);
+        assert!(matches!(server.load_state(&full), LoadState::Loading));
+        let bytes = server.load::<Vec<u8>>("full.bin");
+
+        signal.send(()).unwrap();
+        update_until_loaded(&mut scene);
+        let server = scene.get_resource::<AssetServer>().unwrap();
+        assert!(server.is_loaded(&full));
+        assert!(matches!(
+            server.load_state(&missing),
+            LoadState::Failed(AssetError::Dependency(path, _)) if path == Path::new("lists/missing.bin")
+        ));
+        let lists = scene
+            .get_resource::<Assets<Vec<Handle<Vec<u8>>>>>()
+            .unwrap();
+        assert_eq!(lists.get(&full), Some(&vec![bytes.clone(), bytes]));
+        assert!(!lists.contains(&missing));
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    /// Loader of text processed into uppercase, to tell cached assets apart.
+    struct ShoutLoader;
+
+    impl AssetLoader for ShoutLoader {
+        type Asset = String;
+
+        fn extensions(&self) -> &[&str] {
+            &["shout"]
+        }
+
+        fn load(&self, context: &LoadContext) -> Result<String, LoaderError> {
+            Ok(String::from_utf8(context.bytes().to_vec())?)
+        }
+
+        fn process(&self, text: &String) -> Option<Vec<u8>> {
+            Some(text.to_uppercase().into_bytes())
+        }
+
+        fn load_processed(&self, bytes: &[u8]) -> Result<String, LoaderError> {
+            Ok(String::from_utf8(bytes.to_vec())?)
+        }
+    }
+
+    #[test]
+    fn processed_assets_are_loaded_from_cache() {
+        let (_, directory) = scene("cache");
+        std::fs::write(directory.join("greeting.shout"), "hello").unwrap();
+        let load = |cache: Option<PathBuf>| {
+            let mut server = AssetServer::new(&directory);
+            server.add_loader(ShoutLoader).set_cache(cache);
+            let greeting = server.load::<String>("greeting.shout");
+            let mut scene = Scene::new();
+            scene.insert_resource(server);
+            update_until_loaded(&mut scene);
+            scene
+                .get_resource::<Assets<String>>()
+                .unwrap()
+                .get(&greeting)
+                .unwrap()
+                .clone()
+        };
+
+        let cache = Some(directory.join(".pulse_cache"));
+        assert_eq!(load(cache.clone()), "hello");
+        assert_eq!(load(cache.clone()), "HELLO");
+        assert_eq!(load(None), "hello");
+        std::fs::write(directory.join("greeting.shout"), "bye").unwrap();
+        assert_eq!(load(cache), "bye");
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn mounted_packs_take_precedence_over_files() {
+        let (mut scene, directory) = scene("mount");
+        let packed = directory.join("packed");
+        std::fs::create_dir_all(&packed).unwrap();
+        std::fs::write(packed.join("greeting.txt"), "packed hello").unwrap();
+        pack::build(&packed, directory.join("assets.pack")).unwrap();
+        std::fs::write(directory.join("farewell.txt"), "bye").unwrap();
+
+        let server = scene.get_resource_mut::<AssetServer>().unwrap();
+        server.mount(AssetPack::open(directory.join("assets.pack")).unwrap());
+        let greeting = server.load::<String>("greeting.txt");
+        let farewell = server.load::<String>("farewell.txt");
+
+        update_until_loaded(&mut scene);
+        let assets = scene.get_resource::<Assets<String>>().unwrap();
+        assert_eq!(assets.get(&greeting).unwrap(), "packed hello");
+        assert_eq!(assets.get(&farewell).unwrap(), "bye");
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[cfg(feature = "hot-reload")]
+    #[test]
+    fn modified_files_reload_assets() {
+        let (mut scene, directory) = scene("reload");
+        let server = scene.get_resource_mut::<AssetServer>().unwrap();
+        let greeting = server.load::<String>("greeting.txt");
+        update_until_loaded(&mut scene);
+
+        std::fs::write(directory.join("greeting.txt"), "goodbye").unwrap();
+        let start = std::time::Instant::now();
+        loop {
+            systems::update_assets(&mut scene);
+            let assets = scene.get_resource::<Assets<String>>().unwrap();
+            if assets.get(&greeting).unwrap() == "goodbye" {
+                break;
+            }
+            assert!(start.elapsed().as_secs() < 5, "asset wasn't reloaded");
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        }
+
+        let events = scene.get_resource::<Events<AssetEvent<String>>>().unwrap();
+        assert!(events
+            .iter()
+            .any(|event| *event == AssetEvent::Modified(greeting.id())));
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+}
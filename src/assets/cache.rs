@@ -0,0 +1,246 @@
+//! # Asset Cache
+//!
+//! Directory of assets processed by their [crate::assets::AssetLoader] into bytes that are faster
+//! to load than their source files, e.g. decoded pixels or packed vertices. Entries are keyed by a
+//! hash of the loader, its settings, and the path and content of the asset's file, and record the
+//! hashes of the other files the loader read, so an entry is only used while none of the files
+//! changed. Processed bytes are compressed with zstd. Images are cached as decoded pixels, along
+//! with their blocks if they're compressed to a [crate::render::image::BlockFormat], so they aren't
+//! compressed again. The renderer also caches the prefiltered cubemaps of
+//! [crate::render::environment::EnvironmentLight]s here.
+
+use std::fs;
+use std::io;
+use std::io::Read;
+use std::mem;
+use std::path::Path;
+use std::path::PathBuf;
+
+use bytemuck::Pod;
+use ruzstd::decoding::StreamingDecoder;
+use ruzstd::encoding::CompressionLevel;
+use twox_hash::XxHash3_128;
+
+/// Start of every entry, changed whenever the layout of the entries changes.
+const MAGIC: &[u8; 8] = b"PULSEC01";
+
+/// Returns the key of the entry of the asset loaded by the loader from the file at the path
+/// relative to the root with the bytes.
+pub(crate) fn key(loader: &str, settings: &[u8], path: &Path, bytes: &[u8]) -> u128 {
+    let mut writer = ProcessedWriter::new();
+    writer.write_slice(loader.as_bytes());
+    writer.write_slice(settings);
+    writer.write_slice(path.to_string_lossy().as_bytes());
+    let mut hasher = XxHash3_128::new();
+    hasher.write(&writer.into_bytes());
+    hasher.write(bytes);
+    hasher.finish_128()
+}
+
+/// Returns the paths of the files the cached asset depends on and its processed bytes, or none if
+/// there's no entry for the key or any of the files read with the function changed.
+pub(crate) fn load(
+    directory: &Path,
+    key: u128,
+    read: impl Fn(&Path) -> io::Result<Vec<u8>>,
+) -> Option<(Vec<PathBuf>, Vec<u8>)> {
+    let bytes = fs::read(entry_path(directory, key)).ok()?;
+    let mut reader = ProcessedReader::new(bytes.strip_prefix(MAGIC)?);
+    let count = reader.read::<u32>()?;
+    let mut files = Vec::new();
+    for _ in 0..count {
+        let path = PathBuf::from(String::from_utf8(reader.read_slice()?).ok()?);
+        if reader.read::<u128>()? != file_hash(&read, &path) {
+            return None;
+        }
+        files.push(path);
+    }
+
+    let mut processed = Vec::new();
+    StreamingDecoder::new(reader.remaining())
+        .ok()?
+        .read_to_end(&mut processed)
+        .ok()?;
+    Some((files, processed))
+}
+
+/// Writes the entry of the asset with its processed bytes and the hashes of the files it depends
+/// on, read with the function. The entry is written to a temporary file first, so loads running at
+/// the same time never see a partial entry.
+pub(crate) fn store(
+    directory: &Path,
+    key: u128,
+    read: impl Fn(&Path) -> io::Result<Vec<u8>>,
+    files: &[PathBuf],
+    processed: &[u8],
+) -> io::Result<()> {
+    let mut writer = ProcessedWriter::new();
+    writer.write(u32::try_from(files.len()).map_err(io::Error::other)?);
+    for path in files {
+        writer.write_slice(path.to_string_lossy().as_bytes());
+        writer.write(file_hash(&read, path));
+    }
+    let mut bytes = MAGIC.to_vec();
+    bytes.extend(writer.into_bytes());
+    bytes.extend(ruzstd::encoding::compress_to_vec(
+        processed,
+        CompressionLevel::Fastest,
+    ));
+
+    fs::create_dir_all(directory)?;
+    let path = entry_path(directory, key);
+    let temporary = path.with_extension(format!("{}.tmp", std::process::id()));
+    fs::write(&temporary, bytes)?;
+    fs::rename(&temporary, &path).inspect_err(|_| {
+        let _ = fs::remove_file(&temporary);
+    })
+}
+
+fn entry_path(directory: &Path, key: u128) -> PathBuf {
+    directory.join(format!("{key:032x}.bin"))
+}
+
+/// Returns the hash of the file's content, or zero if it can't be read.
+fn file_hash(read: impl Fn(&Path) -> io::Result<Vec<u8>>, path: &Path) -> u128 {
+    read(path).map_or(0, |bytes| XxHash3_128::oneshot(&bytes))
+}
+
+/// Writes values and slices of plain data into processed bytes. Values are written in native byte
+/// order, since entries are only read on the machine that wrote them.
+#[derive(Default)]
+pub(crate) struct ProcessedWriter {
+    bytes: Vec<u8>,
+}
+
+impl ProcessedWriter {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    pub(crate) fn write<T: Pod>(&mut self, value: T) {
+        self.bytes.extend_from_slice(bytemuck::bytes_of(&value));
+    }
+
+    /// Writes the length of the slice followed by its values.
+    pub(crate) fn write_slice<T: Pod>(&mut self, values: &[T]) {
+        self.write(values.len() as u64);
+        self.bytes.extend_from_slice(bytemuck::cast_slice(values));
+    }
+
+    /// Writes the optional index into other values, e.g. of a mesh's material.
+    pub(crate) fn write_index(&mut self, index: Option<usize>) {
+        self.write(index.map_or(u64::MAX, |index| index as u64));
+    }
+
+    /// Writes the optional string, e.g. a name.
+    pub(crate) fn write_str(&mut self, text: Option<&str>) {
+        self.write(u8::from(text.is_some()));
+        self.write_slice(text.unwrap_or_default().as_bytes());
+    }
+
+    pub(crate) fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+/// Reads the values and slices written by a [ProcessedWriter] in the same order, returning none
+/// once the bytes run out.
+pub(crate) struct ProcessedReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> ProcessedReader<'a> {
+    pub(crate) const fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes }
+    }
+
+    pub(crate) fn read<T: Pod>(&mut self) -> Option<T> {
+        let (value, rest) = self.bytes.split_at_checked(mem::size_of::<T>())?;
+        self.bytes = rest;
+        Some(bytemuck::pod_read_unaligned(value))
+    }
+
+    pub(crate) fn read_slice<T: Pod>(&mut self) -> Option<Vec<T>> {
+        let len = usize::try_from(self.read::<u64>()?).ok()?;
+        let size = len.checked_mul(mem::size_of::<T>())?;
+        let (values, rest) = self.bytes.split_at_checked(size)?;
+        self.bytes = rest;
+        Some(
+            values
+                .chunks_exact(mem::size_of::<T>())
+                .map(bytemuck::pod_read_unaligned)
+                .collect(),
+        )
+    }
+
+    pub(crate) fn read_index(&mut self) -> Option<Option<usize>> {
+        match self.read::<u64>()? {
+            u64::MAX => Some(None),
+            index => usize::try_from(index).ok().map(Some),
+        }
+    }
+
+    pub(crate) fn read_string(&mut self) -> Option<Option<String>> {
+        let some = self.read::<u8>()? != 0;
+        let text = String::from_utf8(self.read_slice()?).ok()?;
+        Some(some.then_some(text))
+    }
+
+    /// Returns the bytes that weren't read yet.
+    pub(crate) const fn remaining(&self) -> &'a [u8] {
+        self.bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entries_are_invalidated_by_changed_files() {
+        let root = std::env::temp_dir().join(format!("pulse-cache-{}", std::process::id()));
+        let directory = root.join(".pulse_cache");
+        let read = |path: &Path| fs::read(root.join(path));
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join("material.ron"), "()").unwrap();
+
+        let key = super::key("Loader", &[], Path::new("model.obj"), b"model");
+        assert_ne!(
+            key,
+            super::key("Loader", &[1], Path::new("model.obj"), b"model")
+        );
+        assert_eq!(load(&directory, key, read), None);
+
+        let files = [PathBuf::from("material.ron")];
+        store(&directory, key, read, &files, b"processed").unwrap();
+        let (loaded, processed) = load(&directory, key, read).unwrap();
+        assert_eq!(loaded, files);
+        assert_eq!(processed, b"processed");
+
+        fs::write(root.join("material.ron"), "(color: 1.0)").unwrap();
+        assert_eq!(load(&directory, key, read), None);
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn readers_read_written_values() {
+        let mut writer = ProcessedWriter::new();
+        writer.write(3u8);
+        writer.write_slice(&[1.5f32, 2.5]);
+        writer.write_index(None);
+        writer.write_index(Some(7));
+        writer.write_str(Some("name"));
+        writer.write_str(None);
+        let bytes = writer.into_bytes();
+
+        let mut reader = ProcessedReader::new(&bytes);
+        assert_eq!(reader.read::<u8>(), Some(3));
+        assert_eq!(reader.read_slice::<f32>(), Some(vec![1.5, 2.5]));
+        assert_eq!(reader.read_index(), Some(None));
+        assert_eq!(reader.read_index(), Some(Some(7)));
+        assert_eq!(reader.read_string(), Some(Some("name".to_string())));
+        assert_eq!(reader.read_string(), Some(None));
+        assert_eq!(reader.read::<u8>(), None);
+        assert!(reader.remaining().is_empty());
+    }
+}
@@ -0,0 +1,1189 @@
+//! # glTF
+//!
+//! Import of glTF 2.0 scenes from `.gltf` and `.glb` files. A [Gltf] asset holds the meshes,
+//! materials, node hierarchy, skins, and animations of a file, and [Gltf::spawn] instantiates its
+//! nodes into a scene, any number of times.
+//!
+//! ```no_run
+//! # use pulse::animation::AnimationPlayer;
+//! # use pulse::assets::gltf::Gltf;
+//! # use pulse::assets::AssetServer;
+//! # use pulse::assets::Assets;
+//! # use pulse::systems;
+//! # use pulse::Scene;
+//! let mut scene = Scene::new();
+//! scene.insert_resource(AssetServer::new("assets"));
+//!
+//! let server = scene.get_resource_mut::<AssetServer>().unwrap();
+//! let fox = server.load::<Gltf>("models/fox.glb");
+//!
+//! systems::update_assets(&mut scene);
+//! let Some(gltf) = scene
+//!     .get_resource::<Assets<Gltf>>()
+//!     .and_then(|gltfs| gltfs.get(&fox))
+//!     .cloned()
+//! else {
+//!     return;
+//! };
+//! let instance = gltf.spawn(&mut scene);
+//! if let Some(run) = instance.animations.first() {
+//!     scene.add(instance.root, AnimationPlayer::new(run.clone()));
+//! }
+//! ```
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::fmt;
+use std::io;
+
+use glam::Mat4;
+use glam::Quat;
+use glam::Vec2;
+use glam::Vec3;
+use glam::Vec4;
+use gltf::animation::util::ReadOutputs;
+use gltf::mesh::Mode;
+use gltf::texture::MagFilter;
+use gltf::texture::MinFilter;
+use gltf::texture::WrappingMode;
+use gltf::Document;
+
+use crate::animation::AnimationChannel;
+use crate::animation::AnimationClip;
+use crate::animation::Interpolation;
+use crate::animation::Keyframes;
+use crate::assets::cache::ProcessedReader;
+use crate::assets::cache::ProcessedWriter;
+use crate::assets::AssetLoader;
+use crate::assets::LoadContext;
+use crate::render::image::AddressMode;
+use crate::render::image::ColorSpace;
+use crate::render::image::FilterMode;
+use crate::render::image::Image;
+use crate::render::image::ImageError;
+use crate::render::image::ImageSampler;
+use crate::render::material;
+use crate::render::material::AlphaMode;
+use crate::render::material::Material;
+use crate::render::material::StandardMaterial;
+use crate::render::mesh::Mesh;
+use crate::render::mesh::MeshData;
+use crate::render::mesh::MeshError;
+use crate::render::mesh::MorphTarget;
+use crate::render::morph::MorphWeights;
+use crate::render::skin::Skeleton;
+use crate::render::skin::SkinnedMesh;
+use crate::render::Color;
+use crate::LocalTransform;
+use crate::Name;
+use crate::Node;
+use crate::Scene;
+use crate::Visibility;
+
+/// # glTF
+///
+/// Contents of a glTF file, with the file's objects referring to each other by their index as in
+/// the file.
+#[derive(Clone, Debug, Default)]
+pub struct Gltf {
+    /// Primitives of each mesh.
+    pub meshes: Vec<Vec<GltfPrimitive>>,
+    /// Metallic-roughness materials with their textures.
+    pub materials: Vec<Material>,
+    /// Nodes of the hierarchy.
+    pub nodes: Vec<GltfNode>,
+    /// Skeletons of the skinned meshes.
+    pub skins: Vec<GltfSkin>,
+    /// Keyframe animations of the nodes.
+    pub animations: Vec<GltfAnimation>,
+    /// Root nodes of each scene.
+    pub scenes: Vec<Vec<usize>>,
+    /// Scene spawned by [Gltf::spawn], if the file has one.
+    pub default_scene: Option<usize>,
+}
+
+/// # glTF Primitive
+///
+/// Part of a glTF mesh drawn with a single material.
+#[derive(Clone, Debug)]
+pub struct GltfPrimitive {
+    /// Triangles of the primitive.
+    pub mesh: Mesh,
+    /// Material of the primitive, or none for the default material.
+    pub material: Option<usize>,
+}
+
+/// # glTF Node
+///
+/// Node of a glTF hierarchy.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GltfNode {
+    /// Name of the node, if it has one.
+    pub name: Option<String>,
+    /// Transform of the node relative to its parent.
+    pub transform: LocalTransform,
+    /// Child nodes.
+    pub children: Vec<usize>,
+    /// Mesh of the node, if it has one.
+    pub mesh: Option<usize>,
+    /// Skin deforming the node's mesh, if it's skinned.
+    pub skin: Option<usize>,
+    /// Initial weights of the morph targets of the node's mesh.
+    pub weights: Vec<f32>,
+}
+
+/// # glTF Skin
+///
+/// Joints deforming a glTF mesh.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GltfSkin {
+    /// Joint nodes, indexed by the mesh's vertex joints.
+    pub joints: Vec<usize>,
+    /// Matrix transforming the mesh to each joint's space in the bind pose.
+    pub inverse_bind_matrices: Vec<Mat4>,
+}
+
+/// # glTF Animation
+///
+/// Named keyframe animation of glTF nodes.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GltfAnimation {
+    /// Name of the animation, if it has one.
+    pub name: Option<String>,
+    /// Animated properties of the nodes.
+    pub channels: Vec<GltfChannel>,
+}
+
+/// # glTF Channel
+///
+/// Keyframes of a property of a glTF node.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GltfChannel {
+    /// Animated node.
+    pub node: usize,
+    /// Increasing times of the keyframes in seconds.
+    pub times: Vec<f32>,
+    /// Values of the property at the keyframes.
+    pub keyframes: Keyframes,
+    /// How the values are interpolated between keyframes.
+    pub interpolation: Interpolation,
+}
+
+/// # glTF Instance
+///
+/// Nodes spawned by [Gltf::spawn].
+#[derive(Clone, Debug, PartialEq)]
+pub struct GltfInstance {
+    /// Node parenting the root nodes of the spawned scene.
+    pub root: Node,
+    /// Spawned node of each glTF node, or none if the node isn't part of the spawned scene.
+    pub nodes: Vec<Option<Node>>,
+    /// Clips of the glTF animations targeting the spawned nodes, in the order of the file.
+    pub animations: Vec<AnimationClip>,
+}
+
+impl Gltf {
+    /// Decodes a `.gltf` or `.glb` file, reading external buffers and images with `read`, given
+    /// their URI relative to the file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file isn't valid glTF, a buffer or image can't be read or decoded,
+    /// or a mesh has points or lines.
+    pub fn decode(
+        bytes: &[u8],
+        read: impl Fn(&str) -> io::Result<Vec<u8>>,
+    ) -> Result<Self, GltfError> {
+        let gltf::Gltf { document, mut blob } = gltf::Gltf::from_slice(bytes)?;
+        let buffers = document
+            .buffers()
+            .map(|buffer| {
+                let data = match buffer.source() {
+                    gltf::buffer::Source::Bin => blob.take(),
+                    gltf::buffer::Source::Uri(uri) => Some(read_uri(uri, &read)?),
+                };
+                data.filter(|data| data.len() >= buffer.length())
+                    .ok_or(GltfError::InvalidBuffer {
+                        index: buffer.index(),
+                    })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Importer {
+            document: &document,
+            buffers,
+            read: &read,
+            images: HashMap::new(),
+        }
+        .import()
+    }
+
+    /// Spawns the nodes of the default scene, or of the first scene if there's no default scene,
+    /// under a new root node. Files without scenes spawn all their nodes.
+    pub fn spawn(&self, scene: &mut Scene) -> GltfInstance {
+        let roots = match self.default_scene.unwrap_or(0) {
+            index if index < self.scenes.len() => self.scenes[index].clone(),
+            _ => {
+                let children = self
+                    .nodes
+                    .iter()
+                    .flat_map(|node| node.children.iter().copied())
+                    .collect::<HashSet<_>>();
+                (0..self.nodes.len())
+                    .filter(|index| !children.contains(index))
+                    .collect()
+            }
+        };
+        self.spawn_nodes(scene, &roots)
+    }
+
+    /// Spawns the nodes of the scene under a new root node, or returns [None] if the file doesn't
+    /// have the scene.
+    pub fn spawn_scene(&self, scene: &mut Scene, index: usize) -> Option<GltfInstance> {
+        let roots = self.scenes.get(index)?;
+        Some(self.spawn_nodes(scene, roots))
+    }
+
+    fn spawn_nodes(&self, scene: &mut Scene, roots: &[usize]) -> GltfInstance {
+        let root = scene.spawn_with((LocalTransform::IDENTITY, Visibility::Visible));
+        let mut nodes = vec![None; self.nodes.len()];
+        let mut stack = roots
+            .iter()
+            .rev()
+            .map(|index| (*index, root))
+            .collect::<Vec<_>>();
+        while let Some((index, parent)) = stack.pop() {
+            let Some(gltf_node) = self.nodes.get(index) else {
+                continue;
+            };
+            if nodes[index].is_some() {
+                continue;
+            }
+
+            let node = scene.spawn_with((gltf_node.transform, Visibility::Visible));
+            scene.set_parent(node, parent);
+            if let Some(name) = &gltf_node.name {
+                scene.add(node, Name::new(name));
+            }
+            nodes[index] = Some(node);
+            stack.extend(gltf_node.children.iter().rev().map(|child| (*child, node)));
+        }
+
+        // Meshes are added once all nodes exist, since skins may refer to joints anywhere in the
+        // hierarchy. The primitives of meshes with several of them get a child node each.
+        let mut mesh_nodes = vec![Vec::new(); self.nodes.len()];
+        for (index, gltf_node) in self.nodes.iter().enumerate() {
+            let (Some(node), Some(primitives)) = (
+                nodes[index],
+                gltf_node.mesh.and_then(|mesh| self.meshes.get(mesh)),
+            ) else {
+                continue;
+            };
+
+            let skinned = gltf_node
+                .skin
+                .and_then(|skin| self.skins.get(skin))
+                .and_then(|skin| {
+                    let joints = skin
+                        .joints
+                        .iter()
+                        .map(|joint| nodes.get(*joint).copied().flatten())
+                        .collect::<Option<Vec<_>>>()?;
+                    Some(SkinnedMesh {
+                        skeleton: Skeleton {
+                            joints,
+                            inverse_bind_matrices: skin.inverse_bind_matrices.clone(),
+                        },
+                    })
+                });
+            for primitive in primitives {
+                let primitive_node = if primitives.len() == 1 {
+                    node
+                } else {
+                    let child = scene.spawn_with((LocalTransform::IDENTITY, Visibility::Visible));
+                    scene.set_parent(child, node);
+                    child
+                };
+
+                let material = primitive
+                    .material
+                    .and_then(|material| self.materials.get(material).cloned())
+                    .unwrap_or_else(|| Material::new(StandardMaterial::default()));
+                scene.add(primitive_node, primitive.mesh.clone());
+                scene.add(primitive_node, material);
+                if let Some(skinned) = &skinned {
+                    scene.add(primitive_node, skinned.clone());
+                }
+                if !gltf_node.weights.is_empty() {
+                    scene.add(primitive_node, MorphWeights(gltf_node.weights.clone()));
+                }
+                mesh_nodes[index].push(primitive_node);
+            }
+        }
+
+        let animations = self
+            .animations
+            .iter()
+            .map(|animation| {
+                let channels = animation
+                    .channels
+                    .iter()
+                    .flat_map(|channel| {
+                        // Weights are animated on the nodes of the mesh's primitives.
+                        let targets = match channel.keyframes {
+                            Keyframes::Weights(_) => mesh_nodes.get(channel.node).cloned(),
+                            _ => nodes
+                                .get(channel.node)
+                                .copied()
+                                .flatten()
+                                .map(|node| vec![node]),
+                        };
+                        targets
+                            .unwrap_or_default()
+                            .into_iter()
+                            .map(|target| AnimationChannel {
+                                target,
+                                times: channel.times.clone(),
+                                keyframes: channel.keyframes.clone(),
+                                interpolation: channel.interpolation,
+                            })
+                    })
+                    .collect();
+                AnimationClip::new(animation.name.clone().unwrap_or_default(), channels)
+            })
+            .collect();
+
+        GltfInstance {
+            root,
+            nodes,
+            animations,
+        }
+    }
+
+    /// Returns the contents packed for the asset cache, or `None` if a material is a
+    /// [crate::render::shader_material::ShaderMaterial].
+    pub(crate) fn to_processed(&self) -> Option<Vec<u8>> {
+        let mut writer = ProcessedWriter::new();
+        material::write_processed(&mut writer, &self.materials)?;
+        writer.write(self.meshes.len() as u64);
+        for primitives in &self.meshes {
+            writer.write(primitives.len() as u64);
+            for primitive in primitives {
+                writer.write_slice(&primitive.mesh.data().to_processed());
+                writer.write_index(primitive.material);
+            }
+        }
+
+        writer.write(self.nodes.len() as u64);
+        for node in &self.nodes {
+            writer.write_str(node.name.as_deref());
+            let LocalTransform {
+                position,
+                rotation,
+                scale,
+            } = node.transform;
+            writer.write(position);
+            writer.write(rotation);
+            writer.write(scale);
+            writer.write_slice(&indices(&node.children));
+            writer.write_index(node.mesh);
+            writer.write_index(node.skin);
+            writer.write_slice(&node.weights);
+        }
+
+        writer.write(self.skins.len() as u64);
+        for skin in &self.skins {
+            writer.write_slice(&indices(&skin.joints));
+            writer.write_slice(&skin.inverse_bind_matrices);
+        }
+
+        writer.write(self.animations.len() as u64);
+        for animation in &self.animations {
+            writer.write_str(animation.name.as_deref());
+            writer.write(animation.channels.len() as u64);
+            for channel in &animation.channels {
+                writer.write(channel.node as u64);
+                writer.write_slice(&channel.times);
+                match &channel.keyframes {
+                    Keyframes::Translation(values) => {
+                        writer.write(0u8);
+                        writer.write_slice(values);
+                    }
+                    Keyframes::Rotation(values) => {
+                        writer.write(1u8);
+                        writer.write_slice(values);
+                    }
+                    Keyframes::Scale(values) => {
+                        writer.write(2u8);
+                        writer.write_slice(values);
+                    }
+                    Keyframes::Weights(values) => {
+                        writer.write(3u8);
+                        writer.write_slice(values);
+                    }
+                }
+                writer.write(match channel.interpolation {
+                    Interpolation::Step => 0u8,
+                    Interpolation::Linear => 1,
+                    Interpolation::CubicSpline => 2,
+                });
+            }
+        }
+
+        writer.write(self.scenes.len() as u64);
+        for roots in &self.scenes {
+            writer.write_slice(&indices(roots));
+        }
+        writer.write_index(self.default_scene);
+        Some(writer.into_bytes())
+    }
+
+    /// Returns the contents packed by [Gltf::to_processed], or `None` if the bytes are invalid.
+    pub(crate) fn from_processed(bytes: &[u8]) -> Option<Self> {
+        let mut reader = ProcessedReader::new(bytes);
+        let materials = material::read_processed(&mut reader)?;
+        let meshes = (0..reader.read::<u64>()?)
+            .map(|_| {
+                (0..reader.read::<u64>()?)
+                    .map(|_| {
+                        let data = MeshData::from_processed(&reader.read_slice::<u8>()?)?;
+                        Some(GltfPrimitive {
+                            mesh: Mesh::new(data).ok()?,
+                            material: reader.read_index()?,
+                        })
+                    })
+                    .collect()
+            })
+            .collect::<Option<_>>()?;
+
+        let nodes = (0..reader.read::<u64>()?)
+            .map(|_| {
+                Some(GltfNode {
+                    name: reader.read_string()?,
+                    transform: LocalTransform::new(reader.read()?, reader.read()?, reader.read()?),
+                    children: read_indices(&mut reader)?,
+                    mesh: reader.read_index()?,
+                    skin: reader.read_index()?,
+                    weights: reader.read_slice()?,
+                })
+            })
+            .collect::<Option<_>>()?;
+
+        let skins = (0..reader.read::<u64>()?)
+            .map(|_| {
+                Some(GltfSkin {
+                    joints: read_indices(&mut reader)?,
+                    inverse_bind_matrices: reader.read_slice()?,
+                })
+            })
+            .collect::<Option<_>>()?;
+
+        let animations = (0..reader.read::<u64>()?)
+            .map(|_| {
+                let name = reader.read_string()?;
+                let channels = (0..reader.read::<u64>()?)
+                    .map(|_| {
+                        Some(GltfChannel {
+                            node: usize::try_from(reader.read::<u64>()?).ok()?,
+                            times: reader.read_slice()?,
+                            keyframes: match reader.read::<u8>()? {
+                                0 => Keyframes::Translation(reader.read_slice()?),
+                                1 => Keyframes::Rotation(reader.read_slice()?),
+                                2 => Keyframes::Scale(reader.read_slice()?),
+                                3 => Keyframes::Weights(reader.read_slice()?),
+                                _ => return None,
+                            },
+                            interpolation: match reader.read::<u8>()? {
+                                0 => Interpolation::Step,
+                                1 => Interpolation::Linear,
+                                2 => Interpolation::CubicSpline,
+                                _ => return None,
+                            },
+                        })
+                    })
+                    .collect::<Option<_>>()?;
+                Some(GltfAnimation { name, channels })
+            })
+            .collect::<Option<_>>()?;
+
+        let scenes = (0..reader.read::<u64>()?)
+            .map(|_| read_indices(&mut reader))
+            .collect::<Option<_>>()?;
+        Some(Self {
+            meshes,
+            materials,
+            nodes,
+            skins,
+            animations,
+            scenes,
+            default_scene: reader.read_index()?,
+        })
+    }
+}
+
+/// Returns the indices as 64-bit values for the asset cache.
+fn indices(indices: &[usize]) -> Vec<u64> {
+    indices.iter().map(|index| *index as u64).collect()
+}
+
+/// Reads indices written as 64-bit values, or returns `None` if one doesn't fit.
+fn read_indices(reader: &mut ProcessedReader) -> Option<Vec<usize>> {
+    reader
+        .read_slice::<u64>()?
+        .into_iter()
+        .map(|index| usize::try_from(index).ok())
+        .collect()
+}
+
+/// Converts the objects of a glTF document, decoding the image of each of its textures once for
+/// each color space it's used in.
+struct Importer<'a> {
+    document: &'a Document,
+    buffers: Vec<Vec<u8>>,
+    read: &'a dyn Fn(&str) -> io::Result<Vec<u8>>,
+    images: HashMap<(usize, ColorSpace), Image>,
+}
+
+impl Importer<'_> {
+    fn import(mut self) -> Result<Gltf, GltfError> {
+        let document = self.document;
+        let materials = document
+            .materials()
+            .map(|material| self.material(&material))
+            .collect::<Result<_, _>>()?;
+        let meshes = document
+            .meshes()
+            .map(|mesh| {
+                mesh.primitives()
+                    .map(|primitive| self.primitive(mesh.index(), &primitive))
+                    .collect()
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Gltf {
+            meshes,
+            materials,
+            nodes: document.nodes().map(|node| self.node(&node)).collect(),
+            skins: document.skins().map(|skin| self.skin(&skin)).collect(),
+            animations: document
+                .animations()
+                .map(|animation| self.animation(&animation))
+                .collect(),
+            scenes: document
+                .scenes()
+                .map(|scene| scene.nodes().map(|node| node.index()).collect())
+                .collect(),
+            default_scene: document.default_scene().map(|scene| scene.index()),
+        })
+    }
+
+    fn buffer(&self, buffer: gltf::Buffer) -> Option<&[u8]> {
+        self.buffers.get(buffer.index()).map(Vec::as_slice)
+    }
+
+    fn material(&mut self, material: &gltf::Material) -> Result<Material, GltfError> {
+        let pbr = material.pbr_metallic_roughness();
+        let [r, g, b, a] = pbr.base_color_factor();
+        let [emissive_r, emissive_g, emissive_b] = material
+            .emissive_factor()
+            .map(|factor| factor * material.emissive_strength().unwrap_or(1.0));
+        let alpha_mode = match material.alpha_mode() {
+            gltf::material::AlphaMode::Opaque => AlphaMode::Opaque,
+            gltf::material::AlphaMode::Mask => {
+                AlphaMode::Mask(material.alpha_cutoff().unwrap_or(0.5))
+            }
+            gltf::material::AlphaMode::Blend => AlphaMode::Blend,
+        };
+
+        let defaults = StandardMaterial::default();
+        let normal = material.normal_texture();
+        let occlusion = material.occlusion_texture();
+        Ok(Material::new(StandardMaterial {
+            base_color: Color::rgba(r, g, b, a),
+            base_color_texture: pbr
+                .base_color_texture()
+                .map(|info| self.image(&info.texture(), ColorSpace::Srgb))
+                .transpose()?,
+            metallic: pbr.metallic_factor(),
+            roughness: pbr.roughness_factor(),
+            metallic_roughness_texture: pbr
+                .metallic_roughness_texture()
+                .map(|info| self.image(&info.texture(), ColorSpace::Linear))
+                .transpose()?,
+            normal_texture: normal
+                .as_ref()
+                .map(|normal| self.image(&normal.texture(), ColorSpace::Linear))
+                .transpose()?,
+            normal_scale: normal.map_or(defaults.normal_scale, |normal| normal.scale()),
+            emissive: Color::rgb(emissive_r, emissive_g, emissive_b),
+            emissive_texture: material
+                .emissive_texture()
+                .map(|info| self.image(&info.texture(), ColorSpace::Srgb))
+                .transpose()?,
+            occlusion_texture: occlusion
+                .as_ref()
+                .map(|occlusion| self.image(&occlusion.texture(), ColorSpace::Linear))
+                .transpose()?,
+            occlusion_strength: occlusion.map_or(defaults.occlusion_strength, |occlusion| {
+                occlusion.strength()
+            }),
+            alpha_mode,
+        }))
+    }
+
+    fn image(
+        &mut self,
+        texture: &gltf::Texture,
+        color_space: ColorSpace,
+    ) -> Result<Image, GltfError> {
+        if let Some(image) = self.images.get(&(texture.index(), color_space)) {
+            return Ok(image.clone());
+        }
+
+        let source = texture.source();
+        let index = source.index();
+        let bytes = match source.source() {
+            gltf::image::Source::View { view, .. } => self
+                .buffer(view.buffer())
+                .and_then(|buffer| buffer.get(view.offset()..view.offset() + view.length()))
+                .ok_or(GltfError::InvalidBuffer {
+                    index: view.buffer().index(),
+                })?
+                .to_vec(),
+            gltf::image::Source::Uri { uri, .. } => read_uri(uri, self.read)?,
+        };
+        let image = Image::decode(&bytes, color_space)
+            .map_err(|error| GltfError::Image { index, error })?;
+
+        let sampler = texture.sampler();
+        let wrap = |mode| match mode {
+            WrappingMode::ClampToEdge => AddressMode::ClampToEdge,
+            WrappingMode::MirroredRepeat => AddressMode::MirrorRepeat,
+            WrappingMode::Repeat => AddressMode::Repeat,
+        };
+        let (min_filter, mipmap_filter) = match sampler.min_filter() {
+            Some(MinFilter::Nearest | MinFilter::NearestMipmapNearest) => {
+                (FilterMode::Nearest, FilterMode::Nearest)
+            }
+            Some(MinFilter::NearestMipmapLinear) => (FilterMode::Nearest, FilterMode::Linear),
+            Some(MinFilter::LinearMipmapNearest) => (FilterMode::Linear, FilterMode::Nearest),
+            Some(MinFilter::Linear | MinFilter::LinearMipmapLinear) | None => {
+                (FilterMode::Linear, FilterMode::Linear)
+            }
+        };
+        let mipmapped = !matches!(
+            sampler.min_filter(),
+            Some(MinFilter::Nearest | MinFilter::Linear)
+        );
+        let image = if mipmapped {
+            image.with_mipmaps()
+        } else {
+            image
+        }
+        .with_sampler(ImageSampler {
+            address_mode_u: wrap(sampler.wrap_s()),
+            address_mode_v: wrap(sampler.wrap_t()),
+            mag_filter: match sampler.mag_filter() {
+                Some(MagFilter::Nearest) => FilterMode::Nearest,
+                Some(MagFilter::Linear) | None => FilterMode::Linear,
+            },
+            min_filter,
+            mipmap_filter,
+            ..ImageSampler::default()
+        });
+        self.images
+            .insert((texture.index(), color_space), image.clone());
+        Ok(image)
+    }
+
+    fn primitive(
+        &self,
+        mesh: usize,
+        primitive: &gltf::Primitive,
+    ) -> Result<GltfPrimitive, GltfError> {
+        let unsupported = || GltfError::UnsupportedPrimitive { mesh };
+        let reader = primitive.reader(|buffer| self.buffer(buffer));
+        let positions = reader
+            .read_positions()
+            .ok_or_else(unsupported)?
+            .map(Vec3::from)
+            .collect::<Vec<_>>();
+        let vertex_count = positions.len();
+
+        let indices = reader
+            .read_indices()
+            .map(|indices| indices.into_u32().collect::<Vec<_>>())
+            .unwrap_or_else(|| (0..vertex_count as u32).collect());
+        let indices = match primitive.mode() {
+            Mode::Triangles => indices,
+            // Every other triangle of a strip is flipped to keep the winding.
+            Mode::TriangleStrip => (2..indices.len())
+                .flat_map(|i| {
+                    if i % 2 == 0 {
+                        [indices[i - 2], indices[i - 1], indices[i]]
+                    } else {
+                        [indices[i - 1], indices[i - 2], indices[i]]
+                    }
+                })
+                .collect(),
+            Mode::TriangleFan => (2..indices.len())
+                .flat_map(|i| [indices[0], indices[i - 1], indices[i]])
+                .collect(),
+            Mode::Points | Mode::Lines | Mode::LineLoop | Mode::LineStrip => {
+                return Err(unsupported())
+            }
+        };
+
+        let normals = reader
+            .read_normals()
+            .map(|normals| normals.map(Vec3::from).collect::<Vec<_>>());
+        let mut data = MeshData {
+            positions,
+            normals: normals.clone().unwrap_or_default(),
+            tangents: reader
+                .read_tangents()
+                .map(|tangents| tangents.map(Vec4::from).collect())
+                .unwrap_or_default(),
+            uvs: reader
+                .read_tex_coords(0)
+                .map(|uvs| uvs.into_f32().map(Vec2::from).collect())
+                .unwrap_or_else(|| vec![Vec2::ZERO; vertex_count]),
+            joints: reader
+                .read_joints(0)
+                .map(|joints| joints.into_u16().collect())
+                .unwrap_or_default(),
+            weights: reader
+                .read_weights(0)
+                .map(|weights| weights.into_f32().map(Vec4::from).collect())
+                .unwrap_or_default(),
+            morph_targets: reader
+                .read_morph_targets()
+                .map(|(positions, normals, _)| MorphTarget {
+                    positions: positions
+                        .map(|positions| positions.map(Vec3::from).collect())
+                        .unwrap_or_else(|| vec![Vec3::ZERO; vertex_count]),
+                    normals: normals
+                        .map(|normals| normals.map(Vec3::from).collect())
+                        .unwrap_or_default(),
+                })
+                .collect(),
+            indices,
+        };
+        if normals.is_none() {
+            data.compute_normals();
+        }
+
+        Ok(GltfPrimitive {
+            mesh: Mesh::new(data).map_err(|error| GltfError::Mesh { mesh, error })?,
+            material: primitive.material().index(),
+        })
+    }
+
+    fn node(&self, node: &gltf::Node) -> GltfNode {
+        let (translation, rotation, scale) = node.transform().decomposed();
+        let mesh = node.mesh();
+        let target_count = mesh
+            .as_ref()
+            .and_then(|mesh| mesh.primitives().next())
+            .map_or(0, |primitive| primitive.morph_targets().count());
+        let weights = node
+            .weights()
+            .or_else(|| mesh.as_ref().and_then(|mesh| mesh.weights()))
+            .map_or_else(|| vec![0.0; target_count], <[f32]>::to_vec);
+
+        GltfNode {
+            name: node.name().map(str::to_owned),
+            transform: LocalTransform::new(
+                Vec3::from(translation),
+                Quat::from_array(rotation),
+                Vec3::from(scale),
+            ),
+            children: node.children().map(|child| child.index()).collect(),
+            mesh: mesh.map(|mesh| mesh.index()),
+            skin: node.skin().map(|skin| skin.index()),
+            weights,
+        }
+    }
+
+    fn skin(&self, skin: &gltf::Skin) -> GltfSkin {
+        let joints = skin.joints().map(|joint| joint.index()).collect::<Vec<_>>();
+        let inverse_bind_matrices = skin
+            .reader(|buffer| self.buffer(buffer))
+            .read_inverse_bind_matrices()
+            .map(|matrices| {
+                matrices
+                    .map(|matrix| Mat4::from_cols_array_2d(&matrix))
+                    .collect()
+            })
+            .unwrap_or_else(|| vec![Mat4::IDENTITY; joints.len()]);
+        GltfSkin {
+            joints,
+            inverse_bind_matrices,
+        }
+    }
+
+    fn animation(&self, animation: &gltf::Animation) -> GltfAnimation {
+        let channels = animation
+            .channels()
+            .filter_map(|channel| {
+                let reader = channel.reader(|buffer| self.buffer(buffer));
+                let keyframes = match reader.read_outputs()? {
+                    ReadOutputs::Translations(values) => {
+                        Keyframes::Translation(values.map(Vec3::from).collect())
+                    }
+                    ReadOutputs::Rotations(values) => {
+                        Keyframes::Rotation(values.into_f32().map(Quat::from_array).collect())
+                    }
+                    ReadOutputs::Scales(values) => {
+                        Keyframes::Scale(values.map(Vec3::from).collect())
+                    }
+                    ReadOutputs::MorphTargetWeights(values) => {
+                        Keyframes::Weights(values.into_f32().collect())
+                    }
+                };
+                let interpolation = match channel.sampler().interpolation() {
+                    gltf::animation::Interpolation::Step => Interpolation::Step,
+                    gltf::animation::Interpolation::Linear => Interpolation::Linear,
+                    gltf::animation::Interpolation::CubicSpline => Interpolation::CubicSpline,
+                };
+                Some(GltfChannel {
+                    node: channel.target().node().index(),
+                    times: reader.read_inputs()?.collect(),
+                    keyframes,
+                    interpolation,
+                })
+            })
+            .collect();
+        GltfAnimation {
+            name: animation.name().map(str::to_owned),
+            channels,
+        }
+    }
+}
+
+/// Returns the data of a `data:` URI, or reads the file at the URI.
+fn read_uri(uri: &str, read: &dyn Fn(&str) -> io::Result<Vec<u8>>) -> Result<Vec<u8>, GltfError> {
+    let Some(data) = uri.strip_prefix("data:") else {
+        return read(uri).map_err(|error| GltfError::Io {
+            uri: uri.to_owned(),
+            error,
+        });
+    };
+
+    data.split_once(";base64,")
+        .and_then(|(_, base64)| decode_base64(base64))
+        .ok_or_else(|| GltfError::InvalidUri {
+            uri: uri.chars().take(64).collect(),
+        })
+}
+
+/// Decodes standard or URL-safe base64 with optional padding, or returns [None] if the text
+/// contains other characters.
+fn decode_base64(text: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(text.len() * 3 / 4);
+    let (mut bits, mut bit_count) = (0u32, 0);
+    for byte in text.trim_end_matches('=').bytes() {
+        let value = match byte {
+            b'A'..=b'Z' => byte - b'A',
+            b'a'..=b'z' => byte - b'a' + 26,
+            b'0'..=b'9' => byte - b'0' + 52,
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            _ => return None,
+        };
+        bits = bits << 6 | u32::from(value);
+        bit_count += 6;
+        if bit_count >= 8 {
+            bit_count -= 8;
+            bytes.push((bits >> bit_count) as u8);
+        }
+    }
+    Some(bytes)
+}
+
+/// # glTF Loader
+///
+/// [AssetLoader] of [Gltf]s from `.gltf` files, with their buffers and images embedded or next to
+/// them, and from binary `.glb` files.
+pub struct GltfLoader;
+
+impl AssetLoader for GltfLoader {
+    type Asset = Gltf;
+
+    fn extensions(&self) -> &[&str] {
+        &["gltf", "glb"]
+    }
+
+    fn load(
+        &self,
+        context: &LoadContext,
+    ) -> Result<Gltf, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(Gltf::decode(context.bytes(), |uri| context.read(uri))?)
+    }
+
+    fn process(&self, gltf: &Gltf) -> Option<Vec<u8>> {
+        gltf.to_processed()
+    }
+
+    fn load_processed(
+        &self,
+        bytes: &[u8],
+    ) -> Result<Gltf, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(Gltf::from_processed(bytes).ok_or(GltfError::InvalidData)?)
+    }
+}
+
+/// # glTF Error
+///
+/// Error returned when a [Gltf] can't be decoded.
+#[derive(Debug)]
+pub enum GltfError {
+    /// The file isn't valid glTF.
+    Gltf(gltf::Error),
+    /// An external buffer or image file couldn't be read.
+    Io {
+        /// URI of the file.
+        uri: String,
+        /// Error reading the file.
+        error: io::Error,
+    },
+    /// A `data:` URI isn't base64 encoded.
+    InvalidUri {
+        /// Start of the URI.
+        uri: String,
+    },
+    /// A buffer is shorter than its declared length or refers to a missing GLB binary chunk.
+    InvalidBuffer {
+        /// Index of the buffer.
+        index: usize,
+    },
+    /// An image couldn't be decoded.
+    Image {
+        /// Index of the image.
+        index: usize,
+        /// Error decoding the image.
+        error: ImageError,
+    },
+    /// A primitive has no positions or consists of points or lines.
+    UnsupportedPrimitive {
+        /// Index of the primitive's mesh.
+        mesh: usize,
+    },
+    /// A primitive's attributes or indices are invalid.
+    Mesh {
+        /// Index of the primitive's mesh.
+        mesh: usize,
+        /// Error of the primitive's data.
+        error: MeshError,
+    },
+    /// The processed contents in the asset cache are invalid.
+    InvalidData,
+}
+
+impl fmt::Display for GltfError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Gltf(error) => write!(f, "invalid glTF: {error}"),
+            Self::Io { uri, error } => write!(f, "failed to read {uri}: {error}"),
+            Self::InvalidUri { uri } => write!(f, "invalid data URI {uri}"),
+            Self::InvalidBuffer { index } => write!(f, "buffer {index} is missing data"),
+            Self::Image { index, error } => write!(f, "failed to decode image {index}: {error}"),
+            Self::UnsupportedPrimitive { mesh } => {
+                write!(f, "mesh {mesh} has a primitive without triangles")
+            }
+            Self::Mesh { mesh, error } => write!(f, "mesh {mesh} is invalid: {error}"),
+            Self::InvalidData => write!(f, "invalid processed glTF"),
+        }
+    }
+}
+
+impl std::error::Error for GltfError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Gltf(error) => Some(error),
+            Self::Io { error, .. } => Some(error),
+            Self::Image { error, .. } => Some(error),
+            Self::Mesh { error, .. } => Some(error),
+            Self::InvalidUri { .. }
+            | Self::InvalidBuffer { .. }
+            | Self::UnsupportedPrimitive { .. }
+            | Self::InvalidData => None,
+        }
+    }
+}
+
+impl From<gltf::Error> for GltfError {
+    fn from(error: gltf::Error) -> Self {
+        Self::Gltf(error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode_base64(bytes: &[u8]) -> String {
+        const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+        bytes
+            .chunks(3)
+            .flat_map(|chunk| {
+                let bits = chunk.iter().enumerate().fold(0u32, |bits, (i, byte)| {
+                    bits | u32::from(*byte) << (16 - i * 8)
+                });
+                (0..4).map(move |i| {
+                    if i <= chunk.len() {
+                        ALPHABET[(bits >> (18 - i * 6)) as usize & 63] as char
+                    } else {
+                        '='
+                    }
+                })
+            })
+            .collect()
+    }
+
+    /// Returns a file with a triangle under a translated root node, and an animation sliding the
+    /// triangle along X.
+    fn triangle() -> String {
+        let floats: [f32; 17] = [
+            0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0, // positions
+            0.0, 1.0, // times
+            0.0, 0.0, 0.0, 2.0, 0.0, 0.0, // translations
+        ];
+        let bytes = floats
+            .iter()
+            .flat_map(|float| float.to_le_bytes())
+            .collect::<Vec<_>>();
+        r#"{
+            "asset": { "version": "2.0" },
+            "scene": 0,
+            "scenes": [{ "nodes": [0] }],
+            "nodes": [
+                { "name": "root", "translation": [0, 1, 0], "children": [1] },
+                { "name": "triangle", "mesh": 0 }
+            ],
+            "meshes": [{ "primitives": [{ "attributes": { "POSITION": 0 }, "material": 0 }] }],
+            "materials": [{ "pbrMetallicRoughness": { "baseColorFactor": [1, 0, 0, 1], "metallicFactor": 0.5 } }],
+            "buffers": [{ "byteLength": 68, "uri": "data:application/octet-stream;base64,BUFFER" }],
+            "bufferViews": [
+                { "buffer": 0, "byteLength": 36 },
+                { "buffer": 0, "byteOffset": 36, "byteLength": 8 },
+                { "buffer": 0, "byteOffset": 44, "byteLength": 24 }
+            ],
+            "accessors": [
+                { "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3", "min": [0, 0, 0], "max": [1, 1, 0] },
+                { "bufferView": 1, "componentType": 5126, "count": 2, "type": "SCALAR", "min": [0], "max": [1] },
+                { "bufferView": 2, "componentType": 5126, "count": 2, "type": "VEC3" }
+            ],
+            "animations": [{
+                "name": "slide",
+                "samplers": [{ "input": 1, "output": 2 }],
+                "channels": [{ "sampler": 0, "target": { "node": 1, "path": "translation" } }]
+            }]
+        }"#
+        .replace("BUFFER", &encode_base64(&bytes))
+    }
+
+    fn no_files(uri: &str) -> io::Result<Vec<u8>> {
+        Err(io::Error::new(io::ErrorKind::NotFound, uri))
+    }
+
+    #[test]
+    fn decode_base64_round_trips() {
+        for len in 0..8 {
+            let bytes = (0..len).map(|i| i * 37).collect::<Vec<u8>>();
+            assert_eq!(decode_base64(&encode_base64(&bytes)), Some(bytes));
+        }
+        assert_eq!(decode_base64("no spaces"), None);
+    }
+
+    #[test]
+    fn decode_imports_meshes_materials_and_animations() {
+        let gltf = Gltf::decode(triangle().as_bytes(), no_files).unwrap();
+        assert_eq!(gltf.scenes, vec![vec![0]]);
+        assert_eq!(gltf.nodes[0].children, vec![1]);
+        assert_eq!(gltf.nodes[0].transform.position, Vec3::Y);
+        assert_eq!(gltf.nodes[1].mesh, Some(0));
+
+        let data = gltf.meshes[0][0].mesh.data();
+        assert_eq!(data.indices, vec![0, 1, 2]);
+        assert_eq!(data.normals, vec![Vec3::Z; 3]);
+        let material = gltf.materials[0].standard().unwrap();
+        assert_eq!(material.base_color, Color::rgba(1.0, 0.0, 0.0, 1.0));
+        assert_eq!(material.metallic, 0.5);
+
+        let channel = &gltf.animations[0].channels[0];
+        assert_eq!(channel.node, 1);
+        assert_eq!(channel.times, vec![0.0, 1.0]);
+        assert_eq!(
+            channel.keyframes,
+            Keyframes::Translation(vec![Vec3::ZERO, Vec3::X * 2.0])
+        );
+    }
+
+    #[test]
+    fn decode_fails_on_missing_files() {
+        let file = triangle().replace("data:application/octet-stream;base64,", "");
+        assert!(matches!(
+            Gltf::decode(file.as_bytes(), no_files),
+            Err(GltfError::Io { .. })
+        ));
+        assert!(matches!(
+            Gltf::decode(b"{}", no_files),
+            Err(GltfError::Gltf(_))
+        ));
+    }
+
+    #[test]
+    fn processed_gltf_matches_decoded() {
+        let gltf = Gltf::decode(triangle().as_bytes(), no_files).unwrap();
+        let processed = Gltf::from_processed(&gltf.to_processed().unwrap()).unwrap();
+        assert_eq!(processed.nodes, gltf.nodes);
+        assert_eq!(processed.animations, gltf.animations);
+        assert_eq!(processed.scenes, gltf.scenes);
+        assert_eq!(processed.default_scene, Some(0));
+        let primitive = &processed.meshes[0][0];
+        assert_eq!(primitive.mesh.data(), gltf.meshes[0][0].mesh.data());
+        assert_eq!(primitive.material, Some(0));
+        assert_eq!(
+            processed.materials[0].standard(),
+            gltf.materials[0].standard()
+        );
+
+        let bytes = gltf.to_processed().unwrap();
+        assert!(Gltf::from_processed(&bytes[..bytes.len() - 1]).is_none());
+    }
+
+    #[test]
+    fn spawn_instantiates_hierarchy_and_animations() {
+        let gltf = Gltf::decode(triangle().as_bytes(), no_files).unwrap();
+        let mut scene = Scene::new();
+        let first = gltf.spawn(&mut scene);
+        let second = gltf.spawn(&mut scene);
+        assert_ne!(first.nodes, second.nodes);
+
+        let [Some(root), Some(triangle)] = first.nodes[..] else {
+            panic!("nodes weren't spawned");
+        };
+        assert_eq!(scene.get_parent(root), Some(first.root));
+        assert_eq!(scene.get_parent(triangle), Some(root));
+        assert_eq!(scene.get::<Name>(triangle), Some(&Name::new("triangle")));
+        assert_eq!(scene.get::<LocalTransform>(root).unwrap().position, Vec3::Y);
+        assert!(scene.get::<Mesh>(triangle).is_some());
+        assert!(scene.get::<Material>(triangle).is_some());
+
+        let slide = &first.animations[0];
+        assert_eq!(slide.name(), "slide");
+        slide.apply(&mut scene, 0.5);
+        assert_eq!(
+            scene.get::<LocalTransform>(triangle).unwrap().position,
+            Vec3::X
+        );
+        assert_eq!(
+            scene
+                .get::<LocalTransform>(second.nodes[1].unwrap())
+                .unwrap()
+                .position,
+            Vec3::ZERO
+        );
+    }
+}
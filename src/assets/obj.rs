@@ -0,0 +1,653 @@
+//! # OBJ
+//!
+//! Import of Wavefront `.obj` files with their `.mtl` material libraries, for prototyping with
+//! meshes exported without a full glTF pipeline. An [Obj] asset holds a mesh for each object or
+//! group of the file and material it uses, and [Obj::spawn] instantiates them into a scene.
+//!
+//! ```no_run
+//! # use pulse::assets::obj::Obj;
+//! # use pulse::assets::AssetServer;
+//! # use pulse::assets::Assets;
+//! # use pulse::systems;
+//! # use pulse::Scene;
+//! let mut scene = Scene::new();
+//! scene.insert_resource(AssetServer::new("assets"));
+//!
+//! let server = scene.get_resource_mut::<AssetServer>().unwrap();
+//! let teapot = server.load::<Obj>("models/teapot.obj");
+//!
+//! systems::update_assets(&mut scene);
+//! if let Some(obj) = scene
+//!     .get_resource::<Assets<Obj>>()
+//!     .and_then(|objs| objs.get(&teapot))
+//!     .cloned()
+//! {
+//!     obj.spawn(&mut scene);
+//! }
+//! ```
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+
+use glam::Vec2;
+use glam::Vec3;
+
+use crate::assets::cache::ProcessedReader;
+use crate::assets::cache::ProcessedWriter;
+use crate::assets::AssetLoader;
+use crate::assets::LoadContext;
+use crate::render::image::ColorSpace;
+use crate::render::image::Image;
+use crate::render::image::ImageError;
+use crate::render::material;
+use crate::render::material::AlphaMode;
+use crate::render::material::Material;
+use crate::render::material::StandardMaterial;
+use crate::render::mesh::Mesh;
+use crate::render::mesh::MeshData;
+use crate::render::mesh::MeshError;
+use crate::render::Color;
+use crate::LocalTransform;
+use crate::Name;
+use crate::Node;
+use crate::Scene;
+use crate::Visibility;
+
+/// # OBJ
+///
+/// Contents of an OBJ file, split into a part for each object or group and material.
+#[derive(Clone, Debug, Default)]
+pub struct Obj {
+    /// Parts of the file in the order they appear.
+    pub parts: Vec<ObjPart>,
+}
+
+/// # OBJ Part
+///
+/// Faces of an OBJ object or group drawn with a single material.
+#[derive(Clone, Debug)]
+pub struct ObjPart {
+    /// Name of the part's object or group, if it has one.
+    pub name: Option<String>,
+    /// Triangles of the part.
+    pub mesh: Mesh,
+    /// Material of the part from the file's material libraries, or none for the default material.
+    pub material: Option<Material>,
+}
+
+impl Obj {
+    /// Decodes the OBJ file, reading its material libraries and their textures with `read`.
+    /// Polygons are split into triangle fans, vertices without normals get smooth normals computed
+    /// from their part's triangles, and vertices without texture coordinates get zeros.
+    pub fn decode(
+        bytes: &[u8],
+        read: impl Fn(&str) -> io::Result<Vec<u8>>,
+    ) -> Result<Self, ObjError> {
+        let text = String::from_utf8_lossy(bytes);
+        let mut positions = Vec::new();
+        let mut uvs = Vec::new();
+        let mut normals = Vec::new();
+        let mut libraries = MaterialLibraries::new(&read);
+        let mut builders = Vec::new();
+        let mut builder = PartBuilder::default();
+
+        for (index, line) in text.lines().enumerate() {
+            let invalid = || ObjError::Parse {
+                path: None,
+                line: index + 1,
+            };
+            let line = line.split('#').next().unwrap_or_default().trim();
+            let mut tokens = line.split_ascii_whitespace();
+            let Some(keyword) = tokens.next() else {
+                continue;
+            };
+            let rest = line[keyword.len()..].trim();
+
+            match keyword {
+                "v" => positions.push(parse_vec3(&mut tokens).ok_or_else(invalid)?),
+                "vt" => {
+                    let u = parse_float(tokens.next()).ok_or_else(invalid)?;
+                    let v = tokens.next().map_or(Some(0.0), |v| v.parse().ok());
+                    // OBJ texture coordinates start at the bottom of the texture.
+                    uvs.push(Vec2::new(u, 1.0 - v.ok_or_else(invalid)?));
+                }
+                "vn" => normals.push(parse_vec3(&mut tokens).ok_or_else(invalid)?),
+                "f" => {
+                    let corners = tokens
+                        .map(|corner| {
+                            parse_corner(corner, positions.len(), uvs.len(), normals.len())
+                        })
+                        .collect::<Option<Vec<_>>>()
+                        .filter(|corners| corners.len() >= 3)
+                        .ok_or_else(invalid)?;
+                    for i in 1..corners.len() - 1 {
+                        for corner in [corners[0], corners[i], corners[i + 1]] {
+                            builder.push(corner, &positions, &uvs, &normals);
+                        }
+                    }
+                }
+                "o" | "g" => {
+                    let name = (!rest.is_empty()).then(|| rest.to_string());
+                    let material = builder.material.clone();
+                    builders.push(std::mem::take(&mut builder));
+                    builder.name = name;
+                    builder.material = material;
+                }
+                "usemtl" => {
+                    let name = builder.name.clone();
+                    builders.push(std::mem::take(&mut builder));
+                    builder.name = name;
+                    builder.material = Some(rest.to_string());
+                }
+                "mtllib" => {
+                    for path in tokens {
+                        libraries.load(path)?;
+                    }
+                }
+                _ => {}
+            }
+        }
+        builders.push(builder);
+
+        let parts = builders
+            .into_iter()
+            .filter(|builder| !builder.data.indices.is_empty())
+            .map(|builder| {
+                let material = builder
+                    .material
+                    .and_then(|name| libraries.materials.get(&name).cloned());
+                let mut data = builder.data;
+                if builder.missing_normals {
+                    data.compute_normals();
+                }
+                let mesh = Mesh::new(data).map_err(|error| ObjError::Mesh {
+                    name: builder.name.clone(),
+                    error,
+                })?;
+                Ok(ObjPart {
+                    name: builder.name,
+                    mesh,
+                    material,
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { parts })
+    }
+
+    /// Spawns the parts as children of a new root node and returns the root.
+    pub fn spawn(&self, scene: &mut Scene) -> Node {
+        let root = scene.spawn_with((LocalTransform::IDENTITY, Visibility::Visible));
+        for part in &self.parts {
+            let material = part
+                .material
+                .clone()
+                .unwrap_or_else(|| Material::new(StandardMaterial::default()));
+            let node = scene.spawn_with((
+                LocalTransform::IDENTITY,
+                Visibility::Visible,
+                part.mesh.clone(),
+                material,
+            ));
+            scene.set_parent(node, root);
+            if let Some(name) = &part.name {
+                scene.add(node, Name::new(name));
+            }
+        }
+        root
+    }
+
+    /// Returns the parts packed for the asset cache, with the materials they share written once,
+    /// or `None` if a material is a [crate::render::shader_material::ShaderMaterial].
+    pub(crate) fn to_processed(&self) -> Option<Vec<u8>> {
+        let mut materials = Vec::new();
+        for material in self.parts.iter().filter_map(|part| part.material.as_ref()) {
+            if !materials.contains(material) {
+                materials.push(material.clone());
+            }
+        }
+
+        let mut writer = ProcessedWriter::new();
+        material::write_processed(&mut writer, &materials)?;
+        writer.write(self.parts.len() as u64);
+        for part in &self.parts {
+            writer.write_str(part.name.as_deref());
+            writer.write_slice(&part.mesh.data().to_processed());
+            writer.write_index(
+                part.material
+                    .as_ref()
+                    .and_then(|material| materials.iter().position(|other| other == material)),
+            );
+        }
+        Some(writer.into_bytes())
+    }
+
+    /// Returns the parts packed by [Obj::to_processed], or `None` if the bytes are invalid.
+    pub(crate) fn from_processed(bytes: &[u8]) -> Option<Self> {
+        let mut reader = ProcessedReader::new(bytes);
+        let materials = material::read_processed(&mut reader)?;
+        let parts = (0..reader.read::<u64>()?)
+            .map(|_| {
+                let name = reader.read_string()?;
+                let data = MeshData::from_processed(&reader.read_slice::<u8>()?)?;
+                let material = match reader.read_index()? {
+                    Some(index) => Some(materials.get(index)?.clone()),
+                    None => None,
+                };
+                Some(ObjPart {
+                    name,
+                    mesh: Mesh::new(data).ok()?,
+                    material,
+                })
+            })
+            .collect::<Option<_>>()?;
+        Some(Self { parts })
+    }
+}
+
+/// Vertices and triangles of the part being decoded, with its vertices deduplicated by their
+/// position, texture coordinate, and normal indices.
+#[derive(Default)]
+struct PartBuilder {
+    name: Option<String>,
+    material: Option<String>,
+    data: MeshData,
+    vertices: HashMap<Corner, u32>,
+    missing_normals: bool,
+}
+
+/// Position, texture coordinate, and normal indices of a face corner.
+type Corner = (usize, Option<usize>, Option<usize>);
+
+impl PartBuilder {
+    fn push(&mut self, corner: Corner, positions: &[Vec3], uvs: &[Vec2], normals: &[Vec3]) {
+        let data = &mut self.data;
+        let index = *self.vertices.entry(corner).or_insert_with(|| {
+            let (position, uv, normal) = corner;
+            data.positions.push(positions[position]);
+            data.uvs.push(uv.map_or(Vec2::ZERO, |uv| uvs[uv]));
+            data.normals
+                .push(normal.map_or(Vec3::Y, |normal| normals[normal]));
+            data.positions.len() as u32 - 1
+        });
+        self.missing_normals |= corner.2.is_none();
+        self.data.indices.push(index);
+    }
+}
+
+/// Materials of the MTL files loaded so far, with their textures shared between materials.
+struct MaterialLibraries<'a, F> {
+    read: &'a F,
+    materials: HashMap<String, Material>,
+    images: HashMap<(String, ColorSpace), Image>,
+}
+
+impl<'a, F: Fn(&str) -> io::Result<Vec<u8>>> MaterialLibraries<'a, F> {
+    fn new(read: &'a F) -> Self {
+        Self {
+            read,
+            materials: HashMap::new(),
+            images: HashMap::new(),
+        }
+    }
+
+    /// Reads the MTL file and adds its materials. Materials without a PBR roughness get one
+    /// approximated from their specular exponent.
+    fn load(&mut self, path: &str) -> Result<(), ObjError> {
+        let bytes = self.read(path)?;
+        let text = String::from_utf8_lossy(&bytes);
+        let mut current: Option<(String, StandardMaterial, Option<f32>)> = None;
+        let mut materials = Vec::new();
+
+        for (index, line) in text.lines().enumerate() {
+            let invalid = || ObjError::Parse {
+                path: Some(path.to_string()),
+                line: index + 1,
+            };
+            let line = line.split('#').next().unwrap_or_default().trim();
+            let mut tokens = line.split_ascii_whitespace();
+            let Some(keyword) = tokens.next() else {
+                continue;
+            };
+            if keyword == "newmtl" {
+                let name = line[keyword.len()..].trim().to_string();
+                let material = StandardMaterial {
+                    roughness: 1.0,
+                    ..Default::default()
+                };
+                materials.extend(current.replace((name, material, None)));
+                continue;
+            }
+            let Some((_, material, roughness)) = &mut current else {
+                continue;
+            };
+
+            match keyword {
+                "Kd" => {
+                    let color = parse_vec3(&mut tokens).ok_or_else(invalid)?;
+                    let alpha = material.base_color.a;
+                    material.base_color = Color::rgba(color.x, color.y, color.z, alpha);
+                }
+                "d" => material.base_color.a = parse_float(tokens.last()).ok_or_else(invalid)?,
+                "Tr" => {
+                    material.base_color.a = 1.0 - parse_float(tokens.last()).ok_or_else(invalid)?
+                }
+                "Ke" => {
+                    let color = parse_vec3(&mut tokens).ok_or_else(invalid)?;
+                    material.emissive = Color::rgb(color.x, color.y, color.z);
+                }
+                "Ns" if roughness.is_none() => {
+                    let exponent = parse_float(tokens.next()).ok_or_else(invalid)?;
+                    material.roughness = (2.0 / (exponent.max(0.0) + 2.0)).sqrt();
+                }
+                "Pr" => {
+                    let value = parse_float(tokens.next()).ok_or_else(invalid)?;
+                    material.roughness = value;
+                    *roughness = Some(value);
+                }
+                "Pm" => material.metallic = parse_float(tokens.next()).ok_or_else(invalid)?,
+                "map_Kd" => {
+                    let file = tokens.last().ok_or_else(invalid)?;
+                    material.base_color_texture = Some(self.image(file, ColorSpace::Srgb)?);
+                }
+                "map_Ke" => {
+                    let file = tokens.last().ok_or_else(invalid)?;
+                    material.emissive_texture = Some(self.image(file, ColorSpace::Srgb)?);
+                    if material.emissive == Color::BLACK {
+                        material.emissive = Color::WHITE;
+                    }
+                }
+                "map_Bump" | "map_bump" | "bump" | "norm" => {
+                    let tokens = tokens.collect::<Vec<_>>();
+                    let file = tokens.last().ok_or_else(invalid)?;
+                    if let Some(scale) = tokens.iter().position(|token| *token == "-bm") {
+                        material.normal_scale =
+                            parse_float(tokens.get(scale + 1).copied()).ok_or_else(invalid)?;
+                    }
+                    material.normal_texture = Some(self.image(file, ColorSpace::Linear)?);
+                }
+                _ => {}
+            }
+        }
+        materials.extend(current);
+
+        for (name, mut material, _) in materials {
+            if material.base_color.a < 1.0 {
+                material.alpha_mode = AlphaMode::Blend;
+            }
+            self.materials.insert(name, Material::new(material));
+        }
+        Ok(())
+    }
+
+    fn read(&self, path: &str) -> Result<Vec<u8>, ObjError> {
+        (self.read)(path).map_err(|error| ObjError::Io {
+            path: path.to_string(),
+            error,
+        })
+    }
+
+    /// Returns the mipmapped texture at the path, decoding it on first use.
+    fn image(&mut self, path: &str, color_space: ColorSpace) -> Result<Image, ObjError> {
+        let key = (path.to_string(), color_space);
+        if let Some(image) = self.images.get(&key) {
+            return Ok(image.clone());
+        }
+
+        let bytes = self.read(path)?;
+        let image = Image::decode(&bytes, color_space)
+            .map_err(|error| ObjError::Image {
+                path: path.to_string(),
+                error,
+            })?
+            .with_mipmaps();
+        self.images.insert(key, image.clone());
+        Ok(image)
+    }
+}
+
+fn parse_float(token: Option<&str>) -> Option<f32> {
+    token?.parse().ok()
+}
+
+fn parse_vec3<'a>(tokens: &mut impl Iterator<Item = &'a str>) -> Option<Vec3> {
+    Some(Vec3::new(
+        parse_float(tokens.next())?,
+        parse_float(tokens.next())?,
+        parse_float(tokens.next())?,
+    ))
+}
+
+/// Parses a face corner `v`, `v/vt`, `v//vn`, or `v/vt/vn` into zero-based indices. Indices are
+/// one-based, or relative to the end of their list if negative.
+fn parse_corner(corner: &str, positions: usize, uvs: usize, normals: usize) -> Option<Corner> {
+    let index = |token: &str, len: usize| {
+        let index = token.parse::<isize>().ok()?;
+        let index = match index {
+            0 => return None,
+            1.. => index - 1,
+            _ => len as isize + index,
+        };
+        usize::try_from(index).ok().filter(|index| *index < len)
+    };
+    let mut tokens = corner.split('/');
+    let position = index(tokens.next()?, positions)?;
+    let uv = match tokens.next() {
+        None | Some("") => None,
+        Some(token) => Some(index(token, uvs)?),
+    };
+    let normal = match tokens.next() {
+        None | Some("") => None,
+        Some(token) => Some(index(token, normals)?),
+    };
+    Some((position, uv, normal))
+}
+
+/// # OBJ Loader
+///
+/// [AssetLoader] of [Obj]s from `.obj` files, with their material libraries and textures next to
+/// them.
+pub struct ObjLoader;
+
+impl AssetLoader for ObjLoader {
+    type Asset = Obj;
+
+    fn extensions(&self) -> &[&str] {
+        &["obj"]
+    }
+
+    fn load(&self, context: &LoadContext) -> Result<Obj, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(Obj::decode(context.bytes(), |path| context.read(path))?)
+    }
+
+    fn process(&self, obj: &Obj) -> Option<Vec<u8>> {
+        obj.to_processed()
+    }
+
+    fn load_processed(
+        &self,
+        bytes: &[u8],
+    ) -> Result<Obj, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(Obj::from_processed(bytes).ok_or(ObjError::InvalidData)?)
+    }
+}
+
+/// # OBJ Error
+///
+/// Error returned when an [Obj] can't be decoded.
+#[derive(Debug)]
+pub enum ObjError {
+    /// A line has missing or invalid values, or a face refers to a missing vertex.
+    Parse {
+        /// Path of the material library, or none for the OBJ file.
+        path: Option<String>,
+        /// One-based number of the line.
+        line: usize,
+    },
+    /// A material library or texture couldn't be read.
+    Io {
+        /// Path of the file.
+        path: String,
+        /// Error reading the file.
+        error: io::Error,
+    },
+    /// A texture couldn't be decoded.
+    Image {
+        /// Path of the texture.
+        path: String,
+        /// Error decoding the texture.
+        error: ImageError,
+    },
+    /// A part's vertices or triangles are invalid.
+    Mesh {
+        /// Name of the part, if it has one.
+        name: Option<String>,
+        /// Error of the part's data.
+        error: MeshError,
+    },
+    /// The processed parts in the asset cache are invalid.
+    InvalidData,
+}
+
+impl fmt::Display for ObjError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Parse { path: None, line } => write!(f, "invalid OBJ line {line}"),
+            Self::Parse {
+                path: Some(path),
+                line,
+            } => write!(f, "invalid line {line} of {path}"),
+            Self::Io { path, error } => write!(f, "failed to read {path}: {error}"),
+            Self::Image { path, error } => write!(f, "failed to decode {path}: {error}"),
+            Self::Mesh { name, error } => match name {
+                Some(name) => write!(f, "part {name} is invalid: {error}"),
+                None => write!(f, "unnamed part is invalid: {error}"),
+            },
+            Self::InvalidData => write!(f, "invalid processed OBJ"),
+        }
+    }
+}
+
+impl std::error::Error for ObjError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Parse { .. } | Self::InvalidData => None,
+            Self::Io { error, .. } => Some(error),
+            Self::Image { error, .. } => Some(error),
+            Self::Mesh { error, .. } => Some(error),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::UVec2;
+
+    use super::*;
+
+    const QUAD: &str = "
+        mtllib quad.mtl
+        v 0 0 0
+        v 1 0 0
+        v 1 1 0
+        v 0 1 0
+        vt 0 0
+        vt 1 1
+        vn 0 0 1
+        o quad
+        usemtl red
+        f 1/1/1 2/1/1 3/2/1 4/2/1
+        o triangle # without normals
+        usemtl missing
+        f -4 -3 -2
+    ";
+
+    const MTL: &str = "
+        newmtl red
+        Kd 1 0 0
+        d 0.5
+        Ns 98
+        map_Kd -s 1 1 1 red.png
+    ";
+
+    fn read(path: &str) -> io::Result<Vec<u8>> {
+        match path {
+            "quad.mtl" => Ok(MTL.as_bytes().to_vec()),
+            "red.png" => Ok(Image::from_pixel([255, 0, 0, 255], ColorSpace::Srgb).to_png()),
+            _ => Err(io::ErrorKind::NotFound.into()),
+        }
+    }
+
+    #[test]
+    fn decode_splits_parts_and_triangulates_faces() {
+        let obj = Obj::decode(QUAD.as_bytes(), read).unwrap();
+        assert_eq!(obj.parts.len(), 2);
+
+        let quad = &obj.parts[0];
+        assert_eq!(quad.name.as_deref(), Some("quad"));
+        let data = quad.mesh.data();
+        assert_eq!(data.positions.len(), 4);
+        assert_eq!(data.indices, [0, 1, 2, 0, 2, 3]);
+        assert_eq!(data.uvs[2], Vec2::new(1.0, 0.0));
+        assert_eq!(data.normals, [Vec3::Z; 4]);
+
+        let triangle = &obj.parts[1];
+        assert_eq!(triangle.name.as_deref(), Some("triangle"));
+        assert!(triangle.material.is_none());
+        let data = triangle.mesh.data();
+        assert_eq!(data.uvs, [Vec2::ZERO; 3]);
+        assert!(data
+            .normals
+            .iter()
+            .all(|normal| normal.abs_diff_eq(Vec3::Z, 1e-6)));
+    }
+
+    #[test]
+    fn decode_reads_materials() {
+        let obj = Obj::decode(QUAD.as_bytes(), read).unwrap();
+        let material = obj.parts[0].material.as_ref().unwrap().standard().unwrap();
+        assert_eq!(material.base_color, Color::rgba(1.0, 0.0, 0.0, 0.5));
+        assert_eq!(material.alpha_mode, AlphaMode::Blend);
+        assert!((material.roughness - 0.02f32.sqrt()).abs() < 1e-6);
+        let texture = material.base_color_texture.as_ref().unwrap();
+        assert_eq!(texture.size(), UVec2::ONE);
+
+        let mut scene = Scene::new();
+        let root = obj.spawn(&mut scene);
+        assert_eq!(scene.get_children(root).map(<[_]>::len), Some(2));
+    }
+
+    #[test]
+    fn processed_obj_matches_decoded() {
+        let mut obj = Obj::decode(QUAD.as_bytes(), read).unwrap();
+        obj.parts[1].material = obj.parts[0].material.clone();
+        let processed = Obj::from_processed(&obj.to_processed().unwrap()).unwrap();
+        assert_eq!(processed.parts.len(), 2);
+        for (part, decoded) in processed.parts.iter().zip(&obj.parts) {
+            assert_eq!(part.name, decoded.name);
+            assert_eq!(part.mesh.data(), decoded.mesh.data());
+        }
+
+        let material = processed.parts[0].material.as_ref().unwrap();
+        assert_eq!(processed.parts[1].material.as_ref(), Some(material));
+        let standard = material.standard().unwrap();
+        assert_eq!(standard.base_color, Color::rgba(1.0, 0.0, 0.0, 0.5));
+        let texture = standard.base_color_texture.as_ref().unwrap();
+        assert_eq!(texture.pixel(0, 0), [255, 0, 0, 255]);
+        assert!(Obj::from_processed(b"part").is_none());
+    }
+
+    #[test]
+    fn decode_rejects_invalid_faces() {
+        let error = Obj::decode(b"v 0 0 0\nf 1 2 3", read).unwrap_err();
+        assert!(matches!(
+            error,
+            ObjError::Parse {
+                path: None,
+                line: 2
+            }
+        ));
+        let error = Obj::decode(b"mtllib missing.mtl", read).unwrap_err();
+        assert!(matches!(error, ObjError::Io { .. }));
+    }
+}
@@ -0,0 +1,371 @@
+//! # Asset Packs
+//!
+//! Archives of asset files, so shipped games load their assets from a single file instead of
+//! loose files. [build] packs the files of a directory, and [AssetServer::mount] mounts an
+//! [AssetPack] as a read-only source of the server's files, taking precedence over the files in
+//! its root directory.
+//!
+//! ```no_run
+//! # use pulse::assets::pack;
+//! # use pulse::assets::pack::AssetPack;
+//! # use pulse::assets::AssetServer;
+//! pack::build("assets", "assets.pack").unwrap();
+//!
+//! let mut server = AssetServer::new("assets");
+//! server.mount(AssetPack::open("assets.pack").unwrap());
+//! ```
+//!
+//! A pack starts with the magic `PULSEPAK`, its version, and the number of files, followed by the
+//! path, offset, and length of each file and then the content of the files. Numbers are
+//! little-endian, and paths are UTF-8 with `/` separators.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::BufReader;
+use std::io::BufWriter;
+use std::io::Read;
+use std::io::Seek;
+use std::io::SeekFrom;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+#[cfg(doc)]
+use crate::assets::AssetServer;
+
+/// Start of every pack.
+const MAGIC: &[u8; 8] = b"PULSEPAK";
+
+/// Version of the pack format, changed whenever the layout of packs changes.
+const VERSION: u32 = 1;
+
+/// # Asset Pack
+///
+/// Archive of asset files built with [build], mounted with [AssetServer::mount]. Only the index of
+/// a pack opened from a file is read upfront, and its files are read as they're loaded.
+pub struct AssetPack {
+    source: PackSource,
+    /// Offset and length of each file by its path.
+    files: HashMap<PathBuf, (u64, u64)>,
+}
+
+enum PackSource {
+    File(Mutex<File>),
+    Bytes(Vec<u8>),
+}
+
+impl AssetPack {
+    /// Opens the pack file and reads its index.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file can't be read or isn't a valid pack.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, PackError> {
+        let mut file = File::open(path).map_err(PackError::Io)?;
+        let len = file.metadata().map_err(PackError::Io)?.len();
+        let files = read_index(&mut BufReader::new(&mut file), len)?;
+        Ok(Self {
+            source: PackSource::File(Mutex::new(file)),
+            files,
+        })
+    }
+
+    /// Returns the pack with the content of a pack file, e.g. embedded with `include_bytes!` on
+    /// the web.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the bytes aren't a valid pack.
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Self, PackError> {
+        let files = read_index(&mut bytes.as_slice(), bytes.len() as u64)?;
+        Ok(Self {
+            source: PackSource::Bytes(bytes),
+            files,
+        })
+    }
+
+    /// Returns the number of files in the pack.
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    /// Returns true if the pack has no files.
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// Returns true if the pack has the file at the path relative to the packed directory.
+    pub fn contains(&self, path: impl AsRef<Path>) -> bool {
+        self.files.contains_key(path.as_ref())
+    }
+
+    /// Returns the paths of the files relative to the packed directory, in no particular order.
+    pub fn paths(&self) -> impl Iterator<Item = &Path> {
+        self.files.keys().map(PathBuf::as_path)
+    }
+
+    /// Reads the file at the path relative to the packed directory.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error of kind [io::ErrorKind::NotFound] if the pack doesn't have the file, or
+    /// the error reading the pack file.
+    pub fn read(&self, path: impl AsRef<Path>) -> io::Result<Vec<u8>> {
+        let &(offset, len) = self
+            .files
+            .get(path.as_ref())
+            .ok_or(io::ErrorKind::NotFound)?;
+        let len = usize::try_from(len).map_err(io::Error::other)?;
+        match &self.source {
+            PackSource::File(file) => {
+                let mut file = file.lock().unwrap();
+                file.seek(SeekFrom::Start(offset))?;
+                let mut bytes = vec![0; len];
+                file.read_exact(&mut bytes)?;
+                Ok(bytes)
+            }
+            PackSource::Bytes(bytes) => {
+                let offset = offset as usize;
+                Ok(bytes[offset..offset + len].to_vec())
+            }
+        }
+    }
+}
+
+impl fmt::Debug for AssetPack {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AssetPack")
+            .field("files", &self.files.len())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Returns the offset and length of each file by its path from the index at the start of a pack
+/// of the length.
+fn read_index(reader: &mut impl Read, len: u64) -> Result<HashMap<PathBuf, (u64, u64)>, PackError> {
+    let magic = read_array(reader).map_err(|_| PackError::InvalidHeader)?;
+    if &magic != MAGIC {
+        return Err(PackError::InvalidHeader);
+    }
+    let version = u32::from_le_bytes(read_array(reader)?);
+    if version != VERSION {
+        return Err(PackError::UnsupportedVersion(version));
+    }
+
+    let count = u32::from_le_bytes(read_array(reader)?);
+    let mut files = HashMap::new();
+    for _ in 0..count {
+        let path_len = u32::from_le_bytes(read_array(reader)?);
+        if u64::from(path_len) > len {
+            return Err(PackError::InvalidIndex);
+        }
+        let mut path = vec![0; path_len as usize];
+        reader.read_exact(&mut path).map_err(index_error)?;
+        let path = String::from_utf8(path).map_err(|_| PackError::InvalidIndex)?;
+        let offset = u64::from_le_bytes(read_array(reader)?);
+        let file_len = u64::from_le_bytes(read_array(reader)?);
+        if offset.checked_add(file_len).is_none_or(|end| end > len) {
+            return Err(PackError::InvalidIndex);
+        }
+        files.insert(path.split('/').collect(), (offset, file_len));
+    }
+    Ok(files)
+}
+
+fn read_array<const N: usize>(reader: &mut impl Read) -> Result<[u8; N], PackError> {
+    let mut bytes = [0; N];
+    reader.read_exact(&mut bytes).map_err(index_error)?;
+    Ok(bytes)
+}
+
+/// Returns the error of a failed read of a pack's index, which is invalid if it ended early.
+fn index_error(error: io::Error) -> PackError {
+    match error.kind() {
+        io::ErrorKind::UnexpectedEof => PackError::InvalidIndex,
+        _ => PackError::Io(error),
+    }
+}
+
+/// Packs the files in the directory and its subdirectories into a pack file at the path, which
+/// shouldn't be in the directory, and returns the number of packed files. Files and directories
+/// whose names start with a dot are skipped, e.g. the cache directory of an [AssetServer].
+///
+/// # Errors
+///
+/// Returns an error if a file can't be read, its path isn't valid UTF-8, or the pack can't be
+/// written.
+pub fn build(directory: impl AsRef<Path>, pack: impl AsRef<Path>) -> io::Result<usize> {
+    let directory = directory.as_ref();
+    let mut files = Vec::new();
+    collect_files(directory, Path::new(""), &mut files)?;
+    files.sort();
+
+    let mut index = Vec::with_capacity(files.len());
+    for path in &files {
+        let name = path
+            .components()
+            .map(|component| component.as_os_str().to_str())
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{} isn't valid UTF-8", path.display()),
+                )
+            })?
+            .join("/");
+        let len = fs::metadata(directory.join(path))?.len();
+        index.push((name, len));
+    }
+
+    let index_len = index
+        .iter()
+        .map(|(name, _)| 4 + name.len() as u64 + 16)
+        .sum::<u64>();
+    let mut offset = MAGIC.len() as u64 + 8 + index_len;
+    let mut writer = BufWriter::new(File::create(pack)?);
+    writer.write_all(MAGIC)?;
+    writer.write_all(&VERSION.to_le_bytes())?;
+    writer.write_all(&(index.len() as u32).to_le_bytes())?;
+    for (name, len) in &index {
+        writer.write_all(&(name.len() as u32).to_le_bytes())?;
+        writer.write_all(name.as_bytes())?;
+        writer.write_all(&offset.to_le_bytes())?;
+        writer.write_all(&len.to_le_bytes())?;
+        offset += len;
+    }
+    for (path, (_, len)) in files.iter().zip(&index) {
+        let file = File::open(directory.join(path))?;
+        if io::copy(&mut file.take(*len), &mut writer)? != *len {
+            return Err(io::Error::other(format!(
+                "{} changed while it was packed",
+                path.display()
+            )));
+        }
+    }
+    writer.flush()?;
+    Ok(files.len())
+}
+
+/// Adds the paths relative to the directory of the files in its subdirectory at the relative path,
+/// skipping hidden files and directories.
+fn collect_files(directory: &Path, relative: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(directory.join(relative))? {
+        let entry = entry?;
+        let name = entry.file_name();
+        if name.to_string_lossy().starts_with('.') {
+            continue;
+        }
+        let path = relative.join(name);
+        if entry.path().is_dir() {
+            collect_files(directory, &path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// # Pack Error
+///
+/// Error returned when an [AssetPack] can't be opened.
+#[derive(Debug)]
+pub enum PackError {
+    /// The pack file couldn't be read.
+    Io(io::Error),
+    /// The file doesn't start with the pack magic.
+    InvalidHeader,
+    /// The pack was built with a different version of the format.
+    UnsupportedVersion(u32),
+    /// The index ends early, or a path isn't valid UTF-8 or a file is outside of the pack.
+    InvalidIndex,
+}
+
+impl fmt::Display for PackError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "failed to read asset pack: {error}"),
+            Self::InvalidHeader => write!(f, "invalid asset pack header"),
+            Self::UnsupportedVersion(version) => {
+                write!(f, "unsupported asset pack version {version}")
+            }
+            Self::InvalidIndex => write!(f, "invalid or truncated asset pack index"),
+        }
+    }
+}
+
+impl std::error::Error for PackError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(error) => Some(error),
+            Self::InvalidHeader | Self::UnsupportedVersion(_) | Self::InvalidIndex => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn build_packs_visible_files() {
+        let directory = std::env::temp_dir().join(format!("pulse-pack-{}", std::process::id()));
+        fs::create_dir_all(directory.join("assets/models")).unwrap();
+        fs::create_dir_all(directory.join("assets/.pulse_cache")).unwrap();
+        fs::write(directory.join("assets/greeting.txt"), "hello").unwrap();
+        fs::write(directory.join("assets/models/cube.obj"), "v 0 0 0").unwrap();
+        fs::write(directory.join("assets/.pulse_cache/entry.bin"), "cached").unwrap();
+
+        let file = directory.join("assets.pack");
+        assert_eq!(build(directory.join("assets"), &file).unwrap(), 2);
+        let packs = [
+            AssetPack::open(&file).unwrap(),
+            AssetPack::from_bytes(fs::read(&file).unwrap()).unwrap(),
+        ];
+        for pack in packs {
+            assert_eq!(pack.len(), 2);
+            assert_eq!(pack.read("greeting.txt").unwrap(), b"hello");
+            assert_eq!(pack.read("models/cube.obj").unwrap(), b"v 0 0 0");
+            assert!(!pack.contains(".pulse_cache/entry.bin"));
+            assert_eq!(
+                pack.read("missing.txt").unwrap_err().kind(),
+                io::ErrorKind::NotFound
+            );
+        }
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn open_rejects_invalid_packs() {
+        assert!(matches!(
+            AssetPack::from_bytes(b"PK\x03\x04".to_vec()),
+            Err(PackError::InvalidHeader)
+        ));
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend(2u32.to_le_bytes());
+        assert!(matches!(
+            AssetPack::from_bytes(bytes.clone()),
+            Err(PackError::UnsupportedVersion(2))
+        ));
+
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend(VERSION.to_le_bytes());
+        bytes.extend(1u32.to_le_bytes());
+        bytes.extend(1u32.to_le_bytes());
+        bytes.push(b'a');
+        bytes.extend(0u64.to_le_bytes());
+        bytes.extend(100u64.to_le_bytes());
+        assert!(matches!(
+            AssetPack::from_bytes(bytes.clone()),
+            Err(PackError::InvalidIndex)
+        ));
+        assert!(matches!(
+            AssetPack::from_bytes(bytes[..bytes.len() - 4].to_vec()),
+            Err(PackError::InvalidIndex)
+        ));
+    }
+}
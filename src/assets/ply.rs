@@ -0,0 +1,465 @@
+//! # PLY
+//!
+//! Import of meshes from Stanford `.ply` files in the ASCII or binary formats, e.g. scans and
+//! meshes exported for prototyping. [decode] reads the positions, normals, texture coordinates,
+//! and faces of a file into a [Mesh], and the [PlyLoader] loads them as [Mesh] assets.
+//!
+//! ```no_run
+//! # use pulse::assets::AssetServer;
+//! # use pulse::render::mesh::Mesh;
+//! # use pulse::Scene;
+//! let mut scene = Scene::new();
+//! scene.insert_resource(AssetServer::new("assets"));
+//!
+//! let server = scene.get_resource_mut::<AssetServer>().unwrap();
+//! let bunny = server.load::<Mesh>("models/bunny.ply");
+//! ```
+
+use std::fmt;
+use std::str::SplitAsciiWhitespace;
+
+use glam::Vec2;
+use glam::Vec3;
+
+use crate::assets::AssetLoader;
+use crate::assets::LoadContext;
+use crate::render::mesh::Mesh;
+use crate::render::mesh::MeshData;
+use crate::render::mesh::MeshError;
+
+/// Names of the texture coordinate properties of vertices used by different exporters.
+const UV_PROPERTIES: [[&str; 2]; 4] = [
+    ["u", "v"],
+    ["s", "t"],
+    ["texture_u", "texture_v"],
+    ["texture_s", "texture_t"],
+];
+
+/// Decodes the `vertex` and `face` elements of the PLY file into a mesh, ignoring any other
+/// elements and properties. Polygons are split into triangle fans, meshes without normals get
+/// smooth normals computed from their triangles, and meshes without texture coordinates get zeros.
+pub fn decode(bytes: &[u8]) -> Result<Mesh, PlyError> {
+    let (format, elements, body) = decode_header(bytes)?;
+    let mut reader = Reader {
+        format,
+        bytes: body,
+        offset: 0,
+        tokens: match format {
+            Format::Ascii => std::str::from_utf8(body)
+                .map_err(|_| PlyError::InvalidData)?
+                .split_ascii_whitespace(),
+            Format::BinaryLittleEndian | Format::BinaryBigEndian => "".split_ascii_whitespace(),
+        },
+    };
+
+    let mut data = MeshData::default();
+    let mut has_normals = false;
+    for element in &elements {
+        let property = |name: &str| {
+            element
+                .properties
+                .iter()
+                .position(|property| property.name == name && property.count.is_none())
+        };
+        let positions = ["x", "y", "z"].map(property);
+        let normals = ["nx", "ny", "nz"].map(property);
+        let uvs = UV_PROPERTIES
+            .iter()
+            .find_map(|names| Some([property(names[0])?, property(names[1])?]));
+        let indices = element.properties.iter().position(|property| {
+            matches!(property.name.as_str(), "vertex_indices" | "vertex_index")
+                && property.count.is_some()
+        });
+
+        let mut values = vec![0.0; element.properties.len()];
+        let mut list = Vec::new();
+        for _ in 0..element.count {
+            for (index, property) in element.properties.iter().enumerate() {
+                match property.count {
+                    None => values[index] = reader.read(property.value)?,
+                    Some(count) => {
+                        let count = reader.read(count)? as usize;
+                        let keep = Some(index) == indices;
+                        if keep {
+                            list.clear();
+                        }
+                        for _ in 0..count {
+                            let value = reader.read(property.value)?;
+                            if keep {
+                                list.push(value);
+                            }
+                        }
+                    }
+                }
+            }
+
+            match element.name.as_str() {
+                "vertex" => {
+                    let [Some(x), Some(y), Some(z)] = positions else {
+                        return Err(PlyError::InvalidHeader);
+                    };
+                    let vec3 = |[x, y, z]: [usize; 3]| {
+                        Vec3::new(values[x] as f32, values[y] as f32, values[z] as f32)
+                    };
+                    data.positions.push(vec3([x, y, z]));
+                    if let [Some(x), Some(y), Some(z)] = normals {
+                        data.normals.push(vec3([x, y, z]));
+                        has_normals = true;
+                    }
+                    // PLY texture coordinates start at the bottom of the texture.
+                    data.uvs.push(uvs.map_or(Vec2::ZERO, |[u, v]| {
+                        Vec2::new(values[u] as f32, 1.0 - values[v] as f32)
+                    }));
+                }
+                "face" if indices.is_some() && list.len() >= 3 => {
+                    for i in 1..list.len() - 1 {
+                        data.indices
+                            .extend([list[0], list[i], list[i + 1]].map(|index| index as u32));
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let vertex_count = data.positions.len();
+    if let Some(index) = data
+        .indices
+        .iter()
+        .find(|index| **index as usize >= vertex_count)
+    {
+        return Err(PlyError::Mesh(MeshError::IndexOutOfBounds {
+            index: *index,
+            vertex_count,
+        }));
+    }
+    if !has_normals {
+        data.compute_normals();
+    }
+    Mesh::new(data).map_err(PlyError::Mesh)
+}
+
+/// Encoding of the elements after the header.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum Format {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+/// Type of a property value or list count.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum Scalar {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl Scalar {
+    fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "char" | "int8" => Self::I8,
+            "uchar" | "uint8" => Self::U8,
+            "short" | "int16" => Self::I16,
+            "ushort" | "uint16" => Self::U16,
+            "int" | "int32" => Self::I32,
+            "uint" | "uint32" => Self::U32,
+            "float" | "float32" => Self::F32,
+            "double" | "float64" => Self::F64,
+            _ => return None,
+        })
+    }
+
+    fn size(self) -> usize {
+        match self {
+            Self::I8 | Self::U8 => 1,
+            Self::I16 | Self::U16 => 2,
+            Self::I32 | Self::U32 | Self::F32 => 4,
+            Self::F64 => 8,
+        }
+    }
+}
+
+/// Property of an element, with a count type if the property is a list.
+struct Property {
+    name: String,
+    count: Option<Scalar>,
+    value: Scalar,
+}
+
+/// Element declared in the header, e.g. the vertices.
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+/// Returns the format and elements declared by the header, and the data after the header.
+fn decode_header(bytes: &[u8]) -> Result<(Format, Vec<Element>, &[u8]), PlyError> {
+    let end = bytes
+        .windows(b"end_header".len())
+        .position(|window| window == b"end_header")
+        .ok_or(PlyError::InvalidHeader)?;
+    let body = bytes[end..]
+        .iter()
+        .position(|byte| *byte == b'\n')
+        .map_or(bytes.len(), |newline| end + newline + 1);
+    let header = std::str::from_utf8(&bytes[..end]).map_err(|_| PlyError::InvalidHeader)?;
+
+    let mut lines = header.lines();
+    if lines.next().map(str::trim) != Some("ply") {
+        return Err(PlyError::InvalidHeader);
+    }
+
+    let mut format = None;
+    let mut elements: Vec<Element> = Vec::new();
+    for line in lines {
+        let tokens = line.split_ascii_whitespace().collect::<Vec<_>>();
+        match tokens.as_slice() {
+            ["format", name, "1.0"] => {
+                format = Some(match *name {
+                    "ascii" => Format::Ascii,
+                    "binary_little_endian" => Format::BinaryLittleEndian,
+                    "binary_big_endian" => Format::BinaryBigEndian,
+                    _ => return Err(PlyError::UnsupportedFormat),
+                });
+            }
+            ["element", name, count] => elements.push(Element {
+                name: name.to_string(),
+                count: count.parse().map_err(|_| PlyError::InvalidHeader)?,
+                properties: Vec::new(),
+            }),
+            ["property", "list", count, value, name] => {
+                let element = elements.last_mut().ok_or(PlyError::InvalidHeader)?;
+                element.properties.push(Property {
+                    name: name.to_string(),
+                    count: Some(Scalar::parse(count).ok_or(PlyError::UnsupportedFormat)?),
+                    value: Scalar::parse(value).ok_or(PlyError::UnsupportedFormat)?,
+                });
+            }
+            ["property", value, name] => {
+                let element = elements.last_mut().ok_or(PlyError::InvalidHeader)?;
+                element.properties.push(Property {
+                    name: name.to_string(),
+                    count: None,
+                    value: Scalar::parse(value).ok_or(PlyError::UnsupportedFormat)?,
+                });
+            }
+            ["format", ..] => return Err(PlyError::UnsupportedFormat),
+            ["comment" | "obj_info", ..] | [] => {}
+            _ => return Err(PlyError::InvalidHeader),
+        }
+    }
+
+    let format = format.ok_or(PlyError::InvalidHeader)?;
+    Ok((format, elements, &bytes[body..]))
+}
+
+/// Reads the values of the elements after the header.
+struct Reader<'a> {
+    format: Format,
+    bytes: &'a [u8],
+    offset: usize,
+    tokens: SplitAsciiWhitespace<'a>,
+}
+
+impl Reader<'_> {
+    fn read(&mut self, scalar: Scalar) -> Result<f64, PlyError> {
+        if self.format == Format::Ascii {
+            return self
+                .tokens
+                .next()
+                .and_then(|token| token.parse().ok())
+                .ok_or(PlyError::InvalidData);
+        }
+
+        let size = scalar.size();
+        let bytes = self
+            .bytes
+            .get(self.offset..self.offset + size)
+            .ok_or(PlyError::InvalidData)?;
+        self.offset += size;
+        let mut le = [0; 8];
+        le[..size].copy_from_slice(bytes);
+        if self.format == Format::BinaryBigEndian {
+            le[..size].reverse();
+        }
+        let [b0, b1, b2, b3, ..] = le;
+        Ok(match scalar {
+            Scalar::I8 => f64::from(b0 as i8),
+            Scalar::U8 => f64::from(b0),
+            Scalar::I16 => f64::from(i16::from_le_bytes([b0, b1])),
+            Scalar::U16 => f64::from(u16::from_le_bytes([b0, b1])),
+            Scalar::I32 => f64::from(i32::from_le_bytes([b0, b1, b2, b3])),
+            Scalar::U32 => f64::from(u32::from_le_bytes([b0, b1, b2, b3])),
+            Scalar::F32 => f64::from(f32::from_le_bytes([b0, b1, b2, b3])),
+            Scalar::F64 => f64::from_le_bytes(le),
+        })
+    }
+}
+
+/// # PLY Loader
+///
+/// [AssetLoader] of [Mesh]es from `.ply` files.
+pub struct PlyLoader;
+
+impl AssetLoader for PlyLoader {
+    type Asset = Mesh;
+
+    fn extensions(&self) -> &[&str] {
+        &["ply"]
+    }
+
+    fn load(
+        &self,
+        context: &LoadContext,
+    ) -> Result<Mesh, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(decode(context.bytes())?)
+    }
+
+    fn process(&self, mesh: &Mesh) -> Option<Vec<u8>> {
+        Some(mesh.data().to_processed())
+    }
+
+    fn load_processed(
+        &self,
+        bytes: &[u8],
+    ) -> Result<Mesh, Box<dyn std::error::Error + Send + Sync>> {
+        let data = MeshData::from_processed(bytes).ok_or(PlyError::InvalidData)?;
+        Ok(Mesh::new(data)?)
+    }
+}
+
+/// # PLY Error
+///
+/// Error returned when a PLY file can't be decoded.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum PlyError {
+    /// The file doesn't start with a PLY header, or its vertices don't have positions.
+    InvalidHeader,
+    /// The format or a property type isn't one of the PLY 1.0 formats or types.
+    UnsupportedFormat,
+    /// The file ends before all elements were decoded or a value is invalid.
+    InvalidData,
+    /// The faces refer to missing vertices.
+    Mesh(MeshError),
+}
+
+impl fmt::Display for PlyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidHeader => write!(f, "invalid PLY header"),
+            Self::UnsupportedFormat => write!(f, "unsupported PLY format"),
+            Self::InvalidData => write!(f, "invalid or truncated PLY data"),
+            Self::Mesh(error) => write!(f, "invalid PLY mesh: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for PlyError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Mesh(error) => Some(error),
+            Self::InvalidHeader | Self::UnsupportedFormat | Self::InvalidData => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_reads_ascii_files() {
+        let file = b"ply
+format ascii 1.0
+comment quad with texture coordinates
+element vertex 4
+property float x
+property float y
+property float z
+property float s
+property float t
+element face 1
+property list uchar int vertex_indices
+end_header
+0 0 0 0 0
+1 0 0 1 0
+1 1 0 1 1
+0 1 0 0 1
+4 0 1 2 3
+";
+        let mesh = decode(file).unwrap();
+        let data = mesh.data();
+        assert_eq!(data.positions[2], Vec3::new(1.0, 1.0, 0.0));
+        assert_eq!(data.uvs[2], Vec2::new(1.0, 0.0));
+        assert_eq!(data.indices, [0, 1, 2, 0, 2, 3]);
+        assert!(data
+            .normals
+            .iter()
+            .all(|normal| normal.abs_diff_eq(Vec3::Z, 1e-6)));
+    }
+
+    #[test]
+    fn decode_reads_binary_files() {
+        let mut file = b"ply
+format binary_big_endian 1.0
+element vertex 3
+property float x
+property float y
+property float z
+property float nx
+property float ny
+property float nz
+property uchar red
+element material 1
+property list uchar uint16 ids
+element face 1
+property list uchar uint vertex_index
+end_header
+"
+        .to_vec();
+        for vertex in [Vec3::ZERO, Vec3::X, Vec3::Y] {
+            for value in [vertex.x, vertex.y, vertex.z, 0.0, 0.0, -1.0] {
+                file.extend(value.to_be_bytes());
+            }
+            file.push(255);
+        }
+        file.extend([2, 0, 1, 0, 2]);
+        file.push(3);
+        for index in [0u32, 2, 1] {
+            file.extend(index.to_be_bytes());
+        }
+
+        let mesh = decode(&file).unwrap();
+        let data = mesh.data();
+        assert_eq!(data.positions, [Vec3::ZERO, Vec3::X, Vec3::Y]);
+        assert_eq!(data.normals, [Vec3::NEG_Z; 3]);
+        assert_eq!(data.uvs, [Vec2::ZERO; 3]);
+        assert_eq!(data.indices, [0, 2, 1]);
+        let processed = PlyLoader.process(&mesh).unwrap();
+        assert_eq!(PlyLoader.load_processed(&processed).unwrap().data(), data);
+
+        assert_eq!(
+            decode(&file[..file.len() - 1]).unwrap_err(),
+            PlyError::InvalidData
+        );
+    }
+
+    #[test]
+    fn decode_rejects_invalid_headers() {
+        assert_eq!(
+            decode(b"obj\nend_header\n").unwrap_err(),
+            PlyError::InvalidHeader
+        );
+        assert_eq!(
+            decode(b"ply\nformat binary 2.0\nend_header\n").unwrap_err(),
+            PlyError::UnsupportedFormat
+        );
+    }
+}
@@ -1,13 +1,52 @@
+use std::fmt;
+
+use glam::Mat3;
 use glam::Mat4;
 use glam::Quat;
+use glam::UVec2;
 use glam::Vec3;
+use glam::Vec4;
+use serde::Deserialize;
+use serde::Serialize;
 
+use crate::render::tonemap::Exposure;
+use crate::render::tonemap::Tonemapping;
 use crate::Component;
+use crate::Node;
+use crate::Reflect;
+
+/// # Name
+///
+/// Human-readable name of the node.
+#[derive(
+    Clone, Debug, Default, Eq, PartialEq, Hash, Component, Reflect, Serialize, Deserialize,
+)]
+pub struct Name(pub String);
+
+impl Name {
+    /// Returns a name with the given value.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self(name.into())
+    }
+
+    /// Returns the name as a string slice.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for Name {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
 
 /// # Visibility
 ///
 /// Visibility of the node.
-#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+#[derive(
+    Copy, Clone, Debug, Default, Eq, PartialEq, Component, Reflect, Serialize, Deserialize,
+)]
 pub enum Visibility {
     /// Inherit the visibility from the node's parent.
     #[default]
@@ -18,12 +57,10 @@ pub enum Visibility {
     Invisible,
 }
 
-impl Component for Visibility {}
-
 /// # Computed Visibility
 ///
 /// Computed visibility of the node.
-#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Component, Reflect, Serialize, Deserialize)]
 pub enum ComputedVisibility {
     /// Node is visible.
     Visible,
@@ -31,12 +68,10 @@ pub enum ComputedVisibility {
     Invisible,
 }
 
-impl Component for ComputedVisibility {}
-
 /// # Local Transform
 ///
 /// Position, rotation, and scale of the node relative to its parent.
-#[derive(Copy, Clone, Debug, PartialEq)]
+#[derive(Copy, Clone, Debug, PartialEq, Component, Reflect, Serialize, Deserialize)]
 pub struct LocalTransform {
     /// Position of the transform.
     pub position: Vec3,
@@ -70,9 +105,82 @@ impl LocalTransform {
             ..Self::IDENTITY
         }
     }
-}
 
-impl Component for LocalTransform {}
+    /// Returns a transform with the given rotation.
+    pub const fn from_rotation(rotation: Quat) -> Self {
+        Self {
+            rotation,
+            ..Self::IDENTITY
+        }
+    }
+
+    /// Returns a transform with the given scale.
+    pub const fn from_scale(scale: Vec3) -> Self {
+        Self {
+            scale,
+            ..Self::IDENTITY
+        }
+    }
+
+    /// Returns the transform rotated so that [LocalTransform::forward] points at the target and
+    /// [LocalTransform::up] points as close to the given up direction as possible.
+    pub fn looking_at(mut self, target: Vec3, up: Vec3) -> Self {
+        self.look_at(target, up);
+        self
+    }
+
+    /// Rotates the transform so that [LocalTransform::forward] points at the target and
+    /// [LocalTransform::up] points as close to the given up direction as possible. Does nothing if
+    /// the target is at the position of the transform.
+    pub fn look_at(&mut self, target: Vec3, up: Vec3) {
+        let Some(forward) = (target - self.position).try_normalize() else {
+            return;
+        };
+
+        let right = forward
+            .cross(up)
+            .try_normalize()
+            .unwrap_or_else(|| forward.any_orthonormal_vector());
+        let up = right.cross(forward);
+        self.rotation = Quat::from_mat3(&Mat3::from_cols(right, up, -forward));
+    }
+
+    /// Rotates the transform around the given point.
+    pub fn rotate_around(&mut self, point: Vec3, rotation: Quat) {
+        self.position = point + rotation * (self.position - point);
+        self.rotation = (rotation * self.rotation).normalize();
+    }
+
+    /// Returns the forward direction of the transform, i.e. the rotated negative Z axis.
+    pub fn forward(&self) -> Vec3 {
+        self.rotation * Vec3::NEG_Z
+    }
+
+    /// Returns the right direction of the transform, i.e. the rotated X axis.
+    pub fn right(&self) -> Vec3 {
+        self.rotation * Vec3::X
+    }
+
+    /// Returns the up direction of the transform, i.e. the rotated Y axis.
+    pub fn up(&self) -> Vec3 {
+        self.rotation * Vec3::Y
+    }
+
+    /// Returns the transform matrix.
+    pub fn matrix(&self) -> Mat4 {
+        Mat4::from_scale_rotation_translation(self.scale, self.rotation, self.position)
+    }
+
+    /// Returns the point transformed by the scale, rotation, and position.
+    pub fn transform_point(&self, point: Vec3) -> Vec3 {
+        self.position + self.rotation * (self.scale * point)
+    }
+
+    /// Returns the vector transformed by the scale and rotation.
+    pub fn transform_vector(&self, vector: Vec3) -> Vec3 {
+        self.rotation * (self.scale * vector)
+    }
+}
 
 impl Default for LocalTransform {
     fn default() -> Self {
@@ -83,7 +191,7 @@ impl Default for LocalTransform {
 /// # World Transform
 ///
 /// Transform of the node in world coordinates.
-#[derive(Copy, Clone, Debug, PartialEq)]
+#[derive(Copy, Clone, Debug, PartialEq, Component, Reflect, Serialize, Deserialize)]
 pub struct WorldTransform {
     /// Transform matrix.
     pub matrix: Mat4,
@@ -99,12 +207,683 @@ impl WorldTransform {
     pub const fn new(matrix: Mat4) -> Self {
         Self { matrix }
     }
-}
 
-impl Component for WorldTransform {}
+    /// Returns the position of the transform.
+    pub fn translation(&self) -> Vec3 {
+        self.matrix.w_axis.truncate()
+    }
+
+    /// Returns the rotation of the transform.
+    pub fn rotation(&self) -> Quat {
+        self.matrix.to_scale_rotation_translation().1
+    }
+
+    /// Returns the scale of the transform.
+    pub fn scale(&self) -> Vec3 {
+        self.matrix.to_scale_rotation_translation().0
+    }
+
+    /// Returns the forward direction of the transform, i.e. the transformed negative Z axis.
+    pub fn forward(&self) -> Vec3 {
+        -self.matrix.z_axis.truncate().normalize_or_zero()
+    }
+
+    /// Returns the right direction of the transform, i.e. the transformed X axis.
+    pub fn right(&self) -> Vec3 {
+        self.matrix.x_axis.truncate().normalize_or_zero()
+    }
+
+    /// Returns the up direction of the transform, i.e. the transformed Y axis.
+    pub fn up(&self) -> Vec3 {
+        self.matrix.y_axis.truncate().normalize_or_zero()
+    }
+
+    /// Returns the point transformed from local to world coordinates.
+    pub fn transform_point(&self, point: Vec3) -> Vec3 {
+        self.matrix.transform_point3(point)
+    }
+
+    /// Returns the vector transformed from local to world coordinates, ignoring the translation.
+    pub fn transform_vector(&self, vector: Vec3) -> Vec3 {
+        self.matrix.transform_vector3(vector)
+    }
+
+    /// Returns the transform blended between this transform at `0.0` and the other transform at
+    /// `1.0`. The scale and translation are interpolated linearly and the rotation spherically.
+    pub fn lerp(&self, other: &Self, t: f32) -> Self {
+        let (start_scale, start_rotation, start_translation) =
+            self.matrix.to_scale_rotation_translation();
+        let (end_scale, end_rotation, end_translation) =
+            other.matrix.to_scale_rotation_translation();
+
+        Self::new(Mat4::from_scale_rotation_translation(
+            start_scale.lerp(end_scale, t),
+            start_rotation.slerp(end_rotation, t),
+            start_translation.lerp(end_translation, t),
+        ))
+    }
+}
 
 impl Default for WorldTransform {
     fn default() -> Self {
         Self::IDENTITY
     }
 }
+
+/// # Previous World Transform
+///
+/// World transform of the node as of the previous fixed update. Nodes with this component are
+/// rendered at a [WorldTransform] interpolated between the previous and the current fixed update
+/// by [crate::systems::interpolate_transforms], so movement simulated at a fixed timestep doesn't
+/// stutter when the framerate differs from it.
+#[derive(Copy, Clone, Debug, PartialEq, Component, Reflect, Serialize, Deserialize)]
+pub struct PreviousWorldTransform {
+    /// Transform matrix.
+    pub matrix: Mat4,
+}
+
+impl PreviousWorldTransform {
+    /// Identity transform.
+    pub const IDENTITY: Self = Self {
+        matrix: Mat4::IDENTITY,
+    };
+
+    /// Returns a transform with the given transform matrix.
+    pub const fn new(matrix: Mat4) -> Self {
+        Self { matrix }
+    }
+}
+
+impl Default for PreviousWorldTransform {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+/// # Aabb
+///
+/// Axis-aligned bounding box of the node in local coordinates. Transformed into world coordinates
+/// by [crate::systems::compute_world_bounds].
+#[derive(Copy, Clone, Debug, PartialEq, Component, Reflect, Serialize, Deserialize)]
+pub struct Aabb {
+    /// Corner with the smallest coordinates.
+    pub min: Vec3,
+    /// Corner with the largest coordinates.
+    pub max: Vec3,
+}
+
+impl Aabb {
+    /// Returns a box with the given corners.
+    pub const fn new(min: Vec3, max: Vec3) -> Self {
+        Self { min, max }
+    }
+
+    /// Returns a box with the given center and half of its size along each axis.
+    pub fn from_center_half_extents(center: Vec3, half_extents: Vec3) -> Self {
+        Self::new(center - half_extents, center + half_extents)
+    }
+
+    /// Returns the smallest box containing all of the points, or `None` if there are no points.
+    pub fn from_points(points: impl IntoIterator<Item = Vec3>) -> Option<Self> {
+        points
+            .into_iter()
+            .map(|point| Self::new(point, point))
+            .reduce(|a, b| a.union(&b))
+    }
+
+    /// Returns the center of the box.
+    pub fn center(&self) -> Vec3 {
+        (self.min + self.max) * 0.5
+    }
+
+    /// Returns half of the size of the box along each axis.
+    pub fn half_extents(&self) -> Vec3 {
+        (self.max - self.min) * 0.5
+    }
+
+    /// Returns the smallest box containing both boxes.
+    pub fn union(&self, other: &Self) -> Self {
+        Self::new(self.min.min(other.min), self.max.max(other.max))
+    }
+
+    /// Returns the smallest axis-aligned box containing the box transformed by the matrix.
+    pub fn transformed(&self, matrix: &Mat4) -> Self {
+        let center = matrix.transform_point3(self.center());
+        let half_extents = self.half_extents();
+        let half_extents = matrix.x_axis.truncate().abs() * half_extents.x
+            + matrix.y_axis.truncate().abs() * half_extents.y
+            + matrix.z_axis.truncate().abs() * half_extents.z;
+        Self::from_center_half_extents(center, half_extents)
+    }
+
+    /// Returns true if the point is inside or on the surface of the box.
+    pub fn contains_point(&self, point: Vec3) -> bool {
+        point.cmpge(self.min).all() && point.cmple(self.max).all()
+    }
+
+    /// Returns true if the boxes overlap or touch.
+    pub fn intersects(&self, other: &Self) -> bool {
+        self.min.cmple(other.max).all() && self.max.cmpge(other.min).all()
+    }
+}
+
+/// # Bounding Sphere
+///
+/// Bounding sphere of the node in local coordinates. Transformed into world coordinates by
+/// [crate::systems::compute_world_bounds].
+#[derive(Copy, Clone, Debug, PartialEq, Component, Reflect, Serialize, Deserialize)]
+pub struct BoundingSphere {
+    /// Center of the sphere.
+    pub center: Vec3,
+    /// Radius of the sphere.
+    pub radius: f32,
+}
+
+impl BoundingSphere {
+    /// Returns a sphere with the given center and radius.
+    pub const fn new(center: Vec3, radius: f32) -> Self {
+        Self { center, radius }
+    }
+
+    /// Returns the smallest sphere containing the box.
+    pub fn from_aabb(aabb: &Aabb) -> Self {
+        Self::new(aabb.center(), aabb.half_extents().length())
+    }
+
+    /// Returns the smallest box containing the sphere.
+    pub fn aabb(&self) -> Aabb {
+        Aabb::from_center_half_extents(self.center, Vec3::splat(self.radius))
+    }
+
+    /// Returns a sphere containing the sphere transformed by the matrix. Non-uniform scales
+    /// scale the radius by the largest factor.
+    pub fn transformed(&self, matrix: &Mat4) -> Self {
+        let scale = matrix
+            .x_axis
+            .truncate()
+            .length()
+            .max(matrix.y_axis.truncate().length())
+            .max(matrix.z_axis.truncate().length());
+        Self::new(matrix.transform_point3(self.center), self.radius * scale)
+    }
+
+    /// Returns true if the point is inside or on the surface of the sphere.
+    pub fn contains_point(&self, point: Vec3) -> bool {
+        self.center.distance_squared(point) <= self.radius * self.radius
+    }
+
+    /// Returns true if the spheres overlap or touch.
+    pub fn intersects(&self, other: &Self) -> bool {
+        let radius = self.radius + other.radius;
+        self.center.distance_squared(other.center) <= radius * radius
+    }
+}
+
+/// # World Bounds
+///
+/// Bounds of the node in world coordinates, computed by [crate::systems::compute_world_bounds]
+/// from its [Aabb] or [BoundingSphere]. If the node only has one of them, the other one is
+/// derived from it.
+#[derive(Copy, Clone, Debug, PartialEq, Component, Reflect, Serialize, Deserialize)]
+pub struct WorldBounds {
+    /// Axis-aligned bounding box in world coordinates.
+    pub aabb: Aabb,
+    /// Bounding sphere in world coordinates.
+    pub sphere: BoundingSphere,
+}
+
+/// # Hierarchy Bounds
+///
+/// Axis-aligned bounding box of the node and all of its descendants in world coordinates,
+/// computed by [crate::systems::compute_world_bounds] for nodes with [WorldBounds] and their
+/// ancestors.
+#[derive(Copy, Clone, Debug, PartialEq, Component, Reflect, Serialize, Deserialize)]
+pub struct HierarchyBounds {
+    /// Axis-aligned bounding box in world coordinates.
+    pub aabb: Aabb,
+}
+
+/// # Projection
+///
+/// Projection of a [Camera] from view to clip coordinates. Cameras look along their forward
+/// direction, the negative Z axis of their world transform.
+#[derive(Copy, Clone, Debug, PartialEq, Reflect, Serialize, Deserialize)]
+pub enum Projection {
+    /// Perspective projection with the given vertical field of view in radians.
+    Perspective {
+        /// Vertical field of view in radians.
+        fov_y: f32,
+        /// Distance of the near clipping plane.
+        near: f32,
+        /// Distance of the far clipping plane.
+        far: f32,
+    },
+    /// Orthographic projection showing the given height in world units.
+    Orthographic {
+        /// Height of the view in world units.
+        height: f32,
+        /// Distance of the near clipping plane.
+        near: f32,
+        /// Distance of the far clipping plane.
+        far: f32,
+    },
+}
+
+/// # Scaling Mode
+///
+/// How a [Camera]'s view is fit into its render target when their aspect ratios differ, e.g. to
+/// keep a 2D game crisp at any window size. Parts of the target outside of the camera's
+/// [Viewport] show the scene's [crate::render::ClearColor].
+#[derive(Copy, Clone, Debug, Default, PartialEq, Reflect, Serialize, Deserialize)]
+pub enum ScalingMode {
+    /// The view is stretched over the whole target with the camera's aspect ratio.
+    #[default]
+    Stretch,
+    /// The view covers the whole target, with the camera's aspect ratio set to the target's by
+    /// [crate::systems::fit_cameras]. Orthographic cameras show a fixed number of vertical units
+    /// and more or less horizontally.
+    FixedVertical,
+    /// The view keeps the camera's aspect ratio and is fit into the center of the target, with
+    /// bars at the sides or at the top and bottom.
+    Letterbox,
+    /// The view is rendered at the virtual resolution scaled by the largest integer factor that
+    /// fits into the target, centered, so every virtual pixel covers the same number of target
+    /// pixels. Orthographic cameras are moved in steps of virtual pixels. Targets smaller than the
+    /// virtual resolution fall back to [ScalingMode::Letterbox].
+    PixelPerfect {
+        /// Width of the virtual resolution in pixels.
+        width: u32,
+        /// Height of the virtual resolution in pixels.
+        height: u32,
+    },
+}
+
+/// # Viewport
+///
+/// Rectangle of a render target in pixels a [Camera] renders to.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct Viewport {
+    /// Top left corner of the rectangle.
+    pub position: UVec2,
+    /// Size of the rectangle.
+    pub size: UVec2,
+}
+
+/// # Camera
+///
+/// Camera rendering the scene from the node's [WorldTransform]. Cameras are created with a
+/// manual exposure of 0 EV, ACES tonemapping, and [ScalingMode::Stretch].
+///
+/// ```
+/// # use pulse::Camera;
+/// # use pulse::ScalingMode;
+/// // A 2D camera showing 180 world units vertically at a virtual resolution of 320x180.
+/// let camera = Camera {
+///     scaling: ScalingMode::PixelPerfect {
+///         width: 320,
+///         height: 180,
+///     },
+///     ..Camera::orthographic(180.0, 320.0 / 180.0, -1.0, 1.0)
+/// };
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq, Component, Reflect, Serialize, Deserialize)]
+pub struct Camera {
+    /// Projection from view to clip coordinates.
+    pub projection: Projection,
+    /// Width of the view divided by its height.
+    pub aspect_ratio: f32,
+    /// Exposure of the light seen by the camera.
+    pub exposure: Exposure,
+    /// Mapping of the exposed light to the range of the render target.
+    pub tonemapping: Tonemapping,
+    /// Fitting of the view into the render target.
+    #[serde(default)]
+    pub scaling: ScalingMode,
+}
+
+impl Camera {
+    /// Returns a camera with a perspective projection.
+    pub const fn perspective(fov_y: f32, aspect_ratio: f32, near: f32, far: f32) -> Self {
+        Self {
+            projection: Projection::Perspective { fov_y, near, far },
+            aspect_ratio,
+            exposure: Exposure::Manual { ev: 0.0 },
+            tonemapping: Tonemapping::Aces,
+            scaling: ScalingMode::Stretch,
+        }
+    }
+
+    /// Returns a camera with an orthographic projection.
+    pub const fn orthographic(height: f32, aspect_ratio: f32, near: f32, far: f32) -> Self {
+        Self {
+            projection: Projection::Orthographic { height, near, far },
+            aspect_ratio,
+            exposure: Exposure::Manual { ev: 0.0 },
+            tonemapping: Tonemapping::Aces,
+            scaling: ScalingMode::Stretch,
+        }
+    }
+
+    /// Returns the projection matrix, mapping depth to the range `0.0..=1.0`.
+    pub fn projection_matrix(&self) -> Mat4 {
+        match self.projection {
+            Projection::Perspective { fov_y, near, far } => {
+                Mat4::perspective_rh(fov_y, self.aspect_ratio, near, far)
+            }
+            Projection::Orthographic { height, near, far } => {
+                let half_height = height * 0.5;
+                let half_width = half_height * self.aspect_ratio;
+                Mat4::orthographic_rh(
+                    -half_width,
+                    half_width,
+                    -half_height,
+                    half_height,
+                    near,
+                    far,
+                )
+            }
+        }
+    }
+
+    /// Returns the matrix transforming world to clip coordinates for a camera at the given
+    /// world transform. Pixel perfect orthographic cameras are snapped to the grid of virtual
+    /// pixels, so sprites don't shimmer as the camera moves.
+    pub fn view_projection_matrix(&self, transform: &WorldTransform) -> Mat4 {
+        let mut view = transform.matrix.inverse();
+        if let (
+            Projection::Orthographic { height, .. },
+            ScalingMode::PixelPerfect {
+                height: pixels @ 1..,
+                ..
+            },
+        ) = (self.projection, self.scaling)
+        {
+            let pixel = height / pixels as f32;
+            view.w_axis.x = (view.w_axis.x / pixel).round() * pixel;
+            view.w_axis.y = (view.w_axis.y / pixel).round() * pixel;
+        }
+        self.projection_matrix() * view
+    }
+
+    /// Returns the rectangle of a render target of the size the camera renders to with its
+    /// [ScalingMode].
+    pub fn viewport(&self, target: UVec2) -> Viewport {
+        let size = match self.scaling {
+            ScalingMode::Stretch | ScalingMode::FixedVertical => target,
+            ScalingMode::Letterbox => fit(target, self.aspect_ratio),
+            ScalingMode::PixelPerfect { width, height } => {
+                let virtual_size = UVec2::new(width, height).max(UVec2::ONE);
+                match (target / virtual_size).min_element() {
+                    0 => fit(target, virtual_size.x as f32 / virtual_size.y as f32),
+                    scale => virtual_size * scale,
+                }
+            }
+        };
+        Viewport {
+            position: (target - size) / 2,
+            size,
+        }
+    }
+
+    /// Returns the frustum of the camera at the given world transform in world coordinates.
+    pub fn frustum(&self, transform: &WorldTransform) -> Frustum {
+        Frustum::from_matrix(&self.view_projection_matrix(transform))
+    }
+}
+
+/// Returns the largest size with the aspect ratio fitting into the target.
+fn fit(target: UVec2, aspect_ratio: f32) -> UVec2 {
+    let width = (target.y as f32 * aspect_ratio).round() as u32;
+    let size = if width <= target.x {
+        UVec2::new(width, target.y)
+    } else {
+        UVec2::new(target.x, (target.x as f32 / aspect_ratio).round() as u32)
+    };
+    size.max(UVec2::ONE).min(target)
+}
+
+/// # Frustum
+///
+/// Volume enclosed by six planes, e.g. the volume visible to a [Camera]. Each plane is stored as
+/// a normal pointing into the frustum and a distance in `w`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Frustum {
+    /// Left, right, bottom, top, near, and far planes.
+    pub planes: [Vec4; 6],
+}
+
+impl Frustum {
+    /// Returns the frustum of a matrix transforming to clip coordinates with depth in the range
+    /// `0.0..=1.0`.
+    pub fn from_matrix(matrix: &Mat4) -> Self {
+        let [x, y, z, w] = [0, 1, 2, 3].map(|index| matrix.row(index));
+        let planes =
+            [w + x, w - x, w + y, w - y, z, w - z].map(|plane| plane / plane.truncate().length());
+        Self { planes }
+    }
+
+    /// Returns true if the point is inside the frustum.
+    pub fn contains_point(&self, point: Vec3) -> bool {
+        self.planes
+            .iter()
+            .all(|plane| plane.truncate().dot(point) + plane.w >= 0.0)
+    }
+
+    /// Returns true if the sphere is at least partially inside the frustum.
+    pub fn intersects_sphere(&self, sphere: &BoundingSphere) -> bool {
+        self.planes
+            .iter()
+            .all(|plane| plane.truncate().dot(sphere.center) + plane.w >= -sphere.radius)
+    }
+
+    /// Returns true if the box is at least partially inside the frustum. Boxes close to a corner
+    /// of the frustum may be reported as intersecting although they are outside.
+    pub fn intersects_aabb(&self, aabb: &Aabb) -> bool {
+        let center = aabb.center();
+        let half_extents = aabb.half_extents();
+        self.planes.iter().all(|plane| {
+            let normal = plane.truncate();
+            normal.dot(center) + normal.abs().dot(half_extents) + plane.w >= 0.0
+        })
+    }
+}
+
+/// # Visible Nodes
+///
+/// Nodes visible to the camera of the node, computed by [crate::systems::cull_frustum].
+#[derive(Clone, Debug, Default, PartialEq, Component)]
+pub struct VisibleNodes(pub Vec<Node>);
+
+impl VisibleNodes {
+    /// Returns true if the node is visible to the camera.
+    pub fn contains(&self, node: Node) -> bool {
+        self.0.contains(&node)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_2;
+
+    use super::*;
+
+    #[test]
+    fn look_at_forward_points_at_target() {
+        let transform = LocalTransform::from_position(Vec3::new(1.0, 0.0, 0.0))
+            .looking_at(Vec3::new(1.0, 0.0, 5.0), Vec3::Y);
+
+        assert!(transform.forward().abs_diff_eq(Vec3::Z, 1e-6));
+        assert!(transform.up().abs_diff_eq(Vec3::Y, 1e-6));
+        assert!(transform.right().abs_diff_eq(Vec3::NEG_X, 1e-6));
+    }
+
+    #[test]
+    fn look_at_parallel_up_points_at_target() {
+        let transform = LocalTransform::IDENTITY.looking_at(Vec3::Y, Vec3::Y);
+
+        assert!(transform.forward().abs_diff_eq(Vec3::Y, 1e-6));
+    }
+
+    #[test]
+    fn rotate_around_moves_position_around_point() {
+        let mut transform = LocalTransform::from_position(Vec3::new(2.0, 0.0, 0.0));
+
+        transform.rotate_around(Vec3::X, Quat::from_rotation_y(FRAC_PI_2));
+
+        assert!(transform
+            .position
+            .abs_diff_eq(Vec3::new(1.0, 0.0, -1.0), 1e-6));
+        assert!(transform.right().abs_diff_eq(Vec3::NEG_Z, 1e-6));
+    }
+
+    #[test]
+    fn transform_point_matches_matrix() {
+        let transform = LocalTransform::new(
+            Vec3::new(1.0, 2.0, 3.0),
+            Quat::from_rotation_z(FRAC_PI_2),
+            Vec3::splat(2.0),
+        );
+        let world = WorldTransform::new(transform.matrix());
+
+        let point = transform.transform_point(Vec3::X);
+
+        assert!(point.abs_diff_eq(Vec3::new(1.0, 4.0, 3.0), 1e-6));
+        assert!(point.abs_diff_eq(world.transform_point(Vec3::X), 1e-6));
+        assert!(world
+            .transform_vector(Vec3::X)
+            .abs_diff_eq(transform.transform_vector(Vec3::X), 1e-6));
+    }
+
+    #[test]
+    fn world_transform_decomposes_matrix() {
+        let rotation = Quat::from_rotation_x(FRAC_PI_2);
+        let world = WorldTransform::new(
+            LocalTransform::new(Vec3::ONE, rotation, Vec3::splat(3.0)).matrix(),
+        );
+
+        assert!(world.translation().abs_diff_eq(Vec3::ONE, 1e-6));
+        assert!(world.rotation().abs_diff_eq(rotation, 1e-6));
+        assert!(world.scale().abs_diff_eq(Vec3::splat(3.0), 1e-5));
+        assert!(world.forward().abs_diff_eq(Vec3::Y, 1e-6));
+    }
+
+    #[test]
+    fn lerp_blends_translation_and_rotation() {
+        let start = WorldTransform::IDENTITY;
+        let end = WorldTransform::new(
+            LocalTransform::new(Vec3::X, Quat::from_rotation_y(FRAC_PI_2), Vec3::ONE).matrix(),
+        );
+
+        let transform = start.lerp(&end, 0.5);
+
+        assert!(transform
+            .translation()
+            .abs_diff_eq(Vec3::new(0.5, 0.0, 0.0), 1e-6));
+        assert!(transform
+            .rotation()
+            .abs_diff_eq(Quat::from_rotation_y(FRAC_PI_2 / 2.0), 1e-6));
+    }
+
+    #[test]
+    fn aabb_transformed_contains_transformed_corners() {
+        let aabb = Aabb::new(Vec3::ZERO, Vec3::new(2.0, 1.0, 1.0));
+        let matrix =
+            LocalTransform::new(Vec3::X, Quat::from_rotation_z(FRAC_PI_2), Vec3::ONE).matrix();
+
+        let transformed = aabb.transformed(&matrix);
+
+        assert!(transformed.min.abs_diff_eq(Vec3::new(0.0, 0.0, 0.0), 1e-6));
+        assert!(transformed.max.abs_diff_eq(Vec3::new(1.0, 2.0, 1.0), 1e-6));
+    }
+
+    #[test]
+    fn aabb_intersects_touching_boxes() {
+        let a = Aabb::new(Vec3::ZERO, Vec3::ONE);
+        let b = Aabb::new(Vec3::ONE, Vec3::splat(2.0));
+        let c = Aabb::new(Vec3::splat(1.5), Vec3::splat(2.0));
+
+        assert!(a.intersects(&b));
+        assert!(!a.intersects(&c));
+        assert_eq!(a.union(&c), Aabb::new(Vec3::ZERO, Vec3::splat(2.0)));
+    }
+
+    #[test]
+    fn bounding_sphere_transformed_scales_radius_by_largest_scale() {
+        let sphere = BoundingSphere::new(Vec3::X, 1.0);
+        let matrix = Mat4::from_scale(Vec3::new(1.0, 3.0, 2.0));
+
+        let transformed = sphere.transformed(&matrix);
+
+        assert_eq!(transformed, BoundingSphere::new(Vec3::X, 3.0));
+        assert!(transformed.contains_point(Vec3::new(1.0, 3.0, 0.0)));
+    }
+
+    #[test]
+    fn frustum_intersects_bounds_in_front_of_camera() {
+        let camera = Camera::perspective(FRAC_PI_2, 1.0, 0.1, 100.0);
+        let transform = WorldTransform::new(
+            LocalTransform::from_position(Vec3::Z)
+                .looking_at(Vec3::new(0.0, 0.0, -10.0), Vec3::Y)
+                .matrix(),
+        );
+
+        let frustum = camera.frustum(&transform);
+
+        assert!(frustum.contains_point(Vec3::new(0.0, 0.0, -5.0)));
+        assert!(!frustum.contains_point(Vec3::new(0.0, 0.0, 5.0)));
+        assert!(!frustum.contains_point(Vec3::new(0.0, 0.0, -200.0)));
+        assert!(frustum.intersects_sphere(&BoundingSphere::new(Vec3::new(5.5, 0.0, -4.0), 1.0)));
+        assert!(!frustum.intersects_sphere(&BoundingSphere::new(Vec3::new(8.0, 0.0, -4.0), 1.0)));
+        assert!(frustum.intersects_aabb(&Aabb::new(Vec3::splat(-1.0), Vec3::splat(1.0))));
+        assert!(!frustum.intersects_aabb(&Aabb::new(Vec3::splat(2.0), Vec3::splat(3.0))));
+    }
+
+    #[test]
+    fn viewport_fits_view_into_target() {
+        let target = UVec2::new(800, 400);
+        let camera = |scaling| Camera {
+            scaling,
+            ..Camera::orthographic(10.0, 1.0, -1.0, 1.0)
+        };
+
+        let stretched = camera(ScalingMode::Stretch).viewport(target);
+        let letterboxed = camera(ScalingMode::Letterbox).viewport(target);
+        let scaled = camera(ScalingMode::PixelPerfect {
+            width: 160,
+            height: 90,
+        })
+        .viewport(target);
+        let fallback = camera(ScalingMode::PixelPerfect {
+            width: 1600,
+            height: 400,
+        })
+        .viewport(target);
+
+        assert_eq!(stretched.position, UVec2::ZERO);
+        assert_eq!(stretched.size, target);
+        assert_eq!(letterboxed.position, UVec2::new(200, 0));
+        assert_eq!(letterboxed.size, UVec2::new(400, 400));
+        assert_eq!(scaled.position, UVec2::new(80, 20));
+        assert_eq!(scaled.size, UVec2::new(640, 360));
+        assert_eq!(fallback.position, UVec2::new(0, 100));
+        assert_eq!(fallback.size, UVec2::new(800, 200));
+    }
+
+    #[test]
+    fn pixel_perfect_view_snaps_to_virtual_pixels() {
+        let camera = Camera {
+            scaling: ScalingMode::PixelPerfect {
+                width: 20,
+                height: 10,
+            },
+            ..Camera::orthographic(10.0, 2.0, -1.0, 1.0)
+        };
+        let at = |x| {
+            let transform = WorldTransform::new(Mat4::from_translation(Vec3::new(x, 0.0, 0.0)));
+            camera.view_projection_matrix(&transform)
+        };
+
+        assert_eq!(at(1.2), at(1.0));
+        assert_ne!(at(1.6), at(1.0));
+    }
+}
@@ -0,0 +1,66 @@
+use std::fmt;
+
+use crate::render::RenderError;
+use crate::systems::ScheduleError;
+
+/// # Error
+///
+/// Error returned when an application fails to run.
+#[derive(Debug)]
+pub enum Error {
+    /// The event loop couldn't be created or failed while running, e.g. because there's no display
+    /// server.
+    EventLoop(winit::error::EventLoopError),
+    /// The application window couldn't be created.
+    Window(winit::error::OsError),
+    /// The application's systems couldn't be ordered.
+    Schedule(ScheduleError),
+    /// The renderer couldn't be created, e.g. because there's no compatible graphics adapter.
+    Render(RenderError),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::EventLoop(error) => write!(f, "event loop failed: {error}"),
+            Self::Window(error) => write!(f, "failed to create window: {error}"),
+            Self::Schedule(error) => write!(f, "invalid schedule: {error}"),
+            Self::Render(error) => write!(f, "failed to create renderer: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::EventLoop(error) => Some(error),
+            Self::Window(error) => Some(error),
+            Self::Schedule(error) => Some(error),
+            Self::Render(error) => Some(error),
+        }
+    }
+}
+
+impl From<winit::error::EventLoopError> for Error {
+    fn from(error: winit::error::EventLoopError) -> Self {
+        Self::EventLoop(error)
+    }
+}
+
+impl From<winit::error::OsError> for Error {
+    fn from(error: winit::error::OsError) -> Self {
+        Self::Window(error)
+    }
+}
+
+impl From<ScheduleError> for Error {
+    fn from(error: ScheduleError) -> Self {
+        Self::Schedule(error)
+    }
+}
+
+impl From<RenderError> for Error {
+    fn from(error: RenderError) -> Self {
+        Self::Render(error)
+    }
+}
@@ -0,0 +1,62 @@
+/// # Events
+///
+/// Scene resource holding the events of type `T` sent during the current frame, e.g. for systems
+/// and plugins to communicate without depending on each other. Registered with
+/// [crate::AppBuilder::add_event], which clears the events at the end of every frame.
+///
+/// ```
+/// # use pulse::Events;
+/// struct Collision(u32);
+///
+/// let mut events = Events::new();
+/// events.send(Collision(17));
+///
+/// assert_eq!(events.iter().map(|collision| collision.0).sum::<u32>(), 17);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Events<T> {
+    events: Vec<T>,
+}
+
+impl<T> Events<T> {
+    /// Returns the resource without any events.
+    pub const fn new() -> Self {
+        Self { events: Vec::new() }
+    }
+
+    /// Sends the event.
+    pub fn send(&mut self, event: T) {
+        self.events.push(event);
+    }
+
+    /// Returns the events sent during the current frame in the order they were sent.
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.events.iter()
+    }
+
+    /// Returns the number of events sent during the current frame.
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    /// Returns true if no events were sent during the current frame.
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// Removes and returns the events sent during the current frame.
+    pub fn drain(&mut self) -> impl '_ + Iterator<Item = T> {
+        self.events.drain(..)
+    }
+
+    /// Removes all of the events.
+    pub fn clear(&mut self) {
+        self.events.clear();
+    }
+}
+
+impl<T> Default for Events<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
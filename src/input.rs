@@ -0,0 +1,610 @@
+//! # Input
+
+use glam::Vec2;
+
+#[cfg(feature = "gamepad")]
+use crate::input::gamepad::Gamepads;
+use crate::Event;
+
+#[cfg(feature = "gamepad")]
+pub mod gamepad;
+pub mod map;
+
+macro_rules! keys {
+    ($($(#[$meta:meta])* $key:ident => $code:ident,)*) => {
+        /// # Key
+        ///
+        /// Physical key on a keyboard, named after the key at its position on a US keyboard
+        /// regardless of the keyboard layout.
+        #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+        pub enum Key {
+            $($(#[$meta])* $key,)*
+        }
+
+        impl Key {
+            pub(crate) fn from_winit(code: winit::keyboard::KeyCode) -> Option<Self> {
+                match code {
+                    $(winit::keyboard::KeyCode::$code => Some(Self::$key),)*
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+keys! {
+    /// <kbd>A</kbd> on a US keyboard.
+    A => KeyA,
+    /// <kbd>B</kbd> on a US keyboard.
+    B => KeyB,
+    /// <kbd>C</kbd> on a US keyboard.
+    C => KeyC,
+    /// <kbd>D</kbd> on a US keyboard.
+    D => KeyD,
+    /// <kbd>E</kbd> on a US keyboard.
+    E => KeyE,
+    /// <kbd>F</kbd> on a US keyboard.
+    F => KeyF,
+    /// <kbd>G</kbd> on a US keyboard.
+    G => KeyG,
+    /// <kbd>H</kbd> on a US keyboard.
+    H => KeyH,
+    /// <kbd>I</kbd> on a US keyboard.
+    I => KeyI,
+    /// <kbd>J</kbd> on a US keyboard.
+    J => KeyJ,
+    /// <kbd>K</kbd> on a US keyboard.
+    K => KeyK,
+    /// <kbd>L</kbd> on a US keyboard.
+    L => KeyL,
+    /// <kbd>M</kbd> on a US keyboard.
+    M => KeyM,
+    /// <kbd>N</kbd> on a US keyboard.
+    N => KeyN,
+    /// <kbd>O</kbd> on a US keyboard.
+    O => KeyO,
+    /// <kbd>P</kbd> on a US keyboard.
+    P => KeyP,
+    /// <kbd>Q</kbd> on a US keyboard.
+    Q => KeyQ,
+    /// <kbd>R</kbd> on a US keyboard.
+    R => KeyR,
+    /// <kbd>S</kbd> on a US keyboard.
+    S => KeyS,
+    /// <kbd>T</kbd> on a US keyboard.
+    T => KeyT,
+    /// <kbd>U</kbd> on a US keyboard.
+    U => KeyU,
+    /// <kbd>V</kbd> on a US keyboard.
+    V => KeyV,
+    /// <kbd>W</kbd> on a US keyboard.
+    W => KeyW,
+    /// <kbd>X</kbd> on a US keyboard.
+    X => KeyX,
+    /// <kbd>Y</kbd> on a US keyboard.
+    Y => KeyY,
+    /// <kbd>Z</kbd> on a US keyboard.
+    Z => KeyZ,
+    /// <kbd>0</kbd> in the row above the letters.
+    Digit0 => Digit0,
+    /// <kbd>1</kbd> in the row above the letters.
+    Digit1 => Digit1,
+    /// <kbd>2</kbd> in the row above the letters.
+    Digit2 => Digit2,
+    /// <kbd>3</kbd> in the row above the letters.
+    Digit3 => Digit3,
+    /// <kbd>4</kbd> in the row above the letters.
+    Digit4 => Digit4,
+    /// <kbd>5</kbd> in the row above the letters.
+    Digit5 => Digit5,
+    /// <kbd>6</kbd> in the row above the letters.
+    Digit6 => Digit6,
+    /// <kbd>7</kbd> in the row above the letters.
+    Digit7 => Digit7,
+    /// <kbd>8</kbd> in the row above the letters.
+    Digit8 => Digit8,
+    /// <kbd>9</kbd> in the row above the letters.
+    Digit9 => Digit9,
+    /// <kbd>F1</kbd> function key.
+    F1 => F1,
+    /// <kbd>F2</kbd> function key.
+    F2 => F2,
+    /// <kbd>F3</kbd> function key.
+    F3 => F3,
+    /// <kbd>F4</kbd> function key.
+    F4 => F4,
+    /// <kbd>F5</kbd> function key.
+    F5 => F5,
+    /// <kbd>F6</kbd> function key.
+    F6 => F6,
+    /// <kbd>F7</kbd> function key.
+    F7 => F7,
+    /// <kbd>F8</kbd> function key.
+    F8 => F8,
+    /// <kbd>F9</kbd> function key.
+    F9 => F9,
+    /// <kbd>F10</kbd> function key.
+    F10 => F10,
+    /// <kbd>F11</kbd> function key.
+    F11 => F11,
+    /// <kbd>F12</kbd> function key.
+    F12 => F12,
+    /// <kbd>0</kbd> on the numeric keypad.
+    Numpad0 => Numpad0,
+    /// <kbd>1</kbd> on the numeric keypad.
+    Numpad1 => Numpad1,
+    /// <kbd>2</kbd> on the numeric keypad.
+    Numpad2 => Numpad2,
+    /// <kbd>3</kbd> on the numeric keypad.
+    Numpad3 => Numpad3,
+    /// <kbd>4</kbd> on the numeric keypad.
+    Numpad4 => Numpad4,
+    /// <kbd>5</kbd> on the numeric keypad.
+    Numpad5 => Numpad5,
+    /// <kbd>6</kbd> on the numeric keypad.
+    Numpad6 => Numpad6,
+    /// <kbd>7</kbd> on the numeric keypad.
+    Numpad7 => Numpad7,
+    /// <kbd>8</kbd> on the numeric keypad.
+    Numpad8 => Numpad8,
+    /// <kbd>9</kbd> on the numeric keypad.
+    Numpad9 => Numpad9,
+    /// <kbd>`</kbd> on a US keyboard.
+    Backquote => Backquote,
+    /// <kbd>\\</kbd> on a US keyboard.
+    Backslash => Backslash,
+    /// <kbd>[</kbd> on a US keyboard.
+    BracketLeft => BracketLeft,
+    /// <kbd>]</kbd> on a US keyboard.
+    BracketRight => BracketRight,
+    /// <kbd>,</kbd> on a US keyboard.
+    Comma => Comma,
+    /// <kbd>=</kbd> on a US keyboard.
+    Equal => Equal,
+    /// <kbd>-</kbd> on a US keyboard.
+    Minus => Minus,
+    /// <kbd>.</kbd> on a US keyboard.
+    Period => Period,
+    /// <kbd>'</kbd> on a US keyboard.
+    Quote => Quote,
+    /// <kbd>;</kbd> on a US keyboard.
+    Semicolon => Semicolon,
+    /// <kbd>/</kbd> on a US keyboard.
+    Slash => Slash,
+    /// Left <kbd>Alt</kbd>.
+    AltLeft => AltLeft,
+    /// Right <kbd>Alt</kbd>.
+    AltRight => AltRight,
+    /// Left <kbd>Control</kbd>.
+    ControlLeft => ControlLeft,
+    /// Right <kbd>Control</kbd>.
+    ControlRight => ControlRight,
+    /// Left <kbd>Shift</kbd>.
+    ShiftLeft => ShiftLeft,
+    /// Right <kbd>Shift</kbd>.
+    ShiftRight => ShiftRight,
+    /// Left <kbd>Windows</kbd>, <kbd>Command</kbd>, or other OS key.
+    SuperLeft => SuperLeft,
+    /// Right <kbd>Windows</kbd>, <kbd>Command</kbd>, or other OS key.
+    SuperRight => SuperRight,
+    /// <kbd>Backspace</kbd>.
+    Backspace => Backspace,
+    /// <kbd>CapsLock</kbd>.
+    CapsLock => CapsLock,
+    /// <kbd>Enter</kbd> or <kbd>Return</kbd>.
+    Enter => Enter,
+    /// <kbd>Space</kbd>.
+    Space => Space,
+    /// <kbd>Tab</kbd>.
+    Tab => Tab,
+    /// <kbd>Escape</kbd>.
+    Escape => Escape,
+    /// <kbd>Delete</kbd>.
+    Delete => Delete,
+    /// <kbd>Insert</kbd>.
+    Insert => Insert,
+    /// <kbd>Home</kbd>.
+    Home => Home,
+    /// <kbd>End</kbd>.
+    End => End,
+    /// <kbd>Page Up</kbd>.
+    PageUp => PageUp,
+    /// <kbd>Page Down</kbd>.
+    PageDown => PageDown,
+    /// <kbd>↑</kbd>.
+    ArrowUp => ArrowUp,
+    /// <kbd>↓</kbd>.
+    ArrowDown => ArrowDown,
+    /// <kbd>←</kbd>.
+    ArrowLeft => ArrowLeft,
+    /// <kbd>→</kbd>.
+    ArrowRight => ArrowRight,
+    /// <kbd>+</kbd> on the numeric keypad.
+    NumpadAdd => NumpadAdd,
+    /// <kbd>-</kbd> on the numeric keypad.
+    NumpadSubtract => NumpadSubtract,
+    /// <kbd>*</kbd> on the numeric keypad.
+    NumpadMultiply => NumpadMultiply,
+    /// <kbd>/</kbd> on the numeric keypad.
+    NumpadDivide => NumpadDivide,
+    /// <kbd>.</kbd> on the numeric keypad.
+    NumpadDecimal => NumpadDecimal,
+    /// <kbd>Enter</kbd> on the numeric keypad.
+    NumpadEnter => NumpadEnter,
+    /// <kbd>NumLock</kbd>.
+    NumLock => NumLock,
+    /// <kbd>PrintScreen</kbd>.
+    PrintScreen => PrintScreen,
+    /// <kbd>ScrollLock</kbd>.
+    ScrollLock => ScrollLock,
+    /// <kbd>Pause</kbd>.
+    Pause => Pause,
+    /// <kbd>Menu</kbd> key, usually next to the right <kbd>Control</kbd>.
+    ContextMenu => ContextMenu,
+}
+
+/// # Mouse Button
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum MouseButton {
+    /// Left mouse button.
+    Left,
+    /// Right mouse button.
+    Right,
+    /// Middle mouse button, usually the wheel.
+    Middle,
+    /// Back side button.
+    Back,
+    /// Forward side button.
+    Forward,
+    /// Other button with a platform-specific code.
+    Other(u16),
+}
+
+impl MouseButton {
+    pub(crate) fn from_winit(button: winit::event::MouseButton) -> Self {
+        match button {
+            winit::event::MouseButton::Left => Self::Left,
+            winit::event::MouseButton::Right => Self::Right,
+            winit::event::MouseButton::Middle => Self::Middle,
+            winit::event::MouseButton::Back => Self::Back,
+            winit::event::MouseButton::Forward => Self::Forward,
+            winit::event::MouseButton::Other(code) => Self::Other(code),
+        }
+    }
+}
+
+/// # Mouse Scroll Unit
+///
+/// Unit of a [crate::Event::MouseWheel] delta.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum MouseScrollUnit {
+    /// Lines or rows, reported by most mouse wheels.
+    Line,
+    /// Pixels, reported by touchpads and other devices with precise scrolling.
+    Pixel,
+}
+
+/// # Button Input
+///
+/// Pressed state of a set of buttons, e.g. keys or mouse buttons, along with the buttons pressed
+/// and released during the current frame.
+#[derive(Clone, Debug)]
+pub struct ButtonInput<T> {
+    pressed: Vec<T>,
+    just_pressed: Vec<T>,
+    just_released: Vec<T>,
+}
+
+impl<T: Copy + Eq> ButtonInput<T> {
+    /// Returns a state without any pressed buttons.
+    pub const fn new() -> Self {
+        Self {
+            pressed: Vec::new(),
+            just_pressed: Vec::new(),
+            just_released: Vec::new(),
+        }
+    }
+
+    /// Presses the button. Does nothing if the button is already pressed.
+    pub fn press(&mut self, button: T) {
+        if !self.pressed.contains(&button) {
+            self.pressed.push(button);
+            self.just_pressed.push(button);
+        }
+    }
+
+    /// Releases the button. Does nothing if the button isn't pressed.
+    pub fn release(&mut self, button: T) {
+        if let Some(index) = self.pressed.iter().position(|pressed| *pressed == button) {
+            self.pressed.swap_remove(index);
+            self.just_released.push(button);
+        }
+    }
+
+    /// Releases all of the pressed buttons.
+    pub fn release_all(&mut self) {
+        self.just_released.append(&mut self.pressed);
+    }
+
+    /// Returns true if the button is pressed.
+    pub fn is_pressed(&self, button: T) -> bool {
+        self.pressed.contains(&button)
+    }
+
+    /// Returns true if the button was pressed during the current frame.
+    pub fn just_pressed(&self, button: T) -> bool {
+        self.just_pressed.contains(&button)
+    }
+
+    /// Returns true if the button was released during the current frame.
+    pub fn just_released(&self, button: T) -> bool {
+        self.just_released.contains(&button)
+    }
+
+    /// Returns the pressed buttons.
+    pub fn get_pressed(&self) -> impl '_ + Iterator<Item = T> {
+        self.pressed.iter().copied()
+    }
+
+    /// Forgets the buttons pressed and released during the current frame.
+    pub fn clear(&mut self) {
+        self.just_pressed.clear();
+        self.just_released.clear();
+    }
+}
+
+impl<T: Copy + Eq> Default for ButtonInput<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// # Input
+///
+/// Keyboard and mouse state maintained by the application runner as a [crate::Scene] resource.
+/// The state is updated from the events before they're passed to
+/// [crate::Application::handle_event], and the per-frame state is cleared after the scene's
+/// systems were run.
+///
+/// ```
+/// # use pulse::input::Input;
+/// # use pulse::input::Key;
+/// # use pulse::Event;
+/// let mut input = Input::new();
+/// input.handle_event(&Event::KeyPressed {
+///     key: Key::Space,
+///     repeat: false,
+/// });
+/// assert!(input.just_pressed(Key::Space));
+///
+/// input.end_frame();
+/// assert!(input.is_pressed(Key::Space));
+/// assert!(!input.just_pressed(Key::Space));
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Input {
+    keys: ButtonInput<Key>,
+    mouse_buttons: ButtonInput<MouseButton>,
+    cursor_position: Option<Vec2>,
+    mouse_delta: Vec2,
+    line_scroll: Vec2,
+    pixel_scroll: Vec2,
+    #[cfg(feature = "gamepad")]
+    gamepads: Gamepads,
+}
+
+impl Input {
+    /// Returns the state without any pressed keys or buttons.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the state of the keyboard keys.
+    pub fn keys(&self) -> &ButtonInput<Key> {
+        &self.keys
+    }
+
+    /// Returns the state of the mouse buttons.
+    pub fn mouse_buttons(&self) -> &ButtonInput<MouseButton> {
+        &self.mouse_buttons
+    }
+
+    /// Returns true if the key is pressed.
+    pub fn is_pressed(&self, key: Key) -> bool {
+        self.keys.is_pressed(key)
+    }
+
+    /// Returns true if the key was pressed during the current frame.
+    pub fn just_pressed(&self, key: Key) -> bool {
+        self.keys.just_pressed(key)
+    }
+
+    /// Returns true if the key was released during the current frame.
+    pub fn just_released(&self, key: Key) -> bool {
+        self.keys.just_released(key)
+    }
+
+    /// Returns true if the mouse button is pressed.
+    pub fn is_mouse_pressed(&self, button: MouseButton) -> bool {
+        self.mouse_buttons.is_pressed(button)
+    }
+
+    /// Returns true if the mouse button was pressed during the current frame.
+    pub fn mouse_just_pressed(&self, button: MouseButton) -> bool {
+        self.mouse_buttons.just_pressed(button)
+    }
+
+    /// Returns true if the mouse button was released during the current frame.
+    pub fn mouse_just_released(&self, button: MouseButton) -> bool {
+        self.mouse_buttons.just_released(button)
+    }
+
+    /// Returns the position of the cursor in physical pixels relative to the top-left corner of
+    /// the window, or `None` if the cursor hasn't entered the window yet.
+    pub fn cursor_position(&self) -> Option<Vec2> {
+        self.cursor_position
+    }
+
+    /// Returns the distance the mouse moved during the current frame from the
+    /// [Event::MouseMotion] events.
+    pub fn mouse_delta(&self) -> Vec2 {
+        self.mouse_delta
+    }
+
+    /// Returns the distance scrolled in the given unit during the current frame.
+    pub fn scroll_delta(&self, unit: MouseScrollUnit) -> Vec2 {
+        match unit {
+            MouseScrollUnit::Line => self.line_scroll,
+            MouseScrollUnit::Pixel => self.pixel_scroll,
+        }
+    }
+
+    /// Returns the connected gamepads and their state.
+    #[cfg(feature = "gamepad")]
+    pub fn gamepads(&self) -> &Gamepads {
+        &self.gamepads
+    }
+
+    /// Returns a mutable reference to the connected gamepads, e.g. to set the dead zone or request
+    /// rumble.
+    #[cfg(feature = "gamepad")]
+    pub fn gamepads_mut(&mut self) -> &mut Gamepads {
+        &mut self.gamepads
+    }
+
+    /// Updates the state from the event.
+    pub fn handle_event(&mut self, event: &Event) {
+        #[cfg(feature = "gamepad")]
+        self.gamepads.handle_event(event);
+
+        match *event {
+            Event::KeyPressed { key, .. } => self.keys.press(key),
+            Event::KeyReleased { key } => self.keys.release(key),
+            Event::MouseButtonPressed { button } => self.mouse_buttons.press(button),
+            Event::MouseButtonReleased { button } => self.mouse_buttons.release(button),
+            Event::MouseMoved { position, .. } => self.cursor_position = Some(position),
+            Event::MouseMotion { delta } => self.mouse_delta += delta,
+            Event::MouseWheel {
+                delta,
+                unit: MouseScrollUnit::Line,
+            } => self.line_scroll += delta,
+            Event::MouseWheel {
+                delta,
+                unit: MouseScrollUnit::Pixel,
+            } => self.pixel_scroll += delta,
+            _ => {}
+        }
+    }
+
+    /// Releases all of the keys and mouse buttons, e.g. when the window loses focus and the
+    /// release events would be missed.
+    pub fn release_all(&mut self) {
+        self.keys.release_all();
+        self.mouse_buttons.release_all();
+    }
+
+    /// Clears the state of the current frame, i.e. the keys and buttons pressed and released, the
+    /// mouse delta, and the scroll delta.
+    pub fn end_frame(&mut self) {
+        self.keys.clear();
+        self.mouse_buttons.clear();
+        self.mouse_delta = Vec2::ZERO;
+        self.line_scroll = Vec2::ZERO;
+        self.pixel_scroll = Vec2::ZERO;
+        #[cfg(feature = "gamepad")]
+        self.gamepads.end_frame();
+    }
+}
+
+/// Returns the delta and unit of a winit scroll delta.
+pub(crate) fn scroll_from_winit(delta: winit::event::MouseScrollDelta) -> (Vec2, MouseScrollUnit) {
+    match delta {
+        winit::event::MouseScrollDelta::LineDelta(x, y) => (Vec2::new(x, y), MouseScrollUnit::Line),
+        winit::event::MouseScrollDelta::PixelDelta(position) => (
+            Vec2::new(position.x as f32, position.y as f32),
+            MouseScrollUnit::Pixel,
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use winit::dpi::PhysicalPosition;
+    use winit::event::MouseScrollDelta;
+
+    use super::*;
+
+    #[test]
+    fn handle_event_tracks_pressed_and_released_buttons() {
+        let mut input = Input::new();
+        input.handle_event(&Event::KeyPressed {
+            key: Key::W,
+            repeat: false,
+        });
+        input.handle_event(&Event::MouseButtonPressed {
+            button: MouseButton::Left,
+        });
+        input.end_frame();
+
+        input.handle_event(&Event::KeyReleased { key: Key::W });
+        input.handle_event(&Event::KeyPressed {
+            key: Key::W,
+            repeat: false,
+        });
+
+        assert!(input.is_pressed(Key::W));
+        assert!(input.just_pressed(Key::W));
+        assert!(input.just_released(Key::W));
+        assert!(input.is_mouse_pressed(MouseButton::Left));
+        assert!(!input.mouse_just_pressed(MouseButton::Left));
+    }
+
+    #[test]
+    fn handle_event_accumulates_deltas_until_end_frame() {
+        let mut input = Input::new();
+        for delta in [Vec2::X, Vec2::Y] {
+            input.handle_event(&Event::MouseMotion { delta });
+            input.handle_event(&Event::MouseWheel {
+                delta,
+                unit: MouseScrollUnit::Line,
+            });
+        }
+
+        assert_eq!(input.mouse_delta(), Vec2::ONE);
+        assert_eq!(input.scroll_delta(MouseScrollUnit::Line), Vec2::ONE);
+        assert_eq!(input.scroll_delta(MouseScrollUnit::Pixel), Vec2::ZERO);
+
+        input.end_frame();
+
+        assert_eq!(input.mouse_delta(), Vec2::ZERO);
+        assert_eq!(input.scroll_delta(MouseScrollUnit::Line), Vec2::ZERO);
+    }
+
+    #[test]
+    fn release_all_releases_pressed_buttons() {
+        let mut input = Input::new();
+        input.handle_event(&Event::KeyPressed {
+            key: Key::ShiftLeft,
+            repeat: false,
+        });
+
+        input.release_all();
+
+        assert!(!input.is_pressed(Key::ShiftLeft));
+        assert!(input.just_released(Key::ShiftLeft));
+    }
+
+    #[test]
+    fn scroll_from_winit_keeps_unit() {
+        assert_eq!(
+            scroll_from_winit(MouseScrollDelta::LineDelta(0.0, -1.0)),
+            (Vec2::new(0.0, -1.0), MouseScrollUnit::Line)
+        );
+        assert_eq!(
+            scroll_from_winit(MouseScrollDelta::PixelDelta(PhysicalPosition::new(
+                3.0, 4.5
+            ))),
+            (Vec2::new(3.0, 4.5), MouseScrollUnit::Pixel)
+        );
+    }
+}
@@ -0,0 +1,568 @@
+//! # Gamepad
+//!
+//! Gamepad input through [gilrs], available with the `gamepad` feature. The connected gamepads and
+//! their state are available through [crate::input::Input::gamepads].
+
+use std::time::Duration;
+
+use gilrs::ff::BaseEffect;
+use gilrs::ff::BaseEffectType;
+use gilrs::ff::Effect;
+use gilrs::ff::EffectBuilder;
+use gilrs::ff::Replay;
+use gilrs::ff::Ticks;
+use gilrs::EventType;
+use gilrs::Gilrs;
+use glam::Vec2;
+
+use crate::input::ButtonInput;
+use crate::input::Input;
+use crate::time::Instant;
+use crate::Event;
+
+/// # Gamepad
+///
+/// Identifier of a gamepad, stable while the gamepad is connected.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct Gamepad(pub usize);
+
+/// # Gamepad Button
+///
+/// Button on a gamepad, named after its position on the gamepad.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum GamepadButton {
+    /// Bottom face button, e.g. A on Xbox controllers.
+    South,
+    /// Right face button, e.g. B on Xbox controllers.
+    East,
+    /// Top face button, e.g. Y on Xbox controllers.
+    North,
+    /// Left face button, e.g. X on Xbox controllers.
+    West,
+    /// Left shoulder button.
+    LeftShoulder,
+    /// Left trigger.
+    LeftTrigger,
+    /// Right shoulder button.
+    RightShoulder,
+    /// Right trigger.
+    RightTrigger,
+    /// Select or back button.
+    Select,
+    /// Start button.
+    Start,
+    /// Mode or home button.
+    Mode,
+    /// Left stick pressed in.
+    LeftStick,
+    /// Right stick pressed in.
+    RightStick,
+    /// Up on the directional pad.
+    DPadUp,
+    /// Down on the directional pad.
+    DPadDown,
+    /// Left on the directional pad.
+    DPadLeft,
+    /// Right on the directional pad.
+    DPadRight,
+}
+
+impl GamepadButton {
+    fn from_gilrs(button: gilrs::Button) -> Option<Self> {
+        Some(match button {
+            gilrs::Button::South => Self::South,
+            gilrs::Button::East => Self::East,
+            gilrs::Button::North => Self::North,
+            gilrs::Button::West => Self::West,
+            gilrs::Button::LeftTrigger => Self::LeftShoulder,
+            gilrs::Button::LeftTrigger2 => Self::LeftTrigger,
+            gilrs::Button::RightTrigger => Self::RightShoulder,
+            gilrs::Button::RightTrigger2 => Self::RightTrigger,
+            gilrs::Button::Select => Self::Select,
+            gilrs::Button::Start => Self::Start,
+            gilrs::Button::Mode => Self::Mode,
+            gilrs::Button::LeftThumb => Self::LeftStick,
+            gilrs::Button::RightThumb => Self::RightStick,
+            gilrs::Button::DPadUp => Self::DPadUp,
+            gilrs::Button::DPadDown => Self::DPadDown,
+            gilrs::Button::DPadLeft => Self::DPadLeft,
+            gilrs::Button::DPadRight => Self::DPadRight,
+            _ => return None,
+        })
+    }
+}
+
+/// # Gamepad Axis
+///
+/// Analog axis on a gamepad, ranging from -1 to 1 with positive values pointing right and up.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum GamepadAxis {
+    /// Horizontal axis of the left stick.
+    LeftStickX,
+    /// Vertical axis of the left stick.
+    LeftStickY,
+    /// Horizontal axis of the right stick.
+    RightStickX,
+    /// Vertical axis of the right stick.
+    RightStickY,
+}
+
+impl GamepadAxis {
+    fn from_gilrs(axis: gilrs::Axis) -> Option<Self> {
+        Some(match axis {
+            gilrs::Axis::LeftStickX => Self::LeftStickX,
+            gilrs::Axis::LeftStickY => Self::LeftStickY,
+            gilrs::Axis::RightStickX => Self::RightStickX,
+            gilrs::Axis::RightStickY => Self::RightStickY,
+            _ => return None,
+        })
+    }
+}
+
+/// # Rumble
+///
+/// Force feedback to play on a gamepad. See [Gamepads::rumble].
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Rumble {
+    /// Magnitude of the strong, low-frequency motor from 0 to 1.
+    pub strong: f32,
+    /// Magnitude of the weak, high-frequency motor from 0 to 1.
+    pub weak: f32,
+    /// Duration of the rumble.
+    pub duration: Duration,
+}
+
+/// # Gamepad State
+///
+/// Buttons and axes of a connected gamepad.
+#[derive(Clone, Debug)]
+pub struct GamepadState {
+    name: String,
+    rumble_supported: bool,
+    buttons: ButtonInput<GamepadButton>,
+    button_values: Vec<(GamepadButton, f32)>,
+    axes: Vec<(GamepadAxis, f32)>,
+}
+
+impl GamepadState {
+    fn new() -> Self {
+        Self {
+            name: String::new(),
+            rumble_supported: false,
+            buttons: ButtonInput::new(),
+            button_values: Vec::new(),
+            axes: Vec::new(),
+        }
+    }
+
+    /// Returns the name of the gamepad reported by the operating system.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns true if the gamepad supports [Rumble].
+    pub fn is_rumble_supported(&self) -> bool {
+        self.rumble_supported
+    }
+
+    /// Returns the state of the gamepad's buttons.
+    pub fn buttons(&self) -> &ButtonInput<GamepadButton> {
+        &self.buttons
+    }
+
+    /// Returns how far the button is pressed from 0 to 1, e.g. for analog triggers.
+    pub fn button_value(&self, button: GamepadButton) -> f32 {
+        find(&self.button_values, button)
+            .unwrap_or_else(|| f32::from(u8::from(self.buttons.is_pressed(button))))
+    }
+
+    /// Returns the raw value of the axis without the dead zone applied.
+    pub fn raw_axis(&self, axis: GamepadAxis) -> f32 {
+        find(&self.axes, axis).unwrap_or(0.0)
+    }
+}
+
+fn find<T: Copy + Eq>(values: &[(T, f32)], key: T) -> Option<f32> {
+    values
+        .iter()
+        .find(|(other, _)| *other == key)
+        .map(|(_, value)| *value)
+}
+
+fn set<T: Copy + Eq>(values: &mut Vec<(T, f32)>, key: T, value: f32) {
+    match values.iter_mut().find(|(other, _)| *other == key) {
+        Some((_, previous)) => *previous = value,
+        None => values.push((key, value)),
+    }
+}
+
+/// # Gamepads
+///
+/// Connected gamepads and their state, maintained as part of the [Input] resource.
+#[derive(Clone, Debug)]
+pub struct Gamepads {
+    gamepads: Vec<(Gamepad, GamepadState)>,
+    dead_zone: f32,
+    rumbles: Vec<(Gamepad, Rumble)>,
+}
+
+impl Gamepads {
+    /// Dead zone of the axes by default.
+    pub const DEFAULT_DEAD_ZONE: f32 = 0.1;
+
+    /// Returns the state without any connected gamepads.
+    pub fn new() -> Self {
+        Self {
+            gamepads: Vec::new(),
+            dead_zone: Self::DEFAULT_DEAD_ZONE,
+            rumbles: Vec::new(),
+        }
+    }
+
+    /// Returns the connected gamepads.
+    pub fn iter(&self) -> impl '_ + Iterator<Item = Gamepad> {
+        self.gamepads.iter().map(|(gamepad, _)| *gamepad)
+    }
+
+    /// Returns true if the gamepad is connected.
+    pub fn is_connected(&self, gamepad: Gamepad) -> bool {
+        self.get(gamepad).is_some()
+    }
+
+    /// Returns the state of the gamepad, or `None` if it isn't connected.
+    pub fn get(&self, gamepad: Gamepad) -> Option<&GamepadState> {
+        self.gamepads
+            .iter()
+            .find(|(other, _)| *other == gamepad)
+            .map(|(_, state)| state)
+    }
+
+    /// Returns the dead zone of the axes.
+    pub fn dead_zone(&self) -> f32 {
+        self.dead_zone
+    }
+
+    /// Sets the dead zone of the axes. Axis values with a magnitude below the dead zone are
+    /// reported as zero and the remaining range is rescaled to start at zero.
+    pub fn set_dead_zone(&mut self, dead_zone: f32) {
+        self.dead_zone = dead_zone.clamp(0.0, 0.99);
+    }
+
+    /// Returns true if the button is pressed on the gamepad.
+    pub fn is_pressed(&self, gamepad: Gamepad, button: GamepadButton) -> bool {
+        self.get(gamepad)
+            .is_some_and(|state| state.buttons.is_pressed(button))
+    }
+
+    /// Returns true if the button was pressed on the gamepad during the current frame.
+    pub fn just_pressed(&self, gamepad: Gamepad, button: GamepadButton) -> bool {
+        self.get(gamepad)
+            .is_some_and(|state| state.buttons.just_pressed(button))
+    }
+
+    /// Returns true if the button was released on the gamepad during the current frame.
+    pub fn just_released(&self, gamepad: Gamepad, button: GamepadButton) -> bool {
+        self.get(gamepad)
+            .is_some_and(|state| state.buttons.just_released(button))
+    }
+
+    /// Returns the value of the axis with the dead zone applied, or zero if the gamepad isn't
+    /// connected.
+    pub fn axis(&self, gamepad: Gamepad, axis: GamepadAxis) -> f32 {
+        let value = self.get(gamepad).map_or(0.0, |state| state.raw_axis(axis));
+        if value.abs() < self.dead_zone {
+            0.0
+        } else {
+            value.signum() * (value.abs() - self.dead_zone) / (1.0 - self.dead_zone)
+        }
+    }
+
+    /// Returns the position of the left stick with the dead zone applied.
+    pub fn left_stick(&self, gamepad: Gamepad) -> Vec2 {
+        Vec2::new(
+            self.axis(gamepad, GamepadAxis::LeftStickX),
+            self.axis(gamepad, GamepadAxis::LeftStickY),
+        )
+    }
+
+    /// Returns the position of the right stick with the dead zone applied.
+    pub fn right_stick(&self, gamepad: Gamepad) -> Vec2 {
+        Vec2::new(
+            self.axis(gamepad, GamepadAxis::RightStickX),
+            self.axis(gamepad, GamepadAxis::RightStickY),
+        )
+    }
+
+    /// Requests the rumble to be played on the gamepad. The request is ignored if the gamepad
+    /// doesn't support rumble.
+    pub fn rumble(&mut self, gamepad: Gamepad, rumble: Rumble) {
+        if self
+            .get(gamepad)
+            .is_some_and(|state| state.rumble_supported)
+        {
+            self.rumbles.push((gamepad, rumble));
+        }
+    }
+
+    /// Updates the state from the event.
+    pub fn handle_event(&mut self, event: &Event) {
+        match *event {
+            Event::GamepadConnected { gamepad } => {
+                self.state_mut(gamepad);
+            }
+            Event::GamepadDisconnected { gamepad } => {
+                self.gamepads.retain(|(other, _)| *other != gamepad);
+                self.rumbles.retain(|(other, _)| *other != gamepad);
+            }
+            Event::GamepadButtonPressed { gamepad, button } => {
+                self.state_mut(gamepad).buttons.press(button);
+            }
+            Event::GamepadButtonReleased { gamepad, button } => {
+                self.state_mut(gamepad).buttons.release(button);
+            }
+            Event::GamepadButtonChanged {
+                gamepad,
+                button,
+                value,
+            } => {
+                set(&mut self.state_mut(gamepad).button_values, button, value);
+            }
+            Event::GamepadAxisChanged {
+                gamepad,
+                axis,
+                value,
+            } => {
+                set(&mut self.state_mut(gamepad).axes, axis, value);
+            }
+            _ => {}
+        }
+    }
+
+    /// Clears the buttons pressed and released during the current frame.
+    pub fn end_frame(&mut self) {
+        for (_, state) in &mut self.gamepads {
+            state.buttons.clear();
+        }
+    }
+
+    /// Registers the gamepad with its name and capabilities. Returns false if the gamepad was
+    /// already connected.
+    fn connect(&mut self, gamepad: Gamepad, info: gilrs::Gamepad<'_>) -> bool {
+        if self.is_connected(gamepad) {
+            return false;
+        }
+
+        let state = self.state_mut(gamepad);
+        state.name = info.name().to_string();
+        state.rumble_supported = info.is_ff_supported();
+        true
+    }
+
+    fn state_mut(&mut self, gamepad: Gamepad) -> &mut GamepadState {
+        let index = match self
+            .gamepads
+            .iter()
+            .position(|(other, _)| *other == gamepad)
+        {
+            Some(index) => index,
+            None => {
+                self.gamepads.push((gamepad, GamepadState::new()));
+                self.gamepads.len() - 1
+            }
+        };
+
+        &mut self.gamepads[index].1
+    }
+}
+
+impl Default for Gamepads {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Polls the gamepads through gilrs and plays the requested rumbles for the application runner.
+pub(crate) struct GamepadContext {
+    gilrs: Option<Gilrs>,
+    effects: Vec<(Instant, Effect)>,
+}
+
+impl GamepadContext {
+    /// Returns the context, without gamepad support if gilrs fails to initialize.
+    pub(crate) fn new() -> Self {
+        Self {
+            gilrs: Gilrs::new().ok(),
+            effects: Vec::new(),
+        }
+    }
+
+    /// Returns the gamepad events since the previous poll. Newly connected gamepads are registered
+    /// with the input, including the gamepads connected before the first poll.
+    pub(crate) fn poll(&mut self, input: &mut Input) -> Vec<Event> {
+        let Some(gilrs) = &mut self.gilrs else {
+            return Vec::new();
+        };
+
+        let mut events = Vec::new();
+        for (id, gamepad) in gilrs.gamepads() {
+            if input.gamepads.connect(Gamepad(id.into()), gamepad) {
+                events.push(Event::GamepadConnected {
+                    gamepad: Gamepad(id.into()),
+                });
+            }
+        }
+
+        while let Some(gilrs::Event { id, event, .. }) = gilrs.next_event() {
+            let gamepad = Gamepad(id.into());
+            events.extend(match event {
+                EventType::Connected => input
+                    .gamepads
+                    .connect(gamepad, gilrs.gamepad(id))
+                    .then_some(Event::GamepadConnected { gamepad }),
+                EventType::Disconnected => Some(Event::GamepadDisconnected { gamepad }),
+                EventType::ButtonPressed(button, _) => GamepadButton::from_gilrs(button)
+                    .map(|button| Event::GamepadButtonPressed { gamepad, button }),
+                EventType::ButtonReleased(button, _) => GamepadButton::from_gilrs(button)
+                    .map(|button| Event::GamepadButtonReleased { gamepad, button }),
+                EventType::ButtonChanged(button, value, _) => GamepadButton::from_gilrs(button)
+                    .map(|button| Event::GamepadButtonChanged {
+                        gamepad,
+                        button,
+                        value,
+                    }),
+                EventType::AxisChanged(axis, value, _) => {
+                    GamepadAxis::from_gilrs(axis).map(|axis| Event::GamepadAxisChanged {
+                        gamepad,
+                        axis,
+                        value,
+                    })
+                }
+                _ => None,
+            });
+        }
+
+        events
+    }
+
+    /// Plays the rumbles requested through the input and stops the finished ones.
+    pub(crate) fn play_rumbles(&mut self, input: &mut Input) {
+        let now = Instant::now();
+        self.effects.retain(|(end, _)| *end > now);
+
+        let Some(gilrs) = &mut self.gilrs else {
+            input.gamepads.rumbles.clear();
+            return;
+        };
+
+        for (gamepad, rumble) in input.gamepads.rumbles.drain(..) {
+            let Some((id, _)) = gilrs
+                .gamepads()
+                .find(|(id, _)| usize::from(*id) == gamepad.0)
+            else {
+                continue;
+            };
+
+            let play_for =
+                Ticks::from_ms(rumble.duration.as_millis().try_into().unwrap_or(u32::MAX));
+            let magnitude = |value: f32| (value.clamp(0.0, 1.0) * f32::from(u16::MAX)) as u16;
+            let effect = EffectBuilder::new()
+                .add_effect(BaseEffect {
+                    kind: BaseEffectType::Strong {
+                        magnitude: magnitude(rumble.strong),
+                    },
+                    scheduling: Replay {
+                        play_for,
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                })
+                .add_effect(BaseEffect {
+                    kind: BaseEffectType::Weak {
+                        magnitude: magnitude(rumble.weak),
+                    },
+                    scheduling: Replay {
+                        play_for,
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                })
+                .gamepads(&[id])
+                .finish(gilrs);
+
+            if let Ok(effect) = effect {
+                if effect.play().is_ok() {
+                    self.effects.push((now + rumble.duration, effect));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn axis_applies_dead_zone() {
+        let gamepad = Gamepad(0);
+        let mut gamepads = Gamepads::new();
+        gamepads.set_dead_zone(0.2);
+        gamepads.handle_event(&Event::GamepadAxisChanged {
+            gamepad,
+            axis: GamepadAxis::LeftStickX,
+            value: 0.1,
+        });
+        gamepads.handle_event(&Event::GamepadAxisChanged {
+            gamepad,
+            axis: GamepadAxis::LeftStickY,
+            value: -0.6,
+        });
+
+        assert_eq!(gamepads.axis(gamepad, GamepadAxis::LeftStickX), 0.0);
+        assert!((gamepads.left_stick(gamepad).y + 0.5).abs() < 1e-6);
+        assert_eq!(gamepads.axis(Gamepad(1), GamepadAxis::LeftStickX), 0.0);
+    }
+
+    #[test]
+    fn disconnect_forgets_gamepad() {
+        let gamepad = Gamepad(3);
+        let mut gamepads = Gamepads::new();
+        gamepads.handle_event(&Event::GamepadConnected { gamepad });
+        gamepads.handle_event(&Event::GamepadButtonPressed {
+            gamepad,
+            button: GamepadButton::South,
+        });
+
+        assert!(gamepads.just_pressed(gamepad, GamepadButton::South));
+        assert_eq!(
+            gamepads
+                .get(gamepad)
+                .unwrap()
+                .button_value(GamepadButton::South),
+            1.0
+        );
+
+        gamepads.handle_event(&Event::GamepadDisconnected { gamepad });
+
+        assert!(!gamepads.is_connected(gamepad));
+        assert!(!gamepads.is_pressed(gamepad, GamepadButton::South));
+    }
+
+    #[test]
+    fn rumble_ignored_without_support() {
+        let gamepad = Gamepad(0);
+        let mut gamepads = Gamepads::new();
+        gamepads.handle_event(&Event::GamepadConnected { gamepad });
+
+        gamepads.rumble(
+            gamepad,
+            Rumble {
+                strong: 1.0,
+                weak: 0.5,
+                duration: Duration::from_millis(200),
+            },
+        );
+
+        assert!(gamepads.rumbles.is_empty());
+    }
+}
@@ -0,0 +1,388 @@
+//! # Input Map
+//!
+//! Named actions and axes bound to keys, mouse buttons, and gamepad inputs, so gameplay code can
+//! query "jump" rather than the space bar and the bindings can be changed at runtime.
+
+use std::collections::BTreeMap;
+
+#[cfg(feature = "gamepad")]
+use crate::input::gamepad::GamepadAxis;
+#[cfg(feature = "gamepad")]
+use crate::input::gamepad::GamepadButton;
+use crate::input::Input;
+use crate::input::Key;
+use crate::input::MouseButton;
+
+/// # Binding
+///
+/// Button an action is bound to.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum Binding {
+    /// Keyboard key.
+    Key(Key),
+    /// Mouse button.
+    Mouse(MouseButton),
+    /// Button on any connected gamepad.
+    #[cfg(feature = "gamepad")]
+    Gamepad(GamepadButton),
+}
+
+impl Binding {
+    fn is_pressed(self, input: &Input) -> bool {
+        match self {
+            Binding::Key(key) => input.is_pressed(key),
+            Binding::Mouse(button) => input.is_mouse_pressed(button),
+            #[cfg(feature = "gamepad")]
+            Binding::Gamepad(button) => {
+                let gamepads = input.gamepads();
+                gamepads
+                    .iter()
+                    .any(|gamepad| gamepads.is_pressed(gamepad, button))
+            }
+        }
+    }
+
+    fn just_pressed(self, input: &Input) -> bool {
+        match self {
+            Binding::Key(key) => input.just_pressed(key),
+            Binding::Mouse(button) => input.mouse_just_pressed(button),
+            #[cfg(feature = "gamepad")]
+            Binding::Gamepad(button) => {
+                let gamepads = input.gamepads();
+                gamepads
+                    .iter()
+                    .any(|gamepad| gamepads.just_pressed(gamepad, button))
+            }
+        }
+    }
+
+    fn just_released(self, input: &Input) -> bool {
+        match self {
+            Binding::Key(key) => input.just_released(key),
+            Binding::Mouse(button) => input.mouse_just_released(button),
+            #[cfg(feature = "gamepad")]
+            Binding::Gamepad(button) => {
+                let gamepads = input.gamepads();
+                gamepads
+                    .iter()
+                    .any(|gamepad| gamepads.just_released(gamepad, button))
+            }
+        }
+    }
+
+    fn value(self, input: &Input) -> f32 {
+        #[cfg(feature = "gamepad")]
+        if let Binding::Gamepad(button) = self {
+            let gamepads = input.gamepads();
+            return gamepads
+                .iter()
+                .filter_map(|gamepad| gamepads.get(gamepad))
+                .map(|state| state.button_value(button))
+                .fold(0.0, f32::max);
+        }
+
+        f32::from(u8::from(self.is_pressed(input)))
+    }
+}
+
+impl From<Key> for Binding {
+    fn from(key: Key) -> Self {
+        Binding::Key(key)
+    }
+}
+
+impl From<MouseButton> for Binding {
+    fn from(button: MouseButton) -> Self {
+        Binding::Mouse(button)
+    }
+}
+
+#[cfg(feature = "gamepad")]
+impl From<GamepadButton> for Binding {
+    fn from(button: GamepadButton) -> Self {
+        Binding::Gamepad(button)
+    }
+}
+
+/// # Axis Binding
+///
+/// Input an axis is bound to, ranging from -1 to 1.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum AxisBinding {
+    /// Pair of buttons for the negative and positive direction, e.g. A and D for moving sideways.
+    Buttons {
+        /// Button for the negative direction.
+        negative: Binding,
+        /// Button for the positive direction.
+        positive: Binding,
+    },
+    /// Axis on any connected gamepad, with the dead zone applied.
+    #[cfg(feature = "gamepad")]
+    Gamepad(GamepadAxis),
+}
+
+impl AxisBinding {
+    /// Returns the binding for the pair of buttons.
+    pub fn buttons(negative: impl Into<Binding>, positive: impl Into<Binding>) -> Self {
+        AxisBinding::Buttons {
+            negative: negative.into(),
+            positive: positive.into(),
+        }
+    }
+
+    fn value(self, input: &Input) -> f32 {
+        match self {
+            AxisBinding::Buttons { negative, positive } => {
+                positive.value(input) - negative.value(input)
+            }
+            #[cfg(feature = "gamepad")]
+            AxisBinding::Gamepad(axis) => {
+                let gamepads = input.gamepads();
+                gamepads
+                    .iter()
+                    .map(|gamepad| gamepads.axis(gamepad, axis))
+                    .sum()
+            }
+        }
+    }
+}
+
+#[cfg(feature = "gamepad")]
+impl From<GamepadAxis> for AxisBinding {
+    fn from(axis: GamepadAxis) -> Self {
+        AxisBinding::Gamepad(axis)
+    }
+}
+
+/// # Input Map
+///
+/// Bindings of named actions and axes. The bindings are queried against the [Input] state through
+/// [InputMap::actions].
+///
+/// ```
+/// # use pulse::input::map::AxisBinding;
+/// # use pulse::input::map::InputMap;
+/// # use pulse::input::Input;
+/// # use pulse::input::Key;
+/// # use pulse::Event;
+/// let mut map = InputMap::new();
+/// map.bind("jump", Key::Space)
+///     .bind_axis("move_x", AxisBinding::buttons(Key::A, Key::D));
+///
+/// let mut input = Input::new();
+/// input.handle_event(&Event::KeyPressed {
+///     key: Key::D,
+///     repeat: false,
+/// });
+///
+/// let actions = map.actions(&input);
+/// assert!(!actions.pressed("jump"));
+/// assert_eq!(actions.axis("move_x"), 1.0);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct InputMap {
+    actions: BTreeMap<String, Vec<Binding>>,
+    axes: BTreeMap<String, Vec<AxisBinding>>,
+}
+
+impl InputMap {
+    /// Returns a map without any bindings.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Binds the action to the button in addition to its existing bindings.
+    pub fn bind(&mut self, action: &str, binding: impl Into<Binding>) -> &mut Self {
+        let binding = binding.into();
+        let bindings = self.actions.entry(action.to_string()).or_default();
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+
+        self
+    }
+
+    /// Removes the binding of the action to the button.
+    pub fn unbind(&mut self, action: &str, binding: impl Into<Binding>) -> &mut Self {
+        let binding = binding.into();
+        if let Some(bindings) = self.actions.get_mut(action) {
+            bindings.retain(|other| *other != binding);
+        }
+
+        self
+    }
+
+    /// Removes all of the bindings of the action.
+    pub fn clear_action(&mut self, action: &str) -> &mut Self {
+        self.actions.remove(action);
+        self
+    }
+
+    /// Returns the bindings of the action.
+    pub fn bindings(&self, action: &str) -> &[Binding] {
+        self.actions.get(action).map_or(&[], Vec::as_slice)
+    }
+
+    /// Binds the axis to the input in addition to its existing bindings.
+    pub fn bind_axis(&mut self, axis: &str, binding: impl Into<AxisBinding>) -> &mut Self {
+        let binding = binding.into();
+        let bindings = self.axes.entry(axis.to_string()).or_default();
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+
+        self
+    }
+
+    /// Removes the binding of the axis to the input.
+    pub fn unbind_axis(&mut self, axis: &str, binding: impl Into<AxisBinding>) -> &mut Self {
+        let binding = binding.into();
+        if let Some(bindings) = self.axes.get_mut(axis) {
+            bindings.retain(|other| *other != binding);
+        }
+
+        self
+    }
+
+    /// Removes all of the bindings of the axis.
+    pub fn clear_axis(&mut self, axis: &str) -> &mut Self {
+        self.axes.remove(axis);
+        self
+    }
+
+    /// Returns the bindings of the axis.
+    pub fn axis_bindings(&self, axis: &str) -> &[AxisBinding] {
+        self.axes.get(axis).map_or(&[], Vec::as_slice)
+    }
+
+    /// Returns the state of the actions and axes for the input.
+    pub fn actions<'a>(&'a self, input: &'a Input) -> Actions<'a> {
+        Actions { map: self, input }
+    }
+}
+
+/// # Actions
+///
+/// State of the actions and axes of an [InputMap] for the current [Input].
+#[derive(Copy, Clone, Debug)]
+pub struct Actions<'a> {
+    map: &'a InputMap,
+    input: &'a Input,
+}
+
+impl Actions<'_> {
+    /// Returns true if any of the action's buttons is pressed.
+    pub fn pressed(&self, action: &str) -> bool {
+        self.map
+            .bindings(action)
+            .iter()
+            .any(|binding| binding.is_pressed(self.input))
+    }
+
+    /// Returns true if any of the action's buttons was pressed during the current frame.
+    pub fn just_pressed(&self, action: &str) -> bool {
+        self.map
+            .bindings(action)
+            .iter()
+            .any(|binding| binding.just_pressed(self.input))
+    }
+
+    /// Returns true if one of the action's buttons was released during the current frame and none
+    /// of them is still pressed.
+    pub fn just_released(&self, action: &str) -> bool {
+        let bindings = self.map.bindings(action);
+        bindings
+            .iter()
+            .any(|binding| binding.just_released(self.input))
+            && !bindings
+                .iter()
+                .any(|binding| binding.is_pressed(self.input))
+    }
+
+    /// Returns the value of the axis from -1 to 1, combining all of its bindings, or zero if it has
+    /// none.
+    pub fn axis(&self, axis: &str) -> f32 {
+        self.map
+            .axis_bindings(axis)
+            .iter()
+            .map(|binding| binding.value(self.input))
+            .sum::<f32>()
+            .clamp(-1.0, 1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Event;
+
+    fn press(input: &mut Input, key: Key) {
+        input.handle_event(&Event::KeyPressed { key, repeat: false });
+    }
+
+    #[test]
+    fn action_pressed_by_any_binding() {
+        let mut map = InputMap::new();
+        map.bind("fire", Key::Enter).bind("fire", MouseButton::Left);
+        let mut input = Input::new();
+        input.handle_event(&Event::MouseButtonPressed {
+            button: MouseButton::Left,
+        });
+
+        let actions = map.actions(&input);
+
+        assert!(actions.pressed("fire"));
+        assert!(actions.just_pressed("fire"));
+        assert!(!actions.pressed("jump"));
+    }
+
+    #[test]
+    fn action_just_released_when_no_binding_pressed() {
+        let mut map = InputMap::new();
+        map.bind("jump", Key::Space).bind("jump", Key::W);
+        let mut input = Input::new();
+        press(&mut input, Key::Space);
+        press(&mut input, Key::W);
+        input.end_frame();
+
+        input.handle_event(&Event::KeyReleased { key: Key::Space });
+        assert!(!map.actions(&input).just_released("jump"));
+
+        input.handle_event(&Event::KeyReleased { key: Key::W });
+        assert!(map.actions(&input).just_released("jump"));
+    }
+
+    #[test]
+    fn unbind_rebinds_action() {
+        let mut map = InputMap::new();
+        map.bind("jump", Key::Space)
+            .unbind("jump", Key::Space)
+            .bind("jump", Key::J);
+        let mut input = Input::new();
+        press(&mut input, Key::Space);
+
+        assert_eq!(map.bindings("jump"), &[Binding::Key(Key::J)]);
+        assert!(!map.actions(&input).pressed("jump"));
+    }
+
+    #[test]
+    fn axis_combines_and_clamps_bindings() {
+        let mut map = InputMap::new();
+        map.bind_axis("move_x", AxisBinding::buttons(Key::A, Key::D))
+            .bind_axis(
+                "move_x",
+                AxisBinding::buttons(Key::ArrowLeft, Key::ArrowRight),
+            );
+        let mut input = Input::new();
+        press(&mut input, Key::D);
+        press(&mut input, Key::ArrowRight);
+
+        assert_eq!(map.actions(&input).axis("move_x"), 1.0);
+
+        press(&mut input, Key::A);
+        press(&mut input, Key::ArrowLeft);
+
+        assert_eq!(map.actions(&input).axis("move_x"), 0.0);
+        assert_eq!(map.actions(&input).axis("move_y"), 0.0);
+    }
+}
@@ -20,18 +20,101 @@
 //! - Asset management system
 //! - Mouse, keyboard, and gamepad input
 
+pub use pulse_derive::Bundle;
+pub use pulse_derive::Component;
+pub use pulse_derive::Reflect;
+pub use pulse_derive::Relationship;
+pub use pulse_derive::Tag;
+
 pub use crate::app::Application;
 pub use crate::app::ApplicationState;
 pub use crate::app::Event;
+pub use crate::app::Headless;
+pub use crate::components::Aabb;
+pub use crate::components::BoundingSphere;
+pub use crate::components::Camera;
 pub use crate::components::ComputedVisibility;
+pub use crate::components::Frustum;
+pub use crate::components::HierarchyBounds;
 pub use crate::components::LocalTransform;
+pub use crate::components::Name;
+pub use crate::components::PreviousWorldTransform;
+pub use crate::components::Projection;
+pub use crate::components::ScalingMode;
+pub use crate::components::Viewport;
 pub use crate::components::Visibility;
+pub use crate::components::VisibleNodes;
+pub use crate::components::WorldBounds;
+pub use crate::components::WorldTransform;
+pub use crate::error::Error;
+pub use crate::events::Events;
+pub use crate::plugin::AppBuilder;
+pub use crate::plugin::Plugin;
+pub use crate::reflect::Reflect;
+pub use crate::scene::access::ComponentWrites;
+pub use crate::scene::commands::CommandNode;
+pub use crate::scene::commands::Commands;
+pub use crate::scene::filter::Added;
+pub use crate::scene::filter::Changed;
+pub use crate::scene::filter::QueryFilter;
+pub use crate::scene::filter::With;
+pub use crate::scene::filter::Without;
+pub use crate::scene::hierarchy::Ancestors;
+pub use crate::scene::hierarchy::Descendants;
+pub use crate::scene::hierarchy::DescendantsBreadthFirst;
+pub use crate::scene::merge::NodeRemap;
+#[cfg(feature = "rayon")]
+pub use crate::scene::parallel::ParQuery;
+pub use crate::scene::prefab::Prefab;
+pub use crate::scene::prefab::PrefabOverrides;
+pub use crate::scene::relationship::Relationship;
+pub use crate::scene::snapshot::SceneSnapshot;
+pub use crate::scene::stats::ComponentStats;
+pub use crate::scene::stats::SceneStats;
+pub use crate::scene::stats::TagStats;
+#[cfg(any(debug_assertions, feature = "validate"))]
+pub use crate::scene::validate::ValidationError;
+#[cfg(any(debug_assertions, feature = "validate"))]
+pub use crate::scene::validate::ValidationReport;
+pub use crate::scene::Bundle;
 pub use crate::scene::Component;
 pub use crate::scene::ComponentEvent;
+pub use crate::scene::ComponentMut;
 pub use crate::scene::Node;
+pub use crate::scene::NodeEvent;
+pub use crate::scene::Query;
 pub use crate::scene::Scene;
+pub use crate::scene::Tag;
+pub use crate::state::States;
+pub use crate::time::FramePacing;
+pub use crate::time::FrameWait;
+pub use crate::time::Time;
+pub use crate::window::Cursor;
+pub use crate::window::CursorGrab;
+pub use crate::window::CursorIcon;
+pub use crate::window::Monitor;
+pub use crate::window::UserAttention;
+pub use crate::window::VideoMode;
+pub use crate::window::Window;
+pub use crate::window::WindowIcon;
+pub use crate::window::WindowMode;
+
+extern crate self as pulse;
 
+pub mod animation;
 mod app;
+pub mod assets;
 mod components;
+mod error;
+mod events;
+pub mod input;
+pub mod occlusion;
+mod plugin;
+mod reflect;
+pub mod render;
 mod scene;
+pub mod spatial;
+mod state;
 pub mod systems;
+mod time;
+mod window;
@@ -0,0 +1,298 @@
+//! # Occlusion
+//!
+//! Software occlusion culling. The meshes of the [Occluder]s in view of a camera are rasterized
+//! into a small depth buffer on the CPU, reduced to a pyramid of the farthest depth of each
+//! region, and the nodes whose bounds lie behind the occluders in every pixel they cover are
+//! removed from the camera's [crate::VisibleNodes] by [crate::systems::cull_frustum].
+
+use glam::BVec3;
+use glam::IVec2;
+use glam::Mat4;
+use glam::UVec2;
+use glam::Vec2;
+use glam::Vec3;
+use glam::Vec4Swizzles;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::render::mesh::Mesh;
+use crate::Aabb;
+use crate::Component;
+use crate::Node;
+use crate::Reflect;
+use crate::Scene;
+use crate::WorldBounds;
+use crate::WorldTransform;
+
+/// # Occluder
+///
+/// Component marking the node's [Mesh] as an occluder, hiding the nodes behind it from cameras.
+/// Good occluders are large, closed, and simple meshes such as walls, buildings, or terrain, as
+/// each of their triangles is rasterized for every camera. Occluders are rasterized without
+/// deformation by skins or morph targets.
+///
+/// ```
+/// # use pulse::occlusion::Occluder;
+/// # use pulse::render::mesh::Mesh;
+/// # use pulse::LocalTransform;
+/// # use pulse::Scene;
+/// let mut scene = Scene::new();
+/// scene.spawn_with((Mesh::cube(10.0), LocalTransform::IDENTITY, Occluder));
+/// ```
+#[derive(Copy, Clone, Debug, Default, PartialEq, Component)]
+pub struct Occluder;
+
+/// # Occlusion Settings
+///
+/// Scene resource configuring the depth buffer the [Occluder]s are rasterized into. Defaults are
+/// used if the scene has none. A larger buffer culls nodes more tightly at a higher cost.
+/// Coverage is sampled at the center of each pixel, so nodes hidden up to a pixel of the buffer
+/// behind the edges of an occluder are culled as well.
+///
+/// ```
+/// # use pulse::occlusion::OcclusionSettings;
+/// # use pulse::Scene;
+/// let mut scene = Scene::new();
+/// scene.insert_resource(OcclusionSettings {
+///     enabled: true,
+///     width: 512,
+///     height: 256,
+/// });
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq, Reflect, Serialize, Deserialize)]
+pub struct OcclusionSettings {
+    /// Whether nodes are culled by occluders.
+    pub enabled: bool,
+    /// Width of the depth buffer in pixels.
+    pub width: u32,
+    /// Height of the depth buffer in pixels.
+    pub height: u32,
+}
+
+impl Default for OcclusionSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            width: 256,
+            height: 128,
+        }
+    }
+}
+
+/// Removes the nodes hidden behind the visible [Occluder]s of the scene from the visible nodes of
+/// a camera with the view projection matrix.
+pub(crate) fn cull_occluded(scene: &Scene, view_projection: &Mat4, visible: &mut Vec<Node>) {
+    let settings = scene
+        .get_resource::<OcclusionSettings>()
+        .copied()
+        .unwrap_or_default();
+    if !settings.enabled {
+        return;
+    }
+    let occluders = visible
+        .iter()
+        .filter(|node| scene.get::<Occluder>(**node).is_some())
+        .filter_map(|node| {
+            Some((
+                scene.get::<Mesh>(*node)?,
+                scene.get::<WorldTransform>(*node)?,
+            ))
+        })
+        .collect::<Vec<_>>();
+    if occluders.is_empty() {
+        return;
+    }
+
+    let mut buffer = DepthBuffer::new(UVec2::new(settings.width, settings.height));
+    for (mesh, transform) in occluders {
+        let data = mesh.data();
+        let matrix = *view_projection * transform.matrix;
+        for triangle in data.indices.chunks_exact(3) {
+            let position = |index: u32| data.positions[index as usize];
+            buffer.rasterize(
+                &matrix,
+                [
+                    position(triangle[0]),
+                    position(triangle[1]),
+                    position(triangle[2]),
+                ],
+            );
+        }
+    }
+    let pyramid = buffer.into_pyramid();
+    visible.retain(|node| {
+        scene
+            .get::<WorldBounds>(*node)
+            .is_none_or(|bounds| !pyramid.occludes(view_projection, &bounds.aabb))
+    });
+}
+
+/// Depth buffer of the nearest occluder depth of each pixel, one at the far plane.
+#[derive(Clone, Debug)]
+struct DepthBuffer {
+    size: UVec2,
+    depths: Vec<f32>,
+}
+
+impl DepthBuffer {
+    fn new(size: UVec2) -> Self {
+        let size = size.max(UVec2::ONE);
+        Self {
+            size,
+            depths: vec![1.0; (size.x * size.y) as usize],
+        }
+    }
+
+    /// Rasterizes the triangle transformed to clip coordinates by the matrix, keeping the nearer
+    /// depth in each pixel whose center it covers. Triangles crossing the near plane are skipped.
+    fn rasterize(&mut self, matrix: &Mat4, triangle: [Vec3; 3]) {
+        let mut points = [Vec3::ZERO; 3];
+        for (point, position) in points.iter_mut().zip(triangle) {
+            let clip = *matrix * position.extend(1.0);
+            if clip.w <= f32::EPSILON || clip.z < 0.0 {
+                return;
+            }
+            *point = self.screen(clip.xyz() / clip.w);
+        }
+        let [a, b, c] = points;
+        let area = edge(a, b, c);
+        if area.abs() <= f32::EPSILON {
+            return;
+        }
+
+        let min = a.min(b).min(c);
+        let max = a.max(b).max(c);
+        let start = min.truncate().floor().max(Vec2::ZERO).as_uvec2();
+        let end = (max.truncate().ceil().as_ivec2())
+            .min(self.size.as_ivec2())
+            .max(IVec2::ZERO)
+            .as_uvec2();
+        for y in start.y..end.y {
+            for x in start.x..end.x {
+                let center = Vec3::new(x as f32 + 0.5, y as f32 + 0.5, 0.0);
+                let weights =
+                    Vec3::new(edge(b, c, center), edge(c, a, center), edge(a, b, center)) / area;
+                if weights.min_element() < 0.0 {
+                    continue;
+                }
+                let depth = weights.dot(Vec3::new(a.z, b.z, c.z));
+                let pixel = &mut self.depths[(y * self.size.x + x) as usize];
+                *pixel = pixel.min(depth);
+            }
+        }
+    }
+
+    /// Returns the pixel coordinates and depth of the point in normalized device coordinates.
+    fn screen(&self, ndc: Vec3) -> Vec3 {
+        Vec3::new(
+            (ndc.x * 0.5 + 0.5) * self.size.x as f32,
+            (0.5 - ndc.y * 0.5) * self.size.y as f32,
+            ndc.z,
+        )
+    }
+
+    /// Returns the pyramid of the buffer, each level halving the size of the previous one and
+    /// keeping the farthest depth of the pixels it covers.
+    fn into_pyramid(self) -> DepthPyramid {
+        let mut levels = vec![self];
+        while levels.last().unwrap().size.max_element() > 1 {
+            let previous = levels.last().unwrap();
+            let size = (previous.size + 1) / 2;
+            let mut depths = Vec::with_capacity((size.x * size.y) as usize);
+            for y in 0..size.y {
+                for x in 0..size.x {
+                    let mut depth = 0.0_f32;
+                    for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                        let source = UVec2::new(x * 2 + dx, y * 2 + dy).min(previous.size - 1);
+                        depth = depth
+                            .max(previous.depths[(source.y * previous.size.x + source.x) as usize]);
+                    }
+                    depths.push(depth);
+                }
+            }
+            levels.push(DepthBuffer { size, depths });
+        }
+        DepthPyramid { levels }
+    }
+}
+
+/// Returns twice the signed area of the triangle of the points in pixel coordinates.
+fn edge(a: Vec3, b: Vec3, point: Vec3) -> f32 {
+    (b.x - a.x) * (point.y - a.y) - (b.y - a.y) * (point.x - a.x)
+}
+
+/// Levels of the farthest occluder depths of a depth buffer, from the full buffer to one pixel.
+#[derive(Clone, Debug)]
+struct DepthPyramid {
+    levels: Vec<DepthBuffer>,
+}
+
+impl DepthPyramid {
+    /// Returns true if the box is behind the occluders in every pixel it covers, expanded by a
+    /// pixel to each side. Boxes crossing the near plane are never occluded.
+    fn occludes(&self, view_projection: &Mat4, aabb: &Aabb) -> bool {
+        let base = &self.levels[0];
+        let mut min = Vec3::splat(f32::INFINITY);
+        let mut max = Vec3::splat(f32::NEG_INFINITY);
+        for corner in 0..8 {
+            let position = Vec3::select(
+                BVec3::new(corner & 1 != 0, corner & 2 != 0, corner & 4 != 0),
+                aabb.max,
+                aabb.min,
+            );
+            let clip = *view_projection * position.extend(1.0);
+            if clip.w <= f32::EPSILON || clip.z < 0.0 {
+                return false;
+            }
+            let point = base.screen(clip.xyz() / clip.w);
+            min = min.min(point);
+            max = max.max(point);
+        }
+
+        let last = base.size.as_ivec2() - 1;
+        let start = (min.truncate().floor().as_ivec2() - 1).clamp(IVec2::ZERO, last);
+        let end = (max.truncate().floor().as_ivec2() + 1).clamp(IVec2::ZERO, last);
+        let extent = (end - start + 1).max_element() as u32;
+        let level = (u32::BITS - extent.leading_zeros())
+            .saturating_sub(2)
+            .min(self.levels.len() as u32 - 1);
+        let buffer = &self.levels[level as usize];
+        let (start, end) = (start.as_uvec2() >> level, end.as_uvec2() >> level);
+
+        let mut depth = 0.0_f32;
+        for y in start.y..=end.y {
+            for x in start.x..=end.x {
+                depth = depth.max(buffer.depths[(y * buffer.size.x + x) as usize]);
+            }
+        }
+        min.z > depth
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn depth_pyramid_occludes_boxes_behind_triangles() {
+        let view_projection = Mat4::perspective_rh(std::f32::consts::FRAC_PI_2, 1.0, 0.1, 100.0);
+        let mut buffer = DepthBuffer::new(UVec2::new(64, 64));
+        let wall = [
+            Vec3::new(-2.0, -2.0, -4.0),
+            Vec3::new(2.0, -2.0, -4.0),
+            Vec3::new(2.0, 2.0, -4.0),
+            Vec3::new(-2.0, 2.0, -4.0),
+        ];
+        buffer.rasterize(&view_projection, [wall[0], wall[1], wall[2]]);
+        buffer.rasterize(&view_projection, [wall[0], wall[2], wall[3]]);
+        let pyramid = buffer.into_pyramid();
+        let aabb = |center: Vec3| Aabb::from_center_half_extents(center, Vec3::splat(0.5));
+
+        assert!(pyramid.occludes(&view_projection, &aabb(Vec3::new(0.0, 0.0, -10.0))));
+        assert!(pyramid.occludes(&view_projection, &aabb(Vec3::new(2.0, 0.0, -10.0))));
+        // Boxes in front of the wall, peeking past its edge, or crossing the near plane.
+        assert!(!pyramid.occludes(&view_projection, &aabb(Vec3::new(0.0, 0.0, -2.0))));
+        assert!(!pyramid.occludes(&view_projection, &aabb(Vec3::new(5.0, 0.0, -10.0))));
+        assert!(!pyramid.occludes(&view_projection, &aabb(Vec3::ZERO)));
+    }
+}
@@ -0,0 +1,364 @@
+use std::any::Any;
+use std::any::TypeId;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::state::SharedStateHooks;
+use crate::state::StateHooks;
+use crate::systems::IntoSystem;
+use crate::systems::Stage;
+use crate::systems::Stages;
+use crate::systems::SystemConfig;
+use crate::Error;
+use crate::Event;
+use crate::Events;
+use crate::Scene;
+use crate::States;
+
+type SceneHook = Box<dyn FnMut(&mut Scene)>;
+type StartupHook = Box<dyn FnOnce(&mut Scene)>;
+type EventHandler = Box<dyn FnMut(&mut Scene, &Event)>;
+
+/// # Plugin
+///
+/// Opt-in engine feature, e.g. a renderer or physics integration, that adds its systems,
+/// resources, and event handling to an application through [AppBuilder::add_plugin].
+///
+/// ```
+/// # use pulse::AppBuilder;
+/// # use pulse::Plugin;
+/// # use pulse::Scene;
+/// # use pulse::systems::Stage;
+/// struct Score(u32);
+///
+/// struct ScorePlugin;
+///
+/// impl Plugin for ScorePlugin {
+///     fn build(&self, app: &mut AppBuilder) {
+///         app.insert_resource(Score(0));
+///         app.add_system(Stage::Update, |scene: &mut Scene| {
+///             scene.get_resource_mut::<Score>().unwrap().0 += 1;
+///         });
+///     }
+/// }
+///
+/// let mut app = AppBuilder::new();
+/// app.add_plugin(ScorePlugin).add_plugin(ScorePlugin);
+/// assert!(app.has_plugin::<ScorePlugin>());
+/// ```
+pub trait Plugin: 'static {
+    /// Adds the plugin's systems, resources, and hooks to the application.
+    fn build(&self, app: &mut AppBuilder);
+}
+
+/// # App Builder
+///
+/// Systems, resources, event types, and hooks collected from an [crate::Application] and its
+/// [Plugin]s before the application runs. See [crate::Application::build].
+pub struct AppBuilder {
+    stages: Stages,
+    plugins: Vec<TypeId>,
+    startup: Vec<StartupHook>,
+    event_handlers: Vec<EventHandler>,
+    frame_end: Vec<SceneHook>,
+    states: Vec<(TypeId, Box<dyn Any>)>,
+}
+
+impl AppBuilder {
+    /// Returns a builder with the built-in systems of [Stages::with_builtin_systems].
+    pub fn new() -> Self {
+        Self {
+            stages: Stages::with_builtin_systems(),
+            plugins: Vec::new(),
+            startup: Vec::new(),
+            event_handlers: Vec::new(),
+            frame_end: Vec::new(),
+            states: Vec::new(),
+        }
+    }
+
+    /// Adds the plugin to the application. Does nothing if a plugin of the same type was already
+    /// added, so plugins can add the plugins they depend on.
+    pub fn add_plugin<P: Plugin>(&mut self, plugin: P) -> &mut Self {
+        if !self.has_plugin::<P>() {
+            self.plugins.push(TypeId::of::<P>());
+            plugin.build(self);
+        }
+
+        self
+    }
+
+    /// Returns true if a plugin of the type was added.
+    pub fn has_plugin<P: Plugin>(&self) -> bool {
+        self.plugins.contains(&TypeId::of::<P>())
+    }
+
+    /// Returns a mutable reference to the stages run on the scene every frame.
+    pub fn stages_mut(&mut self) -> &mut Stages {
+        &mut self.stages
+    }
+
+    /// Adds the system to the stage and returns its config for ordering.
+    pub fn add_system<M>(&mut self, stage: Stage, system: impl IntoSystem<M>) -> SystemConfig<'_> {
+        self.stages.add_system(stage, system)
+    }
+
+    /// Inserts the resource into the scene when the application starts, replacing any resource
+    /// of the same type.
+    pub fn insert_resource<T: 'static + Send + Sync>(&mut self, value: T) -> &mut Self {
+        self.add_startup(move |scene| {
+            scene.insert_resource(value);
+        })
+    }
+
+    /// Registers the event type by inserting an empty [Events] resource when the application
+    /// starts and clearing the events at the end of every frame.
+    pub fn add_event<T: 'static + Send + Sync>(&mut self) -> &mut Self {
+        self.add_startup(|scene| {
+            if !scene.contains_resource::<Events<T>>() {
+                scene.insert_resource(Events::<T>::new());
+            }
+        });
+        self.add_frame_end(|scene| {
+            if let Some(events) = scene.get_resource_mut::<Events<T>>() {
+                events.clear();
+            }
+        })
+    }
+
+    /// Adds the function to be called once with the scene when the application starts, after the
+    /// window was created and before the first frame.
+    pub fn add_startup(&mut self, startup: impl 'static + FnOnce(&mut Scene)) -> &mut Self {
+        self.startup.push(Box::new(startup));
+        self
+    }
+
+    /// Adds the function to be called with the scene for every event before it's passed to
+    /// [crate::Application::handle_event].
+    pub fn add_event_handler(
+        &mut self,
+        handler: impl 'static + FnMut(&mut Scene, &Event),
+    ) -> &mut Self {
+        self.event_handlers.push(Box::new(handler));
+        self
+    }
+
+    /// Adds the function to be called with the scene at the end of every frame, after the
+    /// scene's systems were run.
+    pub fn add_frame_end(&mut self, frame_end: impl 'static + FnMut(&mut Scene)) -> &mut Self {
+        self.frame_end.push(Box::new(frame_end));
+        self
+    }
+
+    /// Registers the state type by inserting a [States] resource with the initial state when the
+    /// application starts, and applying the transitions requested through it at the end of every
+    /// frame. Does nothing if the state type was already added.
+    pub fn add_state<S: 'static + Clone + PartialEq + Send + Sync>(
+        &mut self,
+        initial: S,
+    ) -> &mut Self {
+        if self.state_hooks::<S>().is_some() {
+            return self;
+        }
+
+        let hooks: SharedStateHooks<S> = Rc::new(RefCell::new(StateHooks::new()));
+        self.states
+            .push((TypeId::of::<S>(), Box::new(Rc::clone(&hooks))));
+
+        let startup_hooks = Rc::clone(&hooks);
+        self.add_startup(move |scene| {
+            scene.insert_resource(States::new(initial));
+            startup_hooks.borrow_mut().apply_transitions(scene);
+        });
+        self.add_frame_end(move |scene| hooks.borrow_mut().apply_transitions(scene))
+    }
+
+    /// Adds the function to be called with the scene when the state is pushed onto the stack.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the state type wasn't added with [AppBuilder::add_state].
+    pub fn on_enter<S: 'static + Clone + PartialEq + Send + Sync>(
+        &mut self,
+        state: S,
+        hook: impl 'static + FnMut(&mut Scene),
+    ) -> &mut Self {
+        self.expect_state_hooks::<S>()
+            .borrow_mut()
+            .enter
+            .push((state, Box::new(hook)));
+        self
+    }
+
+    /// Adds the function to be called with the scene when the state is popped off the stack.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the state type wasn't added with [AppBuilder::add_state].
+    pub fn on_exit<S: 'static + Clone + PartialEq + Send + Sync>(
+        &mut self,
+        state: S,
+        hook: impl 'static + FnMut(&mut Scene),
+    ) -> &mut Self {
+        self.expect_state_hooks::<S>()
+            .borrow_mut()
+            .exit
+            .push((state, Box::new(hook)));
+        self
+    }
+
+    /// Adds the function to be called with the scene when another state is pushed on top of the
+    /// state.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the state type wasn't added with [AppBuilder::add_state].
+    pub fn on_pause<S: 'static + Clone + PartialEq + Send + Sync>(
+        &mut self,
+        state: S,
+        hook: impl 'static + FnMut(&mut Scene),
+    ) -> &mut Self {
+        self.expect_state_hooks::<S>()
+            .borrow_mut()
+            .pause
+            .push((state, Box::new(hook)));
+        self
+    }
+
+    /// Adds the function to be called with the scene when the state on top of the state is
+    /// popped.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the state type wasn't added with [AppBuilder::add_state].
+    pub fn on_resume<S: 'static + Clone + PartialEq + Send + Sync>(
+        &mut self,
+        state: S,
+        hook: impl 'static + FnMut(&mut Scene),
+    ) -> &mut Self {
+        self.expect_state_hooks::<S>()
+            .borrow_mut()
+            .resume
+            .push((state, Box::new(hook)));
+        self
+    }
+
+    fn state_hooks<S: 'static>(&self) -> Option<&SharedStateHooks<S>> {
+        self.states
+            .iter()
+            .find(|(id, _)| *id == TypeId::of::<S>())
+            .and_then(|(_, hooks)| hooks.downcast_ref())
+    }
+
+    fn expect_state_hooks<S: 'static>(&self) -> &SharedStateHooks<S> {
+        self.state_hooks::<S>()
+            .expect("state type must be added with AppBuilder::add_state")
+    }
+
+    /// Builds the stages, returning an error if the systems can't be ordered.
+    pub(crate) fn build(&mut self) -> Result<(), Error> {
+        self.stages.build()?;
+        Ok(())
+    }
+
+    /// Calls the startup functions.
+    pub(crate) fn run_startup(&mut self, scene: &mut Scene) {
+        for startup in self.startup.drain(..) {
+            startup(scene);
+        }
+    }
+
+    /// Calls the event handlers with the event.
+    pub(crate) fn handle_event(&mut self, scene: &mut Scene, event: &Event) {
+        for handler in &mut self.event_handlers {
+            handler(scene, event);
+        }
+    }
+
+    /// Calls the frame end functions.
+    pub(crate) fn end_frame(&mut self, scene: &mut Scene) {
+        for frame_end in &mut self.frame_end {
+            frame_end(scene);
+        }
+    }
+}
+
+impl Default for AppBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Counter;
+
+    impl Plugin for Counter {
+        fn build(&self, app: &mut AppBuilder) {
+            app.insert_resource(0u32).add_startup(|scene| {
+                *scene.get_resource_mut::<u32>().unwrap() += 1;
+            });
+        }
+    }
+
+    #[test]
+    fn add_plugin_builds_each_plugin_once() {
+        let mut app = AppBuilder::new();
+        app.add_plugin(Counter).add_plugin(Counter);
+        let mut scene = Scene::new();
+
+        app.run_startup(&mut scene);
+
+        assert_eq!(scene.get_resource::<u32>(), Some(&1));
+    }
+
+    #[test]
+    fn add_state_applies_transitions_at_frame_end() {
+        #[derive(Clone, Debug, PartialEq)]
+        enum GameState {
+            Menu,
+            Game,
+        }
+
+        let mut app = AppBuilder::new();
+        app.insert_resource(0u32)
+            .add_state(GameState::Menu)
+            .on_exit(GameState::Menu, |scene| {
+                *scene.get_resource_mut::<u32>().unwrap() += 1;
+            })
+            .on_enter(GameState::Game, |scene| {
+                *scene.get_resource_mut::<u32>().unwrap() += 10;
+            });
+        let mut scene = Scene::new();
+        app.run_startup(&mut scene);
+
+        let states = scene.get_resource_mut::<States<GameState>>().unwrap();
+        assert!(states.is_current(&GameState::Menu));
+        states.set(GameState::Game);
+        assert!(states.is_current(&GameState::Menu));
+
+        app.end_frame(&mut scene);
+
+        let states = scene.get_resource::<States<GameState>>().unwrap();
+        assert!(states.is_current(&GameState::Game));
+        assert_eq!(scene.get_resource::<u32>(), Some(&11));
+    }
+
+    #[test]
+    fn add_event_clears_events_at_frame_end() {
+        let mut app = AppBuilder::new();
+        app.add_event::<&'static str>();
+        let mut scene = Scene::new();
+        app.run_startup(&mut scene);
+
+        let events = scene.get_resource_mut::<Events<&str>>().unwrap();
+        events.send("hit");
+        assert_eq!(events.len(), 1);
+
+        app.end_frame(&mut scene);
+
+        assert!(scene.get_resource::<Events<&str>>().unwrap().is_empty());
+    }
+}
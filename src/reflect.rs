@@ -0,0 +1,208 @@
+use std::any::Any;
+
+use glam::Mat4;
+use glam::Quat;
+use glam::Vec2;
+use glam::Vec3;
+use glam::Vec4;
+
+/// # Reflect
+///
+/// Runtime access to the fields of a value by name. Implement with `#[derive(Reflect)]`, which
+/// exposes named struct fields by name, tuple struct fields by index (`"0"`, `"1"`, ...), and
+/// treats enums as opaque values.
+pub trait Reflect: Any {
+    /// Returns the name of the value's type.
+    fn type_name(&self) -> &'static str;
+
+    /// Returns the names of the value's fields.
+    fn field_names(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    /// Returns the field with the given name.
+    fn field(&self, _name: &str) -> Option<&dyn Reflect> {
+        None
+    }
+
+    /// Returns a mutable reference to the field with the given name.
+    fn field_mut(&mut self, _name: &str) -> Option<&mut dyn Reflect> {
+        None
+    }
+
+    /// Replaces the value with a copy of the given value. Returns false if the given value has a
+    /// different type.
+    fn apply(&mut self, value: &dyn Reflect) -> bool;
+
+    /// Returns the value as [Any].
+    fn as_any(&self) -> &dyn Any;
+
+    /// Returns the value as mutable [Any].
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl dyn Reflect {
+    /// Returns the value at the given dot-separated field path, e.g. `"position.x"`. An empty path
+    /// returns the value itself.
+    pub fn path(&self, path: &str) -> Option<&dyn Reflect> {
+        path.split('.')
+            .filter(|name| !name.is_empty())
+            .try_fold(self, |value, name| value.field(name))
+    }
+
+    /// Returns a mutable reference to the value at the given dot-separated field path.
+    pub fn path_mut(&mut self, path: &str) -> Option<&mut dyn Reflect> {
+        path.split('.')
+            .filter(|name| !name.is_empty())
+            .try_fold(self, |value, name| value.field_mut(name))
+    }
+
+    /// Returns the value as the given type.
+    pub fn downcast_ref<T: Reflect>(&self) -> Option<&T> {
+        self.as_any().downcast_ref()
+    }
+
+    /// Returns the value as the given mutable type.
+    pub fn downcast_mut<T: Reflect>(&mut self) -> Option<&mut T> {
+        self.as_any_mut().downcast_mut()
+    }
+}
+
+macro_rules! impl_reflect_value {
+    ($($ty:ty),*) => {
+        $(
+            impl Reflect for $ty {
+                fn type_name(&self) -> &'static str {
+                    std::any::type_name::<Self>()
+                }
+
+                fn apply(&mut self, value: &dyn Reflect) -> bool {
+                    match value.as_any().downcast_ref::<Self>() {
+                        Some(value) => {
+                            *self = value.clone();
+                            true
+                        }
+                        None => false,
+                    }
+                }
+
+                fn as_any(&self) -> &dyn Any {
+                    self
+                }
+
+                fn as_any_mut(&mut self) -> &mut dyn Any {
+                    self
+                }
+            }
+        )*
+    };
+}
+
+impl_reflect_value!(
+    bool, char, u8, u16, u32, u64, usize, i8, i16, i32, i64, isize, f32, f64, String, Mat4
+);
+
+macro_rules! impl_reflect_vector {
+    ($ty:ty, $($field:ident),*) => {
+        impl Reflect for $ty {
+            fn type_name(&self) -> &'static str {
+                std::any::type_name::<Self>()
+            }
+
+            fn field_names(&self) -> &'static [&'static str] {
+                &[$(stringify!($field)),*]
+            }
+
+            fn field(&self, name: &str) -> Option<&dyn Reflect> {
+                match name {
+                    $(stringify!($field) => Some(&self.$field),)*
+                    _ => None,
+                }
+            }
+
+            fn field_mut(&mut self, name: &str) -> Option<&mut dyn Reflect> {
+                match name {
+                    $(stringify!($field) => Some(&mut self.$field),)*
+                    _ => None,
+                }
+            }
+
+            fn apply(&mut self, value: &dyn Reflect) -> bool {
+                match value.as_any().downcast_ref::<Self>() {
+                    Some(value) => {
+                        *self = *value;
+                        true
+                    }
+                    None => false,
+                }
+            }
+
+            fn as_any(&self) -> &dyn Any {
+                self
+            }
+
+            fn as_any_mut(&mut self) -> &mut dyn Any {
+                self
+            }
+        }
+    };
+}
+
+impl_reflect_vector!(Vec2, x, y);
+impl_reflect_vector!(Vec3, x, y, z);
+impl_reflect_vector!(Vec4, x, y, z, w);
+impl_reflect_vector!(Quat, x, y, z, w);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LocalTransform;
+    use crate::Name;
+    use crate::Visibility;
+
+    #[test]
+    fn path_returns_nested_field() {
+        let transform = LocalTransform::from_position(Vec3::new(1.0, 2.0, 3.0));
+        let value: &dyn Reflect = &transform;
+
+        assert_eq!(
+            value
+                .path("position.y")
+                .and_then(|y| y.downcast_ref::<f32>()),
+            Some(&2.0)
+        );
+        assert!(value.path("position.nope").is_none());
+    }
+
+    #[test]
+    fn path_mut_modifies_nested_field() {
+        let mut transform = LocalTransform::IDENTITY;
+        let value: &mut dyn Reflect = &mut transform;
+
+        *value
+            .path_mut("scale.x")
+            .and_then(|x| x.downcast_mut::<f32>())
+            .unwrap() = 2.0;
+
+        assert_eq!(transform.scale, Vec3::new(2.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn field_names_returns_struct_fields() {
+        assert_eq!(
+            LocalTransform::IDENTITY.field_names(),
+            ["position", "rotation", "scale"]
+        );
+        assert_eq!(Name::new("Player").field_names(), ["0"]);
+        assert!(Visibility::Visible.field_names().is_empty());
+    }
+
+    #[test]
+    fn apply_replaces_value_of_same_type() {
+        let mut visibility = Visibility::Inherit;
+
+        assert!(visibility.apply(&Visibility::Invisible));
+        assert!(!visibility.apply(&17u32));
+        assert_eq!(visibility, Visibility::Invisible);
+    }
+}
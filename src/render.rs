@@ -0,0 +1,731 @@
+//! # Render
+//!
+//! Rendering of the scene to the application window with [wgpu]. The application runner creates
+//! the [Renderer] after the window and inserts it into the scene as a resource before
+//! [crate::Application::startup], so GPU resources can be created against the window's surface.
+
+use std::fmt;
+use std::sync::Arc;
+
+use glam::UVec2;
+use glam::Vec4;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::render::capture::Captures;
+use crate::render::capture::FrameCapture;
+use crate::render::graph::GpuResources;
+use crate::render::graph::RenderContext;
+use crate::render::graph::RenderGraph;
+use crate::render::image::Image;
+use crate::render::image::COMPRESSION_FEATURES;
+use crate::render::stats::FrameProfiler;
+use crate::render::stats::RenderStats;
+use crate::render::stats::TIMESTAMP_FEATURES;
+use crate::render::target::camera_targets;
+use crate::Reflect;
+use crate::Scene;
+
+pub mod anti_aliasing;
+pub mod atmosphere;
+pub mod bloom;
+pub mod capture;
+pub mod cluster;
+pub mod compute;
+pub mod debug;
+pub mod deferred;
+pub mod environment;
+pub mod fog;
+pub mod graph;
+pub mod image;
+pub mod light;
+pub mod material;
+pub mod mesh;
+pub mod morph;
+pub mod prepass;
+pub mod probe;
+pub mod shader;
+pub mod shader_material;
+pub mod shadow;
+pub mod skin;
+pub mod skybox;
+pub mod sprite;
+pub mod stats;
+pub mod target;
+pub mod terrain;
+pub mod text;
+pub mod tonemap;
+
+mod forward;
+
+/// Format of the images created with [Image::render_target].
+const TARGET_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+
+/// Minimum alignment of dynamic uniform buffer offsets guaranteed by all devices.
+pub(crate) const UNIFORM_ALIGNMENT: wgpu::BufferAddress = 256;
+
+/// # Color
+///
+/// Color with linear RGB components and alpha.
+///
+/// ```
+/// # use pulse::render::Color;
+/// let color = Color::srgb(1.0, 0.5, 0.0);
+///
+/// assert_eq!(color.r, 1.0);
+/// assert!((color.g - 0.214).abs() < 1e-3);
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq, Reflect, Serialize, Deserialize)]
+pub struct Color {
+    /// Linear red component.
+    pub r: f32,
+    /// Linear green component.
+    pub g: f32,
+    /// Linear blue component.
+    pub b: f32,
+    /// Alpha component, from 0 for fully transparent to 1 for opaque.
+    pub a: f32,
+}
+
+impl Color {
+    /// Opaque black.
+    pub const BLACK: Self = Self::rgb(0.0, 0.0, 0.0);
+    /// Opaque white.
+    pub const WHITE: Self = Self::rgb(1.0, 1.0, 1.0);
+    /// Fully transparent black.
+    pub const TRANSPARENT: Self = Self::rgba(0.0, 0.0, 0.0, 0.0);
+
+    /// Returns the opaque color with the linear components.
+    pub const fn rgb(r: f32, g: f32, b: f32) -> Self {
+        Self::rgba(r, g, b, 1.0)
+    }
+
+    /// Returns the color with the linear components and alpha.
+    pub const fn rgba(r: f32, g: f32, b: f32, a: f32) -> Self {
+        Self { r, g, b, a }
+    }
+
+    /// Returns the opaque color with the sRGB components, e.g. picked in an image editor.
+    pub fn srgb(r: f32, g: f32, b: f32) -> Self {
+        Self::rgb(srgb_to_linear(r), srgb_to_linear(g), srgb_to_linear(b))
+    }
+
+    /// Returns the color as a vector of the linear components and alpha.
+    pub fn to_vec4(self) -> Vec4 {
+        Vec4::new(self.r, self.g, self.b, self.a)
+    }
+
+    pub(crate) fn to_wgpu(self) -> wgpu::Color {
+        wgpu::Color {
+            r: f64::from(self.r),
+            g: f64::from(self.g),
+            b: f64::from(self.b),
+            a: f64::from(self.a),
+        }
+    }
+}
+
+pub(crate) fn srgb_to_linear(component: f32) -> f32 {
+    if component <= 0.04045 {
+        component / 12.92
+    } else {
+        ((component + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// # Clear Color
+///
+/// Scene resource with the color the window is cleared to before the scene is rendered. Defaults
+/// to black if the scene has no clear color.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ClearColor(pub Color);
+
+impl Default for ClearColor {
+    fn default() -> Self {
+        Self(Color::BLACK)
+    }
+}
+
+/// # Present Mode
+///
+/// How the frames rendered to the window are presented, set with [Renderer::set_present_mode].
+/// Modes the surface doesn't support fall back to the closest supported one, ending with
+/// [PresentMode::Fifo] which all surfaces support.
+///
+/// ```
+/// # use pulse::render::PresentMode;
+/// # use pulse::render::Renderer;
+/// # use pulse::Scene;
+/// fn disable_vsync(scene: &mut Scene) {
+///     if let Some(renderer) = scene.get_resource_mut::<Renderer>() {
+///         renderer.set_present_mode(PresentMode::Immediate);
+///     }
+/// }
+/// ```
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Reflect, Serialize, Deserialize)]
+pub enum PresentMode {
+    /// Frames wait for the display's vertical blank in a queue, without tearing. Rendering is
+    /// blocked while the queue is full.
+    #[default]
+    Fifo,
+    /// Frames replace the one waiting for the vertical blank, without tearing and without
+    /// blocking rendering. Falls back to [PresentMode::Fifo].
+    Mailbox,
+    /// Frames are presented immediately, with the lowest latency but possible tearing. Falls back
+    /// to [PresentMode::Mailbox].
+    Immediate,
+}
+
+impl PresentMode {
+    /// Returns the mode or the one it falls back to that's in the supported modes.
+    fn resolve(self, supported: &[wgpu::PresentMode]) -> Self {
+        let fallback = match self {
+            Self::Fifo => return Self::Fifo,
+            Self::Mailbox => Self::Fifo,
+            Self::Immediate => Self::Mailbox,
+        };
+        if supported.contains(&self.to_wgpu()) {
+            self
+        } else {
+            fallback.resolve(supported)
+        }
+    }
+
+    fn to_wgpu(self) -> wgpu::PresentMode {
+        match self {
+            Self::Fifo => wgpu::PresentMode::Fifo,
+            Self::Mailbox => wgpu::PresentMode::Mailbox,
+            Self::Immediate => wgpu::PresentMode::Immediate,
+        }
+    }
+}
+
+/// # Render Error
+///
+/// Error returned when the [Renderer] can't be created.
+#[derive(Debug)]
+pub enum RenderError {
+    /// The window's surface couldn't be created.
+    Surface(wgpu::CreateSurfaceError),
+    /// No graphics adapter compatible with the window's surface was found.
+    NoAdapter,
+    /// The graphics device couldn't be created.
+    Device(wgpu::RequestDeviceError),
+}
+
+impl fmt::Display for RenderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Surface(error) => write!(f, "failed to create surface: {error}"),
+            Self::NoAdapter => write!(f, "no compatible graphics adapter found"),
+            Self::Device(error) => write!(f, "failed to create device: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for RenderError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Surface(error) => Some(error),
+            Self::NoAdapter => None,
+            Self::Device(error) => Some(error),
+        }
+    }
+}
+
+/// # Renderer
+///
+/// Scene resource owning the GPU device and the window's surface, which is presented with the
+/// rendered scene at the end of every frame.
+pub struct Renderer {
+    instance: wgpu::Instance,
+    window: Arc<winit::window::Window>,
+    surface: Option<wgpu::Surface<'static>>,
+    adapter: wgpu::Adapter,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    config: wgpu::SurfaceConfiguration,
+    /// Present mode requested with [Renderer::set_present_mode].
+    present_mode: PresentMode,
+    graph: RenderGraph,
+    resources: GpuResources,
+    profiler: FrameProfiler,
+    captures: Captures,
+}
+
+impl Renderer {
+    /// Returns the renderer for the window.
+    pub(crate) async fn new(window: Arc<winit::window::Window>) -> Result<Self, RenderError> {
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor::default());
+        let surface = instance
+            .create_surface(Arc::clone(&window))
+            .map_err(RenderError::Surface)?;
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::HighPerformance,
+                force_fallback_adapter: false,
+                compatible_surface: Some(&surface),
+            })
+            .await
+            .ok_or(RenderError::NoAdapter)?;
+        let (device, queue) = adapter
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    label: Some("pulse"),
+                    required_features: adapter.features()
+                        & (TIMESTAMP_FEATURES | COMPRESSION_FEATURES),
+                    required_limits: wgpu::Limits::downlevel_webgl2_defaults()
+                        .using_resolution(adapter.limits()),
+                    memory_hints: wgpu::MemoryHints::Performance,
+                },
+                None,
+            )
+            .await
+            .map_err(RenderError::Device)?;
+
+        let size = window.inner_size();
+        let capabilities = surface.get_capabilities(&adapter);
+        let format = capabilities
+            .formats
+            .iter()
+            .copied()
+            .find(wgpu::TextureFormat::is_srgb)
+            .unwrap_or(capabilities.formats[0]);
+        // Frames can only be captured if the surface's textures can be copied.
+        let usage = wgpu::TextureUsages::RENDER_ATTACHMENT
+            | (capabilities.usages & wgpu::TextureUsages::COPY_SRC);
+        let config = wgpu::SurfaceConfiguration {
+            usage,
+            format,
+            width: size.width.max(1),
+            height: size.height.max(1),
+            present_mode: wgpu::PresentMode::Fifo,
+            desired_maximum_frame_latency: 2,
+            alpha_mode: capabilities.alpha_modes[0],
+            view_formats: Vec::new(),
+        };
+        surface.configure(&device, &config);
+        let graph = RenderGraph::with_builtin_nodes(&device);
+        let profiler = FrameProfiler::new(&device);
+
+        Ok(Self {
+            instance,
+            window,
+            surface: Some(surface),
+            adapter,
+            device,
+            queue,
+            config,
+            present_mode: PresentMode::default(),
+            graph,
+            resources: GpuResources::default(),
+            profiler,
+            captures: Captures::default(),
+        })
+    }
+
+    /// Returns information about the graphics adapter, e.g. its name and backend.
+    pub fn adapter_info(&self) -> wgpu::AdapterInfo {
+        self.adapter.get_info()
+    }
+
+    /// Returns the GPU device for creating buffers, textures, and pipelines.
+    pub fn device(&self) -> &wgpu::Device {
+        &self.device
+    }
+
+    /// Returns the queue for writing to buffers and textures and submitting commands.
+    pub fn queue(&self) -> &wgpu::Queue {
+        &self.queue
+    }
+
+    /// Returns the format of the window's surface textures.
+    pub fn surface_format(&self) -> wgpu::TextureFormat {
+        self.config.format
+    }
+
+    /// Returns the size of the window's surface textures in physical pixels.
+    pub fn surface_size(&self) -> UVec2 {
+        UVec2::new(self.config.width, self.config.height)
+    }
+
+    /// Returns the mode the window's frames are presented with, the requested one or its fallback.
+    pub fn present_mode(&self) -> PresentMode {
+        match self.config.present_mode {
+            wgpu::PresentMode::Mailbox => PresentMode::Mailbox,
+            wgpu::PresentMode::Immediate => PresentMode::Immediate,
+            _ => PresentMode::Fifo,
+        }
+    }
+
+    /// Presents the window's frames with the mode, or the mode it falls back to if the surface
+    /// doesn't support it, and returns the mode used. The surface is reconfigured if the mode
+    /// changed, and the mode is kept when the surface is recreated.
+    pub fn set_present_mode(&mut self, mode: PresentMode) -> PresentMode {
+        self.present_mode = mode;
+        self.configure();
+        self.present_mode()
+    }
+
+    /// Returns the graph of render nodes run every frame.
+    pub fn graph(&self) -> &RenderGraph {
+        &self.graph
+    }
+
+    /// Returns the graph of render nodes run every frame, e.g. to add custom nodes.
+    pub fn graph_mut(&mut self) -> &mut RenderGraph {
+        &mut self.graph
+    }
+
+    /// Returns the statistics of the last rendered frame, also inserted into the scene as a
+    /// resource after every frame.
+    pub fn stats(&self) -> &RenderStats {
+        &self.profiler.stats
+    }
+
+    /// Returns a capture of the next frame rendered to the window, completed once the frame was
+    /// read back from the GPU.
+    pub fn capture_frame(&mut self) -> FrameCapture {
+        self.captures.request()
+    }
+
+    /// Reconfigures the surface for the window's new inner size in physical pixels. Does nothing
+    /// while the window is minimized to a zero size.
+    pub(crate) fn resize(&mut self, size: UVec2) {
+        if size.x == 0 || size.y == 0 {
+            return;
+        }
+
+        self.config.width = size.x;
+        self.config.height = size.y;
+        self.configure();
+    }
+
+    /// Drops the surface while the application is suspended, as the native window may be
+    /// destroyed.
+    pub(crate) fn suspend(&mut self) {
+        self.surface = None;
+    }
+
+    /// Recreates the surface after the application was resumed.
+    pub(crate) fn resume(&mut self) {
+        if self.surface.is_some() {
+            return;
+        }
+
+        match self.instance.create_surface(Arc::clone(&self.window)) {
+            Ok(surface) => {
+                self.surface = Some(surface);
+                self.configure();
+            }
+            Err(error) => println!("Failed to create surface: {error}"),
+        }
+    }
+
+    /// Configures the surface, presenting with the requested mode or its supported fallback.
+    fn configure(&mut self) {
+        if let Some(surface) = &self.surface {
+            let supported = surface.get_capabilities(&self.adapter).present_modes;
+            self.config.present_mode = self.present_mode.resolve(&supported).to_wgpu();
+            surface.configure(&self.device, &self.config);
+        }
+    }
+
+    /// Renders the scene to the window's surface and presents it.
+    pub(crate) fn render(&mut self, scene: &Scene) {
+        self.captures.poll(&self.device);
+        let Some(surface) = &self.surface else {
+            return;
+        };
+        let frame = match surface.get_current_texture() {
+            Ok(frame) => frame,
+            Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => {
+                self.configure();
+                return;
+            }
+            Err(wgpu::SurfaceError::Timeout) => return,
+            Err(error) => {
+                println!("Failed to acquire surface texture: {error}");
+                return;
+            }
+        };
+        let view = frame
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+
+        let window = FrameTarget {
+            view: &view,
+            format: self.config.format,
+            size: self.surface_size(),
+        };
+        render_frame(
+            &self.device,
+            &self.queue,
+            &mut self.graph,
+            &mut self.resources,
+            &mut self.profiler,
+            scene,
+            window,
+        );
+        self.captures
+            .copy(&self.device, &self.queue, &frame.texture);
+
+        self.window.pre_present_notify();
+        frame.present();
+    }
+}
+
+/// Texture the window's frame is rendered to.
+struct FrameTarget<'a> {
+    view: &'a wgpu::TextureView,
+    format: wgpu::TextureFormat,
+    size: UVec2,
+}
+
+/// Runs the graph for every render target with the cameras rendering to it, the offscreen images
+/// first and the window last. Every target's commands are submitted separately, as the nodes write
+/// their buffers for the target they render. The joint palettes and morph
+/// weights are written once for all targets.
+fn render_frame(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    graph: &mut RenderGraph,
+    resources: &mut GpuResources,
+    profiler: &mut FrameProfiler,
+    scene: &Scene,
+    window: FrameTarget,
+) {
+    let targets = camera_targets(scene);
+    let keys = targets
+        .iter()
+        .map(|(image, _)| image.as_ref().map(Image::id))
+        .collect::<Vec<_>>();
+    resources.retain_targets(&keys);
+    resources.skins.write(device, queue, scene);
+    resources.morphs.write(device, queue, scene);
+    profiler.begin_frame(device);
+
+    for ((image, cameras), key) in targets.iter().zip(keys) {
+        resources.select_target(key);
+        let offscreen = image.as_ref().map(|image| {
+            resources.images.upload(device, queue, image);
+            let view = resources.images.target_view(image).unwrap();
+            (view, image.size())
+        });
+        let (target, target_format, target_size) = match &offscreen {
+            Some((view, size)) => (view, TARGET_FORMAT, *size),
+            None => (window.view, window.format, window.size),
+        };
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("frame"),
+        });
+        let mut context = RenderContext {
+            device,
+            queue,
+            encoder: &mut encoder,
+            target,
+            target_format,
+            target_size,
+            cameras,
+            resources,
+            profiler,
+        };
+        graph.run(&mut context, scene);
+        queue.submit([encoder.finish()]);
+    }
+    profiler.finish_frame(device, queue);
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::systems::Schedule;
+
+    /// Returns a device of any available adapter, e.g. a software rasterizer, or `None` if there's
+    /// none so GPU tests can be skipped.
+    pub(crate) fn device() -> Option<(wgpu::Device, wgpu::Queue)> {
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor::default());
+        let adapter =
+            pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions::default()))?;
+        let descriptor = wgpu::DeviceDescriptor {
+            required_features: adapter.features() & (TIMESTAMP_FEATURES | COMPRESSION_FEATURES),
+            ..wgpu::DeviceDescriptor::default()
+        };
+        pollster::block_on(adapter.request_device(&descriptor, None)).ok()
+    }
+
+    /// Returns a texture with the format and size that can be rendered to and read back.
+    pub(crate) fn target(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        size: UVec2,
+    ) -> wgpu::Texture {
+        device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("target"),
+            size: wgpu::Extent3d {
+                width: size.x,
+                height: size.y,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        })
+    }
+
+    /// Renders the scene with the built-in render nodes and returns the 64x64 RGBA pixels, or
+    /// `None` if there's no adapter.
+    pub(crate) fn render(scene: &Scene) -> Option<Vec<u8>> {
+        render_frames(scene, 1)
+    }
+
+    /// Renders the frames of the scene with the same render nodes and returns the 64x64 RGBA
+    /// pixels of the last one, or `None` if there's no adapter.
+    pub(crate) fn render_frames(scene: &Scene, frames: usize) -> Option<Vec<u8>> {
+        render_with(scene, frames, |_, _| {})
+    }
+
+    /// Renders the frames of the scene with the built-in render nodes changed by the function and
+    /// returns the 64x64 RGBA pixels of the last one, or `None` if there's no adapter.
+    pub(crate) fn render_with(
+        scene: &Scene,
+        frames: usize,
+        configure: impl FnOnce(&wgpu::Device, &mut RenderGraph),
+    ) -> Option<Vec<u8>> {
+        let (device, queue) = device()?;
+        let size = UVec2::new(64, 64);
+        let format = wgpu::TextureFormat::Rgba8Unorm;
+        let target = target(&device, format, size);
+        let view = target.create_view(&wgpu::TextureViewDescriptor::default());
+        let mut graph = RenderGraph::with_builtin_nodes(&device);
+        configure(&device, &mut graph);
+        let mut resources = GpuResources::default();
+        let mut profiler = FrameProfiler::new(&device);
+
+        for _ in 0..frames {
+            let window = FrameTarget {
+                view: &view,
+                format,
+                size,
+            };
+            render_frame(
+                &device,
+                &queue,
+                &mut graph,
+                &mut resources,
+                &mut profiler,
+                scene,
+                window,
+            );
+        }
+        Some(read(&device, &queue, &target))
+    }
+
+    /// Renders the frames of the scene with the built-in render nodes, running the built-in systems
+    /// on the scene before each frame, and returns the 64x64 RGBA pixels of the last one, or
+    /// `None` if there's no adapter.
+    pub(crate) fn render_updated(scene: &mut Scene, frames: usize) -> Option<Vec<u8>> {
+        let (device, queue) = device()?;
+        let size = UVec2::new(64, 64);
+        let format = wgpu::TextureFormat::Rgba8Unorm;
+        let target = target(&device, format, size);
+        let view = target.create_view(&wgpu::TextureViewDescriptor::default());
+        let mut graph = RenderGraph::with_builtin_nodes(&device);
+        let mut resources = GpuResources::default();
+        let mut profiler = FrameProfiler::new(&device);
+        let mut schedule = Schedule::with_builtin_systems();
+
+        for _ in 0..frames {
+            schedule.run(scene);
+            let window = FrameTarget {
+                view: &view,
+                format,
+                size,
+            };
+            render_frame(
+                &device,
+                &queue,
+                &mut graph,
+                &mut resources,
+                &mut profiler,
+                scene,
+                window,
+            );
+        }
+        Some(read(&device, &queue, &target))
+    }
+
+    /// Returns the bytes of the texture's pixels row by row. The texture's rows must be a multiple
+    /// of 256 bytes.
+    pub(crate) fn read(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        texture: &wgpu::Texture,
+    ) -> Vec<u8> {
+        let size = texture.size();
+        let bytes_per_row = size.width * texture.format().block_copy_size(None).unwrap();
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("readback"),
+            size: u64::from(bytes_per_row * size.height),
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        encoder.copy_texture_to_buffer(
+            texture.as_image_copy(),
+            wgpu::ImageCopyBuffer {
+                buffer: &buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(bytes_per_row),
+                    rows_per_image: None,
+                },
+            },
+            size,
+        );
+        queue.submit([encoder.finish()]);
+
+        buffer
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, |result| result.unwrap());
+        device.poll(wgpu::Maintain::Wait);
+        let bytes = buffer.slice(..).get_mapped_range().to_vec();
+        bytes
+    }
+
+    #[test]
+    fn present_mode_falls_back_to_supported_mode() {
+        let [fifo, mailbox, immediate] = [
+            PresentMode::Fifo,
+            PresentMode::Mailbox,
+            PresentMode::Immediate,
+        ]
+        .map(PresentMode::to_wgpu);
+
+        assert_eq!(
+            PresentMode::Immediate.resolve(&[fifo, immediate]),
+            PresentMode::Immediate
+        );
+        assert_eq!(
+            PresentMode::Immediate.resolve(&[fifo, mailbox]),
+            PresentMode::Mailbox
+        );
+        assert_eq!(PresentMode::Immediate.resolve(&[fifo]), PresentMode::Fifo);
+        assert_eq!(
+            PresentMode::Mailbox.resolve(&[fifo, immediate]),
+            PresentMode::Fifo
+        );
+        assert_eq!(PresentMode::Fifo.resolve(&[]), PresentMode::Fifo);
+    }
+
+    #[test]
+    fn srgb_converts_to_linear() {
+        assert_eq!(Color::srgb(0.0, 1.0, 0.0), Color::rgb(0.0, 1.0, 0.0));
+        assert!((Color::srgb(0.5, 0.5, 0.5).r - 0.2140).abs() < 1e-4);
+        assert!((Color::srgb(0.02, 0.02, 0.02).r - 0.02 / 12.92).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,422 @@
+//! # Anti-Aliasing
+//!
+//! Smoothing of jagged edges, selected with the [AntiAliasing] scene resource. Multisample
+//! anti-aliasing (MSAA) is resolved by the [crate::render::graph::FORWARD] node, while FXAA and
+//! TAA are applied to the high dynamic range target by the
+//! [crate::render::graph::ANTI_ALIASING] node before bloom and tonemapping.
+
+use bytemuck::Pod;
+use bytemuck::Zeroable;
+use glam::Mat4;
+use glam::UVec2;
+use glam::Vec2;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::render::graph::RenderContext;
+use crate::render::graph::RenderNode;
+use crate::render::tonemap::HdrTarget;
+use crate::render::tonemap::HDR_FORMAT;
+use crate::Reflect;
+use crate::Scene;
+
+/// Weight of the current frame in the history of TAA.
+const TAA_BLEND: f32 = 0.1;
+
+/// Number of subpixel offsets TAA jitters the cameras by before repeating them.
+const TAA_JITTERS: u32 = 8;
+
+/// # Anti-Aliasing
+///
+/// Scene resource selecting the anti-aliasing of the rendered frames. MSAA with 4 samples is used
+/// if the scene has none. Changing it recreates the render targets as needed.
+///
+/// ```
+/// # use pulse::render::anti_aliasing::AntiAliasing;
+/// # use pulse::Scene;
+/// let mut scene = Scene::new();
+/// scene.insert_resource(AntiAliasing::Fxaa);
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Reflect, Serialize, Deserialize)]
+pub enum AntiAliasing {
+    /// No anti-aliasing.
+    None,
+    /// Multisample anti-aliasing, shading each pixel once but testing the coverage and depth of
+    /// the meshes at multiple samples. Only smooths the edges of meshes.
+    Msaa {
+        /// Number of samples per pixel. Devices support 1 and 4 samples, so counts above 1 use 4.
+        samples: u32,
+    },
+    /// Fast approximate anti-aliasing, blurring the edges found in the image. Cheap, but blurs
+    /// fine details too.
+    Fxaa,
+    /// Temporal anti-aliasing, jittering the cameras by a subpixel offset every frame and
+    /// accumulating the frames. Smooths all edges, but blurs moving images.
+    Taa,
+}
+
+impl AntiAliasing {
+    /// Returns the number of samples per pixel of the meshes' render target.
+    pub(crate) fn sample_count(self) -> u32 {
+        match self {
+            Self::Msaa { samples } if samples > 1 => 4,
+            _ => 1,
+        }
+    }
+}
+
+impl Default for AntiAliasing {
+    fn default() -> Self {
+        Self::Msaa { samples: 4 }
+    }
+}
+
+/// Returns the matrix offsetting clip coordinates by the subpixel jitter of TAA's frame in a target
+/// of the size.
+pub(crate) fn jitter(frame: u32, size: UVec2) -> Mat4 {
+    let index = frame % TAA_JITTERS + 1;
+    let offset = Vec2::new(halton(index, 2), halton(index, 3)) - 0.5;
+    let offset = offset * 2.0 / size.max(UVec2::ONE).as_vec2();
+    Mat4::from_translation(offset.extend(0.0))
+}
+
+/// Returns the element of the Halton sequence with the base, evenly distributed in `0.0..1.0`.
+fn halton(mut index: u32, base: u32) -> f32 {
+    let mut result = 0.0;
+    let mut fraction = 1.0;
+    while index > 0 {
+        fraction /= base as f32;
+        result += fraction * (index % base) as f32;
+        index /= base;
+    }
+    result
+}
+
+/// Uniforms of the anti-aliasing shader.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+struct Params {
+    blend: f32,
+    _padding: [f32; 3],
+}
+
+/// Render resource with the textures of the anti-aliasing node for the size of the render target.
+struct Textures {
+    size: UVec2,
+    /// Output of FXAA, or the history of TAA of the previous and the current frame, swapped every
+    /// frame.
+    textures: [(wgpu::Texture, wgpu::TextureView); 2],
+    /// Whether the history holds the previous frame, so TAA can accumulate it.
+    history: bool,
+}
+
+impl Textures {
+    /// Inserts textures with the size of the context's target into the context unless it has them
+    /// already.
+    fn prepare(context: &mut RenderContext) {
+        let size = context.target_size;
+        if context
+            .resource::<Self>()
+            .is_some_and(|textures| textures.size == size)
+        {
+            return;
+        }
+
+        let texture = || {
+            let texture = context.device.create_texture(&wgpu::TextureDescriptor {
+                label: Some("anti-aliasing"),
+                size: wgpu::Extent3d {
+                    width: size.x.max(1),
+                    height: size.y.max(1),
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: HDR_FORMAT,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu::TextureUsages::TEXTURE_BINDING
+                    | wgpu::TextureUsages::COPY_SRC,
+                view_formats: &[],
+            });
+            let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+            (texture, view)
+        };
+        let textures = [texture(), texture()];
+        context.insert_resource(Self {
+            size,
+            textures,
+            history: false,
+        });
+    }
+}
+
+/// Render node applying FXAA or TAA to the [HdrTarget].
+pub(crate) struct AntiAliasingPass {
+    layout: wgpu::BindGroupLayout,
+    fxaa: wgpu::RenderPipeline,
+    taa: wgpu::RenderPipeline,
+    sampler: wgpu::Sampler,
+    params: wgpu::Buffer,
+}
+
+impl AntiAliasingPass {
+    pub(crate) fn new(device: &wgpu::Device) -> Self {
+        let shader = device.create_shader_module(wgpu::include_wgsl!("shaders/anti_aliasing.wgsl"));
+        let texture = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        };
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("anti-aliasing"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(
+                            std::mem::size_of::<Params>() as u64
+                        ),
+                    },
+                    count: None,
+                },
+                texture(1),
+                texture(2),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+        let pipeline = |entry_point| pipeline(device, &shader, &layout, entry_point);
+
+        Self {
+            fxaa: pipeline("fs_fxaa"),
+            taa: pipeline("fs_taa"),
+            layout,
+            sampler: device.create_sampler(&wgpu::SamplerDescriptor {
+                label: Some("anti-aliasing"),
+                mag_filter: wgpu::FilterMode::Linear,
+                min_filter: wgpu::FilterMode::Linear,
+                ..wgpu::SamplerDescriptor::default()
+            }),
+            params: device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("anti-aliasing params"),
+                size: std::mem::size_of::<Params>() as u64,
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }),
+        }
+    }
+
+    fn bind_group(
+        &self,
+        device: &wgpu::Device,
+        source: &wgpu::TextureView,
+        history: &wgpu::TextureView,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("anti-aliasing"),
+            layout: &self.layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: self.params.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(source),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(history),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler),
+                },
+            ],
+        })
+    }
+}
+
+impl RenderNode for AntiAliasingPass {
+    /// Writes the anti-aliased [HdrTarget] to a texture and copies it back.
+    fn run(&mut self, context: &mut RenderContext, scene: &Scene) {
+        let anti_aliasing = scene
+            .get_resource::<AntiAliasing>()
+            .copied()
+            .unwrap_or_default();
+        let taa = match anti_aliasing {
+            AntiAliasing::Fxaa => false,
+            AntiAliasing::Taa => true,
+            AntiAliasing::None | AntiAliasing::Msaa { .. } => {
+                context.remove_resource::<Textures>();
+                return;
+            }
+        };
+        if context.resource::<HdrTarget>().is_none() {
+            return;
+        }
+        let device = context.device;
+
+        Textures::prepare(context);
+        let textures = context.resources.resource_mut::<Textures>().unwrap();
+        let blend = if taa && textures.history {
+            TAA_BLEND
+        } else {
+            1.0
+        };
+        textures.history = taa;
+        textures.textures.swap(0, 1);
+        let params = Params {
+            blend,
+            _padding: [0.0; 3],
+        };
+        context
+            .queue
+            .write_buffer(&self.params, 0, bytemuck::bytes_of(&params));
+
+        let hdr = context.resources.resource::<HdrTarget>().unwrap();
+        let textures = context.resources.resource::<Textures>().unwrap();
+        let [(_, history), (output, output_view)] = &textures.textures;
+        let bind_group = self.bind_group(device, &hdr.view, history);
+
+        {
+            let mut pass = context
+                .encoder
+                .begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some("anti-aliasing"),
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view: output_view,
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                            store: wgpu::StoreOp::Store,
+                        },
+                    })],
+                    depth_stencil_attachment: None,
+                    timestamp_writes: None,
+                    occlusion_query_set: None,
+                });
+            pass.set_pipeline(if taa { &self.taa } else { &self.fxaa });
+            pass.set_bind_group(0, &bind_group, &[]);
+            pass.draw(0..3, 0..1);
+        }
+        context.encoder.copy_texture_to_texture(
+            output.as_image_copy(),
+            hdr.texture.as_image_copy(),
+            hdr.texture.size(),
+        );
+        context.count_draws(1, 1);
+    }
+}
+
+fn pipeline(
+    device: &wgpu::Device,
+    shader: &wgpu::ShaderModule,
+    layout: &wgpu::BindGroupLayout,
+    entry_point: &str,
+) -> wgpu::RenderPipeline {
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("anti-aliasing"),
+        bind_group_layouts: &[layout],
+        push_constant_ranges: &[],
+    });
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some(entry_point),
+        layout: Some(&pipeline_layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: "vs_main",
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+            buffers: &[],
+        },
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState::default(),
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point,
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+            targets: &[Some(HDR_FORMAT.into())],
+        }),
+        multiview: None,
+        cache: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec3;
+
+    use super::*;
+    use crate::render::mesh::Mesh;
+    use crate::render::tests::render_frames;
+    use crate::render::tonemap::Tonemapping;
+    use crate::render::ClearColor;
+    use crate::render::Color;
+    use crate::Camera;
+    use crate::VisibleNodes;
+    use crate::WorldTransform;
+
+    /// Renders the frames of a dark cube rotated around the view direction against a blue
+    /// background, and returns the number of pixels partially covered by the cube, or `None` if
+    /// there's no adapter.
+    fn render_edges(anti_aliasing: AntiAliasing, frames: usize) -> Option<usize> {
+        let mut scene = Scene::new();
+        scene.insert_resource(ClearColor(Color::rgb(0.0, 0.0, 1.0)));
+        scene.insert_resource(anti_aliasing);
+        let transform = WorldTransform::new(Mat4::from_rotation_z(0.5));
+        let cube = scene.spawn_with((Mesh::cube(1.0), transform));
+        scene.spawn_with((
+            Camera {
+                tonemapping: Tonemapping::None,
+                ..Camera::perspective(1.0, 1.0, 0.1, 10.0)
+            },
+            WorldTransform::new(Mat4::from_translation(Vec3::new(0.0, 0.0, 3.0))),
+            VisibleNodes(vec![cube]),
+        ));
+
+        let pixels = render_frames(&scene, frames)?;
+        Some(
+            pixels
+                .chunks(4)
+                .filter(|pixel| (40..240).contains(&pixel[2]))
+                .count(),
+        )
+    }
+
+    #[test]
+    fn render_smooths_edges() {
+        let Some(aliased) = render_edges(AntiAliasing::None, 1) else {
+            return;
+        };
+
+        assert_eq!(aliased, 0);
+        assert!(render_edges(AntiAliasing::default(), 1).unwrap() > 20);
+        assert!(render_edges(AntiAliasing::Fxaa, 1).unwrap() > 20);
+        assert!(render_edges(AntiAliasing::Taa, 8).unwrap() > 20);
+    }
+
+    #[test]
+    fn jitter_stays_within_pixel() {
+        let size = UVec2::new(100, 50);
+        for frame in 0..TAA_JITTERS {
+            let offset = jitter(frame, size).w_axis.truncate().truncate() * size.as_vec2() * 0.5;
+            assert!(offset.abs().max_element() < 0.5);
+        }
+        assert_ne!(jitter(0, size), jitter(1, size));
+        assert_eq!(jitter(0, size), jitter(TAA_JITTERS, size));
+    }
+}
@@ -0,0 +1,538 @@
+//! # Atmosphere
+//!
+//! Physically based sky drawn behind the meshes by the [crate::render::graph::ATMOSPHERE] node.
+//! The light of the sun, the first directional light, is scattered by the molecules (Rayleigh
+//! scattering) and aerosols (Mie scattering) of a planet's atmosphere, so the sky turns blue at
+//! noon and red at sunset with the direction of the light. The average light of the sky replaces
+//! the [crate::render::light::AmbientLight] of the meshes.
+//!
+//! The atmosphere assumes world units of meters, with the ground at a height of zero.
+
+use std::collections::HashMap;
+use std::f32::consts::PI;
+
+use bytemuck::Pod;
+use bytemuck::Zeroable;
+use glam::Mat4;
+use glam::Vec2;
+use glam::Vec3;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::render::anti_aliasing::AntiAliasing;
+use crate::render::forward::Background;
+use crate::render::forward::Targets;
+use crate::render::graph::RenderContext;
+use crate::render::graph::RenderNode;
+use crate::render::light::world_transform;
+use crate::render::light::DirectionalLight;
+use crate::render::target::set_viewport;
+use crate::render::tonemap::HdrTarget;
+use crate::render::tonemap::HDR_FORMAT;
+use crate::render::ClearColor;
+use crate::Camera;
+use crate::Component;
+use crate::ComputedVisibility;
+use crate::Node;
+use crate::Reflect;
+use crate::Scene;
+
+/// Number of samples along a view ray through the atmosphere.
+const VIEW_SAMPLES: u32 = 16;
+
+/// Number of samples along the ray from a view sample towards the sun.
+const LIGHT_SAMPLES: u32 = 8;
+
+/// Number of elevations and azimuths the sky is sampled at for the ambient light.
+const AMBIENT_SAMPLES: (u32, u32) = (4, 8);
+
+/// # Atmosphere
+///
+/// Atmosphere of a planet drawn as the sky of the node's [Camera]. Insert it into the scene as a
+/// resource to draw it for every camera without one. Like the [crate::render::skybox::Skybox],
+/// only the atmosphere of a render target's first camera is drawn, and it's drawn instead of the
+/// skybox. Defaults to the atmosphere of the earth.
+///
+/// ```
+/// # use glam::Vec3;
+/// # use pulse::render::atmosphere::Atmosphere;
+/// # use pulse::render::light::DirectionalLight;
+/// # use pulse::LocalTransform;
+/// # use pulse::Scene;
+/// let mut scene = Scene::new();
+/// scene.insert_resource(Atmosphere::default());
+/// // The sun low above the horizon.
+/// scene.spawn_with((
+///     DirectionalLight::default(),
+///     LocalTransform::IDENTITY.looking_at(Vec3::new(0.0, -0.1, -1.0), Vec3::Y),
+/// ));
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq, Component, Reflect, Serialize, Deserialize)]
+pub struct Atmosphere {
+    /// Rayleigh scattering coefficients of the red, green, and blue light per meter at the
+    /// ground.
+    pub rayleigh_scattering: Vec3,
+    /// Height over which the density of the molecules falls off by a factor of e.
+    pub rayleigh_scale_height: f32,
+    /// Mie scattering coefficient per meter at the ground.
+    pub mie_scattering: f32,
+    /// Mie absorption coefficient per meter at the ground.
+    pub mie_absorption: f32,
+    /// Height over which the density of the aerosols falls off by a factor of e.
+    pub mie_scale_height: f32,
+    /// Anisotropy of Mie scattering, from 0 for scattering in all directions to 1 for scattering
+    /// forwards only, making the haze around the sun.
+    pub mie_anisotropy: f32,
+    /// Radius of the planet.
+    pub planet_radius: f32,
+    /// Height of the top of the atmosphere above the ground.
+    pub atmosphere_height: f32,
+    /// Angular radius of the sun's disk in radians, or zero to hide it.
+    pub sun_angular_radius: f32,
+}
+
+impl Default for Atmosphere {
+    fn default() -> Self {
+        Self {
+            rayleigh_scattering: Vec3::new(5.802e-6, 13.558e-6, 33.1e-6),
+            rayleigh_scale_height: 8_000.0,
+            mie_scattering: 3.996e-6,
+            mie_absorption: 4.4e-6,
+            mie_scale_height: 1_200.0,
+            mie_anisotropy: 0.8,
+            planet_radius: 6_360_000.0,
+            atmosphere_height: 100_000.0,
+            sun_angular_radius: 0.0047,
+        }
+    }
+}
+
+impl Atmosphere {
+    /// Returns the light of the sky seen in the direction from the altitude above the ground,
+    /// scattered from the sun in the direction with the illuminance. The ground is black.
+    pub fn radiance(
+        &self,
+        direction: Vec3,
+        altitude: f32,
+        sun_direction: Vec3,
+        sun_illuminance: Vec3,
+    ) -> Vec3 {
+        let direction = direction.normalize_or_zero();
+        let sun_direction = sun_direction.normalize_or_zero();
+        let origin = Vec3::Y * (self.planet_radius + self.altitude(altitude));
+        let top = self.planet_radius + self.atmosphere_height.max(0.0);
+        let Some(mut length) = sphere_exit(origin, direction, top) else {
+            return Vec3::ZERO;
+        };
+        if let Some(ground) = sphere_entry(origin, direction, self.planet_radius) {
+            length = length.min(ground);
+        }
+
+        let step = length / VIEW_SAMPLES as f32;
+        let mut depth = Vec2::ZERO;
+        let mut rayleigh = Vec3::ZERO;
+        let mut mie = Vec3::ZERO;
+        for sample in 0..VIEW_SAMPLES {
+            let point = origin + direction * (sample as f32 + 0.5) * step;
+            let density = self.density(point) * step;
+            depth += density;
+            let Some(light_depth) = self.light_depth(point, sun_direction) else {
+                continue;
+            };
+            let transmittance = self.transmittance(depth + light_depth);
+            rayleigh += transmittance * density.x;
+            mie += transmittance * density.y;
+        }
+
+        let cos_angle = direction.dot(sun_direction);
+        let rayleigh_phase = 3.0 / (16.0 * PI) * (1.0 + cos_angle * cos_angle);
+        let g = self.mie_anisotropy.clamp(0.0, 0.999);
+        let mie_phase = 3.0 / (8.0 * PI) * (1.0 - g * g) * (1.0 + cos_angle * cos_angle)
+            / ((2.0 + g * g) * (1.0 + g * g - 2.0 * g * cos_angle).powf(1.5));
+        sun_illuminance
+            * (rayleigh * self.rayleigh_scattering * rayleigh_phase
+                + mie * self.mie_scattering * mie_phase)
+    }
+
+    /// Returns the average light of the sky over the upper hemisphere, seen from the altitude
+    /// above the ground and lit by the sun in the direction with the illuminance.
+    pub fn ambient(&self, altitude: f32, sun_direction: Vec3, sun_illuminance: Vec3) -> Vec3 {
+        let (elevations, azimuths) = AMBIENT_SAMPLES;
+        let mut sum = Vec3::ZERO;
+        let mut weights = 0.0;
+        for elevation in 0..elevations {
+            let elevation = (elevation as f32 + 0.5) / elevations as f32 * PI * 0.5;
+            // Rings at lower elevations cover more of the hemisphere.
+            let weight = elevation.cos();
+            for azimuth in 0..azimuths {
+                let azimuth = azimuth as f32 / azimuths as f32 * PI * 2.0;
+                let direction = Vec3::new(
+                    elevation.cos() * azimuth.sin(),
+                    elevation.sin(),
+                    elevation.cos() * azimuth.cos(),
+                );
+                sum += self.radiance(direction, altitude, sun_direction, sun_illuminance) * weight;
+                weights += weight;
+            }
+        }
+        sum / weights
+    }
+
+    /// Returns the altitude clamped to the atmosphere.
+    fn altitude(&self, altitude: f32) -> f32 {
+        altitude.clamp(0.0, self.atmosphere_height.max(0.0) * 0.999)
+    }
+
+    /// Returns the relative densities of the molecules and aerosols at the point.
+    fn density(&self, point: Vec3) -> Vec2 {
+        let height = (point.length() - self.planet_radius).max(0.0);
+        Vec2::new(
+            (-height / self.rayleigh_scale_height.max(1.0)).exp(),
+            (-height / self.mie_scale_height.max(1.0)).exp(),
+        )
+    }
+
+    /// Returns the optical depths of the molecules and aerosols from the point to the top of the
+    /// atmosphere in the direction, or `None` if the planet is in the way.
+    fn light_depth(&self, point: Vec3, direction: Vec3) -> Option<Vec2> {
+        if sphere_entry(point, direction, self.planet_radius).is_some() {
+            return None;
+        }
+        let top = self.planet_radius + self.atmosphere_height.max(0.0);
+        let step = sphere_exit(point, direction, top)? / LIGHT_SAMPLES as f32;
+        Some(
+            (0..LIGHT_SAMPLES)
+                .map(|sample| self.density(point + direction * (sample as f32 + 0.5) * step))
+                .sum::<Vec2>()
+                * step,
+        )
+    }
+
+    /// Returns the fraction of the light passing through the optical depths.
+    fn transmittance(&self, depth: Vec2) -> Vec3 {
+        let mie = self.mie_scattering + self.mie_absorption;
+        (-(self.rayleigh_scattering * depth.x + mie * depth.y)).exp()
+    }
+}
+
+/// Returns the distance to where the ray from inside the sphere around the origin leaves it.
+fn sphere_exit(origin: Vec3, direction: Vec3, radius: f32) -> Option<f32> {
+    let (b, discriminant) = sphere_discriminant(origin, direction, radius)?;
+    Some((-b + discriminant.sqrt()).max(0.0))
+}
+
+/// Returns the distance to where the ray from outside or on the sphere around the origin enters
+/// it, if it does.
+fn sphere_entry(origin: Vec3, direction: Vec3, radius: f32) -> Option<f32> {
+    let (b, discriminant) = sphere_discriminant(origin, direction, radius)?;
+    Some(-b - discriminant.sqrt()).filter(|distance| b < 0.0 && *distance >= 0.0)
+}
+
+/// Returns the half of the linear coefficient and the discriminant of the intersection of the ray
+/// with the sphere, or `None` if they don't intersect. The constant coefficient is factored to
+/// keep precision for spheres as large as a planet.
+fn sphere_discriminant(origin: Vec3, direction: Vec3, radius: f32) -> Option<(f32, f32)> {
+    let b = origin.dot(direction);
+    let distance = origin.length();
+    let c = (distance - radius) * (distance + radius);
+    let discriminant = b * b - c;
+    (discriminant >= 0.0).then_some((b, discriminant))
+}
+
+/// Returns the direction towards the sun, the first visible [DirectionalLight] of the scene, and
+/// its illuminance, or `None` if the scene has none.
+pub(crate) fn sun(scene: &Scene) -> Option<(Vec3, Vec3)> {
+    let (node, light) = scene
+        .query::<(DirectionalLight,)>()
+        .filter(|(node, _)| {
+            scene.get::<ComputedVisibility>(*node) == Some(&ComputedVisibility::Visible)
+        })
+        .min_by_key(|(node, _)| *node)?;
+    let direction = -world_transform(scene, node).forward().normalize_or_zero();
+    let illuminance = light.color.to_vec4().truncate() * light.intensity.max(0.0);
+    Some((direction, illuminance))
+}
+
+/// Returns the average light of the camera's sky, or `None` if the camera node has no
+/// [Atmosphere] and the scene has none.
+pub(crate) fn sky_ambient(scene: &Scene, node: Node) -> Option<Vec3> {
+    let atmosphere = scene
+        .get::<Atmosphere>(node)
+        .or_else(|| scene.get_resource::<Atmosphere>())?;
+    let Some((direction, illuminance)) = sun(scene) else {
+        return Some(Vec3::ZERO);
+    };
+    let altitude = world_transform(scene, node).translation().y;
+    Some(atmosphere.ambient(altitude, direction, illuminance))
+}
+
+/// Uniforms of the atmosphere shader.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+struct Params {
+    inverse_view_projection: [[f32; 4]; 4],
+    /// Direction towards the sun and cosine of the angular radius of its disk.
+    sun_direction: [f32; 4],
+    sun_illuminance: [f32; 4],
+    /// Rayleigh scattering coefficients and scale height.
+    rayleigh: [f32; 4],
+    /// Mie scattering and absorption coefficients, scale height, and anisotropy.
+    mie: [f32; 4],
+    /// Radius of the planet, radius of the top of the atmosphere, and altitude of the camera.
+    planet: [f32; 4],
+}
+
+/// Render node drawing the [Atmosphere] of the first camera as the background of the forward
+/// pass.
+pub(crate) struct AtmospherePass {
+    shader: wgpu::ShaderModule,
+    layout: wgpu::BindGroupLayout,
+    bind_group: wgpu::BindGroup,
+    /// Pipelines for each sample count.
+    pipelines: HashMap<u32, wgpu::RenderPipeline>,
+    params: wgpu::Buffer,
+}
+
+impl AtmospherePass {
+    pub(crate) fn new(device: &wgpu::Device) -> Self {
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("atmosphere"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: wgpu::BufferSize::new(std::mem::size_of::<Params>() as u64),
+                },
+                count: None,
+            }],
+        });
+        let params = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("atmosphere params"),
+            size: std::mem::size_of::<Params>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("atmosphere"),
+            layout: &layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: params.as_entire_binding(),
+            }],
+        });
+
+        Self {
+            shader: device.create_shader_module(wgpu::include_wgsl!("shaders/atmosphere.wgsl")),
+            layout,
+            bind_group,
+            pipelines: HashMap::new(),
+            params,
+        }
+    }
+}
+
+impl RenderNode for AtmospherePass {
+    fn run(&mut self, context: &mut RenderContext, scene: &Scene) {
+        let Some(&node) = context.cameras.first() else {
+            return;
+        };
+        let Some(atmosphere) = scene
+            .get::<Atmosphere>(node)
+            .or_else(|| scene.get_resource::<Atmosphere>())
+        else {
+            return;
+        };
+        let device = context.device;
+        let samples = scene
+            .get_resource::<AntiAliasing>()
+            .copied()
+            .unwrap_or_default()
+            .sample_count();
+        HdrTarget::prepare(context);
+        Targets::prepare(context, samples);
+        let pipeline = self
+            .pipelines
+            .entry(samples)
+            .or_insert_with(|| pipeline(device, &self.shader, &self.layout, samples));
+
+        // The sky only turns with the camera, and rises with its height.
+        let camera = scene.get::<Camera>(node).unwrap();
+        let transform = world_transform(scene, node);
+        let (_, rotation, translation) = transform.matrix.to_scale_rotation_translation();
+        let view_projection = camera.projection_matrix() * Mat4::from_quat(rotation.inverse());
+        let (sun_direction, sun_illuminance) = sun(scene).unwrap_or_default();
+        let params = Params {
+            inverse_view_projection: view_projection.inverse().to_cols_array_2d(),
+            sun_direction: sun_direction
+                .extend(atmosphere.sun_angular_radius.cos())
+                .to_array(),
+            sun_illuminance: sun_illuminance.extend(0.0).to_array(),
+            rayleigh: atmosphere
+                .rayleigh_scattering
+                .extend(atmosphere.rayleigh_scale_height.max(1.0))
+                .to_array(),
+            mie: [
+                atmosphere.mie_scattering,
+                atmosphere.mie_absorption,
+                atmosphere.mie_scale_height.max(1.0),
+                atmosphere.mie_anisotropy.clamp(0.0, 0.999),
+            ],
+            planet: [
+                atmosphere.planet_radius,
+                atmosphere.planet_radius + atmosphere.atmosphere_height.max(0.0),
+                atmosphere.altitude(translation.y),
+                0.0,
+            ],
+        };
+        context
+            .queue
+            .write_buffer(&self.params, 0, bytemuck::bytes_of(&params));
+
+        // Letterbox bars outside of the camera's viewport show the clear color.
+        let viewport = camera.viewport(context.target_size);
+        let clear_color = scene
+            .get_resource::<ClearColor>()
+            .copied()
+            .unwrap_or_default()
+            .0;
+        let hdr = &context.resources.resource::<HdrTarget>().unwrap().view;
+        let targets = context.resources.resource::<Targets>().unwrap();
+        {
+            let mut pass = context
+                .encoder
+                .begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some("atmosphere"),
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view: targets.color.as_ref().unwrap_or(hdr),
+                        resolve_target: targets.color.as_ref().map(|_| hdr),
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(clear_color.to_wgpu()),
+                            store: wgpu::StoreOp::Store,
+                        },
+                    })],
+                    depth_stencil_attachment: None,
+                    timestamp_writes: None,
+                    occlusion_query_set: None,
+                });
+            set_viewport(&mut pass, viewport);
+            pass.set_pipeline(pipeline);
+            pass.set_bind_group(0, &self.bind_group, &[]);
+            pass.draw(0..3, 0..1);
+        }
+        context.count_draws(1, 1);
+        context.insert_resource(Background);
+    }
+}
+
+fn pipeline(
+    device: &wgpu::Device,
+    shader: &wgpu::ShaderModule,
+    layout: &wgpu::BindGroupLayout,
+    samples: u32,
+) -> wgpu::RenderPipeline {
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("atmosphere"),
+        bind_group_layouts: &[layout],
+        push_constant_ranges: &[],
+    });
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("atmosphere"),
+        layout: Some(&pipeline_layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: "vs_main",
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+            buffers: &[],
+        },
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState {
+            count: samples,
+            ..wgpu::MultisampleState::default()
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: "fs_main",
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+            targets: &[Some(HDR_FORMAT.into())],
+        }),
+        multiview: None,
+        cache: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::tests::render;
+    use crate::render::tonemap::Tonemapping;
+    use crate::LocalTransform;
+    use crate::WorldTransform;
+
+    #[test]
+    fn radiance_is_blue_at_noon_and_red_at_sunset() {
+        let atmosphere = Atmosphere::default();
+
+        let noon = atmosphere.radiance(Vec3::new(0.0, 0.5, 1.0), 0.0, Vec3::Y, Vec3::ONE);
+        let sunset = atmosphere.radiance(
+            Vec3::new(0.0, 0.02, -1.0),
+            0.0,
+            Vec3::new(0.0, 0.01, -1.0),
+            Vec3::ONE,
+        );
+        let ground = atmosphere.radiance(Vec3::NEG_Y, 0.0, Vec3::Y, Vec3::ONE);
+
+        assert!(noon.z > noon.y && noon.y > noon.x);
+        assert!(sunset.x > sunset.z);
+        assert_eq!(ground, Vec3::ZERO);
+    }
+
+    #[test]
+    fn ambient_follows_sun() {
+        let atmosphere = Atmosphere::default();
+
+        let day = atmosphere.ambient(0.0, Vec3::Y, Vec3::ONE);
+        let night = atmosphere.ambient(0.0, Vec3::NEG_Y, Vec3::ONE);
+
+        assert!(day.z > day.x && day.x > 0.0);
+        assert_eq!(night, Vec3::ZERO);
+    }
+
+    #[test]
+    fn render_draws_sky_lit_by_sun() {
+        let mut scene = Scene::new();
+        scene.insert_resource(AntiAliasing::None);
+        scene.insert_resource(Atmosphere::default());
+        scene.spawn_with((
+            DirectionalLight {
+                intensity: 20.0,
+                ..DirectionalLight::default()
+            },
+            WorldTransform::new(
+                LocalTransform::IDENTITY
+                    .looking_at(Vec3::new(0.0, -1.0, -1.0), Vec3::Y)
+                    .matrix(),
+            ),
+            ComputedVisibility::Visible,
+        ));
+        scene.spawn_with((
+            Camera {
+                tonemapping: Tonemapping::None,
+                ..Camera::perspective(1.5, 1.0, 0.1, 100.0)
+            },
+            WorldTransform::new(Mat4::from_translation(Vec3::Y)),
+        ));
+        let Some(pixels) = render(&scene) else {
+            return;
+        };
+        let pixel =
+            |x: usize, y: usize| -> [u8; 4] { pixels[(y * 64 + x) * 4..][..4].try_into().unwrap() };
+
+        // Blue sky above the horizon, and black ground below it.
+        let sky = pixel(32, 8);
+        assert!(sky[2] > sky[0] && sky[2] > 0);
+        assert_eq!(pixel(32, 60), [0, 0, 0, 255]);
+    }
+}
@@ -0,0 +1,408 @@
+//! # Bloom
+//!
+//! Glow around bright light, added to the high dynamic range target by the
+//! [crate::render::graph::BLOOM] node before tonemapping if the first camera has a [Bloom]. The
+//! light above the threshold is blurred by downsampling it into a chain of mips, then upsampling
+//! and adding them up again.
+
+use bytemuck::Pod;
+use bytemuck::Zeroable;
+use glam::UVec2;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::render::graph::RenderContext;
+use crate::render::graph::RenderNode;
+use crate::render::tonemap::HdrTarget;
+use crate::render::tonemap::HDR_FORMAT;
+use crate::Component;
+use crate::Reflect;
+use crate::Scene;
+
+/// Maximum number of mips the light is blurred with. The first mip has half the size of the
+/// render target.
+const MAX_MIPS: u32 = 6;
+
+/// # Bloom
+///
+/// Bloom of the light seen by the node's [crate::Camera]. Cameras without the component don't bloom.
+///
+/// ```
+/// # use pulse::render::bloom::Bloom;
+/// # use pulse::Camera;
+/// # use pulse::Scene;
+/// let mut scene = Scene::new();
+/// scene.spawn_with((
+///     Camera::perspective(1.0, 16.0 / 9.0, 0.1, 100.0),
+///     Bloom {
+///         intensity: 0.3,
+///         ..Bloom::default()
+///     },
+/// ));
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq, Component, Reflect, Serialize, Deserialize)]
+pub struct Bloom {
+    /// Fraction of the blurred light added to the image.
+    pub intensity: f32,
+    /// Brightness above which light blooms.
+    pub threshold: f32,
+    /// Range below the threshold in which light blooms partially, softening the transition.
+    pub knee: f32,
+}
+
+impl Default for Bloom {
+    fn default() -> Self {
+        Self {
+            intensity: 0.15,
+            threshold: 1.0,
+            knee: 0.5,
+        }
+    }
+}
+
+/// Uniforms of the bloom shader.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+struct Params {
+    threshold: f32,
+    knee: f32,
+    intensity: f32,
+    mips: f32,
+}
+
+/// Render node adding the [Bloom] of the first camera to the [HdrTarget].
+pub(crate) struct BloomPass {
+    layout: wgpu::BindGroupLayout,
+    prefilter: wgpu::RenderPipeline,
+    downsample: wgpu::RenderPipeline,
+    upsample: wgpu::RenderPipeline,
+    composite: wgpu::RenderPipeline,
+    sampler: wgpu::Sampler,
+    params: wgpu::Buffer,
+}
+
+/// Render resource with the views of the mips of the bloom texture for the size of the render
+/// target.
+struct BloomMips {
+    size: UVec2,
+    views: Vec<wgpu::TextureView>,
+}
+
+impl BloomMips {
+    /// Inserts the mips for the size of the context's target into the context unless it has them
+    /// already.
+    fn prepare(context: &mut RenderContext) {
+        let size = context.target_size;
+        if context
+            .resource::<Self>()
+            .is_some_and(|mips| mips.size == size)
+        {
+            return;
+        }
+
+        let first = (size / 2).max(UVec2::ONE);
+        let texture = context.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("bloom"),
+            size: wgpu::Extent3d {
+                width: first.x,
+                height: first.y,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: (first.min_element().ilog2() + 1).min(MAX_MIPS),
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: HDR_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        let views = (0..texture.mip_level_count())
+            .map(|mip| {
+                texture.create_view(&wgpu::TextureViewDescriptor {
+                    base_mip_level: mip,
+                    mip_level_count: Some(1),
+                    ..wgpu::TextureViewDescriptor::default()
+                })
+            })
+            .collect();
+        context.insert_resource(Self { size, views });
+    }
+}
+
+impl BloomPass {
+    pub(crate) fn new(device: &wgpu::Device) -> Self {
+        let shader = device.create_shader_module(wgpu::include_wgsl!("shaders/bloom.wgsl"));
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("bloom"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(
+                            std::mem::size_of::<Params>() as u64
+                        ),
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+
+        // Adds the blurred light to the target, keeping its alpha.
+        let additive = wgpu::BlendState {
+            color: wgpu::BlendComponent {
+                src_factor: wgpu::BlendFactor::One,
+                dst_factor: wgpu::BlendFactor::One,
+                operation: wgpu::BlendOperation::Add,
+            },
+            alpha: wgpu::BlendComponent {
+                src_factor: wgpu::BlendFactor::Zero,
+                dst_factor: wgpu::BlendFactor::One,
+                operation: wgpu::BlendOperation::Add,
+            },
+        };
+        let pipeline = |entry_point, blend| pipeline(device, &shader, &layout, entry_point, blend);
+
+        Self {
+            prefilter: pipeline("fs_prefilter", None),
+            downsample: pipeline("fs_downsample", None),
+            upsample: pipeline("fs_upsample", Some(additive)),
+            composite: pipeline("fs_composite", Some(additive)),
+            layout,
+            sampler: device.create_sampler(&wgpu::SamplerDescriptor {
+                label: Some("bloom"),
+                mag_filter: wgpu::FilterMode::Linear,
+                min_filter: wgpu::FilterMode::Linear,
+                ..wgpu::SamplerDescriptor::default()
+            }),
+            params: device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("bloom params"),
+                size: std::mem::size_of::<Params>() as u64,
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }),
+        }
+    }
+
+    fn bind_group(&self, device: &wgpu::Device, source: &wgpu::TextureView) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("bloom"),
+            layout: &self.layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: self.params.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(source),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler),
+                },
+            ],
+        })
+    }
+}
+
+impl RenderNode for BloomPass {
+    fn run(&mut self, context: &mut RenderContext, scene: &Scene) {
+        if context.resource::<HdrTarget>().is_none() {
+            return;
+        }
+        let Some(bloom) = context
+            .cameras
+            .first()
+            .and_then(|node| scene.get::<Bloom>(*node))
+        else {
+            return;
+        };
+        let device = context.device;
+
+        BloomMips::prepare(context);
+        let hdr = context.resources.resource::<HdrTarget>().unwrap();
+        let mips = &context.resources.resource::<BloomMips>().unwrap().views;
+        let params = Params {
+            threshold: bloom.threshold.max(0.0),
+            knee: bloom.knee.max(0.0),
+            intensity: bloom.intensity.max(0.0),
+            mips: mips.len() as f32,
+        };
+        context
+            .queue
+            .write_buffer(&self.params, 0, bytemuck::bytes_of(&params));
+
+        let encoder = &mut *context.encoder;
+        let clear = wgpu::LoadOp::Clear(wgpu::Color::BLACK);
+        let bind_group = self.bind_group(device, &hdr.view);
+        draw(encoder, &self.prefilter, &bind_group, &mips[0], clear);
+        for (larger, smaller) in mips.iter().zip(&mips[1..]) {
+            let bind_group = self.bind_group(device, larger);
+            draw(encoder, &self.downsample, &bind_group, smaller, clear);
+        }
+        for (larger, smaller) in mips.iter().zip(&mips[1..]).rev() {
+            let bind_group = self.bind_group(device, smaller);
+            draw(
+                encoder,
+                &self.upsample,
+                &bind_group,
+                larger,
+                wgpu::LoadOp::Load,
+            );
+        }
+        let bind_group = self.bind_group(device, &mips[0]);
+        draw(
+            encoder,
+            &self.composite,
+            &bind_group,
+            &hdr.view,
+            wgpu::LoadOp::Load,
+        );
+        let draw_calls = 2 * mips.len() as u32;
+        context.count_draws(draw_calls, u64::from(draw_calls));
+    }
+}
+
+fn pipeline(
+    device: &wgpu::Device,
+    shader: &wgpu::ShaderModule,
+    layout: &wgpu::BindGroupLayout,
+    entry_point: &str,
+    blend: Option<wgpu::BlendState>,
+) -> wgpu::RenderPipeline {
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("bloom"),
+        bind_group_layouts: &[layout],
+        push_constant_ranges: &[],
+    });
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some(entry_point),
+        layout: Some(&pipeline_layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: "vs_main",
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+            buffers: &[],
+        },
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState::default(),
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point,
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+            targets: &[Some(wgpu::ColorTargetState {
+                format: HDR_FORMAT,
+                blend,
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        multiview: None,
+        cache: None,
+    })
+}
+
+/// Records a pass drawing a triangle covering the target.
+fn draw(
+    encoder: &mut wgpu::CommandEncoder,
+    pipeline: &wgpu::RenderPipeline,
+    bind_group: &wgpu::BindGroup,
+    target: &wgpu::TextureView,
+    load: wgpu::LoadOp<wgpu::Color>,
+) {
+    let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: Some("bloom"),
+        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+            view: target,
+            resolve_target: None,
+            ops: wgpu::Operations {
+                load,
+                store: wgpu::StoreOp::Store,
+            },
+        })],
+        depth_stencil_attachment: None,
+        timestamp_writes: None,
+        occlusion_query_set: None,
+    });
+    pass.set_pipeline(pipeline);
+    pass.set_bind_group(0, bind_group, &[]);
+    pass.draw(0..3, 0..1);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::tonemap::Exposure;
+    use crate::render::tonemap::Tonemapping;
+    use crate::render::ClearColor;
+    use crate::render::Color;
+    use crate::Camera;
+
+    /// Renders a scene cleared to the gray with a camera with the bloom, exposed by 3 EV without
+    /// tonemapping, and returns the red component of a pixel, or `None` if there's no adapter.
+    fn render_gray(gray: f32, bloom: Option<Bloom>) -> Option<u8> {
+        let mut scene = Scene::new();
+        scene.insert_resource(ClearColor(Color::rgb(gray, gray, gray)));
+        let camera = scene.spawn_with(Camera {
+            exposure: Exposure::Manual { ev: 3.0 },
+            tonemapping: Tonemapping::None,
+            ..Camera::perspective(1.0, 1.0, 0.1, 10.0)
+        });
+        if let Some(bloom) = bloom {
+            scene.add(camera, bloom);
+        }
+
+        crate::render::tests::render(&scene).map(|pixels| pixels[0])
+    }
+
+    #[test]
+    fn render_adds_light_above_threshold() {
+        let bloom = Bloom {
+            intensity: 0.1,
+            threshold: 1.0,
+            knee: 0.0,
+        };
+        let Some(without) = render_gray(4.0, None) else {
+            return;
+        };
+
+        // 4 / 8 without bloom, and (4 + 0.1 * (4 - 1)) / 8 with.
+        assert_eq!(without, 128);
+        assert!(render_gray(4.0, Some(bloom)).unwrap().abs_diff(137) <= 1);
+        assert_eq!(render_gray(0.8, Some(bloom)), render_gray(0.8, None));
+    }
+
+    #[test]
+    fn render_blooms_in_knee_below_threshold() {
+        let bloom = Bloom {
+            intensity: 1.0,
+            threshold: 1.0,
+            knee: 0.5,
+        };
+        let Some(without) = render_gray(0.9, None) else {
+            return;
+        };
+
+        // 0.4^2 / (4 * 0.5) = 0.08 of the light in the knee blooms.
+        assert!(render_gray(0.9, Some(bloom)).unwrap() > without);
+        assert_eq!(render_gray(0.4, Some(bloom)), render_gray(0.4, None));
+    }
+}
@@ -0,0 +1,287 @@
+//! # Capture
+//!
+//! Captures of rendered frames read back from the GPU, e.g. for screenshots, bug reports, and
+//! golden image tests.
+
+use std::fmt;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use glam::UVec2;
+
+use crate::render::image::ColorSpace;
+use crate::render::image::Image;
+
+/// # Frame Capture
+///
+/// Handle to a capture of a frame requested with [crate::render::Renderer::capture_frame]. The
+/// frame is copied after it's rendered and read back asynchronously, so the image is usually ready
+/// a frame later.
+///
+/// ```no_run
+/// # use pulse::render::Renderer;
+/// # use pulse::Scene;
+/// # fn screenshot(scene: &mut Scene) {
+/// let capture = scene.get_resource_mut::<Renderer>().unwrap().capture_frame();
+///
+/// // In a later frame:
+/// if let Some(Ok(image)) = capture.result() {
+///     std::fs::write("screenshot.png", image.to_png()).unwrap();
+/// }
+/// # }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct FrameCapture {
+    result: Arc<Mutex<Option<Result<Image, CaptureError>>>>,
+}
+
+impl FrameCapture {
+    /// Returns the image of the captured frame in sRGB, or the error if it couldn't be captured,
+    /// or `None` while the capture is pending.
+    pub fn result(&self) -> Option<Result<Image, CaptureError>> {
+        self.result.lock().unwrap().clone()
+    }
+
+    /// Returns true if the capture was completed or failed.
+    pub fn is_done(&self) -> bool {
+        self.result.lock().unwrap().is_some()
+    }
+
+    fn complete(&self, result: Result<Image, CaptureError>) {
+        *self.result.lock().unwrap() = Some(result);
+    }
+}
+
+/// # Capture Error
+///
+/// Error of a [FrameCapture] that couldn't be completed.
+#[derive(Clone, Debug, PartialEq)]
+pub enum CaptureError {
+    /// The frame's texture can't be copied or its format isn't 8-bit RGBA or BGRA.
+    Unsupported(wgpu::TextureFormat),
+    /// The copy of the frame couldn't be read back.
+    Readback(wgpu::BufferAsyncError),
+}
+
+impl fmt::Display for CaptureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unsupported(format) => write!(f, "can't capture frames of format {format:?}"),
+            Self::Readback(error) => write!(f, "failed to read back frame: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for CaptureError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Unsupported(_) => None,
+            Self::Readback(error) => Some(error),
+        }
+    }
+}
+
+/// Copy of a frame being read back for its captures.
+struct Readback {
+    buffer: wgpu::Buffer,
+    size: UVec2,
+    bytes_per_row: u32,
+    bgra: bool,
+    mapped: Arc<Mutex<Option<Result<(), wgpu::BufferAsyncError>>>>,
+    captures: Vec<FrameCapture>,
+}
+
+/// Captures requested for the next frame and those being read back.
+#[derive(Default)]
+pub(crate) struct Captures {
+    requested: Vec<FrameCapture>,
+    readbacks: Vec<Readback>,
+}
+
+impl Captures {
+    /// Returns a capture of the next frame copied with [Captures::copy].
+    pub(crate) fn request(&mut self) -> FrameCapture {
+        let capture = FrameCapture::default();
+        self.requested.push(capture.clone());
+        capture
+    }
+
+    /// Copies the texture of the rendered frame for the requested captures.
+    pub(crate) fn copy(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        texture: &wgpu::Texture,
+    ) {
+        if self.requested.is_empty() {
+            return;
+        }
+        let captures = std::mem::take(&mut self.requested);
+        let format = texture.format();
+        let bgra = match format {
+            wgpu::TextureFormat::Rgba8Unorm | wgpu::TextureFormat::Rgba8UnormSrgb => Some(false),
+            wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb => Some(true),
+            _ => None,
+        };
+        let Some(bgra) = bgra.filter(|_| texture.usage().contains(wgpu::TextureUsages::COPY_SRC))
+        else {
+            for capture in captures {
+                capture.complete(Err(CaptureError::Unsupported(format)));
+            }
+            return;
+        };
+
+        let size = UVec2::new(texture.width(), texture.height());
+        let bytes_per_row = (size.x * 4).next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("frame capture"),
+            size: u64::from(bytes_per_row * size.y),
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("frame capture"),
+        });
+        encoder.copy_texture_to_buffer(
+            texture.as_image_copy(),
+            wgpu::ImageCopyBuffer {
+                buffer: &buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(bytes_per_row),
+                    rows_per_image: None,
+                },
+            },
+            texture.size(),
+        );
+        queue.submit([encoder.finish()]);
+
+        let mapped = Arc::new(Mutex::new(None));
+        let callback = Arc::clone(&mapped);
+        buffer
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                *callback.lock().unwrap() = Some(result);
+            });
+        self.readbacks.push(Readback {
+            buffer,
+            size,
+            bytes_per_row,
+            bgra,
+            mapped,
+            captures,
+        });
+    }
+
+    /// Completes the captures whose frames were read back.
+    pub(crate) fn poll(&mut self, device: &wgpu::Device) {
+        if self.readbacks.is_empty() {
+            return;
+        }
+
+        device.poll(wgpu::Maintain::Poll);
+        self.readbacks.retain(|readback| {
+            let Some(mapped) = readback.mapped.lock().unwrap().take() else {
+                return true;
+            };
+            let result = mapped
+                .map(|()| readback.image())
+                .map_err(CaptureError::Readback);
+            for capture in &readback.captures {
+                capture.complete(result.clone());
+            }
+            false
+        });
+    }
+}
+
+impl Readback {
+    /// Returns the image of the mapped buffer without the rows' padding.
+    fn image(&self) -> Image {
+        let bytes = self.buffer.slice(..).get_mapped_range();
+        let mut pixels = Vec::with_capacity(self.size.x as usize * self.size.y as usize * 4);
+        for row in bytes.chunks_exact(self.bytes_per_row as usize) {
+            pixels.extend_from_slice(&row[..self.size.x as usize * 4]);
+        }
+        drop(bytes);
+        self.buffer.unmap();
+
+        if self.bgra {
+            for pixel in pixels.chunks_exact_mut(4) {
+                pixel.swap(0, 2);
+            }
+        }
+        Image::new(self.size, pixels, ColorSpace::Srgb).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::tests::device;
+    use crate::render::tests::target;
+
+    /// Returns the capture of a texture of the format cleared to the color.
+    fn capture(format: wgpu::TextureFormat, color: wgpu::Color) -> Option<FrameCapture> {
+        let (device, queue) = device()?;
+        let texture = target(&device, format, UVec2::new(3, 2));
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: None,
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &texture.create_view(&wgpu::TextureViewDescriptor::default()),
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(color),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        queue.submit([encoder.finish()]);
+
+        let mut captures = Captures::default();
+        let capture = captures.request();
+        captures.copy(&device, &queue, &texture);
+        device.poll(wgpu::Maintain::Wait);
+        captures.poll(&device);
+        Some(capture)
+    }
+
+    #[test]
+    fn capture_reads_back_frame_without_row_padding() {
+        let color = wgpu::Color {
+            r: 1.0,
+            g: 0.0,
+            b: 0.2,
+            a: 1.0,
+        };
+        for format in [
+            wgpu::TextureFormat::Rgba8Unorm,
+            wgpu::TextureFormat::Bgra8Unorm,
+        ] {
+            let Some(capture) = capture(format, color) else {
+                return;
+            };
+            let image = capture.result().unwrap().unwrap();
+            assert_eq!(image.size(), UVec2::new(3, 2));
+            assert_eq!(image.pixels().len(), 3 * 2 * 4);
+            assert_eq!(image.pixel(2, 1), [255, 0, 51, 255]);
+        }
+    }
+
+    #[test]
+    fn capture_fails_for_unsupported_formats() {
+        let Some(capture) = capture(wgpu::TextureFormat::Rgba16Float, wgpu::Color::BLACK) else {
+            return;
+        };
+        assert_eq!(
+            capture.result(),
+            Some(Err(CaptureError::Unsupported(
+                wgpu::TextureFormat::Rgba16Float
+            )))
+        );
+    }
+}
@@ -0,0 +1,289 @@
+//! # Cluster
+//!
+//! Clustered forward lighting. The view of each camera is divided into a grid of clusters, tiles
+//! of the target split into slices of exponentially increasing depth, and a compute pass run by
+//! the [crate::render::graph::FORWARD] node bins the camera's point and spot lights into the
+//! clusters their range reaches. Fragments are then only shaded by the directional lights and the
+//! lights of their cluster, so scenes with hundreds of local lights stay viable.
+
+use bytemuck::Pod;
+use bytemuck::Zeroable;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::render::light::LightUniform;
+use crate::render::light::LightsUniform;
+use crate::Camera;
+use crate::Projection;
+use crate::Reflect;
+use crate::Viewport;
+use crate::WorldTransform;
+
+/// Number of clusters binned by each workgroup of the compute pass.
+const WORKGROUP_SIZE: u32 = 64;
+
+/// Minimum depth the slices start growing from, so their depths stay finite for cameras with a
+/// near plane at zero.
+const MIN_NEAR: f32 = 0.05;
+
+/// # Cluster Settings
+///
+/// Scene resource configuring the clusters lights are binned into. Defaults are used if the scene
+/// has none. More clusters bin lights more tightly at the cost of a larger cluster buffer.
+///
+/// ```
+/// # use pulse::render::cluster::ClusterSettings;
+/// # use pulse::Scene;
+/// let mut scene = Scene::new();
+/// scene.insert_resource(ClusterSettings {
+///     max_lights_per_cluster: 128,
+///     ..ClusterSettings::default()
+/// });
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq, Reflect, Serialize, Deserialize)]
+pub struct ClusterSettings {
+    /// Number of tiles the target is divided into horizontally.
+    pub tiles_x: u32,
+    /// Number of tiles the target is divided into vertically.
+    pub tiles_y: u32,
+    /// Number of depth slices between the camera's near and far planes.
+    pub slices: u32,
+    /// Maximum number of point and spot lights shading a cluster. Further lights are ignored in
+    /// the cluster.
+    pub max_lights_per_cluster: u32,
+}
+
+impl ClusterSettings {
+    /// Returns the number of clusters of a camera.
+    pub fn cluster_count(&self) -> u32 {
+        self.tiles_x.max(1) * self.tiles_y.max(1) * self.slices.max(1)
+    }
+
+    /// Returns the number of entries of a camera's clusters in the cluster buffer, a light count
+    /// followed by the light indices for each cluster.
+    pub(crate) fn stride(&self) -> u32 {
+        self.cluster_count() * (self.max_lights_per_cluster + 1)
+    }
+}
+
+impl Default for ClusterSettings {
+    fn default() -> Self {
+        Self {
+            tiles_x: 16,
+            tiles_y: 9,
+            slices: 24,
+            max_lights_per_cluster: 64,
+        }
+    }
+}
+
+/// Uniforms of a camera's clusters in the shader, part of its [LightsUniform].
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, Pod, Zeroable)]
+pub(crate) struct ClusterUniform {
+    /// Number of tiles along X and Y, number of slices, and maximum number of lights per cluster.
+    dimensions: [u32; 4],
+    /// Depth the slices start growing from, the first slice also covering the depths in front of
+    /// it, and depth of the far plane.
+    params: [f32; 4],
+    /// Position and size of the camera's viewport in the target in pixels.
+    viewport: [f32; 4],
+    /// Matrix transforming world to view coordinates.
+    view: [[f32; 4]; 4],
+    /// Matrix transforming clip to view coordinates.
+    inverse_projection: [[f32; 4]; 4],
+}
+
+impl ClusterUniform {
+    /// Returns the uniform of the clusters of the camera at the transform rendering to the
+    /// viewport.
+    pub(crate) fn new(
+        camera: &Camera,
+        transform: &WorldTransform,
+        settings: &ClusterSettings,
+        viewport: Viewport,
+    ) -> Self {
+        let (near, far) = match camera.projection {
+            Projection::Perspective { near, far, .. } => (near, far),
+            Projection::Orthographic { near, far, .. } => (near, far),
+        };
+        let near = near.max(MIN_NEAR);
+        Self {
+            dimensions: [
+                settings.tiles_x.max(1),
+                settings.tiles_y.max(1),
+                settings.slices.max(1),
+                settings.max_lights_per_cluster,
+            ],
+            params: [near, far.max(near * 2.0), 0.0, 0.0],
+            viewport: [
+                viewport.position.x as f32,
+                viewport.position.y as f32,
+                viewport.size.x as f32,
+                viewport.size.y as f32,
+            ],
+            view: transform.matrix.inverse().to_cols_array_2d(),
+            inverse_projection: camera.projection_matrix().inverse().to_cols_array_2d(),
+        }
+    }
+}
+
+/// Light and cluster buffers of the cameras, with the compute pipeline binning the lights of each
+/// camera into its clusters.
+pub(crate) struct LightClusters {
+    pipeline: wgpu::ComputePipeline,
+    layout: wgpu::BindGroupLayout,
+    bind_group: Option<wgpu::BindGroup>,
+    /// Lights of all cameras, each camera's lights starting at the index in its [LightsUniform].
+    pub(crate) lights: wgpu::Buffer,
+    /// Light counts and indices of the clusters of all cameras.
+    pub(crate) clusters: wgpu::Buffer,
+    /// Incremented whenever the buffers are recreated, so bind groups binding them are recreated.
+    pub(crate) generation: u64,
+}
+
+impl LightClusters {
+    pub(crate) fn new(device: &wgpu::Device) -> Self {
+        let storage = |binding, read_only| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("clusters"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: true,
+                        min_binding_size: wgpu::BufferSize::new(
+                            std::mem::size_of::<LightsUniform>() as u64,
+                        ),
+                    },
+                    count: None,
+                },
+                storage(1, true),
+                storage(2, false),
+            ],
+        });
+        let shader = device.create_shader_module(wgpu::include_wgsl!("shaders/cluster.wgsl"));
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("clusters"),
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("clusters"),
+            layout: Some(&pipeline_layout),
+            module: &shader,
+            entry_point: "cs_main",
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+            cache: None,
+        });
+
+        Self {
+            pipeline,
+            layout,
+            bind_group: None,
+            lights: buffer(device, "lights", std::mem::size_of::<LightUniform>() as u64),
+            clusters: buffer(device, "clusters", 4),
+            generation: 0,
+        }
+    }
+
+    /// Writes the lights of all cameras to the light buffer, growing the light buffer and the
+    /// cluster buffer to fit the clusters of the cameras as needed.
+    pub(crate) fn write(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        lights: &[LightUniform],
+        cameras: usize,
+        settings: &ClusterSettings,
+    ) {
+        let light_size =
+            std::mem::size_of_val(lights).max(std::mem::size_of::<LightUniform>()) as u64;
+        let cluster_size = u64::from(settings.stride()) * cameras.max(1) as u64 * 4;
+        if self.lights.size() < light_size {
+            self.lights = buffer(device, "lights", light_size.next_power_of_two());
+            self.bind_group = None;
+            self.generation += 1;
+        }
+        if self.clusters.size() < cluster_size {
+            self.clusters = buffer(device, "clusters", cluster_size.next_power_of_two());
+            self.bind_group = None;
+            self.generation += 1;
+        }
+        queue.write_buffer(&self.lights, 0, bytemuck::cast_slice(lights));
+    }
+
+    /// Bins the lights of each camera into its clusters, reading the cameras' [LightsUniform]s
+    /// from the buffer at the offsets.
+    pub(crate) fn bin(
+        &mut self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        uniforms: &wgpu::Buffer,
+        offsets: impl IntoIterator<Item = u32>,
+        settings: &ClusterSettings,
+    ) {
+        let bind_group = self.bind_group.get_or_insert_with(|| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("clusters"),
+                layout: &self.layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                            buffer: uniforms,
+                            offset: 0,
+                            size: wgpu::BufferSize::new(
+                                std::mem::size_of::<LightsUniform>() as u64
+                            ),
+                        }),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: self.lights.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: self.clusters.as_entire_binding(),
+                    },
+                ],
+            })
+        });
+
+        let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("clusters"),
+            timestamp_writes: None,
+        });
+        pass.set_pipeline(&self.pipeline);
+        let workgroups = settings.cluster_count().div_ceil(WORKGROUP_SIZE);
+        for offset in offsets {
+            pass.set_bind_group(0, &*bind_group, &[offset]);
+            pass.dispatch_workgroups(workgroups, 1, 1);
+        }
+    }
+
+    /// Drops the bind group binding the uniforms, e.g. after their buffer was recreated.
+    pub(crate) fn invalidate(&mut self) {
+        self.bind_group = None;
+    }
+}
+
+fn buffer(device: &wgpu::Device, label: &str, size: u64) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some(label),
+        size,
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
@@ -0,0 +1,598 @@
+//! # Compute
+//!
+//! Render nodes dispatching a compute shader with the bindings they declare, e.g. to simulate GPU
+//! particles or cull instances without changing the built-in nodes. The buffers and textures
+//! bound by the nodes are named [ComputeResources] of the render target, created by the first
+//! node declaring them and shared with the later nodes of the [crate::render::graph::RenderGraph],
+//! including nodes drawing from them.
+
+use std::collections::HashMap;
+
+use glam::UVec2;
+use glam::UVec3;
+use wgpu::naga;
+
+use crate::render::graph::RenderContext;
+use crate::render::graph::RenderNode;
+use crate::Scene;
+
+/// Function returning the bytes of a uniform buffer for the frame.
+type UniformFn = Box<dyn Fn(&Scene) -> Vec<u8> + Send + Sync>;
+
+/// # Compute Binding
+///
+/// Resource bound by a [ComputeNode] in the bind group 0 of its shader, at the index of the
+/// binding in the order the node declares them.
+pub enum ComputeBinding {
+    /// `var<uniform>` with the bytes returned by the function every frame, padded to 16 bytes.
+    Uniform(UniformFn),
+    /// `var<storage>` buffer with the name and size in bytes, zeroed when created, and only read
+    /// by the shader if `read_only`. The buffer is recreated if it's smaller than the size.
+    Buffer {
+        /// Name of the buffer in the [ComputeResources].
+        name: &'static str,
+        /// Size of the buffer in bytes.
+        size: u64,
+        /// Whether the shader only reads the buffer.
+        read_only: bool,
+    },
+    /// `texture_storage_2d` written by the shader with the name, format, and size, or the size of
+    /// the render target if `None`. The texture is recreated if its format or size differ.
+    StorageTexture {
+        /// Name of the texture in the [ComputeResources].
+        name: &'static str,
+        /// Format of the texture, one supporting storage binding such as `Rgba8Unorm`,
+        /// `Rgba16Float`, or `R32Float`.
+        format: wgpu::TextureFormat,
+        /// Size of the texture in pixels, or the size of the render target if `None`.
+        size: Option<UVec2>,
+    },
+    /// `texture_2d<f32>` with the name, read with `textureLoad` as it's bound without a sampler.
+    /// The node skips its dispatch while there's no texture with the name.
+    Texture(&'static str),
+}
+
+impl ComputeBinding {
+    /// Returns the uniform binding of the function's bytes.
+    pub fn uniform(bytes: impl 'static + Fn(&Scene) -> Vec<u8> + Send + Sync) -> Self {
+        Self::Uniform(Box::new(bytes))
+    }
+
+    fn layout_entry(&self, binding: u32) -> wgpu::BindGroupLayoutEntry {
+        let ty = match self {
+            Self::Uniform(_) => wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            Self::Buffer { read_only, .. } => wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage {
+                    read_only: *read_only,
+                },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            Self::StorageTexture { format, .. } => wgpu::BindingType::StorageTexture {
+                access: wgpu::StorageTextureAccess::WriteOnly,
+                format: *format,
+                view_dimension: wgpu::TextureViewDimension::D2,
+            },
+            Self::Texture(_) => wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+        };
+        wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty,
+            count: None,
+        }
+    }
+}
+
+/// # Workgroups
+///
+/// Number of workgroups dispatched by a [ComputeNode].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Workgroups {
+    /// The number of workgroups along each axis.
+    Count(UVec3),
+    /// Enough workgroups of the size along the X and Y axes to cover every pixel of the render
+    /// target, for shaders with the same `@workgroup_size`.
+    Target(UVec2),
+}
+
+impl Workgroups {
+    fn count(self, target_size: UVec2) -> UVec3 {
+        match self {
+            Self::Count(count) => count,
+            Self::Target(size) => {
+                let size = size.max(UVec2::ONE);
+                UVec3::new(
+                    target_size.x.div_ceil(size.x),
+                    target_size.y.div_ceil(size.y),
+                    1,
+                )
+            }
+        }
+    }
+}
+
+/// # Compute Resources
+///
+/// Render resource with the named buffers and textures of the [ComputeNode]s of the render target.
+/// Other nodes can bind them, e.g. as vertex buffers of particles simulated by a compute node, or
+/// insert their own before the compute nodes run, e.g. to initialize a buffer. Buffers can be
+/// used as storage, vertex, and indirect buffers, and textures as storage and sampled textures,
+/// and both can be copied from and to.
+///
+/// ```
+/// # use pulse::render::compute::ComputeResources;
+/// # use pulse::render::graph::RenderContext;
+/// # use pulse::render::graph::RenderNode;
+/// # use pulse::Scene;
+/// struct DrawParticles;
+///
+/// impl RenderNode for DrawParticles {
+///     fn run(&mut self, context: &mut RenderContext, _: &Scene) {
+///         let Some(particles) = context
+///             .resource::<ComputeResources>()
+///             .and_then(|resources| resources.buffer("particles"))
+///         else {
+///             return;
+///         };
+///         // Draw the particles from the buffer...
+///     }
+/// }
+/// ```
+#[derive(Debug, Default)]
+pub struct ComputeResources {
+    buffers: HashMap<&'static str, wgpu::Buffer>,
+    textures: HashMap<&'static str, (wgpu::Texture, wgpu::TextureView)>,
+}
+
+impl ComputeResources {
+    /// Returns the buffer with the name, if any.
+    pub fn buffer(&self, name: &str) -> Option<&wgpu::Buffer> {
+        self.buffers.get(name)
+    }
+
+    /// Returns the texture with the name, if any.
+    pub fn texture(&self, name: &str) -> Option<&wgpu::Texture> {
+        self.textures.get(name).map(|(texture, _)| texture)
+    }
+
+    /// Returns the view of the texture with the name, if any.
+    pub fn view(&self, name: &str) -> Option<&wgpu::TextureView> {
+        self.textures.get(name).map(|(_, view)| view)
+    }
+
+    /// Inserts the buffer with the name, replacing the previous one.
+    pub fn insert_buffer(&mut self, name: &'static str, buffer: wgpu::Buffer) {
+        self.buffers.insert(name, buffer);
+    }
+
+    /// Inserts the texture with the name, replacing the previous one.
+    pub fn insert_texture(&mut self, name: &'static str, texture: wgpu::Texture) {
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        self.textures.insert(name, (texture, view));
+    }
+
+    /// Creates the buffer with the name unless there's one of at least the size.
+    fn prepare_buffer(&mut self, device: &wgpu::Device, name: &'static str, size: u64) {
+        if self
+            .buffers
+            .get(name)
+            .is_some_and(|buffer| buffer.size() >= size)
+        {
+            return;
+        }
+
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(name),
+            size: size.max(4).next_multiple_of(4),
+            usage: wgpu::BufferUsages::STORAGE
+                | wgpu::BufferUsages::VERTEX
+                | wgpu::BufferUsages::INDIRECT
+                | wgpu::BufferUsages::COPY_SRC
+                | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        self.buffers.insert(name, buffer);
+    }
+
+    /// Creates the texture with the name unless there's one of the format and size.
+    fn prepare_texture(
+        &mut self,
+        device: &wgpu::Device,
+        name: &'static str,
+        format: wgpu::TextureFormat,
+        size: UVec2,
+    ) {
+        let size = wgpu::Extent3d {
+            width: size.x.max(1),
+            height: size.y.max(1),
+            depth_or_array_layers: 1,
+        };
+        if self
+            .texture(name)
+            .is_some_and(|texture| texture.format() == format && texture.size() == size)
+        {
+            return;
+        }
+
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(name),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::STORAGE_BINDING
+                | wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_SRC
+                | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        self.insert_texture(name, texture);
+    }
+}
+
+/// # Compute Node
+///
+/// Render node dispatching the entry point of a WGSL compute shader with its [ComputeBinding]s.
+/// The shader is compiled the first time the node runs, and a node whose shader fails to compile
+/// prints the error and never dispatches.
+///
+/// ```
+/// # use glam::UVec3;
+/// # use pulse::render::compute::ComputeBinding;
+/// # use pulse::render::compute::ComputeNode;
+/// # use pulse::render::compute::Workgroups;
+/// # use pulse::render::graph::RenderGraph;
+/// # use pulse::render::graph::FORWARD;
+/// # use pulse::Time;
+/// let shader = "
+///     @group(0) @binding(0) var<uniform> delta: vec4<f32>;
+///     @group(0) @binding(1) var<storage, read_write> particles: array<vec4<f32>>;
+///
+///     @compute @workgroup_size(64)
+///     fn update(@builtin(global_invocation_id) id: vec3<u32>) {
+///         particles[id.x].y -= delta.x;
+///     }
+/// ";
+/// let particles = ComputeNode::new(shader, "update", Workgroups::Count(UVec3::new(16, 1, 1)))
+///     .with_binding(ComputeBinding::uniform(|scene| {
+///         let delta = scene.get_resource::<Time>().map_or(0.0, Time::delta_seconds);
+///         [delta, 0.0, 0.0, 0.0].iter().flat_map(|x| x.to_le_bytes()).collect()
+///     }))
+///     .with_binding(ComputeBinding::Buffer {
+///         name: "particles",
+///         size: 1024 * 16,
+///         read_only: false,
+///     });
+///
+/// let mut graph = RenderGraph::new();
+/// graph.add_node("particles", particles);
+/// ```
+pub struct ComputeNode {
+    source: String,
+    entry_point: &'static str,
+    workgroups: Workgroups,
+    bindings: Vec<ComputeBinding>,
+    pipeline: Option<Option<ComputePipeline>>,
+}
+
+struct ComputePipeline {
+    pipeline: wgpu::ComputePipeline,
+    layout: wgpu::BindGroupLayout,
+    uniforms: Vec<Option<wgpu::Buffer>>,
+}
+
+impl ComputeNode {
+    /// Returns the node dispatching the workgroups of the shader's entry point without bindings.
+    pub fn new(
+        source: impl Into<String>,
+        entry_point: &'static str,
+        workgroups: Workgroups,
+    ) -> Self {
+        Self {
+            source: source.into(),
+            entry_point,
+            workgroups,
+            bindings: Vec::new(),
+            pipeline: None,
+        }
+    }
+
+    /// Adds the binding after the previous ones.
+    pub fn with_binding(mut self, binding: ComputeBinding) -> Self {
+        self.bindings.push(binding);
+        self.pipeline = None;
+        self
+    }
+}
+
+impl RenderNode for ComputeNode {
+    fn run(&mut self, context: &mut RenderContext, scene: &Scene) {
+        let device = context.device;
+        let target_size = context.target_size;
+        let workgroups = self.workgroups.count(target_size);
+        let mut resources = context
+            .remove_resource::<ComputeResources>()
+            .unwrap_or_default();
+        for binding in &self.bindings {
+            match binding {
+                ComputeBinding::Buffer { name, size, .. } => {
+                    resources.prepare_buffer(device, name, *size);
+                }
+                ComputeBinding::StorageTexture { name, format, size } => {
+                    resources.prepare_texture(device, name, *format, size.unwrap_or(target_size));
+                }
+                ComputeBinding::Uniform(_) | ComputeBinding::Texture(_) => {}
+            }
+        }
+
+        let missing = self.bindings.iter().any(|binding| match binding {
+            ComputeBinding::Texture(name) => resources.view(name).is_none(),
+            _ => false,
+        });
+        let bindings = &self.bindings;
+        let pipeline = self
+            .pipeline
+            .get_or_insert_with(|| compile(device, &self.source, self.entry_point, bindings));
+        if let Some(pipeline) = pipeline.as_mut().filter(|_| !missing) {
+            for (binding, uniform) in bindings.iter().zip(&mut pipeline.uniforms) {
+                let ComputeBinding::Uniform(bytes) = binding else {
+                    continue;
+                };
+                let mut bytes = bytes(scene);
+                bytes.resize(bytes.len().max(16).next_multiple_of(16), 0);
+                if uniform
+                    .as_ref()
+                    .is_none_or(|buffer| buffer.size() != bytes.len() as u64)
+                {
+                    *uniform = Some(device.create_buffer(&wgpu::BufferDescriptor {
+                        label: Some("compute uniforms"),
+                        size: bytes.len() as u64,
+                        usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                        mapped_at_creation: false,
+                    }));
+                }
+                context
+                    .queue
+                    .write_buffer(uniform.as_ref().unwrap(), 0, &bytes);
+            }
+
+            let entries = bindings
+                .iter()
+                .zip(&pipeline.uniforms)
+                .enumerate()
+                .map(|(index, (binding, uniform))| wgpu::BindGroupEntry {
+                    binding: index as u32,
+                    resource: match binding {
+                        ComputeBinding::Uniform(_) => uniform.as_ref().unwrap().as_entire_binding(),
+                        ComputeBinding::Buffer { name, .. } => {
+                            resources.buffer(name).unwrap().as_entire_binding()
+                        }
+                        ComputeBinding::StorageTexture { name, .. }
+                        | ComputeBinding::Texture(name) => {
+                            wgpu::BindingResource::TextureView(resources.view(name).unwrap())
+                        }
+                    },
+                })
+                .collect::<Vec<_>>();
+            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("compute"),
+                layout: &pipeline.layout,
+                entries: &entries,
+            });
+
+            let mut pass = context
+                .encoder
+                .begin_compute_pass(&wgpu::ComputePassDescriptor {
+                    label: Some("compute"),
+                    timestamp_writes: None,
+                });
+            pass.set_pipeline(&pipeline.pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            pass.dispatch_workgroups(workgroups.x, workgroups.y, workgroups.z);
+        }
+        context.insert_resource(resources);
+    }
+}
+
+/// Returns the pipeline of the shader's entry point with the bindings, or `None` after printing
+/// the error if the shader is invalid.
+fn compile(
+    device: &wgpu::Device,
+    source: &str,
+    entry_point: &str,
+    bindings: &[ComputeBinding],
+) -> Option<ComputePipeline> {
+    if let Err(error) = validate(source) {
+        println!("Failed to compile compute shader: {error}");
+        return None;
+    }
+
+    let entries = bindings
+        .iter()
+        .enumerate()
+        .map(|(index, binding)| binding.layout_entry(index as u32))
+        .collect::<Vec<_>>();
+    let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some("compute"),
+        entries: &entries,
+    });
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("compute"),
+        source: wgpu::ShaderSource::Wgsl(source.into()),
+    });
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("compute"),
+        bind_group_layouts: &[&layout],
+        push_constant_ranges: &[],
+    });
+    let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: Some("compute"),
+        layout: Some(&pipeline_layout),
+        module: &shader,
+        entry_point,
+        compilation_options: wgpu::PipelineCompilationOptions::default(),
+        cache: None,
+    });
+    Some(ComputePipeline {
+        pipeline,
+        layout,
+        uniforms: bindings.iter().map(|_| None).collect(),
+    })
+}
+
+/// Parses and validates the shader, returning the error message if it's invalid.
+fn validate(source: &str) -> Result<(), String> {
+    let module =
+        naga::front::wgsl::parse_str(source).map_err(|error| error.emit_to_string(source))?;
+    naga::valid::Validator::new(
+        naga::valid::ValidationFlags::all(),
+        naga::valid::Capabilities::default(),
+    )
+    .validate(&module)
+    .map_err(|error| error.emit_to_string(source))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::graph::GpuResources;
+    use crate::render::graph::RenderGraph;
+    use crate::render::stats::FrameProfiler;
+    use crate::render::tests::device;
+    use crate::render::tests::target;
+
+    /// Runs the graph once on a 4x4 target and returns the `u32`s of the compute buffer with the
+    /// name, or `None` if there's no adapter.
+    fn run(mut graph: RenderGraph, name: &str) -> Option<Vec<u32>> {
+        let (device, queue) = device()?;
+        let size = UVec2::splat(4);
+        let format = wgpu::TextureFormat::Rgba8Unorm;
+        let view = target(&device, format, size).create_view(&Default::default());
+        let mut resources = GpuResources::default();
+        let mut profiler = FrameProfiler::new(&device);
+        let mut encoder = device.create_command_encoder(&Default::default());
+        let mut context = RenderContext {
+            device: &device,
+            queue: &queue,
+            encoder: &mut encoder,
+            target: &view,
+            target_format: format,
+            target_size: size,
+            cameras: &[],
+            resources: &mut resources,
+            profiler: &mut profiler,
+        };
+        graph.run(&mut context, &Scene::new());
+
+        let buffer = resources.resource::<ComputeResources>()?.buffer(name)?;
+        let readback = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("readback"),
+            size: buffer.size(),
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        encoder.copy_buffer_to_buffer(buffer, 0, &readback, 0, buffer.size());
+        queue.submit([encoder.finish()]);
+        readback
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, |result| result.unwrap());
+        device.poll(wgpu::Maintain::Wait);
+        let words = bytemuck::cast_slice(&readback.slice(..).get_mapped_range()).to_vec();
+        Some(words)
+    }
+
+    #[test]
+    fn compute_node_writes_buffer_with_uniforms() {
+        let shader = "
+            @group(0) @binding(0) var<uniform> offset: vec4<u32>;
+            @group(0) @binding(1) var<storage, read_write> values: array<u32>;
+
+            @compute @workgroup_size(8)
+            fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+                values[id.x] = id.x * 2u + offset.x;
+            }
+        ";
+        let node = ComputeNode::new(shader, "main", Workgroups::Count(UVec3::new(2, 1, 1)))
+            .with_binding(ComputeBinding::uniform(|_| 7_u32.to_le_bytes().to_vec()))
+            .with_binding(ComputeBinding::Buffer {
+                name: "values",
+                size: 16 * 4,
+                read_only: false,
+            });
+        let mut graph = RenderGraph::new();
+        graph.add_node("values", node);
+
+        let Some(values) = run(graph, "values") else {
+            return;
+        };
+
+        assert_eq!(values, (0..16).map(|x| x * 2 + 7).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn compute_nodes_share_textures() {
+        let write = "
+            @group(0) @binding(0) var image: texture_storage_2d<r32float, write>;
+
+            @compute @workgroup_size(2, 2)
+            fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+                textureStore(image, id.xy, vec4<f32>(f32(id.x + id.y * 4u), 0.0, 0.0, 1.0));
+            }
+        ";
+        let read = "
+            @group(0) @binding(0) var image: texture_2d<f32>;
+            @group(0) @binding(1) var<storage, read_write> sum: array<u32>;
+
+            @compute @workgroup_size(1)
+            fn main() {
+                var total = 0.0;
+                for (var y = 0; y < 4; y++) {
+                    for (var x = 0; x < 4; x++) {
+                        total += textureLoad(image, vec2<i32>(x, y), 0).r;
+                    }
+                }
+                sum[0] = u32(total);
+            }
+        ";
+        let mut graph = RenderGraph::new();
+        graph
+            .add_node(
+                "sum",
+                ComputeNode::new(read, "main", Workgroups::Count(UVec3::ONE))
+                    .with_binding(ComputeBinding::Texture("image"))
+                    .with_binding(ComputeBinding::Buffer {
+                        name: "sum",
+                        size: 4,
+                        read_only: false,
+                    }),
+            )
+            .after("image");
+        graph.add_node(
+            "image",
+            ComputeNode::new(write, "main", Workgroups::Target(UVec2::splat(2))).with_binding(
+                ComputeBinding::StorageTexture {
+                    name: "image",
+                    format: wgpu::TextureFormat::R32Float,
+                    size: None,
+                },
+            ),
+        );
+
+        let Some(sum) = run(graph, "sum") else {
+            return;
+        };
+
+        assert_eq!(sum, [(0..16).sum::<u32>()]);
+    }
+}
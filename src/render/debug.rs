@@ -0,0 +1,371 @@
+//! # Debug
+//!
+//! Debug views of the renderer toggled at runtime with the [DebugView] resource. They're drawn by
+//! the forward node from the same draws as the meshes, so they show what the renderer actually
+//! draws.
+
+use std::collections::HashMap;
+
+use glam::Vec3;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::render::forward::DEPTH_FORMAT;
+use crate::render::light::world_transform;
+use crate::render::mesh::mesh_shader;
+use crate::render::mesh::Instance;
+use crate::render::mesh::Mesh;
+use crate::render::mesh::Vertex;
+use crate::render::tonemap::HDR_FORMAT;
+use crate::Aabb;
+use crate::Node;
+use crate::Reflect;
+use crate::Scene;
+
+/// # Debug View
+///
+/// Scene resource selecting debug views of the meshes seen by the cameras. The scene is rendered
+/// normally if it has none.
+///
+/// ```
+/// # use pulse::render::debug::DebugShading;
+/// # use pulse::render::debug::DebugView;
+/// # use pulse::Scene;
+/// let mut scene = Scene::new();
+/// scene.insert_resource(DebugView {
+///     shading: DebugShading::Normals,
+///     wireframe: true,
+///     ..DebugView::default()
+/// });
+/// ```
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Reflect, Serialize, Deserialize)]
+pub struct DebugView {
+    /// What the meshes' pixels show.
+    pub shading: DebugShading,
+    /// Whether the edges of the meshes' triangles are drawn in white over them.
+    pub wireframe: bool,
+    /// Whether the [Aabb]s of the visible nodes, or of their meshes if they have none, are drawn
+    /// in yellow.
+    pub bounds: bool,
+}
+
+/// # Debug Shading
+///
+/// What the pixels of the meshes show in a [DebugView].
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash, Reflect, Serialize, Deserialize)]
+pub enum DebugShading {
+    /// The shaded meshes.
+    #[default]
+    Lit,
+    /// The world space normals of the meshes, with their components mapped from -1..1 to 0..1.
+    Normals,
+    /// How many fragments were shaded for each pixel, brighter the more meshes cover it, ignoring
+    /// the depth test.
+    Overdraw,
+}
+
+impl DebugShading {
+    /// Returns the shader def of the mesh shader for the shading, if any.
+    pub(crate) fn def(self) -> Option<&'static str> {
+        match self {
+            Self::Lit => None,
+            Self::Normals => Some("DEBUG_NORMALS"),
+            Self::Overdraw => Some("DEBUG_OVERDRAW"),
+        }
+    }
+}
+
+/// Lines drawn by the debug views.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub(crate) enum DebugLines {
+    /// Edges of meshes, drawn with the vertex buffers of the meshes and the instance buffer.
+    Wireframe,
+    /// Bounding boxes, drawn with a vertex buffer of world space positions.
+    Bounds,
+}
+
+/// Pipelines drawing the lines of the debug views and the vertex buffer of the bounding boxes.
+pub(crate) struct DebugPipelines {
+    shader: wgpu::ShaderModule,
+    /// Pipelines for each kind of lines and sample count.
+    pipelines: HashMap<(DebugLines, u32), wgpu::RenderPipeline>,
+    pub(crate) bounds: wgpu::Buffer,
+}
+
+impl DebugPipelines {
+    pub(crate) fn new(device: &wgpu::Device) -> Self {
+        Self {
+            shader: mesh_shader(device, "debug", include_str!("shaders/debug.wgsl")),
+            pipelines: HashMap::new(),
+            bounds: bounds_buffer(device, 1),
+        }
+    }
+
+    /// Creates the pipeline for the lines and the sample count, with the camera bind group of the
+    /// forward pass, if it doesn't exist yet.
+    pub(crate) fn prepare(
+        &mut self,
+        device: &wgpu::Device,
+        camera_layout: &wgpu::BindGroupLayout,
+        lines: DebugLines,
+        samples: u32,
+    ) {
+        let shader = &self.shader;
+        self.pipelines.entry((lines, samples)).or_insert_with(|| {
+            let (label, vertex, fragment) = match lines {
+                DebugLines::Wireframe => ("debug wireframe", "vs_wireframe", "fs_wireframe"),
+                DebugLines::Bounds => ("debug bounds", "vs_bounds", "fs_bounds"),
+            };
+            let buffers = match lines {
+                DebugLines::Wireframe => vec![Vertex::layout(), Instance::layout()],
+                DebugLines::Bounds => vec![wgpu::VertexBufferLayout {
+                    array_stride: std::mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
+                    step_mode: wgpu::VertexStepMode::Vertex,
+                    attributes: &wgpu::vertex_attr_array![0 => Float32x3],
+                }],
+            };
+            let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some(label),
+                bind_group_layouts: &[camera_layout],
+                push_constant_ranges: &[],
+            });
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(label),
+                layout: Some(&layout),
+                vertex: wgpu::VertexState {
+                    module: shader,
+                    entry_point: vertex,
+                    compilation_options: wgpu::PipelineCompilationOptions::default(),
+                    buffers: &buffers,
+                },
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::LineList,
+                    ..wgpu::PrimitiveState::default()
+                },
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: DEPTH_FORMAT,
+                    depth_write_enabled: false,
+                    depth_compare: wgpu::CompareFunction::LessEqual,
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState::default(),
+                }),
+                multisample: wgpu::MultisampleState {
+                    count: samples,
+                    ..wgpu::MultisampleState::default()
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: shader,
+                    entry_point: fragment,
+                    compilation_options: wgpu::PipelineCompilationOptions::default(),
+                    targets: &[Some(HDR_FORMAT.into())],
+                }),
+                multiview: None,
+                cache: None,
+            })
+        });
+    }
+
+    /// Returns the pipeline for the lines and the sample count if it was prepared.
+    pub(crate) fn get(&self, lines: DebugLines, samples: u32) -> Option<&wgpu::RenderPipeline> {
+        self.pipelines.get(&(lines, samples))
+    }
+
+    /// Writes the positions of the bounding boxes' lines to the bounds buffer, growing it if
+    /// needed.
+    pub(crate) fn write_bounds(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        positions: &[[f32; 3]],
+    ) {
+        let size = std::mem::size_of_val(positions) as wgpu::BufferAddress;
+        if self.bounds.size() < size {
+            self.bounds = bounds_buffer(device, (positions.len() as u64).next_power_of_two());
+        }
+        queue.write_buffer(&self.bounds, 0, bytemuck::cast_slice(positions));
+    }
+}
+
+/// Appends the world space positions of the line list of the bounding box of each visible node
+/// with an [Aabb] or a [Mesh] to the positions.
+pub(crate) fn bounds_lines(scene: &Scene, visible: &[Node], positions: &mut Vec<[f32; 3]>) {
+    const EDGES: [(usize, usize); 12] = [
+        (0, 1),
+        (2, 3),
+        (4, 5),
+        (6, 7),
+        (0, 2),
+        (1, 3),
+        (4, 6),
+        (5, 7),
+        (0, 4),
+        (1, 5),
+        (2, 6),
+        (3, 7),
+    ];
+
+    for &node in visible {
+        let Some(aabb) = scene
+            .get::<Aabb>(node)
+            .copied()
+            .or_else(|| scene.get::<Mesh>(node).and_then(Mesh::aabb))
+        else {
+            continue;
+        };
+        let transform = world_transform(scene, node);
+        // Corner i has the maximum coordinate along the axes of its set bits.
+        let corners: [Vec3; 8] = std::array::from_fn(|i| {
+            let max = |bit| i & bit != 0;
+            let corner = Vec3::new(
+                if max(1) { aabb.max.x } else { aabb.min.x },
+                if max(2) { aabb.max.y } else { aabb.min.y },
+                if max(4) { aabb.max.z } else { aabb.min.z },
+            );
+            transform.transform_point(corner)
+        });
+        for (a, b) in EDGES {
+            positions.extend([corners[a].to_array(), corners[b].to_array()]);
+        }
+    }
+}
+
+fn bounds_buffer(device: &wgpu::Device, positions: u64) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("debug bounds"),
+        size: positions.max(1) * std::mem::size_of::<[f32; 3]>() as u64,
+        usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_2;
+
+    use glam::Mat4;
+
+    use super::*;
+    use crate::render::anti_aliasing::AntiAliasing;
+    use crate::render::material::Material;
+    use crate::render::material::StandardMaterial;
+    use crate::render::tests::render;
+    use crate::render::tonemap::Tonemapping;
+    use crate::render::Color;
+    use crate::Camera;
+    use crate::VisibleNodes;
+    use crate::WorldTransform;
+
+    #[test]
+    fn bounds_lines_follow_transform() {
+        let mut scene = Scene::new();
+        let transform = WorldTransform::new(Mat4::from_translation(Vec3::X));
+        let nodes = [
+            scene.spawn_with((Mesh::cube(2.0), transform)),
+            scene.spawn_with((Aabb::new(Vec3::ZERO, Vec3::ONE), Mesh::cube(4.0))),
+            scene.spawn_with(transform),
+        ];
+        let mut positions = Vec::new();
+
+        bounds_lines(&scene, &nodes, &mut positions);
+
+        assert_eq!(positions.len(), 2 * 24);
+        let (min, max) = positions[..24].iter().fold(
+            (Vec3::splat(f32::MAX), Vec3::splat(f32::MIN)),
+            |(min, max), &position| (min.min(position.into()), max.max(position.into())),
+        );
+        assert_eq!(
+            (min, max),
+            (Vec3::new(0.0, -1.0, -1.0), Vec3::new(2.0, 1.0, 1.0))
+        );
+        assert!(positions[24..]
+            .iter()
+            .all(|position| position.iter().all(|x| (0.0..=1.0).contains(x))));
+    }
+
+    /// Renders a red unlit cube in front of a plane covering the view with the debug view, seen
+    /// from +Z with the cube's front face covering the center, and returns the 64x64 RGBA pixels,
+    /// or `None` if there's no adapter.
+    fn render_debug(view: DebugView) -> Option<Vec<u8>> {
+        let mut scene = Scene::new();
+        scene.insert_resource(AntiAliasing::None);
+        scene.insert_resource(view);
+        let material = Material::new(StandardMaterial {
+            base_color: Color::BLACK,
+            emissive: Color::rgb(1.0, 0.0, 0.0),
+            ..StandardMaterial::default()
+        });
+        let cube = scene.spawn_with((Mesh::cube(1.0), material.clone(), WorldTransform::IDENTITY));
+        let plane = scene.spawn_with((
+            Mesh::plane(100.0),
+            material,
+            WorldTransform::new(
+                Mat4::from_translation(Vec3::NEG_Z) * Mat4::from_rotation_x(FRAC_PI_2),
+            ),
+        ));
+        scene.spawn_with((
+            Camera {
+                tonemapping: Tonemapping::None,
+                ..Camera::perspective(1.0, 1.0, 0.1, 10.0)
+            },
+            WorldTransform::new(Mat4::from_translation(Vec3::new(0.0, 0.0, 2.0))),
+            VisibleNodes(vec![cube, plane]),
+        ));
+        render(&scene)
+    }
+
+    /// Returns the number of pixels with the RGB color.
+    fn count(pixels: &[u8], color: [u8; 3]) -> usize {
+        pixels
+            .chunks_exact(4)
+            .filter(|pixel| pixel[..3] == color)
+            .count()
+    }
+
+    #[test]
+    fn render_draws_wireframe_and_bounds_lines() {
+        let Some(pixels) = render_debug(DebugView::default()) else {
+            return;
+        };
+        assert_eq!(count(&pixels, [255, 255, 255]), 0);
+        assert_eq!(count(&pixels, [255, 255, 0]), 0);
+
+        let pixels = render_debug(DebugView {
+            wireframe: true,
+            ..DebugView::default()
+        })
+        .unwrap();
+        assert!(count(&pixels, [255, 255, 255]) > 32);
+        assert_eq!(count(&pixels, [255, 255, 0]), 0);
+
+        let pixels = render_debug(DebugView {
+            bounds: true,
+            ..DebugView::default()
+        })
+        .unwrap();
+        assert_eq!(count(&pixels, [255, 255, 255]), 0);
+        assert!(count(&pixels, [255, 255, 0]) > 32);
+    }
+
+    #[test]
+    fn render_shades_normals_and_overdraw() {
+        let Some(pixels) = render_debug(DebugView {
+            shading: DebugShading::Normals,
+            ..DebugView::default()
+        }) else {
+            return;
+        };
+        // The front face's normal is +Z.
+        assert_eq!(&pixels[(32 * 64 + 32) * 4..][..4], [128, 128, 255, 255]);
+
+        let pixels = render_debug(DebugView {
+            shading: DebugShading::Overdraw,
+            ..DebugView::default()
+        })
+        .unwrap();
+        // The cube's front face and the plane behind it cover the center, and the back faces are
+        // culled.
+        let (center, corner) = (pixels[(32 * 64 + 32) * 4], pixels[0]);
+        assert!(corner > 16);
+        assert!(center.abs_diff(corner * 2) <= 1);
+    }
+}
@@ -0,0 +1,446 @@
+//! # Deferred
+//!
+//! Deferred rendering path, selected with the [RenderPath] resource. The
+//! [crate::render::graph::FORWARD] node then draws the surfaces of the opaque and alpha masked
+//! meshes with a standard material into the [GBuffer] of each camera, and lights each pixel of the
+//! G-buffer once in a fullscreen pass, so the cost of lighting doesn't grow with the number of
+//! overlapping meshes. Both passes are variants of the same physically based shader as the forward
+//! path. Alpha blended meshes and meshes with a shader material are drawn forward on top.
+
+use glam::UVec2;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::render::forward::pbr_shader;
+use crate::render::forward::CUSTOM_MATERIAL;
+use crate::render::graph::RenderContext;
+use crate::render::tonemap::HDR_FORMAT;
+use crate::Reflect;
+
+/// Shader def of the physically based shader writing the surface to the G-buffer.
+pub(crate) const DEFERRED_GBUFFER: &str = "DEFERRED_GBUFFER";
+
+/// Shader def of the physically based shader with the pass lighting the G-buffer.
+const DEFERRED_LIGHTING: &str = "DEFERRED_LIGHTING";
+
+/// # Render Path
+///
+/// Scene resource choosing how the meshes are drawn. Forward rendering is used if the scene has
+/// none. Deferred rendering suits scenes with many lights and post effects reading the
+/// [GBuffer]. It's only used without MSAA and debug shadings, falling back to forward rendering
+/// otherwise.
+///
+/// ```
+/// # use pulse::render::anti_aliasing::AntiAliasing;
+/// # use pulse::render::deferred::RenderPath;
+/// # use pulse::Scene;
+/// let mut scene = Scene::new();
+/// scene.insert_resource(RenderPath::Deferred);
+/// scene.insert_resource(AntiAliasing::Taa);
+/// ```
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Reflect, Serialize, Deserialize)]
+pub enum RenderPath {
+    /// Meshes are shaded as they're drawn.
+    #[default]
+    Forward,
+    /// Surfaces of opaque meshes are drawn to a G-buffer first and lit in a fullscreen pass.
+    Deferred,
+}
+
+/// # G-Buffer Target
+///
+/// Texture of a [GBuffer].
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum GBufferTarget {
+    /// Base color and metallic, in [wgpu::TextureFormat::Rgba8UnormSrgb].
+    BaseColor,
+    /// World space normal and roughness, in [wgpu::TextureFormat::Rgba16Float].
+    Normal,
+    /// Emissive color and ambient occlusion, in [wgpu::TextureFormat::Rgba16Float].
+    Emissive,
+    /// One where the surface receives shadows and zero otherwise, in
+    /// [wgpu::TextureFormat::R8Unorm].
+    ShadowReceiver,
+}
+
+impl GBufferTarget {
+    /// Targets in the order of the G-buffer shader's outputs.
+    const ALL: [Self; 4] = [
+        Self::BaseColor,
+        Self::Normal,
+        Self::Emissive,
+        Self::ShadowReceiver,
+    ];
+
+    /// Returns the format of the target's textures.
+    pub fn format(self) -> wgpu::TextureFormat {
+        match self {
+            Self::BaseColor => wgpu::TextureFormat::Rgba8UnormSrgb,
+            Self::Normal | Self::Emissive => wgpu::TextureFormat::Rgba16Float,
+            Self::ShadowReceiver => wgpu::TextureFormat::R8Unorm,
+        }
+    }
+}
+
+/// # G-Buffer
+///
+/// Render resource with the surfaces of the opaque meshes seen by each camera of the render
+/// target, in the order of [RenderContext::cameras], drawn by the [crate::render::graph::FORWARD]
+/// node on the [RenderPath::Deferred]. The resource only exists in frames rendered on the deferred
+/// path. Pixels without a surface are zero.
+///
+/// ```
+/// # use pulse::render::deferred::GBuffer;
+/// # use pulse::render::deferred::GBufferTarget;
+/// # use pulse::render::graph::RenderContext;
+/// # use pulse::render::graph::RenderNode;
+/// # use pulse::Scene;
+/// struct Outline;
+///
+/// impl RenderNode for Outline {
+///     fn run(&mut self, context: &mut RenderContext, _: &Scene) {
+///         let Some(gbuffer) = context.resource::<GBuffer>() else {
+///             return;
+///         };
+///         for camera in 0..context.cameras().len() {
+///             let normals = gbuffer.view(camera, GBufferTarget::Normal).unwrap();
+///             // Find edges between the normals in a fullscreen pass...
+///         }
+///     }
+/// }
+/// ```
+pub struct GBuffer {
+    size: UVec2,
+    /// Views of the textures of each camera, in the order of [GBufferTarget::ALL].
+    views: Vec<[wgpu::TextureView; 4]>,
+}
+
+impl GBuffer {
+    /// Returns the view of the target's texture of the camera with the index.
+    pub fn view(&self, camera: usize, target: GBufferTarget) -> Option<&wgpu::TextureView> {
+        Some(&self.views.get(camera)?[target as usize])
+    }
+
+    /// Inserts textures for the cameras with the size of the context's target into the context
+    /// unless it has them already.
+    pub(crate) fn prepare(context: &mut RenderContext) {
+        let size = context.target_size;
+        let cameras = context.cameras.len();
+        if context
+            .resource::<Self>()
+            .is_some_and(|gbuffer| gbuffer.size == size && gbuffer.views.len() >= cameras)
+        {
+            return;
+        }
+
+        let device = context.device;
+        let views = (0..cameras)
+            .map(|_| {
+                GBufferTarget::ALL.map(|target| {
+                    device
+                        .create_texture(&wgpu::TextureDescriptor {
+                            label: Some("gbuffer"),
+                            size: wgpu::Extent3d {
+                                width: size.x.max(1),
+                                height: size.y.max(1),
+                                depth_or_array_layers: 1,
+                            },
+                            mip_level_count: 1,
+                            sample_count: 1,
+                            dimension: wgpu::TextureDimension::D2,
+                            format: target.format(),
+                            usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                                | wgpu::TextureUsages::TEXTURE_BINDING,
+                            view_formats: &[],
+                        })
+                        .create_view(&wgpu::TextureViewDescriptor::default())
+                })
+            })
+            .collect();
+        context.insert_resource(Self { size, views });
+    }
+
+    /// Returns the color attachments of the camera's textures, cleared to zero.
+    pub(crate) fn attachments(
+        &self,
+        camera: usize,
+    ) -> [Option<wgpu::RenderPassColorAttachment<'_>>; 4] {
+        self.views[camera].each_ref().map(|view| {
+            Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                    store: wgpu::StoreOp::Store,
+                },
+            })
+        })
+    }
+}
+
+/// Returns the color targets of the G-buffer shader.
+pub(crate) fn gbuffer_targets() -> [Option<wgpu::ColorTargetState>; 4] {
+    GBufferTarget::ALL.map(|target| Some(target.format().into()))
+}
+
+/// Fullscreen pipeline lighting the [GBuffer] of a camera onto the HDR target, with the camera's
+/// uniforms and lights in the forward pass's camera bind group.
+pub(crate) struct DeferredLighting {
+    pub(crate) pipeline: wgpu::RenderPipeline,
+    layout: wgpu::BindGroupLayout,
+}
+
+impl DeferredLighting {
+    pub(crate) fn new(
+        device: &wgpu::Device,
+        camera_layout: &wgpu::BindGroupLayout,
+        environment_layout: &wgpu::BindGroupLayout,
+        probe_layout: &wgpu::BindGroupLayout,
+    ) -> Self {
+        let texture = |binding, sample_type| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                sample_type,
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        };
+        let float = wgpu::TextureSampleType::Float { filterable: false };
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("gbuffer"),
+            entries: &[
+                texture(0, float),
+                texture(1, float),
+                texture(2, float),
+                texture(3, float),
+                texture(4, float),
+            ],
+        });
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("deferred lighting"),
+            source: wgpu::ShaderSource::Wgsl(
+                pbr_shader(&[CUSTOM_MATERIAL, DEFERRED_LIGHTING]).into(),
+            ),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("deferred lighting"),
+            bind_group_layouts: &[camera_layout, &layout, environment_layout, probe_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("deferred lighting"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_fullscreen",
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                buffers: &[],
+            },
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_lighting",
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                targets: &[Some(HDR_FORMAT.into())],
+            }),
+            multiview: None,
+            cache: None,
+        });
+
+        Self { pipeline, layout }
+    }
+
+    /// Returns the bind group of the camera's G-buffer textures and depth.
+    pub(crate) fn bind_group(
+        &self,
+        device: &wgpu::Device,
+        gbuffer: &GBuffer,
+        camera: usize,
+        depth: &wgpu::TextureView,
+    ) -> wgpu::BindGroup {
+        let [base_color, normal, emissive, shadow_receiver] = &gbuffer.views[camera];
+        let entry = |binding, view| wgpu::BindGroupEntry {
+            binding,
+            resource: wgpu::BindingResource::TextureView(view),
+        };
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("gbuffer"),
+            layout: &self.layout,
+            entries: &[
+                entry(0, base_color),
+                entry(1, normal),
+                entry(2, emissive),
+                entry(3, shadow_receiver),
+                entry(4, depth),
+            ],
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::sync::Mutex;
+
+    use glam::Mat4;
+    use glam::Vec3;
+
+    use super::*;
+    use crate::render::anti_aliasing::AntiAliasing;
+    use crate::render::graph::RenderNode;
+    use crate::render::graph::FORWARD;
+    use crate::render::graph::PREPASS;
+    use crate::render::light::DirectionalLight;
+    use crate::render::light::PointLight;
+    use crate::render::material::AlphaMode;
+    use crate::render::material::Material;
+    use crate::render::material::MaterialFeatures;
+    use crate::render::material::StandardMaterial;
+    use crate::render::mesh::Mesh;
+    use crate::render::tests::render_with;
+    use crate::render::tonemap::Tonemapping;
+    use crate::render::Color;
+    use crate::Camera;
+    use crate::ComputedVisibility;
+    use crate::Scene;
+    use crate::VisibleNodes;
+    use crate::WorldTransform;
+
+    #[test]
+    fn gbuffer_and_lighting_shaders_are_valid() {
+        use wgpu::naga;
+
+        let gbuffer = MaterialFeatures::all()
+            .filter(|features| !features.contains(MaterialFeatures::ALPHA_BLEND))
+            .map(|features| {
+                let mut defs = features.defs();
+                defs.push(DEFERRED_GBUFFER);
+                pbr_shader(&defs)
+            });
+        for source in gbuffer.chain([pbr_shader(&[CUSTOM_MATERIAL, DEFERRED_LIGHTING])]) {
+            let module = naga::front::wgsl::parse_str(&source).unwrap();
+            naga::valid::Validator::new(
+                naga::valid::ValidationFlags::all(),
+                naga::valid::Capabilities::empty(),
+            )
+            .validate(&module)
+            .unwrap();
+        }
+    }
+
+    /// Returns a scene of overlapping opaque, alpha masked, and alpha blended meshes lit by a
+    /// directional and a point light.
+    fn scene(path: RenderPath, anti_aliasing: AntiAliasing) -> Scene {
+        let mut scene = Scene::new();
+        scene.insert_resource(path);
+        scene.insert_resource(anti_aliasing);
+        let material = |alpha_mode| {
+            Material::new(StandardMaterial {
+                base_color: Color::rgba(0.2, 0.6, 0.9, 0.5),
+                emissive: Color::rgb(0.1, 0.0, 0.0),
+                alpha_mode,
+                ..StandardMaterial::default()
+            })
+        };
+        let at = |x, z| WorldTransform::new(Mat4::from_translation(Vec3::new(x, 0.0, z)));
+        let visible = vec![
+            scene.spawn_with((Mesh::cube(1.0), at(0.0, 0.0))),
+            scene.spawn_with((Mesh::sphere(0.8, 16, 8), at(0.3, -0.5))),
+            scene.spawn_with((
+                Mesh::cube(0.5),
+                material(AlphaMode::Mask(0.4)),
+                at(-0.5, 0.5),
+            )),
+            scene.spawn_with((Mesh::cube(0.5), material(AlphaMode::Blend), at(0.5, 0.8))),
+        ];
+        scene.spawn_with((
+            Camera {
+                tonemapping: Tonemapping::None,
+                ..Camera::perspective(1.0, 1.0, 0.1, 10.0)
+            },
+            WorldTransform::new(Mat4::from_translation(Vec3::new(0.0, 0.5, 3.0))),
+            VisibleNodes(visible),
+        ));
+        scene.spawn_with((
+            DirectionalLight::default(),
+            WorldTransform::new(Mat4::from_rotation_x(-0.5)),
+            ComputedVisibility::Visible,
+        ));
+        scene.spawn_with((
+            PointLight {
+                color: Color::rgb(1.0, 0.5, 0.0),
+                range: 3.0,
+                ..PointLight::default()
+            },
+            WorldTransform::new(Mat4::from_translation(Vec3::new(-1.0, 1.0, 1.0))),
+            ComputedVisibility::Visible,
+        ));
+        scene
+    }
+
+    #[test]
+    fn render_deferred_matches_forward() {
+        for prepass in [false, true] {
+            let render = |path| {
+                render_with(&scene(path, AntiAliasing::None), 1, |_, graph| {
+                    graph.set_enabled(PREPASS, prepass)
+                })
+            };
+            let Some(forward) = render(RenderPath::Forward) else {
+                return;
+            };
+            let deferred = render(RenderPath::Deferred).unwrap();
+
+            // The G-buffer quantizes the surfaces slightly.
+            let difference = forward
+                .iter()
+                .zip(&deferred)
+                .map(|(a, b)| a.abs_diff(*b))
+                .max();
+            assert!(difference <= Some(3), "{difference:?}");
+            assert_ne!(forward[(32 * 64 + 32) * 4..][..4], [0, 0, 0, 255]);
+        }
+    }
+
+    /// Render node recording whether the frame had a [GBuffer].
+    struct Probe(Arc<Mutex<Option<bool>>>);
+
+    impl RenderNode for Probe {
+        fn run(&mut self, context: &mut RenderContext, _: &Scene) {
+            let gbuffer = context.resource::<GBuffer>();
+            assert!(gbuffer.is_none_or(|gbuffer| {
+                gbuffer.view(0, GBufferTarget::Normal).is_some()
+                    && gbuffer.view(1, GBufferTarget::Normal).is_none()
+            }));
+            *self.0.lock().unwrap() = Some(gbuffer.is_some());
+        }
+    }
+
+    #[test]
+    fn gbuffer_exists_on_deferred_path_without_msaa() {
+        for (path, anti_aliasing, expected) in [
+            (RenderPath::Deferred, AntiAliasing::None, true),
+            (
+                RenderPath::Deferred,
+                AntiAliasing::Msaa { samples: 4 },
+                false,
+            ),
+            (RenderPath::Forward, AntiAliasing::None, false),
+        ] {
+            let probe = Arc::new(Mutex::new(None));
+            let node = Probe(probe.clone());
+            let rendered = render_with(&scene(path, anti_aliasing), 1, |_, graph| {
+                graph.add_node("probe", node).after(FORWARD);
+            });
+            if rendered.is_none() {
+                return;
+            }
+
+            assert_eq!(*probe.lock().unwrap(), Some(expected));
+        }
+    }
+}
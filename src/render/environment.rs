@@ -0,0 +1,875 @@
+//! # Environment
+//!
+//! Image-based lighting of meshes by their surroundings, captured in an equirectangular
+//! [HdrImage]. When an environment is first used, it's converted to a cubemap on the GPU, which
+//! is prefiltered into a specular cubemap with a mip for each roughness and an irradiance cubemap
+//! for diffuse lighting. The prefiltered cubemaps are read back and stored in the cache directory
+//! of the scene's [crate::assets::AssetServer], so unchanged environments are only filtered once.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::Weak;
+
+use bytemuck::Pod;
+use bytemuck::Zeroable;
+use wgpu::util::DeviceExt;
+
+use crate::assets::cache;
+use crate::assets::cache::ProcessedReader;
+use crate::assets::cache::ProcessedWriter;
+use crate::render::image::HdrImage;
+use crate::render::image::HdrImageData;
+use crate::render::UNIFORM_ALIGNMENT;
+use crate::Component;
+
+/// Number of mips of the specular cubemap, for roughness from 0 to 1.
+pub(crate) const SPECULAR_MIPS: u32 = 6;
+
+/// Size of the source cubemap the other cubemaps are filtered from.
+const SOURCE_SIZE: u32 = 256;
+
+/// Number of mips of the source cubemap, down to 1x1.
+const SOURCE_MIPS: u32 = SOURCE_SIZE.ilog2() + 1;
+
+/// Size of the specular cubemap's first mip.
+const SPECULAR_SIZE: u32 = 128;
+
+/// Size of the irradiance cubemap, which has no high frequencies.
+const IRRADIANCE_SIZE: u32 = 32;
+
+/// Format of the cubemaps.
+const ENVIRONMENT_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+/// Size of a texel of the cubemaps in bytes.
+const TEXEL_SIZE: u32 = 8;
+
+/// # Environment Light
+///
+/// Light reaching surfaces from the surroundings captured in an equirectangular [HdrImage], e.g.
+/// a sky. Add it to a camera to light the meshes seen by the camera, or insert it into the scene
+/// as a resource to light the meshes seen by every camera without one. It replaces the
+/// [crate::render::light::AmbientLight].
+///
+/// ```
+/// # use pulse::render::environment::EnvironmentLight;
+/// # use pulse::render::image::HdrImage;
+/// # use pulse::Scene;
+/// let mut scene = Scene::new();
+/// scene.insert_resource(EnvironmentLight::new(HdrImage::from_pixel([0.4, 0.6, 1.0])));
+/// ```
+#[derive(Clone, Debug, PartialEq, Component)]
+pub struct EnvironmentLight {
+    /// Equirectangular image of the surroundings, with the top row straight up and the center
+    /// in the negative Z direction.
+    pub image: HdrImage,
+    /// Factor the light of the image is multiplied with.
+    pub intensity: f32,
+}
+
+impl EnvironmentLight {
+    /// Returns the light of the image with an intensity of one.
+    pub fn new(image: HdrImage) -> Self {
+        Self {
+            image,
+            intensity: 1.0,
+        }
+    }
+}
+
+/// Uniforms of a cube face rendered while generating the cubemaps.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+struct Params {
+    face: u32,
+    roughness: f32,
+    source_size: f32,
+    source_mips: f32,
+}
+
+/// Bind groups with the prefiltered cubemaps of the environment images used so far, keyed by the
+/// image's pixels, so images shared between cameras are filtered once.
+pub(crate) struct GpuEnvironments {
+    layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    generator: Generator,
+    /// Bind group with black cubemaps for cameras without an environment.
+    fallback: wgpu::BindGroup,
+    environments: HashMap<usize, (Weak<HdrImageData>, wgpu::BindGroup)>,
+    /// Cubemaps being read back to store them in the cache.
+    readbacks: Vec<Readback>,
+}
+
+/// Prefiltered cubemaps of an environment being read back to store them in the cache.
+struct Readback {
+    buffer: wgpu::Buffer,
+    directory: PathBuf,
+    key: u128,
+    mapped: Arc<Mutex<Option<Result<(), wgpu::BufferAsyncError>>>>,
+}
+
+impl GpuEnvironments {
+    pub(crate) fn new(device: &wgpu::Device) -> Self {
+        let cube = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                view_dimension: wgpu::TextureViewDimension::Cube,
+                multisampled: false,
+            },
+            count: None,
+        };
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("environment"),
+            entries: &[
+                cube(0),
+                cube(1),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("environment"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Linear,
+            ..wgpu::SamplerDescriptor::default()
+        });
+
+        // New textures are cleared to zero.
+        let black = cube_texture(device, 1, 1, wgpu::TextureUsages::TEXTURE_BINDING);
+        let black = black.create_view(&cube_view());
+        let fallback = bind_group(device, &layout, &sampler, &black, &black);
+
+        Self {
+            layout,
+            sampler,
+            generator: Generator::new(device),
+            fallback,
+            environments: HashMap::new(),
+            readbacks: Vec::new(),
+        }
+    }
+
+    /// Returns the layout of the bind groups of the environments.
+    pub(crate) fn layout(&self) -> &wgpu::BindGroupLayout {
+        &self.layout
+    }
+
+    /// Records the commands filtering the image's cubemaps if they weren't filtered yet. With a
+    /// cache directory, the cubemaps are uploaded from the cache if they were stored there, or else
+    /// read back once they're filtered to store them.
+    pub(crate) fn upload(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        image: &HdrImage,
+        cache: Option<&Path>,
+    ) {
+        if let Some((data, _)) = self.environments.get(&image.id()) {
+            // The id of a dropped image may have been reused by a new one.
+            if data.strong_count() > 0 {
+                return;
+            }
+        }
+
+        let cache = cache.map(|directory| (directory, cache_key(image)));
+        let cached = cache.and_then(|(directory, key)| {
+            let (_, processed) = cache::load(directory, key, |path| fs::read(path))?;
+            cached_cubemaps(device, queue, &processed)
+        });
+        let (specular, irradiance) = match (cached, cache) {
+            (Some(cubemaps), _) => cubemaps,
+            (None, Some((directory, key))) => {
+                // The cubemaps are filtered with their own commands, so they can be read back as
+                // soon as they're submitted.
+                let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("environment"),
+                });
+                let equirectangular = equirectangular_texture(device, queue, image);
+                let source = EnvironmentSource::Equirectangular(&equirectangular);
+                let (specular, irradiance) = self.generator.generate(device, &mut encoder, source);
+                let buffer = copy_cubemaps(device, &mut encoder, [&specular, &irradiance]);
+                queue.submit([encoder.finish()]);
+                self.readbacks
+                    .push(Readback::new(buffer, directory.to_path_buf(), key));
+                (specular, irradiance)
+            }
+            (None, None) => {
+                let equirectangular = equirectangular_texture(device, queue, image);
+                let source = EnvironmentSource::Equirectangular(&equirectangular);
+                self.generator.generate(device, encoder, source)
+            }
+        };
+        let bind_group = bind_group(
+            device,
+            &self.layout,
+            &self.sampler,
+            &specular.create_view(&cube_view()),
+            &irradiance.create_view(&cube_view()),
+        );
+        self.environments
+            .insert(image.id(), (image.downgrade(), bind_group));
+    }
+
+    /// Stores the cubemaps that were read back in the cache.
+    pub(crate) fn poll(&mut self, device: &wgpu::Device) {
+        if self.readbacks.is_empty() {
+            return;
+        }
+
+        device.poll(wgpu::Maintain::Poll);
+        self.readbacks.retain(|readback| {
+            let Some(mapped) = readback.mapped.lock().unwrap().take() else {
+                return true;
+            };
+            if let Err(error) = mapped
+                .map_err(io::Error::other)
+                .and_then(|()| readback.store())
+            {
+                println!("Failed to cache environment: {error}");
+            }
+            false
+        });
+    }
+
+    /// Returns the bind group of the image if it was uploaded, or else of a black environment.
+    pub(crate) fn get(&self, image: Option<&HdrImage>) -> &wgpu::BindGroup {
+        image
+            .and_then(|image| self.environments.get(&image.id()))
+            .map_or(&self.fallback, |(_, bind_group)| bind_group)
+    }
+
+    /// Drops the bind groups of images that no longer exist.
+    pub(crate) fn collect_garbage(&mut self) {
+        self.environments
+            .retain(|_, (data, _)| data.strong_count() > 0);
+    }
+}
+
+impl Readback {
+    /// Maps the buffer the cubemaps were copied to with [copy_cubemaps] after its commands were
+    /// submitted.
+    fn new(buffer: wgpu::Buffer, directory: PathBuf, key: u128) -> Self {
+        let mapped = Arc::new(Mutex::new(None));
+        let callback = Arc::clone(&mapped);
+        buffer
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                *callback.lock().unwrap() = Some(result);
+            });
+        Self {
+            buffer,
+            directory,
+            key,
+            mapped,
+        }
+    }
+
+    /// Stores the texels of every mip of the mapped buffer without the rows' padding.
+    fn store(&self) -> io::Result<()> {
+        let bytes = self.buffer.slice(..).get_mapped_range();
+        let mut writer = ProcessedWriter::new();
+        let mut offset = 0;
+        for (_, _, size) in cached_mips() {
+            let bytes_per_row = padded_bytes_per_row(size) as usize;
+            let rows = size as usize * 6;
+            let mip = &bytes[offset..offset + bytes_per_row * rows];
+            let texels = mip
+                .chunks_exact(bytes_per_row)
+                .flat_map(|row| &row[..(size * TEXEL_SIZE) as usize])
+                .copied()
+                .collect::<Vec<_>>();
+            writer.write_slice(&texels);
+            offset += bytes_per_row * rows;
+        }
+        drop(bytes);
+        self.buffer.unmap();
+
+        cache::store(
+            &self.directory,
+            self.key,
+            |path| fs::read(path),
+            &[],
+            &writer.into_bytes(),
+        )
+    }
+}
+
+/// Returns the key of the image's prefiltered cubemaps in the cache, which changes with the shader
+/// and sizes they're filtered with.
+fn cache_key(image: &HdrImage) -> u128 {
+    let mut settings = ProcessedWriter::new();
+    settings.write([SOURCE_SIZE, SPECULAR_SIZE, SPECULAR_MIPS, IRRADIANCE_SIZE]);
+    settings.write_slice(include_str!("shaders/environment.wgsl").as_bytes());
+    settings.write(image.size());
+    cache::key(
+        std::any::type_name::<GpuEnvironments>(),
+        &settings.into_bytes(),
+        Path::new(""),
+        bytemuck::cast_slice(image.pixels()),
+    )
+}
+
+/// Returns the cubemap, either specular or irradiance, level, and size of each mip of the
+/// prefiltered cubemaps, in the order they're cached.
+fn cached_mips() -> impl Iterator<Item = (usize, u32, u32)> {
+    [(SPECULAR_SIZE, SPECULAR_MIPS), (IRRADIANCE_SIZE, 1)]
+        .into_iter()
+        .enumerate()
+        .flat_map(|(cubemap, (size, mips))| (0..mips).map(move |mip| (cubemap, mip, size >> mip)))
+}
+
+/// Returns the bytes per row of a mip of the size copied to a buffer.
+fn padded_bytes_per_row(size: u32) -> u32 {
+    (size * TEXEL_SIZE).next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT)
+}
+
+/// Records the commands copying every mip of the specular and irradiance cubemaps to a buffer
+/// that can be read back.
+fn copy_cubemaps(
+    device: &wgpu::Device,
+    encoder: &mut wgpu::CommandEncoder,
+    cubemaps: [&wgpu::Texture; 2],
+) -> wgpu::Buffer {
+    let size = cached_mips()
+        .map(|(_, _, size)| u64::from(padded_bytes_per_row(size) * size * 6))
+        .sum();
+    let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("environment readback"),
+        size,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+    // Every mip's rows are padded to the alignment, so their offsets are aligned too.
+    let mut offset = 0;
+    for (cubemap, mip, size) in cached_mips() {
+        let bytes_per_row = padded_bytes_per_row(size);
+        encoder.copy_texture_to_buffer(
+            wgpu::ImageCopyTexture {
+                texture: cubemaps[cubemap],
+                mip_level: mip,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::ImageCopyBuffer {
+                buffer: &buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset,
+                    bytes_per_row: Some(bytes_per_row),
+                    rows_per_image: Some(size),
+                },
+            },
+            wgpu::Extent3d {
+                width: size,
+                height: size,
+                depth_or_array_layers: 6,
+            },
+        );
+        offset += u64::from(bytes_per_row * size * 6);
+    }
+    buffer
+}
+
+/// Returns the specular and irradiance cubemaps uploaded from their cached bytes, or none if the
+/// bytes don't have every mip.
+fn cached_cubemaps(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    processed: &[u8],
+) -> Option<(wgpu::Texture, wgpu::Texture)> {
+    let mut reader = ProcessedReader::new(processed);
+    let mips = cached_mips()
+        .map(|mip| Some((mip, reader.read_slice::<u8>()?)))
+        .collect::<Option<Vec<_>>>()?;
+    if mips
+        .iter()
+        .any(|((_, _, size), texels)| texels.len() != (size * size * 6 * TEXEL_SIZE) as usize)
+    {
+        return None;
+    }
+
+    let usage = wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST;
+    let cubemaps = [
+        cube_texture(device, SPECULAR_SIZE, SPECULAR_MIPS, usage),
+        cube_texture(device, IRRADIANCE_SIZE, 1, usage),
+    ];
+    for ((cubemap, mip, size), texels) in mips {
+        queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture: &cubemaps[cubemap],
+                mip_level: mip,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            &texels,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(size * TEXEL_SIZE),
+                rows_per_image: Some(size),
+            },
+            wgpu::Extent3d {
+                width: size,
+                height: size,
+                depth_or_array_layers: 6,
+            },
+        );
+    }
+    let [specular, irradiance] = cubemaps;
+    Some((specular, irradiance))
+}
+
+/// Images the source cubemap of an environment is rendered from.
+#[derive(Copy, Clone)]
+pub(crate) enum EnvironmentSource<'a> {
+    /// Equirectangular image uploaded with [equirectangular_texture].
+    Equirectangular(&'a wgpu::TextureView),
+    /// Images of a camera looking along each face in the order +X, -X, +Y, -Y, +Z, and -Z, with
+    /// +Y up and -Z and +Z up when looking along +Y and -Y respectively.
+    Faces([&'a wgpu::TextureView; 6]),
+}
+
+/// Pipelines rendering the faces of the cubemaps of an environment.
+pub(crate) struct Generator {
+    equirectangular_layout: wgpu::BindGroupLayout,
+    face_layout: wgpu::BindGroupLayout,
+    mip_layout: wgpu::BindGroupLayout,
+    cube_layout: wgpu::BindGroupLayout,
+    equirectangular: wgpu::RenderPipeline,
+    face: wgpu::RenderPipeline,
+    downsample: wgpu::RenderPipeline,
+    prefilter: wgpu::RenderPipeline,
+    irradiance: wgpu::RenderPipeline,
+    sampler: wgpu::Sampler,
+}
+
+impl Generator {
+    pub(crate) fn new(device: &wgpu::Device) -> Self {
+        let shader = device.create_shader_module(wgpu::include_wgsl!("shaders/environment.wgsl"));
+        let params = wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: true,
+                min_binding_size: wgpu::BufferSize::new(std::mem::size_of::<Params>() as u64),
+            },
+            count: None,
+        };
+        let texture = |binding, filterable, view_dimension| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable },
+                view_dimension,
+                multisampled: false,
+            },
+            count: None,
+        };
+        let sampler = wgpu::BindGroupLayoutEntry {
+            binding: 4,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+            count: None,
+        };
+        let layout = |label, entries: &[wgpu::BindGroupLayoutEntry]| {
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some(label),
+                entries,
+            })
+        };
+        let equirectangular_layout = layout(
+            "environment equirectangular",
+            &[params, texture(1, false, wgpu::TextureViewDimension::D2)],
+        );
+        let face_layout = layout(
+            "environment face",
+            &[
+                params,
+                texture(5, true, wgpu::TextureViewDimension::D2),
+                sampler,
+            ],
+        );
+        let mip_layout = layout(
+            "environment mip",
+            &[
+                params,
+                texture(2, true, wgpu::TextureViewDimension::Cube),
+                sampler,
+            ],
+        );
+        let cube_layout = layout(
+            "environment cube",
+            &[
+                params,
+                texture(3, true, wgpu::TextureViewDimension::Cube),
+                sampler,
+            ],
+        );
+
+        let pipeline = |entry_point, layout: &wgpu::BindGroupLayout| {
+            let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("environment"),
+                bind_group_layouts: &[layout],
+                push_constant_ranges: &[],
+            });
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(entry_point),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "vs_main",
+                    compilation_options: wgpu::PipelineCompilationOptions::default(),
+                    buffers: &[],
+                },
+                primitive: wgpu::PrimitiveState::default(),
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState::default(),
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point,
+                    compilation_options: wgpu::PipelineCompilationOptions::default(),
+                    targets: &[Some(ENVIRONMENT_FORMAT.into())],
+                }),
+                multiview: None,
+                cache: None,
+            })
+        };
+
+        Self {
+            equirectangular: pipeline("fs_equirectangular", &equirectangular_layout),
+            face: pipeline("fs_face", &face_layout),
+            downsample: pipeline("fs_downsample", &mip_layout),
+            prefilter: pipeline("fs_prefilter", &cube_layout),
+            irradiance: pipeline("fs_irradiance", &cube_layout),
+            equirectangular_layout,
+            face_layout,
+            mip_layout,
+            cube_layout,
+            sampler: device.create_sampler(&wgpu::SamplerDescriptor {
+                label: Some("environment source"),
+                mag_filter: wgpu::FilterMode::Linear,
+                min_filter: wgpu::FilterMode::Linear,
+                mipmap_filter: wgpu::FilterMode::Linear,
+                ..wgpu::SamplerDescriptor::default()
+            }),
+        }
+    }
+
+    /// Records the commands rendering the input to the source cubemap and its mips, and
+    /// filtering it, and returns the specular and irradiance cubemaps, which can be copied.
+    pub(crate) fn generate(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        input: EnvironmentSource,
+    ) -> (wgpu::Texture, wgpu::Texture) {
+        let usage = wgpu::TextureUsages::RENDER_ATTACHMENT
+            | wgpu::TextureUsages::TEXTURE_BINDING
+            | wgpu::TextureUsages::COPY_SRC;
+        let source = cube_texture(device, SOURCE_SIZE, SOURCE_MIPS, usage);
+        let specular = cube_texture(device, SPECULAR_SIZE, SPECULAR_MIPS, usage);
+        let irradiance = cube_texture(device, IRRADIANCE_SIZE, 1, usage);
+        let source_view = source.create_view(&cube_view());
+        let source_faces = face_views(&source, SOURCE_MIPS);
+        let source_mips = (0..SOURCE_MIPS)
+            .map(|mip| {
+                source.create_view(&wgpu::TextureViewDescriptor {
+                    base_mip_level: mip,
+                    mip_level_count: Some(1),
+                    ..cube_view()
+                })
+            })
+            .collect::<Vec<_>>();
+        let specular_faces = face_views(&specular, SPECULAR_MIPS);
+        let irradiance_faces = face_views(&irradiance, 1);
+
+        // Each face of each mip is rendered with a pipeline, the binding of its source in the
+        // pipeline's layout, and its params.
+        let mut draws = Vec::new();
+        for (face, target) in (0..).zip(&source_faces[0]) {
+            match input {
+                EnvironmentSource::Equirectangular(equirectangular) => {
+                    let source = (&self.equirectangular_layout, 1, equirectangular);
+                    draws.push((&self.equirectangular, source, target, face, 0.0));
+                }
+                EnvironmentSource::Faces(faces) => {
+                    let source = (&self.face_layout, 5, faces[face as usize]);
+                    draws.push((&self.face, source, target, face, 0.0));
+                }
+            }
+        }
+        for (previous, faces) in source_mips.iter().zip(&source_faces[1..]) {
+            for (face, target) in (0..).zip(faces) {
+                let source = (&self.mip_layout, 2, previous);
+                draws.push((&self.downsample, source, target, face, 0.0));
+            }
+        }
+        for (mip, faces) in specular_faces.iter().enumerate() {
+            let roughness = mip as f32 / (SPECULAR_MIPS - 1) as f32;
+            for (face, target) in (0..).zip(faces) {
+                let source = (&self.cube_layout, 3, &source_view);
+                draws.push((&self.prefilter, source, target, face, roughness));
+            }
+        }
+        for (face, target) in (0..).zip(&irradiance_faces[0]) {
+            let source = (&self.cube_layout, 3, &source_view);
+            draws.push((&self.irradiance, source, target, face, 0.0));
+        }
+
+        let mut uniforms = vec![0; draws.len() * UNIFORM_ALIGNMENT as usize];
+        for (uniform, (_, _, _, face, roughness)) in
+            uniforms.chunks_mut(UNIFORM_ALIGNMENT as usize).zip(&draws)
+        {
+            let params = Params {
+                face: *face,
+                roughness: *roughness,
+                source_size: SOURCE_SIZE as f32,
+                source_mips: SOURCE_MIPS as f32,
+            };
+            uniform[..std::mem::size_of::<Params>()].copy_from_slice(bytemuck::bytes_of(&params));
+        }
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("environment params"),
+            contents: &uniforms,
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+
+        for (index, (pipeline, (layout, binding, source), target, _, _)) in draws.iter().enumerate()
+        {
+            let mut entries = vec![
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                        buffer: &buffer,
+                        offset: 0,
+                        size: wgpu::BufferSize::new(std::mem::size_of::<Params>() as u64),
+                    }),
+                },
+                wgpu::BindGroupEntry {
+                    binding: *binding,
+                    resource: wgpu::BindingResource::TextureView(source),
+                },
+            ];
+            // The equirectangular image is loaded without a sampler.
+            if *binding != 1 {
+                entries.push(wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler),
+                });
+            }
+            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("environment"),
+                layout,
+                entries: &entries,
+            });
+
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("environment"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: target,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            pass.set_pipeline(pipeline);
+            let offset = index as u32 * UNIFORM_ALIGNMENT as u32;
+            pass.set_bind_group(0, &bind_group, &[offset]);
+            pass.draw(0..3, 0..1);
+        }
+
+        (specular, irradiance)
+    }
+}
+
+fn bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    sampler: &wgpu::Sampler,
+    specular: &wgpu::TextureView,
+    irradiance: &wgpu::TextureView,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("environment"),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(specular),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::TextureView(irradiance),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: wgpu::BindingResource::Sampler(sampler),
+            },
+        ],
+    })
+}
+
+pub(crate) fn cube_texture(
+    device: &wgpu::Device,
+    size: u32,
+    mips: u32,
+    usage: wgpu::TextureUsages,
+) -> wgpu::Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("environment"),
+        size: wgpu::Extent3d {
+            width: size,
+            height: size,
+            depth_or_array_layers: 6,
+        },
+        mip_level_count: mips,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: ENVIRONMENT_FORMAT,
+        usage,
+        view_formats: &[],
+    })
+}
+
+pub(crate) fn cube_view() -> wgpu::TextureViewDescriptor<'static> {
+    wgpu::TextureViewDescriptor {
+        dimension: Some(wgpu::TextureViewDimension::Cube),
+        ..wgpu::TextureViewDescriptor::default()
+    }
+}
+
+/// Returns views of each face of each mip of the cube texture.
+fn face_views(texture: &wgpu::Texture, mips: u32) -> Vec<Vec<wgpu::TextureView>> {
+    (0..mips)
+        .map(|mip| {
+            (0..6)
+                .map(|face| {
+                    texture.create_view(&wgpu::TextureViewDescriptor {
+                        dimension: Some(wgpu::TextureViewDimension::D2),
+                        base_mip_level: mip,
+                        mip_level_count: Some(1),
+                        base_array_layer: face,
+                        array_layer_count: Some(1),
+                        ..wgpu::TextureViewDescriptor::default()
+                    })
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Uploads the image to a 32-bit float texture, skipping pixels if it's larger than the device
+/// supports.
+pub(crate) fn equirectangular_texture(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    image: &HdrImage,
+) -> wgpu::TextureView {
+    let max_size = device.limits().max_texture_dimension_2d;
+    let step = image.size().max_element().div_ceil(max_size).max(1);
+    let width = image.size().x.div_ceil(step);
+    let height = image.size().y.div_ceil(step);
+    let pixels = (0..height)
+        .flat_map(|y| (0..width).map(move |x| (x, y)))
+        .map(|(x, y)| {
+            let [r, g, b] = image.pixel(x * step, y * step);
+            [r, g, b, 1.0]
+        })
+        .collect::<Vec<_>>();
+
+    let size = wgpu::Extent3d {
+        width,
+        height,
+        depth_or_array_layers: 1,
+    };
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("environment equirectangular"),
+        size,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: wgpu::TextureFormat::Rgba32Float,
+        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        view_formats: &[],
+    });
+    queue.write_texture(
+        texture.as_image_copy(),
+        bytemuck::cast_slice(&pixels),
+        wgpu::ImageDataLayout {
+            offset: 0,
+            bytes_per_row: Some(width * 16),
+            rows_per_image: None,
+        },
+        size,
+    );
+
+    texture.create_view(&wgpu::TextureViewDescriptor::default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::tests::device;
+
+    #[test]
+    fn cubemaps_are_cached() {
+        let Some((device, queue)) = device() else {
+            return;
+        };
+        let directory =
+            std::env::temp_dir().join(format!("pulse-environment-{}", std::process::id()));
+        let image = HdrImage::from_pixel([0.4, 0.6, 1.0]);
+        let upload = |environments: &mut GpuEnvironments| {
+            let mut encoder =
+                device.create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+            environments.upload(&device, &queue, &mut encoder, &image, Some(&directory));
+            queue.submit([encoder.finish()]);
+        };
+
+        let mut environments = GpuEnvironments::new(&device);
+        upload(&mut environments);
+        assert_eq!(environments.readbacks.len(), 1);
+        device.poll(wgpu::Maintain::Wait);
+        environments.poll(&device);
+        assert!(environments.readbacks.is_empty());
+        let (_, processed) =
+            cache::load(&directory, cache_key(&image), |path| fs::read(path)).unwrap();
+        assert!(processed.iter().any(|&byte| byte != 0));
+
+        let mut environments = GpuEnvironments::new(&device);
+        upload(&mut environments);
+        assert!(environments.readbacks.is_empty());
+        assert!(environments.environments.contains_key(&image.id()));
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn shader_is_valid() {
+        use wgpu::naga;
+
+        let module =
+            naga::front::wgsl::parse_str(include_str!("shaders/environment.wgsl")).unwrap();
+        naga::valid::Validator::new(
+            naga::valid::ValidationFlags::all(),
+            naga::valid::Capabilities::empty(),
+        )
+        .validate(&module)
+        .unwrap();
+    }
+}
@@ -0,0 +1,186 @@
+//! # Fog
+//!
+//! Distance and height fog blending the meshes seen by a camera into a fog color, applied by the
+//! shader of the [crate::render::graph::FORWARD] node. The fog lit by the sun, the first
+//! directional light, glows in its direction.
+
+use bytemuck::Pod;
+use bytemuck::Zeroable;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::render::Color;
+use crate::Component;
+use crate::Reflect;
+
+/// # Fog Falloff
+///
+/// How the amount of fog grows with the distance from the camera.
+#[derive(Copy, Clone, Debug, PartialEq, Reflect, Serialize, Deserialize)]
+pub enum FogFalloff {
+    /// No fog before the start distance, growing linearly to full fog at the end distance.
+    Linear {
+        /// Distance the fog starts at.
+        start: f32,
+        /// Distance of full fog.
+        end: f32,
+    },
+    /// Fog absorbing the given fraction of the light per unit of distance.
+    Exponential {
+        /// Density of the fog.
+        density: f32,
+    },
+    /// Fog growing with the square of the distance, keeping the near view clear.
+    ExponentialSquared {
+        /// Density of the fog.
+        density: f32,
+    },
+}
+
+/// # Fog
+///
+/// Fog seen by the node's [crate::Camera]. Insert it into the scene as a resource to use it for
+/// every camera without one. With a height falloff, the fog is densest below its base height and
+/// thins out exponentially above it, e.g. for mist in valleys.
+///
+/// Shader materials apply the fog to their colors with the `fog` function, see
+/// [crate::render::shader_material::ShaderMaterial].
+///
+/// ```
+/// # use pulse::render::fog::Fog;
+/// # use pulse::render::fog::FogFalloff;
+/// # use pulse::render::Color;
+/// # use pulse::Scene;
+/// let mut scene = Scene::new();
+/// scene.insert_resource(Fog {
+///     color: Color::rgb(0.5, 0.6, 0.7),
+///     falloff: FogFalloff::Exponential { density: 0.02 },
+///     height_falloff: 0.1,
+///     ..Fog::default()
+/// });
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq, Component, Reflect, Serialize, Deserialize)]
+pub struct Fog {
+    /// Color of the fog, in the same units as the light of the shaded surfaces.
+    pub color: Color,
+    /// Growth of the fog with the distance.
+    pub falloff: FogFalloff,
+    /// Rate the density of the fog falls off with per unit of height above the base height, or
+    /// zero for fog of the same density at every height.
+    pub height_falloff: f32,
+    /// Height the density of the fog is measured at.
+    pub base_height: f32,
+    /// Color of the sun's light scattered by the fog towards the camera when looking towards the
+    /// sun.
+    pub sun_color: Color,
+    /// Exponent narrowing the glow of the sun, higher values concentrating it around the sun.
+    pub sun_exponent: f32,
+}
+
+impl Default for Fog {
+    fn default() -> Self {
+        Self {
+            color: Color::rgb(0.5, 0.5, 0.5),
+            falloff: FogFalloff::Exponential { density: 0.05 },
+            height_falloff: 0.0,
+            base_height: 0.0,
+            sun_color: Color::BLACK,
+            sun_exponent: 8.0,
+        }
+    }
+}
+
+/// Uniforms of a camera's fog in the shader, part of its camera uniform.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, Pod, Zeroable)]
+pub(crate) struct FogUniform {
+    /// Color of the fog and falloff, zero without fog, one for linear, two for exponential, and
+    /// three for exponential squared fog.
+    color: [f32; 4],
+    /// Start distance or density, end distance, height falloff, and base height.
+    params: [f32; 4],
+    /// Color and exponent of the sun's glow.
+    sun: [f32; 4],
+}
+
+impl FogUniform {
+    /// Returns the uniform of the fog, or of no fog.
+    pub(crate) fn new(fog: Option<&Fog>) -> Self {
+        let Some(fog) = fog else {
+            return Self::zeroed();
+        };
+        let (falloff, start, end) = match fog.falloff {
+            FogFalloff::Linear { start, end } => (1.0, start, end),
+            FogFalloff::Exponential { density } => (2.0, density.max(0.0), 0.0),
+            FogFalloff::ExponentialSquared { density } => (3.0, density.max(0.0), 0.0),
+        };
+        let [r, g, b, _] = fog.color.to_vec4().to_array();
+        let [sun_r, sun_g, sun_b, _] = fog.sun_color.to_vec4().to_array();
+        Self {
+            color: [r, g, b, falloff],
+            params: [start, end, fog.height_falloff.max(0.0), fog.base_height],
+            sun: [sun_r, sun_g, sun_b, fog.sun_exponent.max(0.0)],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Mat4;
+    use glam::Vec3;
+
+    use super::*;
+    use crate::render::anti_aliasing::AntiAliasing;
+    use crate::render::mesh::Mesh;
+    use crate::render::tests::render;
+    use crate::render::tonemap::Tonemapping;
+    use crate::render::ClearColor;
+    use crate::Camera;
+    use crate::Scene;
+    use crate::VisibleNodes;
+    use crate::WorldTransform;
+
+    #[test]
+    fn render_fades_distant_meshes_into_fog() {
+        let mut scene = Scene::new();
+        scene.insert_resource(ClearColor(Color::BLACK));
+        scene.insert_resource(AntiAliasing::None);
+        let near = scene.spawn_with((
+            Mesh::cube(1.0),
+            WorldTransform::new(Mat4::from_translation(Vec3::new(-1.0, 0.0, -4.0))),
+        ));
+        let far = scene.spawn_with((
+            Mesh::cube(20.0),
+            WorldTransform::new(Mat4::from_translation(Vec3::new(20.0, 0.0, -80.0))),
+        ));
+        let camera = Camera {
+            tonemapping: Tonemapping::None,
+            ..Camera::perspective(1.0, 1.0, 0.1, 200.0)
+        };
+        scene.spawn_with((
+            camera,
+            WorldTransform::IDENTITY,
+            VisibleNodes(vec![near, far]),
+            Fog {
+                color: Color::rgb(0.0, 0.0, 1.0),
+                falloff: FogFalloff::Linear {
+                    start: 10.0,
+                    end: 50.0,
+                },
+                ..Fog::default()
+            },
+        ));
+        let Some(pixels) = render(&scene) else {
+            return;
+        };
+        let pixel =
+            |x: usize, y: usize| -> [u8; 4] { pixels[(y * 64 + x) * 4..][..4].try_into().unwrap() };
+
+        // The near cube is lit by the ambient light only, the far one is hidden by the fog.
+        let near = pixel(17, 32);
+        assert!(near[0] > 0 && near[2] == near[0]);
+        assert_eq!(pixel(52, 32), [0, 0, 255, 255]);
+        // The background isn't fogged.
+        assert_eq!(pixel(32, 2), [0, 0, 0, 255]);
+    }
+}
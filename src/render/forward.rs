@@ -0,0 +1,1637 @@
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::ops::Range;
+use std::sync::Weak;
+
+use bytemuck::Pod;
+use bytemuck::Zeroable;
+use glam::Mat4;
+use glam::UVec2;
+use glam::Vec3;
+
+use crate::assets::AssetServer;
+use crate::render::anti_aliasing::jitter;
+use crate::render::anti_aliasing::AntiAliasing;
+use crate::render::atmosphere::sky_ambient;
+use crate::render::cluster::ClusterSettings;
+use crate::render::cluster::ClusterUniform;
+use crate::render::cluster::LightClusters;
+use crate::render::debug::bounds_lines;
+use crate::render::debug::DebugLines;
+use crate::render::debug::DebugPipelines;
+use crate::render::debug::DebugShading;
+use crate::render::debug::DebugView;
+use crate::render::deferred::gbuffer_targets;
+use crate::render::deferred::DeferredLighting;
+use crate::render::deferred::GBuffer;
+use crate::render::deferred::RenderPath;
+use crate::render::deferred::DEFERRED_GBUFFER;
+use crate::render::environment::EnvironmentLight;
+use crate::render::environment::GpuEnvironments;
+use crate::render::fog::Fog;
+use crate::render::fog::FogUniform;
+use crate::render::graph::GpuResources;
+use crate::render::graph::RenderContext;
+use crate::render::graph::RenderNode;
+use crate::render::image::HdrImage;
+use crate::render::light::gather_lights;
+use crate::render::light::lights_uniform;
+use crate::render::light::world_transform;
+use crate::render::light::LightsUniform;
+use crate::render::material::AlphaMode;
+use crate::render::material::GpuMaterial;
+use crate::render::material::GpuMaterials;
+use crate::render::material::Material;
+use crate::render::material::MaterialFeatures;
+use crate::render::material::ShaderKey;
+use crate::render::mesh::deform_entries;
+use crate::render::mesh::deform_layout_entries;
+use crate::render::mesh::deformed_source;
+use crate::render::mesh::GpuMeshes;
+use crate::render::mesh::Instance;
+use crate::render::mesh::Mesh;
+use crate::render::mesh::Vertex;
+use crate::render::prepass::PrepassDepth;
+use crate::render::prepass::Prepassed;
+use crate::render::probe::gather_probes;
+use crate::render::probe::GpuProbes;
+use crate::render::probe::MAX_PROBES;
+use crate::render::shader::preprocess;
+use crate::render::shader_material::compile;
+use crate::render::shader_material::ShaderData;
+use crate::render::shader_material::ShaderMaterial;
+use crate::render::shadow::ShadowMaps;
+use crate::render::shadow::ShadowReceiver;
+use crate::render::target::set_viewport;
+use crate::render::tonemap::HdrTarget;
+use crate::render::tonemap::HDR_FORMAT;
+use crate::render::ClearColor;
+use crate::render::UNIFORM_ALIGNMENT;
+use crate::Camera;
+use crate::Node;
+use crate::Scene;
+use crate::Viewport;
+use crate::VisibleNodes;
+
+/// Format of the depth buffer.
+pub(crate) const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+
+/// Offset of a camera's [LightsUniform] from its [CameraUniform] in the camera buffer.
+const LIGHTS_OFFSET: wgpu::BufferAddress = UNIFORM_ALIGNMENT;
+
+/// Size of the uniforms of a camera in the camera buffer, its [CameraUniform] followed by its
+/// [LightsUniform].
+const CAMERA_STRIDE: wgpu::BufferAddress = (LIGHTS_OFFSET
+    + std::mem::size_of::<LightsUniform>() as wgpu::BufferAddress)
+    .next_multiple_of(UNIFORM_ALIGNMENT);
+
+/// Source of the physically based shader, compiled for each combination of material features.
+const PBR_SHADER: &str = include_str!("shaders/pbr.wgsl");
+
+/// Shader def of the physically based shader without the standard material's bindings and
+/// fragment shader, which shader materials append their own to.
+pub(crate) const CUSTOM_MATERIAL: &str = "CUSTOM_MATERIAL";
+
+/// Uniforms of a camera in the shader.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+struct CameraUniform {
+    view_projection: [[f32; 4]; 4],
+    position: [f32; 4],
+    /// Forward direction, to compute the view depth of fragments for choosing shadow cascades.
+    forward: [f32; 4],
+    fog: FogUniform,
+    /// Matrix transforming clip to world coordinates, to reconstruct the positions of the
+    /// G-buffer's pixels from their depth.
+    inverse_view_projection: [[f32; 4]; 4],
+}
+
+/// Instances of a mesh with a material.
+type Batch = (Material, Mesh, Vec<Instance>);
+
+/// Draws of a camera, the instances of each mesh and material in the instance buffer.
+pub(crate) struct CameraDraws {
+    /// Opaque and alpha masked instances, grouped by material and mesh.
+    pub(crate) batches: Vec<(Material, Mesh, Range<u32>)>,
+    /// Alpha blended instances, one per draw from back to front.
+    transparent: Vec<(Material, Mesh, Range<u32>)>,
+    environment: Option<EnvironmentLight>,
+    /// Keys of the bind group of the camera's reflection probes.
+    probes: [usize; MAX_PROBES],
+    /// Vertices of the bounding box lines of the [DebugView] in the bounds buffer.
+    bounds: Range<u32>,
+    /// Rectangle of the target the camera renders to.
+    pub(crate) viewport: Viewport,
+}
+
+/// Render resource with the depth buffer and multisampled color target of the forward pass,
+/// resolved to the [HdrTarget].
+pub(crate) struct Targets {
+    size: UVec2,
+    samples: u32,
+    depth: wgpu::TextureView,
+    /// Multisampled color target if there's more than one sample.
+    pub(crate) color: Option<wgpu::TextureView>,
+    /// Frame counter for the camera jitter of TAA.
+    pub(crate) frame: u32,
+}
+
+/// Variant of the mesh pipeline.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+struct PipelineKey {
+    features: MaterialFeatures,
+    /// Shader of a shader material, drawn with the standard material's shader if `None`.
+    shader: Option<ShaderKey>,
+    shading: DebugShading,
+    samples: u32,
+    /// Whether the depth was drawn by the prepass, so opaque meshes only pass the depth test where
+    /// they're visible.
+    prepass: bool,
+    /// Whether the surface is drawn to the [GBuffer] instead of being lit.
+    gbuffer: bool,
+}
+
+/// Render resource marking that a node drew the background of the frame to the [Targets], so the
+/// forward pass draws on top of it instead of clearing them.
+pub(crate) struct Background;
+
+impl CameraDraws {
+    /// Returns the image of the camera's environment light, if any.
+    fn environment_image(&self) -> Option<&HdrImage> {
+        self.environment
+            .as_ref()
+            .map(|environment| &environment.image)
+    }
+}
+
+impl Targets {
+    /// Inserts targets with the size of the context's target and the sample count into the
+    /// context unless it has them already.
+    pub(crate) fn prepare(context: &mut RenderContext, samples: u32) {
+        let size = context.target_size;
+        if context
+            .resource::<Self>()
+            .is_some_and(|targets| targets.size == size && targets.samples == samples)
+        {
+            return;
+        }
+
+        let device = context.device;
+        context.insert_resource(Self {
+            size,
+            samples,
+            depth: texture(device, "depth", DEPTH_FORMAT, size, samples),
+            color: (samples > 1).then(|| texture(device, "msaa", HDR_FORMAT, size, samples)),
+            frame: 0,
+        });
+    }
+}
+
+/// Render node drawing the visible [Mesh] nodes of every camera to the [HdrTarget], shaded with
+/// their [Material] and the lights and [ShadowMaps] of the camera. The point and spot lights of
+/// each camera are first binned into its clusters by a compute pass. Each camera draws its opaque
+/// meshes first, its alpha blended meshes on top of them from back to front, and the lines of the
+/// scene's [DebugView] last. On the [RenderPath::Deferred], the opaque meshes with a standard
+/// material are drawn to the [GBuffer] and lit by a fullscreen pass instead.
+pub(crate) struct ForwardPass {
+    pipelines: HashMap<PipelineKey, wgpu::RenderPipeline>,
+    /// Shaders of the shader material pipelines and the generation they were last compiled from.
+    shaders: HashMap<PipelineKey, (Weak<ShaderData>, u64)>,
+    debug: DebugPipelines,
+    materials: GpuMaterials,
+    environments: GpuEnvironments,
+    probes: GpuProbes,
+    clusters: LightClusters,
+    /// Pipeline lighting the G-buffer, created the first time the deferred path is used.
+    deferred: Option<DeferredLighting>,
+    default_material: Material,
+    camera_layout: wgpu::BindGroupLayout,
+    camera_buffer: wgpu::Buffer,
+    camera_bind_group: Option<wgpu::BindGroup>,
+    /// Generation of the shadow maps bound in the camera bind group.
+    shadow_generation: u64,
+    /// Generation of the light and cluster buffers bound in the camera bind group.
+    cluster_generation: u64,
+    /// Generation of the buffers deforming meshes bound in the camera bind group.
+    deform_generation: u64,
+    instance_buffer: wgpu::Buffer,
+}
+
+impl ForwardPass {
+    pub(crate) fn new(device: &wgpu::Device) -> Self {
+        let [palettes, morph_targets, morph_weights] = deform_layout_entries(6);
+        let camera_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("camera"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: true,
+                        min_binding_size: wgpu::BufferSize::new(
+                            std::mem::size_of::<CameraUniform>() as u64,
+                        ),
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: true,
+                        min_binding_size: wgpu::BufferSize::new(
+                            std::mem::size_of::<LightsUniform>() as u64,
+                        ),
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Depth,
+                        view_dimension: wgpu::TextureViewDimension::D2Array,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Comparison),
+                    count: None,
+                },
+                storage_entry(4),
+                storage_entry(5),
+                palettes,
+                morph_targets,
+                morph_weights,
+            ],
+        });
+
+        Self {
+            pipelines: HashMap::new(),
+            shaders: HashMap::new(),
+            debug: DebugPipelines::new(device),
+            materials: GpuMaterials::new(device),
+            environments: GpuEnvironments::new(device),
+            probes: GpuProbes::new(device),
+            clusters: LightClusters::new(device),
+            deferred: None,
+            default_material: Material::default(),
+            camera_layout,
+            camera_buffer: camera_buffer(device, 1),
+            camera_bind_group: None,
+            shadow_generation: 0,
+            cluster_generation: 0,
+            deform_generation: 0,
+            instance_buffer: instance_buffer(device, 1),
+        }
+    }
+
+    /// Creates the pipeline variant if it doesn't exist yet, or recreates the variant of the
+    /// shader material if its shader was reloaded since. Shaders that don't compile keep their
+    /// previous pipeline.
+    fn prepare_pipeline(
+        &mut self,
+        device: &wgpu::Device,
+        key: PipelineKey,
+        material: Option<&ShaderMaterial>,
+    ) {
+        let source = match material {
+            None if self.pipelines.contains_key(&key) => return,
+            None => {
+                let mut defs = key.features.defs();
+                defs.extend(key.shading.def());
+                defs.extend(key.gbuffer.then_some(DEFERRED_GBUFFER));
+                pbr_shader(&defs)
+            }
+            Some(material) => {
+                let generation = material.shader.generation();
+                if self
+                    .shaders
+                    .get(&key)
+                    .is_some_and(|(_, compiled)| *compiled == generation)
+                {
+                    return;
+                }
+
+                self.shaders
+                    .insert(key, (material.shader.downgrade(), generation));
+                let prelude = pbr_shader(&[CUSTOM_MATERIAL]);
+                match compile(&prelude, material) {
+                    Ok(source) => source,
+                    Err(error) => {
+                        println!("Failed to compile shader material: {error}");
+                        return;
+                    }
+                }
+            }
+        };
+        self.create_pipeline(device, key, source, material);
+    }
+
+    fn create_pipeline(
+        &mut self,
+        device: &wgpu::Device,
+        key: PipelineKey,
+        source: String,
+        material: Option<&ShaderMaterial>,
+    ) {
+        let PipelineKey {
+            features,
+            shading,
+            samples,
+            prepass,
+            gbuffer,
+            ..
+        } = key;
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("pbr"),
+            source: wgpu::ShaderSource::Wgsl(source.into()),
+        });
+        let blend = features.contains(MaterialFeatures::ALPHA_BLEND);
+        // Overdraw adds up every fragment, hidden or not.
+        let overdraw = shading == DebugShading::Overdraw;
+        let blend_state = if overdraw {
+            let add = wgpu::BlendComponent {
+                src_factor: wgpu::BlendFactor::One,
+                dst_factor: wgpu::BlendFactor::One,
+                operation: wgpu::BlendOperation::Add,
+            };
+            Some(wgpu::BlendState {
+                color: add,
+                alpha: add,
+            })
+        } else {
+            blend.then_some(wgpu::BlendState::ALPHA_BLENDING)
+        };
+        let material_layout = match material {
+            Some(material) => self.materials.shader_layout(device, material),
+            None => self.materials.layout(device, features),
+        };
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("pbr"),
+            bind_group_layouts: &[
+                &self.camera_layout,
+                material_layout,
+                self.environments.layout(),
+                self.probes.layout(),
+            ],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("pbr"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                buffers: &[Vertex::layout(), Instance::layout()],
+            },
+            primitive: wgpu::PrimitiveState {
+                cull_mode: Some(wgpu::Face::Back),
+                ..wgpu::PrimitiveState::default()
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: DEPTH_FORMAT,
+                // Blended meshes are sorted instead, and don't hide the meshes behind them.
+                depth_write_enabled: !blend && !overdraw && !prepass,
+                depth_compare: if overdraw {
+                    wgpu::CompareFunction::Always
+                } else if gbuffer {
+                    // Equal to the depth of the prepass if it ran.
+                    wgpu::CompareFunction::LessEqual
+                } else if prepass {
+                    wgpu::CompareFunction::Equal
+                } else {
+                    wgpu::CompareFunction::Less
+                },
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState {
+                count: samples,
+                ..wgpu::MultisampleState::default()
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                targets: &if gbuffer {
+                    gbuffer_targets().to_vec()
+                } else {
+                    vec![Some(wgpu::ColorTargetState {
+                        format: HDR_FORMAT,
+                        blend: blend_state,
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })]
+                },
+            }),
+            multiview: None,
+            cache: None,
+        });
+        self.pipelines.insert(key, pipeline);
+    }
+}
+
+impl RenderNode for ForwardPass {
+    /// Draws the scene from every camera onto the [HdrTarget], which is cleared to the scene's
+    /// [ClearColor] first, testing against the depth of the [PrepassDepth] if the prepass ran.
+    fn run(&mut self, context: &mut RenderContext, scene: &Scene) {
+        let device = context.device;
+        let queue = context.queue;
+        let anti_aliasing = scene
+            .get_resource::<AntiAliasing>()
+            .copied()
+            .unwrap_or_default();
+        let samples = anti_aliasing.sample_count();
+        let debug = scene
+            .get_resource::<DebugView>()
+            .copied()
+            .unwrap_or_default();
+        let deferred = scene
+            .get_resource::<RenderPath>()
+            .copied()
+            .unwrap_or_default()
+            == RenderPath::Deferred
+            && samples == 1
+            && debug.shading == DebugShading::Lit
+            && !context.cameras.is_empty();
+        Targets::prepare(context, samples);
+        HdrTarget::prepare(context);
+        if deferred {
+            GBuffer::prepare(context);
+        } else {
+            context.remove_resource::<GBuffer>();
+        }
+        // Without the shadow node, bind empty shadow maps.
+        if context.resource::<ShadowMaps>().is_none() {
+            ShadowMaps::prepare(context, 1);
+        }
+
+        let background = context.remove_resource::<Background>().is_some();
+        if context.remove_resource::<Prepassed>().is_none() {
+            context.remove_resource::<PrepassDepth>();
+        }
+        let prepass = context.resource::<PrepassDepth>().is_some();
+        let clear_color = scene
+            .get_resource::<ClearColor>()
+            .copied()
+            .unwrap_or_default()
+            .0;
+        let frame = context.resource::<Targets>().unwrap().frame;
+        let cluster_settings = scene
+            .get_resource::<ClusterSettings>()
+            .copied()
+            .unwrap_or_default();
+        let shadow_maps = context.resources.resource::<ShadowMaps>().unwrap();
+        let mut uniforms = Vec::new();
+        let mut lights_buffer = Vec::new();
+        let mut instances = Vec::new();
+        let mut bounds = Vec::new();
+        let mut draws = Vec::new();
+        for &node in context.cameras {
+            let camera = scene.get::<Camera>(node).unwrap();
+            let transform = world_transform(scene, node);
+            let viewport = camera.viewport(context.target_size);
+            let view_projection = view_projection(scene, node, anti_aliasing, frame, viewport.size);
+            let mut uniform = [0; CAMERA_STRIDE as usize];
+            let camera_uniform = CameraUniform {
+                view_projection: view_projection.to_cols_array_2d(),
+                position: transform.translation().extend(1.0).to_array(),
+                forward: transform
+                    .forward()
+                    .normalize_or_zero()
+                    .extend(0.0)
+                    .to_array(),
+                fog: FogUniform::new(
+                    scene
+                        .get::<Fog>(node)
+                        .or_else(|| scene.get_resource::<Fog>()),
+                ),
+                inverse_view_projection: view_projection.inverse().to_cols_array_2d(),
+            };
+            uniform[..std::mem::size_of::<CameraUniform>()]
+                .copy_from_slice(bytemuck::bytes_of(&camera_uniform));
+            let environment = scene
+                .get::<EnvironmentLight>(node)
+                .or_else(|| scene.get_resource::<EnvironmentLight>());
+            let frustum = camera.frustum(&transform);
+            let lights = gather_lights(scene, &frustum);
+            let mut lights = lights_uniform(
+                scene,
+                &lights,
+                shadow_maps.camera(node),
+                &shadow_maps.matrices,
+                environment,
+                sky_ambient(scene, node),
+                &mut lights_buffer,
+            );
+            lights.count[3] = draws.len() as u32 * cluster_settings.stride();
+            lights.clusters = ClusterUniform::new(camera, &transform, &cluster_settings, viewport);
+            let probes = gather_probes(scene, &frustum, transform.translation());
+            let (probe_uniforms, probe_keys) =
+                self.probes
+                    .upload(device, context.encoder, &context.resources.images, &probes);
+            lights.probes = probe_uniforms;
+            uniform[LIGHTS_OFFSET as usize..][..std::mem::size_of::<LightsUniform>()]
+                .copy_from_slice(bytemuck::bytes_of(&lights));
+            uniforms.extend(uniform);
+
+            let visible = scene
+                .get::<VisibleNodes>(node)
+                .map_or(&[][..], |visible| &visible.0);
+            let view = (transform.forward(), transform.translation());
+            let mut draw = batch(
+                scene,
+                visible,
+                view,
+                &self.default_material,
+                context.resources,
+                &mut instances,
+            );
+            draw.environment = environment.cloned();
+            draw.probes = probe_keys;
+            draw.viewport = viewport;
+            if debug.bounds {
+                let start = bounds.len() as u32;
+                bounds_lines(scene, visible, &mut bounds);
+                draw.bounds = start..bounds.len() as u32;
+            }
+            draws.push(draw);
+        }
+
+        let resources = &mut *context.resources;
+        let cache = scene
+            .get_resource::<AssetServer>()
+            .and_then(AssetServer::cache);
+        self.environments.poll(device);
+        for draw in &draws {
+            if let Some(environment) = &draw.environment {
+                self.environments
+                    .upload(device, queue, context.encoder, &environment.image, cache);
+            }
+            for (material, mesh, _) in draw.batches.iter().chain(&draw.transparent) {
+                resources.meshes.upload(device, mesh);
+                self.materials
+                    .upload(device, queue, &mut resources.images, material);
+                let shader = material.shader();
+                if let Some(shader) = shader {
+                    shader.shader.poll_reload();
+                }
+                let Some(gpu_material) = self.materials.get(material) else {
+                    continue;
+                };
+                let key = pipeline_key(gpu_material, debug.shading, samples, prepass, deferred);
+                self.prepare_pipeline(device, key, shader);
+                if debug.wireframe {
+                    resources.meshes.upload_edges(device, mesh);
+                }
+            }
+        }
+        if deferred && self.deferred.is_none() {
+            self.deferred = Some(DeferredLighting::new(
+                device,
+                &self.camera_layout,
+                self.environments.layout(),
+                self.probes.layout(),
+            ));
+        }
+        for (enabled, lines) in [
+            (debug.wireframe, DebugLines::Wireframe),
+            (debug.bounds, DebugLines::Bounds),
+        ] {
+            if enabled {
+                self.debug
+                    .prepare(device, &self.camera_layout, lines, samples);
+            }
+        }
+        if !bounds.is_empty() {
+            self.debug.write_bounds(device, queue, &bounds);
+        }
+        resources.meshes.collect_garbage();
+        self.materials.collect_garbage();
+        self.shaders
+            .retain(|_, (shader, _)| shader.strong_count() > 0);
+        let shaders = &self.shaders;
+        self.pipelines
+            .retain(|key, _| key.shader.is_none() || shaders.contains_key(key));
+        self.environments.collect_garbage();
+        self.probes.collect_garbage();
+        resources.images.collect_garbage();
+
+        let targets = resources.resource_mut::<Targets>().unwrap();
+        targets.frame = targets.frame.wrapping_add(1);
+
+        let shadow_maps = resources.resource::<ShadowMaps>().unwrap();
+        let hdr = &resources.resource::<HdrTarget>().unwrap().view;
+        let targets = resources.resource::<Targets>().unwrap();
+        let prepass_depth = resources.resource::<PrepassDepth>();
+        let camera_count = draws.len().max(1) as u64;
+        if self.camera_buffer.size() < camera_count * CAMERA_STRIDE {
+            self.camera_buffer = camera_buffer(device, camera_count.next_power_of_two());
+            self.camera_bind_group = None;
+            self.clusters.invalidate();
+        }
+        if self.shadow_generation != shadow_maps.generation {
+            self.shadow_generation = shadow_maps.generation;
+            self.camera_bind_group = None;
+        }
+        self.clusters.write(
+            device,
+            queue,
+            &lights_buffer,
+            draws.len(),
+            &cluster_settings,
+        );
+        if self.cluster_generation != self.clusters.generation {
+            self.cluster_generation = self.clusters.generation;
+            self.camera_bind_group = None;
+        }
+        if self.deform_generation != resources.deform_generation() {
+            self.deform_generation = resources.deform_generation();
+            self.camera_bind_group = None;
+        }
+        let camera_bind_group = self.camera_bind_group.get_or_insert_with(|| {
+            camera_bind_group(
+                device,
+                &self.camera_layout,
+                &self.camera_buffer,
+                shadow_maps,
+                &self.clusters,
+                resources,
+            )
+        });
+        queue.write_buffer(&self.camera_buffer, 0, &uniforms);
+        let offsets = (0..draws.len() as u32)
+            .map(|index| index * CAMERA_STRIDE as u32 + LIGHTS_OFFSET as u32);
+        self.clusters.bin(
+            device,
+            context.encoder,
+            &self.camera_buffer,
+            offsets,
+            &cluster_settings,
+        );
+
+        let instance_size = std::mem::size_of::<Instance>() as u64;
+        if self.instance_buffer.size() < instances.len() as u64 * instance_size {
+            self.instance_buffer =
+                instance_buffer(device, (instances.len() as u64).next_power_of_two());
+        }
+        queue.write_buffer(&self.instance_buffer, 0, bytemuck::cast_slice(&instances));
+
+        // Clear the view even if there are no cameras.
+        if draws.is_empty() {
+            draws.push(CameraDraws {
+                batches: Vec::new(),
+                transparent: Vec::new(),
+                environment: None,
+                probes: [0; MAX_PROBES],
+                bounds: 0..0,
+                viewport: Viewport::default(),
+            });
+        }
+
+        let key = |material: &GpuMaterial| {
+            pipeline_key(material, debug.shading, samples, prepass, deferred)
+        };
+        let (mut draw_calls, mut triangles) = (0, 0);
+        for (index, draw) in draws.iter().enumerate() {
+            let clear = index == 0 && !background;
+            let depth = prepass_depth
+                .and_then(|depth| depth.view(index))
+                .unwrap_or(&targets.depth);
+            if deferred {
+                let gbuffer = resources.resource::<GBuffer>().unwrap();
+                let mut pass = context
+                    .encoder
+                    .begin_render_pass(&wgpu::RenderPassDescriptor {
+                        label: Some("gbuffer"),
+                        color_attachments: &gbuffer.attachments(index),
+                        depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                            view: depth,
+                            depth_ops: Some(wgpu::Operations {
+                                load: match prepass {
+                                    true => wgpu::LoadOp::Load,
+                                    false => wgpu::LoadOp::Clear(1.0),
+                                },
+                                store: wgpu::StoreOp::Store,
+                            }),
+                            stencil_ops: None,
+                        }),
+                        timestamp_writes: None,
+                        occlusion_query_set: None,
+                    });
+                set_viewport(&mut pass, draw.viewport);
+                let offset = index as u32 * CAMERA_STRIDE as u32;
+                pass.set_bind_group(0, &*camera_bind_group, &[offset, offset]);
+                pass.set_bind_group(2, self.environments.get(draw.environment_image()), &[]);
+                pass.set_bind_group(3, self.probes.get(&draw.probes), &[]);
+                pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
+                let (calls, count) = draw_batches(
+                    &mut pass,
+                    &draw.batches,
+                    &resources.meshes,
+                    &self.materials,
+                    &self.pipelines,
+                    key,
+                    true,
+                );
+                drop(pass);
+                draw_calls += calls;
+                triangles += count;
+
+                let lighting = self.deferred.as_ref().unwrap();
+                let bind_group = lighting.bind_group(device, gbuffer, index, depth);
+                let mut pass = context
+                    .encoder
+                    .begin_render_pass(&wgpu::RenderPassDescriptor {
+                        label: Some("deferred lighting"),
+                        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                            view: hdr,
+                            resolve_target: None,
+                            ops: wgpu::Operations {
+                                load: match clear {
+                                    true => wgpu::LoadOp::Clear(clear_color.to_wgpu()),
+                                    false => wgpu::LoadOp::Load,
+                                },
+                                store: wgpu::StoreOp::Store,
+                            },
+                        })],
+                        depth_stencil_attachment: None,
+                        timestamp_writes: None,
+                        occlusion_query_set: None,
+                    });
+                set_viewport(&mut pass, draw.viewport);
+                pass.set_pipeline(&lighting.pipeline);
+                pass.set_bind_group(0, &*camera_bind_group, &[offset, offset]);
+                pass.set_bind_group(1, &bind_group, &[]);
+                pass.set_bind_group(2, self.environments.get(draw.environment_image()), &[]);
+                pass.set_bind_group(3, self.probes.get(&draw.probes), &[]);
+                pass.draw(0..3, 0..1);
+                draw_calls += 1;
+            }
+
+            let load = if clear && !deferred {
+                wgpu::LoadOp::Clear(clear_color.to_wgpu())
+            } else {
+                wgpu::LoadOp::Load
+            };
+            // The depth of the prepass and G-buffer is kept, and otherwise only needed by the pass.
+            let depth_ops = if prepass || deferred {
+                wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                }
+            } else {
+                wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.0),
+                    store: wgpu::StoreOp::Discard,
+                }
+            };
+            let mut pass = context
+                .encoder
+                .begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some("forward"),
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view: targets.color.as_ref().unwrap_or(hdr),
+                        resolve_target: targets.color.as_ref().map(|_| hdr),
+                        ops: wgpu::Operations {
+                            load,
+                            store: wgpu::StoreOp::Store,
+                        },
+                    })],
+                    depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                        view: depth,
+                        depth_ops: Some(depth_ops),
+                        stencil_ops: None,
+                    }),
+                    timestamp_writes: None,
+                    occlusion_query_set: None,
+                });
+
+            set_viewport(&mut pass, draw.viewport);
+            let offset = index as u32 * CAMERA_STRIDE as u32;
+            pass.set_bind_group(0, &*camera_bind_group, &[offset, offset]);
+            pass.set_bind_group(2, self.environments.get(draw.environment_image()), &[]);
+            pass.set_bind_group(3, self.probes.get(&draw.probes), &[]);
+            pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
+            let (calls, count) = draw_batches(
+                &mut pass,
+                draw.batches.iter().chain(&draw.transparent),
+                &resources.meshes,
+                &self.materials,
+                &self.pipelines,
+                key,
+                false,
+            );
+            draw_calls += calls;
+            triangles += count;
+
+            if let Some(pipeline) = self
+                .debug
+                .get(DebugLines::Wireframe, samples)
+                .filter(|_| debug.wireframe)
+            {
+                pass.set_pipeline(pipeline);
+                for (_, mesh, instances) in draw.batches.iter().chain(&draw.transparent) {
+                    let Some((gpu_mesh, (edges, edge_count))) = resources
+                        .meshes
+                        .get(mesh)
+                        .and_then(|gpu_mesh| Some((gpu_mesh, gpu_mesh.edges.as_ref()?)))
+                    else {
+                        continue;
+                    };
+                    pass.set_vertex_buffer(0, gpu_mesh.vertices.slice(..));
+                    pass.set_index_buffer(edges.slice(..), wgpu::IndexFormat::Uint32);
+                    pass.draw_indexed(0..*edge_count, 0, instances.clone());
+                    draw_calls += 1;
+                }
+            }
+            if let Some(pipeline) = self
+                .debug
+                .get(DebugLines::Bounds, samples)
+                .filter(|_| !draw.bounds.is_empty())
+            {
+                pass.set_pipeline(pipeline);
+                pass.set_vertex_buffer(0, self.debug.bounds.slice(..));
+                pass.draw(draw.bounds.clone(), 0..1);
+                draw_calls += 1;
+            }
+        }
+        context.count_draws(draw_calls, triangles);
+    }
+}
+
+/// Returns the physically based shader with the defs, followed by the skinning and morphing
+/// functions.
+pub(crate) fn pbr_shader(defs: &[&str]) -> String {
+    deformed_source(&preprocess(PBR_SHADER, defs).expect("pbr shader must be valid"))
+}
+
+/// Returns the pipeline variant for the uploaded material. Only opaque meshes are drawn by the
+/// prepass, debug shadings don't apply to shader materials, and on the deferred path only the
+/// opaque and alpha masked standard materials are drawn to the G-buffer.
+fn pipeline_key(
+    material: &GpuMaterial,
+    shading: DebugShading,
+    samples: u32,
+    prepass: bool,
+    deferred: bool,
+) -> PipelineKey {
+    let gbuffer = deferred
+        && material.shader.is_none()
+        && !material.features.contains(MaterialFeatures::ALPHA_BLEND);
+    PipelineKey {
+        features: material.features,
+        shader: material.shader,
+        shading: match material.shader {
+            Some(_) => DebugShading::Lit,
+            None => shading,
+        },
+        samples,
+        prepass: prepass && is_opaque(material.features) && !gbuffer,
+        gbuffer,
+    }
+}
+
+/// Draws the batches whose pipeline variant draws to the G-buffer if `gbuffer` is true, or to the
+/// color target otherwise, and returns the number of draw calls and triangles.
+fn draw_batches<'a>(
+    pass: &mut wgpu::RenderPass,
+    batches: impl IntoIterator<Item = &'a (Material, Mesh, Range<u32>)>,
+    meshes: &GpuMeshes,
+    materials: &GpuMaterials,
+    pipelines: &HashMap<PipelineKey, wgpu::RenderPipeline>,
+    key: impl Fn(&GpuMaterial) -> PipelineKey,
+    gbuffer: bool,
+) -> (u32, u64) {
+    let (mut draw_calls, mut triangles) = (0, 0);
+    let mut pipeline = None;
+    for (material, mesh, instances) in batches {
+        let (Some(gpu_mesh), Some(gpu_material)) = (meshes.get(mesh), materials.get(material))
+        else {
+            continue;
+        };
+        let key = key(gpu_material);
+        if key.gbuffer != gbuffer {
+            continue;
+        }
+        if pipeline != Some(key) {
+            // Shader materials that never compiled aren't drawn.
+            let Some(variant) = pipelines.get(&key) else {
+                continue;
+            };
+            pipeline = Some(key);
+            pass.set_pipeline(variant);
+        }
+        pass.set_bind_group(1, &gpu_material.bind_group, &[]);
+        pass.set_vertex_buffer(0, gpu_mesh.vertices.slice(..));
+        pass.set_index_buffer(gpu_mesh.indices.slice(..), wgpu::IndexFormat::Uint32);
+        pass.draw_indexed(0..gpu_mesh.index_count, 0, instances.clone());
+        draw_calls += 1;
+        triangles += u64::from(gpu_mesh.index_count / 3) * instances.len() as u64;
+    }
+    (draw_calls, triangles)
+}
+
+/// Returns whether meshes with the material features are drawn by the prepass.
+pub(crate) fn is_opaque(features: MaterialFeatures) -> bool {
+    !features.contains(MaterialFeatures::ALPHA_MASK)
+        && !features.contains(MaterialFeatures::ALPHA_BLEND)
+}
+
+/// Returns the view projection matrix of the camera node, jittered by TAA's frame in a target of
+/// the size if TAA is used.
+pub(crate) fn view_projection(
+    scene: &Scene,
+    node: Node,
+    anti_aliasing: AntiAliasing,
+    frame: u32,
+    size: UVec2,
+) -> Mat4 {
+    let camera = scene.get::<Camera>(node).unwrap();
+    let view_projection = camera.view_projection_matrix(&world_transform(scene, node));
+    if anti_aliasing == AntiAliasing::Taa {
+        jitter(frame, size) * view_projection
+    } else {
+        view_projection
+    }
+}
+
+/// Appends the instances of the visible mesh nodes to the instances and returns their draws.
+/// Opaque and alpha masked nodes are grouped by material and mesh, and alpha blended nodes are
+/// drawn one by one from back to front as seen from the position along the forward direction of
+/// the view. Nodes without a material use the default material, and skinned and morphed nodes are
+/// deformed by their buffers in the resources.
+pub(crate) fn batch(
+    scene: &Scene,
+    visible: &[Node],
+    (forward, position): (Vec3, Vec3),
+    default_material: &Material,
+    resources: &GpuResources,
+    instances: &mut Vec<Instance>,
+) -> CameraDraws {
+    let mut groups = BTreeMap::<(MaterialFeatures, usize, usize), Batch>::new();
+    let mut transparent = Vec::new();
+    for &node in visible {
+        let Some(mesh) = scene.get::<Mesh>(node) else {
+            continue;
+        };
+        let material = scene.get::<Material>(node).unwrap_or(default_material);
+        let receiver = scene.get::<ShadowReceiver>(node) != Some(&ShadowReceiver(false));
+        let transform = world_transform(scene, node);
+        let instance = Instance::new(&transform, receiver);
+        if let Some(instance) = instance.map(|instance| instance.deformed(resources, node)) {
+            if material.alpha_mode() == AlphaMode::Blend {
+                let depth = (transform.translation() - position).dot(forward);
+                transparent.push((depth, material, mesh, instance));
+                continue;
+            }
+            // Sorting by features first minimizes pipeline switches.
+            let key = (material.features(), material.id(), mesh.id());
+            groups
+                .entry(key)
+                .or_insert_with(|| (material.clone(), mesh.clone(), Vec::new()))
+                .2
+                .push(instance);
+        }
+    }
+
+    let batches = groups
+        .into_values()
+        .map(|(material, mesh, group)| {
+            let start = instances.len() as u32;
+            instances.extend(group);
+            (material, mesh, start..instances.len() as u32)
+        })
+        .collect();
+
+    transparent.sort_by(|a, b| b.0.total_cmp(&a.0));
+    let transparent = transparent
+        .into_iter()
+        .map(|(_, material, mesh, instance)| {
+            let start = instances.len() as u32;
+            instances.push(instance);
+            (material.clone(), mesh.clone(), start..start + 1)
+        })
+        .collect();
+
+    CameraDraws {
+        batches,
+        transparent,
+        environment: None,
+        probes: [0; MAX_PROBES],
+        bounds: 0..0,
+        viewport: Viewport::default(),
+    }
+}
+
+fn camera_buffer(device: &wgpu::Device, cameras: u64) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("cameras"),
+        size: cameras * CAMERA_STRIDE,
+        usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+fn camera_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    buffer: &wgpu::Buffer,
+    shadow_maps: &ShadowMaps,
+    clusters: &LightClusters,
+    resources: &GpuResources,
+) -> wgpu::BindGroup {
+    let [palettes, morph_targets, morph_weights] = deform_entries(resources, 6);
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("cameras"),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                    buffer,
+                    offset: 0,
+                    size: wgpu::BufferSize::new(std::mem::size_of::<CameraUniform>() as u64),
+                }),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                    buffer,
+                    offset: LIGHTS_OFFSET,
+                    size: wgpu::BufferSize::new(std::mem::size_of::<LightsUniform>() as u64),
+                }),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: wgpu::BindingResource::TextureView(&shadow_maps.view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 3,
+                resource: wgpu::BindingResource::Sampler(&shadow_maps.sampler),
+            },
+            wgpu::BindGroupEntry {
+                binding: 4,
+                resource: clusters.lights.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 5,
+                resource: clusters.clusters.as_entire_binding(),
+            },
+            palettes,
+            morph_targets,
+            morph_weights,
+        ],
+    })
+}
+
+fn storage_entry(binding: u32) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Storage { read_only: true },
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    }
+}
+
+fn instance_buffer(device: &wgpu::Device, instances: u64) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("instances"),
+        size: instances.max(1) * std::mem::size_of::<Instance>() as u64,
+        usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+fn texture(
+    device: &wgpu::Device,
+    label: &str,
+    format: wgpu::TextureFormat,
+    size: UVec2,
+    samples: u32,
+) -> wgpu::TextureView {
+    // The depth is sampled by the deferred lighting pass, which isn't used with MSAA.
+    let mut usage = wgpu::TextureUsages::RENDER_ATTACHMENT;
+    if samples == 1 {
+        usage |= wgpu::TextureUsages::TEXTURE_BINDING;
+    }
+    device
+        .create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size: wgpu::Extent3d {
+                width: size.x.max(1),
+                height: size.y.max(1),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: samples,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage,
+            view_formats: &[],
+        })
+        .create_view(&wgpu::TextureViewDescriptor::default())
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Mat4;
+    use glam::Vec3;
+    use glam::Vec4;
+
+    use super::*;
+    use crate::render::image::ColorSpace;
+    use crate::render::image::HdrImage;
+    use crate::render::image::Image;
+    use crate::render::image::ImageSampler;
+    use crate::render::light::DirectionalLight;
+    use crate::render::light::PointLight;
+    use crate::render::material::StandardMaterial;
+    use crate::render::mesh::MorphTarget;
+    use crate::render::morph::MorphWeights;
+    use crate::render::shader_material::MaterialShader;
+    use crate::render::shader_material::UniformValue;
+    use crate::render::skin::Skeleton;
+    use crate::render::skin::SkinnedMesh;
+    use crate::render::tests::render;
+    use crate::render::tonemap::Tonemapping;
+    use crate::render::Color;
+    use crate::systems::compute_joint_palettes;
+    use crate::ComputedVisibility;
+    use crate::LocalTransform;
+    use crate::WorldTransform;
+
+    #[test]
+    fn shader_is_valid_for_all_features() {
+        use wgpu::naga;
+
+        let shadings = [
+            DebugShading::Lit,
+            DebugShading::Normals,
+            DebugShading::Overdraw,
+        ];
+        for (features, shading) in
+            MaterialFeatures::all().flat_map(|features| shadings.map(|shading| (features, shading)))
+        {
+            let mut defs = features.defs();
+            defs.extend(shading.def());
+            let source = pbr_shader(&defs);
+            let module = naga::front::wgsl::parse_str(&source).unwrap();
+            naga::valid::Validator::new(
+                naga::valid::ValidationFlags::all(),
+                naga::valid::Capabilities::empty(),
+            )
+            .validate(&module)
+            .unwrap();
+        }
+    }
+
+    #[test]
+    fn shader_material_compiles_after_vertex_stage() {
+        let prelude = pbr_shader(&[CUSTOM_MATERIAL]);
+        let shader = MaterialShader::new(
+            "@fragment
+            fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+                var surface: Surface;
+                surface.base_color = material.tint.rgb;
+                surface.normal = normalize(in.world_normal);
+                surface.view = normalize(camera.position.xyz - in.world_position);
+                let color = lighting(surface, in) + textureSample(glow_texture, glow_sampler, in.uv).rgb;
+                return vec4<f32>(color, 1.0);
+            }",
+        );
+        let mut material = ShaderMaterial {
+            uniforms: vec![("tint".into(), UniformValue::Color(Color::WHITE))],
+            textures: vec![("glow".into(), None)],
+            ..ShaderMaterial::new(shader)
+        };
+
+        assert!(compile(&prelude, &material).is_ok());
+        material.textures.clear();
+        assert!(compile(&prelude, &material).is_err());
+    }
+
+    /// Renders a unit cube seen from +Z and lit from +Z with the material and returns the 64x64
+    /// RGBA pixels, or `None` if there's no adapter.
+    fn render_cube(material: Option<Material>) -> Option<Vec<u8>> {
+        let mut scene = Scene::new();
+        scene.insert_resource(ClearColor(crate::render::Color::rgb(0.0, 0.0, 1.0)));
+        let cube = scene.spawn_with((Mesh::cube(1.0), WorldTransform::IDENTITY));
+        if let Some(material) = material {
+            scene.add(cube, material);
+        }
+        scene.spawn_with((
+            Camera {
+                tonemapping: Tonemapping::None,
+                ..Camera::perspective(1.0, 1.0, 0.1, 10.0)
+            },
+            WorldTransform::new(Mat4::from_translation(Vec3::new(0.0, 0.0, 3.0))),
+            VisibleNodes(vec![cube]),
+        ));
+        scene.spawn_with((DirectionalLight::default(), ComputedVisibility::Visible));
+        render(&scene)
+    }
+
+    #[test]
+    fn render_draws_visible_meshes() {
+        let Some(pixels) = render_cube(None) else {
+            return;
+        };
+
+        let pixel = |x: usize, y: usize| &pixels[(y * 64 + x) * 4..][..4];
+        assert_eq!(pixel(0, 0), [0, 0, 255, 255]);
+        let center = pixel(32, 32);
+        assert!(center[0] > 0 && center[0] == center[1] && center[1] == center[2]);
+    }
+
+    #[test]
+    fn render_deforms_skinned_meshes_with_their_joints() {
+        let mut data = Mesh::cube(1.0).data().clone();
+        data.joints = vec![[0; 4]; data.positions.len()];
+        data.weights = vec![Vec4::X; data.positions.len()];
+        let mut scene = Scene::new();
+        scene.insert_resource(ClearColor(Color::rgb(0.0, 0.0, 1.0)));
+        let cube = scene.spawn_with((Mesh::new(data).unwrap(), WorldTransform::IDENTITY));
+        let joint = scene.spawn_with(WorldTransform::IDENTITY);
+        let skeleton = Skeleton::from_pose(&scene, cube, vec![joint]);
+        scene.add(cube, SkinnedMesh { skeleton });
+        scene.set(
+            joint,
+            WorldTransform::new(Mat4::from_translation(Vec3::new(-1.0, 0.0, 0.0))),
+        );
+        compute_joint_palettes(&mut scene);
+        scene.spawn_with((
+            Camera {
+                tonemapping: Tonemapping::None,
+                ..Camera::perspective(1.0, 1.0, 0.1, 10.0)
+            },
+            WorldTransform::new(Mat4::from_translation(Vec3::new(0.0, 0.0, 3.0))),
+            VisibleNodes(vec![cube]),
+        ));
+        scene.spawn_with((DirectionalLight::default(), ComputedVisibility::Visible));
+        let Some(pixels) = render(&scene) else {
+            return;
+        };
+
+        let pixel = |x: usize, y: usize| &pixels[(y * 64 + x) * 4..][..4];
+        assert_ne!(pixel(9, 32), [0, 0, 255, 255]);
+        assert_eq!(pixel(32, 32), [0, 0, 255, 255]);
+    }
+
+    #[test]
+    fn render_offsets_morphed_meshes_by_their_weights() {
+        let mut data = Mesh::cube(1.0).data().clone();
+        data.morph_targets.push(MorphTarget {
+            positions: vec![Vec3::new(-2.0, 0.0, 0.0); data.vertex_count()],
+            normals: Vec::new(),
+        });
+        let mut scene = Scene::new();
+        scene.insert_resource(ClearColor(Color::rgb(0.0, 0.0, 1.0)));
+        let cube = scene.spawn_with((
+            Mesh::new(data).unwrap(),
+            MorphWeights(vec![0.5]),
+            WorldTransform::IDENTITY,
+        ));
+        scene.spawn_with((
+            Camera {
+                tonemapping: Tonemapping::None,
+                ..Camera::perspective(1.0, 1.0, 0.1, 10.0)
+            },
+            WorldTransform::new(Mat4::from_translation(Vec3::new(0.0, 0.0, 3.0))),
+            VisibleNodes(vec![cube]),
+        ));
+        scene.spawn_with((DirectionalLight::default(), ComputedVisibility::Visible));
+        let Some(pixels) = render(&scene) else {
+            return;
+        };
+
+        let pixel = |x: usize, y: usize| &pixels[(y * 64 + x) * 4..][..4];
+        assert_ne!(pixel(9, 32), [0, 0, 255, 255]);
+        assert_eq!(pixel(32, 32), [0, 0, 255, 255]);
+    }
+
+    #[test]
+    fn render_shades_shader_materials() {
+        let shader = MaterialShader::new(
+            "@fragment
+            fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+                return material.color;
+            }",
+        );
+        let material = Material::from(ShaderMaterial {
+            uniforms: vec![(
+                "color".into(),
+                UniformValue::Color(Color::rgb(1.0, 0.0, 0.0)),
+            )],
+            ..ShaderMaterial::new(shader)
+        });
+        let Some(pixels) = render_cube(Some(material)) else {
+            return;
+        };
+
+        assert_eq!(&pixels[(32 * 64 + 32) * 4..][..4], [255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn render_samples_material_textures() {
+        // Mip levels and custom samplers are uploaded with the texture.
+        let red = Image::new(
+            glam::UVec2::splat(4),
+            [255, 0, 0, 255].repeat(16),
+            ColorSpace::Srgb,
+        )
+        .unwrap()
+        .with_mipmaps()
+        .with_sampler(ImageSampler::default());
+        let material = Material::new(StandardMaterial {
+            base_color_texture: Some(red),
+            roughness: 1.0,
+            emissive: crate::render::Color::rgb(0.0, 1.0, 0.0),
+            ..StandardMaterial::default()
+        });
+        let Some(pixels) = render_cube(Some(material)) else {
+            return;
+        };
+
+        let center = &pixels[(32 * 64 + 32) * 4..][..4];
+        assert!(center[0] > 200 && center[1] == 255 && center[2] < 8);
+    }
+
+    #[test]
+    fn render_shades_fragments_with_lights_of_their_cluster() {
+        // A plane seen from above, with more local lights along its right edge than a camera
+        // could have before clustering, and a light in range of the left of the view only.
+        let down = Mat4::from_rotation_x(-std::f32::consts::FRAC_PI_2);
+        let mut scene = Scene::new();
+        scene.insert_resource(AntiAliasing::None);
+        let plane = scene.spawn_with((Mesh::plane(10.0), WorldTransform::IDENTITY));
+        for index in 0..200 {
+            let position = Vec3::new(1.5, 0.2, index as f32 / 100.0 - 1.0);
+            scene.spawn_with((
+                PointLight {
+                    range: 0.3,
+                    ..PointLight::default()
+                },
+                WorldTransform::new(Mat4::from_translation(position)),
+                ComputedVisibility::Visible,
+            ));
+        }
+        scene.spawn_with((
+            Camera {
+                tonemapping: Tonemapping::None,
+                ..Camera::perspective(1.0, 1.0, 0.1, 10.0)
+            },
+            WorldTransform::new(Mat4::from_translation(Vec3::new(0.0, 3.0, 0.0)) * down),
+            VisibleNodes(vec![plane]),
+        ));
+        let Some(unlit) = render(&scene) else {
+            return;
+        };
+
+        scene.spawn_with((
+            PointLight {
+                range: 1.0,
+                ..PointLight::default()
+            },
+            WorldTransform::new(Mat4::from_translation(Vec3::new(-1.0, 0.5, 0.0))),
+            ComputedVisibility::Visible,
+        ));
+        let lit = render(&scene).unwrap();
+
+        let pixel = |pixels: &[u8], x: usize| pixels[(32 * 64 + x) * 4];
+        assert!(pixel(&lit, 12) > pixel(&unlit, 12) + 32);
+        assert_eq!(pixel(&lit, 32), pixel(&unlit, 32));
+        assert!(pixel(&lit, 60) > pixel(&lit, 32));
+    }
+
+    #[test]
+    fn render_casts_shadows_onto_receivers() {
+        // A cube between a light and a plane, both seen from above. Only the plane is drawn, so
+        // the cube's shadow is seen in the center.
+        let down = Mat4::from_rotation_x(-std::f32::consts::FRAC_PI_2);
+        let mut scene = Scene::new();
+        let plane = scene.spawn_with((
+            Mesh::plane(10.0),
+            WorldTransform::IDENTITY,
+            ComputedVisibility::Visible,
+        ));
+        scene.spawn_with((
+            Mesh::cube(1.0),
+            WorldTransform::new(Mat4::from_translation(Vec3::new(0.0, 1.0, 0.0))),
+            ComputedVisibility::Visible,
+        ));
+        scene.spawn_with((
+            Camera {
+                tonemapping: Tonemapping::None,
+                ..Camera::perspective(1.0, 1.0, 0.1, 10.0)
+            },
+            WorldTransform::new(Mat4::from_translation(Vec3::new(0.0, 3.0, 0.0)) * down),
+            VisibleNodes(vec![plane]),
+        ));
+        scene.spawn_with((
+            DirectionalLight {
+                shadows: true,
+                ..DirectionalLight::default()
+            },
+            WorldTransform::new(down),
+            ComputedVisibility::Visible,
+        ));
+
+        let Some(pixels) = render(&scene) else {
+            return;
+        };
+        let pixel = |x: usize, y: usize| pixels[(y * 64 + x) * 4];
+        assert!(pixel(32, 32) < pixel(4, 4) / 2);
+
+        scene.add(plane, ShadowReceiver(false));
+        let pixels = render(&scene).unwrap();
+        let pixel = |x: usize, y: usize| pixels[(y * 64 + x) * 4];
+        assert_eq!(pixel(32, 32), pixel(33, 32));
+        assert!(pixel(32, 32) >= pixel(4, 4));
+    }
+
+    #[test]
+    fn render_lights_meshes_with_environment() {
+        // A red sky above blue ground, lighting the top of a cube seen from above.
+        let (red, blue) = ([1.0, 0.0, 0.0], [0.0, 0.0, 1.0]);
+        let sky = HdrImage::new(UVec2::new(2, 8), [[red; 8], [blue; 8]].concat()).unwrap();
+        let mut scene = Scene::new();
+        let cube = scene.spawn_with((Mesh::cube(1.0), WorldTransform::IDENTITY));
+        scene.spawn_with((
+            Camera {
+                tonemapping: Tonemapping::None,
+                ..Camera::perspective(1.0, 1.0, 0.1, 10.0)
+            },
+            WorldTransform::new(
+                Mat4::from_translation(Vec3::new(0.0, 3.0, 0.0))
+                    * Mat4::from_rotation_x(-std::f32::consts::FRAC_PI_2),
+            ),
+            VisibleNodes(vec![cube]),
+            EnvironmentLight::new(sky),
+        ));
+
+        let Some(pixels) = render(&scene) else {
+            return;
+        };
+
+        let center = &pixels[(32 * 64 + 32) * 4..][..4];
+        assert!(center[0] > 200 && center[1] == 0 && center[2] < 32);
+    }
+
+    #[test]
+    fn batch_groups_instances_by_material_and_mesh() {
+        let cube = Mesh::cube(1.0);
+        let sphere = Mesh::sphere(1.0, 8, 4);
+        let mut scene = Scene::new();
+        let nodes = [
+            scene.spawn_with((cube.clone(), WorldTransform::IDENTITY)),
+            scene.spawn_with((sphere, WorldTransform::IDENTITY)),
+            scene.spawn_with((cube.clone(), WorldTransform::IDENTITY)),
+            scene.spawn_with((cube, Material::default(), WorldTransform::IDENTITY)),
+            scene.spawn_with(LocalTransform::IDENTITY),
+            scene.spawn_with((
+                Mesh::plane(1.0),
+                WorldTransform::new(Mat4::from_scale(Vec3::ZERO)),
+            )),
+        ];
+        let mut instances = Vec::new();
+
+        let view = (Vec3::NEG_Z, Vec3::ZERO);
+        let draws = batch(
+            &scene,
+            &nodes,
+            view,
+            &Material::default(),
+            &GpuResources::default(),
+            &mut instances,
+        );
+
+        assert_eq!(instances.len(), 4);
+        assert!(draws.transparent.is_empty());
+        let mut counts = draws
+            .batches
+            .iter()
+            .map(|(_, _, range)| range.len())
+            .collect::<Vec<_>>();
+        counts.sort();
+        assert_eq!(counts, [1, 1, 2]);
+    }
+
+    #[test]
+    fn batch_sorts_blended_instances_back_to_front() {
+        let glass = Material::new(StandardMaterial {
+            alpha_mode: AlphaMode::Blend,
+            ..StandardMaterial::default()
+        });
+        let meshes = [1.0, 2.0, 3.0].map(Mesh::cube);
+        let mut scene = Scene::new();
+        let at = |z| WorldTransform::new(Mat4::from_translation(Vec3::new(0.0, 0.0, z)));
+        let nodes = [
+            scene.spawn_with((meshes[0].clone(), glass.clone(), at(-2.0))),
+            scene.spawn_with((meshes[1].clone(), glass.clone(), at(-5.0))),
+            scene.spawn_with((meshes[0].clone(), at(-1.0))),
+            scene.spawn_with((meshes[2].clone(), glass, at(-3.0))),
+        ];
+        let mut instances = Vec::new();
+
+        let view = (Vec3::NEG_Z, Vec3::ZERO);
+        let draws = batch(
+            &scene,
+            &nodes,
+            view,
+            &Material::default(),
+            &GpuResources::default(),
+            &mut instances,
+        );
+
+        assert_eq!(instances.len(), 4);
+        assert_eq!(draws.batches.len(), 1);
+        let transparent = draws
+            .transparent
+            .into_iter()
+            .map(|(_, mesh, range)| (mesh, range))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            transparent,
+            [
+                (meshes[1].clone(), 1..2),
+                (meshes[2].clone(), 2..3),
+                (meshes[0].clone(), 3..4),
+            ]
+        );
+    }
+
+    /// Renders quads facing a camera at the origin with unlit materials of the colors and alpha
+    /// modes, at the distances from it, and returns the center pixel.
+    fn render_quads(quads: &[(Color, AlphaMode, f32)]) -> Option<[u8; 4]> {
+        let mut scene = Scene::new();
+        scene.insert_resource(AntiAliasing::None);
+        let visible = quads
+            .iter()
+            .map(|&(color, alpha_mode, distance)| {
+                let material = Material::new(StandardMaterial {
+                    base_color: Color::rgba(0.0, 0.0, 0.0, color.a),
+                    emissive: Color::rgb(color.r, color.g, color.b),
+                    alpha_mode,
+                    ..StandardMaterial::default()
+                });
+                let transform = Mat4::from_translation(Vec3::new(0.0, 0.0, -distance))
+                    * Mat4::from_rotation_x(std::f32::consts::FRAC_PI_2);
+                scene.spawn_with((Mesh::plane(10.0), material, WorldTransform::new(transform)))
+            })
+            .collect();
+        scene.spawn_with((
+            Camera {
+                tonemapping: Tonemapping::None,
+                ..Camera::perspective(1.0, 1.0, 0.1, 10.0)
+            },
+            WorldTransform::IDENTITY,
+            VisibleNodes(visible),
+        ));
+
+        let pixels = render(&scene)?;
+        Some(pixels[(32 * 64 + 32) * 4..][..4].try_into().unwrap())
+    }
+
+    #[test]
+    fn render_blends_transparent_meshes_back_to_front() {
+        let red = Color::rgba(1.0, 0.0, 0.0, 0.5);
+        let green = Color::rgba(0.0, 1.0, 0.0, 0.5);
+        // The nearer green quad is listed first but drawn last.
+        let Some(pixel) =
+            render_quads(&[(green, AlphaMode::Blend, 1.0), (red, AlphaMode::Blend, 2.0)])
+        else {
+            return;
+        };
+        assert_eq!(pixel, [64, 128, 0, 255]);
+
+        // Opaque meshes hide the blended meshes behind them.
+        let blue = Color::rgb(0.0, 0.0, 1.0);
+        let pixel = render_quads(&[(red, AlphaMode::Blend, 3.0), (blue, AlphaMode::Opaque, 2.0)]);
+        assert_eq!(pixel, Some([0, 0, 255, 255]));
+    }
+
+    #[test]
+    fn render_discards_masked_fragments_below_cutoff() {
+        let red = Color::rgba(1.0, 0.0, 0.0, 0.4);
+        let green = Color::rgba(0.0, 1.0, 0.0, 0.6);
+        let Some(pixel) = render_quads(&[
+            (red, AlphaMode::Mask(0.5), 1.0),
+            (green, AlphaMode::Mask(0.5), 2.0),
+        ]) else {
+            return;
+        };
+
+        assert_eq!(pixel, [0, 255, 0, 255]);
+    }
+}
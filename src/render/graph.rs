@@ -0,0 +1,551 @@
+//! # Render Graph
+//!
+//! Ordered set of render nodes recording the GPU commands of a frame. Nodes share GPU resources,
+//! e.g. shadow maps rendered by one node and sampled by another, through the typed resources of
+//! the [RenderContext]. Compute shaders are dispatched by
+//! [crate::render::compute::ComputeNode]s declaring their bindings.
+
+use std::any::Any;
+use std::any::TypeId;
+use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::fmt;
+
+use glam::UVec2;
+
+use crate::render::anti_aliasing::AntiAliasingPass;
+use crate::render::atmosphere::AtmospherePass;
+use crate::render::bloom::BloomPass;
+use crate::render::forward::ForwardPass;
+use crate::render::image::GpuImages;
+use crate::render::mesh::GpuMeshes;
+use crate::render::morph::GpuMorphs;
+use crate::render::prepass::PrepassNode;
+use crate::render::shadow::ShadowPass;
+use crate::render::skin::GpuSkins;
+use crate::render::skybox::SkyboxPass;
+use crate::render::sprite::SpritePass;
+use crate::render::stats::FrameProfiler;
+use crate::render::tonemap::TonemapPass;
+use crate::Node;
+use crate::Scene;
+
+/// Label of the built-in node rendering the shadow maps of shadow casting lights.
+pub const SHADOW: &str = "pulse::shadow";
+
+/// Label of the built-in node drawing the depth of the opaque meshes seen by every camera before
+/// the [FORWARD] node, so it only shades the visible fragments and the depth can be sampled by later
+/// nodes. Disabled by default.
+pub const PREPASS: &str = "pulse::prepass";
+
+/// Label of the built-in node drawing the skybox of the first camera as the background of the
+/// meshes.
+pub const SKYBOX: &str = "pulse::skybox";
+
+/// Label of the built-in node drawing the atmosphere of the first camera as the background of the
+/// meshes, instead of its skybox.
+pub const ATMOSPHERE: &str = "pulse::atmosphere";
+
+/// Label of the built-in node drawing the meshes seen by every camera to a high dynamic range
+/// target.
+pub const FORWARD: &str = "pulse::forward";
+
+/// Label of the built-in node drawing the sprites and texts seen by every camera to the high
+/// dynamic range target.
+pub const SPRITE: &str = "pulse::sprite";
+
+/// Label of the built-in node applying FXAA or TAA to the high dynamic range target.
+pub const ANTI_ALIASING: &str = "pulse::anti_aliasing";
+
+/// Label of the built-in node adding bloom to the high dynamic range target.
+pub const BLOOM: &str = "pulse::bloom";
+
+/// Label of the built-in node writing the exposed and tonemapped high dynamic range target to the
+/// render target.
+pub const TONEMAP: &str = "pulse::tonemap";
+
+/// # Render Node
+///
+/// Step of a [RenderGraph] recording GPU commands for the scene.
+///
+/// ```
+/// # use pulse::render::graph::RenderContext;
+/// # use pulse::render::graph::RenderNode;
+/// # use pulse::Scene;
+/// struct FrameCounter(u64);
+///
+/// impl RenderNode for FrameCounter {
+///     fn run(&mut self, context: &mut RenderContext, _: &Scene) {
+///         self.0 += 1;
+///         context.insert_resource(self.0);
+///     }
+/// }
+/// ```
+pub trait RenderNode: 'static + Send + Sync {
+    /// Records the node's commands with the context's encoder.
+    fn run(&mut self, context: &mut RenderContext, scene: &Scene);
+}
+
+/// # Render Context
+///
+/// Device, command encoder, and target of the frame passed to every [RenderNode], with typed
+/// resources shared between the nodes. The graph runs once for every render target, the window or
+/// an offscreen [crate::render::target::RenderTarget], with the cameras rendering to it. Resources
+/// persist between frames and are kept separately for every render target, e.g. textures with the
+/// target's size.
+pub struct RenderContext<'a> {
+    pub(crate) device: &'a wgpu::Device,
+    pub(crate) queue: &'a wgpu::Queue,
+    pub(crate) encoder: &'a mut wgpu::CommandEncoder,
+    pub(crate) target: &'a wgpu::TextureView,
+    pub(crate) target_format: wgpu::TextureFormat,
+    pub(crate) target_size: UVec2,
+    pub(crate) cameras: &'a [Node],
+    pub(crate) resources: &'a mut GpuResources,
+    pub(crate) profiler: &'a mut FrameProfiler,
+}
+
+impl RenderContext<'_> {
+    /// Returns the GPU device.
+    pub fn device(&self) -> &wgpu::Device {
+        self.device
+    }
+
+    /// Returns the queue for writing to buffers and textures.
+    pub fn queue(&self) -> &wgpu::Queue {
+        self.queue
+    }
+
+    /// Returns the encoder recording the frame's commands.
+    pub fn encoder(&mut self) -> &mut wgpu::CommandEncoder {
+        self.encoder
+    }
+
+    /// Returns the view of the texture the frame is rendered to.
+    pub fn target(&self) -> &wgpu::TextureView {
+        self.target
+    }
+
+    /// Returns the format of the texture the frame is rendered to.
+    pub fn target_format(&self) -> wgpu::TextureFormat {
+        self.target_format
+    }
+
+    /// Returns the size of the texture the frame is rendered to in pixels.
+    pub fn target_size(&self) -> UVec2 {
+        self.target_size
+    }
+
+    /// Returns the camera nodes rendering to the target, sorted by node.
+    pub fn cameras(&self) -> &[Node] {
+        self.cameras
+    }
+
+    /// Adds draw calls and the triangles they drew to the frame's
+    /// [crate::render::stats::RenderStats].
+    pub fn count_draws(&mut self, draw_calls: u32, triangles: u64) {
+        self.profiler.stats.draw_calls += draw_calls;
+        self.profiler.stats.triangles += triangles;
+    }
+
+    /// Inserts the resource for the following nodes, replacing the previous one of the same type.
+    pub fn insert_resource<T: 'static + Send + Sync>(&mut self, resource: T) {
+        self.resources
+            .slots
+            .insert(TypeId::of::<T>(), Box::new(resource));
+    }
+
+    /// Returns the resource of the type, if any.
+    pub fn resource<T: 'static + Send + Sync>(&self) -> Option<&T> {
+        self.resources.resource()
+    }
+
+    /// Returns the resource of the type mutably, if any.
+    pub fn resource_mut<T: 'static + Send + Sync>(&mut self) -> Option<&mut T> {
+        self.resources.resource_mut()
+    }
+
+    /// Removes the resource of the type and returns it, if any.
+    pub fn remove_resource<T: 'static + Send + Sync>(&mut self) -> Option<T> {
+        self.resources
+            .slots
+            .remove(&TypeId::of::<T>())
+            .and_then(|resource| resource.downcast().ok())
+            .map(|resource| *resource)
+    }
+}
+
+/// Typed resources of a render target.
+type Slots = HashMap<TypeId, Box<dyn Any + Send + Sync>>;
+
+/// GPU resources shared between render nodes. Meshes, images, joint palettes, and morph weights are
+/// shared between all render targets, while the typed resources are kept for each target, keyed by
+/// the id of its image or `None` for the window.
+#[derive(Default)]
+pub(crate) struct GpuResources {
+    pub(crate) meshes: GpuMeshes,
+    pub(crate) images: GpuImages,
+    pub(crate) skins: GpuSkins,
+    pub(crate) morphs: GpuMorphs,
+    /// Resources of the target being rendered.
+    slots: Slots,
+    target: Option<usize>,
+    /// Resources of the other targets.
+    targets: HashMap<Option<usize>, Slots>,
+}
+
+impl GpuResources {
+    /// Returns a value that changes whenever a buffer deforming meshes is recreated, so bind
+    /// groups binding them are recreated.
+    pub(crate) fn deform_generation(&self) -> u64 {
+        self.skins.generation + self.morphs.generation
+    }
+
+    pub(crate) fn resource<T: 'static + Send + Sync>(&self) -> Option<&T> {
+        self.slots
+            .get(&TypeId::of::<T>())
+            .and_then(|resource| resource.downcast_ref())
+    }
+
+    pub(crate) fn resource_mut<T: 'static + Send + Sync>(&mut self) -> Option<&mut T> {
+        self.slots
+            .get_mut(&TypeId::of::<T>())
+            .and_then(|resource| resource.downcast_mut())
+    }
+
+    /// Switches the typed resources to those of the target.
+    pub(crate) fn select_target(&mut self, target: Option<usize>) {
+        if target == self.target {
+            return;
+        }
+
+        let slots = self.targets.remove(&target).unwrap_or_default();
+        let previous = std::mem::replace(&mut self.slots, slots);
+        self.targets.insert(self.target, previous);
+        self.target = target;
+    }
+
+    /// Drops the typed resources of the targets that aren't in the list.
+    pub(crate) fn retain_targets(&mut self, targets: &[Option<usize>]) {
+        self.targets.retain(|target, _| targets.contains(target));
+        if !targets.contains(&self.target) {
+            self.slots.clear();
+        }
+    }
+}
+
+struct NodeEntry {
+    label: &'static str,
+    node: Box<dyn RenderNode>,
+    before: Vec<&'static str>,
+    after: Vec<&'static str>,
+    enabled: bool,
+}
+
+/// # Render Graph
+///
+/// Render nodes run in the order they were added unless reordered by their `before` and `after`
+/// constraints. The renderer's graph starts with the built-in nodes labelled [SHADOW], [PREPASS],
+/// [SKYBOX], [ATMOSPHERE], [FORWARD], [SPRITE], [ANTI_ALIASING], [BLOOM], and [TONEMAP], all of
+/// them enabled except for [PREPASS].
+///
+/// ```
+/// # use pulse::render::graph::RenderContext;
+/// # use pulse::render::graph::RenderGraph;
+/// # use pulse::render::graph::RenderNode;
+/// # use pulse::Scene;
+/// struct Overlay;
+///
+/// impl RenderNode for Overlay {
+///     fn run(&mut self, _: &mut RenderContext, _: &Scene) {}
+/// }
+///
+/// let mut graph = RenderGraph::new();
+/// graph.add_node("overlay", Overlay).after("scene");
+/// graph.add_node("scene", Overlay);
+///
+/// assert_eq!(graph.node_labels().unwrap(), ["scene", "overlay"]);
+/// ```
+#[derive(Default)]
+pub struct RenderGraph {
+    nodes: Vec<NodeEntry>,
+    order: Option<Vec<usize>>,
+}
+
+impl RenderGraph {
+    /// Returns an empty graph.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns a graph with the built-in nodes, the [SHADOW], [PREPASS], [SKYBOX], [ATMOSPHERE],
+    /// [FORWARD], [SPRITE], [ANTI_ALIASING], [BLOOM], and [TONEMAP] nodes in this order, with
+    /// [PREPASS] disabled.
+    pub(crate) fn with_builtin_nodes(device: &wgpu::Device) -> Self {
+        let mut graph = Self::new();
+        graph.add_node(SHADOW, ShadowPass::new(device));
+        graph
+            .add_node(PREPASS, PrepassNode::new(device))
+            .after(SHADOW);
+        graph.set_enabled(PREPASS, false);
+        graph
+            .add_node(SKYBOX, SkyboxPass::new(device))
+            .after(PREPASS);
+        graph
+            .add_node(ATMOSPHERE, AtmospherePass::new(device))
+            .after(SKYBOX);
+        graph
+            .add_node(FORWARD, ForwardPass::new(device))
+            .after(ATMOSPHERE);
+        graph
+            .add_node(SPRITE, SpritePass::new(device))
+            .after(FORWARD);
+        graph
+            .add_node(ANTI_ALIASING, AntiAliasingPass::new(device))
+            .after(SPRITE);
+        graph
+            .add_node(BLOOM, BloomPass::new(device))
+            .after(ANTI_ALIASING);
+        graph
+            .add_node(TONEMAP, TonemapPass::new(device))
+            .after(BLOOM);
+        graph
+    }
+
+    /// Adds the node with the unique label to the graph and returns a builder for its ordering
+    /// constraints.
+    pub fn add_node(&mut self, label: &'static str, node: impl RenderNode) -> RenderNodeConfig<'_> {
+        self.order = None;
+        self.nodes.push(NodeEntry {
+            label,
+            node: Box::new(node),
+            before: Vec::new(),
+            after: Vec::new(),
+            enabled: true,
+        });
+
+        RenderNodeConfig {
+            entry: self.nodes.last_mut().unwrap(),
+        }
+    }
+
+    /// Removes the node with the label and returns whether there was one.
+    pub fn remove_node(&mut self, label: &'static str) -> bool {
+        let len = self.nodes.len();
+        self.nodes.retain(|entry| entry.label != label);
+        self.order = None;
+        self.nodes.len() < len
+    }
+
+    /// Enables or disables the node with the label. Disabled nodes are skipped when the graph is
+    /// run.
+    pub fn set_enabled(&mut self, label: &'static str, enabled: bool) {
+        for entry in &mut self.nodes {
+            if entry.label == label {
+                entry.enabled = enabled;
+            }
+        }
+    }
+
+    /// Returns false if the node with the label is disabled.
+    pub fn is_enabled(&self, label: &'static str) -> bool {
+        self.nodes
+            .iter()
+            .any(|entry| entry.label == label && entry.enabled)
+    }
+
+    /// Returns the labels of the nodes in the order they will be run.
+    pub fn node_labels(&mut self) -> Result<Vec<&'static str>, RenderGraphError> {
+        self.build()?;
+        Ok(self
+            .order
+            .iter()
+            .flatten()
+            .map(|index| self.nodes[*index].label)
+            .collect())
+    }
+
+    /// Resolves the order of the nodes from their constraints. The order is cached until a node
+    /// is added or removed.
+    pub fn build(&mut self) -> Result<(), RenderGraphError> {
+        if self.order.is_none() {
+            self.order = Some(self.sort()?);
+        }
+
+        Ok(())
+    }
+
+    /// Runs the enabled nodes in order, timing them if the device supports timestamp queries.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the constraints of the nodes can't be satisfied.
+    pub(crate) fn run(&mut self, context: &mut RenderContext, scene: &Scene) {
+        if let Err(error) = self.build() {
+            panic!("{error}");
+        }
+
+        for index in self.order.iter().flatten() {
+            let entry = &mut self.nodes[*index];
+            if entry.enabled {
+                context.profiler.start_node(context.encoder, entry.label);
+                entry.node.run(context, scene);
+            }
+        }
+        context.profiler.end_run(context.encoder);
+    }
+
+    /// Sorts the nodes topologically, keeping the insertion order between unconstrained nodes.
+    fn sort(&self) -> Result<Vec<usize>, RenderGraphError> {
+        let index_of = |label: &'static str| {
+            self.nodes
+                .iter()
+                .position(|entry| entry.label == label)
+                .ok_or(RenderGraphError::UnknownLabel(label))
+        };
+
+        let mut successors = vec![Vec::new(); self.nodes.len()];
+        let mut predecessor_counts = vec![0; self.nodes.len()];
+        for (index, entry) in self.nodes.iter().enumerate() {
+            if index_of(entry.label)? != index {
+                return Err(RenderGraphError::DuplicateLabel(entry.label));
+            }
+
+            for label in &entry.before {
+                let successor = index_of(label)?;
+                successors[index].push(successor);
+                predecessor_counts[successor] += 1;
+            }
+
+            for label in &entry.after {
+                let predecessor = index_of(label)?;
+                successors[predecessor].push(index);
+                predecessor_counts[index] += 1;
+            }
+        }
+
+        let mut ready = (0..self.nodes.len())
+            .filter(|index| predecessor_counts[*index] == 0)
+            .collect::<VecDeque<_>>();
+        let mut order = Vec::with_capacity(self.nodes.len());
+        while let Some(index) = ready.pop_front() {
+            order.push(index);
+
+            for successor in &successors[index] {
+                predecessor_counts[*successor] -= 1;
+                if predecessor_counts[*successor] == 0 {
+                    let position = ready.partition_point(|index| index < successor);
+                    ready.insert(position, *successor);
+                }
+            }
+        }
+
+        if order.len() < self.nodes.len() {
+            let index = (0..self.nodes.len())
+                .find(|index| !order.contains(index))
+                .unwrap();
+            return Err(RenderGraphError::Cycle(self.nodes[index].label));
+        }
+
+        Ok(order)
+    }
+}
+
+/// # Render Node Config
+///
+/// Builder for the ordering constraints of a node returned by [RenderGraph::add_node].
+pub struct RenderNodeConfig<'a> {
+    entry: &'a mut NodeEntry,
+}
+
+impl RenderNodeConfig<'_> {
+    /// Runs the node before the node with the given label.
+    pub fn before(self, label: &'static str) -> Self {
+        self.entry.before.push(label);
+        self
+    }
+
+    /// Runs the node after the node with the given label.
+    pub fn after(self, label: &'static str) -> Self {
+        self.entry.after.push(label);
+        self
+    }
+}
+
+/// # Render Graph Error
+///
+/// Error returned when the ordering constraints of a [RenderGraph] can't be satisfied.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum RenderGraphError {
+    /// A `before` or `after` constraint refers to a label no node has.
+    UnknownLabel(&'static str),
+    /// More than one node has the label.
+    DuplicateLabel(&'static str),
+    /// The constraints of the labelled node form a cycle.
+    Cycle(&'static str),
+}
+
+impl fmt::Display for RenderGraphError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownLabel(label) => write!(f, "no render node has the label `{label}`"),
+            Self::DuplicateLabel(label) => {
+                write!(f, "more than one render node has the label `{label}`")
+            }
+            Self::Cycle(label) => {
+                write!(
+                    f,
+                    "ordering constraints of render node `{label}` form a cycle"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for RenderGraphError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Empty;
+
+    impl RenderNode for Empty {
+        fn run(&mut self, _: &mut RenderContext, _: &Scene) {}
+    }
+
+    #[test]
+    fn node_labels_in_constraint_order() {
+        let mut graph = RenderGraph::new();
+        graph.add_node("post", Empty).after("main");
+        graph.add_node("main", Empty);
+        graph.add_node("pre", Empty).before("main");
+
+        assert_eq!(graph.node_labels().unwrap(), ["pre", "main", "post"]);
+        assert!(graph.remove_node("pre"));
+        assert_eq!(graph.node_labels().unwrap(), ["main", "post"]);
+    }
+
+    #[test]
+    fn node_labels_with_invalid_constraints() {
+        let mut graph = RenderGraph::new();
+        graph.add_node("a", Empty).after("b");
+        graph.add_node("b", Empty).after("a");
+        assert_eq!(graph.node_labels(), Err(RenderGraphError::Cycle("a")));
+
+        let mut graph = RenderGraph::new();
+        graph.add_node("a", Empty).after("missing");
+        assert_eq!(
+            graph.node_labels(),
+            Err(RenderGraphError::UnknownLabel("missing"))
+        );
+
+        let mut graph = RenderGraph::new();
+        graph.add_node("a", Empty);
+        graph.add_node("a", Empty);
+        assert_eq!(
+            graph.node_labels(),
+            Err(RenderGraphError::DuplicateLabel("a"))
+        );
+    }
+}
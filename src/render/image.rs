@@ -0,0 +1,1850 @@
+//! # Image
+//!
+//! Images sampled by materials as textures, images cameras render to, and high dynamic range
+//! images and cubemaps lighting the scene or drawn behind it. The [ImageLoader] loads PNG, JPEG,
+//! and KTX2 files as [Image]s, and the [HdrImageLoader] loads Radiance HDR and OpenEXR files as
+//! [HdrImage]s. Images can be compressed to a [BlockFormat], which devices supporting it sample
+//! instead of the 8-bit RGBA pixels.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::io::Read;
+use std::sync::Arc;
+use std::sync::Weak;
+
+use glam::UVec2;
+use ktx2::Format;
+use ktx2::SupercompressionScheme;
+use ruzstd::decoding::StreamingDecoder;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::assets::cache::ProcessedReader;
+use crate::assets::cache::ProcessedWriter;
+use crate::assets::AssetLoader;
+use crate::assets::LoadContext;
+use crate::render::srgb_to_linear;
+
+mod bc;
+
+/// Device features required for sampling the blocks of compressed images instead of their 8-bit
+/// RGBA pixels.
+pub(crate) const COMPRESSION_FEATURES: wgpu::Features = wgpu::Features::TEXTURE_COMPRESSION_BC;
+
+/// # Color Space
+///
+/// Color space of an [Image]'s color channels.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub enum ColorSpace {
+    /// sRGB encoded colors, e.g. of base color and emissive textures. Converted to linear colors
+    /// when sampled.
+    #[default]
+    Srgb,
+    /// Linear data, e.g. of normal, metallic-roughness, and occlusion textures.
+    Linear,
+}
+
+/// # Block Format
+///
+/// GPU-compressed format of an [Image], storing each block of 4x4 pixels in 8 or 16 bytes instead
+/// of 64. Compressed images take a quarter or less of the GPU memory and bandwidth of 8-bit RGBA
+/// ones, at a loss of quality.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub enum BlockFormat {
+    /// BC1, RGB colors in 8 bytes per block, e.g. for opaque base color and normal textures. The
+    /// alpha of compressed images is dropped.
+    Bc1,
+    /// BC3, RGB colors and alpha in 16 bytes per block, e.g. for base color textures with alpha.
+    Bc3,
+    /// BC4, the red channel in 8 bytes per block, e.g. for occlusion textures. Sampled as linear
+    /// data, whatever the image's color space.
+    Bc4,
+    /// BC5, the red and green channels in 16 bytes per block. Sampled as linear data, whatever the
+    /// image's color space.
+    Bc5,
+}
+
+impl BlockFormat {
+    /// Returns the number of bytes of a block of 4x4 pixels.
+    const fn block_len(self) -> usize {
+        match self {
+            Self::Bc1 | Self::Bc4 => 8,
+            Self::Bc3 | Self::Bc5 => 16,
+        }
+    }
+
+    /// Returns whether the format only stores linear data.
+    const fn is_linear(self) -> bool {
+        matches!(self, Self::Bc4 | Self::Bc5)
+    }
+
+    fn to_wgpu(self, color_space: ColorSpace) -> wgpu::TextureFormat {
+        match (self, color_space) {
+            (Self::Bc1, ColorSpace::Srgb) => wgpu::TextureFormat::Bc1RgbaUnormSrgb,
+            (Self::Bc1, ColorSpace::Linear) => wgpu::TextureFormat::Bc1RgbaUnorm,
+            (Self::Bc3, ColorSpace::Srgb) => wgpu::TextureFormat::Bc3RgbaUnormSrgb,
+            (Self::Bc3, ColorSpace::Linear) => wgpu::TextureFormat::Bc3RgbaUnorm,
+            (Self::Bc4, _) => wgpu::TextureFormat::Bc4RUnorm,
+            (Self::Bc5, _) => wgpu::TextureFormat::Bc5RgUnorm,
+        }
+    }
+}
+
+/// # Address Mode
+///
+/// How an [ImageSampler] samples texture coordinates outside of the image.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub enum AddressMode {
+    /// The pixels at the edges extend outwards.
+    ClampToEdge,
+    /// The image is tiled.
+    #[default]
+    Repeat,
+    /// The image is tiled, mirrored every other time.
+    MirrorRepeat,
+}
+
+impl AddressMode {
+    fn to_wgpu(self) -> wgpu::AddressMode {
+        match self {
+            Self::ClampToEdge => wgpu::AddressMode::ClampToEdge,
+            Self::Repeat => wgpu::AddressMode::Repeat,
+            Self::MirrorRepeat => wgpu::AddressMode::MirrorRepeat,
+        }
+    }
+}
+
+/// # Filter Mode
+///
+/// How an [ImageSampler] samples between pixels or mip levels.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub enum FilterMode {
+    /// The nearest pixel or mip level is sampled, e.g. for pixel art.
+    Nearest,
+    /// The neighbouring pixels or mip levels are blended.
+    #[default]
+    Linear,
+}
+
+impl FilterMode {
+    fn to_wgpu(self) -> wgpu::FilterMode {
+        match self {
+            Self::Nearest => wgpu::FilterMode::Nearest,
+            Self::Linear => wgpu::FilterMode::Linear,
+        }
+    }
+}
+
+/// # Image Sampler
+///
+/// How an [Image] is sampled as a texture. Defaults to tiling the image with linear filtering.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ImageSampler {
+    /// Address mode of horizontal texture coordinates.
+    pub address_mode_u: AddressMode,
+    /// Address mode of vertical texture coordinates.
+    pub address_mode_v: AddressMode,
+    /// Filter of magnified pixels.
+    pub mag_filter: FilterMode,
+    /// Filter of minified pixels.
+    pub min_filter: FilterMode,
+    /// Filter between mip levels.
+    pub mipmap_filter: FilterMode,
+    /// Maximum anisotropy of the filtering, from 1 for none to 16. Ignored unless all filters are
+    /// linear.
+    pub anisotropy: u16,
+}
+
+impl ImageSampler {
+    fn descriptor(&self) -> wgpu::SamplerDescriptor<'static> {
+        let linear = [self.mag_filter, self.min_filter, self.mipmap_filter]
+            .iter()
+            .all(|filter| *filter == FilterMode::Linear);
+        wgpu::SamplerDescriptor {
+            label: Some("image"),
+            address_mode_u: self.address_mode_u.to_wgpu(),
+            address_mode_v: self.address_mode_v.to_wgpu(),
+            mag_filter: self.mag_filter.to_wgpu(),
+            min_filter: self.min_filter.to_wgpu(),
+            mipmap_filter: self.mipmap_filter.to_wgpu(),
+            anisotropy_clamp: if linear {
+                self.anisotropy.clamp(1, 16)
+            } else {
+                1
+            },
+            ..wgpu::SamplerDescriptor::default()
+        }
+    }
+}
+
+impl Default for ImageSampler {
+    fn default() -> Self {
+        Self {
+            address_mode_u: AddressMode::Repeat,
+            address_mode_v: AddressMode::Repeat,
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Linear,
+            mipmap_filter: FilterMode::Linear,
+            anisotropy: 1,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct ImageData {
+    size: UVec2,
+    pixels: Vec<u8>,
+    /// Pixels of the mip levels after the first, each half the size of the previous one.
+    mips: Vec<Vec<u8>>,
+    color_space: ColorSpace,
+    sampler: Option<ImageSampler>,
+    blocks: Option<Blocks>,
+}
+
+/// Block compressed pixels of each mip level of an image, uploaded instead of its 8-bit RGBA
+/// pixels to devices that support the format.
+#[derive(Clone, Debug)]
+struct Blocks {
+    format: BlockFormat,
+    levels: Vec<Vec<u8>>,
+}
+
+/// # Image
+///
+/// Image with 8-bit RGBA pixels. The pixels are shared between clones of the image, so the same
+/// image can be used by many materials and is uploaded to the GPU once. Images are equal if they
+/// share the same pixels.
+///
+/// ```
+/// # use glam::UVec2;
+/// # use pulse::render::image::ColorSpace;
+/// # use pulse::render::image::Image;
+/// let checker = [[255, 255, 255, 255], [0, 0, 0, 255], [0, 0, 0, 255], [255, 255, 255, 255]];
+/// let image = Image::new(UVec2::new(2, 2), checker.concat(), ColorSpace::Srgb).unwrap();
+///
+/// assert_eq!(image.pixel(1, 0), [0, 0, 0, 255]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Image {
+    data: Arc<ImageData>,
+}
+
+impl Image {
+    /// Returns the image with the pixels in 8-bit RGBA row by row from the top-left corner, or
+    /// `None` if the number of pixels doesn't match the size or the size is zero.
+    pub fn new(size: UVec2, pixels: Vec<u8>, color_space: ColorSpace) -> Option<Self> {
+        Self::from_levels(size, vec![pixels], color_space)
+    }
+
+    /// Returns the image with the pixels of each mip level, or `None` if the number of pixels of a
+    /// level doesn't match its size, the size is zero, or there are more levels than halvings of
+    /// the size down to 1x1.
+    fn from_levels(size: UVec2, mut levels: Vec<Vec<u8>>, color_space: ColorSpace) -> Option<Self> {
+        let valid = !levels.is_empty()
+            && levels.len() as u32 <= mip_level_count(size)
+            && levels.iter().enumerate().all(|(level, pixels)| {
+                let size = mip_size(size, level as u32);
+                let len = u64::from(size.x) * u64::from(size.y) * 4;
+                len > 0 && pixels.len() as u64 == len
+            });
+        valid.then(|| {
+            let pixels = levels.remove(0);
+            Self {
+                data: Arc::new(ImageData {
+                    size,
+                    pixels,
+                    mips: levels,
+                    color_space,
+                    sampler: None,
+                    blocks: None,
+                }),
+            }
+        })
+    }
+
+    /// Decodes the image from the bytes of a PNG or JPEG file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file isn't a valid PNG or JPEG file.
+    pub fn decode(bytes: &[u8], color_space: ColorSpace) -> Result<Self, ImageError> {
+        let decoded = image::load_from_memory(bytes)
+            .map_err(ImageError::Decode)?
+            .to_rgba8();
+        let size = UVec2::new(decoded.width(), decoded.height());
+        Self::new(size, decoded.into_raw(), color_space).ok_or(ImageError::InvalidData)
+    }
+
+    /// Decodes the image and its mip levels from the bytes of a KTX2 file with 8-bit RGB or RGBA
+    /// pixels, or with blocks of a [BlockFormat] of which the pixels are decoded, uncompressed or
+    /// Zstandard supercompressed. The color space is the one of the file's format.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file isn't a valid KTX2 file, it has Basis Universal pixels, which
+    /// aren't transcoded, or it has a different format, e.g. BC7 blocks, or more than a single 2D
+    /// image.
+    pub fn decode_ktx2(bytes: &[u8]) -> Result<Self, ImageError> {
+        let reader = ktx2::Reader::new(bytes).map_err(ImageError::Ktx2)?;
+        let header = reader.header();
+        // Basis Universal files have no format, as their pixels are transcoded to one when loaded.
+        if header.format.is_none()
+            || header.supercompression_scheme == Some(SupercompressionScheme::BasisLZ)
+        {
+            return Err(ImageError::BasisUniversal);
+        }
+        // Formats either have 8-bit channels or blocks, with the channels of block formats unused.
+        let (channels, block_format, color_space) = match header.format {
+            Some(Format::R8G8B8A8_SRGB) => (4, None, ColorSpace::Srgb),
+            Some(Format::R8G8B8A8_UNORM) => (4, None, ColorSpace::Linear),
+            Some(Format::R8G8B8_SRGB) => (3, None, ColorSpace::Srgb),
+            Some(Format::R8G8B8_UNORM) => (3, None, ColorSpace::Linear),
+            Some(Format::BC1_RGBA_SRGB_BLOCK) => (0, Some(BlockFormat::Bc1), ColorSpace::Srgb),
+            Some(Format::BC1_RGBA_UNORM_BLOCK) => (0, Some(BlockFormat::Bc1), ColorSpace::Linear),
+            Some(Format::BC3_SRGB_BLOCK) => (0, Some(BlockFormat::Bc3), ColorSpace::Srgb),
+            Some(Format::BC3_UNORM_BLOCK) => (0, Some(BlockFormat::Bc3), ColorSpace::Linear),
+            Some(Format::BC4_UNORM_BLOCK) => (0, Some(BlockFormat::Bc4), ColorSpace::Linear),
+            Some(Format::BC5_UNORM_BLOCK) => (0, Some(BlockFormat::Bc5), ColorSpace::Linear),
+            _ => return Err(ImageError::UnsupportedFormat),
+        };
+        if header.pixel_depth > 1 || header.layer_count > 1 || header.face_count != 1 {
+            return Err(ImageError::UnsupportedFormat);
+        }
+
+        let size = UVec2::new(header.pixel_width, header.pixel_height.max(1));
+        let levels = reader
+            .levels()
+            .enumerate()
+            .map(|(level, data)| {
+                let data = match header.supercompression_scheme {
+                    None => data.data.to_vec(),
+                    Some(SupercompressionScheme::Zstandard) => {
+                        let mut decompressed = Vec::new();
+                        StreamingDecoder::new(data.data)
+                            .map_err(|_| ImageError::InvalidData)?
+                            .read_to_end(&mut decompressed)
+                            .map_err(|_| ImageError::InvalidData)?;
+                        decompressed
+                    }
+                    Some(_) => return Err(ImageError::UnsupportedFormat),
+                };
+                if block_format.is_some() {
+                    return Ok(data);
+                }
+
+                let level_size = mip_size(size, level as u32);
+                let len = level_size.x as usize * level_size.y as usize * channels;
+                let pixels = data.get(..len).ok_or(ImageError::InvalidData)?;
+                Ok(if channels == 3 {
+                    pixels
+                        .chunks_exact(3)
+                        .flat_map(|rgb| [rgb[0], rgb[1], rgb[2], 255])
+                        .collect()
+                } else {
+                    pixels.to_vec()
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        match block_format {
+            Some(format) => Self::from_blocks(size, format, levels, color_space),
+            None => Self::from_levels(size, levels, color_space),
+        }
+        .ok_or(ImageError::InvalidData)
+    }
+
+    /// Returns a 1x1 image of the color.
+    pub fn from_pixel(pixel: [u8; 4], color_space: ColorSpace) -> Self {
+        Self::new(UVec2::ONE, pixel.to_vec(), color_space).unwrap()
+    }
+
+    /// Returns an sRGB image of the size for cameras to render to with a
+    /// [crate::render::target::RenderTarget], or `None` if the size is zero. Its pixels only exist
+    /// on the GPU, so [Image::pixels] is empty.
+    pub fn render_target(size: UVec2) -> Option<Self> {
+        (size.x > 0 && size.y > 0).then(|| Self {
+            data: Arc::new(ImageData {
+                size,
+                pixels: Vec::new(),
+                mips: Vec::new(),
+                color_space: ColorSpace::Srgb,
+                sampler: None,
+                blocks: None,
+            }),
+        })
+    }
+
+    /// Returns the size of the image in pixels.
+    pub fn size(&self) -> UVec2 {
+        self.data.size
+    }
+
+    /// Returns the pixels in 8-bit RGBA row by row from the top-left corner.
+    pub fn pixels(&self) -> &[u8] {
+        &self.data.pixels
+    }
+
+    /// Returns the pixel at the coordinates from the top-left corner.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the coordinates are outside of the image or the image is a render target.
+    pub fn pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        assert!(x < self.data.size.x && y < self.data.size.y);
+        let index = (y as usize * self.data.size.x as usize + x as usize) * 4;
+        self.data.pixels[index..index + 4].try_into().unwrap()
+    }
+
+    /// Returns the color space of the image's color channels.
+    pub fn color_space(&self) -> ColorSpace {
+        self.data.color_space
+    }
+
+    /// Returns the number of mip levels, including the full size image.
+    pub fn mip_level_count(&self) -> u32 {
+        1 + self.data.mips.len() as u32
+    }
+
+    /// Returns the pixels of the mip level in 8-bit RGBA, with level 0 being the full size image,
+    /// or `None` if the image doesn't have the level.
+    pub fn mip_pixels(&self, level: u32) -> Option<&[u8]> {
+        match level {
+            0 => Some(&self.data.pixels),
+            _ => self.data.mips.get(level as usize - 1).map(Vec::as_slice),
+        }
+    }
+
+    /// Returns the image with mip levels down to 1x1, each averaging 2x2 pixels of the previous
+    /// level. The colors of sRGB images are averaged in linear space. Render targets are returned
+    /// unchanged.
+    pub fn with_mipmaps(self) -> Self {
+        if self.is_render_target() {
+            return self;
+        }
+
+        let mut data = Arc::unwrap_or_clone(self.data);
+        data.mips.clear();
+        if let Some(blocks) = &mut data.blocks {
+            blocks.levels.truncate(1);
+        }
+        for level in 1..mip_level_count(data.size) {
+            let previous = data.mips.last().unwrap_or(&data.pixels);
+            let mip = downsample(mip_size(data.size, level - 1), previous, data.color_space);
+            if let Some(blocks) = &mut data.blocks {
+                let encoded = bc::encode(blocks.format, mip_size(data.size, level), &mip);
+                blocks.levels.push(encoded);
+            }
+            data.mips.push(mip);
+        }
+        Self {
+            data: Arc::new(data),
+        }
+    }
+
+    /// Returns the image with its mip levels compressed to the block format, which devices that
+    /// support the format sample instead of the 8-bit RGBA pixels. The pixels are replaced by the
+    /// decoded blocks, so the image looks the same on all devices. Compressing takes a while, so
+    /// the [ImageLoader] caches the compressed images of its [ImageSettings::compression].
+    /// Render targets are returned unchanged.
+    pub fn compressed(self, format: BlockFormat) -> Self {
+        if self.is_render_target() {
+            return self;
+        }
+
+        let levels = (0..self.mip_level_count())
+            .map(|level| {
+                let size = mip_size(self.size(), level);
+                bc::encode(format, size, self.mip_pixels(level).unwrap())
+            })
+            .collect();
+        let image = Self::from_blocks(self.size(), format, levels, self.color_space()).unwrap();
+        match self.sampler() {
+            Some(sampler) => image.with_sampler(sampler),
+            None => image,
+        }
+    }
+
+    /// Returns the image with the blocks of each mip level and the pixels decoded from them, or
+    /// `None` if a level has fewer blocks than its size needs, the size is zero, or there are more
+    /// levels than halvings of the size down to 1x1.
+    fn from_blocks(
+        size: UVec2,
+        format: BlockFormat,
+        mut levels: Vec<Vec<u8>>,
+        color_space: ColorSpace,
+    ) -> Option<Self> {
+        let pixels = levels
+            .iter_mut()
+            .enumerate()
+            .map(|(level, blocks)| {
+                let size = mip_size(size, level as u32);
+                blocks.truncate(bc::len(format, size));
+                bc::decode(format, size, blocks)
+            })
+            .collect::<Option<_>>()?;
+        let mut image = Self::from_levels(size, pixels, color_space)?;
+        Arc::get_mut(&mut image.data).unwrap().blocks = Some(Blocks { format, levels });
+        Some(image)
+    }
+
+    /// Returns the block format the image is compressed to, if it's compressed.
+    pub fn block_format(&self) -> Option<BlockFormat> {
+        self.data.blocks.as_ref().map(|blocks| blocks.format)
+    }
+
+    /// Returns the image sampled with the sampler, instead of the default sampler of the material
+    /// or sprite drawing it.
+    pub fn with_sampler(self, sampler: ImageSampler) -> Self {
+        let mut data = Arc::unwrap_or_clone(self.data);
+        data.sampler = Some(sampler);
+        Self {
+            data: Arc::new(data),
+        }
+    }
+
+    /// Returns the sampler of the image, if it has one.
+    pub fn sampler(&self) -> Option<ImageSampler> {
+        self.data.sampler
+    }
+
+    /// Returns true if the image was created with [Image::render_target].
+    pub fn is_render_target(&self) -> bool {
+        self.data.pixels.is_empty()
+    }
+
+    /// Encodes the image as an uncompressed PNG file, e.g. to save a
+    /// [crate::render::capture::FrameCapture].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the image is a render target.
+    pub fn to_png(&self) -> Vec<u8> {
+        assert!(!self.is_render_target());
+        let size = self.data.size;
+        let mut header = Vec::with_capacity(13);
+        header.extend(size.x.to_be_bytes());
+        header.extend(size.y.to_be_bytes());
+        // 8-bit RGBA without interlacing.
+        header.extend([8, 6, 0, 0, 0]);
+
+        let mut scanlines = Vec::with_capacity(self.data.pixels.len() + size.y as usize);
+        for row in self.data.pixels.chunks_exact(size.x as usize * 4) {
+            // No filter.
+            scanlines.push(0);
+            scanlines.extend_from_slice(row);
+        }
+
+        let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+        png_chunk(&mut png, b"IHDR", &header);
+        if self.data.color_space == ColorSpace::Srgb {
+            // Perceptual rendering intent.
+            png_chunk(&mut png, b"sRGB", &[0]);
+        }
+        png_chunk(&mut png, b"IDAT", &zlib_stored(&scanlines));
+        png_chunk(&mut png, b"IEND", &[]);
+        png
+    }
+
+    /// Returns an identifier for the image's pixels, valid while the image exists.
+    pub(crate) fn id(&self) -> usize {
+        Arc::as_ptr(&self.data) as usize
+    }
+
+    /// Returns a weak reference to the image's pixels, to check whether the image still exists.
+    pub(crate) fn downgrade(&self) -> Weak<ImageData> {
+        Arc::downgrade(&self.data)
+    }
+
+    /// Returns the image with its mip levels and sampler processed for the asset cache.
+    pub(crate) fn to_processed(&self) -> Vec<u8> {
+        let mut writer = ProcessedWriter::new();
+        let metadata = (
+            self.data.color_space,
+            self.data.sampler,
+            self.block_format(),
+        );
+        writer.write_slice(ron::to_string(&metadata).unwrap().as_bytes());
+        writer.write(self.data.size);
+        writer.write(self.mip_level_count());
+        for level in 0..self.mip_level_count() {
+            writer.write_slice(self.mip_pixels(level).unwrap());
+        }
+        for blocks in self.data.blocks.iter().flat_map(|blocks| &blocks.levels) {
+            writer.write_slice(blocks);
+        }
+        writer.into_bytes()
+    }
+
+    /// Returns the image processed by [Image::to_processed], or `None` if the bytes are invalid.
+    pub(crate) fn from_processed(bytes: &[u8]) -> Option<Self> {
+        let mut reader = ProcessedReader::new(bytes);
+        let (color_space, sampler, format): (_, _, Option<BlockFormat>) =
+            ron::de::from_bytes(&reader.read_slice::<u8>()?).ok()?;
+        let size = reader.read()?;
+        let level_count = reader.read::<u32>()?;
+        let levels = (0..level_count)
+            .map(|_| reader.read_slice())
+            .collect::<Option<_>>()?;
+        let mut image = Self::from_levels(size, levels, color_space)?;
+        if let Some(format) = format {
+            let levels = (0..level_count)
+                .map(|level| {
+                    let blocks = reader.read_slice::<u8>()?;
+                    let len = bc::len(format, mip_size(size, level));
+                    (blocks.len() == len).then_some(blocks)
+                })
+                .collect::<Option<_>>()?;
+            Arc::get_mut(&mut image.data).unwrap().blocks = Some(Blocks { format, levels });
+        }
+        Some(match sampler {
+            Some(sampler) => image.with_sampler(sampler),
+            None => image,
+        })
+    }
+}
+
+impl PartialEq for Image {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.data, &other.data)
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct HdrImageData {
+    size: UVec2,
+    pixels: Vec<[f32; 3]>,
+}
+
+/// # HDR Image
+///
+/// High dynamic range image with linear RGB pixels, e.g. an equirectangular environment lighting
+/// the scene. Like [Image], the pixels are shared between clones of the image and images are
+/// equal if they share the same pixels.
+///
+/// ```
+/// # use glam::UVec2;
+/// # use pulse::render::image::HdrImage;
+/// let sky = HdrImage::new(UVec2::new(1, 2), vec![[0.5, 0.7, 1.0], [0.2, 0.2, 0.2]]).unwrap();
+///
+/// assert_eq!(sky.pixel(0, 1), [0.2, 0.2, 0.2]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct HdrImage {
+    data: Arc<HdrImageData>,
+}
+
+impl HdrImage {
+    /// Returns the image with the linear RGB pixels row by row from the top-left corner, or `None`
+    /// if the number of pixels doesn't match the size or the size is zero.
+    pub fn new(size: UVec2, pixels: Vec<[f32; 3]>) -> Option<Self> {
+        let len = u64::from(size.x) * u64::from(size.y);
+        (len > 0 && pixels.len() as u64 == len).then(|| Self {
+            data: Arc::new(HdrImageData { size, pixels }),
+        })
+    }
+
+    /// Returns a 1x1 image of the linear RGB color.
+    pub fn from_pixel(pixel: [f32; 3]) -> Self {
+        Self::new(UVec2::ONE, vec![pixel]).unwrap()
+    }
+
+    /// Decodes the image from the bytes of a Radiance RGBE file, usually with the `.hdr`
+    /// extension.
+    pub fn decode(bytes: &[u8]) -> Result<Self, HdrError> {
+        let (size, mut data) = decode_header(bytes)?;
+        let mut pixels = Vec::with_capacity(size.x as usize * size.y as usize);
+        let mut scanline = vec![[0; 4]; size.x as usize];
+        for _ in 0..size.y {
+            data = decode_scanline(data, &mut scanline)?;
+            pixels.extend(scanline.iter().map(|rgbe| rgbe_to_rgb(*rgbe)));
+        }
+
+        Ok(Self::new(size, pixels).unwrap())
+    }
+
+    /// Decodes the image from the bytes of an OpenEXR file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file isn't a valid OpenEXR file.
+    pub fn decode_exr(bytes: &[u8]) -> Result<Self, ImageError> {
+        let decoded = image::load_from_memory_with_format(bytes, image::ImageFormat::OpenExr)
+            .map_err(ImageError::Decode)?
+            .to_rgb32f();
+        let size = UVec2::new(decoded.width(), decoded.height());
+        let pixels = decoded
+            .into_raw()
+            .chunks_exact(3)
+            .map(|rgb| [rgb[0], rgb[1], rgb[2]])
+            .collect();
+        Self::new(size, pixels).ok_or(ImageError::InvalidData)
+    }
+
+    /// Returns the size of the image in pixels.
+    pub fn size(&self) -> UVec2 {
+        self.data.size
+    }
+
+    /// Returns the linear RGB pixels row by row from the top-left corner.
+    pub fn pixels(&self) -> &[[f32; 3]] {
+        &self.data.pixels
+    }
+
+    /// Returns the pixel at the coordinates from the top-left corner.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the coordinates are outside of the image.
+    pub fn pixel(&self, x: u32, y: u32) -> [f32; 3] {
+        assert!(x < self.data.size.x && y < self.data.size.y);
+        self.data.pixels[y as usize * self.data.size.x as usize + x as usize]
+    }
+
+    /// Returns an identifier for the image's pixels, valid while the image exists.
+    pub(crate) fn id(&self) -> usize {
+        Arc::as_ptr(&self.data) as usize
+    }
+
+    /// Returns a weak reference to the image's pixels, to check whether the image still exists.
+    pub(crate) fn downgrade(&self) -> Weak<HdrImageData> {
+        Arc::downgrade(&self.data)
+    }
+
+    /// Returns the image's pixels processed for the asset cache.
+    pub(crate) fn to_processed(&self) -> Vec<u8> {
+        let mut writer = ProcessedWriter::new();
+        writer.write(self.size());
+        writer.write_slice(self.pixels());
+        writer.into_bytes()
+    }
+
+    /// Returns the image processed by [HdrImage::to_processed], or `None` if the bytes are
+    /// invalid.
+    pub(crate) fn from_processed(bytes: &[u8]) -> Option<Self> {
+        let mut reader = ProcessedReader::new(bytes);
+        Self::new(reader.read()?, reader.read_slice()?)
+    }
+}
+
+impl PartialEq for HdrImage {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.data, &other.data)
+    }
+}
+
+/// # HDR Cubemap
+///
+/// Six square [HdrImage] faces of the same size, each seen from the center of a cube. Like
+/// [HdrImage], the faces are shared between clones of the cubemap and cubemaps are equal if they
+/// share the same faces.
+///
+/// ```
+/// # use pulse::render::image::HdrCubemap;
+/// # use pulse::render::image::HdrImage;
+/// let faces = [0.1, 0.2, 1.0, 0.0, 0.3, 0.4].map(|value| HdrImage::from_pixel([value; 3]));
+/// let sky = HdrCubemap::new(faces).unwrap();
+///
+/// assert_eq!(sky.faces()[2].pixel(0, 0), [1.0; 3]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct HdrCubemap {
+    faces: Arc<[HdrImage; 6]>,
+}
+
+impl HdrCubemap {
+    /// Returns the cubemap with the faces in the order +X, -X, +Y, -Y, +Z, and -Z, or `None` if
+    /// the faces aren't squares of the same size. Faces are seen from the inside with +Y up, and
+    /// +Y and -Y with -Z and +Z up respectively.
+    pub fn new(faces: [HdrImage; 6]) -> Option<Self> {
+        let size = faces[0].size();
+        (size.x == size.y && faces.iter().all(|face| face.size() == size)).then(|| Self {
+            faces: Arc::new(faces),
+        })
+    }
+
+    /// Returns the faces in the order +X, -X, +Y, -Y, +Z, and -Z.
+    pub fn faces(&self) -> &[HdrImage; 6] {
+        &self.faces
+    }
+
+    /// Returns the width and height of the faces in pixels.
+    pub fn size(&self) -> u32 {
+        self.faces[0].size().x
+    }
+
+    /// Returns an identifier for the cubemap's faces, valid while the cubemap exists.
+    pub(crate) fn id(&self) -> usize {
+        Arc::as_ptr(&self.faces) as usize
+    }
+
+    /// Returns a weak reference to the cubemap's faces, to check whether the cubemap still exists.
+    pub(crate) fn downgrade(&self) -> Weak<[HdrImage; 6]> {
+        Arc::downgrade(&self.faces)
+    }
+}
+
+impl PartialEq for HdrCubemap {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.faces, &other.faces)
+    }
+}
+
+/// # HDR Image Loader
+///
+/// [AssetLoader] of [HdrImage]s from Radiance RGBE and OpenEXR files.
+pub struct HdrImageLoader;
+
+impl AssetLoader for HdrImageLoader {
+    type Asset = HdrImage;
+
+    fn extensions(&self) -> &[&str] {
+        &["hdr", "exr"]
+    }
+
+    fn load(
+        &self,
+        context: &LoadContext,
+    ) -> Result<HdrImage, Box<dyn std::error::Error + Send + Sync>> {
+        let exr = context
+            .path()
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("exr"));
+        if exr {
+            Ok(HdrImage::decode_exr(context.bytes())?)
+        } else {
+            Ok(HdrImage::decode(context.bytes())?)
+        }
+    }
+
+    fn process(&self, image: &HdrImage) -> Option<Vec<u8>> {
+        Some(image.to_processed())
+    }
+
+    fn load_processed(
+        &self,
+        bytes: &[u8],
+    ) -> Result<HdrImage, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(HdrImage::from_processed(bytes).ok_or(HdrError::InvalidData)?)
+    }
+}
+
+/// # Image Settings
+///
+/// How the [ImageLoader] loads an image. The settings of an image are read from a RON file next
+/// to it, named after the image with an added `.ron` extension, e.g. `bricks_normal.png.ron`
+/// containing `(color_space: Linear)`. Missing fields keep their default.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ImageSettings {
+    /// Color space of PNG and JPEG images. KTX2 images use the color space of their format.
+    pub color_space: ColorSpace,
+    /// Sampler of the image, or none for the default sampler of the material or sprite drawing
+    /// it.
+    pub sampler: Option<ImageSampler>,
+    /// Whether mip levels are generated for images without them.
+    pub mipmaps: bool,
+    /// Block format PNG and JPEG images are compressed to, or none to keep them uncompressed.
+    /// KTX2 images keep the format of their file.
+    pub compression: Option<BlockFormat>,
+}
+
+impl Default for ImageSettings {
+    fn default() -> Self {
+        Self {
+            color_space: ColorSpace::Srgb,
+            sampler: None,
+            mipmaps: true,
+            compression: None,
+        }
+    }
+}
+
+/// # Image Loader
+///
+/// [AssetLoader] of [Image]s from PNG, JPEG, and KTX2 files, with the [ImageSettings] next to
+/// them or else the loader's settings.
+#[derive(Clone, Debug, Default)]
+pub struct ImageLoader {
+    /// Settings of images without a settings file.
+    pub settings: ImageSettings,
+}
+
+impl AssetLoader for ImageLoader {
+    type Asset = Image;
+
+    fn extensions(&self) -> &[&str] {
+        &["png", "jpg", "jpeg", "ktx2"]
+    }
+
+    fn load(
+        &self,
+        context: &LoadContext,
+    ) -> Result<Image, Box<dyn std::error::Error + Send + Sync>> {
+        let name = context
+            .path()
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let settings = match context.read(format!("{name}.ron")) {
+            Ok(bytes) => ron::de::from_bytes(&bytes)?,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => self.settings,
+            Err(error) => return Err(error.into()),
+        };
+
+        let ktx2 = name.to_lowercase().ends_with(".ktx2");
+        let mut image = if ktx2 {
+            Image::decode_ktx2(context.bytes())?
+        } else {
+            Image::decode(context.bytes(), settings.color_space)?
+        };
+        if settings.mipmaps && image.mip_level_count() == 1 {
+            image = image.with_mipmaps();
+        }
+        if let Some(format) = settings.compression.filter(|_| !ktx2) {
+            image = image.compressed(format);
+        }
+        if let Some(sampler) = settings.sampler {
+            image = image.with_sampler(sampler);
+        }
+        Ok(image)
+    }
+
+    fn process(&self, image: &Image) -> Option<Vec<u8>> {
+        Some(image.to_processed())
+    }
+
+    fn load_processed(
+        &self,
+        bytes: &[u8],
+    ) -> Result<Image, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(Image::from_processed(bytes).ok_or(ImageError::InvalidData)?)
+    }
+
+    fn settings(&self) -> Vec<u8> {
+        ron::to_string(&self.settings).unwrap().into_bytes()
+    }
+}
+
+/// # Image Error
+///
+/// Error returned when an [Image] or an OpenEXR [HdrImage] can't be decoded.
+#[derive(Debug)]
+pub enum ImageError {
+    /// The file isn't a valid PNG, JPEG, or OpenEXR file.
+    Decode(image::ImageError),
+    /// The file isn't a valid KTX2 file.
+    Ktx2(ktx2::ParseError),
+    /// The KTX2 file's format or supercompression isn't supported, or it has more than a single
+    /// 2D image.
+    UnsupportedFormat,
+    /// The KTX2 file has Basis Universal pixels, which would have to be transcoded to a format the
+    /// GPU supports.
+    BasisUniversal,
+    /// The file has fewer pixels than its size, or its size is zero.
+    InvalidData,
+}
+
+impl fmt::Display for ImageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Decode(error) => write!(f, "invalid image: {error}"),
+            Self::Ktx2(error) => write!(f, "invalid KTX2 file: {error}"),
+            Self::UnsupportedFormat => write!(f, "unsupported KTX2 format"),
+            Self::BasisUniversal => write!(f, "KTX2 Basis Universal pixels aren't supported"),
+            Self::InvalidData => write!(f, "image is missing pixels"),
+        }
+    }
+}
+
+impl std::error::Error for ImageError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Decode(error) => Some(error),
+            Self::Ktx2(error) => Some(error),
+            Self::UnsupportedFormat | Self::BasisUniversal | Self::InvalidData => None,
+        }
+    }
+}
+
+/// # HDR Error
+///
+/// Error returned when an [HdrImage] can't be decoded.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum HdrError {
+    /// The file doesn't start with a Radiance header or its resolution line is invalid.
+    InvalidHeader,
+    /// The pixels aren't in the RGBE format or the scanlines aren't ordered from the top-left
+    /// corner.
+    UnsupportedFormat,
+    /// The file ends before all pixels were decoded or a run exceeds its scanline.
+    InvalidData,
+}
+
+impl fmt::Display for HdrError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidHeader => write!(f, "invalid radiance header"),
+            Self::UnsupportedFormat => write!(f, "unsupported radiance pixel format"),
+            Self::InvalidData => write!(f, "invalid or truncated radiance pixel data"),
+        }
+    }
+}
+
+impl std::error::Error for HdrError {}
+
+/// Returns the size of the image and the data after the header.
+fn decode_header(bytes: &[u8]) -> Result<(UVec2, &[u8]), HdrError> {
+    let mut lines = bytes.split(|byte| *byte == b'\n');
+    let magic = lines.next().ok_or(HdrError::InvalidHeader)?;
+    if !magic.starts_with(b"#?") {
+        return Err(HdrError::InvalidHeader);
+    }
+
+    let mut offset = magic.len() + 1;
+    for line in lines.by_ref() {
+        offset += line.len() + 1;
+        if line.is_empty() {
+            break;
+        }
+        if let Some(format) = line.strip_prefix(b"FORMAT=") {
+            if format != b"32-bit_rle_rgbe" {
+                return Err(HdrError::UnsupportedFormat);
+            }
+        }
+    }
+
+    let resolution = lines.next().ok_or(HdrError::InvalidHeader)?;
+    offset += resolution.len() + 1;
+    let resolution = std::str::from_utf8(resolution).map_err(|_| HdrError::InvalidHeader)?;
+    let size = match resolution.split_whitespace().collect::<Vec<_>>()[..] {
+        ["-Y", height, "+X", width] => UVec2::new(
+            width.parse().map_err(|_| HdrError::InvalidHeader)?,
+            height.parse().map_err(|_| HdrError::InvalidHeader)?,
+        ),
+        [_, _, _, _] => return Err(HdrError::UnsupportedFormat),
+        _ => return Err(HdrError::InvalidHeader),
+    };
+    if size.x == 0 || size.y == 0 {
+        return Err(HdrError::InvalidHeader);
+    }
+
+    Ok((size, bytes.get(offset..).ok_or(HdrError::InvalidData)?))
+}
+
+/// Decodes a scanline of RGBE pixels, either flat or run-length encoded per channel, and returns
+/// the remaining data.
+fn decode_scanline<'a>(data: &'a [u8], scanline: &mut [[u8; 4]]) -> Result<&'a [u8], HdrError> {
+    let width = scanline.len();
+    let encoded = (8..0x8000).contains(&width)
+        && data.len() >= 4
+        && data[0] == 2
+        && data[1] == 2
+        && usize::from(data[2]) << 8 | usize::from(data[3]) == width;
+    if !encoded {
+        let (flat, rest) = data
+            .split_at_checked(width * 4)
+            .ok_or(HdrError::InvalidData)?;
+        for (pixel, rgbe) in scanline.iter_mut().zip(flat.chunks_exact(4)) {
+            pixel.copy_from_slice(rgbe);
+        }
+        return Ok(rest);
+    }
+
+    let mut data = &data[4..];
+    for channel in 0..4 {
+        let mut x = 0;
+        while x < width {
+            let (&count, rest) = data.split_first().ok_or(HdrError::InvalidData)?;
+            if count == 0 {
+                return Err(HdrError::InvalidData);
+            }
+
+            if count > 128 {
+                let run = usize::from(count - 128);
+                let (&value, rest) = rest.split_first().ok_or(HdrError::InvalidData)?;
+                let pixels = scanline.get_mut(x..x + run).ok_or(HdrError::InvalidData)?;
+                for pixel in pixels {
+                    pixel[channel] = value;
+                }
+                x += run;
+                data = rest;
+            } else {
+                let run = usize::from(count);
+                let (values, rest) = rest.split_at_checked(run).ok_or(HdrError::InvalidData)?;
+                let pixels = scanline.get_mut(x..x + run).ok_or(HdrError::InvalidData)?;
+                for (pixel, value) in pixels.iter_mut().zip(values) {
+                    pixel[channel] = *value;
+                }
+                x += run;
+                data = rest;
+            }
+        }
+    }
+
+    Ok(data)
+}
+
+fn rgbe_to_rgb([r, g, b, e]: [u8; 4]) -> [f32; 3] {
+    if e == 0 {
+        return [0.0; 3];
+    }
+
+    let scale = 2f32.powi(i32::from(e) - (128 + 8));
+    [r, g, b].map(|component| f32::from(component) * scale)
+}
+
+/// Appends the PNG chunk of the type with the data and its checksum.
+fn png_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend((data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend(kind);
+    png.extend(data);
+    let crc = crc32(&png[start..]);
+    png.extend(crc.to_be_bytes());
+}
+
+/// Returns the zlib stream of the data in uncompressed deflate blocks.
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut stream = vec![0x78, 0x01];
+    let mut blocks = data.chunks(usize::from(u16::MAX)).peekable();
+    if blocks.peek().is_none() {
+        stream.extend([1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        let len = block.len() as u16;
+        stream.push(u8::from(blocks.peek().is_none()));
+        stream.extend(len.to_le_bytes());
+        stream.extend((!len).to_le_bytes());
+        stream.extend(block);
+    }
+    stream.extend(adler32(data).to_be_bytes());
+    stream
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in bytes {
+        crc ^= u32::from(*byte);
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+fn adler32(bytes: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for byte in bytes {
+        a = (a + u32::from(*byte)) % 65521;
+        b = (b + a) % 65521;
+    }
+    b << 16 | a
+}
+
+/// Returns the number of mip levels of an image of the size, halving it down to 1x1.
+fn mip_level_count(size: UVec2) -> u32 {
+    size.max_element().max(1).ilog2() + 1
+}
+
+/// Returns the size of the mip level of an image of the size.
+fn mip_size(size: UVec2, level: u32) -> UVec2 {
+    (size >> level).max(UVec2::ONE)
+}
+
+/// Returns the next mip level of the pixels of the size, each pixel averaging 2x2 pixels. Pixels
+/// at the odd edges of the image are counted twice.
+fn downsample(size: UVec2, pixels: &[u8], color_space: ColorSpace) -> Vec<u8> {
+    let linear: [f32; 256] = std::array::from_fn(|value| match color_space {
+        ColorSpace::Srgb => srgb_to_linear(value as f32 / 255.0),
+        ColorSpace::Linear => value as f32 / 255.0,
+    });
+    let next = mip_size(size, 1);
+    let mut mip = Vec::with_capacity(next.x as usize * next.y as usize * 4);
+    for y in 0..next.y {
+        for x in 0..next.x {
+            let offsets = [(0, 0), (1, 0), (0, 1), (1, 1)].map(|(dx, dy)| {
+                let (x, y) = ((x * 2 + dx).min(size.x - 1), (y * 2 + dy).min(size.y - 1));
+                (y as usize * size.x as usize + x as usize) * 4
+            });
+            for channel in 0..4 {
+                let sum = offsets
+                    .iter()
+                    .map(|offset| pixels[offset + channel])
+                    .map(|value| match channel {
+                        3 => f32::from(value) / 255.0,
+                        _ => linear[value as usize],
+                    })
+                    .sum::<f32>();
+                let average = match (channel, color_space) {
+                    (0..=2, ColorSpace::Srgb) => linear_to_srgb(sum / 4.0),
+                    _ => sum / 4.0,
+                };
+                mip.push((average * 255.0).round() as u8);
+            }
+        }
+    }
+    mip
+}
+
+fn linear_to_srgb(component: f32) -> f32 {
+    if component <= 0.003_130_8 {
+        component * 12.92
+    } else {
+        1.055 * component.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// GPU texture of an image and a view of it.
+type GpuImage = (Weak<ImageData>, wgpu::Texture, wgpu::TextureView);
+
+/// GPU textures of the images used so far, keyed by the image's pixels, so images shared between
+/// materials are uploaded once.
+#[derive(Default)]
+pub(crate) struct GpuImages {
+    images: HashMap<usize, GpuImage>,
+    samplers: HashMap<ImageSampler, wgpu::Sampler>,
+}
+
+impl GpuImages {
+    /// Uploads the image if it wasn't uploaded yet.
+    pub(crate) fn upload(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, image: &Image) {
+        self.images
+            .entry(image.id())
+            .and_modify(|gpu_image| {
+                // The id of a dropped image may have been reused by a new one.
+                if gpu_image.0.strong_count() == 0 {
+                    *gpu_image = create_texture(device, queue, image);
+                }
+            })
+            .or_insert_with(|| create_texture(device, queue, image));
+        if let Some(sampler) = image.sampler() {
+            self.samplers
+                .entry(sampler)
+                .or_insert_with(|| device.create_sampler(&sampler.descriptor()));
+        }
+    }
+
+    /// Returns the texture of the image if it was uploaded.
+    pub(crate) fn get(&self, image: &Image) -> Option<&wgpu::TextureView> {
+        self.images.get(&image.id()).map(|(_, _, view)| view)
+    }
+
+    /// Returns the sampler of the image if it has one and was uploaded.
+    pub(crate) fn sampler(&self, image: &Image) -> Option<&wgpu::Sampler> {
+        self.samplers.get(&image.sampler()?)
+    }
+
+    /// Returns a new view of the image's texture to render to if it was uploaded.
+    pub(crate) fn target_view(&self, image: &Image) -> Option<wgpu::TextureView> {
+        self.images
+            .get(&image.id())
+            .map(|(_, texture, _)| texture.create_view(&wgpu::TextureViewDescriptor::default()))
+    }
+
+    /// Drops the textures of images that no longer exist.
+    pub(crate) fn collect_garbage(&mut self) {
+        self.images
+            .retain(|_, (data, _, _)| data.strong_count() > 0);
+    }
+}
+
+/// Creates the image's texture, which render targets can be rendered to and copied from instead of
+/// having their pixels uploaded.
+fn create_texture(device: &wgpu::Device, queue: &wgpu::Queue, image: &Image) -> GpuImage {
+    let size = wgpu::Extent3d {
+        width: image.size().x,
+        height: image.size().y,
+        depth_or_array_layers: 1,
+    };
+    // Devices only create block compressed textures whose size is a multiple of the block size.
+    let blocks = image.data.blocks.as_ref().filter(|_| {
+        device.features().contains(COMPRESSION_FEATURES) && image.size() % 4 == UVec2::ZERO
+    });
+    let color_space = match image.block_format() {
+        Some(format) if format.is_linear() => ColorSpace::Linear,
+        _ => image.color_space(),
+    };
+    let format = match (blocks, color_space) {
+        (Some(blocks), _) => blocks.format.to_wgpu(color_space),
+        (None, ColorSpace::Srgb) => wgpu::TextureFormat::Rgba8UnormSrgb,
+        (None, ColorSpace::Linear) => wgpu::TextureFormat::Rgba8Unorm,
+    };
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("image"),
+        size,
+        mip_level_count: image.mip_level_count(),
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        usage: if image.is_render_target() {
+            wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::RENDER_ATTACHMENT
+                | wgpu::TextureUsages::COPY_SRC
+        } else {
+            wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST
+        },
+        view_formats: &[],
+    });
+    let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+    if image.is_render_target() {
+        return (Arc::downgrade(&image.data), texture, view);
+    }
+
+    for level in 0..image.mip_level_count() {
+        let mip_size = mip_size(image.size(), level);
+        // Blocks are copied whole, even at the edges of levels smaller than a block.
+        let (data, bytes_per_row, copy_size) = match blocks {
+            Some(blocks) => (
+                blocks.levels[level as usize].as_slice(),
+                mip_size.x.div_ceil(4) * blocks.format.block_len() as u32,
+                (mip_size + 3) / 4 * 4,
+            ),
+            None => (image.mip_pixels(level).unwrap(), mip_size.x * 4, mip_size),
+        };
+        queue.write_texture(
+            wgpu::ImageCopyTexture {
+                mip_level: level,
+                ..texture.as_image_copy()
+            },
+            data,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(bytes_per_row),
+                rows_per_image: None,
+            },
+            wgpu::Extent3d {
+                width: copy_size.x,
+                height: copy_size.y,
+                depth_or_array_layers: 1,
+            },
+        );
+    }
+
+    (Arc::downgrade(&image.data), texture, view)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new_checks_size() {
+        assert!(Image::new(UVec2::new(2, 1), vec![0; 8], ColorSpace::Linear).is_some());
+        assert!(Image::new(UVec2::new(2, 1), vec![0; 4], ColorSpace::Linear).is_none());
+        assert!(Image::new(UVec2::ZERO, Vec::new(), ColorSpace::Linear).is_none());
+        assert!(
+            Image::render_target(UVec2::new(2, 1)).is_some_and(|image| image.is_render_target())
+        );
+        assert!(Image::render_target(UVec2::new(2, 0)).is_none());
+    }
+
+    #[test]
+    fn to_png_writes_checksummed_chunks() {
+        assert_eq!(crc32(b"IEND"), 0xae42_6082);
+        assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+
+        let pixels = [[255, 0, 0, 255], [0, 255, 0, 128]].concat();
+        let png = Image::new(UVec2::new(2, 1), pixels, ColorSpace::Srgb)
+            .unwrap()
+            .to_png();
+        assert!(png.starts_with(b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR\0\0\0\x02\0\0\0\x01\x08\x06"));
+        assert!(png.ends_with(b"\0\0\0\0IEND\xae\x42\x60\x82"));
+
+        // The scanline's filter byte and pixels in a single stored block.
+        let idat = png.windows(4).position(|kind| kind == b"IDAT").unwrap();
+        let stream = &png[idat + 4..idat + 4 + 2 + 5 + 9 + 4];
+        assert_eq!(stream[..7], [0x78, 0x01, 1, 9, 0, !9, 0xff]);
+        assert_eq!(stream[7..16], [0, 255, 0, 0, 255, 0, 255, 0, 128]);
+    }
+
+    #[test]
+    fn decode_reads_png() {
+        let pixels = [[255, 0, 0, 255], [0, 255, 0, 128]].concat();
+        let png = Image::new(UVec2::new(2, 1), pixels.clone(), ColorSpace::Srgb)
+            .unwrap()
+            .to_png();
+
+        let image = Image::decode(&png, ColorSpace::Linear).unwrap();
+        assert_eq!(image.size(), UVec2::new(2, 1));
+        assert_eq!(image.pixels(), pixels);
+        assert_eq!(image.color_space(), ColorSpace::Linear);
+        assert!(matches!(
+            Image::decode(b"not an image", ColorSpace::Srgb),
+            Err(ImageError::Decode(_))
+        ));
+    }
+
+    #[test]
+    fn with_mipmaps_averages_colors_in_linear_space() {
+        let pixels = [[255, 255, 255, 255], [0, 0, 0, 0]].concat();
+        let srgb = Image::new(UVec2::new(2, 1), pixels.clone(), ColorSpace::Srgb)
+            .unwrap()
+            .with_mipmaps();
+        let linear = Image::new(UVec2::new(2, 1), pixels, ColorSpace::Linear)
+            .unwrap()
+            .with_mipmaps();
+
+        assert_eq!(srgb.mip_level_count(), 2);
+        assert_eq!(srgb.mip_pixels(1), Some(&[188, 188, 188, 128][..]));
+        assert_eq!(linear.mip_pixels(1), Some(&[128, 128, 128, 128][..]));
+        assert_eq!(linear.mip_pixels(2), None);
+
+        let odd = Image::new(UVec2::new(5, 3), vec![0; 60], ColorSpace::Srgb)
+            .unwrap()
+            .with_mipmaps();
+        let sizes = (0..odd.mip_level_count())
+            .map(|level| odd.mip_pixels(level).unwrap().len() / 4)
+            .collect::<Vec<_>>();
+        assert_eq!(sizes, vec![15, 2, 1]);
+    }
+
+    #[test]
+    fn with_sampler_keeps_pixels() {
+        let image = Image::from_pixel([1, 2, 3, 4], ColorSpace::Srgb);
+        let sampler = ImageSampler {
+            mag_filter: FilterMode::Nearest,
+            anisotropy: 16,
+            ..ImageSampler::default()
+        };
+        let sampled = image.clone().with_sampler(sampler);
+
+        assert_eq!(image.sampler(), None);
+        assert_eq!(sampled.sampler(), Some(sampler));
+        assert_eq!(sampled.pixels(), image.pixels());
+        assert_ne!(sampled, image);
+        // Anisotropic filtering needs all filters to be linear.
+        assert_eq!(sampler.descriptor().anisotropy_clamp, 1);
+    }
+
+    #[test]
+    fn processed_images_keep_levels_and_sampler() {
+        let pixels = [[255, 0, 0, 255], [0, 255, 0, 255]].concat();
+        let image = Image::new(UVec2::new(2, 1), pixels, ColorSpace::Linear)
+            .unwrap()
+            .with_mipmaps()
+            .with_sampler(ImageSampler::default());
+        let processed = Image::from_processed(&image.to_processed()).unwrap();
+
+        assert_eq!(processed.size(), image.size());
+        assert_eq!(processed.color_space(), ColorSpace::Linear);
+        assert_eq!(processed.sampler(), image.sampler());
+        assert_eq!(processed.mip_level_count(), 2);
+        assert_eq!(processed.mip_pixels(1), image.mip_pixels(1));
+        assert_eq!(processed.pixels(), image.pixels());
+        assert!(Image::from_processed(&[]).is_none());
+    }
+
+    /// Returns an image of the size with a gradient in red and alpha, a checker in green, and
+    /// noise in blue.
+    fn pattern(size: UVec2) -> Image {
+        let pixels = (0..size.y)
+            .flat_map(|y| {
+                (0..size.x).flat_map(move |x| {
+                    let noise = (x * 7919 + y * 104_729) % 256;
+                    [
+                        x * 255 / size.x,
+                        (x / 4 + y / 4) % 2 * 255,
+                        noise,
+                        y * 255 / size.y,
+                    ]
+                    .map(|value| value as u8)
+                })
+            })
+            .collect();
+        Image::new(size, pixels, ColorSpace::Linear).unwrap()
+    }
+
+    #[test]
+    fn compressed_images_keep_blocks_of_levels() {
+        let image = pattern(UVec2::splat(8)).with_sampler(ImageSampler::default());
+        let compressed = image.clone().compressed(BlockFormat::Bc3).with_mipmaps();
+        assert_eq!(compressed.block_format(), Some(BlockFormat::Bc3));
+        assert_eq!(compressed.sampler(), image.sampler());
+        assert_eq!(compressed.mip_level_count(), 4);
+        let blocks = &compressed.data.blocks.as_ref().unwrap().levels;
+        assert_eq!(
+            blocks.iter().map(Vec::len).collect::<Vec<_>>(),
+            [64, 16, 16, 16]
+        );
+        // The checker in green is exact, since each block has two values of it.
+        assert_eq!(compressed.pixel(4, 0)[1], 255);
+        assert_eq!(compressed.pixel(4, 4)[1], 0);
+
+        let processed = Image::from_processed(&compressed.to_processed()).unwrap();
+        assert_eq!(processed.block_format(), Some(BlockFormat::Bc3));
+        assert_eq!(processed.pixels(), compressed.pixels());
+        assert_eq!(processed.mip_pixels(3), compressed.mip_pixels(3));
+        assert_eq!(processed.data.blocks.as_ref().unwrap().levels, *blocks);
+
+        let target = Image::render_target(UVec2::ONE).unwrap();
+        assert!(target.compressed(BlockFormat::Bc1).block_format().is_none());
+    }
+
+    #[test]
+    fn compressed_textures_sample_like_decoded_pixels() {
+        let Some((device, queue)) = crate::render::tests::device() else {
+            return;
+        };
+        if !device.features().contains(COMPRESSION_FEATURES) {
+            return;
+        }
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: None,
+            source: wgpu::ShaderSource::Wgsl(
+                "
+                @group(0) @binding(0) var image: texture_2d<f32>;
+
+                @vertex
+                fn vertex(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+                    let uv = vec2<f32>(f32(index & 1u) * 2.0, f32(index >> 1u) * 2.0);
+                    return vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+                }
+
+                @fragment
+                fn fragment(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+                    return textureLoad(image, vec2<i32>(position.xy), 0);
+                }
+                "
+                .into(),
+            ),
+        });
+        let format = wgpu::TextureFormat::Rgba8Unorm;
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: None,
+            layout: None,
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vertex",
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                buffers: &[],
+            },
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fragment",
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                targets: &[Some(format.into())],
+            }),
+            multiview: None,
+            cache: None,
+        });
+
+        let size = UVec2::splat(64);
+        let mut images = GpuImages::default();
+        for block_format in [
+            BlockFormat::Bc1,
+            BlockFormat::Bc3,
+            BlockFormat::Bc4,
+            BlockFormat::Bc5,
+        ] {
+            // Levels smaller than a block are uploaded too.
+            let image = pattern(size).with_mipmaps().compressed(block_format);
+            images.upload(&device, &queue, &image);
+            let (_, texture, view) = &images.images[&image.id()];
+            assert_eq!(texture.format(), block_format.to_wgpu(ColorSpace::Linear));
+
+            let target = crate::render::tests::target(&device, format, size);
+            let target_view = target.create_view(&wgpu::TextureViewDescriptor::default());
+            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: None,
+                layout: &pipeline.get_bind_group_layout(0),
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(view),
+                }],
+            });
+            let mut encoder =
+                device.create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+            {
+                let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view: &target_view,
+                        resolve_target: None,
+                        ops: wgpu::Operations::default(),
+                    })],
+                    ..wgpu::RenderPassDescriptor::default()
+                });
+                pass.set_pipeline(&pipeline);
+                pass.set_bind_group(0, &bind_group, &[]);
+                pass.draw(0..3, 0..1);
+            }
+            queue.submit([encoder.finish()]);
+
+            // Devices may round the interpolated palette entries differently.
+            let sampled = crate::render::tests::read(&device, &queue, &target);
+            let error = sampled
+                .iter()
+                .zip(image.pixels())
+                .map(|(sampled, decoded)| sampled.abs_diff(*decoded))
+                .max();
+            assert!(error <= Some(2), "{block_format:?} differs by {error:?}");
+        }
+    }
+
+    /// Returns a KTX2 file with the format, supercompression, size, and levels.
+    fn ktx2(
+        format: Option<Format>,
+        supercompression_scheme: Option<SupercompressionScheme>,
+        size: UVec2,
+        levels: &[Vec<u8>],
+    ) -> Vec<u8> {
+        let level_index_end = ktx2::Header::LENGTH + levels.len() * ktx2::LevelIndex::LENGTH;
+        // A data format descriptor with just its total size.
+        let dfd = 4u32.to_le_bytes();
+        let header = ktx2::Header {
+            format,
+            type_size: 1,
+            pixel_width: size.x,
+            pixel_height: size.y,
+            pixel_depth: 0,
+            layer_count: 0,
+            face_count: 1,
+            level_count: levels.len() as u32,
+            supercompression_scheme,
+            index: ktx2::Index {
+                dfd_byte_offset: level_index_end as u32,
+                dfd_byte_length: dfd.len() as u32,
+                kvd_byte_offset: 0,
+                kvd_byte_length: 0,
+                sgd_byte_offset: 0,
+                sgd_byte_length: 0,
+            },
+        };
+
+        let mut bytes = header.as_bytes().to_vec();
+        let mut offset = level_index_end + dfd.len();
+        for level in levels {
+            let index = ktx2::LevelIndex {
+                byte_offset: offset as u64,
+                byte_length: level.len() as u64,
+                uncompressed_byte_length: level.len() as u64,
+            };
+            bytes.extend(index.as_bytes());
+            offset += level.len();
+        }
+        bytes.extend(dfd);
+        bytes.extend(levels.concat());
+        bytes
+    }
+
+    #[test]
+    fn decode_ktx2_reads_levels() {
+        let levels = [vec![255; 2 * 2 * 3], vec![0, 128, 255]];
+        let file = ktx2(Some(Format::R8G8B8_SRGB), None, UVec2::splat(2), &levels);
+        let image = Image::decode_ktx2(&file).unwrap();
+        assert_eq!(image.size(), UVec2::splat(2));
+        assert_eq!(image.color_space(), ColorSpace::Srgb);
+        assert_eq!(image.mip_level_count(), 2);
+        assert_eq!(image.pixel(1, 1), [255; 4]);
+        assert_eq!(image.mip_pixels(1), Some(&[0, 128, 255, 255][..]));
+
+        let pixels = vec![7, 8, 9, 10];
+        let compressed = ruzstd::encoding::compress_to_vec(
+            &pixels[..],
+            ruzstd::encoding::CompressionLevel::Fastest,
+        );
+        let file = ktx2(
+            Some(Format::R8G8B8A8_UNORM),
+            Some(SupercompressionScheme::Zstandard),
+            UVec2::ONE,
+            &[compressed],
+        );
+        let image = Image::decode_ktx2(&file).unwrap();
+        assert_eq!(image.color_space(), ColorSpace::Linear);
+        assert_eq!(image.pixels(), pixels);
+    }
+
+    #[test]
+    fn decode_ktx2_decodes_blocks() {
+        // A BC4 block of a gradient between its endpoints, in rows of 200, 186, 172, and 158.
+        let indices = (0..16).fold(0u64, |indices, i| {
+            indices | [0, 2, 3, 4][i as usize / 4] << (i * 3)
+        });
+        let block = [&[200, 102], &indices.to_le_bytes()[..6]].concat();
+        let file = ktx2(
+            Some(Format::BC4_UNORM_BLOCK),
+            None,
+            UVec2::new(4, 3),
+            &[block],
+        );
+        let image = Image::decode_ktx2(&file).unwrap();
+        assert_eq!(image.block_format(), Some(BlockFormat::Bc4));
+        assert_eq!(image.color_space(), ColorSpace::Linear);
+        assert_eq!(image.size(), UVec2::new(4, 3));
+        assert_eq!(image.pixel(3, 0), [200, 0, 0, 255]);
+        assert_eq!(image.pixel(0, 2), [172, 0, 0, 255]);
+
+        // A BC1 block of red and blue, with a third of the way between them below red.
+        let block = [0x00, 0xf8, 0x1f, 0x00, 0b0100, 0b10, 0, 0];
+        let file = ktx2(
+            Some(Format::BC1_RGBA_SRGB_BLOCK),
+            None,
+            UVec2::splat(2),
+            &[block.to_vec()],
+        );
+        let image = Image::decode_ktx2(&file).unwrap();
+        assert_eq!(image.block_format(), Some(BlockFormat::Bc1));
+        assert_eq!(image.color_space(), ColorSpace::Srgb);
+        assert_eq!(image.pixel(0, 0), [255, 0, 0, 255]);
+        assert_eq!(image.pixel(1, 0), [0, 0, 255, 255]);
+        assert_eq!(image.pixel(0, 1), [170, 0, 85, 255]);
+
+        let short = ktx2(
+            Some(Format::BC3_UNORM_BLOCK),
+            None,
+            UVec2::ONE,
+            &[vec![0; 8]],
+        );
+        assert!(matches!(
+            Image::decode_ktx2(&short),
+            Err(ImageError::InvalidData)
+        ));
+    }
+
+    #[test]
+    fn decode_ktx2_rejects_unsupported_files() {
+        let etc1s = ktx2(
+            None,
+            Some(SupercompressionScheme::BasisLZ),
+            UVec2::ONE,
+            &[vec![0; 4]],
+        );
+        let uastc = ktx2(
+            None,
+            Some(SupercompressionScheme::Zstandard),
+            UVec2::ONE,
+            &[vec![0; 16]],
+        );
+        let compressed = ktx2(
+            Some(Format::BC7_SRGB_BLOCK),
+            None,
+            UVec2::ONE,
+            &[vec![0; 16]],
+        );
+        let short = ktx2(
+            Some(Format::R8G8B8A8_SRGB),
+            None,
+            UVec2::splat(2),
+            &[vec![0; 4]],
+        );
+
+        assert!(matches!(
+            Image::decode_ktx2(&etc1s),
+            Err(ImageError::BasisUniversal)
+        ));
+        assert!(matches!(
+            Image::decode_ktx2(&uastc),
+            Err(ImageError::BasisUniversal)
+        ));
+        assert!(matches!(
+            Image::decode_ktx2(&compressed),
+            Err(ImageError::UnsupportedFormat)
+        ));
+        assert!(matches!(
+            Image::decode_ktx2(&short),
+            Err(ImageError::InvalidData)
+        ));
+        assert!(matches!(
+            Image::decode_ktx2(b"KTX 11"),
+            Err(ImageError::Ktx2(_))
+        ));
+    }
+
+    #[test]
+    fn decode_exr_reads_linear_pixels() {
+        let pixels = vec![0.25, 2.0, 16.0, 0.0, 0.5, 1.0];
+        let exr = image::Rgb32FImage::from_raw(2, 1, pixels).unwrap();
+        let mut bytes = io::Cursor::new(Vec::new());
+        exr.write_to(&mut bytes, image::ImageFormat::OpenExr)
+            .unwrap();
+
+        let image = HdrImage::decode_exr(bytes.get_ref()).unwrap();
+        assert_eq!(image.size(), UVec2::new(2, 1));
+        assert_eq!(image.pixel(0, 0), [0.25, 2.0, 16.0]);
+        assert_eq!(image.pixel(1, 0), [0.0, 0.5, 1.0]);
+    }
+
+    #[test]
+    fn cubemap_checks_faces() {
+        let face = HdrImage::from_pixel([1.0; 3]);
+        let faces = || std::array::from_fn(|_| face.clone());
+        assert!(HdrCubemap::new(faces()).is_some());
+
+        let mut different = faces();
+        different[3] = HdrImage::new(UVec2::new(2, 2), vec![[0.0; 3]; 4]).unwrap();
+        assert!(HdrCubemap::new(different).is_none());
+
+        let wide = HdrImage::new(UVec2::new(2, 1), vec![[0.0; 3]; 2]).unwrap();
+        assert!(HdrCubemap::new(std::array::from_fn(|_| wide.clone())).is_none());
+    }
+
+    #[test]
+    fn decode_flat_and_run_length_encoded_scanlines() {
+        let mut bytes = b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y 2 +X 8\n".to_vec();
+        for x in 0..8 {
+            bytes.extend([x * 16, 0, 0, 129]);
+        }
+        bytes.extend([2, 2, 0, 8]);
+        bytes.extend([128 + 8, 128]);
+        bytes.extend([8, 0, 1, 2, 3, 4, 5, 6, 7]);
+        bytes.extend([128 + 8, 0]);
+        bytes.extend([128 + 4, 136, 128 + 4, 137]);
+
+        let image = HdrImage::decode(&bytes).unwrap();
+
+        assert_eq!(image.size(), UVec2::new(8, 2));
+        assert_eq!(image.pixel(2, 0), [0.25, 0.0, 0.0]);
+        assert_eq!(image.pixel(1, 1), [128.0, 1.0, 0.0]);
+        assert_eq!(image.pixel(7, 1), [256.0, 14.0, 0.0]);
+    }
+
+    #[test]
+    fn decode_literal_runs_of_128_values() {
+        let mut bytes = b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y 1 +X 256\n".to_vec();
+        bytes.extend([2, 2, 1, 0]);
+        for channel in 0..4u8 {
+            for half in 0..2u8 {
+                bytes.push(128);
+                bytes.extend((0..128).map(|x| match channel {
+                    0 => half * 128 + x,
+                    1 => 255 - x,
+                    2 => x % 7,
+                    _ => 136,
+                }));
+            }
+        }
+
+        let image = HdrImage::decode(&bytes).unwrap();
+
+        assert_eq!(image.size(), UVec2::new(256, 1));
+        assert_eq!(image.pixel(3, 0), [3.0, 252.0, 3.0]);
+        assert_eq!(image.pixel(200, 0), [200.0, 183.0, 2.0]);
+    }
+
+    #[test]
+    fn decode_rejects_invalid_files() {
+        let decode = |bytes: &[u8]| HdrImage::decode(bytes).unwrap_err();
+
+        assert_eq!(decode(b"P6\n1 1\n255\n"), HdrError::InvalidHeader);
+        assert_eq!(
+            decode(b"#?RADIANCE\nFORMAT=32-bit_rle_xyze\n\n-Y 1 +X 1\n"),
+            HdrError::UnsupportedFormat
+        );
+        assert_eq!(
+            decode(b"#?RADIANCE\n\n+Y 1 +X 1\n"),
+            HdrError::UnsupportedFormat
+        );
+        assert_eq!(
+            decode(b"#?RADIANCE\n\n-Y 1 +X 2\n\0\0\0\0"),
+            HdrError::InvalidData
+        );
+    }
+}
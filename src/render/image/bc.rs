@@ -0,0 +1,314 @@
+//! Encoding and decoding of the BC1, BC3, BC4, and BC5 block compressed formats, which store each
+//! block of 4x4 pixels as two endpoints and an index per pixel into a palette interpolated between
+//! them. Blocks at the right and bottom edges of images whose size isn't a multiple of 4 repeat
+//! the edge pixels.
+
+use glam::UVec2;
+use glam::Vec3;
+
+use crate::render::image::BlockFormat;
+
+/// Returns the number of bytes of the blocks of an image of the size.
+pub(super) fn len(format: BlockFormat, size: UVec2) -> usize {
+    let (width, height) = (size.x.div_ceil(4), size.y.div_ceil(4));
+    width as usize * height as usize * format.block_len()
+}
+
+/// Returns the blocks of the 8-bit RGBA pixels of the size, row by row from the top-left corner.
+pub(super) fn encode(format: BlockFormat, size: UVec2, pixels: &[u8]) -> Vec<u8> {
+    let mut blocks = Vec::with_capacity(len(format, size));
+    for block_y in 0..size.y.div_ceil(4) {
+        for block_x in 0..size.x.div_ceil(4) {
+            let texels: [[u8; 4]; 16] = std::array::from_fn(|i| {
+                let x = (block_x * 4 + i as u32 % 4).min(size.x - 1);
+                let y = (block_y * 4 + i as u32 / 4).min(size.y - 1);
+                let index = (y as usize * size.x as usize + x as usize) * 4;
+                pixels[index..index + 4].try_into().unwrap()
+            });
+            let channel = |channel: usize| texels.map(|texel| texel[channel]);
+            match format {
+                BlockFormat::Bc1 => blocks.extend(encode_color(&texels)),
+                BlockFormat::Bc3 => {
+                    blocks.extend(encode_channel(&channel(3)));
+                    blocks.extend(encode_color(&texels));
+                }
+                BlockFormat::Bc4 => blocks.extend(encode_channel(&channel(0))),
+                BlockFormat::Bc5 => {
+                    blocks.extend(encode_channel(&channel(0)));
+                    blocks.extend(encode_channel(&channel(1)));
+                }
+            }
+        }
+    }
+    blocks
+}
+
+/// Returns the 8-bit RGBA pixels of the blocks of an image of the size, or `None` if there are
+/// fewer blocks than the size needs. Channels missing from the format are 0, or 255 for alpha.
+pub(super) fn decode(format: BlockFormat, size: UVec2, blocks: &[u8]) -> Option<Vec<u8>> {
+    let blocks = blocks.get(..len(format, size))?;
+    let width = size.x.div_ceil(4) as usize;
+    let mut pixels = vec![0; size.x as usize * size.y as usize * 4];
+    for (index, block) in blocks.chunks_exact(format.block_len()).enumerate() {
+        let texels = match format {
+            BlockFormat::Bc1 => decode_color(block, true),
+            BlockFormat::Bc3 => {
+                let alpha = decode_channel(&block[..8]);
+                let mut texels = decode_color(&block[8..], false);
+                for (texel, alpha) in texels.iter_mut().zip(alpha) {
+                    texel[3] = alpha;
+                }
+                texels
+            }
+            BlockFormat::Bc4 => decode_channel(block).map(|red| [red, 0, 0, 255]),
+            BlockFormat::Bc5 => {
+                let green = decode_channel(&block[8..]);
+                let mut texels = decode_channel(&block[..8]).map(|red| [red, 0, 0, 255]);
+                for (texel, green) in texels.iter_mut().zip(green) {
+                    texel[1] = green;
+                }
+                texels
+            }
+        };
+
+        let (block_x, block_y) = ((index % width) * 4, (index / width) * 4);
+        for (i, texel) in texels.iter().enumerate() {
+            let (x, y) = (block_x + i % 4, block_y + i / 4);
+            if x < size.x as usize && y < size.y as usize {
+                let offset = (y * size.x as usize + x) * 4;
+                pixels[offset..offset + 4].copy_from_slice(texel);
+            }
+        }
+    }
+    Some(pixels)
+}
+
+/// Returns a BC1 block of the colors, with the endpoints at the extremes of the colors along their
+/// principal axis. Alpha is ignored, and the block always uses the opaque four color palette.
+fn encode_color(texels: &[[u8; 4]; 16]) -> [u8; 8] {
+    let colors = texels.map(|[r, g, b, _]| Vec3::new(r.into(), g.into(), b.into()));
+    let mean = colors.iter().sum::<Vec3>() / 16.0;
+    let (mut xx, mut xy, mut xz, mut yy, mut yz, mut zz) = (0.0, 0.0, 0.0, 0.0, 0.0, 0.0);
+    for color in colors {
+        let d = color - mean;
+        (xx, xy, xz) = (xx + d.x * d.x, xy + d.x * d.y, xz + d.x * d.z);
+        (yy, yz, zz) = (yy + d.y * d.y, yz + d.y * d.z, zz + d.z * d.z);
+    }
+    // Power iteration on the covariance matrix converges to the axis of the largest spread.
+    let mut axis = Vec3::ONE;
+    for _ in 0..8 {
+        let next = Vec3::new(
+            xx * axis.x + xy * axis.y + xz * axis.z,
+            xy * axis.x + yy * axis.y + yz * axis.z,
+            xz * axis.x + yz * axis.y + zz * axis.z,
+        );
+        if next.length_squared() < 1e-6 {
+            break;
+        }
+        axis = next.normalize();
+    }
+
+    let (min, max) = colors
+        .iter()
+        .map(|color| (*color - mean).dot(axis))
+        .fold((f32::MAX, f32::MIN), |(min, max), t| {
+            (min.min(t), max.max(t))
+        });
+    let mut endpoints = [to_565(mean + axis * max), to_565(mean + axis * min)];
+    if endpoints[0] < endpoints[1] {
+        endpoints.swap(0, 1);
+    }
+
+    let palette = color_palette(endpoints, false);
+    let mut indices = 0u32;
+    // Equal endpoints would select the three color palette, so all pixels use the first color.
+    if endpoints[0] != endpoints[1] {
+        for (i, color) in colors.iter().enumerate() {
+            indices |= nearest(&palette, |entry| {
+                (Vec3::new(entry[0].into(), entry[1].into(), entry[2].into()) - *color)
+                    .length_squared()
+            }) << (i * 2);
+        }
+    }
+
+    let mut block = [0; 8];
+    block[..2].copy_from_slice(&endpoints[0].to_le_bytes());
+    block[2..4].copy_from_slice(&endpoints[1].to_le_bytes());
+    block[4..].copy_from_slice(&indices.to_le_bytes());
+    block
+}
+
+/// Returns the colors of a BC1 block. Blocks whose first endpoint isn't greater than the second
+/// use a three color palette with transparent black, unless they're the color of a BC3 block.
+fn decode_color(block: &[u8], three_colors: bool) -> [[u8; 4]; 16] {
+    let endpoints = [0, 2].map(|i| u16::from_le_bytes([block[i], block[i + 1]]));
+    let palette = color_palette(endpoints, three_colors && endpoints[0] <= endpoints[1]);
+    let indices = u32::from_le_bytes(block[4..8].try_into().unwrap());
+    std::array::from_fn(|i| palette[(indices >> (i * 2)) as usize & 3])
+}
+
+/// Returns the four colors between the RGB565 endpoints, or three colors and transparent black.
+fn color_palette(endpoints: [u16; 2], three_colors: bool) -> [[u8; 4]; 4] {
+    let [a, b] = endpoints.map(from_565);
+    let mix = |weight_a: u16, weight_b: u16| -> [u8; 4] {
+        let sum = weight_a + weight_b;
+        std::array::from_fn(|channel| match channel {
+            3 => 255,
+            _ => {
+                ((u16::from(a[channel]) * weight_a + u16::from(b[channel]) * weight_b) / sum) as u8
+            }
+        })
+    };
+    if three_colors {
+        [a, b, mix(1, 1), [0; 4]]
+    } else {
+        [a, b, mix(2, 1), mix(1, 2)]
+    }
+}
+
+fn to_565(color: Vec3) -> u16 {
+    let quantize = |value: f32, max: f32| (value.clamp(0.0, 255.0) / 255.0 * max).round() as u16;
+    quantize(color.x, 31.0) << 11 | quantize(color.y, 63.0) << 5 | quantize(color.z, 31.0)
+}
+
+/// Expands the RGB565 color to 8 bits per channel by repeating the high bits in the low bits.
+fn from_565(color: u16) -> [u8; 4] {
+    let (r, g, b) = (
+        (color >> 11) as u8,
+        (color >> 5 & 63) as u8,
+        (color & 31) as u8,
+    );
+    [r << 3 | r >> 2, g << 2 | g >> 4, b << 3 | b >> 2, 255]
+}
+
+/// Returns a BC4 block of the values, with the endpoints at their minimum and maximum.
+fn encode_channel(values: &[u8; 16]) -> [u8; 8] {
+    let (min, max) = (*values.iter().min().unwrap(), *values.iter().max().unwrap());
+    // Equal endpoints select the six value palette, whose first value is still the endpoint.
+    let palette = channel_palette(max, min);
+    let mut indices = 0u64;
+    for (i, value) in values.iter().enumerate() {
+        let index = nearest(&palette, |entry| entry.abs_diff(*value));
+        indices |= u64::from(index) << (i * 3);
+    }
+
+    let mut block = [0; 8];
+    block[0] = max;
+    block[1] = min;
+    block[2..].copy_from_slice(&indices.to_le_bytes()[..6]);
+    block
+}
+
+/// Returns the values of a BC4 block.
+fn decode_channel(block: &[u8]) -> [u8; 16] {
+    let palette = channel_palette(block[0], block[1]);
+    let mut bytes = [0; 8];
+    bytes[..6].copy_from_slice(&block[2..8]);
+    let indices = u64::from_le_bytes(bytes);
+    std::array::from_fn(|i| palette[(indices >> (i * 3)) as usize & 7])
+}
+
+/// Returns the eight values between the endpoints if the first is greater, or else six values
+/// between them followed by 0 and 255.
+fn channel_palette(a: u8, b: u8) -> [u8; 8] {
+    let (a, b) = (u32::from(a), u32::from(b));
+    let steps = if a > b { 7 } else { 5 };
+    let mix = |step: u32| ((a * (steps - step) + b * step + steps / 2) / steps) as u8;
+    std::array::from_fn(|index| match index as u32 {
+        0 => a as u8,
+        1 => b as u8,
+        6 if steps == 5 => 0,
+        7 if steps == 5 => 255,
+        index => mix(index - 1),
+    })
+}
+
+/// Returns the index of the palette entry with the smallest error.
+fn nearest<T, E: PartialOrd>(palette: &[T], error: impl Fn(&T) -> E) -> u32 {
+    let mut best = (0, error(&palette[0]));
+    for (index, entry) in palette.iter().enumerate().skip(1) {
+        let entry_error = error(entry);
+        if entry_error < best.1 {
+            best = (index as u32, entry_error);
+        }
+    }
+    best.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns a gradient of the size with colors changing to the right and alpha downwards, so
+    /// the colors of each block lie on a line.
+    fn gradient(size: UVec2) -> Vec<u8> {
+        (0..size.y)
+            .flat_map(|y| {
+                (0..size.x).flat_map(move |x| {
+                    let t = x * 255 / size.x;
+                    [t, 255 - t / 2, 128, y * 255 / size.y].map(|value| value as u8)
+                })
+            })
+            .collect()
+    }
+
+    fn max_error(a: &[u8], b: &[u8], channels: usize) -> u8 {
+        a.chunks_exact(4)
+            .zip(b.chunks_exact(4))
+            .flat_map(|(a, b)| (0..channels).map(|channel| a[channel].abs_diff(b[channel])))
+            .max()
+            .unwrap()
+    }
+
+    #[test]
+    fn decode_encoded_blocks_approximates_pixels() {
+        let size = UVec2::new(10, 6);
+        let pixels = gradient(size);
+        for (format, channels, tolerance) in [
+            (BlockFormat::Bc1, 3, 12),
+            (BlockFormat::Bc3, 4, 12),
+            (BlockFormat::Bc4, 1, 3),
+            (BlockFormat::Bc5, 2, 3),
+        ] {
+            let blocks = encode(format, size, &pixels);
+            assert_eq!(blocks.len(), len(format, size));
+            let decoded = decode(format, size, &blocks).unwrap();
+            assert!(
+                max_error(&pixels, &decoded, channels) <= tolerance,
+                "{format:?}"
+            );
+            assert!(decode(format, size, &blocks[1..]).is_none());
+        }
+    }
+
+    #[test]
+    fn uniform_blocks_are_exact() {
+        let pixels = [8, 180, 255, 255].repeat(16);
+        let size = UVec2::splat(4);
+        let decoded = decode(
+            BlockFormat::Bc4,
+            size,
+            &encode(BlockFormat::Bc4, size, &pixels),
+        );
+        assert_eq!(decoded.unwrap()[..4], [8, 0, 0, 255]);
+
+        // Colors that are exact in RGB565.
+        let pixels = [[0, 0, 0, 255], [255, 255, 255, 255]].repeat(8).concat();
+        let decoded = decode(
+            BlockFormat::Bc1,
+            size,
+            &encode(BlockFormat::Bc1, size, &pixels),
+        );
+        assert_eq!(decoded.unwrap(), pixels);
+    }
+
+    #[test]
+    fn three_color_blocks_have_transparent_black() {
+        // Equal endpoints select the three color palette, in which index 3 is transparent.
+        let block = [0xff, 0xff, 0xff, 0xff, 0b11, 0, 0, 0];
+        let texels = decode_color(&block, true);
+        assert_eq!(texels[0], [0; 4]);
+        assert_eq!(texels[1], [255; 4]);
+        assert_eq!(decode_color(&block, false)[0][3], 255);
+    }
+}
@@ -0,0 +1,377 @@
+//! # Light
+//!
+//! Lights illuminating meshes shaded with a [crate::render::material::Material]. Directional and
+//! spot lights shine along the forward direction of the node's [WorldTransform], the negative Z
+//! axis, and point and spot lights from its translation.
+
+use bytemuck::Pod;
+use bytemuck::Zeroable;
+use glam::Mat4;
+use glam::Vec3;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::render::cluster::ClusterUniform;
+use crate::render::environment::EnvironmentLight;
+use crate::render::environment::SPECULAR_MIPS;
+use crate::render::probe::ProbeUniform;
+use crate::render::probe::MAX_PROBES;
+use crate::render::shadow::CameraShadows;
+use crate::render::shadow::ShadowSettings;
+use crate::render::shadow::MAX_SHADOW_MAPS;
+use crate::render::Color;
+use crate::BoundingSphere;
+use crate::Component;
+use crate::ComputedVisibility;
+use crate::Frustum;
+use crate::Node;
+use crate::Reflect;
+use crate::Scene;
+use crate::WorldTransform;
+
+/// Maximum number of lights illuminating the meshes seen by a camera. Further lights are ignored.
+/// Point and spot lights only shade the fragments in the clusters their range reaches, see
+/// [crate::render::cluster::ClusterSettings].
+pub const MAX_LIGHTS: usize = 1024;
+
+/// # Directional Light
+///
+/// Light infinitely far away shining in the node's forward direction, e.g. the sun.
+#[derive(Copy, Clone, Debug, PartialEq, Component, Reflect, Serialize, Deserialize)]
+pub struct DirectionalLight {
+    /// Color of the light.
+    pub color: Color,
+    /// Intensity of the light, the same everywhere.
+    pub intensity: f32,
+    /// Whether the light casts shadows, rendered to a cascade of shadow maps covering the view
+    /// of each camera. See [crate::render::shadow::ShadowSettings].
+    pub shadows: bool,
+}
+
+impl Default for DirectionalLight {
+    fn default() -> Self {
+        Self {
+            color: Color::WHITE,
+            intensity: 3.0,
+            shadows: false,
+        }
+    }
+}
+
+/// # Point Light
+///
+/// Light shining from the node's position in all directions, e.g. a light bulb.
+#[derive(Copy, Clone, Debug, PartialEq, Component, Reflect, Serialize, Deserialize)]
+pub struct PointLight {
+    /// Color of the light.
+    pub color: Color,
+    /// Intensity of the light at a distance of one unit. It falls off with the square of the
+    /// distance.
+    pub intensity: f32,
+    /// Distance at which the light smoothly falls off to zero.
+    pub range: f32,
+}
+
+impl Default for PointLight {
+    fn default() -> Self {
+        Self {
+            color: Color::WHITE,
+            intensity: 10.0,
+            range: 20.0,
+        }
+    }
+}
+
+/// # Spot Light
+///
+/// Light shining from the node's position in a cone around its forward direction, e.g. a
+/// flashlight.
+#[derive(Copy, Clone, Debug, PartialEq, Component, Reflect, Serialize, Deserialize)]
+pub struct SpotLight {
+    /// Color of the light.
+    pub color: Color,
+    /// Intensity of the light at a distance of one unit. It falls off with the square of the
+    /// distance.
+    pub intensity: f32,
+    /// Distance at which the light smoothly falls off to zero.
+    pub range: f32,
+    /// Angle in radians from the forward direction up to which the light has full intensity.
+    pub inner_angle: f32,
+    /// Angle in radians from the forward direction at which the light falls off to zero.
+    pub outer_angle: f32,
+    /// Whether the light casts shadows, rendered to a shadow map covering its cone.
+    pub shadows: bool,
+}
+
+impl Default for SpotLight {
+    fn default() -> Self {
+        Self {
+            color: Color::WHITE,
+            intensity: 10.0,
+            range: 20.0,
+            inner_angle: 0.0,
+            outer_angle: std::f32::consts::FRAC_PI_4,
+            shadows: false,
+        }
+    }
+}
+
+/// # Ambient Light
+///
+/// Scene resource with light reaching every surface from all directions, approximating light
+/// bounced off the surroundings. Defaults to a dim white light if the scene has none.
+#[derive(Copy, Clone, Debug, PartialEq, Reflect, Serialize, Deserialize)]
+pub struct AmbientLight {
+    /// Color of the light.
+    pub color: Color,
+    /// Intensity of the light.
+    pub intensity: f32,
+}
+
+impl Default for AmbientLight {
+    fn default() -> Self {
+        Self {
+            color: Color::WHITE,
+            intensity: 0.1,
+        }
+    }
+}
+
+/// Kinds of lights in the shader, stored in the `w` component of the direction.
+const DIRECTIONAL: f32 = 0.0;
+const POINT: f32 = 1.0;
+const SPOT: f32 = 2.0;
+
+/// Uniforms of a light in the shader, stored in the light buffer.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, Pod, Zeroable)]
+pub(crate) struct LightUniform {
+    /// Color multiplied with the intensity.
+    color: [f32; 4],
+    /// Position and range.
+    position: [f32; 4],
+    /// Direction the light shines in and kind.
+    direction: [f32; 4],
+    /// Cosine of the outer angle and the inverse of the difference to the cosine of the inner
+    /// angle, so the spot factor is a single multiply-add.
+    spot: [f32; 4],
+    /// Index of the light's first shadow map, or -1 if it has none, and its number of maps.
+    shadow: [f32; 4],
+}
+
+/// Uniforms of the lights seen by a camera in the shader.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+pub(crate) struct LightsUniform {
+    ambient: [f32; 4],
+    /// Number of directional lights, which come first and shade every fragment, number of all
+    /// lights, index of the first light in the light buffer, and index of the first cluster in
+    /// the cluster buffer.
+    pub(crate) count: [u32; 4],
+    /// Distance along the camera's forward direction up to which each cascade of directional
+    /// light shadow maps is used.
+    cascade_splits: [f32; 4],
+    /// PCF radius in texels, size of a texel, depth bias, and normal bias.
+    shadow_params: [f32; 4],
+    /// Intensity of the environment light, or zero without one, and the level of detail of the
+    /// roughest mip of the specular cubemaps of the environment light and the reflection probes.
+    environment: [f32; 4],
+    pub(crate) clusters: ClusterUniform,
+    shadow_matrices: [[[f32; 4]; 4]; MAX_SHADOW_MAPS],
+    /// Reflection probes nearest to the camera.
+    pub(crate) probes: [ProbeUniform; MAX_PROBES],
+}
+
+/// Returns the visible light nodes that can illuminate the meshes inside the frustum: all
+/// directional lights and the point and spot lights whose range intersects it. Directional lights
+/// come first, then the other lights in node order, up to [MAX_LIGHTS].
+pub(crate) fn gather_lights(scene: &Scene, frustum: &Frustum) -> Vec<Node> {
+    let visible =
+        |node: Node| scene.get::<ComputedVisibility>(node) == Some(&ComputedVisibility::Visible);
+    let in_range = |node: Node, range: f32| {
+        let position = world_transform(scene, node).translation();
+        frustum.intersects_sphere(&BoundingSphere::new(position, range))
+    };
+
+    let mut directional = scene
+        .query::<(DirectionalLight,)>()
+        .map(|(node, _)| node)
+        .filter(|node| visible(*node))
+        .collect::<Vec<_>>();
+    directional.sort();
+    let points = scene
+        .query::<(PointLight,)>()
+        .filter(|(node, light)| visible(*node) && in_range(*node, light.range))
+        .map(|(node, _)| node);
+    let spots = scene
+        .query::<(SpotLight,)>()
+        .filter(|(node, light)| visible(*node) && in_range(*node, light.range))
+        .map(|(node, _)| node);
+    let mut local = points.chain(spots).collect::<Vec<_>>();
+    local.sort();
+
+    directional.extend(local);
+    directional.truncate(MAX_LIGHTS);
+    directional
+}
+
+/// Returns the uniforms of the light nodes, with the shadow maps rendered for them, the
+/// view-projection matrices of all shadow maps, and the environment light or the light of the
+/// sky replacing the ambient light. The lights are appended to the light buffer.
+pub(crate) fn lights_uniform(
+    scene: &Scene,
+    lights: &[Node],
+    shadows: Option<&CameraShadows>,
+    matrices: &[Mat4],
+    environment: Option<&EnvironmentLight>,
+    sky: Option<Vec3>,
+    buffer: &mut Vec<LightUniform>,
+) -> LightsUniform {
+    let mut uniform = LightsUniform::zeroed();
+    uniform.environment[1] = (SPECULAR_MIPS - 1) as f32;
+    if let Some(environment) = environment {
+        uniform.environment[0] = environment.intensity.max(0.0);
+    } else if let Some(sky) = sky {
+        uniform.ambient = sky.extend(0.0).to_array();
+    } else {
+        let ambient = scene
+            .get_resource::<AmbientLight>()
+            .copied()
+            .unwrap_or_default();
+        uniform.ambient = radiance(ambient.color, ambient.intensity);
+    }
+    let settings = scene
+        .get_resource::<ShadowSettings>()
+        .copied()
+        .unwrap_or_default();
+    uniform.shadow_params = [
+        settings.pcf_radius as f32,
+        1.0 / settings.resolution.max(1) as f32,
+        settings.depth_bias,
+        settings.normal_bias,
+    ];
+    if let Some(shadows) = shadows {
+        uniform.cascade_splits = shadows.cascade_splits;
+    }
+    for (slot, matrix) in uniform.shadow_matrices.iter_mut().zip(matrices) {
+        *slot = matrix.to_cols_array_2d();
+    }
+
+    let start = buffer.len();
+    for node in lights {
+        let Some(mut light) = light_uniform(scene, *node) else {
+            continue;
+        };
+        light.shadow = shadows
+            .and_then(|shadows| shadows.maps(*node))
+            .map_or([-1.0, 0.0, 0.0, 0.0], |maps| {
+                [maps.start as f32, maps.len() as f32, 0.0, 0.0]
+            });
+        buffer.push(light);
+    }
+    let directional = buffer[start..]
+        .iter()
+        .take_while(|light| light.direction[3] == DIRECTIONAL)
+        .count();
+    uniform.count = [
+        directional as u32,
+        (buffer.len() - start) as u32,
+        start as u32,
+        0,
+    ];
+    uniform
+}
+
+fn light_uniform(scene: &Scene, node: Node) -> Option<LightUniform> {
+    let transform = world_transform(scene, node);
+    let direction = transform.forward().normalize_or_zero();
+    let position = transform.translation();
+    if let Some(light) = scene.get::<DirectionalLight>(node) {
+        Some(LightUniform {
+            color: radiance(light.color, light.intensity),
+            position: [0.0; 4],
+            direction: direction.extend(DIRECTIONAL).to_array(),
+            spot: [0.0; 4],
+            shadow: [0.0; 4],
+        })
+    } else if let Some(light) = scene.get::<PointLight>(node) {
+        Some(LightUniform {
+            color: radiance(light.color, light.intensity),
+            position: position.extend(light.range).to_array(),
+            direction: [0.0, 0.0, 0.0, POINT],
+            spot: [0.0; 4],
+            shadow: [0.0; 4],
+        })
+    } else {
+        scene.get::<SpotLight>(node).map(|light| {
+            let outer = light.outer_angle.cos();
+            let inner = light.inner_angle.min(light.outer_angle).cos();
+            LightUniform {
+                color: radiance(light.color, light.intensity),
+                position: position.extend(light.range).to_array(),
+                direction: direction.extend(SPOT).to_array(),
+                spot: [outer, 1.0 / (inner - outer).max(1e-4), 0.0, 0.0],
+                shadow: [0.0; 4],
+            }
+        })
+    }
+}
+
+pub(crate) fn world_transform(scene: &Scene, node: Node) -> WorldTransform {
+    scene
+        .get::<WorldTransform>(node)
+        .copied()
+        .unwrap_or(WorldTransform::IDENTITY)
+}
+
+fn radiance(color: Color, intensity: f32) -> [f32; 4] {
+    (color.to_vec4().truncate() * intensity.max(0.0))
+        .extend(0.0)
+        .to_array()
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec3;
+
+    use super::*;
+    use crate::Camera;
+
+    #[test]
+    fn gather_lights_in_frustum() {
+        let mut scene = Scene::new();
+        let at = |x: f32| WorldTransform::new(Mat4::from_translation(Vec3::new(x, 0.0, -5.0)));
+        let point = scene.spawn_with((PointLight::default(), at(0.0), ComputedVisibility::Visible));
+        scene.spawn_with((
+            PointLight {
+                range: 1.0,
+                ..PointLight::default()
+            },
+            at(100.0),
+            ComputedVisibility::Visible,
+        ));
+        scene.spawn_with((SpotLight::default(), at(1.0), ComputedVisibility::Invisible));
+        let directional =
+            scene.spawn_with((DirectionalLight::default(), ComputedVisibility::Visible));
+        let frustum = Camera::perspective(1.0, 1.0, 0.1, 10.0).frustum(&WorldTransform::IDENTITY);
+
+        let lights = gather_lights(&scene, &frustum);
+        // Lights of a previous camera in the light buffer.
+        let mut buffer = vec![LightUniform::zeroed()];
+        let uniform = lights_uniform(&scene, &lights, None, &[], None, None, &mut buffer);
+
+        assert_eq!(lights, [directional, point]);
+        assert_eq!(uniform.count, [1, 2, 1, 0]);
+        assert_eq!(buffer[1].direction, [0.0, 0.0, -1.0, DIRECTIONAL]);
+        assert_eq!(buffer[2].position, [0.0, 0.0, -5.0, 20.0]);
+        assert_eq!(buffer[2].shadow, [-1.0, 0.0, 0.0, 0.0]);
+        assert_eq!(uniform.ambient, [0.1, 0.1, 0.1, 0.0]);
+    }
+
+    #[test]
+    fn shader_has_max_shadow_maps() {
+        let shader = include_str!("shaders/pbr.wgsl");
+
+        assert!(shader.contains(&format!("array<mat4x4<f32>, {MAX_SHADOW_MAPS}>")));
+    }
+}
@@ -0,0 +1,868 @@
+//! # Material
+//!
+//! Surface properties of meshes for physically based shading.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::Weak;
+
+use bytemuck::Pod;
+use bytemuck::Zeroable;
+use serde::Deserialize;
+use serde::Serialize;
+use wgpu::util::DeviceExt;
+
+use crate::assets::cache::ProcessedReader;
+use crate::assets::cache::ProcessedWriter;
+use crate::render::image::ColorSpace;
+use crate::render::image::GpuImages;
+use crate::render::image::Image;
+use crate::render::shader_material::ShaderMaterial;
+use crate::render::Color;
+use crate::Component;
+
+/// # Standard Material
+///
+/// Physically based metallic-roughness material, following the glTF conventions. Each factor is
+/// multiplied with its texture if it has one. Textures are sampled at the mesh's UVs.
+///
+/// ```
+/// # use pulse::render::material::Material;
+/// # use pulse::render::material::StandardMaterial;
+/// # use pulse::render::Color;
+/// let gold = Material::new(StandardMaterial {
+///     base_color: Color::srgb(1.0, 0.77, 0.34),
+///     metallic: 1.0,
+///     roughness: 0.3,
+///     ..StandardMaterial::default()
+/// });
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct StandardMaterial {
+    /// Color of diffusely reflected light for dielectrics and of specularly reflected light for
+    /// metals. The alpha is used according to the alpha mode.
+    pub base_color: Color,
+    /// sRGB texture multiplied with the base color, alpha included.
+    pub base_color_texture: Option<Image>,
+    /// How metallic the surface is, from 0 for dielectrics to 1 for metals.
+    pub metallic: f32,
+    /// Perceptual roughness of the surface, from 0 for mirror-like to 1 for fully rough.
+    pub roughness: f32,
+    /// Linear texture with the roughness in its green and the metallic in its blue channel,
+    /// multiplied with the factors.
+    pub metallic_roughness_texture: Option<Image>,
+    /// Linear tangent space normal texture, with the green channel pointing up in the texture.
+    pub normal_texture: Option<Image>,
+    /// Scale of the normal texture's X and Y components.
+    pub normal_scale: f32,
+    /// Color of the light emitted by the surface.
+    pub emissive: Color,
+    /// sRGB texture multiplied with the emissive color.
+    pub emissive_texture: Option<Image>,
+    /// Linear texture with the ambient occlusion in its red channel.
+    pub occlusion_texture: Option<Image>,
+    /// How much the occlusion texture darkens ambient light, from 0 to 1.
+    pub occlusion_strength: f32,
+    /// How the alpha of the base color is used.
+    pub alpha_mode: AlphaMode,
+}
+
+impl StandardMaterial {
+    /// Returns the shader features used by the material.
+    pub(crate) fn features(&self) -> MaterialFeatures {
+        let alpha = MaterialFeatures::alpha(self.alpha_mode);
+        self.textures()
+            .into_iter()
+            .filter(|(_, texture)| texture.is_some())
+            .fold(alpha, |features, (feature, _)| features | feature)
+    }
+
+    /// Returns the material's textures with their features, in the order of their bindings.
+    fn textures(&self) -> [(MaterialFeatures, Option<&Image>); 5] {
+        [
+            (
+                MaterialFeatures::BASE_COLOR_TEXTURE,
+                self.base_color_texture.as_ref(),
+            ),
+            (
+                MaterialFeatures::METALLIC_ROUGHNESS_TEXTURE,
+                self.metallic_roughness_texture.as_ref(),
+            ),
+            (
+                MaterialFeatures::NORMAL_TEXTURE,
+                self.normal_texture.as_ref(),
+            ),
+            (
+                MaterialFeatures::EMISSIVE_TEXTURE,
+                self.emissive_texture.as_ref(),
+            ),
+            (
+                MaterialFeatures::OCCLUSION_TEXTURE,
+                self.occlusion_texture.as_ref(),
+            ),
+        ]
+    }
+}
+
+impl Default for StandardMaterial {
+    fn default() -> Self {
+        Self {
+            base_color: Color::WHITE,
+            base_color_texture: None,
+            metallic: 0.0,
+            roughness: 0.5,
+            metallic_roughness_texture: None,
+            normal_texture: None,
+            normal_scale: 1.0,
+            emissive: Color::BLACK,
+            emissive_texture: None,
+            occlusion_texture: None,
+            occlusion_strength: 1.0,
+            alpha_mode: AlphaMode::Opaque,
+        }
+    }
+}
+
+/// # Alpha Mode
+///
+/// How the alpha of a [StandardMaterial]'s base color or a [ShaderMaterial]'s color is used,
+/// following the glTF conventions.
+///
+/// ```
+/// # use pulse::render::material::AlphaMode;
+/// # use pulse::render::material::Material;
+/// # use pulse::render::material::StandardMaterial;
+/// # use pulse::render::Color;
+/// let glass = Material::new(StandardMaterial {
+///     base_color: Color::rgba(0.8, 0.9, 1.0, 0.2),
+///     roughness: 0.05,
+///     alpha_mode: AlphaMode::Blend,
+///     ..StandardMaterial::default()
+/// });
+/// ```
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum AlphaMode {
+    /// The alpha is ignored and the surface is fully opaque.
+    #[default]
+    Opaque,
+    /// The surface is fully opaque where the alpha is at least the cutoff and invisible
+    /// elsewhere, e.g. for foliage.
+    Mask(f32),
+    /// The surface is blended over the meshes behind it by its alpha, e.g. for glass. Blended
+    /// meshes don't write depth and are drawn after the opaque ones from back to front, sorted by
+    /// their origins, so intersecting meshes may blend in the wrong order.
+    Blend,
+}
+
+/// # Material
+///
+/// Material of the node's [crate::render::mesh::Mesh], either a [StandardMaterial] or a
+/// [ShaderMaterial]. Mesh nodes without a material are drawn with the default [StandardMaterial].
+/// The material is shared between clones, so the same material can be added to many nodes and is
+/// uploaded to the GPU once. Materials are equal if they share the same properties.
+///
+/// ```
+/// # use pulse::render::material::Material;
+/// # use pulse::render::material::StandardMaterial;
+/// # use pulse::render::mesh::Mesh;
+/// # use pulse::render::Color;
+/// # use pulse::Scene;
+/// let red = Material::new(StandardMaterial {
+///     base_color: Color::rgb(1.0, 0.0, 0.0),
+///     ..StandardMaterial::default()
+/// });
+///
+/// let mut scene = Scene::new();
+/// scene.spawn_with((Mesh::cube(1.0), red.clone()));
+/// scene.spawn_with((Mesh::sphere(0.5, 32, 16), red));
+/// ```
+#[derive(Clone, Debug, Component)]
+pub struct Material {
+    data: Arc<MaterialData>,
+}
+
+#[derive(Debug)]
+enum MaterialData {
+    Standard(StandardMaterial),
+    Shader(ShaderMaterial),
+}
+
+impl Material {
+    /// Returns the material with the standard material's properties.
+    pub fn new(standard: StandardMaterial) -> Self {
+        Self {
+            data: Arc::new(MaterialData::Standard(standard)),
+        }
+    }
+
+    /// Returns the material shaded by the shader material's shader.
+    pub fn custom(shader: ShaderMaterial) -> Self {
+        Self {
+            data: Arc::new(MaterialData::Shader(shader)),
+        }
+    }
+
+    /// Returns the standard material's properties, if it's one.
+    pub fn standard(&self) -> Option<&StandardMaterial> {
+        match &*self.data {
+            MaterialData::Standard(standard) => Some(standard),
+            MaterialData::Shader(_) => None,
+        }
+    }
+
+    /// Returns the shader material's properties, if it's one.
+    pub fn shader(&self) -> Option<&ShaderMaterial> {
+        match &*self.data {
+            MaterialData::Standard(_) => None,
+            MaterialData::Shader(shader) => Some(shader),
+        }
+    }
+
+    /// Returns how the alpha of the material's color is used.
+    pub fn alpha_mode(&self) -> AlphaMode {
+        match &*self.data {
+            MaterialData::Standard(standard) => standard.alpha_mode,
+            MaterialData::Shader(shader) => shader.alpha_mode,
+        }
+    }
+
+    /// Returns the shader features used by the material, only the alpha mode for shader
+    /// materials.
+    pub(crate) fn features(&self) -> MaterialFeatures {
+        match &*self.data {
+            MaterialData::Standard(standard) => standard.features(),
+            MaterialData::Shader(shader) => MaterialFeatures::alpha(shader.alpha_mode),
+        }
+    }
+
+    /// Returns an identifier for the material's properties, valid while the material exists.
+    pub(crate) fn id(&self) -> usize {
+        Arc::as_ptr(&self.data) as usize
+    }
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Self::new(StandardMaterial::default())
+    }
+}
+
+impl PartialEq for Material {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.data, &other.data)
+    }
+}
+
+impl From<StandardMaterial> for Material {
+    fn from(standard: StandardMaterial) -> Self {
+        Self::new(standard)
+    }
+}
+
+impl From<ShaderMaterial> for Material {
+    fn from(shader: ShaderMaterial) -> Self {
+        Self::custom(shader)
+    }
+}
+
+/// Writes the standard materials for the asset cache, with the textures they share written once,
+/// or returns `None` if one of them is a shader material.
+pub(crate) fn write_processed(writer: &mut ProcessedWriter, materials: &[Material]) -> Option<()> {
+    let standards = materials
+        .iter()
+        .map(Material::standard)
+        .collect::<Option<Vec<_>>>()?;
+    let mut images = Vec::<&Image>::new();
+    for (_, texture) in standards.iter().flat_map(|standard| standard.textures()) {
+        if let Some(texture) = texture.filter(|texture| !images.contains(texture)) {
+            images.push(texture);
+        }
+    }
+
+    writer.write(images.len() as u64);
+    for image in &images {
+        writer.write_slice(&image.to_processed());
+    }
+    writer.write(standards.len() as u64);
+    for standard in standards {
+        let properties = (
+            standard.base_color,
+            standard.metallic,
+            standard.roughness,
+            standard.normal_scale,
+            standard.emissive,
+            standard.occlusion_strength,
+            standard.alpha_mode,
+        );
+        writer.write_slice(ron::to_string(&properties).unwrap().as_bytes());
+        for (_, texture) in standard.textures() {
+            writer.write_index(
+                texture.and_then(|texture| images.iter().position(|image| *image == texture)),
+            );
+        }
+    }
+    Some(())
+}
+
+/// Reads the materials written by [write_processed], or returns `None` if the bytes are invalid.
+pub(crate) fn read_processed(reader: &mut ProcessedReader) -> Option<Vec<Material>> {
+    let images = (0..reader.read::<u64>()?)
+        .map(|_| Image::from_processed(&reader.read_slice::<u8>()?))
+        .collect::<Option<Vec<_>>>()?;
+    (0..reader.read::<u64>()?)
+        .map(|_| {
+            let (
+                base_color,
+                metallic,
+                roughness,
+                normal_scale,
+                emissive,
+                occlusion_strength,
+                alpha_mode,
+            ) = ron::de::from_bytes(&reader.read_slice::<u8>()?).ok()?;
+            // Textures are read in the order of their bindings, as written.
+            let mut texture = || match reader.read_index()? {
+                Some(index) => images.get(index).cloned().map(Some),
+                None => Some(None),
+            };
+            Some(Material::new(StandardMaterial {
+                base_color,
+                base_color_texture: texture()?,
+                metallic,
+                roughness,
+                metallic_roughness_texture: texture()?,
+                normal_texture: texture()?,
+                normal_scale,
+                emissive,
+                emissive_texture: texture()?,
+                occlusion_texture: texture()?,
+                occlusion_strength,
+                alpha_mode,
+            }))
+        })
+        .collect()
+}
+
+/// Set of optional shader features of a material, each compiled into the shader with a shader def
+/// of the same name.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash, PartialOrd, Ord)]
+pub(crate) struct MaterialFeatures(u32);
+
+impl MaterialFeatures {
+    pub(crate) const NONE: Self = Self(0);
+    pub(crate) const BASE_COLOR_TEXTURE: Self = Self(1 << 0);
+    pub(crate) const METALLIC_ROUGHNESS_TEXTURE: Self = Self(1 << 1);
+    pub(crate) const NORMAL_TEXTURE: Self = Self(1 << 2);
+    pub(crate) const EMISSIVE_TEXTURE: Self = Self(1 << 3);
+    pub(crate) const OCCLUSION_TEXTURE: Self = Self(1 << 4);
+    pub(crate) const ALPHA_MASK: Self = Self(1 << 5);
+    pub(crate) const ALPHA_BLEND: Self = Self(1 << 6);
+
+    /// Features of the textures, in the order of their bindings.
+    const TEXTURES: [Self; 5] = [
+        Self::BASE_COLOR_TEXTURE,
+        Self::METALLIC_ROUGHNESS_TEXTURE,
+        Self::NORMAL_TEXTURE,
+        Self::EMISSIVE_TEXTURE,
+        Self::OCCLUSION_TEXTURE,
+    ];
+
+    const DEFS: [(Self, &'static str); 7] = [
+        (Self::BASE_COLOR_TEXTURE, "BASE_COLOR_TEXTURE"),
+        (
+            Self::METALLIC_ROUGHNESS_TEXTURE,
+            "METALLIC_ROUGHNESS_TEXTURE",
+        ),
+        (Self::NORMAL_TEXTURE, "NORMAL_TEXTURE"),
+        (Self::EMISSIVE_TEXTURE, "EMISSIVE_TEXTURE"),
+        (Self::OCCLUSION_TEXTURE, "OCCLUSION_TEXTURE"),
+        (Self::ALPHA_MASK, "ALPHA_MASK"),
+        (Self::ALPHA_BLEND, "ALPHA_BLEND"),
+    ];
+
+    /// Returns the feature of the alpha mode.
+    pub(crate) fn alpha(alpha_mode: AlphaMode) -> Self {
+        match alpha_mode {
+            AlphaMode::Opaque => Self::NONE,
+            AlphaMode::Mask(_) => Self::ALPHA_MASK,
+            AlphaMode::Blend => Self::ALPHA_BLEND,
+        }
+    }
+
+    /// Returns every combination of features, with at most one alpha mode.
+    #[cfg(test)]
+    pub(crate) fn all() -> impl Iterator<Item = Self> {
+        (0..1 << Self::DEFS.len())
+            .map(Self)
+            .filter(|features| !features.contains(Self::ALPHA_MASK | Self::ALPHA_BLEND))
+    }
+
+    /// Returns the features without the alpha mode, which don't change the bindings.
+    pub(crate) fn textures(self) -> Self {
+        Self::TEXTURES
+            .into_iter()
+            .filter(|feature| self.contains(*feature))
+            .fold(Self::NONE, |features, feature| features | feature)
+    }
+
+    /// Returns whether all the other features are in the set.
+    pub(crate) fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Returns the shader defs of the features.
+    pub(crate) fn defs(self) -> Vec<&'static str> {
+        Self::DEFS
+            .iter()
+            .filter(|(feature, _)| self.contains(*feature))
+            .map(|(_, def)| *def)
+            .collect()
+    }
+}
+
+impl std::ops::BitOr for MaterialFeatures {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+/// Uniforms of a material in the shader.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+struct MaterialUniform {
+    base_color: [f32; 4],
+    emissive: [f32; 4],
+    metallic: f32,
+    roughness: f32,
+    normal_scale: f32,
+    occlusion_strength: f32,
+    alpha_cutoff: f32,
+    _padding: [f32; 3],
+}
+
+impl MaterialUniform {
+    fn new(material: &StandardMaterial) -> Self {
+        Self {
+            base_color: material.base_color.to_vec4().to_array(),
+            emissive: material.emissive.to_vec4().to_array(),
+            metallic: material.metallic.clamp(0.0, 1.0),
+            roughness: material.roughness.clamp(0.0, 1.0),
+            normal_scale: material.normal_scale,
+            occlusion_strength: material.occlusion_strength.clamp(0.0, 1.0),
+            alpha_cutoff: match material.alpha_mode {
+                AlphaMode::Mask(cutoff) => cutoff,
+                AlphaMode::Opaque | AlphaMode::Blend => 0.0,
+            },
+            _padding: [0.0; 3],
+        }
+    }
+}
+
+/// Shader of a [ShaderMaterial] with the bindings of the material, identified by the shader's id
+/// and [ShaderMaterial::layout_hash].
+pub(crate) type ShaderKey = (usize, u64);
+
+/// Bind group of a [Material] uploaded to the GPU.
+pub(crate) struct GpuMaterial {
+    pub(crate) features: MaterialFeatures,
+    /// Shader of a shader material.
+    pub(crate) shader: Option<ShaderKey>,
+    pub(crate) bind_group: wgpu::BindGroup,
+}
+
+/// GPU bind groups of the materials drawn so far, keyed by the material's properties, and the bind
+/// group layouts of each combination of features.
+///
+/// A material's uniform is at binding 0 and each texture of the material and its sampler at the
+/// next two bindings in the order of [StandardMaterial::textures], whether the material has the
+/// previous textures or not, so the bindings in the shader don't depend on the features. Shader
+/// materials bind their textures in the order of [ShaderMaterial::textures] the same way, and
+/// their layouts are keyed by the size of their uniforms and their number of textures.
+pub(crate) struct GpuMaterials {
+    sampler: wgpu::Sampler,
+    /// Texture of shader material textures without an image.
+    white: Image,
+    layouts: HashMap<MaterialFeatures, wgpu::BindGroupLayout>,
+    shader_layouts: HashMap<(u64, usize), wgpu::BindGroupLayout>,
+    materials: HashMap<usize, (Weak<MaterialData>, GpuMaterial)>,
+}
+
+impl GpuMaterials {
+    pub(crate) fn new(device: &wgpu::Device) -> Self {
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("material"),
+            address_mode_u: wgpu::AddressMode::Repeat,
+            address_mode_v: wgpu::AddressMode::Repeat,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Linear,
+            ..wgpu::SamplerDescriptor::default()
+        });
+
+        Self {
+            sampler,
+            white: Image::from_pixel([255; 4], ColorSpace::Linear),
+            layouts: HashMap::new(),
+            shader_layouts: HashMap::new(),
+            materials: HashMap::new(),
+        }
+    }
+
+    /// Returns the bind group layout of materials with the features, creating it if it doesn't
+    /// exist yet.
+    pub(crate) fn layout(
+        &mut self,
+        device: &wgpu::Device,
+        features: MaterialFeatures,
+    ) -> &wgpu::BindGroupLayout {
+        self.layouts
+            .entry(features.textures())
+            .or_insert_with(|| create_layout(device, features))
+    }
+
+    /// Returns the bind group layout of the shader material, creating it if it doesn't exist yet.
+    pub(crate) fn shader_layout(
+        &mut self,
+        device: &wgpu::Device,
+        material: &ShaderMaterial,
+    ) -> &wgpu::BindGroupLayout {
+        let uniform_size = material
+            .uniform_bytes()
+            .map_or(0, |bytes| bytes.len() as u64);
+        let textures = material.textures.len();
+        self.shader_layouts
+            .entry((uniform_size, textures))
+            .or_insert_with(|| create_shader_layout(device, uniform_size, textures))
+    }
+
+    /// Uploads the material and its textures if it wasn't uploaded yet.
+    pub(crate) fn upload(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        images: &mut GpuImages,
+        material: &Material,
+    ) {
+        if let Some((data, _)) = self.materials.get(&material.id()) {
+            // The id of a dropped material may have been reused by a new one.
+            if data.strong_count() > 0 {
+                return;
+            }
+        }
+
+        let gpu_material = match &*material.data {
+            MaterialData::Standard(standard) => {
+                self.upload_standard(device, queue, images, standard)
+            }
+            MaterialData::Shader(shader) => self.upload_shader(device, queue, images, shader),
+        };
+        self.materials.insert(
+            material.id(),
+            (Arc::downgrade(&material.data), gpu_material),
+        );
+    }
+
+    fn upload_standard(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        images: &mut GpuImages,
+        standard: &StandardMaterial,
+    ) -> GpuMaterial {
+        for (_, image) in standard.textures() {
+            if let Some(image) = image {
+                images.upload(device, queue, image);
+            }
+        }
+
+        let features = standard.features();
+        let uniform = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("material"),
+            contents: bytemuck::bytes_of(&MaterialUniform::new(standard)),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+        let mut entries = vec![wgpu::BindGroupEntry {
+            binding: 0,
+            resource: uniform.as_entire_binding(),
+        }];
+        for (binding, (_, image)) in (1..).step_by(2).zip(standard.textures()) {
+            let Some((image, view)) = image.and_then(|image| Some((image, images.get(image)?)))
+            else {
+                continue;
+            };
+            entries.push(wgpu::BindGroupEntry {
+                binding,
+                resource: wgpu::BindingResource::TextureView(view),
+            });
+            entries.push(wgpu::BindGroupEntry {
+                binding: binding + 1,
+                resource: wgpu::BindingResource::Sampler(
+                    images.sampler(image).unwrap_or(&self.sampler),
+                ),
+            });
+        }
+        let layout = self
+            .layouts
+            .entry(features.textures())
+            .or_insert_with(|| create_layout(device, features));
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("material"),
+            layout,
+            entries: &entries,
+        });
+
+        GpuMaterial {
+            features,
+            shader: None,
+            bind_group,
+        }
+    }
+
+    fn upload_shader(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        images: &mut GpuImages,
+        material: &ShaderMaterial,
+    ) -> GpuMaterial {
+        self.shader_layout(device, material);
+        let textures = material
+            .textures
+            .iter()
+            .map(|(_, image)| image.as_ref().unwrap_or(&self.white))
+            .collect::<Vec<_>>();
+        for image in &textures {
+            images.upload(device, queue, image);
+        }
+
+        let uniform = material.uniform_bytes().map(|bytes| {
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("shader material"),
+                contents: &bytes,
+                usage: wgpu::BufferUsages::UNIFORM,
+            })
+        });
+        let mut entries = Vec::new();
+        if let Some(uniform) = &uniform {
+            entries.push(wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform.as_entire_binding(),
+            });
+        }
+        for (binding, image) in (1..).step_by(2).zip(textures) {
+            entries.push(wgpu::BindGroupEntry {
+                binding,
+                resource: wgpu::BindingResource::TextureView(images.get(image).unwrap()),
+            });
+            entries.push(wgpu::BindGroupEntry {
+                binding: binding + 1,
+                resource: wgpu::BindingResource::Sampler(
+                    images.sampler(image).unwrap_or(&self.sampler),
+                ),
+            });
+        }
+        let uniform_size = uniform.as_ref().map_or(0, wgpu::Buffer::size);
+        let layout = &self.shader_layouts[&(uniform_size, material.textures.len())];
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("shader material"),
+            layout,
+            entries: &entries,
+        });
+
+        GpuMaterial {
+            features: MaterialFeatures::alpha(material.alpha_mode),
+            shader: Some((material.shader.id(), material.layout_hash())),
+            bind_group,
+        }
+    }
+
+    /// Returns the bind group of the material if it was uploaded.
+    pub(crate) fn get(&self, material: &Material) -> Option<&GpuMaterial> {
+        self.materials
+            .get(&material.id())
+            .map(|(_, gpu_material)| gpu_material)
+    }
+
+    /// Drops the bind groups of materials that no longer exist.
+    pub(crate) fn collect_garbage(&mut self) {
+        self.materials
+            .retain(|_, (data, _)| data.strong_count() > 0);
+    }
+}
+
+fn create_layout(device: &wgpu::Device, features: MaterialFeatures) -> wgpu::BindGroupLayout {
+    let mut entries = vec![wgpu::BindGroupLayoutEntry {
+        binding: 0,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: wgpu::BufferSize::new(std::mem::size_of::<MaterialUniform>() as u64),
+        },
+        count: None,
+    }];
+    for (binding, feature) in (1..).step_by(2).zip(MaterialFeatures::TEXTURES) {
+        if !features.contains(feature) {
+            continue;
+        }
+        entries.push(wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        });
+        entries.push(wgpu::BindGroupLayoutEntry {
+            binding: binding + 1,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+            count: None,
+        });
+    }
+
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some("material"),
+        entries: &entries,
+    })
+}
+
+fn create_shader_layout(
+    device: &wgpu::Device,
+    uniform_size: u64,
+    textures: usize,
+) -> wgpu::BindGroupLayout {
+    let mut entries = Vec::new();
+    if uniform_size > 0 {
+        entries.push(wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: wgpu::BufferSize::new(uniform_size),
+            },
+            count: None,
+        });
+    }
+    for binding in (1..).step_by(2).take(textures) {
+        entries.push(wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        });
+        entries.push(wgpu::BindGroupLayoutEntry {
+            binding: binding + 1,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+            count: None,
+        });
+    }
+
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some("shader material"),
+        entries: &entries,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::image::ColorSpace;
+    use crate::render::shader_material::MaterialShader;
+
+    #[test]
+    fn features_of_textures() {
+        let image = Image::from_pixel([255; 4], ColorSpace::Linear);
+        let material = StandardMaterial {
+            normal_texture: Some(image.clone()),
+            occlusion_texture: Some(image),
+            ..StandardMaterial::default()
+        };
+
+        let features = material.features();
+
+        assert_eq!(
+            features,
+            MaterialFeatures::NORMAL_TEXTURE | MaterialFeatures::OCCLUSION_TEXTURE
+        );
+        assert_eq!(features.defs(), ["NORMAL_TEXTURE", "OCCLUSION_TEXTURE"]);
+        assert_eq!(
+            StandardMaterial::default().features(),
+            MaterialFeatures::NONE
+        );
+    }
+
+    #[test]
+    fn features_of_alpha_modes() {
+        let image = Image::from_pixel([255; 4], ColorSpace::Srgb);
+        let material = StandardMaterial {
+            base_color_texture: Some(image),
+            alpha_mode: AlphaMode::Mask(0.5),
+            ..StandardMaterial::default()
+        };
+
+        let features = material.features();
+
+        assert_eq!(
+            features,
+            MaterialFeatures::BASE_COLOR_TEXTURE | MaterialFeatures::ALPHA_MASK
+        );
+        assert_eq!(features.textures(), MaterialFeatures::BASE_COLOR_TEXTURE);
+        assert_eq!(
+            StandardMaterial {
+                alpha_mode: AlphaMode::Blend,
+                ..StandardMaterial::default()
+            }
+            .features()
+            .defs(),
+            ["ALPHA_BLEND"]
+        );
+    }
+
+    #[test]
+    fn processed_materials_share_textures() {
+        let image = Image::from_pixel([255, 0, 0, 255], ColorSpace::Linear);
+        let materials = [
+            Material::new(StandardMaterial {
+                normal_texture: Some(image.clone()),
+                occlusion_texture: Some(image.clone()),
+                alpha_mode: AlphaMode::Mask(0.25),
+                ..StandardMaterial::default()
+            }),
+            Material::new(StandardMaterial {
+                metallic_roughness_texture: Some(image),
+                metallic: 1.0,
+                ..StandardMaterial::default()
+            }),
+        ];
+        let mut writer = ProcessedWriter::new();
+        write_processed(&mut writer, &materials).unwrap();
+        let bytes = writer.into_bytes();
+
+        let processed = read_processed(&mut ProcessedReader::new(&bytes)).unwrap();
+        let [first, second] = [0, 1].map(|index| processed[index].standard().unwrap());
+        assert_eq!(first.alpha_mode, AlphaMode::Mask(0.25));
+        assert_eq!(second.metallic, 1.0);
+        assert!(first.base_color_texture.is_none());
+        let normal = first.normal_texture.as_ref().unwrap();
+        assert_eq!(normal.pixel(0, 0), [255, 0, 0, 255]);
+        assert_eq!(first.occlusion_texture.as_ref(), Some(normal));
+        assert_eq!(second.metallic_roughness_texture.as_ref(), Some(normal));
+
+        let shader = [Material::custom(ShaderMaterial::new(MaterialShader::new(
+            "",
+        )))];
+        assert!(write_processed(&mut ProcessedWriter::new(), &shader).is_none());
+        assert!(read_processed(&mut ProcessedReader::new(&bytes[..bytes.len() - 1])).is_none());
+    }
+}
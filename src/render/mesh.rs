@@ -0,0 +1,887 @@
+//! # Mesh
+//!
+//! Triangle meshes drawn by the renderer, and built-in primitives for prototyping.
+
+use std::collections::HashMap;
+use std::f32::consts::FRAC_PI_2;
+use std::f32::consts::PI;
+use std::f32::consts::TAU;
+use std::fmt;
+use std::sync::Arc;
+use std::sync::Weak;
+
+use bytemuck::Pod;
+use bytemuck::Zeroable;
+use glam::Mat3;
+use glam::Vec2;
+use glam::Vec3;
+use glam::Vec4;
+use wgpu::util::DeviceExt;
+
+use crate::assets::cache::ProcessedReader;
+use crate::assets::cache::ProcessedWriter;
+use crate::render::graph::GpuResources;
+use crate::render::morph::MORPH_SHADER;
+use crate::render::morph::NO_MORPH;
+use crate::render::skin::NO_SKIN;
+use crate::render::skin::SKIN_SHADER;
+use crate::Aabb;
+use crate::Component;
+use crate::Node;
+use crate::WorldTransform;
+
+/// # Mesh Data
+///
+/// Vertex attributes and triangle indices of a [Mesh]. Triangles are wound counter-clockwise when
+/// seen from the front.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MeshData {
+    /// Vertex positions.
+    pub positions: Vec<Vec3>,
+    /// Unit vertex normals, one for each position.
+    pub normals: Vec<Vec3>,
+    /// Unit vertex tangents pointing towards increasing `u`, one for each position, or none to
+    /// compute them from the normals and texture coordinates. `w` is the handedness of the
+    /// bitangent `normal.cross(tangent) * w`, which points towards the top of the texture at `v`
+    /// zero.
+    pub tangents: Vec<Vec4>,
+    /// Vertex texture coordinates, one for each position.
+    pub uvs: Vec<Vec2>,
+    /// Indices of the four joints moving each vertex in the [crate::render::skin::Skeleton] of a
+    /// [crate::render::skin::SkinnedMesh], one for each position, or none if the mesh isn't
+    /// skinned.
+    pub joints: Vec<[u16; 4]>,
+    /// Weights of the four joints of each vertex summing to one, one for each position, or none if
+    /// the mesh isn't skinned.
+    pub weights: Vec<Vec4>,
+    /// Blend shapes offsetting the vertices by the node's [crate::render::morph::MorphWeights].
+    pub morph_targets: Vec<MorphTarget>,
+    /// Vertex indices, three for each triangle.
+    pub indices: Vec<u32>,
+}
+
+/// # Morph Target
+///
+/// Offsets of the vertices of a [MeshData] at the full weight of a blend shape.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MorphTarget {
+    /// Position offsets, one for each position.
+    pub positions: Vec<Vec3>,
+    /// Normal offsets, one for each position, or none if the target doesn't change the normals.
+    pub normals: Vec<Vec3>,
+}
+
+impl MeshData {
+    /// Returns the number of vertices.
+    pub fn vertex_count(&self) -> usize {
+        self.positions.len()
+    }
+
+    /// Returns the number of triangles.
+    pub fn triangle_count(&self) -> usize {
+        self.indices.len() / 3
+    }
+
+    /// Returns the indices of the pairs of vertices of each edge of the triangles, for drawing the
+    /// mesh as lines. Edges shared by triangles are returned once.
+    pub(crate) fn edges(&self) -> Vec<u32> {
+        let mut edges = self
+            .indices
+            .chunks_exact(3)
+            .flat_map(|triangle| {
+                [(0, 1), (1, 2), (2, 0)].map(|(a, b)| {
+                    let (a, b) = (triangle[a], triangle[b]);
+                    (a.min(b), a.max(b))
+                })
+            })
+            .collect::<Vec<_>>();
+        edges.sort_unstable();
+        edges.dedup();
+        edges.into_iter().flat_map(|(a, b)| [a, b]).collect()
+    }
+
+    /// Returns the vertex attributes and indices packed for the asset cache.
+    pub(crate) fn to_processed(&self) -> Vec<u8> {
+        let mut writer = ProcessedWriter::new();
+        writer.write_slice(&self.positions);
+        writer.write_slice(&self.normals);
+        writer.write_slice(&self.tangents);
+        writer.write_slice(&self.uvs);
+        writer.write_slice(&self.joints);
+        writer.write_slice(&self.weights);
+        writer.write(self.morph_targets.len() as u64);
+        for target in &self.morph_targets {
+            writer.write_slice(&target.positions);
+            writer.write_slice(&target.normals);
+        }
+        writer.write_slice(&self.indices);
+        writer.into_bytes()
+    }
+
+    /// Returns the data packed by [MeshData::to_processed], or `None` if the bytes are invalid.
+    pub(crate) fn from_processed(bytes: &[u8]) -> Option<Self> {
+        let mut reader = ProcessedReader::new(bytes);
+        Some(Self {
+            positions: reader.read_slice()?,
+            normals: reader.read_slice()?,
+            tangents: reader.read_slice()?,
+            uvs: reader.read_slice()?,
+            joints: reader.read_slice()?,
+            weights: reader.read_slice()?,
+            morph_targets: (0..reader.read::<u64>()?)
+                .map(|_| {
+                    Some(MorphTarget {
+                        positions: reader.read_slice()?,
+                        normals: reader.read_slice()?,
+                    })
+                })
+                .collect::<Option<_>>()?,
+            indices: reader.read_slice()?,
+        })
+    }
+
+    /// Returns an error if an attribute or morph target doesn't have a value for each position, an
+    /// index is out of bounds, or the indices don't form whole triangles.
+    pub fn validate(&self) -> Result<(), MeshError> {
+        let vertex_count = self.vertex_count();
+        let lengths = [
+            ("normals", self.normals.len()),
+            ("uvs", self.uvs.len()),
+            ("tangents", self.tangents.len()),
+            ("joints", self.joints.len()),
+            ("weights", self.weights.len()),
+        ];
+        let targets = self.morph_targets.iter().flat_map(|target| {
+            [
+                ("morph target positions", target.positions.len()),
+                ("morph target normals", target.normals.len()),
+            ]
+        });
+        for (attribute, len) in lengths.into_iter().chain(targets) {
+            let optional = matches!(
+                attribute,
+                "tangents" | "joints" | "weights" | "morph target normals"
+            );
+            if len != vertex_count && !(optional && len == 0) {
+                return Err(MeshError::AttributeLength {
+                    attribute,
+                    len,
+                    vertex_count,
+                });
+            }
+        }
+
+        // Skinned meshes need both joints and weights.
+        if self.joints.len() != self.weights.len() {
+            let attribute = if self.joints.is_empty() {
+                "joints"
+            } else {
+                "weights"
+            };
+            return Err(MeshError::AttributeLength {
+                attribute,
+                len: 0,
+                vertex_count,
+            });
+        }
+
+        if !self.indices.len().is_multiple_of(3) {
+            return Err(MeshError::IncompleteTriangle {
+                index_count: self.indices.len(),
+            });
+        }
+        if let Some(&index) = self
+            .indices
+            .iter()
+            .find(|index| **index as usize >= vertex_count)
+        {
+            return Err(MeshError::IndexOutOfBounds {
+                index,
+                vertex_count,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Computes smooth normals from the triangles, replacing any existing normals. Each vertex gets
+    /// the average of the normals of its triangles weighted by their areas, and vertices without
+    /// triangles point up.
+    pub fn compute_normals(&mut self) {
+        let mut normals = vec![Vec3::ZERO; self.vertex_count()];
+        for triangle in self.indices.chunks_exact(3) {
+            let [a, b, c] = [0, 1, 2].map(|i| triangle[i] as usize);
+            let normal = (self.positions[b] - self.positions[a])
+                .cross(self.positions[c] - self.positions[a]);
+            for vertex in [a, b, c] {
+                normals[vertex] += normal;
+            }
+        }
+
+        self.normals = normals
+            .into_iter()
+            .map(|normal| normal.try_normalize().unwrap_or(Vec3::Y))
+            .collect();
+    }
+
+    /// Computes the tangents from the normals and texture coordinates, replacing any existing
+    /// tangents. Vertices whose triangles have degenerate texture coordinates get an arbitrary
+    /// tangent perpendicular to the normal.
+    pub fn compute_tangents(&mut self) {
+        let mut tangents = vec![Vec3::ZERO; self.vertex_count()];
+        let mut bitangents = vec![Vec3::ZERO; self.vertex_count()];
+        for triangle in self.indices.chunks_exact(3) {
+            let [a, b, c] = [0, 1, 2].map(|i| triangle[i] as usize);
+            let edge1 = self.positions[b] - self.positions[a];
+            let edge2 = self.positions[c] - self.positions[a];
+            let delta1 = self.uvs[b] - self.uvs[a];
+            let delta2 = self.uvs[c] - self.uvs[a];
+            let determinant = delta1.x * delta2.y - delta2.x * delta1.y;
+            if determinant.abs() <= f32::EPSILON {
+                continue;
+            }
+
+            let tangent = (edge1 * delta2.y - edge2 * delta1.y) / determinant;
+            let bitangent = (edge2 * delta1.x - edge1 * delta2.x) / determinant;
+            for vertex in [a, b, c] {
+                tangents[vertex] += tangent;
+                bitangents[vertex] += bitangent;
+            }
+        }
+
+        self.tangents = self
+            .normals
+            .iter()
+            .zip(tangents.iter().zip(&bitangents))
+            .map(|(normal, (tangent, bitangent))| {
+                let tangent = (*tangent - *normal * normal.dot(*tangent))
+                    .try_normalize()
+                    .unwrap_or_else(|| normal.any_orthonormal_vector());
+                // The bitangent points towards decreasing `v`, the top of the texture.
+                let handedness = if normal.cross(tangent).dot(*bitangent) > 0.0 {
+                    -1.0
+                } else {
+                    1.0
+                };
+                tangent.extend(handedness)
+            })
+            .collect();
+    }
+}
+
+/// # Mesh Error
+///
+/// Error returned when [MeshData] is invalid.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum MeshError {
+    /// A vertex attribute doesn't have a value for each position.
+    AttributeLength {
+        /// Name of the attribute.
+        attribute: &'static str,
+        /// Number of values of the attribute.
+        len: usize,
+        /// Number of positions.
+        vertex_count: usize,
+    },
+    /// An index refers to a vertex that doesn't exist.
+    IndexOutOfBounds {
+        /// Index of the vertex.
+        index: u32,
+        /// Number of vertices.
+        vertex_count: usize,
+    },
+    /// The number of indices isn't a multiple of three.
+    IncompleteTriangle {
+        /// Number of indices.
+        index_count: usize,
+    },
+}
+
+impl fmt::Display for MeshError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::AttributeLength {
+                attribute,
+                len,
+                vertex_count,
+            } => write!(f, "mesh has {len} {attribute} for {vertex_count} vertices"),
+            Self::IndexOutOfBounds {
+                index,
+                vertex_count,
+            } => write!(f, "index {index} out of bounds for {vertex_count} vertices"),
+            Self::IncompleteTriangle { index_count } => {
+                write!(f, "{index_count} indices don't form whole triangles")
+            }
+        }
+    }
+}
+
+impl std::error::Error for MeshError {}
+
+/// # Mesh
+///
+/// Triangle mesh drawn at the node's [crate::WorldTransform] when the node is visible to a camera.
+/// The data is shared between clones of the mesh, so the same mesh can be added to many nodes and
+/// is uploaded to the GPU once. Meshes are equal if they share the same data.
+///
+/// The node's [Aabb] is computed from the mesh by [crate::systems::compute_mesh_bounds].
+///
+/// ```
+/// # use pulse::render::mesh::Mesh;
+/// # use pulse::Scene;
+/// let cube = Mesh::cube(1.0);
+///
+/// let mut scene = Scene::new();
+/// scene.spawn_with(cube.clone());
+/// scene.spawn_with(cube);
+/// ```
+#[derive(Clone, Debug, Component)]
+pub struct Mesh {
+    data: Arc<MeshData>,
+    aabb: Option<Aabb>,
+}
+
+impl Mesh {
+    /// Returns the mesh for the data, computing the tangents if it has none. The bounds of the mesh
+    /// cover each of its morph targets at full weight.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the data is invalid. See [MeshData::validate].
+    pub fn new(mut data: MeshData) -> Result<Self, MeshError> {
+        data.validate()?;
+        if data.tangents.is_empty() {
+            data.compute_tangents();
+        }
+
+        let morphed = data.morph_targets.iter().flat_map(|target| {
+            data.positions
+                .iter()
+                .zip(&target.positions)
+                .map(|(position, offset)| *position + *offset)
+        });
+        Ok(Self {
+            aabb: Aabb::from_points(data.positions.iter().copied().chain(morphed)),
+            data: Arc::new(data),
+        })
+    }
+
+    /// Returns the mesh's vertex attributes and indices.
+    pub fn data(&self) -> &MeshData {
+        &self.data
+    }
+
+    /// Returns the bounding box of the mesh's positions, or `None` if it has no vertices.
+    pub fn aabb(&self) -> Option<Aabb> {
+        self.aabb
+    }
+
+    /// Returns a cube centered on the origin with the given edge length.
+    pub fn cube(size: f32) -> Self {
+        let half = size * 0.5;
+        let faces = [
+            (Vec3::X, Vec3::NEG_Z, Vec3::Y),
+            (Vec3::NEG_X, Vec3::Z, Vec3::Y),
+            (Vec3::Y, Vec3::X, Vec3::NEG_Z),
+            (Vec3::NEG_Y, Vec3::X, Vec3::Z),
+            (Vec3::Z, Vec3::X, Vec3::Y),
+            (Vec3::NEG_Z, Vec3::NEG_X, Vec3::Y),
+        ];
+
+        let mut data = MeshData::default();
+        for (normal, right, up) in faces {
+            let base = data.positions.len() as u32;
+            let corners = [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)];
+            for (x, y) in corners {
+                data.positions.push((normal + right * x + up * y) * half);
+                data.normals.push(normal);
+                data.uvs.push(Vec2::new(x + 1.0, 1.0 - y) * 0.5);
+            }
+            data.indices
+                .extend([0, 1, 2, 0, 2, 3].map(|index| base + index));
+        }
+
+        Self::primitive(data)
+    }
+
+    /// Returns a square plane centered on the origin facing up the Y axis with the given edge
+    /// length.
+    pub fn plane(size: f32) -> Self {
+        let half = size * 0.5;
+        let data = MeshData {
+            positions: vec![
+                Vec3::new(-half, 0.0, half),
+                Vec3::new(half, 0.0, half),
+                Vec3::new(half, 0.0, -half),
+                Vec3::new(-half, 0.0, -half),
+            ],
+            normals: vec![Vec3::Y; 4],
+            tangents: Vec::new(),
+            uvs: vec![
+                Vec2::new(0.0, 1.0),
+                Vec2::new(1.0, 1.0),
+                Vec2::new(1.0, 0.0),
+                Vec2::new(0.0, 0.0),
+            ],
+            indices: vec![0, 1, 2, 0, 2, 3],
+            ..MeshData::default()
+        };
+
+        Self::primitive(data)
+    }
+
+    /// Returns a sphere centered on the origin with the given number of segments around the Y
+    /// axis and from pole to pole.
+    pub fn sphere(radius: f32, sectors: u32, stacks: u32) -> Self {
+        let rings = (0..=stacks).map(|stack| (PI * stack as f32 / stacks as f32, 0.0));
+        Self::primitive(revolve(radius, sectors, rings))
+    }
+
+    /// Returns a capsule centered on the origin along the Y axis, a cylinder of the given length
+    /// capped by hemispheres, with the given number of segments around the Y axis and from the
+    /// pole to the equator of each hemisphere.
+    pub fn capsule(radius: f32, length: f32, sectors: u32, rings: u32) -> Self {
+        let half = length * 0.5;
+        let angle = |ring: u32| FRAC_PI_2 * ring as f32 / rings as f32;
+        let top = (0..=rings).map(|ring| (angle(ring), half));
+        let bottom = (0..=rings).map(|ring| (FRAC_PI_2 + angle(ring), -half));
+        Self::primitive(revolve(radius, sectors, top.chain(bottom)))
+    }
+
+    fn primitive(data: MeshData) -> Self {
+        Self::new(data).expect("primitive mesh must be valid")
+    }
+
+    /// Returns an identifier for the mesh's data, valid while the mesh exists.
+    pub(crate) fn id(&self) -> usize {
+        Arc::as_ptr(&self.data) as usize
+    }
+
+    /// Returns a weak reference to the mesh's data, for caches keyed by [Mesh::id].
+    pub(crate) fn downgrade(&self) -> Weak<MeshData> {
+        Arc::downgrade(&self.data)
+    }
+}
+
+impl PartialEq for Mesh {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.data, &other.data)
+    }
+}
+
+impl TryFrom<MeshData> for Mesh {
+    type Error = MeshError;
+
+    fn try_from(data: MeshData) -> Result<Self, Self::Error> {
+        Self::new(data)
+    }
+}
+
+/// Returns the mesh of rings of vertices around the Y axis, each given by its angle from the Y
+/// axis and its offset along the Y axis, from top to bottom.
+fn revolve(radius: f32, sectors: u32, rings: impl Iterator<Item = (f32, f32)>) -> MeshData {
+    let rings = rings.collect::<Vec<_>>();
+    let top = rings.first().map_or(0.0, |(_, offset)| offset + radius);
+    let height = top - rings.last().map_or(0.0, |(_, offset)| offset - radius);
+
+    let mut data = MeshData::default();
+    for (angle, offset) in &rings {
+        for sector in 0..=sectors {
+            let theta = TAU * sector as f32 / sectors as f32;
+            let normal = Vec3::new(
+                angle.sin() * theta.sin(),
+                angle.cos(),
+                angle.sin() * theta.cos(),
+            );
+            let position = normal * radius + Vec3::Y * *offset;
+            data.positions.push(position);
+            data.normals.push(normal);
+            data.uvs.push(Vec2::new(
+                sector as f32 / sectors as f32,
+                (top - position.y) / height,
+            ));
+        }
+    }
+
+    let row = sectors + 1;
+    let last = rings.len() as u32 - 1;
+    for ring in 0..last {
+        for sector in 0..sectors {
+            let a = ring * row + sector;
+            let b = a + row;
+            if ring != 0 {
+                data.indices.extend([a, b, a + 1]);
+            }
+            if ring != last - 1 {
+                data.indices.extend([a + 1, b, b + 1]);
+            }
+        }
+    }
+
+    data
+}
+
+/// Vertex of a [Mesh] in a GPU vertex buffer.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+pub(crate) struct Vertex {
+    position: [f32; 3],
+    normal: [f32; 3],
+    tangent: [f32; 4],
+    uv: [f32; 2],
+    joints: [u16; 4],
+    weights: [f32; 4],
+}
+
+impl Vertex {
+    // The joints and weights follow the locations of the instance attributes, which came first.
+    const ATTRIBUTES: [wgpu::VertexAttribute; 6] = wgpu::vertex_attr_array![
+        0 => Float32x3,
+        1 => Float32x3,
+        2 => Float32x4,
+        3 => Float32x2,
+        12 => Uint16x4,
+        13 => Float32x4,
+    ];
+
+    /// Returns the layout of the vertex buffers of [GpuMesh]es.
+    pub(crate) fn layout() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Self>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+}
+
+/// Per-instance vertex data of a mesh node in the shaders, its transforms, whether it receives
+/// shadows, the index of its first joint matrix in the joint palettes if it's skinned, and the
+/// index of its morph weights if it's morphed.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+pub(crate) struct Instance {
+    model: [[f32; 4]; 4],
+    normal: [[f32; 3]; 3],
+    shadow_receiver: f32,
+    skin: u32,
+    morph: u32,
+}
+
+impl Instance {
+    const ATTRIBUTES: [wgpu::VertexAttribute; 10] = wgpu::vertex_attr_array![
+        4 => Float32x4,
+        5 => Float32x4,
+        6 => Float32x4,
+        7 => Float32x4,
+        8 => Float32x3,
+        9 => Float32x3,
+        10 => Float32x3,
+        11 => Float32,
+        14 => Uint32,
+        15 => Uint32,
+    ];
+
+    /// Returns the instance of the node with the transform, or `None` if the transform can't be
+    /// inverted for transforming normals, e.g. because it has a zero scale.
+    pub(crate) fn new(transform: &WorldTransform, shadow_receiver: bool) -> Option<Self> {
+        let normal = Mat3::from_mat4(transform.matrix);
+        if normal.determinant().abs() <= f32::EPSILON {
+            return None;
+        }
+
+        Some(Self {
+            model: transform.matrix.to_cols_array_2d(),
+            normal: normal.inverse().transpose().to_cols_array_2d(),
+            shadow_receiver: if shadow_receiver { 1.0 } else { 0.0 },
+            skin: NO_SKIN,
+            morph: NO_MORPH,
+        })
+    }
+
+    /// Returns the instance deformed by the joint palette and morph weights of the node, if any.
+    pub(crate) fn deformed(self, resources: &GpuResources, node: Node) -> Self {
+        Self {
+            skin: resources.skins.offset(node).unwrap_or(NO_SKIN),
+            morph: resources.morphs.offset(node).unwrap_or(NO_MORPH),
+            ..self
+        }
+    }
+
+    /// Returns the layout of the instance buffers drawn with [GpuMesh]es.
+    pub(crate) fn layout() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Self>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+}
+
+/// Returns the source of the mesh shader followed by the skinning and morphing functions, which
+/// use the buffers bound by [deform_layout_entries].
+pub(crate) fn deformed_source(source: &str) -> String {
+    format!("{source}{SKIN_SHADER}{MORPH_SHADER}")
+}
+
+/// Returns the shader module of the mesh shader followed by the skinning and morphing functions.
+pub(crate) fn mesh_shader(device: &wgpu::Device, label: &str, source: &str) -> wgpu::ShaderModule {
+    device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some(label),
+        source: wgpu::ShaderSource::Wgsl(deformed_source(source).into()),
+    })
+}
+
+/// Returns the layout entries of the buffers deforming meshes in the vertex shaders, the joint
+/// palettes, morph targets, and morph weights, starting at the binding.
+pub(crate) fn deform_layout_entries(binding: u32) -> [wgpu::BindGroupLayoutEntry; 3] {
+    [0, 1, 2].map(|offset| wgpu::BindGroupLayoutEntry {
+        binding: binding + offset,
+        visibility: wgpu::ShaderStages::VERTEX,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Storage { read_only: true },
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    })
+}
+
+/// Returns the entries binding the buffers deforming meshes at the bindings of
+/// [deform_layout_entries].
+pub(crate) fn deform_entries(
+    resources: &GpuResources,
+    binding: u32,
+) -> [wgpu::BindGroupEntry<'_>; 3] {
+    let (targets, weights) = resources.morphs.buffers();
+    let buffers = [resources.skins.buffer(), targets, weights];
+    std::array::from_fn(|offset| wgpu::BindGroupEntry {
+        binding: binding + offset as u32,
+        resource: buffers[offset].as_entire_binding(),
+    })
+}
+
+/// Vertex and index buffers of a [Mesh] uploaded to the GPU.
+pub(crate) struct GpuMesh {
+    pub(crate) vertices: wgpu::Buffer,
+    pub(crate) indices: wgpu::Buffer,
+    pub(crate) index_count: u32,
+    /// Line list indices of the edges and their count, uploaded for drawing wireframes.
+    pub(crate) edges: Option<(wgpu::Buffer, u32)>,
+}
+
+impl GpuMesh {
+    fn new(device: &wgpu::Device, mesh: &MeshData) -> Self {
+        let vertices = (0..mesh.vertex_count())
+            .map(|i| Vertex {
+                position: mesh.positions[i].to_array(),
+                normal: mesh.normals[i].to_array(),
+                tangent: mesh.tangents[i].to_array(),
+                uv: mesh.uvs[i].to_array(),
+                joints: mesh.joints.get(i).copied().unwrap_or_default(),
+                weights: mesh
+                    .weights
+                    .get(i)
+                    .map_or([0.0; 4], |weights| weights.to_array()),
+            })
+            .collect::<Vec<_>>();
+
+        Self {
+            vertices: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("mesh vertices"),
+                contents: bytemuck::cast_slice(&vertices),
+                usage: wgpu::BufferUsages::VERTEX,
+            }),
+            indices: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("mesh indices"),
+                contents: bytemuck::cast_slice(&mesh.indices),
+                usage: wgpu::BufferUsages::INDEX,
+            }),
+            index_count: mesh.indices.len() as u32,
+            edges: None,
+        }
+    }
+}
+
+/// GPU buffers of the meshes drawn so far, keyed by the mesh's data, so meshes shared between nodes
+/// are uploaded once.
+#[derive(Default)]
+pub(crate) struct GpuMeshes {
+    meshes: HashMap<usize, (Weak<MeshData>, GpuMesh)>,
+}
+
+impl GpuMeshes {
+    /// Uploads the mesh if it wasn't uploaded yet.
+    pub(crate) fn upload(&mut self, device: &wgpu::Device, mesh: &Mesh) {
+        self.meshes
+            .entry(mesh.id())
+            .and_modify(|(data, gpu_mesh)| {
+                // The id of a dropped mesh may have been reused by a new one.
+                if data.strong_count() == 0 {
+                    *data = Arc::downgrade(&mesh.data);
+                    *gpu_mesh = GpuMesh::new(device, &mesh.data);
+                }
+            })
+            .or_insert_with(|| (Arc::downgrade(&mesh.data), GpuMesh::new(device, &mesh.data)));
+    }
+
+    /// Uploads the edges of the mesh if the mesh was uploaded and its edges weren't yet.
+    pub(crate) fn upload_edges(&mut self, device: &wgpu::Device, mesh: &Mesh) {
+        let Some((_, gpu_mesh)) = self.meshes.get_mut(&mesh.id()) else {
+            return;
+        };
+        if gpu_mesh.edges.is_none() {
+            let edges = mesh.data.edges();
+            let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("mesh edges"),
+                contents: bytemuck::cast_slice(&edges),
+                usage: wgpu::BufferUsages::INDEX,
+            });
+            gpu_mesh.edges = Some((buffer, edges.len() as u32));
+        }
+    }
+
+    /// Returns the buffers of the mesh if it was uploaded.
+    pub(crate) fn get(&self, mesh: &Mesh) -> Option<&GpuMesh> {
+        self.meshes.get(&mesh.id()).map(|(_, gpu_mesh)| gpu_mesh)
+    }
+
+    /// Drops the buffers of meshes that no longer exist.
+    pub(crate) fn collect_garbage(&mut self) {
+        self.meshes.retain(|_, (data, _)| data.strong_count() > 0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_valid(mesh: &Mesh) {
+        let data = mesh.data();
+        assert_eq!(data.validate(), Ok(()));
+        assert_eq!(data.tangents.len(), data.vertex_count());
+        for (normal, tangent) in data.normals.iter().zip(&data.tangents) {
+            assert!(normal.is_normalized());
+            assert!(tangent.truncate().is_normalized());
+            assert!(normal.dot(tangent.truncate()).abs() < 1e-4);
+        }
+
+        // Counter-clockwise triangles face away from the center.
+        for triangle in data.indices.chunks_exact(3) {
+            let [a, b, c] = [0, 1, 2].map(|i| data.positions[triangle[i] as usize]);
+            let normal = (b - a).cross(c - a);
+            assert!(normal.length() > 0.0, "degenerate triangle {triangle:?}");
+            assert!(normal.dot(a + b + c) >= 0.0, "inward triangle {triangle:?}");
+        }
+    }
+
+    #[test]
+    fn primitives_are_valid_and_outward_facing() {
+        assert_valid(&Mesh::cube(2.0));
+        assert_valid(&Mesh::sphere(1.0, 16, 8));
+        assert_valid(&Mesh::capsule(0.5, 1.0, 16, 4));
+
+        let plane = Mesh::plane(2.0);
+        assert_eq!(plane.data().validate(), Ok(()));
+        assert_eq!(
+            plane.data().tangents,
+            vec![Vec4::new(1.0, 0.0, 0.0, 1.0); 4]
+        );
+    }
+
+    #[test]
+    fn primitive_bounds() {
+        assert_eq!(
+            Mesh::cube(2.0).aabb(),
+            Some(Aabb::new(Vec3::splat(-1.0), Vec3::ONE))
+        );
+        assert_eq!(Mesh::cube(2.0).data().triangle_count(), 12);
+        // Each face has 4 sides and a diagonal.
+        assert_eq!(Mesh::cube(2.0).data().edges().len(), 6 * 5 * 2);
+
+        let capsule = Mesh::capsule(0.5, 1.0, 16, 4).aabb().unwrap();
+        assert!(capsule.max.abs_diff_eq(Vec3::new(0.5, 1.0, 0.5), 1e-5));
+        assert!(capsule.min.abs_diff_eq(Vec3::new(-0.5, -1.0, -0.5), 1e-5));
+    }
+
+    #[test]
+    fn new_validates_data() {
+        let mut data = MeshData {
+            positions: vec![Vec3::ZERO, Vec3::X, Vec3::Y],
+            normals: vec![Vec3::Z; 3],
+            tangents: Vec::new(),
+            uvs: vec![Vec2::ZERO; 2],
+            indices: vec![0, 1, 3],
+            ..MeshData::default()
+        };
+        assert_eq!(
+            Mesh::new(data.clone()),
+            Err(MeshError::AttributeLength {
+                attribute: "uvs",
+                len: 2,
+                vertex_count: 3,
+            })
+        );
+
+        data.uvs.push(Vec2::ZERO);
+        assert_eq!(
+            Mesh::new(data.clone()),
+            Err(MeshError::IndexOutOfBounds {
+                index: 3,
+                vertex_count: 3,
+            })
+        );
+
+        data.indices.pop();
+        assert_eq!(
+            Mesh::new(data.clone()),
+            Err(MeshError::IncompleteTriangle { index_count: 2 })
+        );
+
+        data.indices.push(2);
+        data.joints = vec![[0; 4]; 3];
+        assert_eq!(
+            Mesh::new(data.clone()),
+            Err(MeshError::AttributeLength {
+                attribute: "weights",
+                len: 0,
+                vertex_count: 3,
+            })
+        );
+
+        data.joints.clear();
+        data.morph_targets.push(MorphTarget {
+            positions: vec![Vec3::X; 3],
+            normals: vec![Vec3::Y],
+        });
+        assert_eq!(
+            Mesh::new(data),
+            Err(MeshError::AttributeLength {
+                attribute: "morph target normals",
+                len: 1,
+                vertex_count: 3,
+            })
+        );
+    }
+
+    #[test]
+    fn bounds_cover_morph_targets() {
+        let mut data = Mesh::cube(2.0).data().clone();
+        data.morph_targets.push(MorphTarget {
+            positions: vec![Vec3::Y; data.vertex_count()],
+            normals: Vec::new(),
+        });
+
+        assert_eq!(
+            Mesh::new(data).unwrap().aabb(),
+            Some(Aabb::new(Vec3::splat(-1.0), Vec3::new(1.0, 2.0, 1.0)))
+        );
+    }
+
+    #[test]
+    fn clones_are_equal() {
+        let mesh = Mesh::cube(1.0);
+
+        assert_eq!(mesh, mesh.clone());
+        assert_ne!(mesh, Mesh::cube(1.0));
+    }
+}
@@ -0,0 +1,201 @@
+//! # Morph
+//!
+//! Morph targets, blend shapes offsetting the vertices of a [Mesh] by the weights of the node's
+//! [MorphWeights], e.g. for facial animation. The offsets of all morphed meshes are uploaded once
+//! and blended in the vertex shaders before skinning, so only the weights are written every frame.
+
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::sync::Weak;
+
+use crate::render::mesh::Mesh;
+use crate::render::mesh::MeshData;
+use crate::Component;
+use crate::Node;
+use crate::Scene;
+
+/// Index of the morph weights of instances without morph targets.
+pub(crate) const NO_MORPH: u32 = u32::MAX;
+
+/// Source of the morphing function appended to the shaders of meshes, which declare the offsets
+/// of all morph targets as `morph_targets` and the weights of all morphed nodes as
+/// `morph_weights`. See [crate::render::mesh::deformed_source].
+pub(crate) const MORPH_SHADER: &str = include_str!("shaders/morph.wgsl");
+
+/// # Morph Weights
+///
+/// Component weighting the [crate::render::mesh::MorphTarget]s of the node's [Mesh], one weight
+/// for each target. Missing weights are zero, and nodes without the component draw the mesh
+/// without offsets.
+///
+/// ```
+/// # use glam::Vec3;
+/// # use pulse::render::mesh::Mesh;
+/// # use pulse::render::mesh::MorphTarget;
+/// # use pulse::render::morph::MorphWeights;
+/// # use pulse::Scene;
+/// let mut data = Mesh::cube(1.0).data().clone();
+/// data.morph_targets.push(MorphTarget {
+///     positions: data.positions.iter().map(|position| *position * 0.5).collect(),
+///     normals: Vec::new(),
+/// });
+///
+/// let mut scene = Scene::new();
+/// let node = scene.spawn_with((Mesh::new(data).unwrap(), MorphWeights(vec![0.0])));
+///
+/// // Grow the cube halfway to the target.
+/// scene.set(node, MorphWeights(vec![0.5]));
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Component)]
+pub struct MorphWeights(pub Vec<f32>);
+
+/// Offsets of the morph targets of all morphed meshes and the weights of all morphed nodes in GPU
+/// storage buffers, written once per frame and shared by the render nodes drawing meshes.
+#[derive(Default)]
+pub(crate) struct GpuMorphs {
+    /// Position and normal offsets of each vertex of each target of the meshes.
+    targets: Option<wgpu::Buffer>,
+    /// Index of the first target offset, number of targets, number of vertices, and weights of
+    /// each node.
+    weights: Option<wgpu::Buffer>,
+    /// Index of the first target offset of each mesh in the target buffer.
+    meshes: HashMap<usize, (Weak<MeshData>, u32)>,
+    /// Index of the weights of each morphed node in the weight buffer.
+    offsets: HashMap<Node, u32>,
+    /// Incremented whenever a buffer is recreated, so bind groups binding them are recreated.
+    pub(crate) generation: u64,
+}
+
+impl GpuMorphs {
+    /// Writes the weights of the scene's morphed nodes to the weight buffer, uploading the targets
+    /// of all of their meshes again if the meshes changed.
+    pub(crate) fn write(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, scene: &Scene) {
+        let nodes = scene
+            .query::<(MorphWeights, Mesh)>()
+            .filter(|(_, _, mesh)| !mesh.data().morph_targets.is_empty())
+            .collect::<Vec<_>>();
+        let meshes = nodes
+            .iter()
+            .map(|(_, _, mesh)| (mesh.id(), *mesh))
+            .collect::<BTreeMap<_, _>>();
+        let current = self.targets.is_some()
+            && meshes.len() == self.meshes.len()
+            && meshes.keys().all(|id| {
+                self.meshes
+                    .get(id)
+                    .is_some_and(|(data, _)| data.strong_count() > 0)
+            });
+        if !current {
+            self.meshes.clear();
+            let mut offsets = Vec::new();
+            for (id, mesh) in meshes {
+                self.meshes
+                    .insert(id, (mesh.downgrade(), offsets.len() as u32));
+                offsets.extend(target_offsets(mesh.data()));
+            }
+            let buffer = write_buffer(device, queue, &mut self.targets, "morph targets", &offsets);
+            self.generation += u64::from(buffer);
+        }
+
+        self.offsets.clear();
+        let mut weights = Vec::new();
+        for (node, node_weights, mesh) in nodes {
+            let data = mesh.data();
+            self.offsets.insert(node, weights.len() as u32);
+            weights.extend([
+                self.meshes[&mesh.id()].1,
+                data.morph_targets.len() as u32,
+                data.vertex_count() as u32,
+            ]);
+            weights.extend(
+                (0..data.morph_targets.len())
+                    .map(|target| node_weights.0.get(target).copied().unwrap_or(0.0).to_bits()),
+            );
+        }
+        let buffer = write_buffer(device, queue, &mut self.weights, "morph weights", &weights);
+        self.generation += u64::from(buffer);
+    }
+
+    /// Returns the target and weight buffers.
+    pub(crate) fn buffers(&self) -> (&wgpu::Buffer, &wgpu::Buffer) {
+        let expect = "morph weights must be written before drawing";
+        (
+            self.targets.as_ref().expect(expect),
+            self.weights.as_ref().expect(expect),
+        )
+    }
+
+    /// Returns the index of the node's morph weights, if it's morphed.
+    pub(crate) fn offset(&self, node: Node) -> Option<u32> {
+        self.offsets.get(&node).copied()
+    }
+}
+
+/// Returns the position and normal offsets of each vertex of each morph target of the mesh.
+fn target_offsets(data: &MeshData) -> impl Iterator<Item = [f32; 4]> + '_ {
+    data.morph_targets.iter().flat_map(move |target| {
+        (0..data.vertex_count()).flat_map(move |vertex| {
+            let normal = target.normals.get(vertex).copied().unwrap_or_default();
+            [
+                target.positions[vertex].extend(0.0).to_array(),
+                normal.extend(0.0).to_array(),
+            ]
+        })
+    })
+}
+
+/// Writes the values to the buffer, recreating it if it's too small, and returns whether it was
+/// recreated. Bind groups need a buffer even without morphed nodes.
+fn write_buffer<T: bytemuck::Pod>(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    buffer: &mut Option<wgpu::Buffer>,
+    label: &str,
+    values: &[T],
+) -> bool {
+    let size = std::mem::size_of_val(values).max(16) as u64;
+    let recreate = buffer.as_ref().is_none_or(|buffer| buffer.size() < size);
+    if recreate {
+        *buffer = Some(device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(label),
+            size: size.next_power_of_two(),
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        }));
+    }
+    if !values.is_empty() {
+        queue.write_buffer(buffer.as_ref().unwrap(), 0, bytemuck::cast_slice(values));
+    }
+    recreate
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec3;
+
+    use super::*;
+    use crate::render::mesh::MorphTarget;
+
+    #[test]
+    fn target_offsets_follow_vertices_of_each_target() {
+        let mut data = Mesh::plane(1.0).data().clone();
+        data.morph_targets = vec![
+            MorphTarget {
+                positions: vec![Vec3::Y; 4],
+                normals: Vec::new(),
+            },
+            MorphTarget {
+                positions: vec![Vec3::X; 4],
+                normals: vec![Vec3::Z; 4],
+            },
+        ];
+
+        let offsets = target_offsets(&data).collect::<Vec<_>>();
+
+        assert_eq!(offsets.len(), 2 * 4 * 2);
+        assert_eq!(offsets[0], [0.0, 1.0, 0.0, 0.0]);
+        assert_eq!(offsets[1], [0.0; 4]);
+        assert_eq!(offsets[8], [1.0, 0.0, 0.0, 0.0]);
+        assert_eq!(offsets[9], [0.0, 0.0, 1.0, 0.0]);
+    }
+}
@@ -0,0 +1,497 @@
+//! # Prepass
+//!
+//! Depth of the opaque meshes drawn before the forward pass by the
+//! [crate::render::graph::PREPASS] node, which is disabled by default. The forward pass then only
+//! shades the fragments whose depth equals the prepass depth, so each pixel is shaded once however
+//! many opaque meshes overlap it, and later nodes can sample the depth of each camera, e.g. for
+//! screen space effects or picking.
+
+use std::collections::HashMap;
+
+use glam::UVec2;
+
+use crate::render::anti_aliasing::AntiAliasing;
+use crate::render::forward::batch;
+use crate::render::forward::is_opaque;
+use crate::render::forward::view_projection;
+use crate::render::forward::Targets;
+use crate::render::forward::DEPTH_FORMAT;
+use crate::render::graph::GpuResources;
+use crate::render::graph::RenderContext;
+use crate::render::graph::RenderNode;
+use crate::render::light::world_transform;
+use crate::render::material::Material;
+use crate::render::mesh::deform_entries;
+use crate::render::mesh::deform_layout_entries;
+use crate::render::mesh::mesh_shader;
+use crate::render::mesh::Instance;
+use crate::render::mesh::Vertex;
+use crate::render::target::set_viewport;
+use crate::Camera;
+use crate::Scene;
+use crate::VisibleNodes;
+
+/// Size of the uniform of a camera in the camera buffer, its view projection matrix aligned to the
+/// minimum alignment of dynamic uniform buffer offsets guaranteed by all devices.
+const CAMERA_STRIDE: wgpu::BufferAddress = 256;
+
+/// # Prepass Depth
+///
+/// Render resource with a depth texture for each camera of the render target, in the order of
+/// [RenderContext::cameras], drawn by the [crate::render::graph::PREPASS] node and completed with
+/// the depth of the alpha masked meshes by the [crate::render::graph::FORWARD] node. The resource
+/// only exists in frames the prepass ran. Without MSAA the textures can be sampled and copied,
+/// while with MSAA they're multisampled and can only be used as depth attachments.
+///
+/// ```
+/// # use pulse::render::graph::RenderContext;
+/// # use pulse::render::graph::RenderNode;
+/// # use pulse::render::prepass::PrepassDepth;
+/// # use pulse::Scene;
+/// struct Fog;
+///
+/// impl RenderNode for Fog {
+///     fn run(&mut self, context: &mut RenderContext, _: &Scene) {
+///         let Some(depth) = context.resource::<PrepassDepth>() else {
+///             return;
+///         };
+///         if depth.sample_count() > 1 {
+///             return;
+///         }
+///         for camera in 0..context.cameras().len() {
+///             let view = depth.view(camera).unwrap();
+///             // Sample the view in a fullscreen pass...
+///         }
+///     }
+/// }
+/// ```
+pub struct PrepassDepth {
+    size: UVec2,
+    samples: u32,
+    textures: Vec<wgpu::Texture>,
+    views: Vec<wgpu::TextureView>,
+}
+
+impl PrepassDepth {
+    /// Returns the depth texture of the camera with the index.
+    pub fn texture(&self, camera: usize) -> Option<&wgpu::Texture> {
+        self.textures.get(camera)
+    }
+
+    /// Returns the view of the depth texture of the camera with the index.
+    pub fn view(&self, camera: usize) -> Option<&wgpu::TextureView> {
+        self.views.get(camera)
+    }
+
+    /// Returns the number of samples per pixel of the textures.
+    pub fn sample_count(&self) -> u32 {
+        self.samples
+    }
+
+    /// Inserts depth textures for the cameras with the size of the context's target and the
+    /// sample count into the context unless it has them already.
+    fn prepare(context: &mut RenderContext, samples: u32) {
+        let size = context.target_size;
+        let cameras = context.cameras.len();
+        if context.resource::<Self>().is_some_and(|depth| {
+            depth.size == size && depth.samples == samples && depth.textures.len() >= cameras
+        }) {
+            return;
+        }
+
+        // Not all devices can sample multisampled depth textures.
+        let mut usage = wgpu::TextureUsages::RENDER_ATTACHMENT;
+        if samples == 1 {
+            usage |= wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_SRC;
+        }
+        let textures = (0..cameras)
+            .map(|_| {
+                context.device.create_texture(&wgpu::TextureDescriptor {
+                    label: Some("prepass depth"),
+                    size: wgpu::Extent3d {
+                        width: size.x.max(1),
+                        height: size.y.max(1),
+                        depth_or_array_layers: 1,
+                    },
+                    mip_level_count: 1,
+                    sample_count: samples,
+                    dimension: wgpu::TextureDimension::D2,
+                    format: DEPTH_FORMAT,
+                    usage,
+                    view_formats: &[],
+                })
+            })
+            .collect::<Vec<_>>();
+        let views = textures
+            .iter()
+            .map(|texture| texture.create_view(&wgpu::TextureViewDescriptor::default()))
+            .collect();
+        context.insert_resource(Self {
+            size,
+            samples,
+            textures,
+            views,
+        });
+    }
+}
+
+/// Render resource marking that the prepass drew the [PrepassDepth] of the current frame.
+pub(crate) struct Prepassed;
+
+/// Render node drawing the depth of the visible opaque meshes of every camera to the
+/// [PrepassDepth].
+pub(crate) struct PrepassNode {
+    shader: wgpu::ShaderModule,
+    /// Pipelines for each sample count.
+    pipelines: HashMap<u32, wgpu::RenderPipeline>,
+    default_material: Material,
+    camera_layout: wgpu::BindGroupLayout,
+    camera_buffer: wgpu::Buffer,
+    camera_bind_group: Option<wgpu::BindGroup>,
+    /// Generation of the buffers deforming meshes bound in the camera bind group.
+    deform_generation: u64,
+    instance_buffer: wgpu::Buffer,
+}
+
+impl PrepassNode {
+    pub(crate) fn new(device: &wgpu::Device) -> Self {
+        let [palettes, morph_targets, morph_weights] = deform_layout_entries(1);
+        let camera_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("prepass camera"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: true,
+                        min_binding_size: wgpu::BufferSize::new(
+                            std::mem::size_of::<[[f32; 4]; 4]>() as u64,
+                        ),
+                    },
+                    count: None,
+                },
+                palettes,
+                morph_targets,
+                morph_weights,
+            ],
+        });
+
+        Self {
+            shader: mesh_shader(device, "prepass", include_str!("shaders/prepass.wgsl")),
+            pipelines: HashMap::new(),
+            default_material: Material::default(),
+            camera_layout,
+            camera_buffer: camera_buffer(device, 1),
+            camera_bind_group: None,
+            deform_generation: 0,
+            instance_buffer: instance_buffer(device, 1),
+        }
+    }
+
+    /// Returns the pipeline for the sample count, creating it if it doesn't exist yet.
+    fn pipeline(&mut self, device: &wgpu::Device, samples: u32) -> &wgpu::RenderPipeline {
+        let (shader, camera_layout) = (&self.shader, &self.camera_layout);
+        self.pipelines.entry(samples).or_insert_with(|| {
+            let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("prepass"),
+                bind_group_layouts: &[camera_layout],
+                push_constant_ranges: &[],
+            });
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some("prepass"),
+                layout: Some(&layout),
+                vertex: wgpu::VertexState {
+                    module: shader,
+                    entry_point: "vs_main",
+                    compilation_options: wgpu::PipelineCompilationOptions::default(),
+                    buffers: &[Vertex::layout(), Instance::layout()],
+                },
+                primitive: wgpu::PrimitiveState {
+                    cull_mode: Some(wgpu::Face::Back),
+                    ..wgpu::PrimitiveState::default()
+                },
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: DEPTH_FORMAT,
+                    depth_write_enabled: true,
+                    depth_compare: wgpu::CompareFunction::Less,
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState::default(),
+                }),
+                multisample: wgpu::MultisampleState {
+                    count: samples,
+                    ..wgpu::MultisampleState::default()
+                },
+                fragment: None,
+                multiview: None,
+                cache: None,
+            })
+        })
+    }
+}
+
+impl RenderNode for PrepassNode {
+    fn run(&mut self, context: &mut RenderContext, scene: &Scene) {
+        let device = context.device;
+        let queue = context.queue;
+        let anti_aliasing = scene
+            .get_resource::<AntiAliasing>()
+            .copied()
+            .unwrap_or_default();
+        let samples = anti_aliasing.sample_count();
+        // The forward pass advances the frame of the TAA jitter after drawing with it.
+        Targets::prepare(context, samples);
+        PrepassDepth::prepare(context, samples);
+
+        let frame = context.resource::<Targets>().unwrap().frame;
+        let mut uniforms = Vec::new();
+        let mut instances = Vec::new();
+        let mut draws = Vec::new();
+        for &node in context.cameras {
+            let viewport = scene
+                .get::<Camera>(node)
+                .unwrap()
+                .viewport(context.target_size);
+            let view_projection = view_projection(scene, node, anti_aliasing, frame, viewport.size);
+            let mut uniform = [0; CAMERA_STRIDE as usize];
+            uniform[..std::mem::size_of::<[[f32; 4]; 4]>()]
+                .copy_from_slice(bytemuck::bytes_of(&view_projection.to_cols_array_2d()));
+            uniforms.extend(uniform);
+
+            let visible = scene
+                .get::<VisibleNodes>(node)
+                .map_or(&[][..], |visible| &visible.0);
+            let transform = world_transform(scene, node);
+            let view = (transform.forward(), transform.translation());
+            let mut draw = batch(
+                scene,
+                visible,
+                view,
+                &self.default_material,
+                context.resources,
+                &mut instances,
+            );
+            draw.batches
+                .retain(|(material, _, _)| is_opaque(material.features()));
+            draw.viewport = viewport;
+            draws.push(draw);
+        }
+
+        let resources = &mut *context.resources;
+        for draw in &draws {
+            for (_, mesh, _) in &draw.batches {
+                resources.meshes.upload(device, mesh);
+            }
+        }
+
+        self.pipeline(device, samples);
+        let camera_count = draws.len().max(1) as u64;
+        if self.camera_buffer.size() < camera_count * CAMERA_STRIDE {
+            self.camera_buffer = camera_buffer(device, camera_count.next_power_of_two());
+            self.camera_bind_group = None;
+        }
+        if self.deform_generation != resources.deform_generation() {
+            self.deform_generation = resources.deform_generation();
+            self.camera_bind_group = None;
+        }
+        let camera_bind_group = self.camera_bind_group.get_or_insert_with(|| {
+            camera_bind_group(device, &self.camera_layout, &self.camera_buffer, resources)
+        });
+        queue.write_buffer(&self.camera_buffer, 0, &uniforms);
+
+        let instance_size = std::mem::size_of::<Instance>() as u64;
+        if self.instance_buffer.size() < instances.len() as u64 * instance_size {
+            self.instance_buffer =
+                instance_buffer(device, (instances.len() as u64).next_power_of_two());
+        }
+        queue.write_buffer(&self.instance_buffer, 0, bytemuck::cast_slice(&instances));
+
+        let pipeline = &self.pipelines[&samples];
+        let depth = resources.resource::<PrepassDepth>().unwrap();
+        let (mut draw_calls, mut triangles) = (0, 0);
+        for (index, draw) in draws.iter().enumerate() {
+            let mut pass = context
+                .encoder
+                .begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some("prepass"),
+                    color_attachments: &[],
+                    depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                        view: &depth.views[index],
+                        depth_ops: Some(wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(1.0),
+                            store: wgpu::StoreOp::Store,
+                        }),
+                        stencil_ops: None,
+                    }),
+                    timestamp_writes: None,
+                    occlusion_query_set: None,
+                });
+
+            pass.set_pipeline(pipeline);
+            set_viewport(&mut pass, draw.viewport);
+            let offset = index as u32 * CAMERA_STRIDE as u32;
+            pass.set_bind_group(0, &*camera_bind_group, &[offset]);
+            pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
+            for (_, mesh, instances) in &draw.batches {
+                let Some(gpu_mesh) = resources.meshes.get(mesh) else {
+                    continue;
+                };
+                pass.set_vertex_buffer(0, gpu_mesh.vertices.slice(..));
+                pass.set_index_buffer(gpu_mesh.indices.slice(..), wgpu::IndexFormat::Uint32);
+                pass.draw_indexed(0..gpu_mesh.index_count, 0, instances.clone());
+                draw_calls += 1;
+                triangles += u64::from(gpu_mesh.index_count / 3) * instances.len() as u64;
+            }
+        }
+        context.count_draws(draw_calls, triangles);
+        context.insert_resource(Prepassed);
+    }
+}
+
+fn camera_buffer(device: &wgpu::Device, cameras: u64) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("prepass cameras"),
+        size: cameras * CAMERA_STRIDE,
+        usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+fn camera_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    buffer: &wgpu::Buffer,
+    resources: &GpuResources,
+) -> wgpu::BindGroup {
+    let [palettes, morph_targets, morph_weights] = deform_entries(resources, 1);
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("prepass cameras"),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                    buffer,
+                    offset: 0,
+                    size: wgpu::BufferSize::new(std::mem::size_of::<[[f32; 4]; 4]>() as u64),
+                }),
+            },
+            palettes,
+            morph_targets,
+            morph_weights,
+        ],
+    })
+}
+
+fn instance_buffer(device: &wgpu::Device, instances: u64) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("prepass instances"),
+        size: instances.max(1) * std::mem::size_of::<Instance>() as u64,
+        usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::sync::Mutex;
+
+    use glam::Mat4;
+    use glam::Vec3;
+
+    use super::*;
+    use crate::render::graph::FORWARD;
+    use crate::render::graph::PREPASS;
+    use crate::render::light::DirectionalLight;
+    use crate::render::material::AlphaMode;
+    use crate::render::material::StandardMaterial;
+    use crate::render::mesh::Mesh;
+    use crate::render::tests::render_with;
+    use crate::render::tonemap::Tonemapping;
+    use crate::render::Color;
+    use crate::Camera;
+    use crate::ComputedVisibility;
+    use crate::WorldTransform;
+
+    /// Returns a lit scene of overlapping opaque, alpha masked, and alpha blended meshes.
+    fn scene(anti_aliasing: AntiAliasing) -> Scene {
+        let mut scene = Scene::new();
+        scene.insert_resource(anti_aliasing);
+        let material = |alpha_mode| {
+            Material::new(StandardMaterial {
+                base_color: Color::rgba(0.2, 0.6, 0.9, 0.5),
+                alpha_mode,
+                ..StandardMaterial::default()
+            })
+        };
+        let at = |x, z| WorldTransform::new(Mat4::from_translation(Vec3::new(x, 0.0, z)));
+        let visible = vec![
+            scene.spawn_with((Mesh::cube(1.0), at(0.0, 0.0))),
+            scene.spawn_with((Mesh::sphere(0.8, 16, 8), at(0.3, -0.5))),
+            scene.spawn_with((
+                Mesh::cube(0.5),
+                material(AlphaMode::Mask(0.4)),
+                at(-0.5, 0.5),
+            )),
+            scene.spawn_with((Mesh::cube(0.5), material(AlphaMode::Blend), at(0.5, 0.8))),
+        ];
+        scene.spawn_with((
+            Camera {
+                tonemapping: Tonemapping::None,
+                ..Camera::perspective(1.0, 1.0, 0.1, 10.0)
+            },
+            WorldTransform::new(Mat4::from_translation(Vec3::new(0.0, 0.5, 3.0))),
+            VisibleNodes(visible),
+        ));
+        scene.spawn_with((
+            DirectionalLight::default(),
+            WorldTransform::new(Mat4::from_rotation_x(-0.5)),
+            ComputedVisibility::Visible,
+        ));
+        scene
+    }
+
+    #[test]
+    fn render_with_prepass_matches_forward_pass() {
+        for anti_aliasing in [AntiAliasing::None, AntiAliasing::default()] {
+            let scene = scene(anti_aliasing);
+            let Some(forward) = render_with(&scene, 1, |_, _| {}) else {
+                return;
+            };
+            let prepass = render_with(&scene, 1, |_, graph| graph.set_enabled(PREPASS, true));
+
+            assert_eq!(prepass.unwrap(), forward);
+        }
+    }
+
+    /// Render node recording the sample count of the [PrepassDepth] of the frame, if any.
+    struct Probe(Arc<Mutex<Option<Option<u32>>>>);
+
+    impl RenderNode for Probe {
+        fn run(&mut self, context: &mut RenderContext, _: &Scene) {
+            let depth = context.resource::<PrepassDepth>();
+            assert!(depth.is_none_or(|depth| depth.view(0).is_some()));
+            *self.0.lock().unwrap() = Some(depth.map(PrepassDepth::sample_count));
+        }
+    }
+
+    #[test]
+    fn prepass_depth_exists_after_forward_pass_when_enabled() {
+        let scene = scene(AntiAliasing::None);
+        for enabled in [true, false] {
+            let probe = Arc::new(Mutex::new(None));
+            let node = Probe(probe.clone());
+            let rendered = render_with(&scene, 2, |_, graph| {
+                graph.set_enabled(PREPASS, enabled);
+                graph.add_node("probe", node).after(FORWARD);
+            });
+            if rendered.is_none() {
+                return;
+            }
+
+            let samples = enabled.then_some(1);
+            assert_eq!(*probe.lock().unwrap(), Some(samples));
+        }
+    }
+}
@@ -0,0 +1,448 @@
+//! # Probe
+//!
+//! Reflection probes capturing the scene around a point into a cubemap, which is reflected by the
+//! surfaces near the probe instead of the cubemap of the
+//! [crate::render::environment::EnvironmentLight], e.g. to reflect the walls of a room instead of
+//! the sky outside. Probes are captured by [crate::systems::capture_reflection_probes] with a
+//! camera rendering each face of the cube for one frame, and the captured faces are prefiltered
+//! like an environment light the first time a camera sees the probe.
+
+use std::collections::HashMap;
+use std::sync::Weak;
+
+use bytemuck::Pod;
+use bytemuck::Zeroable;
+use glam::Mat4;
+use glam::UVec2;
+use glam::Vec3;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::render::environment::cube_texture;
+use crate::render::environment::cube_view;
+use crate::render::environment::EnvironmentSource;
+use crate::render::environment::Generator;
+use crate::render::image::GpuImages;
+use crate::render::image::Image;
+use crate::render::image::ImageData;
+use crate::render::light::world_transform;
+use crate::render::target::RenderTarget;
+use crate::render::tonemap::Tonemapping;
+use crate::BoundingSphere;
+use crate::Camera;
+use crate::Component;
+use crate::Frustum;
+use crate::Node;
+use crate::Reflect;
+use crate::Scene;
+use crate::WorldTransform;
+
+/// Maximum number of reflection probes blended by the meshes seen by a camera, the ones nearest
+/// to the camera.
+pub(crate) const MAX_PROBES: usize = 2;
+
+/// Distance of the near plane of the cameras capturing a probe.
+const CAPTURE_NEAR: f32 = 0.05;
+
+/// Forward and up directions of the cameras capturing the faces of a probe, in the order of the
+/// cubemap's faces.
+const FACES: [(Vec3, Vec3); 6] = [
+    (Vec3::X, Vec3::Y),
+    (Vec3::NEG_X, Vec3::Y),
+    (Vec3::Y, Vec3::NEG_Z),
+    (Vec3::NEG_Y, Vec3::Z),
+    (Vec3::Z, Vec3::Y),
+    (Vec3::NEG_Z, Vec3::Y),
+];
+
+/// # Reflection Probe
+///
+/// Component capturing the scene around the node's translation into a cubemap reflected by the
+/// surfaces within its radius. Surfaces in the falloff at the edge of the radius and between
+/// overlapping probes blend the reflections, and reflect the environment light where no probe
+/// reaches. The scene is captured once the probe was added, and again after its [ProbeCapture]
+/// was removed. Captures are rendered without tonemapping, so light brighter than one is clamped.
+///
+/// ```
+/// # use glam::Vec3;
+/// # use pulse::render::probe::ReflectionProbe;
+/// # use pulse::LocalTransform;
+/// # use pulse::Scene;
+/// let mut scene = Scene::new();
+/// scene.spawn_with((
+///     ReflectionProbe {
+///         radius: 8.0,
+///         ..ReflectionProbe::default()
+///     },
+///     LocalTransform::from_position(Vec3::new(0.0, 2.0, 0.0)),
+/// ));
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq, Component, Reflect, Serialize, Deserialize)]
+pub struct ReflectionProbe {
+    /// Distance from the node within which surfaces reflect the probe.
+    pub radius: f32,
+    /// Distance inside the radius over which the reflection fades out.
+    pub falloff: f32,
+    /// Factor the captured light is multiplied with.
+    pub intensity: f32,
+    /// Width and height of the captured faces in pixels.
+    pub resolution: u32,
+    /// Distance from the node up to which the scene is captured.
+    pub far: f32,
+}
+
+impl Default for ReflectionProbe {
+    fn default() -> Self {
+        Self {
+            radius: 5.0,
+            falloff: 1.0,
+            intensity: 1.0,
+            resolution: 128,
+            far: 100.0,
+        }
+    }
+}
+
+/// # Probe Capture
+///
+/// Component with the faces of the scene captured for the node's [ReflectionProbe], added by
+/// [crate::systems::capture_reflection_probes]. Remove it to capture the probe again, e.g. after
+/// the scene around the probe changed.
+#[derive(Clone, Debug, PartialEq, Component)]
+pub struct ProbeCapture {
+    faces: [Image; 6],
+    ready: bool,
+}
+
+impl ProbeCapture {
+    /// Returns a capture of the probe's faces that wasn't rendered yet, or `None` if the probe's
+    /// resolution is zero.
+    pub(crate) fn new(probe: &ReflectionProbe) -> Option<Self> {
+        let face = || Image::render_target(UVec2::splat(probe.resolution));
+        Some(Self {
+            faces: [face()?, face()?, face()?, face()?, face()?, face()?],
+            ready: false,
+        })
+    }
+
+    /// Returns the render targets of the faces in the order +X, -X, +Y, -Y, +Z, and -Z.
+    pub fn faces(&self) -> &[Image; 6] {
+        &self.faces
+    }
+
+    /// Returns true if the faces were rendered, which happens in the frame after the capture was
+    /// added.
+    pub fn is_ready(&self) -> bool {
+        self.ready
+    }
+
+    /// Marks the faces as rendered.
+    pub(crate) fn complete(&mut self) {
+        self.ready = true;
+    }
+
+    /// Returns the cameras rendering the faces from the position and their transforms.
+    pub(crate) fn cameras(
+        &self,
+        probe: &ReflectionProbe,
+        position: Vec3,
+    ) -> Vec<(Camera, WorldTransform, RenderTarget)> {
+        let camera = Camera {
+            tonemapping: Tonemapping::None,
+            ..Camera::perspective(std::f32::consts::FRAC_PI_2, 1.0, CAPTURE_NEAR, probe.far)
+        };
+        FACES
+            .iter()
+            .zip(&self.faces)
+            .map(|((forward, up), face)| {
+                let view = Mat4::look_to_rh(position, *forward, *up);
+                (
+                    camera,
+                    WorldTransform::new(view.inverse()),
+                    RenderTarget(face.clone()),
+                )
+            })
+            .collect()
+    }
+
+    /// Returns the key of the capture's cubemap, which is never zero.
+    fn key(&self) -> usize {
+        self.faces[0].id()
+    }
+}
+
+/// Component of the cameras rendering the faces of the [ProbeCapture] of the probe node for one
+/// frame.
+#[derive(Copy, Clone, Debug, PartialEq, Component)]
+pub(crate) struct CaptureCamera(pub(crate) Node);
+
+/// Uniforms of a reflection probe in the shader, part of the camera's
+/// [crate::render::light::LightsUniform].
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+pub(crate) struct ProbeUniform {
+    /// Position and radius, zero without a probe.
+    position: [f32; 4],
+    /// Intensity and falloff.
+    params: [f32; 4],
+}
+
+/// Reflection probes of a camera, with the keys of their cubemaps in the order of their
+/// uniforms, zero for unused slots.
+pub(crate) type CameraProbes = ([ProbeUniform; MAX_PROBES], [usize; MAX_PROBES]);
+
+/// Returns the up to [MAX_PROBES] probes with a ready capture whose radius intersects the frustum,
+/// nearest to the position first.
+pub(crate) fn gather_probes(
+    scene: &Scene,
+    frustum: &Frustum,
+    position: Vec3,
+) -> Vec<(Vec3, ReflectionProbe, ProbeCapture)> {
+    let mut probes = scene
+        .query::<(ReflectionProbe,)>()
+        .filter_map(|(node, probe)| {
+            let capture = scene.get::<ProbeCapture>(node)?;
+            let center = world_transform(scene, node).translation();
+            (capture.ready && frustum.intersects_sphere(&BoundingSphere::new(center, probe.radius)))
+                .then(|| (center, *probe, capture.clone()))
+        })
+        .collect::<Vec<_>>();
+    probes.sort_by(|(a, ..), (b, ..)| {
+        a.distance_squared(position)
+            .total_cmp(&b.distance_squared(position))
+    });
+    probes.truncate(MAX_PROBES);
+    probes
+}
+
+/// Specular cubemaps of the captured reflection probes and the bind groups of the probes of each
+/// camera.
+pub(crate) struct GpuProbes {
+    layout: wgpu::BindGroupLayout,
+    /// Generator of the cubemaps, created when the first probe is seen.
+    generator: Option<Generator>,
+    /// Black cubemap bound for cameras with fewer probes.
+    fallback: wgpu::TextureView,
+    /// Specular cubemaps of the captures, keyed by their first face.
+    cubemaps: HashMap<usize, (Weak<ImageData>, wgpu::TextureView)>,
+    bind_groups: HashMap<[usize; MAX_PROBES], wgpu::BindGroup>,
+}
+
+impl GpuProbes {
+    pub(crate) fn new(device: &wgpu::Device) -> Self {
+        let cube = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                view_dimension: wgpu::TextureViewDimension::Cube,
+                multisampled: false,
+            },
+            count: None,
+        };
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("probes"),
+            entries: &[cube(0), cube(1)],
+        });
+        // New textures are cleared to zero.
+        let black = cube_texture(device, 1, 1, wgpu::TextureUsages::TEXTURE_BINDING);
+
+        Self {
+            layout,
+            generator: None,
+            fallback: black.create_view(&cube_view()),
+            cubemaps: HashMap::new(),
+            bind_groups: HashMap::new(),
+        }
+    }
+
+    /// Returns the layout of the bind groups of the probes.
+    pub(crate) fn layout(&self) -> &wgpu::BindGroupLayout {
+        &self.layout
+    }
+
+    /// Records the commands filtering the faces of the probes' captures if they weren't filtered
+    /// yet, and returns their uniforms and keys. Probes whose faces weren't rendered are skipped.
+    pub(crate) fn upload(
+        &mut self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        images: &GpuImages,
+        probes: &[(Vec3, ReflectionProbe, ProbeCapture)],
+    ) -> CameraProbes {
+        let mut uniforms = [ProbeUniform::zeroed(); MAX_PROBES];
+        let mut keys = [0; MAX_PROBES];
+        let mut slots = uniforms.iter_mut().zip(&mut keys);
+        for (position, probe, capture) in probes {
+            if !self.upload_capture(device, encoder, images, capture) {
+                continue;
+            }
+            let Some((uniform, key)) = slots.next() else {
+                break;
+            };
+            *uniform = ProbeUniform {
+                position: position.extend(probe.radius.max(0.0)).to_array(),
+                params: [probe.intensity.max(0.0), probe.falloff.max(0.0), 0.0, 0.0],
+            };
+            *key = capture.key();
+        }
+
+        if !self.bind_groups.contains_key(&keys) {
+            let view = |key| {
+                self.cubemaps
+                    .get(&key)
+                    .map_or(&self.fallback, |(_, view)| view)
+            };
+            let entries = keys
+                .iter()
+                .zip(0..)
+                .map(|(key, binding)| wgpu::BindGroupEntry {
+                    binding,
+                    resource: wgpu::BindingResource::TextureView(view(*key)),
+                })
+                .collect::<Vec<_>>();
+            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("probes"),
+                layout: &self.layout,
+                entries: &entries,
+            });
+            self.bind_groups.insert(keys, bind_group);
+        }
+        (uniforms, keys)
+    }
+
+    /// Returns the bind group of the probes with the keys returned by [GpuProbes::upload].
+    pub(crate) fn get(&self, keys: &[usize; MAX_PROBES]) -> &wgpu::BindGroup {
+        &self.bind_groups[keys]
+    }
+
+    /// Drops the cubemaps of captures that no longer exist and the bind groups using them.
+    pub(crate) fn collect_garbage(&mut self) {
+        self.cubemaps.retain(|_, (face, _)| face.strong_count() > 0);
+        let cubemaps = &self.cubemaps;
+        self.bind_groups.retain(|keys, _| {
+            keys.iter()
+                .all(|key| *key == 0 || cubemaps.contains_key(key))
+        });
+    }
+
+    /// Records the commands filtering the capture's faces unless they were filtered already, and
+    /// returns false if the faces weren't rendered.
+    fn upload_capture(
+        &mut self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        images: &GpuImages,
+        capture: &ProbeCapture,
+    ) -> bool {
+        if let Some((face, _)) = self.cubemaps.get(&capture.key()) {
+            // The id of a dropped image may have been reused by a new one.
+            if face.strong_count() > 0 {
+                return true;
+            }
+        }
+        let Some(views) = capture
+            .faces
+            .iter()
+            .map(|face| images.target_view(face))
+            .collect::<Option<Vec<_>>>()
+        else {
+            return false;
+        };
+
+        let generator = self.generator.get_or_insert_with(|| Generator::new(device));
+        let faces = std::array::from_fn(|face| &views[face]);
+        let (specular, _) = generator.generate(device, encoder, EnvironmentSource::Faces(faces));
+        let specular = specular.create_view(&cube_view());
+        self.cubemaps
+            .insert(capture.key(), (capture.faces[0].downgrade(), specular));
+        // Bind groups of a dropped capture whose key was reused are stale.
+        self.bind_groups
+            .retain(|keys, _| !keys.contains(&capture.key()));
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::material::Material;
+    use crate::render::material::StandardMaterial;
+    use crate::render::mesh::Mesh;
+    use crate::render::tests::render_updated;
+    use crate::render::Color;
+    use crate::LocalTransform;
+
+    #[test]
+    fn capture_cameras_look_along_cube_faces() {
+        let probe = ReflectionProbe::default();
+        let capture = ProbeCapture::new(&probe).unwrap();
+        let position = Vec3::new(1.0, 2.0, 3.0);
+        let cameras = capture.cameras(&probe, position);
+
+        assert_eq!(cameras.len(), 6);
+        for ((camera, transform, target), (forward, _)) in cameras.iter().zip(FACES) {
+            let point = position + forward * 2.0;
+            let clip = camera.view_projection_matrix(transform) * point.extend(1.0);
+
+            // The point in the face's direction is in the center of its image.
+            assert!((clip / clip.w).truncate().truncate().length() < 1e-5);
+            assert!(clip.w > 0.0);
+            assert_eq!(target.0.size(), UVec2::splat(probe.resolution));
+        }
+    }
+
+    /// Returns the red channel at the center of a mirror seen from the front, with a red wall
+    /// behind the camera reflected by a probe between them if `probe` is true.
+    fn render_mirror(probe: bool) -> Option<u8> {
+        let mut scene = Scene::new();
+        scene.spawn_with((
+            Mesh::cube(1.0),
+            Material::new(StandardMaterial {
+                base_color: Color::rgb(1.0, 1.0, 1.0),
+                metallic: 1.0,
+                roughness: 0.0,
+                ..StandardMaterial::default()
+            }),
+            LocalTransform::IDENTITY,
+        ));
+        scene.spawn_with((
+            Mesh::cube(8.0),
+            Material::new(StandardMaterial {
+                base_color: Color::rgb(0.0, 0.0, 0.0),
+                emissive: Color::rgb(1.0, 0.0, 0.0),
+                ..StandardMaterial::default()
+            }),
+            LocalTransform::from_position(Vec3::new(0.0, 0.0, 9.0)),
+        ));
+        scene.spawn_with((
+            Camera {
+                tonemapping: Tonemapping::None,
+                ..Camera::perspective(0.5, 1.0, 0.1, 20.0)
+            },
+            LocalTransform::from_position(Vec3::new(0.0, 0.0, 3.0)),
+        ));
+        if probe {
+            scene.spawn_with((
+                ReflectionProbe {
+                    resolution: 16,
+                    ..ReflectionProbe::default()
+                },
+                LocalTransform::from_position(Vec3::new(0.0, 0.0, 1.0)),
+            ));
+        }
+
+        let pixels = render_updated(&mut scene, 2)?;
+        Some(pixels[(32 * 64 + 32) * 4])
+    }
+
+    #[test]
+    fn render_reflects_probe_capture() {
+        let Some(without) = render_mirror(false) else {
+            return;
+        };
+        let with = render_mirror(true).unwrap();
+
+        assert!(with > 200 && without < 32, "{with} {without}");
+    }
+}
@@ -0,0 +1,121 @@
+//! # Shader
+//!
+//! Preprocessing of WGSL shaders with shader defs, so a single shader source can be compiled into
+//! variants for different features.
+
+use std::fmt;
+
+/// # Preprocess Error
+///
+/// Error returned when a shader's preprocessor directives are unbalanced.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum PreprocessError {
+    /// An `#else` or `#endif` on the line has no matching `#ifdef` or `#ifndef`.
+    Unmatched {
+        /// Line number starting at one.
+        line: usize,
+    },
+    /// An `#ifdef` or `#ifndef` on the line has no matching `#endif`.
+    Unterminated {
+        /// Line number starting at one.
+        line: usize,
+    },
+}
+
+impl fmt::Display for PreprocessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unmatched { line } => write!(f, "unmatched directive on line {line}"),
+            Self::Unterminated { line } => write!(f, "unterminated directive on line {line}"),
+        }
+    }
+}
+
+impl std::error::Error for PreprocessError {}
+
+/// Returns the source with the lines between `#ifdef NAME` and `#else` or `#endif` kept only if
+/// the def is one of the defs, and the lines after `#else` kept otherwise. `#ifndef NAME` keeps
+/// the lines if the def isn't one of the defs. Directives can be nested. Removed lines are
+/// replaced with empty lines to keep the line numbers of errors.
+///
+/// ```
+/// # use pulse::render::shader::preprocess;
+/// let source = "#ifdef TEXTURE\nlet color = sample();\n#else\nlet color = white;\n#endif";
+///
+/// assert_eq!(preprocess(source, &["TEXTURE"]).unwrap().trim(), "let color = sample();");
+/// assert_eq!(preprocess(source, &[]).unwrap().trim(), "let color = white;");
+/// ```
+pub fn preprocess(source: &str, defs: &[&str]) -> Result<String, PreprocessError> {
+    // Whether the lines are kept for each open directive, and the line of the directive.
+    let mut stack: Vec<(bool, usize)> = Vec::new();
+    let mut output = String::with_capacity(source.len());
+    for (index, line) in source.lines().enumerate() {
+        let number = index + 1;
+        let mut words = line.split_whitespace();
+        let keep = stack.last().is_none_or(|(keep, _)| *keep);
+        match words.next() {
+            Some("#ifdef") => {
+                let defined = words.next().is_some_and(|def| defs.contains(&def));
+                stack.push((keep && defined, number));
+            }
+            Some("#ifndef") => {
+                let defined = words.next().is_some_and(|def| defs.contains(&def));
+                stack.push((keep && !defined, number));
+            }
+            Some("#else") => {
+                let (kept, _) = stack
+                    .pop()
+                    .ok_or(PreprocessError::Unmatched { line: number })?;
+                let parent = stack.last().is_none_or(|(keep, _)| *keep);
+                stack.push((parent && !kept, number));
+            }
+            Some("#endif") => {
+                stack
+                    .pop()
+                    .ok_or(PreprocessError::Unmatched { line: number })?;
+            }
+            _ if keep => output.push_str(line),
+            _ => {}
+        }
+        output.push('\n');
+    }
+
+    match stack.last() {
+        Some((_, line)) => Err(PreprocessError::Unterminated { line: *line }),
+        None => Ok(output),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn preprocess_nested_directives() {
+        let source = "a\n#ifdef X\nb\n#ifndef Y\nc\n#else\nd\n#endif\n#else\ne\n#endif\nf";
+        let lines = |defs: &[&str]| {
+            preprocess(source, defs)
+                .unwrap()
+                .split_whitespace()
+                .collect::<String>()
+        };
+
+        assert_eq!(lines(&[]), "aef");
+        assert_eq!(lines(&["X"]), "abcf");
+        assert_eq!(lines(&["X", "Y"]), "abdf");
+        assert_eq!(lines(&["Y"]), "aef");
+        assert_eq!(preprocess(source, &[]).unwrap().lines().count(), 12);
+    }
+
+    #[test]
+    fn preprocess_unbalanced_directives() {
+        assert_eq!(
+            preprocess("a\n#endif", &[]),
+            Err(PreprocessError::Unmatched { line: 2 })
+        );
+        assert_eq!(
+            preprocess("#ifdef X\n#else", &[]),
+            Err(PreprocessError::Unterminated { line: 2 })
+        );
+    }
+}
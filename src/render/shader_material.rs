@@ -0,0 +1,575 @@
+//! # Shader Material
+//!
+//! Materials shaded by user WGSL fragment shaders, with uniforms and textures declared by a schema
+//! and shaders reloaded when their files change.
+
+use std::fmt;
+use std::hash::DefaultHasher;
+use std::hash::Hash;
+use std::hash::Hasher;
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::Weak;
+use std::time::Duration;
+use std::time::SystemTime;
+
+use glam::Vec2;
+use glam::Vec3;
+use glam::Vec4;
+use serde::Deserialize;
+use serde::Serialize;
+use wgpu::naga;
+
+use crate::assets::AssetLoader;
+use crate::assets::LoadContext;
+use crate::render::image::Image;
+use crate::render::material::AlphaMode;
+use crate::render::Color;
+use crate::time::Instant;
+
+/// Minimum time between checks of a shader file for changes.
+const RELOAD_INTERVAL: Duration = Duration::from_millis(250);
+
+/// # Uniform Value
+///
+/// Value of a [ShaderMaterial] uniform, laid out in the material's uniform struct with the
+/// alignment of the WGSL type.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum UniformValue {
+    /// An `f32`.
+    Float(f32),
+    /// A `vec2<f32>`.
+    Vec2(Vec2),
+    /// A `vec3<f32>`.
+    Vec3(Vec3),
+    /// A `vec4<f32>`.
+    Vec4(Vec4),
+    /// A `vec4<f32>` with the linear components and alpha of the color.
+    Color(Color),
+}
+
+impl UniformValue {
+    /// Returns the WGSL type of the value.
+    fn wgsl_type(self) -> &'static str {
+        match self {
+            Self::Float(_) => "f32",
+            Self::Vec2(_) => "vec2<f32>",
+            Self::Vec3(_) => "vec3<f32>",
+            Self::Vec4(_) | Self::Color(_) => "vec4<f32>",
+        }
+    }
+
+    /// Returns the alignment of the value in a uniform buffer and its components.
+    fn layout(self) -> (usize, Vec<f32>) {
+        match self {
+            Self::Float(value) => (4, vec![value]),
+            Self::Vec2(value) => (8, value.to_array().to_vec()),
+            Self::Vec3(value) => (16, value.to_array().to_vec()),
+            Self::Vec4(value) => (16, value.to_array().to_vec()),
+            Self::Color(color) => (16, color.to_vec4().to_array().to_vec()),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct ShaderData {
+    path: Option<PathBuf>,
+    state: Mutex<ShaderState>,
+}
+
+#[derive(Debug)]
+struct ShaderState {
+    source: String,
+    generation: u64,
+    modified: Option<SystemTime>,
+    checked: Option<Instant>,
+}
+
+/// # Material Shader
+///
+/// WGSL source of a [ShaderMaterial]'s fragment shader. Shaders loaded from a file are reloaded
+/// by the renderer when the file changes, and materials using them are drawn with the new source
+/// if it compiles, or keep the previous one otherwise. The shader is shared between clones, and
+/// shaders are equal if they share the same source.
+#[derive(Clone, Debug)]
+pub struct MaterialShader {
+    data: Arc<ShaderData>,
+}
+
+impl MaterialShader {
+    /// Returns the shader with the source, which is never reloaded.
+    pub fn new(source: impl Into<String>) -> Self {
+        Self::with_path(None, source.into(), None)
+    }
+
+    /// Loads the shader from the WGSL file.
+    pub fn load(path: impl Into<PathBuf>) -> io::Result<Self> {
+        let path = path.into();
+        let source = std::fs::read_to_string(&path)?;
+        let modified = modified(&path);
+        Ok(Self::with_path(Some(path), source, modified))
+    }
+
+    fn with_path(path: Option<PathBuf>, source: String, modified: Option<SystemTime>) -> Self {
+        Self {
+            data: Arc::new(ShaderData {
+                path,
+                state: Mutex::new(ShaderState {
+                    source,
+                    generation: 0,
+                    modified,
+                    checked: None,
+                }),
+            }),
+        }
+    }
+
+    /// Returns the path of the shader's file, if it was loaded from one.
+    pub fn path(&self) -> Option<&Path> {
+        self.data.path.as_deref()
+    }
+
+    /// Returns the shader's current source.
+    pub fn source(&self) -> String {
+        self.data.state.lock().unwrap().source.clone()
+    }
+
+    /// Returns the number of times the shader was reloaded.
+    pub fn generation(&self) -> u64 {
+        self.data.state.lock().unwrap().generation
+    }
+
+    /// Reloads the shader if its file was modified since it was last read, and returns whether it
+    /// was reloaded. Does nothing for shaders without a file.
+    pub fn reload(&self) -> io::Result<bool> {
+        let Some(path) = &self.data.path else {
+            return Ok(false);
+        };
+        let mut state = self.data.state.lock().unwrap();
+        state.checked = Some(Instant::now());
+        let modified = modified(path);
+        if modified.is_some() && modified == state.modified {
+            return Ok(false);
+        }
+
+        state.source = std::fs::read_to_string(path)?;
+        state.modified = modified;
+        state.generation += 1;
+        Ok(true)
+    }
+
+    /// Reloads the shader if it wasn't checked recently, printing errors reading the file.
+    pub(crate) fn poll_reload(&self) {
+        let checked = self.data.state.lock().unwrap().checked;
+        if self.data.path.is_none() || checked.is_some_and(|at| at.elapsed() < RELOAD_INTERVAL) {
+            return;
+        }
+
+        if let Err(error) = self.reload() {
+            println!(
+                "Failed to reload shader {:?}: {error}",
+                self.path().unwrap()
+            );
+        }
+    }
+
+    /// Returns an identifier for the shader, valid while the shader exists.
+    pub(crate) fn id(&self) -> usize {
+        Arc::as_ptr(&self.data) as usize
+    }
+
+    /// Returns a weak reference to the shader, to check whether the shader still exists.
+    pub(crate) fn downgrade(&self) -> Weak<ShaderData> {
+        Arc::downgrade(&self.data)
+    }
+}
+
+impl PartialEq for MaterialShader {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.data, &other.data)
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+/// # Shader Material
+///
+/// Material shaded by the fragment shader `fs_main` of its [MaterialShader]. The shader is
+/// appended to the standard shader's vertex stage, so it can use the `VertexOutput` of the
+/// vertices, the `camera` and `lights` uniforms, the `Surface` struct and `lighting` function
+/// of the standard material's shading, and the `fog` function applying the camera's
+/// [crate::render::fog::Fog].
+///
+/// The uniforms are declared in the struct `MaterialUniforms` bound as `material` in the order of
+/// the schema, and every texture as `<name>_texture` with the sampler `<name>_sampler`. Textures
+/// without an image are white. Debug shadings don't apply to shader materials, and alpha masked
+/// materials discard their fragments in the shader.
+///
+/// ```
+/// # use pulse::render::material::Material;
+/// # use pulse::render::shader_material::MaterialShader;
+/// # use pulse::render::shader_material::ShaderMaterial;
+/// # use pulse::render::shader_material::UniformValue;
+/// # use pulse::render::Color;
+/// let shader = MaterialShader::new(
+///     "@fragment
+///     fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+///         let stripe = step(0.5, fract(in.uv.x * material.stripes));
+///         return mix(material.color, vec4<f32>(1.0), stripe);
+///     }",
+/// );
+/// let stripes = Material::from(ShaderMaterial {
+///     uniforms: vec![
+///         ("color".into(), UniformValue::Color(Color::rgb(1.0, 0.0, 0.0))),
+///         ("stripes".into(), UniformValue::Float(8.0)),
+///     ],
+///     ..ShaderMaterial::new(shader)
+/// });
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct ShaderMaterial {
+    /// Shader with the material's fragment shader.
+    pub shader: MaterialShader,
+    /// Names and values of the uniforms.
+    pub uniforms: Vec<(String, UniformValue)>,
+    /// Names and images of the textures.
+    pub textures: Vec<(String, Option<Image>)>,
+    /// How the alpha of the shader's color is used.
+    pub alpha_mode: AlphaMode,
+}
+
+impl ShaderMaterial {
+    /// Returns an opaque material of the shader without uniforms and textures.
+    pub fn new(shader: MaterialShader) -> Self {
+        Self {
+            shader,
+            uniforms: Vec::new(),
+            textures: Vec::new(),
+            alpha_mode: AlphaMode::Opaque,
+        }
+    }
+
+    /// Loads the material from a RON file with its [MaterialSchema], and its shader from the
+    /// schema's path relative to the file.
+    ///
+    /// ```ron
+    /// (
+    ///     shader: "water.wgsl",
+    ///     alpha_mode: Blend,
+    ///     uniforms: [("deep", Color((r: 0.0, g: 0.1, b: 0.3, a: 0.9))), ("speed", Float(0.5))],
+    ///     textures: ["foam"],
+    /// )
+    /// ```
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ShaderMaterialError> {
+        let path = path.as_ref();
+        let schema = std::fs::read_to_string(path).map_err(ShaderMaterialError::Io)?;
+        let schema =
+            ron::from_str::<MaterialSchema>(&schema).map_err(ShaderMaterialError::Schema)?;
+        let shader_path = path.parent().unwrap_or(Path::new("")).join(&schema.shader);
+        let shader = MaterialShader::load(shader_path).map_err(ShaderMaterialError::Io)?;
+        Ok(schema.material(shader))
+    }
+
+    /// Sets the value of the uniform with the name, and returns false if the material has no
+    /// uniform with the name and type of the value.
+    pub fn set_uniform(&mut self, name: &str, value: UniformValue) -> bool {
+        match self
+            .uniforms
+            .iter_mut()
+            .find(|(uniform, _)| uniform == name)
+        {
+            Some((_, uniform)) if uniform.wgsl_type() == value.wgsl_type() => {
+                *uniform = value;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Sets the image of the texture with the name, and returns false if the material has no
+    /// texture with the name.
+    pub fn set_texture(&mut self, name: &str, image: Option<Image>) -> bool {
+        match self
+            .textures
+            .iter_mut()
+            .find(|(texture, _)| texture == name)
+        {
+            Some((_, texture)) => {
+                *texture = image;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns the bytes of the material's uniform struct, or `None` if it has no uniforms.
+    pub(crate) fn uniform_bytes(&self) -> Option<Vec<u8>> {
+        if self.uniforms.is_empty() {
+            return None;
+        }
+
+        let mut bytes = Vec::new();
+        for (_, value) in &self.uniforms {
+            let (align, components) = value.layout();
+            bytes.resize(bytes.len().next_multiple_of(align), 0);
+            bytes.extend(
+                components
+                    .iter()
+                    .flat_map(|component| component.to_le_bytes()),
+            );
+        }
+        // Uniform structs are aligned to 16 bytes.
+        bytes.resize(bytes.len().next_multiple_of(16), 0);
+        Some(bytes)
+    }
+
+    /// Returns a hash of the uniforms' names and types and the textures' names, which determine
+    /// the material's bindings.
+    pub(crate) fn layout_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        for (name, value) in &self.uniforms {
+            (name, value.wgsl_type()).hash(&mut hasher);
+        }
+        for (name, _) in &self.textures {
+            name.hash(&mut hasher);
+        }
+        hasher.finish()
+    }
+
+    /// Returns the WGSL declarations of the material's uniform struct and textures.
+    pub(crate) fn bindings(&self) -> String {
+        let mut bindings = String::new();
+        if !self.uniforms.is_empty() {
+            bindings.push_str("struct MaterialUniforms {\n");
+            for (name, value) in &self.uniforms {
+                bindings.push_str(&format!("    {name}: {},\n", value.wgsl_type()));
+            }
+            bindings.push_str(
+                "};\n\n@group(1) @binding(0)\nvar<uniform> material: MaterialUniforms;\n",
+            );
+        }
+        for (binding, (name, _)) in (1..).step_by(2).zip(&self.textures) {
+            bindings.push_str(&format!(
+                "\n@group(1) @binding({binding})\nvar {name}_texture: texture_2d<f32>;\n\
+                 @group(1) @binding({})\nvar {name}_sampler: sampler;\n",
+                binding + 1
+            ));
+        }
+        bindings
+    }
+}
+
+/// # Material Schema
+///
+/// Contents of a [ShaderMaterial]'s RON file, loaded with [ShaderMaterial::load].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MaterialSchema {
+    /// Path of the WGSL file relative to the schema's file.
+    pub shader: PathBuf,
+    /// How the alpha of the shader's color is used.
+    #[serde(default)]
+    pub alpha_mode: AlphaMode,
+    /// Names and default values of the uniforms.
+    #[serde(default)]
+    pub uniforms: Vec<(String, UniformValue)>,
+    /// Names of the textures.
+    #[serde(default)]
+    pub textures: Vec<String>,
+}
+
+impl MaterialSchema {
+    /// Returns the material of the shader with the schema's uniforms and textures without images.
+    pub fn material(self, shader: MaterialShader) -> ShaderMaterial {
+        ShaderMaterial {
+            shader,
+            uniforms: self.uniforms,
+            textures: self.textures.into_iter().map(|name| (name, None)).collect(),
+            alpha_mode: self.alpha_mode,
+        }
+    }
+}
+
+/// # Material Shader Loader
+///
+/// [AssetLoader] of [MaterialShader]s from WGSL files. Loaded shaders aren't reloaded when their
+/// files change, unlike shaders from [MaterialShader::load].
+pub struct MaterialShaderLoader;
+
+impl AssetLoader for MaterialShaderLoader {
+    type Asset = MaterialShader;
+
+    fn extensions(&self) -> &[&str] {
+        &["wgsl"]
+    }
+
+    fn load(
+        &self,
+        context: &LoadContext,
+    ) -> Result<MaterialShader, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(MaterialShader::new(String::from_utf8(
+            context.bytes().to_vec(),
+        )?))
+    }
+}
+
+/// # Shader Material Loader
+///
+/// [AssetLoader] of [ShaderMaterial]s from RON files with a [MaterialSchema], with their shaders
+/// loaded from the schema's path relative to the file.
+pub struct ShaderMaterialLoader;
+
+impl AssetLoader for ShaderMaterialLoader {
+    type Asset = ShaderMaterial;
+
+    fn extensions(&self) -> &[&str] {
+        &["material.ron"]
+    }
+
+    fn load(
+        &self,
+        context: &LoadContext,
+    ) -> Result<ShaderMaterial, Box<dyn std::error::Error + Send + Sync>> {
+        let schema = ron::de::from_bytes::<MaterialSchema>(context.bytes())?;
+        let shader = String::from_utf8(context.read(&schema.shader)?)?;
+        Ok(schema.material(MaterialShader::new(shader)))
+    }
+}
+
+/// # Shader Material Error
+///
+/// Error returned when a [ShaderMaterial] can't be loaded.
+#[derive(Debug)]
+pub enum ShaderMaterialError {
+    /// The schema or shader file couldn't be read.
+    Io(io::Error),
+    /// The schema isn't a valid [MaterialSchema].
+    Schema(ron::error::SpannedError),
+}
+
+impl fmt::Display for ShaderMaterialError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "failed to read material: {error}"),
+            Self::Schema(error) => write!(f, "invalid material schema: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for ShaderMaterialError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(error) => Some(error),
+            Self::Schema(error) => Some(error),
+        }
+    }
+}
+
+/// Returns the source of the material's shader appended to the standard shader's vertex stage and
+/// the material's bindings, or the error message if it doesn't compile.
+pub(crate) fn compile(prelude: &str, material: &ShaderMaterial) -> Result<String, String> {
+    let source = format!(
+        "{prelude}\n{}\n{}",
+        material.bindings(),
+        material.shader.source()
+    );
+    let module =
+        naga::front::wgsl::parse_str(&source).map_err(|error| error.emit_to_string(&source))?;
+    naga::valid::Validator::new(
+        naga::valid::ValidationFlags::all(),
+        naga::valid::Capabilities::default(),
+    )
+    .validate(&module)
+    .map_err(|error| error.emit_to_string(&source))?;
+    Ok(source)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn material() -> ShaderMaterial {
+        ShaderMaterial {
+            uniforms: vec![
+                ("speed".into(), UniformValue::Float(2.0)),
+                ("tint".into(), UniformValue::Vec3(Vec3::new(1.0, 0.5, 0.25))),
+                ("scale".into(), UniformValue::Vec2(Vec2::new(3.0, 4.0))),
+            ],
+            textures: vec![("noise".into(), None)],
+            ..ShaderMaterial::new(MaterialShader::new(""))
+        }
+    }
+
+    #[test]
+    fn uniform_bytes_follow_wgsl_alignment() {
+        let floats = material()
+            .uniform_bytes()
+            .unwrap()
+            .chunks_exact(4)
+            .map(|bytes| f32::from_le_bytes(bytes.try_into().unwrap()))
+            .collect::<Vec<_>>();
+
+        // The vec3 is aligned to 16 bytes, and the vec2 after it from offset 28 to 32.
+        assert_eq!(
+            floats,
+            [2.0, 0.0, 0.0, 0.0, 1.0, 0.5, 0.25, 0.0, 3.0, 4.0, 0.0, 0.0]
+        );
+        assert_eq!(
+            ShaderMaterial::new(MaterialShader::new("")).uniform_bytes(),
+            None
+        );
+    }
+
+    #[test]
+    fn set_uniform_checks_name_and_type() {
+        let mut material = material();
+        let layout = material.layout_hash();
+
+        assert!(material.set_uniform("speed", UniformValue::Float(3.0)));
+        assert!(!material.set_uniform("speed", UniformValue::Vec2(Vec2::ONE)));
+        assert!(!material.set_uniform("missing", UniformValue::Float(1.0)));
+        assert!(material.set_texture("noise", None));
+        assert!(!material.set_texture("missing", None));
+        assert_eq!(material.uniforms[0].1, UniformValue::Float(3.0));
+        assert_eq!(material.layout_hash(), layout);
+    }
+
+    #[test]
+    fn schema_loads_material_with_reloadable_shader() {
+        let directory = std::env::temp_dir().join(format!("pulse-material-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let schema = "(shader: \"water.wgsl\", alpha_mode: Blend, uniforms: [(\"speed\", Float(0.5))], textures: [\"foam\"])";
+        std::fs::write(directory.join("water.ron"), schema).unwrap();
+        std::fs::write(directory.join("water.wgsl"), "// first").unwrap();
+
+        let material = ShaderMaterial::load(directory.join("water.ron")).unwrap();
+        assert_eq!(material.alpha_mode, AlphaMode::Blend);
+        assert_eq!(
+            material.uniforms,
+            [("speed".into(), UniformValue::Float(0.5))]
+        );
+        assert_eq!(material.textures, [("foam".into(), None)]);
+        assert_eq!(material.shader.source(), "// first");
+        assert!(!material.shader.reload().unwrap());
+
+        std::fs::write(directory.join("water.wgsl"), "// second").unwrap();
+        // Make sure the modification time changes on file systems with coarse timestamps.
+        let file = std::fs::File::options()
+            .write(true)
+            .open(directory.join("water.wgsl"))
+            .unwrap();
+        file.set_modified(SystemTime::now() + Duration::from_secs(10))
+            .unwrap();
+        assert!(material.shader.reload().unwrap());
+        assert_eq!(material.shader.source(), "// second");
+        assert_eq!(material.shader.generation(), 1);
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+}
@@ -0,0 +1,102 @@
+// Post-process anti-aliasing of the high dynamic range target: fast approximate anti-aliasing
+// (FXAA) blurring along the edges found in the luminance, and temporal anti-aliasing (TAA)
+// accumulating the jittered frames in a history clamped to the neighborhood of each pixel.
+
+struct Params {
+    // Weight of the current frame in the history, 1 to reset it.
+    blend: f32,
+};
+
+@group(0) @binding(0)
+var<uniform> params: Params;
+
+@group(0) @binding(1)
+var source: texture_2d<f32>;
+
+@group(0) @binding(2)
+var history: texture_2d<f32>;
+
+@group(0) @binding(3)
+var source_sampler: sampler;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+// Triangle covering the whole target, with UV coordinates from its top-left corner.
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+
+    var out: VertexOutput;
+    out.position = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    out.uv = uv;
+    return out;
+}
+
+// Returns the luminance of the color, compressed to the range 0 to 1 so edges against bright
+// light aren't overemphasized.
+fn luma(color: vec3<f32>) -> f32 {
+    let luminance = dot(color, vec3<f32>(0.2126, 0.7152, 0.0722));
+    return luminance / (1.0 + luminance);
+}
+
+fn sample(uv: vec2<f32>) -> vec3<f32> {
+    return textureSampleLevel(source, source_sampler, uv, 0.0).rgb;
+}
+
+const FXAA_REDUCE_MIN: f32 = 1.0 / 128.0;
+const FXAA_REDUCE_MUL: f32 = 1.0 / 8.0;
+const FXAA_SPAN_MAX: f32 = 8.0;
+
+@fragment
+fn fs_fxaa(in: VertexOutput) -> @location(0) vec4<f32> {
+    let texel = 1.0 / vec2<f32>(textureDimensions(source));
+    let center = textureSampleLevel(source, source_sampler, in.uv, 0.0);
+    let luma_m = luma(center.rgb);
+    let luma_nw = luma(sample(in.uv + texel * vec2<f32>(-1.0, -1.0)));
+    let luma_ne = luma(sample(in.uv + texel * vec2<f32>(1.0, -1.0)));
+    let luma_sw = luma(sample(in.uv + texel * vec2<f32>(-1.0, 1.0)));
+    let luma_se = luma(sample(in.uv + texel * vec2<f32>(1.0, 1.0)));
+    let luma_min = min(luma_m, min(min(luma_nw, luma_ne), min(luma_sw, luma_se)));
+    let luma_max = max(luma_m, max(max(luma_nw, luma_ne), max(luma_sw, luma_se)));
+
+    // Blur along the edge, perpendicular to the gradient of the luminance.
+    var direction = vec2<f32>(
+        (luma_sw + luma_se) - (luma_nw + luma_ne),
+        (luma_nw + luma_sw) - (luma_ne + luma_se),
+    );
+    let reduce = max((luma_nw + luma_ne + luma_sw + luma_se) * 0.25 * FXAA_REDUCE_MUL, FXAA_REDUCE_MIN);
+    let scale = 1.0 / (min(abs(direction.x), abs(direction.y)) + reduce);
+    direction = clamp(direction * scale, vec2<f32>(-FXAA_SPAN_MAX), vec2<f32>(FXAA_SPAN_MAX)) * texel;
+
+    let near = 0.5 * (sample(in.uv + direction * (1.0 / 3.0 - 0.5)) + sample(in.uv + direction * (2.0 / 3.0 - 0.5)));
+    let far = near * 0.5 + 0.25 * (sample(in.uv - direction * 0.5) + sample(in.uv + direction * 0.5));
+    // The wider blur crossed another edge if it leaves the range of the neighborhood.
+    let luma_far = luma(far);
+    if luma_far < luma_min || luma_far > luma_max {
+        return vec4<f32>(near, center.a);
+    }
+    return vec4<f32>(far, center.a);
+}
+
+@fragment
+fn fs_taa(in: VertexOutput) -> @location(0) vec4<f32> {
+    let position = vec2<i32>(in.position.xy);
+    let size = vec2<i32>(textureDimensions(source));
+    let current = textureLoad(source, position, 0);
+
+    // Clamp the history to the colors around the pixel against ghosting where the image changed.
+    var neighborhood_min = current.rgb;
+    var neighborhood_max = current.rgb;
+    for (var y = -1; y <= 1; y++) {
+        for (var x = -1; x <= 1; x++) {
+            let neighbor = textureLoad(source, clamp(position + vec2<i32>(x, y), vec2<i32>(0), size - 1), 0).rgb;
+            neighborhood_min = min(neighborhood_min, neighbor);
+            neighborhood_max = max(neighborhood_max, neighbor);
+        }
+    }
+    let previous = clamp(textureLoad(history, position, 0).rgb, neighborhood_min, neighborhood_max);
+    return vec4<f32>(mix(previous, current.rgb, params.blend), current.a);
+}
@@ -0,0 +1,139 @@
+// Sky of a planet's atmosphere, the light of the sun scattered towards the camera by molecules
+// (Rayleigh scattering) and aerosols (Mie scattering), integrated along the view ray. Matches
+// `Atmosphere::radiance`, which computes the ambient light on the CPU.
+
+const PI: f32 = 3.14159265359;
+const VIEW_SAMPLES: u32 = 16u;
+const LIGHT_SAMPLES: u32 = 8u;
+
+struct Params {
+    // Transforms clip coordinates to directions from the camera in world coordinates.
+    inverse_view_projection: mat4x4<f32>,
+    // Direction towards the sun and cosine of the angular radius of its disk.
+    sun_direction: vec4<f32>,
+    sun_illuminance: vec4<f32>,
+    // Rayleigh scattering coefficients and scale height.
+    rayleigh: vec4<f32>,
+    // Mie scattering and absorption coefficients, scale height, and anisotropy.
+    mie: vec4<f32>,
+    // Radius of the planet, radius of the top of the atmosphere, and altitude of the camera.
+    planet: vec4<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> params: Params;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) clip: vec2<f32>,
+};
+
+// Triangle covering the whole target.
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+
+    var out: VertexOutput;
+    out.clip = vec2<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0);
+    out.position = vec4<f32>(out.clip, 0.0, 1.0);
+    return out;
+}
+
+// Returns the half of the linear coefficient and the discriminant of the intersection of the ray
+// with the sphere around the origin, factoring the constant coefficient to keep precision for
+// spheres as large as a planet.
+fn sphere_discriminant(origin: vec3<f32>, direction: vec3<f32>, radius: f32) -> vec2<f32> {
+    let b = dot(origin, direction);
+    let distance = length(origin);
+    return vec2<f32>(b, b * b - (distance - radius) * (distance + radius));
+}
+
+// Returns the distance to where the ray from inside the sphere leaves it.
+fn sphere_exit(origin: vec3<f32>, direction: vec3<f32>, radius: f32) -> f32 {
+    let d = sphere_discriminant(origin, direction, radius);
+    return max(-d.x + sqrt(max(d.y, 0.0)), 0.0);
+}
+
+// Returns the distance to where the ray from outside or on the sphere enters it, or -1 if it
+// doesn't.
+fn sphere_entry(origin: vec3<f32>, direction: vec3<f32>, radius: f32) -> f32 {
+    let d = sphere_discriminant(origin, direction, radius);
+    if d.y < 0.0 {
+        return -1.0;
+    }
+    let distance = -d.x - sqrt(d.y);
+    return select(-1.0, distance, d.x < 0.0 && distance >= 0.0);
+}
+
+// Returns the relative densities of the molecules and aerosols at the point.
+fn density(point: vec3<f32>) -> vec2<f32> {
+    let height = max(length(point) - params.planet.x, 0.0);
+    return exp(-height / vec2<f32>(params.rayleigh.w, params.mie.z));
+}
+
+// Returns the fraction of the light passing through the optical depths.
+fn transmittance(depth: vec2<f32>) -> vec3<f32> {
+    let mie = params.mie.x + params.mie.y;
+    return exp(-(params.rayleigh.xyz * depth.x + mie * depth.y));
+}
+
+// Returns the optical depths from the point to the top of the atmosphere towards the sun, or -1
+// if the planet is in the way.
+fn light_depth(point: vec3<f32>, direction: vec3<f32>) -> vec2<f32> {
+    if sphere_entry(point, direction, params.planet.x) >= 0.0 {
+        return vec2<f32>(-1.0);
+    }
+    let step = sphere_exit(point, direction, params.planet.y) / f32(LIGHT_SAMPLES);
+    var depth = vec2<f32>(0.0);
+    for (var i = 0u; i < LIGHT_SAMPLES; i++) {
+        depth += density(point + direction * (f32(i) + 0.5) * step);
+    }
+    return depth * step;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let far = params.inverse_view_projection * vec4<f32>(in.clip, 1.0, 1.0);
+    let direction = normalize(far.xyz / far.w);
+    let sun = params.sun_direction.xyz;
+    let origin = vec3<f32>(0.0, params.planet.x + params.planet.z, 0.0);
+    var ray_length = sphere_exit(origin, direction, params.planet.y);
+    let ground = sphere_entry(origin, direction, params.planet.x);
+    if ground >= 0.0 {
+        ray_length = min(ray_length, ground);
+    }
+
+    let step = ray_length / f32(VIEW_SAMPLES);
+    var depth = vec2<f32>(0.0);
+    var rayleigh = vec3<f32>(0.0);
+    var mie = vec3<f32>(0.0);
+    for (var i = 0u; i < VIEW_SAMPLES; i++) {
+        let point = origin + direction * (f32(i) + 0.5) * step;
+        let sample_density = density(point) * step;
+        depth += sample_density;
+        let light = light_depth(point, sun);
+        if light.x < 0.0 {
+            continue;
+        }
+        let attenuation = transmittance(depth + light);
+        rayleigh += attenuation * sample_density.x;
+        mie += attenuation * sample_density.y;
+    }
+
+    let cos_angle = dot(direction, sun);
+    let rayleigh_phase = 3.0 / (16.0 * PI) * (1.0 + cos_angle * cos_angle);
+    let g = params.mie.w;
+    let mie_phase = 3.0 / (8.0 * PI) * (1.0 - g * g) * (1.0 + cos_angle * cos_angle)
+        / ((2.0 + g * g) * pow(1.0 + g * g - 2.0 * g * cos_angle, 1.5));
+    var color = params.sun_illuminance.rgb
+        * (rayleigh * params.rayleigh.xyz * rayleigh_phase + mie * params.mie.x * mie_phase);
+
+    // The sun's disk, dimmed by the atmosphere in front of it, spreads its illuminance over the
+    // solid angle it covers.
+    let cos_radius = params.sun_direction.w;
+    if ground < 0.0 && cos_radius < 1.0 && cos_angle >= cos_radius {
+        let solid_angle = 2.0 * PI * (1.0 - cos_radius);
+        color += params.sun_illuminance.rgb * transmittance(depth) / solid_angle;
+    }
+    return vec4<f32>(color, 1.0);
+}
@@ -0,0 +1,91 @@
+// Bloom of the high dynamic range target. The light above the threshold is downsampled into the
+// mips of a bloom texture, which are upsampled and added to each other from the smallest mip, and
+// the first mip is finally added to the target.
+
+struct Params {
+    // Brightness above which light blooms.
+    threshold: f32,
+    // Range below the threshold in which light blooms partially, for a smooth transition.
+    knee: f32,
+    // Fraction of the blurred light added to the target.
+    intensity: f32,
+    // Number of mips of the bloom texture, each adding the light once.
+    mips: f32,
+};
+
+@group(0) @binding(0)
+var<uniform> params: Params;
+
+@group(0) @binding(1)
+var source: texture_2d<f32>;
+
+@group(0) @binding(2)
+var source_sampler: sampler;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+// Triangle covering the whole target, with UV coordinates from its top-left corner.
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+
+    var out: VertexOutput;
+    out.position = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    out.uv = uv;
+    return out;
+}
+
+// Averages the 4x4 texels of the source around the UV coordinates with four bilinear samples.
+fn box_filter(uv: vec2<f32>) -> vec3<f32> {
+    let texel = 1.0 / vec2<f32>(textureDimensions(source));
+    let a = textureSampleLevel(source, source_sampler, uv + texel * vec2<f32>(-1.0, -1.0), 0.0);
+    let b = textureSampleLevel(source, source_sampler, uv + texel * vec2<f32>(1.0, -1.0), 0.0);
+    let c = textureSampleLevel(source, source_sampler, uv + texel * vec2<f32>(-1.0, 1.0), 0.0);
+    let d = textureSampleLevel(source, source_sampler, uv + texel * vec2<f32>(1.0, 1.0), 0.0);
+    return (a.rgb + b.rgb + c.rgb + d.rgb) * 0.25;
+}
+
+// Blurs the source around the UV coordinates with a 3x3 tent filter.
+fn tent_filter(uv: vec2<f32>) -> vec3<f32> {
+    let texel = 1.0 / vec2<f32>(textureDimensions(source));
+    var color = vec3<f32>(0.0);
+    for (var y = -1; y <= 1; y++) {
+        for (var x = -1; x <= 1; x++) {
+            let weight = f32((2 - abs(x)) * (2 - abs(y))) / 16.0;
+            let offset = texel * vec2<f32>(f32(x), f32(y));
+            color += textureSampleLevel(source, source_sampler, uv + offset, 0.0).rgb * weight;
+        }
+    }
+    return color;
+}
+
+// Keeps the light above the threshold, with a quadratic curve in the knee below it.
+@fragment
+fn fs_prefilter(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = box_filter(in.uv);
+    let brightness = max(color.r, max(color.g, color.b));
+    let soft = clamp(brightness - params.threshold + params.knee, 0.0, 2.0 * params.knee);
+    let soft_contribution = soft * soft / (4.0 * params.knee + 1e-5);
+    let contribution = max(soft_contribution, brightness - params.threshold) / max(brightness, 1e-5);
+    return vec4<f32>(color * contribution, 1.0);
+}
+
+@fragment
+fn fs_downsample(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(box_filter(in.uv), 1.0);
+}
+
+// Blurs the smaller mip, which is added to the larger one by blending.
+@fragment
+fn fs_upsample(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(tent_filter(in.uv), 1.0);
+}
+
+// Blurs the first mip, which is added to the target by blending.
+@fragment
+fn fs_composite(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(tent_filter(in.uv) * params.intensity / params.mips, 1.0);
+}
@@ -0,0 +1,108 @@
+// Binning of a camera's point and spot lights into its clusters. Each invocation tests the lights
+// against the view space bounding box of one cluster, and writes the number of lights reaching it
+// followed by their indices in the light buffer.
+
+struct Light {
+    color: vec4<f32>,
+    // Position and range.
+    position: vec4<f32>,
+    direction: vec4<f32>,
+    spot: vec4<f32>,
+    shadow: vec4<f32>,
+};
+
+struct Clusters {
+    // Number of tiles along X and Y, number of slices, and maximum number of lights per cluster.
+    dimensions: vec4<u32>,
+    // Depth the slices start growing from and depth of the far plane.
+    params: vec4<f32>,
+    // Position and size of the camera's viewport in the target.
+    viewport: vec4<f32>,
+    view: mat4x4<f32>,
+    inverse_projection: mat4x4<f32>,
+};
+
+struct Lights {
+    ambient: vec4<f32>,
+    // Number of directional lights, number of all lights, index of the first light, and index of
+    // the first cluster.
+    count: vec4<u32>,
+    cascade_splits: vec4<f32>,
+    shadow_params: vec4<f32>,
+    environment: vec4<f32>,
+    clusters: Clusters,
+    shadow_matrices: array<mat4x4<f32>, 8>,
+};
+
+@group(0) @binding(0)
+var<uniform> lights: Lights;
+
+@group(0) @binding(1)
+var<storage, read> light_list: array<Light>;
+
+@group(0) @binding(2)
+var<storage, read_write> clusters: array<u32>;
+
+// Returns the view depth at which the slice starts.
+fn slice_depth(slice: u32) -> f32 {
+    let near = lights.clusters.params.x;
+    let far = lights.clusters.params.y;
+    return near * pow(far / near, f32(slice) / f32(lights.clusters.dimensions.z));
+}
+
+// Returns the view space point at the depth on the line through the NDC position from the near to
+// the far plane, so it works for perspective and orthographic projections alike.
+fn view_point(ndc: vec2<f32>, depth: f32) -> vec3<f32> {
+    let near = lights.clusters.inverse_projection * vec4<f32>(ndc, 0.0, 1.0);
+    let far = lights.clusters.inverse_projection * vec4<f32>(ndc, 1.0, 1.0);
+    let a = near.xyz / near.w;
+    let b = far.xyz / far.w;
+    return a + (b - a) * ((-depth - a.z) / (b.z - a.z));
+}
+
+@compute @workgroup_size(64)
+fn cs_main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let dimensions = lights.clusters.dimensions;
+    let index = id.x;
+    if index >= dimensions.x * dimensions.y * dimensions.z {
+        return;
+    }
+
+    let x = index % dimensions.x;
+    let y = index / dimensions.x % dimensions.y;
+    let z = index / (dimensions.x * dimensions.y);
+    var near = 0.0;
+    if z > 0u {
+        near = slice_depth(z);
+    }
+    let far = slice_depth(z + 1u);
+    // Tiles are counted from the top left of the target like fragment coordinates.
+    let tile = vec2<f32>(f32(x), f32(y)) / vec2<f32>(dimensions.xy);
+    let tile_size = vec2<f32>(2.0, -2.0) / vec2<f32>(dimensions.xy);
+    let corner = vec2<f32>(tile.x * 2.0 - 1.0, 1.0 - tile.y * 2.0);
+    var min_point = vec3<f32>(3.4e38);
+    var max_point = vec3<f32>(-3.4e38);
+    for (var i = 0u; i < 8u; i++) {
+        let offset = vec2<f32>(f32(i & 1u), f32((i >> 1u) & 1u)) * tile_size;
+        var depth = near;
+        if (i & 4u) != 0u {
+            depth = far;
+        }
+        let point = view_point(corner + offset, depth);
+        min_point = min(min_point, point);
+        max_point = max(max_point, point);
+    }
+
+    let base = lights.count.w + index * (dimensions.w + 1u);
+    var found = 0u;
+    for (var i = lights.count.x; i < lights.count.y && found < dimensions.w; i++) {
+        let light = light_list[lights.count.z + i];
+        let center = (lights.clusters.view * vec4<f32>(light.position.xyz, 1.0)).xyz;
+        let offset = clamp(center, min_point, max_point) - center;
+        if dot(offset, offset) <= light.position.w * light.position.w {
+            clusters[base + 1u + found] = lights.count.z + i;
+            found++;
+        }
+    }
+    clusters[base] = found;
+}
@@ -0,0 +1,65 @@
+// Lines drawn over the meshes by the debug views: the edges of the meshes' triangles and the
+// bounding boxes of the nodes.
+
+struct Camera {
+    view_projection: mat4x4<f32>,
+    position: vec4<f32>,
+    forward: vec4<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> camera: Camera;
+
+// Joint matrices of all skinned meshes, used by `skin_matrix`.
+@group(0) @binding(6)
+var<storage, read> joint_palettes: array<mat4x4<f32>>;
+// Offsets of all morph targets and weights of all morphed instances, used by `morph`.
+@group(0) @binding(7)
+var<storage, read> morph_targets: array<vec4<f32>>;
+@group(0) @binding(8)
+var<storage, read> morph_weights: array<u32>;
+
+// Returns the clip position of the world position, moved slightly towards the camera so lines
+// aren't hidden by the surfaces they lie on.
+fn clip_position(position: vec3<f32>) -> vec4<f32> {
+    let clip = camera.view_projection * vec4<f32>(position, 1.0);
+    return vec4<f32>(clip.xy, clip.z - 1e-4 * clip.w, clip.w);
+}
+
+struct Instance {
+    @location(4) model_0: vec4<f32>,
+    @location(5) model_1: vec4<f32>,
+    @location(6) model_2: vec4<f32>,
+    @location(7) model_3: vec4<f32>,
+    @location(14) skin: u32,
+    @location(15) morph: u32,
+};
+
+@vertex
+fn vs_wireframe(
+    @location(0) position: vec3<f32>,
+    @location(12) joints: vec4<u32>,
+    @location(13) weights: vec4<f32>,
+    @builtin(vertex_index) index: u32,
+    instance: Instance,
+) -> @builtin(position) vec4<f32> {
+    let offsets = morph(index, instance.morph);
+    let skin = skin_matrix(joints, weights, instance.skin);
+    let model = mat4x4<f32>(instance.model_0, instance.model_1, instance.model_2, instance.model_3);
+    return clip_position((model * (skin * vec4<f32>(position + offsets.position, 1.0))).xyz);
+}
+
+@fragment
+fn fs_wireframe() -> @location(0) vec4<f32> {
+    return vec4<f32>(1.0, 1.0, 1.0, 1.0);
+}
+
+@vertex
+fn vs_bounds(@location(0) position: vec3<f32>) -> @builtin(position) vec4<f32> {
+    return clip_position(position);
+}
+
+@fragment
+fn fs_bounds() -> @location(0) vec4<f32> {
+    return vec4<f32>(1.0, 1.0, 0.0, 1.0);
+}
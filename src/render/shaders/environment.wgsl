@@ -0,0 +1,191 @@
+// Generation of the cubemaps of an environment light from an equirectangular image or the images
+// of the cube's faces: a source cubemap and its mips, a specular cubemap prefiltered for increasing roughness in its mips, and
+// an irradiance cubemap.
+
+const PI: f32 = 3.14159265359;
+
+struct Params {
+    // Cube face being rendered, from +X to -Z.
+    face: u32,
+    // Roughness the specular mip being rendered is prefiltered for.
+    roughness: f32,
+    // Size of the source cubemap's first mip in texels.
+    source_size: f32,
+    // Number of mips of the source cubemap.
+    source_mips: f32,
+};
+
+@group(0) @binding(0)
+var<uniform> params: Params;
+
+@group(0) @binding(1)
+var equirectangular: texture_2d<f32>;
+
+@group(0) @binding(2)
+var source_mip: texture_cube<f32>;
+
+@group(0) @binding(3)
+var source: texture_cube<f32>;
+
+@group(0) @binding(4)
+var source_sampler: sampler;
+
+@group(0) @binding(5)
+var face_image: texture_2d<f32>;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+// Triangle covering the whole face, with UV coordinates from its top-left corner.
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+
+    var out: VertexOutput;
+    out.position = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    out.uv = uv;
+    return out;
+}
+
+// Returns the direction through the UV coordinates of the cube face being rendered.
+fn face_direction(uv: vec2<f32>) -> vec3<f32> {
+    let u = uv.x * 2.0 - 1.0;
+    let v = uv.y * 2.0 - 1.0;
+    switch params.face {
+        case 0u: { return normalize(vec3<f32>(1.0, -v, -u)); }
+        case 1u: { return normalize(vec3<f32>(-1.0, -v, u)); }
+        case 2u: { return normalize(vec3<f32>(u, 1.0, v)); }
+        case 3u: { return normalize(vec3<f32>(u, -1.0, -v)); }
+        case 4u: { return normalize(vec3<f32>(u, -v, 1.0)); }
+        default: { return normalize(vec3<f32>(-u, -v, -1.0)); }
+    }
+}
+
+// Returns the orthonormal basis around the normal.
+fn basis(normal: vec3<f32>) -> mat3x3<f32> {
+    let up = select(vec3<f32>(1.0, 0.0, 0.0), vec3<f32>(0.0, 0.0, 1.0), abs(normal.z) < 0.999);
+    let tangent = normalize(cross(up, normal));
+    return mat3x3<f32>(tangent, cross(normal, tangent), normal);
+}
+
+// Returns the i-th of n points evenly distributed in the unit square.
+fn hammersley(i: u32, n: u32) -> vec2<f32> {
+    return vec2<f32>(f32(i) / f32(n), f32(reverseBits(i)) * 2.3283064365386963e-10);
+}
+
+// Returns the mip of the source cubemap whose texels cover the solid angle of one of the samples
+// drawn with the probability density, so few samples don't miss small bright features.
+fn source_lod(pdf: f32, samples: u32) -> f32 {
+    let sample_angle = 1.0 / (f32(samples) * max(pdf, 1e-6));
+    let texel_angle = 4.0 * PI / (6.0 * params.source_size * params.source_size);
+    return clamp(0.5 * log2(sample_angle / texel_angle) + 1.0, 0.0, params.source_mips - 1.0);
+}
+
+@fragment
+fn fs_equirectangular(in: VertexOutput) -> @location(0) vec4<f32> {
+    let direction = face_direction(in.uv);
+    let uv = vec2<f32>(
+        atan2(direction.x, -direction.z) / (2.0 * PI) + 0.5,
+        acos(clamp(direction.y, -1.0, 1.0)) / PI,
+    );
+
+    // Bilinear filtering, wrapping around horizontally. 32-bit float textures can't be filtered
+    // by samplers on all devices.
+    let size = vec2<i32>(textureDimensions(equirectangular));
+    let position = uv * vec2<f32>(size) - 0.5;
+    let base = vec2<i32>(floor(position));
+    let weight = fract(position);
+    let x0 = (base.x % size.x + size.x) % size.x;
+    let x1 = (x0 + 1) % size.x;
+    let y0 = clamp(base.y, 0, size.y - 1);
+    let y1 = clamp(base.y + 1, 0, size.y - 1);
+    let top = mix(
+        textureLoad(equirectangular, vec2<i32>(x0, y0), 0),
+        textureLoad(equirectangular, vec2<i32>(x1, y0), 0),
+        weight.x,
+    );
+    let bottom = mix(
+        textureLoad(equirectangular, vec2<i32>(x0, y1), 0),
+        textureLoad(equirectangular, vec2<i32>(x1, y1), 0),
+        weight.x,
+    );
+    return vec4<f32>(mix(top, bottom, weight.y).rgb, 1.0);
+}
+
+// Samples the image of a camera looking along the face, which is mirrored horizontally as cube
+// faces are laid out as seen from outside the cube.
+@fragment
+fn fs_face(in: VertexOutput) -> @location(0) vec4<f32> {
+    let uv = vec2<f32>(1.0 - in.uv.x, in.uv.y);
+    return vec4<f32>(textureSampleLevel(face_image, source_sampler, uv, 0.0).rgb, 1.0);
+}
+
+// Averages the 2x2 texels of the previous mip around each texel of the face.
+@fragment
+fn fs_downsample(in: VertexOutput) -> @location(0) vec4<f32> {
+    return textureSampleLevel(source_mip, source_sampler, face_direction(in.uv), 0.0);
+}
+
+const SPECULAR_SAMPLES: u32 = 64u;
+
+// GGX normal distribution of the microfacets, with alpha the squared roughness.
+fn distribution(n_dot_h: f32, alpha: f32) -> f32 {
+    let a2 = alpha * alpha;
+    let d = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+    return a2 / (PI * d * d);
+}
+
+// Integrates the light reflected by a surface with the roughness seen along its normal, with GGX
+// importance sampling.
+@fragment
+fn fs_prefilter(in: VertexOutput) -> @location(0) vec4<f32> {
+    let normal = face_direction(in.uv);
+    if params.roughness == 0.0 {
+        return textureSampleLevel(source, source_sampler, normal, 0.0);
+    }
+
+    let alpha = params.roughness * params.roughness;
+    let tangent_to_world = basis(normal);
+    var color = vec3<f32>(0.0);
+    var weight = 0.0;
+    for (var i = 0u; i < SPECULAR_SAMPLES; i++) {
+        let xi = hammersley(i, SPECULAR_SAMPLES);
+        let phi = 2.0 * PI * xi.x;
+        let cos_theta = sqrt((1.0 - xi.y) / (1.0 + (alpha * alpha - 1.0) * xi.y));
+        let sin_theta = sqrt(1.0 - cos_theta * cos_theta);
+        let half = tangent_to_world * vec3<f32>(cos(phi) * sin_theta, sin(phi) * sin_theta, cos_theta);
+        let direction = 2.0 * dot(normal, half) * half - normal;
+        let n_dot_l = dot(normal, direction);
+        if n_dot_l > 0.0 {
+            // With the view along the normal, the density of the reflected direction is D / 4.
+            let pdf = distribution(cos_theta, alpha) * 0.25;
+            let lod = source_lod(pdf, SPECULAR_SAMPLES);
+            color += textureSampleLevel(source, source_sampler, direction, lod).rgb * n_dot_l;
+            weight += n_dot_l;
+        }
+    }
+    return vec4<f32>(color / max(weight, 1e-4), 1.0);
+}
+
+const IRRADIANCE_SAMPLES: u32 = 256u;
+
+// Integrates the light arriving at a surface with the normal, weighted by the cosine and divided
+// by pi, so it only needs to be multiplied with the diffuse color. Samples are cosine weighted.
+@fragment
+fn fs_irradiance(in: VertexOutput) -> @location(0) vec4<f32> {
+    let normal = face_direction(in.uv);
+    let tangent_to_world = basis(normal);
+    var color = vec3<f32>(0.0);
+    for (var i = 0u; i < IRRADIANCE_SAMPLES; i++) {
+        let xi = hammersley(i, IRRADIANCE_SAMPLES);
+        let phi = 2.0 * PI * xi.x;
+        let cos_theta = sqrt(1.0 - xi.y);
+        let sin_theta = sqrt(xi.y);
+        let direction = tangent_to_world * vec3<f32>(cos(phi) * sin_theta, sin(phi) * sin_theta, cos_theta);
+        let lod = source_lod(cos_theta / PI, IRRADIANCE_SAMPLES);
+        color += textureSampleLevel(source, source_sampler, direction, lod).rgb;
+    }
+    return vec4<f32>(color / f32(IRRADIANCE_SAMPLES), 1.0);
+}
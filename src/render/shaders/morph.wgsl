@@ -0,0 +1,28 @@
+
+// Offsets of a vertex's position and normal by the morph targets of its instance.
+struct Morph {
+    position: vec3<f32>,
+    normal: vec3<f32>,
+};
+
+// Returns the offsets of the vertex by its mesh's morph targets, with the index of the instance's
+// weights in `morph_weights`, or zero offsets if the instance isn't morphed.
+fn morph(vertex: u32, weights: u32) -> Morph {
+    var out = Morph(vec3<f32>(0.0), vec3<f32>(0.0));
+    if weights == 0xffffffffu {
+        return out;
+    }
+    let first = morph_weights[weights];
+    let count = morph_weights[weights + 1u];
+    let vertex_count = morph_weights[weights + 2u];
+    for (var shape = 0u; shape < count; shape++) {
+        let weight = bitcast<f32>(morph_weights[weights + 3u + shape]);
+        if weight == 0.0 {
+            continue;
+        }
+        let index = first + (shape * vertex_count + vertex) * 2u;
+        out.position += morph_targets[index].xyz * weight;
+        out.normal += morph_targets[index + 1u].xyz * weight;
+    }
+    return out;
+}
@@ -0,0 +1,613 @@
+// Physically based shading of meshes with a standard material. Optional textures are compiled in
+// with the shader defs BASE_COLOR_TEXTURE, METALLIC_ROUGHNESS_TEXTURE, NORMAL_TEXTURE,
+// EMISSIVE_TEXTURE, and OCCLUSION_TEXTURE, the alpha modes with ALPHA_MASK and ALPHA_BLEND, and
+// the debug shadings with DEBUG_NORMALS and DEBUG_OVERDRAW. With DEFERRED_GBUFFER the surface is
+// written to the G-buffer instead of being lit, and DEFERRED_LIGHTING adds the fullscreen pass
+// lighting the G-buffer.
+
+const PI: f32 = 3.14159265359;
+
+struct Fog {
+    // Color and falloff, zero without fog, one for linear, two for exponential, and three for
+    // exponential squared fog.
+    color: vec4<f32>,
+    // Start distance or density, end distance, height falloff, and base height.
+    params: vec4<f32>,
+    // Color and exponent of the sun's glow.
+    sun: vec4<f32>,
+};
+
+struct Camera {
+    view_projection: mat4x4<f32>,
+    position: vec4<f32>,
+    forward: vec4<f32>,
+    fog: Fog,
+    inverse_view_projection: mat4x4<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> camera: Camera;
+
+// Kinds of lights, stored in the `w` component of the direction.
+const DIRECTIONAL: f32 = 0.0;
+const SPOT: f32 = 2.0;
+
+struct Light {
+    // Color multiplied with the intensity.
+    color: vec4<f32>,
+    // Position and range.
+    position: vec4<f32>,
+    // Direction the light shines in and kind.
+    direction: vec4<f32>,
+    // Cosine of the outer angle and the inverse of the difference to the cosine of the inner angle.
+    spot: vec4<f32>,
+    // Index of the first shadow map, or -1 if the light has none, and the number of maps.
+    shadow: vec4<f32>,
+};
+
+struct Clusters {
+    // Number of tiles along X and Y, number of slices, and maximum number of lights per cluster.
+    dimensions: vec4<u32>,
+    // Depth the slices start growing from and depth of the far plane.
+    params: vec4<f32>,
+    // Position and size of the camera's viewport in the target.
+    viewport: vec4<f32>,
+    view: mat4x4<f32>,
+    inverse_projection: mat4x4<f32>,
+};
+
+struct Probe {
+    // Position and radius, zero without a probe.
+    position: vec4<f32>,
+    // Intensity and falloff.
+    params: vec4<f32>,
+};
+
+struct Lights {
+    ambient: vec4<f32>,
+    // Number of directional lights, number of all lights, index of the first light, and index of
+    // the first cluster.
+    count: vec4<u32>,
+    // View depth up to which each cascade of directional light shadow maps is used.
+    cascade_splits: vec4<f32>,
+    // PCF radius in texels, size of a texel, depth bias, and normal bias.
+    shadow_params: vec4<f32>,
+    // Intensity of the environment light, or zero without one, and the level of detail of the
+    // roughest mip of the specular maps.
+    environment: vec4<f32>,
+    clusters: Clusters,
+    shadow_matrices: array<mat4x4<f32>, 8>,
+    // Reflection probes nearest to the camera, blended by `environment_light`.
+    probes: array<Probe, 2>,
+};
+
+@group(0) @binding(1)
+var<uniform> lights: Lights;
+
+@group(0) @binding(2)
+var shadow_maps: texture_depth_2d_array;
+@group(0) @binding(3)
+var shadow_sampler: sampler_comparison;
+
+// Lights of all cameras.
+@group(0) @binding(4)
+var<storage, read> light_list: array<Light>;
+// Number of lights of each cluster followed by their indices in the light list.
+@group(0) @binding(5)
+var<storage, read> clusters: array<u32>;
+// Joint matrices of all skinned meshes, used by `skin_matrix`.
+@group(0) @binding(6)
+var<storage, read> joint_palettes: array<mat4x4<f32>>;
+// Offsets of all morph targets and weights of all morphed instances, used by `morph`.
+@group(0) @binding(7)
+var<storage, read> morph_targets: array<vec4<f32>>;
+@group(0) @binding(8)
+var<storage, read> morph_weights: array<u32>;
+
+@group(2) @binding(0)
+var specular_map: texture_cube<f32>;
+@group(2) @binding(1)
+var irradiance_map: texture_cube<f32>;
+@group(2) @binding(2)
+var environment_sampler: sampler;
+
+// Specular maps of the reflection probes, black for unused probes.
+@group(3) @binding(0)
+var probe_map_0: texture_cube<f32>;
+@group(3) @binding(1)
+var probe_map_1: texture_cube<f32>;
+
+#ifndef CUSTOM_MATERIAL
+struct Material {
+    base_color: vec4<f32>,
+    emissive: vec4<f32>,
+    metallic: f32,
+    roughness: f32,
+    normal_scale: f32,
+    occlusion_strength: f32,
+    alpha_cutoff: f32,
+};
+
+@group(1) @binding(0)
+var<uniform> material: Material;
+
+#ifdef BASE_COLOR_TEXTURE
+@group(1) @binding(1)
+var base_color_texture: texture_2d<f32>;
+@group(1) @binding(2)
+var base_color_sampler: sampler;
+#endif
+
+#ifdef METALLIC_ROUGHNESS_TEXTURE
+@group(1) @binding(3)
+var metallic_roughness_texture: texture_2d<f32>;
+@group(1) @binding(4)
+var metallic_roughness_sampler: sampler;
+#endif
+
+#ifdef NORMAL_TEXTURE
+@group(1) @binding(5)
+var normal_texture: texture_2d<f32>;
+@group(1) @binding(6)
+var normal_sampler: sampler;
+#endif
+
+#ifdef EMISSIVE_TEXTURE
+@group(1) @binding(7)
+var emissive_texture: texture_2d<f32>;
+@group(1) @binding(8)
+var emissive_sampler: sampler;
+#endif
+
+#ifdef OCCLUSION_TEXTURE
+@group(1) @binding(9)
+var occlusion_texture: texture_2d<f32>;
+@group(1) @binding(10)
+var occlusion_sampler: sampler;
+#endif
+#endif
+
+struct Vertex {
+    @location(0) position: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) tangent: vec4<f32>,
+    @location(3) uv: vec2<f32>,
+    @location(12) joints: vec4<u32>,
+    @location(13) weights: vec4<f32>,
+    @builtin(vertex_index) index: u32,
+};
+
+struct Instance {
+    @location(4) model_0: vec4<f32>,
+    @location(5) model_1: vec4<f32>,
+    @location(6) model_2: vec4<f32>,
+    @location(7) model_3: vec4<f32>,
+    @location(8) normal_0: vec3<f32>,
+    @location(9) normal_1: vec3<f32>,
+    @location(10) normal_2: vec3<f32>,
+    @location(11) shadow_receiver: f32,
+    @location(14) skin: u32,
+    @location(15) morph: u32,
+};
+
+struct VertexOutput {
+    @builtin(position) @invariant clip_position: vec4<f32>,
+    @location(0) world_position: vec3<f32>,
+    @location(1) world_normal: vec3<f32>,
+    @location(2) world_tangent: vec4<f32>,
+    @location(3) uv: vec2<f32>,
+    @location(4) shadow_receiver: f32,
+};
+
+@vertex
+fn vs_main(vertex: Vertex, instance: Instance) -> VertexOutput {
+    let offsets = morph(vertex.index, instance.morph);
+    let skin = skin_matrix(vertex.joints, vertex.weights, instance.skin);
+    let skin_3 = mat3x3<f32>(skin[0].xyz, skin[1].xyz, skin[2].xyz);
+    let model = mat4x4<f32>(instance.model_0, instance.model_1, instance.model_2, instance.model_3);
+    let normal = mat3x3<f32>(instance.normal_0, instance.normal_1, instance.normal_2);
+    let world_position = model * (skin * vec4<f32>(vertex.position + offsets.position, 1.0));
+
+    var out: VertexOutput;
+    out.clip_position = camera.view_projection * world_position;
+    out.world_position = world_position.xyz;
+    // Joints are assumed to be rotated and scaled uniformly, so their rotation also moves normals.
+    out.world_normal = normal * (skin_3 * (vertex.normal + offsets.normal));
+    let tangent =
+        mat3x3<f32>(model[0].xyz, model[1].xyz, model[2].xyz) * (skin_3 * vertex.tangent.xyz);
+    out.world_tangent = vec4<f32>(tangent, vertex.tangent.w);
+    out.uv = vertex.uv;
+    out.shadow_receiver = instance.shadow_receiver;
+    return out;
+}
+
+// Properties of the surface at a fragment.
+struct Surface {
+    base_color: vec3<f32>,
+    metallic: f32,
+    roughness: f32,
+    normal: vec3<f32>,
+    view: vec3<f32>,
+};
+
+// GGX normal distribution of the microfacets.
+fn distribution(n_dot_h: f32, roughness: f32) -> f32 {
+    let a = roughness * roughness;
+    let a2 = a * a;
+    let d = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+    return a2 / (PI * d * d);
+}
+
+// Height-correlated Smith visibility of the microfacets.
+fn visibility(n_dot_v: f32, n_dot_l: f32, roughness: f32) -> f32 {
+    let a = roughness * roughness;
+    let a2 = a * a;
+    let v = n_dot_l * sqrt(n_dot_v * n_dot_v * (1.0 - a2) + a2);
+    let l = n_dot_v * sqrt(n_dot_l * n_dot_l * (1.0 - a2) + a2);
+    return 0.5 / (v + l);
+}
+
+fn fresnel(f0: vec3<f32>, v_dot_h: f32) -> vec3<f32> {
+    return f0 + (1.0 - f0) * pow(1.0 - v_dot_h, 5.0);
+}
+
+// Returns the light reflected towards the viewer from light arriving from the direction.
+fn shade(surface: Surface, direction: vec3<f32>, radiance: vec3<f32>) -> vec3<f32> {
+    let half = normalize(surface.view + direction);
+    let n_dot_l = max(dot(surface.normal, direction), 0.0);
+    let n_dot_v = max(dot(surface.normal, surface.view), 1e-4);
+    let n_dot_h = max(dot(surface.normal, half), 0.0);
+    let v_dot_h = max(dot(surface.view, half), 0.0);
+
+    let f0 = mix(vec3<f32>(0.04), surface.base_color, surface.metallic);
+    let f = fresnel(f0, v_dot_h);
+    let specular = f * distribution(n_dot_h, surface.roughness)
+        * visibility(n_dot_v, n_dot_l, surface.roughness);
+    let diffuse = (1.0 - f) * (1.0 - surface.metallic) * surface.base_color / PI;
+    return (diffuse + specular) * radiance * n_dot_l;
+}
+
+// Returns the fraction of the specular color reflected towards the viewer, integrated over the
+// environment, with Karis' analytic approximation of the split-sum lookup table.
+fn environment_brdf(f0: vec3<f32>, roughness: f32, n_dot_v: f32) -> vec3<f32> {
+    let c0 = vec4<f32>(-1.0, -0.0275, -0.572, 0.022);
+    let c1 = vec4<f32>(1.0, 0.0425, 1.04, -0.04);
+    let r = roughness * c0 + c1;
+    let a004 = min(r.x * r.x, exp2(-9.28 * n_dot_v)) * r.x + r.y;
+    let ab = vec2<f32>(-1.04, 1.04) * a004 + r.zw;
+    return f0 * ab.x + ab.y;
+}
+
+// Returns the weight of the reflection probe at the position, one inside its radius minus the
+// falloff and fading to zero at its radius.
+fn probe_weight(probe: Probe, position: vec3<f32>) -> f32 {
+    let distance = length(position - probe.position.xyz);
+    return clamp((probe.position.w - distance) / max(probe.params.y, 1e-4), 0.0, 1.0);
+}
+
+// Returns the light of the environment reflected towards the viewer at the position, with the
+// specular light of the reflection probes reaching the position blended over the environment's.
+fn environment_light(surface: Surface, position: vec3<f32>) -> vec3<f32> {
+    let n_dot_v = max(dot(surface.normal, surface.view), 1e-4);
+    let f0 = mix(vec3<f32>(0.04), surface.base_color, surface.metallic);
+    let irradiance = textureSampleLevel(irradiance_map, environment_sampler, surface.normal, 0.0).rgb;
+    let reflection = reflect(-surface.view, surface.normal);
+    let lod = surface.roughness * lights.environment.y;
+    var specular = textureSampleLevel(specular_map, environment_sampler, reflection, lod).rgb;
+    specular *= lights.environment.x;
+
+    // Overlapping probes are normalized, and the environment fills in where they fade out.
+    let weights = vec2<f32>(
+        probe_weight(lights.probes[0], position),
+        probe_weight(lights.probes[1], position),
+    );
+    let total = weights.x + weights.y;
+    if total > 0.0 {
+        let probes = textureSampleLevel(probe_map_0, environment_sampler, reflection, lod).rgb
+            * lights.probes[0].params.x * weights.x
+            + textureSampleLevel(probe_map_1, environment_sampler, reflection, lod).rgb
+            * lights.probes[1].params.x * weights.y;
+        specular = mix(specular, probes / total, min(total, 1.0));
+    }
+
+    let diffuse_color = surface.base_color * (1.0 - surface.metallic);
+    let diffuse = irradiance * diffuse_color * lights.environment.x;
+    return diffuse + specular * environment_brdf(f0, surface.roughness, n_dot_v);
+}
+
+// Returns the fraction of the light reaching the position according to the light's shadow maps,
+// filtered with a PCF kernel.
+fn shadow_factor(light: Light, position: vec3<f32>, normal: vec3<f32>) -> f32 {
+    if light.shadow.x < 0.0 {
+        return 1.0;
+    }
+
+    var layer = i32(light.shadow.x);
+    if light.direction.w == DIRECTIONAL {
+        // Use the first cascade containing the fragment, and no shadows beyond the last one.
+        let depth = dot(position - camera.position.xyz, camera.forward.xyz);
+        let count = i32(light.shadow.y);
+        var cascade = 0;
+        while cascade < count && depth > lights.cascade_splits[cascade] {
+            cascade++;
+        }
+        if cascade == count {
+            return 1.0;
+        }
+        layer += cascade;
+    }
+
+    let biased = position + normal * lights.shadow_params.w;
+    let clip = lights.shadow_matrices[layer] * vec4<f32>(biased, 1.0);
+    let ndc = clip.xyz / clip.w;
+    let uv = vec2<f32>(ndc.x * 0.5 + 0.5, 0.5 - ndc.y * 0.5);
+    if any(uv < vec2<f32>(0.0)) || any(uv > vec2<f32>(1.0)) || ndc.z > 1.0 {
+        return 1.0;
+    }
+
+    let depth = ndc.z - lights.shadow_params.z;
+    let radius = i32(lights.shadow_params.x);
+    let texel = lights.shadow_params.y;
+    var lit = 0.0;
+    for (var y = -radius; y <= radius; y++) {
+        for (var x = -radius; x <= radius; x++) {
+            let offset = vec2<f32>(f32(x), f32(y)) * texel;
+            lit += textureSampleCompareLevel(shadow_maps, shadow_sampler, uv + offset, layer, depth);
+        }
+    }
+    let size = f32(2 * radius + 1);
+    return lit / (size * size);
+}
+
+// Returns the light of the light reflected towards the viewer by the surface at the fragment,
+// shadowed if the fragment receives shadows.
+fn light_color(light: Light, surface: Surface, in: VertexOutput) -> vec3<f32> {
+    var direction = -light.direction.xyz;
+    var radiance = light.color.rgb;
+    if light.direction.w != DIRECTIONAL {
+        let offset = light.position.xyz - in.world_position;
+        let distance_squared = max(dot(offset, offset), 1e-4);
+        direction = offset * inverseSqrt(distance_squared);
+        // Inverse square falloff, smoothly windowed to zero at the range.
+        let ratio = distance_squared / (light.position.w * light.position.w);
+        let window = saturate(1.0 - ratio * ratio);
+        radiance *= window * window / distance_squared;
+        if light.direction.w == SPOT {
+            let cos_angle = dot(-direction, light.direction.xyz);
+            let cone = saturate((cos_angle - light.spot.x) * light.spot.y);
+            radiance *= cone * cone;
+        }
+    }
+    if in.shadow_receiver > 0.0 {
+        radiance *= shadow_factor(light, in.world_position, normalize(in.world_normal));
+    }
+    return shade(surface, direction, radiance);
+}
+
+// Returns the index of the fragment's cluster in the cluster list.
+fn cluster_start(in: VertexOutput) -> u32 {
+    let dimensions = lights.clusters.dimensions;
+    let near = lights.clusters.params.x;
+    let far = lights.clusters.params.y;
+    let viewport = lights.clusters.viewport;
+    let tile = vec2<u32>((in.clip_position.xy - viewport.xy) / viewport.zw * vec2<f32>(dimensions.xy));
+    let depth = dot(in.world_position - camera.position.xyz, camera.forward.xyz);
+    // Slices grow exponentially from the near depth, and the first one covers the depths in front
+    // of it.
+    let slice = log(max(depth, near) / near) / log(far / near) * f32(dimensions.z);
+    let cluster = min(vec3<u32>(tile, u32(slice)), dimensions.xyz - 1u);
+    let index = (cluster.z * dimensions.y + cluster.y) * dimensions.x + cluster.x;
+    return lights.count.w + index * (dimensions.w + 1u);
+}
+
+// Returns the light of the camera's directional lights and the point and spot lights of the
+// fragment's cluster reflected towards the viewer by the surface at the fragment.
+fn lighting(surface: Surface, in: VertexOutput) -> vec3<f32> {
+    var color = vec3<f32>(0.0);
+    for (var i = 0u; i < lights.count.x; i++) {
+        color += light_color(light_list[lights.count.z + i], surface, in);
+    }
+    let start = cluster_start(in);
+    for (var i = 0u; i < clusters[start]; i++) {
+        color += light_color(light_list[clusters[start + 1u + i]], surface, in);
+    }
+    return color;
+}
+
+// Returns the color of the fragment at the world position as seen through the camera's fog.
+fn fog(color: vec3<f32>, world_position: vec3<f32>) -> vec3<f32> {
+    let falloff = camera.fog.color.w;
+    if falloff == 0.0 {
+        return color;
+    }
+
+    let params = camera.fog.params;
+    let offset = world_position - camera.position.xyz;
+    let view_distance = length(offset);
+    // Height fog thins out exponentially above the base height, so the distance is scaled by the
+    // average density along the view ray.
+    var distance = view_distance;
+    if params.z > 0.0 {
+        let rise = params.z * offset.y;
+        distance *= exp(-params.z * (camera.position.y - params.w));
+        if abs(rise) > 1e-4 {
+            distance *= (1.0 - exp(-rise)) / rise;
+        }
+    }
+    var amount: f32;
+    if falloff == 1.0 {
+        amount = saturate((distance - params.x) / max(params.y - params.x, 1e-4));
+    } else if falloff == 2.0 {
+        amount = 1.0 - exp(-params.x * distance);
+    } else {
+        let density = params.x * distance;
+        amount = 1.0 - exp(-density * density);
+    }
+
+    // The sun is the first directional light.
+    var fog_color = camera.fog.color.rgb;
+    if lights.count.x > 0u && view_distance > 0.0 {
+        let sun = light_list[lights.count.z];
+        let alignment = max(dot(offset / view_distance, -sun.direction.xyz), 0.0);
+        fog_color += camera.fog.sun.rgb * pow(alignment, camera.fog.sun.w);
+    }
+    return mix(color, fog_color, amount);
+}
+
+// Returns the color of the surface at the fragment lit by the ambient, environment, and camera's
+// lights, with its emissive color added and seen through the fog.
+fn surface_color(surface: Surface, emissive: vec3<f32>, occlusion: f32, in: VertexOutput) -> vec3<f32> {
+    var color = lights.ambient.rgb * surface.base_color * occlusion + emissive;
+    if lights.environment.x > 0.0 || lights.probes[0].position.w > 0.0 {
+        color += environment_light(surface, in.world_position) * occlusion;
+    }
+    color += lighting(surface, in);
+    return fog(color, in.world_position);
+}
+
+#ifdef DEFERRED_GBUFFER
+// Surface of the opaque fragments, lit by the deferred lighting pass.
+struct GBuffer {
+    // Base color and metallic.
+    @location(0) base_color: vec4<f32>,
+    // Normal and roughness.
+    @location(1) normal: vec4<f32>,
+    // Emissive color and ambient occlusion.
+    @location(2) emissive: vec4<f32>,
+    // One if the fragment receives shadows, zero otherwise.
+    @location(3) shadow_receiver: f32,
+};
+#endif
+
+#ifndef CUSTOM_MATERIAL
+@fragment
+#ifdef DEFERRED_GBUFFER
+fn fs_main(in: VertexOutput) -> GBuffer {
+#else
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+#endif
+    var base_color = material.base_color;
+#ifdef BASE_COLOR_TEXTURE
+    base_color *= textureSample(base_color_texture, base_color_sampler, in.uv);
+#endif
+#ifdef ALPHA_MASK
+    if base_color.a < material.alpha_cutoff {
+        discard;
+    }
+#endif
+
+    var metallic = material.metallic;
+    var roughness = material.roughness;
+#ifdef METALLIC_ROUGHNESS_TEXTURE
+    let metallic_roughness = textureSample(metallic_roughness_texture, metallic_roughness_sampler, in.uv);
+    metallic *= metallic_roughness.b;
+    roughness *= metallic_roughness.g;
+#endif
+
+    var normal = normalize(in.world_normal);
+#ifdef NORMAL_TEXTURE
+    let tangent = normalize(in.world_tangent.xyz - normal * dot(normal, in.world_tangent.xyz));
+    let bitangent = cross(normal, tangent) * in.world_tangent.w;
+    let texel = textureSample(normal_texture, normal_sampler, in.uv).xyz * 2.0 - 1.0;
+    let scaled = vec3<f32>(texel.xy * material.normal_scale, texel.z);
+    normal = normalize(mat3x3<f32>(tangent, bitangent, normal) * scaled);
+#endif
+
+    var emissive = material.emissive.rgb;
+#ifdef EMISSIVE_TEXTURE
+    emissive *= textureSample(emissive_texture, emissive_sampler, in.uv).rgb;
+#endif
+
+    var occlusion = 1.0;
+#ifdef OCCLUSION_TEXTURE
+    let texture_occlusion = textureSample(occlusion_texture, occlusion_sampler, in.uv).r;
+    occlusion = mix(1.0, texture_occlusion, material.occlusion_strength);
+#endif
+
+    var surface: Surface;
+    surface.base_color = base_color.rgb;
+    surface.metallic = metallic;
+    // Very low roughness concentrates highlights into single pixels.
+    surface.roughness = clamp(roughness, 0.045, 1.0);
+    surface.normal = normal;
+    surface.view = normalize(camera.position.xyz - in.world_position);
+
+#ifdef DEFERRED_GBUFFER
+    var out: GBuffer;
+    out.base_color = vec4<f32>(surface.base_color, surface.metallic);
+    out.normal = vec4<f32>(surface.normal, surface.roughness);
+    out.emissive = vec4<f32>(emissive, occlusion);
+    out.shadow_receiver = in.shadow_receiver;
+    return out;
+#else
+    var color = surface_color(surface, emissive, occlusion, in);
+#ifdef DEBUG_NORMALS
+    color = normal * 0.5 + 0.5;
+#endif
+#ifdef DEBUG_OVERDRAW
+    // Added up by the blending, so pixels get brighter with every fragment shaded for them.
+    return vec4<f32>(0.1, 0.04, 0.01, 1.0);
+#else
+#ifdef ALPHA_BLEND
+    return vec4<f32>(color, base_color.a);
+#else
+    return vec4<f32>(color, 1.0);
+#endif
+#endif
+#endif
+}
+#endif
+
+#ifdef DEFERRED_LIGHTING
+@group(1) @binding(0)
+var gbuffer_base_color: texture_2d<f32>;
+@group(1) @binding(1)
+var gbuffer_normal: texture_2d<f32>;
+@group(1) @binding(2)
+var gbuffer_emissive: texture_2d<f32>;
+@group(1) @binding(3)
+var gbuffer_shadow_receiver: texture_2d<f32>;
+// Bound as a float texture, since not all backends can load from depth textures.
+@group(1) @binding(4)
+var gbuffer_depth: texture_2d<f32>;
+
+// Triangle covering the whole target.
+@vertex
+fn vs_fullscreen(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    return vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+}
+
+// Lights the surface in the G-buffer at the pixel, keeping the background where no mesh was drawn.
+@fragment
+fn fs_lighting(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let texel = vec2<i32>(position.xy);
+    let depth = textureLoad(gbuffer_depth, texel, 0).r;
+    if depth >= 1.0 {
+        discard;
+    }
+
+    // The world position is reconstructed from the depth in the camera's viewport.
+    let viewport = lights.clusters.viewport;
+    let uv = (position.xy - viewport.xy) / viewport.zw;
+    let clip = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, depth, 1.0);
+    let world_position = camera.inverse_view_projection * clip;
+    let base_color = textureLoad(gbuffer_base_color, texel, 0);
+    let normal = textureLoad(gbuffer_normal, texel, 0);
+    let emissive = textureLoad(gbuffer_emissive, texel, 0);
+
+    var in: VertexOutput;
+    in.clip_position = position;
+    in.world_position = world_position.xyz / world_position.w;
+    in.world_normal = normal.xyz;
+    in.shadow_receiver = textureLoad(gbuffer_shadow_receiver, texel, 0).r;
+
+    var surface: Surface;
+    surface.base_color = base_color.rgb;
+    surface.metallic = base_color.a;
+    surface.roughness = normal.w;
+    surface.normal = normalize(normal.xyz);
+    surface.view = normalize(camera.position.xyz - in.world_position);
+    return vec4<f32>(surface_color(surface, emissive.rgb, emissive.a, in), 1.0);
+}
+#endif
@@ -0,0 +1,43 @@
+// Depth of the opaque meshes, drawn before the forward pass. Positions are computed exactly like in
+// the forward pass, so its depth test can compare them for equality.
+
+struct Camera {
+    view_projection: mat4x4<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> camera: Camera;
+
+// Joint matrices of all skinned meshes, used by `skin_matrix`.
+@group(0) @binding(1)
+var<storage, read> joint_palettes: array<mat4x4<f32>>;
+// Offsets of all morph targets and weights of all morphed instances, used by `morph`.
+@group(0) @binding(2)
+var<storage, read> morph_targets: array<vec4<f32>>;
+@group(0) @binding(3)
+var<storage, read> morph_weights: array<u32>;
+
+struct Vertex {
+    @location(0) position: vec3<f32>,
+    @location(12) joints: vec4<u32>,
+    @location(13) weights: vec4<f32>,
+    @builtin(vertex_index) index: u32,
+};
+
+struct Instance {
+    @location(4) model_0: vec4<f32>,
+    @location(5) model_1: vec4<f32>,
+    @location(6) model_2: vec4<f32>,
+    @location(7) model_3: vec4<f32>,
+    @location(14) skin: u32,
+    @location(15) morph: u32,
+};
+
+@vertex
+fn vs_main(vertex: Vertex, instance: Instance) -> @builtin(position) @invariant vec4<f32> {
+    let offsets = morph(vertex.index, instance.morph);
+    let skin = skin_matrix(vertex.joints, vertex.weights, instance.skin);
+    let model = mat4x4<f32>(instance.model_0, instance.model_1, instance.model_2, instance.model_3);
+    let world_position = model * (skin * vec4<f32>(vertex.position + offsets.position, 1.0));
+    return camera.view_projection * world_position;
+}
@@ -0,0 +1,42 @@
+// Depth of shadow casting meshes in a shadow map.
+
+struct Shadow {
+    view_projection: mat4x4<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> shadow: Shadow;
+
+// Joint matrices of all skinned meshes, used by `skin_matrix`.
+@group(0) @binding(1)
+var<storage, read> joint_palettes: array<mat4x4<f32>>;
+// Offsets of all morph targets and weights of all morphed instances, used by `morph`.
+@group(0) @binding(2)
+var<storage, read> morph_targets: array<vec4<f32>>;
+@group(0) @binding(3)
+var<storage, read> morph_weights: array<u32>;
+
+struct Vertex {
+    @location(0) position: vec3<f32>,
+    @location(12) joints: vec4<u32>,
+    @location(13) weights: vec4<f32>,
+    @builtin(vertex_index) index: u32,
+};
+
+struct Instance {
+    @location(4) model_0: vec4<f32>,
+    @location(5) model_1: vec4<f32>,
+    @location(6) model_2: vec4<f32>,
+    @location(7) model_3: vec4<f32>,
+    @location(14) skin: u32,
+    @location(15) morph: u32,
+};
+
+@vertex
+fn vs_main(vertex: Vertex, instance: Instance) -> @builtin(position) vec4<f32> {
+    let model = mat4x4<f32>(instance.model_0, instance.model_1, instance.model_2, instance.model_3);
+    let offsets = morph(vertex.index, instance.morph);
+    let skin = skin_matrix(vertex.joints, vertex.weights, instance.skin);
+    let position = vertex.position + offsets.position;
+    return shadow.view_projection * model * (skin * vec4<f32>(position, 1.0));
+}
@@ -0,0 +1,19 @@
+
+// Returns the matrix deforming a vertex by its joints and weights, with the index of the first
+// matrix of the instance's skin in `joint_palettes`, or the identity if the instance has no skin.
+fn skin_matrix(joints: vec4<u32>, weights: vec4<f32>, skin: u32) -> mat4x4<f32> {
+    let total = weights.x + weights.y + weights.z + weights.w;
+    if skin == 0xffffffffu || total <= 0.0 {
+        return mat4x4<f32>(
+            vec4<f32>(1.0, 0.0, 0.0, 0.0),
+            vec4<f32>(0.0, 1.0, 0.0, 0.0),
+            vec4<f32>(0.0, 0.0, 1.0, 0.0),
+            vec4<f32>(0.0, 0.0, 0.0, 1.0),
+        );
+    }
+    let w = weights / total;
+    return joint_palettes[skin + joints.x] * w.x
+        + joint_palettes[skin + joints.y] * w.y
+        + joint_palettes[skin + joints.z] * w.z
+        + joint_palettes[skin + joints.w] * w.w;
+}
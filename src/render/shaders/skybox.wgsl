@@ -0,0 +1,123 @@
+// Sky drawn behind the meshes from an equirectangular image or the six faces of a cubemap, both
+// 32-bit float textures filtered in the shader.
+
+const PI: f32 = 3.14159265359;
+
+struct Params {
+    // Transforms clip coordinates to directions from the camera in world coordinates.
+    inverse_view_projection: mat4x4<f32>,
+    intensity: f32,
+};
+
+@group(0) @binding(0)
+var<uniform> params: Params;
+
+@group(0) @binding(1)
+var equirectangular: texture_2d<f32>;
+
+@group(0) @binding(2)
+// Faces of the cubemap on top of each other.
+var cubemap: texture_2d<f32>;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) clip: vec2<f32>,
+};
+
+// Triangle covering the whole target.
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+
+    var out: VertexOutput;
+    out.clip = vec2<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0);
+    out.position = vec4<f32>(out.clip, 0.0, 1.0);
+    return out;
+}
+
+// Returns the direction from the camera through the point in clip coordinates.
+fn view_direction(clip: vec2<f32>) -> vec3<f32> {
+    let far = params.inverse_view_projection * vec4<f32>(clip, 1.0, 1.0);
+    return normalize(far.xyz / far.w);
+}
+
+// Texels around a position and the weights of the right and bottom texels to filter them
+// bilinearly.
+struct Bilinear {
+    texels: array<vec2<i32>, 4>,
+    weight: vec2<f32>,
+};
+
+// Returns the texels around the position in texels of a texture of the size, wrapping around
+// horizontally if requested and clamping to the edges otherwise.
+fn bilinear(position: vec2<f32>, size: vec2<i32>, wrap: bool) -> Bilinear {
+    let base = vec2<i32>(floor(position - 0.5));
+    var x0 = clamp(base.x, 0, size.x - 1);
+    var x1 = clamp(base.x + 1, 0, size.x - 1);
+    if wrap {
+        x0 = (base.x % size.x + size.x) % size.x;
+        x1 = (x0 + 1) % size.x;
+    }
+    let y0 = clamp(base.y, 0, size.y - 1);
+    let y1 = clamp(base.y + 1, 0, size.y - 1);
+
+    var out: Bilinear;
+    out.texels = array<vec2<i32>, 4>(
+        vec2<i32>(x0, y0),
+        vec2<i32>(x1, y0),
+        vec2<i32>(x0, y1),
+        vec2<i32>(x1, y1),
+    );
+    out.weight = fract(position - 0.5);
+    return out;
+}
+
+fn blend(colors: array<vec3<f32>, 4>, weight: vec2<f32>) -> vec3<f32> {
+    return mix(mix(colors[0], colors[1], weight.x), mix(colors[2], colors[3], weight.x), weight.y);
+}
+
+@fragment
+fn fs_equirectangular(in: VertexOutput) -> @location(0) vec4<f32> {
+    let direction = view_direction(in.clip);
+    let uv = vec2<f32>(
+        atan2(direction.x, -direction.z) / (2.0 * PI) + 0.5,
+        acos(clamp(direction.y, -1.0, 1.0)) / PI,
+    );
+    let size = vec2<i32>(textureDimensions(equirectangular));
+    let texels = bilinear(uv * vec2<f32>(size), size, true);
+    var corners = texels.texels;
+    var colors: array<vec3<f32>, 4>;
+    for (var i = 0; i < 4; i++) {
+        colors[i] = textureLoad(equirectangular, corners[i], 0).rgb;
+    }
+    return vec4<f32>(blend(colors, texels.weight) * params.intensity, 1.0);
+}
+
+@fragment
+fn fs_cubemap(in: VertexOutput) -> @location(0) vec4<f32> {
+    // Face and coordinates from -1 to 1 on it, from its top-left corner, as sampled from cube
+    // textures.
+    let d = view_direction(in.clip);
+    let a = abs(d);
+    var face: i32;
+    var uv: vec2<f32>;
+    if a.x >= a.y && a.x >= a.z {
+        face = select(1, 0, d.x > 0.0);
+        uv = vec2<f32>(select(d.z, -d.z, d.x > 0.0), -d.y) / a.x;
+    } else if a.y >= a.z {
+        face = select(3, 2, d.y > 0.0);
+        uv = vec2<f32>(d.x, select(-d.z, d.z, d.y > 0.0)) / a.y;
+    } else {
+        face = select(5, 4, d.z > 0.0);
+        uv = vec2<f32>(select(-d.x, d.x, d.z > 0.0), -d.y) / a.z;
+    }
+
+    let size = vec2<i32>(textureDimensions(cubemap)) / vec2<i32>(1, 6);
+    let texels = bilinear((uv * 0.5 + 0.5) * vec2<f32>(size), size, false);
+    var corners = texels.texels;
+    var colors: array<vec3<f32>, 4>;
+    for (var i = 0; i < 4; i++) {
+        colors[i] = textureLoad(cubemap, corners[i] + vec2<i32>(0, face * size.y), 0).rgb;
+    }
+    return vec4<f32>(blend(colors, texels.weight) * params.intensity, 1.0);
+}
@@ -0,0 +1,28 @@
+// Splat map shading of terrain, appended to the standard shader as the shader of a shader
+// material. The splat map is stretched over the whole terrain and blends the four layers by the
+// weights in its channels, each layer tiled `tiling` times across the terrain.
+
+fn layer_color(color: vec4<f32>, sample: vec4<f32>) -> vec3<f32> {
+    return color.rgb * sample.rgb;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let splat = textureSample(splat_texture, splat_sampler, in.uv);
+    let weights = splat / max(splat.r + splat.g + splat.b + splat.a, 1e-4);
+    let uv = in.uv * material.tiling;
+
+    let base_color =
+        layer_color(material.layer_0, textureSample(layer_0_texture, layer_0_sampler, uv)) * weights.r
+        + layer_color(material.layer_1, textureSample(layer_1_texture, layer_1_sampler, uv)) * weights.g
+        + layer_color(material.layer_2, textureSample(layer_2_texture, layer_2_sampler, uv)) * weights.b
+        + layer_color(material.layer_3, textureSample(layer_3_texture, layer_3_sampler, uv)) * weights.a;
+
+    var surface: Surface;
+    surface.base_color = base_color;
+    surface.metallic = 0.0;
+    surface.roughness = clamp(dot(weights, material.roughness), 0.045, 1.0);
+    surface.normal = normalize(in.world_normal);
+    surface.view = normalize(camera.position.xyz - in.world_position);
+    return vec4<f32>(surface_color(surface, vec3<f32>(0.0), 1.0, in), 1.0);
+}
@@ -0,0 +1,95 @@
+// Exposure and tonemapping of the high dynamic range target. Auto exposure averages the
+// logarithm of the luminance in the mips of a luminance texture, and adapts the exposure value of
+// the previous frame towards it.
+
+struct Params {
+    // Exposure value of manual exposure.
+    ev: f32,
+    // Exposure compensation and range of auto exposure.
+    compensation: f32,
+    min_ev: f32,
+    max_ev: f32,
+    // Fraction of the way the exposure value adapts towards the target this frame.
+    adaptation: f32,
+    // Whether the exposure is automatic.
+    auto_exposure: u32,
+    // Tonemapping operator, see below.
+    tonemapping: u32,
+};
+
+const NONE: u32 = 0u;
+const REINHARD: u32 = 1u;
+
+@group(0) @binding(0)
+var<uniform> params: Params;
+
+@group(0) @binding(1)
+var source: texture_2d<f32>;
+
+@group(0) @binding(2)
+var source_sampler: sampler;
+
+@group(0) @binding(3)
+var exposure: texture_2d<f32>;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+// Triangle covering the whole target, with UV coordinates from its top-left corner.
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+
+    var out: VertexOutput;
+    out.position = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    out.uv = uv;
+    return out;
+}
+
+fn luminance(color: vec3<f32>) -> f32 {
+    return dot(color, vec3<f32>(0.2126, 0.7152, 0.0722));
+}
+
+@fragment
+fn fs_luminance(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSampleLevel(source, source_sampler, in.uv, 0.0).rgb;
+    return vec4<f32>(log2(max(luminance(color), 1e-5)), 0.0, 0.0, 1.0);
+}
+
+// Averages the 2x2 texels of the previous mip around each texel.
+@fragment
+fn fs_downsample(in: VertexOutput) -> @location(0) vec4<f32> {
+    return textureSampleLevel(source, source_sampler, in.uv, 0.0);
+}
+
+@fragment
+fn fs_adapt(in: VertexOutput) -> @location(0) vec4<f32> {
+    // Expose the average luminance as middle gray.
+    let average = textureLoad(source, vec2<i32>(0), 0).r;
+    let target_ev = clamp(average - log2(0.18), params.min_ev, params.max_ev) - params.compensation;
+    let previous = textureLoad(exposure, vec2<i32>(0), 0).r;
+    return vec4<f32>(mix(previous, target_ev, params.adaptation), 0.0, 0.0, 1.0);
+}
+
+// Narkowicz's fit of the ACES filmic curve.
+fn aces(color: vec3<f32>) -> vec3<f32> {
+    return saturate((color * (2.51 * color + 0.03)) / (color * (2.43 * color + 0.59) + 0.14));
+}
+
+@fragment
+fn fs_tonemap(in: VertexOutput) -> @location(0) vec4<f32> {
+    let hdr = textureLoad(source, vec2<i32>(in.position.xy), 0);
+    var ev = params.ev;
+    if params.auto_exposure != 0u {
+        ev = textureLoad(exposure, vec2<i32>(0), 0).r;
+    }
+    let color = hdr.rgb * exp2(-ev);
+
+    switch params.tonemapping {
+        case NONE: { return vec4<f32>(saturate(color), hdr.a); }
+        case REINHARD: { return vec4<f32>(color / (1.0 + color), hdr.a); }
+        default: { return vec4<f32>(aces(color), hdr.a); }
+    }
+}
@@ -0,0 +1,701 @@
+//! # Shadow
+//!
+//! Shadow maps of the directional and spot lights with `shadows` enabled, rendered by the
+//! [crate::render::graph::SHADOW] node before the meshes are drawn. Directional lights get a
+//! cascade of shadow maps for each camera, covering increasingly distant slices of its view, and
+//! spot lights a single map covering their cone. Shadows are filtered with percentage-closer
+//! filtering (PCF).
+
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::ops::Range;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+
+use bytemuck::Pod;
+use bytemuck::Zeroable;
+use glam::Mat4;
+use glam::Vec3;
+use glam::Vec4;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::render::graph::GpuResources;
+use crate::render::graph::RenderContext;
+use crate::render::graph::RenderNode;
+use crate::render::light::gather_lights;
+use crate::render::light::world_transform;
+use crate::render::light::DirectionalLight;
+use crate::render::light::SpotLight;
+use crate::render::mesh::deform_entries;
+use crate::render::mesh::deform_layout_entries;
+use crate::render::mesh::mesh_shader;
+use crate::render::mesh::Instance;
+use crate::render::mesh::Mesh;
+use crate::render::mesh::Vertex;
+use crate::render::UNIFORM_ALIGNMENT;
+use crate::Camera;
+use crate::Component;
+use crate::ComputedVisibility;
+use crate::Frustum;
+use crate::Node;
+use crate::Projection;
+use crate::Reflect;
+use crate::Scene;
+use crate::WorldBounds;
+use crate::WorldTransform;
+
+/// Maximum number of shadow maps rendered per frame, shared by all cameras. Lights whose maps
+/// don't fit anymore are drawn without shadows.
+pub const MAX_SHADOW_MAPS: usize = 8;
+
+/// Format of the shadow maps.
+const SHADOW_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+
+/// # Shadow Caster
+///
+/// Whether the node's mesh casts shadows. Meshes without the component cast shadows.
+#[derive(Copy, Clone, Debug, PartialEq, Component, Reflect, Serialize, Deserialize)]
+pub struct ShadowCaster(pub bool);
+
+/// # Shadow Receiver
+///
+/// Whether shadows are cast onto the node's mesh. Meshes without the component receive shadows.
+#[derive(Copy, Clone, Debug, PartialEq, Component, Reflect, Serialize, Deserialize)]
+pub struct ShadowReceiver(pub bool);
+
+/// # Shadow Settings
+///
+/// Scene resource configuring the shadow maps. Defaults are used if the scene has none.
+#[derive(Copy, Clone, Debug, PartialEq, Reflect, Serialize, Deserialize)]
+pub struct ShadowSettings {
+    /// Width and height of each shadow map in texels.
+    pub resolution: u32,
+    /// Number of cascades of directional light shadow maps, from 1 to 4.
+    pub cascades: u32,
+    /// Distance from each camera up to which directional lights cast shadows.
+    pub max_distance: f32,
+    /// Radius in texels of the PCF kernel. 0 samples a single texel, 1 a 3x3 kernel, and so on.
+    pub pcf_radius: u32,
+    /// Depth subtracted from the fragment's depth in the shadow map before comparing, against
+    /// shadow acne on surfaces facing the light.
+    pub depth_bias: f32,
+    /// Distance the fragment is moved along its normal before looking it up in the shadow map,
+    /// against shadow acne on surfaces at grazing angles to the light.
+    pub normal_bias: f32,
+}
+
+impl Default for ShadowSettings {
+    fn default() -> Self {
+        Self {
+            resolution: 1024,
+            cascades: 4,
+            max_distance: 50.0,
+            pcf_radius: 1,
+            depth_bias: 0.0005,
+            normal_bias: 0.05,
+        }
+    }
+}
+
+/// Shadow maps of a camera's lights.
+#[derive(Default)]
+pub(crate) struct CameraShadows {
+    lights: Vec<(Node, Range<u32>)>,
+    pub(crate) cascade_splits: [f32; 4],
+}
+
+impl CameraShadows {
+    /// Returns the range of shadow maps of the light, if it has any.
+    pub(crate) fn maps(&self, light: Node) -> Option<Range<u32>> {
+        self.lights
+            .iter()
+            .find(|(node, _)| *node == light)
+            .map(|(_, maps)| maps.clone())
+    }
+}
+
+/// Render resource with the shadow maps rendered in the current frame, a layer of a texture array
+/// for each map.
+pub(crate) struct ShadowMaps {
+    resolution: u32,
+    /// Unique for every created texture, including those of other render targets, so bind groups
+    /// can be updated.
+    pub(crate) generation: u64,
+    pub(crate) view: wgpu::TextureView,
+    layers: Vec<wgpu::TextureView>,
+    pub(crate) sampler: wgpu::Sampler,
+    pub(crate) matrices: Vec<Mat4>,
+    cameras: HashMap<Node, CameraShadows>,
+}
+
+impl ShadowMaps {
+    /// Inserts empty shadow maps with the resolution into the context unless it has maps with the
+    /// resolution already.
+    pub(crate) fn prepare(context: &mut RenderContext, resolution: u32) {
+        static GENERATION: AtomicU64 = AtomicU64::new(0);
+        if context
+            .resource::<Self>()
+            .is_some_and(|maps| maps.resolution == resolution)
+        {
+            return;
+        }
+
+        let generation = GENERATION.fetch_add(1, Ordering::Relaxed);
+        let maps = Self::new(context.device, resolution, generation);
+        context.insert_resource(maps);
+    }
+
+    fn new(device: &wgpu::Device, resolution: u32, generation: u64) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("shadow maps"),
+            size: wgpu::Extent3d {
+                width: resolution,
+                height: resolution,
+                depth_or_array_layers: MAX_SHADOW_MAPS as u32,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: SHADOW_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor {
+            dimension: Some(wgpu::TextureViewDimension::D2Array),
+            ..wgpu::TextureViewDescriptor::default()
+        });
+        let layers = (0..MAX_SHADOW_MAPS as u32)
+            .map(|layer| {
+                texture.create_view(&wgpu::TextureViewDescriptor {
+                    dimension: Some(wgpu::TextureViewDimension::D2),
+                    base_array_layer: layer,
+                    array_layer_count: Some(1),
+                    ..wgpu::TextureViewDescriptor::default()
+                })
+            })
+            .collect();
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("shadow maps"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            compare: Some(wgpu::CompareFunction::LessEqual),
+            ..wgpu::SamplerDescriptor::default()
+        });
+
+        Self {
+            resolution,
+            generation,
+            view,
+            layers,
+            sampler,
+            matrices: Vec::new(),
+            cameras: HashMap::new(),
+        }
+    }
+
+    /// Returns the shadow maps of the camera's lights, if it has any.
+    pub(crate) fn camera(&self, camera: Node) -> Option<&CameraShadows> {
+        self.cameras.get(&camera)
+    }
+}
+
+/// Uniforms of a shadow map in the shader.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+struct ShadowUniform {
+    view_projection: [[f32; 4]; 4],
+}
+
+/// Render node rendering the depth of the shadow casting meshes seen by each shadow casting light
+/// into the [ShadowMaps].
+pub(crate) struct ShadowPass {
+    pipeline: wgpu::RenderPipeline,
+    layout: wgpu::BindGroupLayout,
+    uniform_buffer: wgpu::Buffer,
+    bind_group: Option<wgpu::BindGroup>,
+    /// Generation of the buffers deforming meshes bound in the bind group.
+    deform_generation: u64,
+    instance_buffer: wgpu::Buffer,
+}
+
+impl ShadowPass {
+    pub(crate) fn new(device: &wgpu::Device) -> Self {
+        let shader = mesh_shader(device, "shadow", include_str!("shaders/shadow.wgsl"));
+        let uniform_size = std::mem::size_of::<ShadowUniform>() as u64;
+        let [palettes, morph_targets, morph_weights] = deform_layout_entries(1);
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("shadow"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: true,
+                        min_binding_size: wgpu::BufferSize::new(uniform_size),
+                    },
+                    count: None,
+                },
+                palettes,
+                morph_targets,
+                morph_weights,
+            ],
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("shadow"),
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("shadow"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                buffers: &[Vertex::layout(), Instance::layout()],
+            },
+            // Single-sided meshes like planes cast shadows from both sides.
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: SHADOW_FORMAT,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState {
+                    constant: 2,
+                    slope_scale: 2.0,
+                    clamp: 0.0,
+                },
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            fragment: None,
+            multiview: None,
+            cache: None,
+        });
+
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("shadow maps"),
+            size: MAX_SHADOW_MAPS as u64 * UNIFORM_ALIGNMENT,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        Self {
+            pipeline,
+            layout,
+            uniform_buffer,
+            bind_group: None,
+            deform_generation: 0,
+            instance_buffer: instance_buffer(device, 1),
+        }
+    }
+}
+
+impl RenderNode for ShadowPass {
+    fn run(&mut self, context: &mut RenderContext, scene: &Scene) {
+        let settings = scene
+            .get_resource::<ShadowSettings>()
+            .copied()
+            .unwrap_or_default();
+        let max_resolution = context.device.limits().max_texture_dimension_2d;
+        ShadowMaps::prepare(context, settings.resolution.clamp(1, max_resolution));
+
+        let (matrices, cameras) = allocate(scene, context.cameras, &settings);
+
+        let mut instances = Vec::new();
+        let batches = matrices
+            .iter()
+            .map(|matrix| {
+                let frustum = Frustum::from_matrix(matrix);
+                batch(scene, &frustum, context.resources, &mut instances)
+            })
+            .collect::<Vec<_>>();
+        for mesh in batches.iter().flatten().map(|(mesh, _)| mesh) {
+            context.resources.meshes.upload(context.device, mesh);
+        }
+
+        let mut uniforms = vec![0; MAX_SHADOW_MAPS * UNIFORM_ALIGNMENT as usize];
+        for (uniform, matrix) in uniforms
+            .chunks_mut(UNIFORM_ALIGNMENT as usize)
+            .zip(&matrices)
+        {
+            let shadow = ShadowUniform {
+                view_projection: matrix.to_cols_array_2d(),
+            };
+            uniform[..std::mem::size_of::<ShadowUniform>()]
+                .copy_from_slice(bytemuck::bytes_of(&shadow));
+        }
+        context
+            .queue
+            .write_buffer(&self.uniform_buffer, 0, &uniforms);
+
+        let instance_size = std::mem::size_of::<Instance>() as u64;
+        if self.instance_buffer.size() < instances.len() as u64 * instance_size {
+            self.instance_buffer =
+                instance_buffer(context.device, (instances.len() as u64).next_power_of_two());
+        }
+        context
+            .queue
+            .write_buffer(&self.instance_buffer, 0, bytemuck::cast_slice(&instances));
+
+        let resources = &*context.resources;
+        if self.deform_generation != resources.deform_generation() {
+            self.deform_generation = resources.deform_generation();
+            self.bind_group = None;
+        }
+        let bind_group = self.bind_group.get_or_insert_with(|| {
+            let [palettes, morph_targets, morph_weights] = deform_entries(resources, 1);
+            context
+                .device
+                .create_bind_group(&wgpu::BindGroupDescriptor {
+                    label: Some("shadow maps"),
+                    layout: &self.layout,
+                    entries: &[
+                        wgpu::BindGroupEntry {
+                            binding: 0,
+                            resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                                buffer: &self.uniform_buffer,
+                                offset: 0,
+                                size: wgpu::BufferSize::new(
+                                    std::mem::size_of::<ShadowUniform>() as u64
+                                ),
+                            }),
+                        },
+                        palettes,
+                        morph_targets,
+                        morph_weights,
+                    ],
+                })
+        });
+
+        let maps = context.resources.resource::<ShadowMaps>().unwrap();
+        let (mut draw_calls, mut triangles) = (0, 0);
+        for (layer, batches) in batches.iter().enumerate() {
+            let mut pass = context
+                .encoder
+                .begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some("shadow"),
+                    color_attachments: &[],
+                    depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                        view: &maps.layers[layer],
+                        depth_ops: Some(wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(1.0),
+                            store: wgpu::StoreOp::Store,
+                        }),
+                        stencil_ops: None,
+                    }),
+                    timestamp_writes: None,
+                    occlusion_query_set: None,
+                });
+
+            pass.set_pipeline(&self.pipeline);
+            let offset = layer as u32 * UNIFORM_ALIGNMENT as u32;
+            pass.set_bind_group(0, &*bind_group, &[offset]);
+            pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
+            for (mesh, instances) in batches {
+                let Some(gpu_mesh) = context.resources.meshes.get(mesh) else {
+                    continue;
+                };
+                pass.set_vertex_buffer(0, gpu_mesh.vertices.slice(..));
+                pass.set_index_buffer(gpu_mesh.indices.slice(..), wgpu::IndexFormat::Uint32);
+                pass.draw_indexed(0..gpu_mesh.index_count, 0, instances.clone());
+                draw_calls += 1;
+                triangles += u64::from(gpu_mesh.index_count / 3) * instances.len() as u64;
+            }
+        }
+        context.count_draws(draw_calls, triangles);
+
+        let maps = context.resource_mut::<ShadowMaps>().unwrap();
+        maps.matrices = matrices;
+        maps.cameras = cameras;
+    }
+}
+
+/// Allocates the shadow maps of the shadow casting lights seen by each camera, and returns the
+/// matrices transforming from world to the clip coordinates of each map with the shadows of each
+/// camera. Spot light maps are shared between cameras.
+fn allocate(
+    scene: &Scene,
+    cameras: &[Node],
+    settings: &ShadowSettings,
+) -> (Vec<Mat4>, HashMap<Node, CameraShadows>) {
+    let mut matrices = Vec::new();
+    let mut spot_maps = HashMap::new();
+    let mut camera_shadows = HashMap::new();
+    for &node in cameras {
+        let camera = scene.get::<Camera>(node).unwrap();
+        let transform = world_transform(scene, node);
+        let splits = cascade_splits(camera, settings);
+        let mut shadows = CameraShadows::default();
+        for (split, distance) in shadows.cascade_splits.iter_mut().zip(&splits) {
+            *split = *distance;
+        }
+
+        for light in gather_lights(scene, &camera.frustum(&transform)) {
+            let light_transform = world_transform(scene, light);
+            let start = matrices.len() as u32;
+            if scene
+                .get::<DirectionalLight>(light)
+                .is_some_and(|light| light.shadows)
+            {
+                if matrices.len() + splits.len() > MAX_SHADOW_MAPS {
+                    continue;
+                }
+
+                let direction = light_transform.forward().normalize_or_zero();
+                let mut near = near_distance(camera);
+                for far in &splits {
+                    let corners = slice_corners(camera, &transform, near, *far);
+                    matrices.push(cascade_matrix(&corners, direction, settings));
+                    near = *far;
+                }
+            } else if let Some(spot) = scene.get::<SpotLight>(light).filter(|spot| spot.shadows) {
+                if let Some(maps) = spot_maps.get(&light) {
+                    shadows.lights.push((light, Range::clone(maps)));
+                    continue;
+                }
+                if matrices.len() == MAX_SHADOW_MAPS {
+                    continue;
+                }
+
+                matrices.push(spot_matrix(spot, &light_transform));
+                spot_maps.insert(light, start..start + 1);
+            } else {
+                continue;
+            }
+
+            shadows.lights.push((light, start..matrices.len() as u32));
+        }
+
+        camera_shadows.insert(node, shadows);
+    }
+
+    (matrices, camera_shadows)
+}
+
+/// Returns the instances of the visible shadow casting mesh nodes at least partially inside the
+/// frustum, grouped by mesh, with skinned and morphed nodes deformed by their buffers in the
+/// resources.
+fn batch(
+    scene: &Scene,
+    frustum: &Frustum,
+    resources: &GpuResources,
+    instances: &mut Vec<Instance>,
+) -> Vec<(Mesh, Range<u32>)> {
+    let mut groups = BTreeMap::<usize, (Mesh, Vec<Instance>)>::new();
+    for (node, mesh) in scene.query::<(Mesh,)>() {
+        let casts = scene.get::<ShadowCaster>(node) != Some(&ShadowCaster(false));
+        let visible = scene.get::<ComputedVisibility>(node) == Some(&ComputedVisibility::Visible);
+        let inside = scene
+            .get::<WorldBounds>(node)
+            .is_none_or(|bounds| frustum.intersects_aabb(&bounds.aabb));
+        if !(casts && visible && inside) {
+            continue;
+        }
+
+        let instance = Instance::new(&world_transform(scene, node), false);
+        if let Some(instance) = instance.map(|instance| instance.deformed(resources, node)) {
+            groups
+                .entry(mesh.id())
+                .or_insert_with(|| (mesh.clone(), Vec::new()))
+                .1
+                .push(instance);
+        }
+    }
+
+    groups
+        .into_values()
+        .map(|(mesh, group)| {
+            let start = instances.len() as u32;
+            instances.extend(group);
+            (mesh, start..instances.len() as u32)
+        })
+        .collect()
+}
+
+fn near_distance(camera: &Camera) -> f32 {
+    match camera.projection {
+        Projection::Perspective { near, .. } | Projection::Orthographic { near, .. } => near,
+    }
+}
+
+/// Returns the far distances of the slices of the camera's view covered by each cascade, between
+/// evenly and logarithmically spaced distances.
+fn cascade_splits(camera: &Camera, settings: &ShadowSettings) -> Vec<f32> {
+    let (near, far) = match camera.projection {
+        Projection::Perspective { near, far, .. } | Projection::Orthographic { near, far, .. } => {
+            (near.max(0.01), far.min(settings.max_distance).max(near))
+        }
+    };
+    let count = settings.cascades.clamp(1, 4);
+
+    (1..=count)
+        .map(|cascade| {
+            let t = cascade as f32 / count as f32;
+            let linear = near + (far - near) * t;
+            let logarithmic = near * (far / near).powf(t);
+            (linear + logarithmic) * 0.5
+        })
+        .collect()
+}
+
+/// Returns the world coordinates of the corners of the slice of the camera's view between the
+/// distances.
+fn slice_corners(camera: &Camera, transform: &WorldTransform, near: f32, far: f32) -> [Vec3; 8] {
+    let mut slice = *camera;
+    match &mut slice.projection {
+        Projection::Perspective {
+            near: slice_near,
+            far: slice_far,
+            ..
+        }
+        | Projection::Orthographic {
+            near: slice_near,
+            far: slice_far,
+            ..
+        } => {
+            *slice_near = near;
+            *slice_far = far;
+        }
+    }
+
+    let inverse = slice.view_projection_matrix(transform).inverse();
+    let mut corners = [Vec3::ZERO; 8];
+    for (index, corner) in corners.iter_mut().enumerate() {
+        let x = if index & 1 == 0 { -1.0 } else { 1.0 };
+        let y = if index & 2 == 0 { -1.0 } else { 1.0 };
+        let z = if index & 4 == 0 { 0.0 } else { 1.0 };
+        let world = inverse * Vec4::new(x, y, z, 1.0);
+        *corner = world.truncate() / world.w;
+    }
+
+    corners
+}
+
+/// Returns the matrix of the orthographic shadow map of a directional light covering the bounding
+/// sphere of the corners. The map is snapped to whole texels so shadows don't shimmer while the
+/// camera moves, and extended towards the light to include casters outside of the view.
+fn cascade_matrix(corners: &[Vec3; 8], direction: Vec3, settings: &ShadowSettings) -> Mat4 {
+    let center = corners.iter().sum::<Vec3>() / corners.len() as f32;
+    let radius = corners
+        .iter()
+        .map(|corner| corner.distance(center))
+        .fold(0.0, f32::max);
+    // Rounding the radius keeps the texel size stable while the camera rotates.
+    let radius = (radius * 16.0).ceil() / 16.0;
+
+    let view = Mat4::look_to_rh(Vec3::ZERO, direction, up_for(direction));
+    let texel = 2.0 * radius / settings.resolution.max(1) as f32;
+    let mut center = view.transform_point3(center);
+    center.x = (center.x / texel).floor() * texel;
+    center.y = (center.y / texel).floor() * texel;
+
+    let projection = Mat4::orthographic_rh(
+        center.x - radius,
+        center.x + radius,
+        center.y - radius,
+        center.y + radius,
+        -(center.z + radius + settings.max_distance),
+        -(center.z - radius),
+    );
+    projection * view
+}
+
+/// Returns the matrix of the perspective shadow map of a spot light covering its cone.
+fn spot_matrix(light: &SpotLight, transform: &WorldTransform) -> Mat4 {
+    let direction = transform.forward().normalize_or_zero();
+    let view = Mat4::look_to_rh(transform.translation(), direction, up_for(direction));
+    let fov = (light.outer_angle * 2.0).clamp(0.01, 3.0);
+    let near = (light.range * 0.01).min(0.1);
+    Mat4::perspective_rh(fov, 1.0, near, light.range.max(near * 2.0)) * view
+}
+
+/// Returns an up direction that isn't parallel to the direction.
+fn up_for(direction: Vec3) -> Vec3 {
+    if direction.y.abs() > 0.99 {
+        Vec3::Z
+    } else {
+        Vec3::Y
+    }
+}
+
+fn instance_buffer(device: &wgpu::Device, instances: u64) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("shadow instances"),
+        size: instances.max(1) * std::mem::size_of::<Instance>() as u64,
+        usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cascade_splits_cover_max_distance() {
+        let camera = Camera::perspective(1.0, 1.0, 0.1, 1000.0);
+        let settings = ShadowSettings::default();
+
+        let splits = cascade_splits(&camera, &settings);
+
+        assert_eq!(splits.len(), 4);
+        assert!(splits.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!((splits[3] - settings.max_distance).abs() < 1e-3);
+    }
+
+    #[test]
+    fn cascade_matrix_contains_slice() {
+        let camera = Camera::perspective(1.0, 1.5, 0.1, 100.0);
+        let transform = WorldTransform::new(Mat4::from_translation(Vec3::new(3.0, 2.0, 1.0)));
+        let corners = slice_corners(&camera, &transform, 1.0, 10.0);
+        let direction = Vec3::new(0.3, -1.0, 0.2).normalize();
+
+        let matrix = cascade_matrix(&corners, direction, &ShadowSettings::default());
+
+        for corner in corners {
+            let clip = matrix.project_point3(corner);
+            assert!(clip.x.abs() <= 1.0 && clip.y.abs() <= 1.0);
+            assert!((0.0..=1.0).contains(&clip.z));
+        }
+    }
+
+    #[test]
+    fn allocate_shares_spot_maps_between_cameras() {
+        let mut scene = Scene::new();
+        let visible = ComputedVisibility::Visible;
+        let camera = Camera::perspective(1.0, 1.0, 0.1, 100.0);
+        let cameras = [
+            scene.spawn_with((camera, WorldTransform::IDENTITY)),
+            scene.spawn_with((camera, WorldTransform::IDENTITY)),
+        ];
+        let directional = scene.spawn_with((
+            DirectionalLight {
+                shadows: true,
+                ..DirectionalLight::default()
+            },
+            visible,
+        ));
+        let spot = scene.spawn_with((
+            SpotLight {
+                shadows: true,
+                ..SpotLight::default()
+            },
+            WorldTransform::new(Mat4::from_translation(Vec3::new(0.0, 0.0, -5.0))),
+            visible,
+        ));
+
+        let (matrices, cameras) = allocate(&scene, &cameras, &ShadowSettings::default());
+
+        // The second camera's directional cascades don't fit next to the first camera's.
+        assert_eq!(matrices.len(), 5);
+        let mut shadows = cameras.values().collect::<Vec<_>>();
+        shadows.sort_by_key(|shadows| shadows.lights.len());
+        assert_eq!(shadows[0].maps(spot), Some(4..5));
+        assert_eq!(shadows[0].maps(directional), None);
+        assert_eq!(shadows[1].maps(directional), Some(0..4));
+        assert_eq!(shadows[1].maps(spot), Some(4..5));
+    }
+}
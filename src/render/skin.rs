@@ -0,0 +1,168 @@
+//! # Skin
+//!
+//! Skinned meshes deformed by a skeleton of joint nodes in the scene hierarchy. Each vertex of a
+//! skinned [crate::render::mesh::Mesh] is moved by up to four joints with the weights of its
+//! [crate::render::mesh::MeshData::joints] and [crate::render::mesh::MeshData::weights]. The
+//! joint matrices are computed from the joints' [WorldTransform]s by
+//! [crate::systems::compute_joint_palettes] and applied in the vertex shaders.
+
+use std::collections::HashMap;
+
+use glam::Mat4;
+
+use crate::render::light::world_transform;
+use crate::Component;
+use crate::Node;
+use crate::Scene;
+use crate::WorldTransform;
+
+/// Index of the first joint matrix of instances without a skin.
+pub(crate) const NO_SKIN: u32 = u32::MAX;
+
+/// Source of the skinning function appended to the shaders of meshes, which declare the joint
+/// matrices of all skinned nodes as `joint_palettes`. See [crate::render::mesh::deformed_source].
+pub(crate) const SKIN_SHADER: &str = include_str!("shaders/skin.wgsl");
+
+/// # Skeleton
+///
+/// Joint nodes deforming a skinned mesh, with the inverse bind matrix of each joint transforming
+/// the mesh from its bind pose to the joint's space. Joints are usually descendants of a common
+/// root node, animated by changing their [crate::LocalTransform]s.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Skeleton {
+    /// Joint nodes, indexed by the mesh's vertex joints.
+    pub joints: Vec<Node>,
+    /// Matrix transforming the mesh to each joint's space in the bind pose.
+    pub inverse_bind_matrices: Vec<Mat4>,
+}
+
+impl Skeleton {
+    /// Returns the skeleton binding the mesh of the node to the joints in their current pose,
+    /// computing the inverse bind matrices from their [WorldTransform]s.
+    pub fn from_pose(scene: &Scene, node: Node, joints: Vec<Node>) -> Self {
+        let mesh = world_transform(scene, node).matrix;
+        let inverse_bind_matrices = joints
+            .iter()
+            .map(|joint| world_transform(scene, *joint).matrix.inverse() * mesh)
+            .collect();
+        Self {
+            joints,
+            inverse_bind_matrices,
+        }
+    }
+}
+
+/// # Skinned Mesh
+///
+/// Component skinning the node's [crate::render::mesh::Mesh] with the skeleton. The mesh is drawn
+/// relative to the node's [WorldTransform] as deformed by the joints, and culled with the bounds
+/// of its bind pose.
+///
+/// ```
+/// # use glam::Vec3;
+/// # use pulse::render::skin::Skeleton;
+/// # use pulse::render::skin::SkinnedMesh;
+/// # use pulse::LocalTransform;
+/// # use pulse::Scene;
+/// let mut scene = Scene::new();
+/// let root = scene.spawn_with(LocalTransform::default());
+/// let elbow = scene.spawn_with(LocalTransform::from_position(Vec3::Y));
+/// scene.set_parent(elbow, root);
+///
+/// let skeleton = Skeleton {
+///     joints: vec![root, elbow],
+///     inverse_bind_matrices: vec![
+///         glam::Mat4::IDENTITY,
+///         glam::Mat4::from_translation(-Vec3::Y),
+///     ],
+/// };
+/// scene.spawn_with(SkinnedMesh { skeleton });
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Component)]
+pub struct SkinnedMesh {
+    /// Joints deforming the mesh.
+    pub skeleton: Skeleton,
+}
+
+/// # Joint Palette
+///
+/// Matrices transforming the bind pose of the node's [SkinnedMesh] to the current pose of each
+/// joint relative to the node, computed by [crate::systems::compute_joint_palettes].
+#[derive(Clone, Debug, Default, PartialEq, Component)]
+pub struct JointPalette(pub Vec<Mat4>);
+
+impl JointPalette {
+    /// Returns the palette of the skinned mesh at the node's world transform.
+    pub fn new(scene: &Scene, transform: &WorldTransform, skinned: &SkinnedMesh) -> Self {
+        let inverse = transform.matrix.inverse();
+        let skeleton = &skinned.skeleton;
+        let matrices = skeleton
+            .joints
+            .iter()
+            .zip(&skeleton.inverse_bind_matrices)
+            .map(|(joint, inverse_bind)| {
+                inverse * world_transform(scene, *joint).matrix * *inverse_bind
+            })
+            .collect();
+        Self(matrices)
+    }
+}
+
+/// Joint palettes of all skinned nodes in a GPU storage buffer, written once per frame and shared
+/// by the render nodes drawing meshes.
+#[derive(Default)]
+pub(crate) struct GpuSkins {
+    buffer: Option<wgpu::Buffer>,
+    /// Index of the first joint matrix of each skinned node in the buffer.
+    offsets: HashMap<Node, u32>,
+    /// Incremented whenever the buffer is recreated, so bind groups binding it are recreated.
+    pub(crate) generation: u64,
+}
+
+impl GpuSkins {
+    /// Writes the joint palettes of the scene's nodes to the buffer, growing it as needed.
+    pub(crate) fn write(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, scene: &Scene) {
+        self.offsets.clear();
+        let mut matrices = Vec::new();
+        for (node, palette) in scene.query::<(JointPalette,)>() {
+            self.offsets.insert(node, matrices.len() as u32);
+            matrices.extend(palette.0.iter().map(Mat4::to_cols_array_2d));
+        }
+
+        // Bind groups need a buffer even without skinned nodes.
+        let size = std::mem::size_of_val(matrices.as_slice())
+            .max(std::mem::size_of::<[[f32; 4]; 4]>()) as u64;
+        if self
+            .buffer
+            .as_ref()
+            .is_none_or(|buffer| buffer.size() < size)
+        {
+            self.buffer = Some(buffer(device, size.next_power_of_two()));
+            self.generation += 1;
+        }
+        if !matrices.is_empty() {
+            queue.write_buffer(self.buffer(), 0, bytemuck::cast_slice(&matrices));
+        }
+    }
+
+    /// Returns the buffer of the joint palettes.
+    pub(crate) fn buffer(&self) -> &wgpu::Buffer {
+        self.buffer
+            .as_ref()
+            .expect("joint palettes must be written before drawing")
+    }
+
+    /// Returns the index of the node's first joint matrix, if it's skinned.
+    pub(crate) fn offset(&self, node: Node) -> Option<u32> {
+        self.offsets.get(&node).copied()
+    }
+}
+
+fn buffer(device: &wgpu::Device, size: u64) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("joint palettes"),
+        size,
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
@@ -0,0 +1,451 @@
+//! # Skybox
+//!
+//! Sky drawn behind the meshes by the [crate::render::graph::SKYBOX] node, from an
+//! equirectangular [HdrImage] or an [HdrCubemap]. The sky is drawn at an infinite distance, so it
+//! turns with the camera but doesn't move with it.
+
+use std::collections::HashMap;
+use std::sync::Weak;
+
+use bytemuck::Pod;
+use bytemuck::Zeroable;
+use glam::Mat4;
+
+use crate::render::anti_aliasing::AntiAliasing;
+use crate::render::environment::equirectangular_texture;
+use crate::render::forward::Background;
+use crate::render::forward::Targets;
+use crate::render::graph::RenderContext;
+use crate::render::graph::RenderNode;
+use crate::render::image::HdrCubemap;
+use crate::render::image::HdrImage;
+use crate::render::image::HdrImageData;
+use crate::render::light::world_transform;
+use crate::render::target::set_viewport;
+use crate::render::tonemap::HdrTarget;
+use crate::render::tonemap::HDR_FORMAT;
+use crate::render::ClearColor;
+use crate::Camera;
+use crate::Component;
+use crate::Scene;
+
+/// # Skybox Image
+///
+/// High dynamic range image of the surroundings drawn by a [Skybox].
+#[derive(Clone, Debug, PartialEq)]
+pub enum SkyboxImage {
+    /// Equirectangular image with the top row straight up and the center in the negative Z
+    /// direction, like the image of an [crate::render::environment::EnvironmentLight].
+    Equirectangular(HdrImage),
+    /// Cubemap with a face for each direction.
+    Cubemap(HdrCubemap),
+}
+
+/// # Skybox
+///
+/// Sky drawn behind the meshes seen by the node's [Camera]. Insert it into the scene as a resource
+/// to draw it for every camera without one. Like the other effects of a render target, only the
+/// skybox of its first camera is drawn, and cameras drawing on top of it keep it as their
+/// background.
+///
+/// ```
+/// # use pulse::render::environment::EnvironmentLight;
+/// # use pulse::render::image::HdrImage;
+/// # use pulse::render::skybox::Skybox;
+/// # use pulse::render::skybox::SkyboxImage;
+/// # use pulse::Scene;
+/// let sky = HdrImage::from_pixel([0.4, 0.6, 1.0]);
+///
+/// let mut scene = Scene::new();
+/// scene.insert_resource(Skybox::new(SkyboxImage::Equirectangular(sky.clone())));
+/// scene.insert_resource(EnvironmentLight::new(sky));
+/// ```
+#[derive(Clone, Debug, PartialEq, Component)]
+pub struct Skybox {
+    /// Image of the sky.
+    pub image: SkyboxImage,
+    /// Factor the light of the image is multiplied with.
+    pub intensity: f32,
+}
+
+impl Skybox {
+    /// Returns the skybox of the image with an intensity of one.
+    pub fn new(image: SkyboxImage) -> Self {
+        Self {
+            image,
+            intensity: 1.0,
+        }
+    }
+}
+
+/// Uniforms of the skybox shader.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+struct Params {
+    inverse_view_projection: [[f32; 4]; 4],
+    intensity: f32,
+    _padding: [f32; 3],
+}
+
+/// Render node drawing the [Skybox] of the first camera as the background of the forward pass.
+pub(crate) struct SkyboxPass {
+    shader: wgpu::ShaderModule,
+    equirectangular_layout: wgpu::BindGroupLayout,
+    cubemap_layout: wgpu::BindGroupLayout,
+    /// Pipelines for cubemaps or equirectangular images and each sample count.
+    pipelines: HashMap<(bool, u32), wgpu::RenderPipeline>,
+    params: wgpu::Buffer,
+    /// Textures of the equirectangular images used so far, keyed by the image's pixels.
+    equirectangulars: HashMap<usize, (Weak<HdrImageData>, wgpu::TextureView)>,
+    /// Textures of the cubemaps used so far, keyed by the cubemap's faces.
+    cubemaps: HashMap<usize, (Weak<[HdrImage; 6]>, wgpu::TextureView)>,
+}
+
+impl SkyboxPass {
+    pub(crate) fn new(device: &wgpu::Device) -> Self {
+        let layout = |binding, view_dimension| {
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("skybox"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: wgpu::BufferSize::new(
+                                std::mem::size_of::<Params>() as u64
+                            ),
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                            view_dimension,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                ],
+            })
+        };
+
+        Self {
+            shader: device.create_shader_module(wgpu::include_wgsl!("shaders/skybox.wgsl")),
+            equirectangular_layout: layout(1, wgpu::TextureViewDimension::D2),
+            cubemap_layout: layout(2, wgpu::TextureViewDimension::D2),
+            pipelines: HashMap::new(),
+            params: device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("skybox params"),
+                size: std::mem::size_of::<Params>() as u64,
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }),
+            equirectangulars: HashMap::new(),
+            cubemaps: HashMap::new(),
+        }
+    }
+
+    /// Uploads the image if it wasn't uploaded yet.
+    fn upload(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, image: &SkyboxImage) {
+        // The id of a dropped image may have been reused by a new one.
+        match image {
+            SkyboxImage::Equirectangular(image) => {
+                if self
+                    .equirectangulars
+                    .get(&image.id())
+                    .is_none_or(|(data, _)| data.strong_count() == 0)
+                {
+                    let view = equirectangular_texture(device, queue, image);
+                    self.equirectangulars
+                        .insert(image.id(), (image.downgrade(), view));
+                }
+            }
+            SkyboxImage::Cubemap(cubemap) => {
+                if self
+                    .cubemaps
+                    .get(&cubemap.id())
+                    .is_none_or(|(data, _)| data.strong_count() == 0)
+                {
+                    let view = cubemap_texture(device, queue, cubemap);
+                    self.cubemaps
+                        .insert(cubemap.id(), (cubemap.downgrade(), view));
+                }
+            }
+        }
+    }
+
+    /// Returns the texture of the uploaded image.
+    fn get(&self, image: &SkyboxImage) -> &wgpu::TextureView {
+        match image {
+            SkyboxImage::Equirectangular(image) => &self.equirectangulars[&image.id()].1,
+            SkyboxImage::Cubemap(cubemap) => &self.cubemaps[&cubemap.id()].1,
+        }
+    }
+}
+
+impl RenderNode for SkyboxPass {
+    fn run(&mut self, context: &mut RenderContext, scene: &Scene) {
+        let Some(&node) = context.cameras.first() else {
+            return;
+        };
+        let Some(skybox) = scene
+            .get::<Skybox>(node)
+            .or_else(|| scene.get_resource::<Skybox>())
+        else {
+            return;
+        };
+        let device = context.device;
+        let samples = scene
+            .get_resource::<AntiAliasing>()
+            .copied()
+            .unwrap_or_default()
+            .sample_count();
+        HdrTarget::prepare(context);
+        Targets::prepare(context, samples);
+
+        self.equirectangulars
+            .retain(|_, (data, _)| data.strong_count() > 0);
+        self.cubemaps.retain(|_, (data, _)| data.strong_count() > 0);
+        self.upload(device, context.queue, &skybox.image);
+        let cubemap = matches!(skybox.image, SkyboxImage::Cubemap(_));
+        let (layout, binding, entry_point) = if cubemap {
+            (&self.cubemap_layout, 2, "fs_cubemap")
+        } else {
+            (&self.equirectangular_layout, 1, "fs_equirectangular")
+        };
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("skybox"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: self.params.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding,
+                    resource: wgpu::BindingResource::TextureView(self.get(&skybox.image)),
+                },
+            ],
+        });
+        let pipeline = self
+            .pipelines
+            .entry((cubemap, samples))
+            .or_insert_with(|| pipeline(device, &self.shader, layout, entry_point, samples));
+
+        // The sky only turns with the camera.
+        let camera = scene.get::<Camera>(node).unwrap();
+        let (_, rotation, _) = world_transform(scene, node)
+            .matrix
+            .to_scale_rotation_translation();
+        let view_projection = camera.projection_matrix() * Mat4::from_quat(rotation.inverse());
+        let params = Params {
+            inverse_view_projection: view_projection.inverse().to_cols_array_2d(),
+            intensity: skybox.intensity.max(0.0),
+            _padding: [0.0; 3],
+        };
+        context
+            .queue
+            .write_buffer(&self.params, 0, bytemuck::bytes_of(&params));
+
+        // Letterbox bars outside of the camera's viewport show the clear color.
+        let viewport = camera.viewport(context.target_size);
+        let clear_color = scene
+            .get_resource::<ClearColor>()
+            .copied()
+            .unwrap_or_default()
+            .0;
+        let hdr = &context.resources.resource::<HdrTarget>().unwrap().view;
+        let targets = context.resources.resource::<Targets>().unwrap();
+        {
+            let mut pass = context
+                .encoder
+                .begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some("skybox"),
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view: targets.color.as_ref().unwrap_or(hdr),
+                        resolve_target: targets.color.as_ref().map(|_| hdr),
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(clear_color.to_wgpu()),
+                            store: wgpu::StoreOp::Store,
+                        },
+                    })],
+                    depth_stencil_attachment: None,
+                    timestamp_writes: None,
+                    occlusion_query_set: None,
+                });
+            set_viewport(&mut pass, viewport);
+            pass.set_pipeline(pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            pass.draw(0..3, 0..1);
+        }
+        context.count_draws(1, 1);
+        context.insert_resource(Background);
+    }
+}
+
+fn pipeline(
+    device: &wgpu::Device,
+    shader: &wgpu::ShaderModule,
+    layout: &wgpu::BindGroupLayout,
+    entry_point: &str,
+    samples: u32,
+) -> wgpu::RenderPipeline {
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("skybox"),
+        bind_group_layouts: &[layout],
+        push_constant_ranges: &[],
+    });
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some(entry_point),
+        layout: Some(&pipeline_layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: "vs_main",
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+            buffers: &[],
+        },
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState {
+            count: samples,
+            ..wgpu::MultisampleState::default()
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point,
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+            targets: &[Some(HDR_FORMAT.into())],
+        }),
+        multiview: None,
+        cache: None,
+    })
+}
+
+/// Uploads the faces of the cubemap on top of each other to a 32-bit float texture six faces high.
+fn cubemap_texture(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    cubemap: &HdrCubemap,
+) -> wgpu::TextureView {
+    let size = wgpu::Extent3d {
+        width: cubemap.size(),
+        height: cubemap.size() * 6,
+        depth_or_array_layers: 1,
+    };
+    let pixels = cubemap
+        .faces()
+        .iter()
+        .flat_map(|face| face.pixels())
+        .map(|[r, g, b]| [*r, *g, *b, 1.0])
+        .collect::<Vec<_>>();
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("skybox cubemap"),
+        size,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: wgpu::TextureFormat::Rgba32Float,
+        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        view_formats: &[],
+    });
+    queue.write_texture(
+        texture.as_image_copy(),
+        bytemuck::cast_slice(&pixels),
+        wgpu::ImageDataLayout {
+            offset: 0,
+            bytes_per_row: Some(size.width * 16),
+            rows_per_image: None,
+        },
+        size,
+    );
+
+    texture.create_view(&wgpu::TextureViewDescriptor::default())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_2;
+
+    use glam::Quat;
+    use glam::UVec2;
+    use glam::Vec3;
+
+    use super::*;
+    use crate::render::mesh::Mesh;
+    use crate::render::tests::render;
+    use crate::render::tonemap::Tonemapping;
+    use crate::Node;
+    use crate::VisibleNodes;
+    use crate::WorldTransform;
+
+    /// Renders the scene from a camera at the origin turned by the rotation and seeing the
+    /// nodes, and returns the 64x64 RGBA pixels, or `None` if there's no adapter.
+    fn render_sky(mut scene: Scene, rotation: Quat, visible: Vec<Node>) -> Option<Vec<u8>> {
+        scene.spawn_with((
+            Camera {
+                tonemapping: Tonemapping::None,
+                ..Camera::perspective(1.0, 1.0, 0.1, 10.0)
+            },
+            WorldTransform::new(Mat4::from_quat(rotation)),
+            VisibleNodes(visible),
+        ));
+        render(&scene)
+    }
+
+    #[test]
+    fn render_draws_equirectangular_sky_behind_meshes() {
+        let sky = HdrImage::from_pixel([0.5, 0.25, 0.0]);
+        let mut scene = Scene::new();
+        let cube = scene.spawn_with((
+            Mesh::cube(1.0),
+            WorldTransform::new(Mat4::from_translation(Vec3::new(0.0, 0.0, -3.0))),
+        ));
+        scene.insert_resource(Skybox::new(SkyboxImage::Equirectangular(sky)));
+        let Some(pixels) = render_sky(scene, Quat::IDENTITY, vec![cube]) else {
+            return;
+        };
+
+        let pixel = |x: usize, y: usize| &pixels[(y * 64 + x) * 4..][..4];
+        assert_eq!(pixel(0, 0), [128, 64, 0, 255]);
+        // The unlit cube covers the sky.
+        assert_ne!(pixel(32, 32), [128, 64, 0, 255]);
+    }
+
+    #[test]
+    fn render_draws_cubemap_face_in_view_direction() {
+        let faces = [
+            [1.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0],
+            [0.0, 0.0, 1.0],
+            [1.0, 1.0, 0.0],
+            [1.0, 0.0, 1.0],
+            [0.0, 1.0, 1.0],
+        ];
+        let cubemap = HdrCubemap::new(
+            faces.map(|face| HdrImage::new(UVec2::new(2, 2), vec![face; 4]).unwrap()),
+        )
+        .unwrap();
+        let skybox = Skybox {
+            intensity: 0.5,
+            ..Skybox::new(SkyboxImage::Cubemap(cubemap))
+        };
+        let turns = [
+            (Quat::IDENTITY, [0, 128, 128, 255]),
+            (Quat::from_rotation_y(-FRAC_PI_2), [128, 0, 0, 255]),
+            (Quat::from_rotation_x(FRAC_PI_2), [0, 0, 128, 255]),
+        ];
+        for (rotation, color) in turns {
+            let mut scene = Scene::new();
+            scene.insert_resource(skybox.clone());
+            let Some(pixels) = render_sky(scene, rotation, Vec::new()) else {
+                return;
+            };
+
+            assert_eq!(&pixels[(32 * 64 + 32) * 4..][..4], color);
+        }
+    }
+}
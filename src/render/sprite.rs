@@ -0,0 +1,736 @@
+//! # Sprite
+//!
+//! Textured quads for 2D rendering, drawn by the [crate::render::graph::SPRITE] node on top of
+//! the meshes together with the glyphs of [Text] nodes. Sprites are unlit and alpha blended, so
+//! they're drawn from back to front, and consecutive sprites with the same image are drawn
+//! together.
+
+use std::ops::Range;
+
+use bytemuck::Pod;
+use bytemuck::Zeroable;
+use glam::Mat4;
+use glam::Vec2;
+use glam::Vec3;
+
+use crate::render::graph::RenderContext;
+use crate::render::graph::RenderNode;
+use crate::render::image::ColorSpace;
+use crate::render::image::Image;
+use crate::render::light::world_transform;
+use crate::render::target::set_viewport;
+use crate::render::text::AtlasGlyph;
+use crate::render::text::GlyphAtlas;
+use crate::render::text::Text;
+use crate::render::tonemap::HdrTarget;
+use crate::render::tonemap::HDR_FORMAT;
+use crate::render::Color;
+use crate::render::UNIFORM_ALIGNMENT;
+use crate::Aabb;
+use crate::Camera;
+use crate::Component;
+use crate::Node;
+use crate::ScalingMode;
+use crate::Scene;
+use crate::VisibleNodes;
+
+/// # Sprite
+///
+/// Image drawn on a rectangle in the XY plane of the node's [crate::WorldTransform], facing +Z,
+/// when the node is visible to a camera. Images are drawn in their pixel size in world units
+/// unless the sprite has a custom size, so an orthographic [Camera] whose height is the window's
+/// height shows them pixel for pixel. Sprites without an image are filled with their color.
+///
+/// The node's [Aabb] is computed from the sprite by [crate::systems::compute_sprite_bounds].
+///
+/// ```
+/// # use glam::Vec2;
+/// # use pulse::render::image::ColorSpace;
+/// # use pulse::render::image::Image;
+/// # use pulse::render::sprite::Sprite;
+/// # use pulse::render::Color;
+/// # use pulse::Scene;
+/// let image = Image::from_pixel([255, 255, 255, 255], ColorSpace::Srgb);
+///
+/// let mut scene = Scene::new();
+/// scene.spawn_with(Sprite {
+///     color: Color::rgb(1.0, 0.0, 0.0),
+///     flip_x: true,
+///     anchor: Vec2::ZERO,
+///     custom_size: Some(Vec2::new(32.0, 16.0)),
+///     ..Sprite::new(image)
+/// });
+/// ```
+#[derive(Clone, Debug, PartialEq, Component)]
+pub struct Sprite {
+    /// Image drawn by the sprite, if any.
+    pub image: Option<Image>,
+    /// Color the image is multiplied with.
+    pub color: Color,
+    /// Whether the image is mirrored horizontally.
+    pub flip_x: bool,
+    /// Whether the image is mirrored vertically.
+    pub flip_y: bool,
+    /// Point of the sprite at the node's origin, from (0, 0) at the bottom-left to (1, 1) at the
+    /// top-right corner.
+    pub anchor: Vec2,
+    /// Size of the sprite in world units, instead of the image's size in pixels.
+    pub custom_size: Option<Vec2>,
+}
+
+impl Sprite {
+    /// Returns a sprite drawing the image in its pixel size, centered on the node's origin.
+    pub fn new(image: Image) -> Self {
+        Self {
+            image: Some(image),
+            ..Self::default()
+        }
+    }
+
+    /// Returns the size of the sprite in world units: its custom size, or else its image's size,
+    /// or else 1 by 1.
+    pub fn size(&self) -> Vec2 {
+        self.custom_size.unwrap_or_else(|| {
+            self.image
+                .as_ref()
+                .map_or(Vec2::ONE, |image| image.size().as_vec2())
+        })
+    }
+
+    /// Returns the bounds of the sprite's rectangle.
+    pub fn aabb(&self) -> Aabb {
+        let min = (-self.anchor * self.size()).extend(0.0);
+        let max = min + self.size().extend(0.0);
+        // Negative sizes mirror the sprite.
+        Aabb::new(min.min(max), min.max(max))
+    }
+
+    /// Returns the matrix mapping the unit square to the sprite's rectangle.
+    fn matrix(&self) -> Mat4 {
+        let size = self.size();
+        Mat4::from_translation((-self.anchor * size).extend(0.0))
+            * Mat4::from_scale(size.extend(1.0))
+    }
+
+    /// Returns the texture coordinates of the bottom-left corner, and the offset from it to the
+    /// top-right corner.
+    fn uv_rect(&self) -> [f32; 4] {
+        let (x, width) = if self.flip_x { (1.0, -1.0) } else { (0.0, 1.0) };
+        let (y, height) = if self.flip_y { (0.0, 1.0) } else { (1.0, -1.0) };
+        [x, y, width, height]
+    }
+}
+
+impl Default for Sprite {
+    /// Returns a white 1 by 1 sprite without an image, centered on the node's origin.
+    fn default() -> Self {
+        Self {
+            image: None,
+            color: Color::WHITE,
+            flip_x: false,
+            flip_y: false,
+            anchor: Vec2::splat(0.5),
+            custom_size: None,
+        }
+    }
+}
+
+/// Per-instance vertex data of a sprite in the shader.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+struct SpriteInstance {
+    model: [[f32; 4]; 4],
+    color: [f32; 4],
+    uv_rect: [f32; 4],
+}
+
+impl SpriteInstance {
+    const ATTRIBUTES: [wgpu::VertexAttribute; 6] = wgpu::vertex_attr_array![
+        0 => Float32x4,
+        1 => Float32x4,
+        2 => Float32x4,
+        3 => Float32x4,
+        4 => Float32x4,
+        5 => Float32x4,
+    ];
+
+    fn layout() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Self>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+}
+
+/// Consecutive instances of sprites with the same image, `None` for sprites without one.
+type Batch = (Option<Image>, Range<u32>);
+
+/// Sprite or glyphs of a text to draw, with the world matrix of its node.
+enum Drawable<'a> {
+    Sprite(&'a Sprite, Mat4),
+    Text(&'a Text, Mat4, Vec<(AtlasGlyph, Vec2, f32)>),
+}
+
+/// Render node drawing the visible [Sprite] and [Text] nodes of every camera to the [HdrTarget].
+pub(crate) struct SpritePass {
+    pipeline: wgpu::RenderPipeline,
+    camera_layout: wgpu::BindGroupLayout,
+    texture_layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    /// Sampler of cameras with [ScalingMode::PixelPerfect], keeping the pixels of images sharp.
+    pixel_sampler: wgpu::Sampler,
+    /// Image of sprites without one.
+    white: Image,
+    atlas: GlyphAtlas,
+    camera_buffer: wgpu::Buffer,
+    camera_bind_group: wgpu::BindGroup,
+    instance_buffer: wgpu::Buffer,
+}
+
+impl SpritePass {
+    pub(crate) fn new(device: &wgpu::Device) -> Self {
+        let shader = device.create_shader_module(wgpu::include_wgsl!("shaders/sprite.wgsl"));
+        let camera_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("sprite camera"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: true,
+                    min_binding_size: wgpu::BufferSize::new(
+                        std::mem::size_of::<[[f32; 4]; 4]>() as u64
+                    ),
+                },
+                count: None,
+            }],
+        });
+        let texture_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("sprite texture"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("sprite"),
+            bind_group_layouts: &[&camera_layout, &texture_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("sprite"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                buffers: &[SpriteInstance::layout()],
+            },
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleStrip,
+                ..wgpu::PrimitiveState::default()
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: HDR_FORMAT,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            multiview: None,
+            cache: None,
+        });
+        let camera_buffer = camera_buffer(device, 1);
+
+        Self {
+            pipeline,
+            camera_bind_group: camera_bind_group(device, &camera_layout, &camera_buffer),
+            camera_layout,
+            texture_layout,
+            sampler: device.create_sampler(&wgpu::SamplerDescriptor {
+                label: Some("sprite"),
+                mag_filter: wgpu::FilterMode::Linear,
+                min_filter: wgpu::FilterMode::Linear,
+                mipmap_filter: wgpu::FilterMode::Linear,
+                ..wgpu::SamplerDescriptor::default()
+            }),
+            pixel_sampler: device.create_sampler(&wgpu::SamplerDescriptor {
+                label: Some("sprite pixels"),
+                ..wgpu::SamplerDescriptor::default()
+            }),
+            white: Image::from_pixel([255; 4], ColorSpace::Linear),
+            atlas: GlyphAtlas::new(),
+            camera_buffer,
+            instance_buffer: instance_buffer(device, 1),
+        }
+    }
+}
+
+impl RenderNode for SpritePass {
+    fn run(&mut self, context: &mut RenderContext, scene: &Scene) {
+        if context.resource::<HdrTarget>().is_none() {
+            return;
+        }
+        let device = context.device;
+        let queue = context.queue;
+        let mut uniforms = Vec::new();
+        let mut instances = Vec::new();
+        let mut draws = Vec::new();
+        let mut views = Vec::new();
+        for &node in context.cameras {
+            let camera = scene.get::<Camera>(node).unwrap();
+            let pixel_perfect = matches!(camera.scaling, ScalingMode::PixelPerfect { .. });
+            views.push((camera.viewport(context.target_size), pixel_perfect));
+            let transform = world_transform(scene, node);
+            let mut uniform = [0; UNIFORM_ALIGNMENT as usize];
+            let view_projection = camera.view_projection_matrix(&transform);
+            uniform[..64].copy_from_slice(bytemuck::bytes_of(&view_projection.to_cols_array_2d()));
+            uniforms.extend(uniform);
+
+            let visible = scene
+                .get::<VisibleNodes>(node)
+                .map_or(&[][..], |visible| &visible.0);
+            let forward = transform.forward().normalize_or_zero();
+            let position = transform.translation();
+            draws.push(batch(
+                scene,
+                visible,
+                (forward, position),
+                &mut self.atlas,
+                &mut instances,
+            ));
+        }
+        if instances.is_empty() {
+            return;
+        }
+
+        let images = &mut context.resources.images;
+        images.upload(device, queue, &self.white);
+        for (image, _) in draws.iter().flatten() {
+            if let Some(image) = image {
+                images.upload(device, queue, image);
+            }
+        }
+
+        if self.camera_buffer.size() < uniforms.len() as u64 {
+            self.camera_buffer = camera_buffer(device, (draws.len() as u64).next_power_of_two());
+            self.camera_bind_group =
+                camera_bind_group(device, &self.camera_layout, &self.camera_buffer);
+        }
+        queue.write_buffer(&self.camera_buffer, 0, &uniforms);
+        let instance_size = std::mem::size_of::<SpriteInstance>() as u64;
+        if self.instance_buffer.size() < instances.len() as u64 * instance_size {
+            self.instance_buffer =
+                instance_buffer(device, (instances.len() as u64).next_power_of_two());
+        }
+        queue.write_buffer(&self.instance_buffer, 0, bytemuck::cast_slice(&instances));
+
+        let images = &context.resources.images;
+        let texture_bind_groups = draws
+            .iter()
+            .zip(&views)
+            .map(|(batches, (_, pixel_perfect))| {
+                let sampler = if *pixel_perfect {
+                    &self.pixel_sampler
+                } else {
+                    &self.sampler
+                };
+                batches
+                    .iter()
+                    .map(|(image, _)| {
+                        let image = image.as_ref().unwrap_or(&self.white);
+                        let view = images.get(image).unwrap();
+                        // Pixel perfect cameras sample every image's nearest pixel.
+                        let sampler = match images.sampler(image) {
+                            Some(sampler) if !*pixel_perfect => sampler,
+                            _ => sampler,
+                        };
+                        device.create_bind_group(&wgpu::BindGroupDescriptor {
+                            label: Some("sprite texture"),
+                            layout: &self.texture_layout,
+                            entries: &[
+                                wgpu::BindGroupEntry {
+                                    binding: 0,
+                                    resource: wgpu::BindingResource::TextureView(view),
+                                },
+                                wgpu::BindGroupEntry {
+                                    binding: 1,
+                                    resource: wgpu::BindingResource::Sampler(sampler),
+                                },
+                            ],
+                        })
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        // Every instance is a quad of two triangles.
+        let batches = draws.iter().flatten();
+        let draw_calls = batches.clone().count() as u32;
+        let triangles = batches
+            .map(|(_, instances)| 2 * instances.len() as u64)
+            .sum();
+        context.count_draws(draw_calls, triangles);
+
+        let target = &context.resources.resource::<HdrTarget>().unwrap().view;
+        let mut pass = context
+            .encoder
+            .begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("sprite"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: target,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+        pass.set_pipeline(&self.pipeline);
+        pass.set_vertex_buffer(0, self.instance_buffer.slice(..));
+        for (index, ((batches, bind_groups), (viewport, _))) in draws
+            .iter()
+            .zip(&texture_bind_groups)
+            .zip(&views)
+            .enumerate()
+        {
+            set_viewport(&mut pass, *viewport);
+            let offset = index as u32 * UNIFORM_ALIGNMENT as u32;
+            pass.set_bind_group(0, &self.camera_bind_group, &[offset]);
+            for ((_, instances), bind_group) in batches.iter().zip(bind_groups) {
+                pass.set_bind_group(1, bind_group, &[]);
+                pass.draw(0..4, instances.clone());
+            }
+        }
+    }
+}
+
+/// Appends the instances of the visible sprite and text nodes to the instances, from back to front
+/// as seen from the position along the forward direction of the view, and returns the batches of
+/// consecutive sprites with the same image. Sprites at the same depth are sorted by image to batch
+/// them, and the glyphs of texts are added to the atlas.
+fn batch(
+    scene: &Scene,
+    visible: &[Node],
+    (forward, position): (Vec3, Vec3),
+    atlas: &mut GlyphAtlas,
+    instances: &mut Vec<SpriteInstance>,
+) -> Vec<Batch> {
+    let mut drawables = Vec::new();
+    for &node in visible {
+        let transform = world_transform(scene, node).matrix;
+        if let Some(sprite) = scene.get::<Sprite>(node) {
+            drawables.push(Drawable::Sprite(sprite, transform * sprite.matrix()));
+        }
+        if let Some(text) = scene.get::<Text>(node) {
+            // Glyphs are rasterized at whole pixel sizes and scaled to the font size.
+            let pixels = text.size.round().max(1.0);
+            let glyphs = text
+                .layout()
+                .glyphs
+                .into_iter()
+                .filter_map(|(glyph, origin)| {
+                    let glyph = atlas.glyph(&text.font, glyph, pixels as u32)?;
+                    Some((glyph, origin, text.size / pixels))
+                })
+                .collect();
+            drawables.push(Drawable::Text(text, transform, glyphs));
+        }
+    }
+
+    let atlas_image = drawables
+        .iter()
+        .any(|drawable| matches!(drawable, Drawable::Text(..)))
+        .then(|| atlas.image().clone());
+    let mut drawables = drawables
+        .into_iter()
+        .map(|drawable| {
+            let (matrix, image) = match &drawable {
+                Drawable::Sprite(sprite, matrix) => (matrix, sprite.image.clone()),
+                Drawable::Text(_, matrix, _) => (matrix, atlas_image.clone()),
+            };
+            let depth = (matrix.w_axis.truncate() - position).dot(forward);
+            (depth, image, drawable)
+        })
+        .collect::<Vec<_>>();
+    drawables.sort_by(|a, b| {
+        let image = |image: &Option<Image>| image.as_ref().map_or(0, Image::id);
+        b.0.total_cmp(&a.0).then(image(&a.1).cmp(&image(&b.1)))
+    });
+
+    let mut batches = Vec::<Batch>::new();
+    for (_, drawable_image, drawable) in drawables {
+        let start = instances.len() as u32;
+        match drawable {
+            Drawable::Sprite(sprite, matrix) => instances.push(SpriteInstance {
+                model: matrix.to_cols_array_2d(),
+                color: sprite.color.to_vec4().to_array(),
+                uv_rect: sprite.uv_rect(),
+            }),
+            Drawable::Text(text, matrix, glyphs) => {
+                instances.extend(glyphs.iter().map(|(glyph, origin, scale)| SpriteInstance {
+                    model: (matrix * glyph.matrix(*origin, *scale)).to_cols_array_2d(),
+                    color: text.color.to_vec4().to_array(),
+                    uv_rect: atlas.uv_rect(glyph),
+                }));
+            }
+        }
+        let end = instances.len() as u32;
+        match batches.last_mut() {
+            Some((image, range)) if *image == drawable_image => range.end = end,
+            _ => batches.push((drawable_image, start..end)),
+        }
+    }
+    batches
+}
+
+fn camera_buffer(device: &wgpu::Device, cameras: u64) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("sprite cameras"),
+        size: cameras * UNIFORM_ALIGNMENT,
+        usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+fn camera_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    buffer: &wgpu::Buffer,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("sprite cameras"),
+        layout,
+        entries: &[wgpu::BindGroupEntry {
+            binding: 0,
+            resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                buffer,
+                offset: 0,
+                size: wgpu::BufferSize::new(std::mem::size_of::<[[f32; 4]; 4]>() as u64),
+            }),
+        }],
+    })
+}
+
+fn instance_buffer(device: &wgpu::Device, instances: u64) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("sprite instances"),
+        size: instances.max(1) * std::mem::size_of::<SpriteInstance>() as u64,
+        usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::UVec2;
+
+    use super::*;
+    use crate::render::anti_aliasing::AntiAliasing;
+    use crate::render::tests::render;
+    use crate::render::text::Font;
+    use crate::render::text::Text;
+    use crate::render::tonemap::Tonemapping;
+    use crate::render::ClearColor;
+    use crate::WorldTransform;
+
+    /// Renders the sprites at their positions, see [render_nodes].
+    fn render_sprites(sprites: Vec<(Sprite, Vec3)>) -> Option<impl Fn(usize, usize) -> [u8; 4]> {
+        let mut scene = Scene::new();
+        let sprites = sprites
+            .into_iter()
+            .map(|(sprite, position)| (scene.spawn_with(sprite), position))
+            .collect();
+        render_nodes(scene, sprites)
+    }
+
+    /// Renders the nodes at their positions with a 64 units high orthographic camera and returns
+    /// a function returning the pixels, or `None` if there's no adapter.
+    fn render_nodes(
+        mut scene: Scene,
+        nodes: Vec<(Node, Vec3)>,
+    ) -> Option<impl Fn(usize, usize) -> [u8; 4]> {
+        scene.insert_resource(ClearColor(Color::BLACK));
+        scene.insert_resource(AntiAliasing::None);
+        let nodes = nodes
+            .into_iter()
+            .map(|(node, position)| {
+                scene.add(node, WorldTransform::new(Mat4::from_translation(position)));
+                node
+            })
+            .collect();
+        scene.spawn_with((
+            Camera {
+                tonemapping: Tonemapping::None,
+                ..Camera::orthographic(64.0, 1.0, 0.1, 100.0)
+            },
+            WorldTransform::new(Mat4::from_translation(Vec3::new(0.0, 0.0, 10.0))),
+            VisibleNodes(nodes),
+        ));
+
+        let pixels = render(&scene)?;
+        Some(move |x: usize, y: usize| pixels[(y * 64 + x) * 4..][..4].try_into().unwrap())
+    }
+
+    #[test]
+    fn size_defaults_to_image_size() {
+        let image = Image::new(UVec2::new(4, 2), vec![0; 32], ColorSpace::Srgb).unwrap();
+        let sprite = Sprite {
+            anchor: Vec2::new(0.0, 1.0),
+            ..Sprite::new(image)
+        };
+
+        assert_eq!(sprite.size(), Vec2::new(4.0, 2.0));
+        assert_eq!(
+            sprite.aabb(),
+            Aabb::new(Vec3::new(0.0, -2.0, 0.0), Vec3::new(4.0, 0.0, 0.0))
+        );
+        assert_eq!(Sprite::default().size(), Vec2::ONE);
+    }
+
+    #[test]
+    fn render_draws_flipped_images() {
+        let pixels = [[255, 0, 0, 255], [0, 255, 0, 255]].concat();
+        let image = Image::new(UVec2::new(2, 1), pixels, ColorSpace::Srgb).unwrap();
+        let sprite = Sprite {
+            custom_size: Some(Vec2::splat(32.0)),
+            ..Sprite::new(image)
+        };
+        let flipped = Sprite {
+            flip_x: true,
+            ..sprite.clone()
+        };
+        let Some(pixel) = render_sprites(vec![(sprite, Vec3::ZERO)]) else {
+            return;
+        };
+
+        assert_eq!(pixel(8, 32), [0, 0, 0, 255]);
+        assert_eq!(pixel(20, 32), [255, 0, 0, 255]);
+        assert_eq!(pixel(44, 32), [0, 255, 0, 255]);
+        let pixel = render_sprites(vec![(flipped, Vec3::ZERO)]).unwrap();
+        assert_eq!(pixel(20, 32), [0, 255, 0, 255]);
+        assert_eq!(pixel(44, 32), [255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn render_draws_sprites_back_to_front() {
+        let solid = |color, size| Sprite {
+            color,
+            custom_size: Some(Vec2::splat(size)),
+            ..Sprite::default()
+        };
+        let Some(pixel) = render_sprites(vec![
+            (solid(Color::rgb(0.0, 0.0, 1.0), 16.0), Vec3::Z),
+            (solid(Color::rgb(1.0, 0.0, 0.0), 32.0), Vec3::ZERO),
+            (
+                solid(Color::rgba(0.0, 1.0, 0.0, 0.5), 8.0),
+                Vec3::new(0.0, 16.0, 2.0),
+            ),
+        ]) else {
+            return;
+        };
+
+        assert_eq!(pixel(32, 32), [0, 0, 255, 255]);
+        assert_eq!(pixel(20, 32), [255, 0, 0, 255]);
+        // Half transparent green over red, and over the black background.
+        assert_eq!(pixel(32, 17)[..3], [128, 128, 0]);
+        assert_eq!(pixel(32, 12)[..3], [0, 128, 0]);
+    }
+
+    #[test]
+    fn render_draws_text_glyphs() {
+        let font = include_bytes!("../../assets/fonts/Cantarell-Regular.ttf");
+        let font = Font::from_bytes(font.to_vec()).unwrap();
+        let mut scene = Scene::new();
+        let text = Text {
+            color: Color::rgb(1.0, 0.0, 0.0),
+            anchor: Vec2::splat(0.5),
+            ..Text::new("I", font, 48.0)
+        };
+        let text = scene.spawn_with(text);
+        let below = scene.spawn_with(Sprite {
+            color: Color::rgb(0.0, 0.0, 1.0),
+            custom_size: Some(Vec2::splat(64.0)),
+            ..Sprite::default()
+        });
+        let Some(pixel) = render_nodes(scene, vec![(text, Vec3::Z), (below, Vec3::ZERO)]) else {
+            return;
+        };
+
+        // The stem of the I in front of the sprite.
+        assert_eq!(pixel(32, 32), [255, 0, 0, 255]);
+        assert_eq!(pixel(8, 32), [0, 0, 255, 255]);
+    }
+
+    #[test]
+    fn render_fits_views_into_target() {
+        let render_scaled = |scaling, aspect_ratio| {
+            let mut scene = Scene::new();
+            scene.insert_resource(ClearColor(Color::BLACK));
+            scene.insert_resource(AntiAliasing::None);
+            let sprite = scene.spawn_with((
+                Sprite {
+                    color: Color::rgb(1.0, 0.0, 0.0),
+                    custom_size: Some(Vec2::splat(256.0)),
+                    ..Sprite::default()
+                },
+                WorldTransform::IDENTITY,
+            ));
+            scene.spawn_with((
+                Camera {
+                    tonemapping: Tonemapping::None,
+                    scaling,
+                    ..Camera::orthographic(64.0, aspect_ratio, 0.1, 100.0)
+                },
+                WorldTransform::new(Mat4::from_translation(Vec3::new(0.0, 0.0, 10.0))),
+                VisibleNodes(vec![sprite]),
+            ));
+            let pixels = render(&scene)?;
+            Some(move |x: usize, y: usize| -> [u8; 4] {
+                pixels[(y * 64 + x) * 4..][..4].try_into().unwrap()
+            })
+        };
+        let Some(pixel) = render_scaled(ScalingMode::Letterbox, 2.0) else {
+            return;
+        };
+
+        // Bars above and below the 64x32 view.
+        assert_eq!(pixel(32, 8), [0, 0, 0, 255]);
+        assert_eq!(pixel(32, 32), [255, 0, 0, 255]);
+        assert_eq!(pixel(32, 55), [0, 0, 0, 255]);
+
+        // A 20x20 view scaled by 3 leaves two pixels at each side.
+        let scaling = ScalingMode::PixelPerfect {
+            width: 20,
+            height: 20,
+        };
+        let pixel = render_scaled(scaling, 1.0).unwrap();
+        assert_eq!(pixel(1, 32), [0, 0, 0, 255]);
+        assert_eq!(pixel(2, 32), [255, 0, 0, 255]);
+        assert_eq!(pixel(61, 32), [255, 0, 0, 255]);
+        assert_eq!(pixel(62, 32), [0, 0, 0, 255]);
+    }
+}
@@ -0,0 +1,344 @@
+//! # Render Stats
+//!
+//! Statistics of the rendered frames, e.g. draw calls and the GPU time of every render node, for
+//! finding what makes a frame slow.
+
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Device features required for timing the render nodes.
+pub(crate) const TIMESTAMP_FEATURES: wgpu::Features =
+    wgpu::Features::TIMESTAMP_QUERY.union(wgpu::Features::TIMESTAMP_QUERY_INSIDE_ENCODERS);
+
+/// Maximum number of timestamps written in a frame.
+const MAX_TIMESTAMPS: u32 = 128;
+
+/// # Render Stats
+///
+/// Scene resource with the statistics of the last rendered frame, replaced after every frame. The
+/// GPU times of the nodes are read back asynchronously, so they're those of an earlier frame and
+/// empty if the device doesn't support timestamp queries, e.g. on the web.
+///
+/// ```
+/// # use pulse::render::graph::FORWARD;
+/// # use pulse::render::stats::RenderStats;
+/// # use pulse::Scene;
+/// fn report(scene: &Scene) {
+///     if let Some(stats) = scene.get_resource::<RenderStats>() {
+///         println!("{} draw calls", stats.draw_calls);
+///         println!("forward pass: {:?}", stats.node_time(FORWARD));
+///     }
+/// }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RenderStats {
+    /// Number of draw calls recorded by the render nodes.
+    pub draw_calls: u32,
+    /// Number of triangles drawn, including every instance.
+    pub triangles: u64,
+    /// GPU time of every enabled render node, summed over the render targets, in the order the
+    /// nodes ran.
+    pub node_times: Vec<(&'static str, Duration)>,
+    /// Bytes of GPU memory allocated for buffers, or zero if the backend doesn't track it.
+    pub buffer_memory: u64,
+    /// Bytes of GPU memory allocated for textures, or zero if the backend doesn't track it.
+    pub texture_memory: u64,
+}
+
+impl RenderStats {
+    /// Returns the GPU time of the render node with the label, if it was timed.
+    pub fn node_time(&self, label: &str) -> Option<Duration> {
+        self.node_times
+            .iter()
+            .find(|(node, _)| *node == label)
+            .map(|(_, time)| *time)
+    }
+
+    /// Returns the GPU time of all timed render nodes.
+    pub fn gpu_time(&self) -> Duration {
+        self.node_times.iter().map(|(_, time)| *time).sum()
+    }
+}
+
+/// Records the statistics of the frames, timing the render nodes if the device supports it.
+pub(crate) struct FrameProfiler {
+    pub(crate) stats: RenderStats,
+    timer: Option<GpuTimer>,
+}
+
+impl FrameProfiler {
+    pub(crate) fn new(device: &wgpu::Device) -> Self {
+        Self {
+            stats: RenderStats::default(),
+            timer: device
+                .features()
+                .contains(TIMESTAMP_FEATURES)
+                .then(|| GpuTimer::new(device)),
+        }
+    }
+
+    /// Resets the counts for a new frame and takes the node times of an earlier frame once they
+    /// were read back.
+    pub(crate) fn begin_frame(&mut self, device: &wgpu::Device) {
+        self.stats.draw_calls = 0;
+        self.stats.triangles = 0;
+        if let Some(times) = self.timer.as_mut().and_then(|timer| timer.begin(device)) {
+            self.stats.node_times = times;
+        }
+    }
+
+    /// Writes the timestamp starting the node, which ends the previous node of the graph's run.
+    pub(crate) fn start_node(&mut self, encoder: &mut wgpu::CommandEncoder, label: &'static str) {
+        if let Some(timer) = &mut self.timer {
+            timer.start(encoder, label);
+        }
+    }
+
+    /// Writes the timestamp ending the last node of the graph's run.
+    pub(crate) fn end_run(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        if let Some(timer) = &mut self.timer {
+            timer.end(encoder);
+        }
+    }
+
+    /// Reads the memory counters and starts reading back the frame's timestamps.
+    pub(crate) fn finish_frame(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        let counters = device.get_internal_counters();
+        self.stats.buffer_memory = counters.hal.buffer_memory.read().max(0) as u64;
+        self.stats.texture_memory = counters.hal.texture_memory.read().max(0) as u64;
+        if let Some(timer) = &mut self.timer {
+            timer.finish(device, queue);
+        }
+    }
+}
+
+/// Timed nodes with the index of their start timestamp, ended by the next one.
+type Spans = Vec<(&'static str, u32)>;
+
+/// Timestamp queries around the render nodes, read back one frame at a time. Frames recorded while
+/// the previous readback is pending aren't timed.
+struct GpuTimer {
+    query_set: wgpu::QuerySet,
+    resolve: wgpu::Buffer,
+    readback: wgpu::Buffer,
+    /// Nanoseconds per timestamp tick.
+    period: f64,
+    /// Whether the frame being recorded is timed.
+    recording: bool,
+    /// Nodes timed in the frame being recorded.
+    spans: Spans,
+    count: u32,
+    /// Nodes of the frame being read back and whether the readback is mapped.
+    pending: Option<(Spans, Arc<AtomicBool>)>,
+}
+
+impl GpuTimer {
+    fn new(device: &wgpu::Device) -> Self {
+        let size = u64::from(MAX_TIMESTAMPS) * wgpu::QUERY_SIZE as u64;
+        Self {
+            query_set: device.create_query_set(&wgpu::QuerySetDescriptor {
+                label: Some("node timestamps"),
+                ty: wgpu::QueryType::Timestamp,
+                count: MAX_TIMESTAMPS,
+            }),
+            resolve: device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("node timestamps"),
+                size,
+                usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+                mapped_at_creation: false,
+            }),
+            readback: device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("node timestamps readback"),
+                size,
+                usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+                mapped_at_creation: false,
+            }),
+            period: 0.0,
+            recording: false,
+            spans: Vec::new(),
+            count: 0,
+            pending: None,
+        }
+    }
+
+    /// Starts recording a frame if no readback is pending and returns the node times of the last
+    /// frame read back, if any.
+    fn begin(&mut self, device: &wgpu::Device) -> Option<Vec<(&'static str, Duration)>> {
+        device.poll(wgpu::Maintain::Poll);
+        let mut times = None;
+        if let Some((spans, _)) = self
+            .pending
+            .take_if(|(_, mapped)| mapped.load(Ordering::Acquire))
+        {
+            times = Some(self.read(&spans));
+            self.readback.unmap();
+        }
+
+        self.recording = self.pending.is_none();
+        self.spans.clear();
+        self.count = 0;
+        times
+    }
+
+    fn start(&mut self, encoder: &mut wgpu::CommandEncoder, label: &'static str) {
+        // Keep room for the timestamp ending the node.
+        if !self.recording || self.count + 1 >= MAX_TIMESTAMPS {
+            return;
+        }
+
+        encoder.write_timestamp(&self.query_set, self.count);
+        self.spans.push((label, self.count));
+        self.count += 1;
+    }
+
+    fn end(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        if self
+            .spans
+            .last()
+            .is_some_and(|(_, start)| start + 1 == self.count)
+        {
+            encoder.write_timestamp(&self.query_set, self.count);
+            self.count += 1;
+        }
+    }
+
+    fn finish(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        if !self.recording || self.count == 0 {
+            return;
+        }
+
+        let size = u64::from(self.count) * wgpu::QUERY_SIZE as u64;
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("node timestamps"),
+        });
+        encoder.resolve_query_set(&self.query_set, 0..self.count, &self.resolve, 0);
+        encoder.copy_buffer_to_buffer(&self.resolve, 0, &self.readback, 0, size);
+        queue.submit([encoder.finish()]);
+        self.period = f64::from(queue.get_timestamp_period());
+
+        let mapped = Arc::new(AtomicBool::new(false));
+        let callback = Arc::clone(&mapped);
+        self.readback
+            .slice(..size)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                callback.store(result.is_ok(), Ordering::Release);
+            });
+        self.pending = Some((std::mem::take(&mut self.spans), mapped));
+    }
+
+    /// Returns the times of the nodes from the mapped timestamps, summing those of the same node.
+    fn read(&self, spans: &[(&'static str, u32)]) -> Vec<(&'static str, Duration)> {
+        let end = spans.iter().map(|(_, start)| start + 2).max().unwrap_or(0);
+        let bytes = self
+            .readback
+            .slice(..u64::from(end) * wgpu::QUERY_SIZE as u64)
+            .get_mapped_range();
+        let timestamps = bytemuck::cast_slice::<u8, u64>(&bytes);
+
+        let mut times = Vec::<(&'static str, Duration)>::new();
+        for (label, start) in spans {
+            let ticks = timestamps[*start as usize + 1].saturating_sub(timestamps[*start as usize]);
+            let time = Duration::from_nanos((ticks as f64 * self.period) as u64);
+            match times.iter_mut().find(|(node, _)| node == label) {
+                Some((_, total)) => *total += time,
+                None => times.push((label, time)),
+            }
+        }
+        times
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use glam::Mat4;
+    use glam::Vec3;
+
+    use super::*;
+    use crate::render::graph::RenderContext;
+    use crate::render::graph::RenderNode;
+    use crate::render::graph::FORWARD;
+    use crate::render::graph::SPRITE;
+    use crate::render::mesh::Mesh;
+    use crate::render::tests::device;
+    use crate::render::tests::render_with;
+    use crate::Camera;
+    use crate::Scene;
+    use crate::VisibleNodes;
+    use crate::WorldTransform;
+
+    struct Probe(Arc<Mutex<RenderStats>>);
+
+    impl RenderNode for Probe {
+        fn run(&mut self, context: &mut RenderContext, _: &Scene) {
+            *self.0.lock().unwrap() = context.profiler.stats.clone();
+        }
+    }
+
+    #[test]
+    fn render_counts_draw_calls_and_triangles() {
+        let mut scene = Scene::new();
+        let cube = Mesh::cube(1.0);
+        let sphere = Mesh::sphere(0.5, 8, 4);
+        let at = |x| WorldTransform::new(Mat4::from_translation(Vec3::new(x, 0.0, 0.0)));
+        let visible = vec![
+            scene.spawn_with((cube.clone(), at(-1.0))),
+            scene.spawn_with((cube, at(1.0))),
+            scene.spawn_with((sphere.clone(), at(0.0))),
+        ];
+        scene.spawn_with((
+            Camera::perspective(1.0, 1.0, 0.1, 100.0),
+            WorldTransform::new(Mat4::from_translation(Vec3::new(0.0, 0.0, 5.0))),
+            VisibleNodes(visible),
+        ));
+
+        let stats = Arc::new(Mutex::new(RenderStats::default()));
+        let probe = Probe(Arc::clone(&stats));
+        let rendered = render_with(&scene, 2, |_, graph| {
+            graph.add_node("probe", probe).after(FORWARD).before(SPRITE);
+        });
+        if rendered.is_none() {
+            return;
+        }
+
+        // One instanced draw of the cubes and one of the sphere.
+        let stats = stats.lock().unwrap();
+        assert_eq!(stats.draw_calls, 2);
+        let sphere_triangles = sphere.data().indices.len() as u64 / 3;
+        assert_eq!(stats.triangles, 2 * 12 + sphere_triangles);
+    }
+
+    #[test]
+    fn timer_reads_back_node_times() {
+        let Some((device, queue)) = device() else {
+            return;
+        };
+        if !device.features().contains(TIMESTAMP_FEATURES) {
+            return;
+        }
+
+        let mut profiler = FrameProfiler::new(&device);
+        profiler.begin_frame(&device);
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        profiler.start_node(&mut encoder, "first");
+        profiler.start_node(&mut encoder, "second");
+        profiler.end_run(&mut encoder);
+        profiler.start_node(&mut encoder, "first");
+        profiler.end_run(&mut encoder);
+        queue.submit([encoder.finish()]);
+        profiler.finish_frame(&device, &queue);
+
+        device.poll(wgpu::Maintain::Wait);
+        profiler.begin_frame(&device);
+        let labels = profiler
+            .stats
+            .node_times
+            .iter()
+            .map(|(label, _)| *label)
+            .collect::<Vec<_>>();
+        assert_eq!(labels, ["first", "second"]);
+    }
+}
@@ -0,0 +1,157 @@
+//! # Render Target
+//!
+//! Offscreen images cameras render to instead of the window, e.g. for mirrors, portals, minimaps,
+//! and editor thumbnails. The images are sampled by materials and sprites like any other [Image].
+
+use crate::render::image::Image;
+use crate::Camera;
+use crate::Component;
+use crate::Node;
+use crate::Scene;
+use crate::Viewport;
+
+/// # Render Target
+///
+/// Image created with [Image::render_target] the node's [Camera] renders to instead of the
+/// window. Cameras targeting the same image draw on top of each other like cameras rendering to
+/// the window, and cameras targeting other images aren't rendered. Offscreen targets are rendered
+/// before the window, so the window shows them in the same frame, while targets showing each other
+/// show the previous frame.
+///
+/// ```
+/// # use glam::UVec2;
+/// # use pulse::render::image::Image;
+/// # use pulse::render::sprite::Sprite;
+/// # use pulse::render::target::RenderTarget;
+/// # use pulse::Camera;
+/// # use pulse::Scene;
+/// let minimap = Image::render_target(UVec2::new(256, 256)).unwrap();
+///
+/// let mut scene = Scene::new();
+/// scene.spawn_with((
+///     Camera::orthographic(100.0, 1.0, 0.1, 100.0),
+///     RenderTarget(minimap.clone()),
+/// ));
+/// scene.spawn_with(Sprite::new(minimap));
+/// ```
+#[derive(Clone, Debug, PartialEq, Component)]
+pub struct RenderTarget(pub Image);
+
+/// Returns the cameras of the scene sorted by node and grouped by the image they render to, the
+/// offscreen images in the order of their first camera and the window, `None`, last.
+pub(crate) fn camera_targets(scene: &Scene) -> Vec<(Option<Image>, Vec<Node>)> {
+    let mut cameras = scene
+        .query::<(Camera,)>()
+        .map(|(node, _)| node)
+        .collect::<Vec<_>>();
+    cameras.sort();
+
+    let mut targets = Vec::<(Option<Image>, Vec<Node>)>::new();
+    let mut window = Vec::new();
+    for node in cameras {
+        let Some(RenderTarget(image)) = scene.get::<RenderTarget>(node) else {
+            window.push(node);
+            continue;
+        };
+        if !image.is_render_target() {
+            continue;
+        }
+
+        match targets
+            .iter_mut()
+            .find(|(target, _)| target.as_ref() == Some(image))
+        {
+            Some((_, nodes)) => nodes.push(node),
+            None => targets.push((Some(image.clone()), vec![node])),
+        }
+    }
+    targets.push((None, window));
+    targets
+}
+
+/// Restricts the drawing of the pass to the viewport, unless it has no pixels.
+pub(crate) fn set_viewport(pass: &mut wgpu::RenderPass, viewport: Viewport) {
+    if viewport.size.min_element() > 0 {
+        let position = viewport.position.as_vec2();
+        let size = viewport.size.as_vec2();
+        pass.set_viewport(position.x, position.y, size.x, size.y, 0.0, 1.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Mat4;
+    use glam::UVec2;
+    use glam::Vec2;
+    use glam::Vec3;
+
+    use super::*;
+    use crate::render::anti_aliasing::AntiAliasing;
+    use crate::render::image::ColorSpace;
+    use crate::render::sprite::Sprite;
+    use crate::render::tests::render;
+    use crate::render::tonemap::Tonemapping;
+    use crate::render::Color;
+    use crate::VisibleNodes;
+    use crate::WorldTransform;
+
+    #[test]
+    fn camera_targets_groups_cameras_by_image() {
+        let first = Image::render_target(UVec2::ONE).unwrap();
+        let second = Image::render_target(UVec2::ONE).unwrap();
+        let camera = Camera::perspective(1.0, 1.0, 0.1, 10.0);
+        let mut scene = Scene::new();
+        let window = scene.spawn_with(camera);
+        let a = scene.spawn_with((camera, RenderTarget(second.clone())));
+        let b = scene.spawn_with((camera, RenderTarget(first.clone())));
+        let c = scene.spawn_with((camera, RenderTarget(second.clone())));
+        let pixels = Image::from_pixel([0; 4], ColorSpace::Srgb);
+        scene.spawn_with((camera, RenderTarget(pixels)));
+
+        assert_eq!(
+            camera_targets(&scene),
+            [
+                (Some(second), vec![a, c]),
+                (Some(first), vec![b]),
+                (None, vec![window]),
+            ]
+        );
+    }
+
+    #[test]
+    fn render_shows_offscreen_target_in_same_frame() {
+        let image = Image::render_target(UVec2::new(8, 8)).unwrap();
+        let mut scene = Scene::new();
+        scene.insert_resource(AntiAliasing::None);
+        let camera = Camera {
+            tonemapping: Tonemapping::None,
+            ..Camera::orthographic(64.0, 1.0, 0.1, 10.0)
+        };
+        let transform = WorldTransform::new(Mat4::from_translation(Vec3::Z));
+        let red = scene.spawn_with(Sprite {
+            color: Color::rgb(1.0, 0.0, 0.0),
+            custom_size: Some(Vec2::splat(64.0)),
+            ..Sprite::default()
+        });
+        let screen = scene.spawn_with(Sprite::new(image.clone()));
+        for node in [red, screen] {
+            scene.add(node, WorldTransform::default());
+        }
+        scene.spawn_with((camera, transform, VisibleNodes(vec![screen])));
+        scene.spawn_with((
+            camera,
+            transform,
+            VisibleNodes(vec![red]),
+            RenderTarget(image),
+        ));
+
+        let Some(pixels) = render(&scene) else {
+            return;
+        };
+        let pixel = |x: usize, y: usize| &pixels[(y * 64 + x) * 4..][..4];
+
+        // The 8x8 target is drawn in the center of the window.
+        assert_eq!(pixel(32, 32), [255, 0, 0, 255]);
+        assert_eq!(pixel(8, 8), [0, 0, 0, 255]);
+    }
+}
@@ -0,0 +1,638 @@
+//! # Terrain
+//!
+//! Heightmap terrain split into square chunks, each a child node with a [Mesh] of the chunk at the
+//! level of detail selected by [crate::systems::update_terrain] from its distance to the nearest
+//! camera. Each level halves the vertices along the sides of the chunk of the previous one
+//! (geo-mipmapping), and the chunks have skirts hanging below their edges to hide the cracks
+//! between neighbors at different levels. As the chunks are mesh nodes, they are culled by the
+//! cameras and indexed by the [crate::spatial::SpatialIndex] like any other mesh.
+
+use std::sync::Arc;
+use std::sync::OnceLock;
+
+use glam::Mat4;
+use glam::UVec2;
+use glam::Vec2;
+use glam::Vec3;
+use glam::Vec4;
+
+use crate::render::image::ColorSpace;
+use crate::render::image::Image;
+use crate::render::material::Material;
+use crate::render::mesh::Mesh;
+use crate::render::mesh::MeshData;
+use crate::render::shader_material::MaterialShader;
+use crate::render::shader_material::ShaderMaterial;
+use crate::render::shader_material::UniformValue;
+use crate::render::Color;
+use crate::Aabb;
+use crate::Component;
+use crate::LocalTransform;
+use crate::Node;
+use crate::Scene;
+
+static SPLAT_SHADER: OnceLock<MaterialShader> = OnceLock::new();
+
+/// # Heightmap
+///
+/// Grid of heights from 0 to 1, row by row from the corner at the smallest coordinates of the
+/// terrain. The heights are shared between clones of the heightmap, and heightmaps are equal if
+/// they share the same heights.
+///
+/// ```
+/// # use glam::UVec2;
+/// # use glam::Vec2;
+/// # use pulse::render::terrain::Heightmap;
+/// let ramp = Heightmap::from_fn(UVec2::new(3, 2), |texel| texel.x as f32 * 0.5).unwrap();
+///
+/// assert_eq!(ramp.height(2, 1), 1.0);
+/// assert_eq!(ramp.sample(Vec2::new(0.25, 0.5)), 0.25);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Heightmap {
+    size: UVec2,
+    heights: Arc<[f32]>,
+}
+
+impl Heightmap {
+    /// Returns the heightmap of the heights row by row, or `None` if the number of heights doesn't
+    /// match the size or the size is zero.
+    pub fn new(size: UVec2, heights: Vec<f32>) -> Option<Self> {
+        let len = u64::from(size.x) * u64::from(size.y);
+        (len > 0 && heights.len() as u64 == len).then(|| Self {
+            size,
+            heights: heights.into(),
+        })
+    }
+
+    /// Returns the heightmap of the size with the height of each texel returned by the function,
+    /// or `None` if the size is zero.
+    pub fn from_fn(size: UVec2, height: impl FnMut(UVec2) -> f32) -> Option<Self> {
+        let heights = (0..size.y)
+            .flat_map(|y| (0..size.x).map(move |x| UVec2::new(x, y)))
+            .map(height)
+            .collect();
+        Self::new(size, heights)
+    }
+
+    /// Returns the heightmap of the red channel of the image, or `None` if the image has no pixels
+    /// on the CPU.
+    pub fn from_image(image: &Image) -> Option<Self> {
+        let heights = image
+            .pixels()
+            .chunks_exact(4)
+            .map(|pixel| f32::from(pixel[0]) / 255.0)
+            .collect();
+        Self::new(image.size(), heights)
+    }
+
+    /// Returns the number of heights along each axis.
+    pub fn size(&self) -> UVec2 {
+        self.size
+    }
+
+    /// Returns the height of the texel.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the texel is outside of the heightmap.
+    pub fn height(&self, x: u32, y: u32) -> f32 {
+        assert!(x < self.size.x && y < self.size.y);
+        self.heights[(y * self.size.x + x) as usize]
+    }
+
+    /// Returns the height bilinearly interpolated at the coordinates from 0 to 1 across the
+    /// heightmap, clamped to its edges.
+    pub fn sample(&self, uv: Vec2) -> f32 {
+        let last = self.size - 1;
+        let position = uv.clamp(Vec2::ZERO, Vec2::ONE) * last.as_vec2();
+        let texel = position.floor().as_uvec2().min(last.max(UVec2::ONE) - 1);
+        let fraction = position - texel.as_vec2();
+        let height = |x: u32, y: u32| self.height(x.min(last.x), y.min(last.y));
+        let top = lerp(
+            height(texel.x, texel.y),
+            height(texel.x + 1, texel.y),
+            fraction.x,
+        );
+        let bottom = lerp(
+            height(texel.x, texel.y + 1),
+            height(texel.x + 1, texel.y + 1),
+            fraction.x,
+        );
+        lerp(top, bottom, fraction.y)
+    }
+}
+
+impl PartialEq for Heightmap {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.heights, &other.heights)
+    }
+}
+
+/// # Terrain
+///
+/// Component of a terrain spanning `size` along the X and Z axes centered on the node, raised
+/// along the Y axis by `height` times the heights of its heightmap. The terrain is split into
+/// chunks of `chunk_quads` texels of the heightmap along each side, rounded up to a power of two,
+/// and the chunks along the far edges sample the heightmap beyond its last texel as its edge.
+///
+/// A chunk is at full detail up to `lod_distance` from the nearest camera, and each doubling of
+/// the distance halves its detail, down to `lod_levels` levels. Chunks are shaded by the
+/// terrain's material, such as a [SplatMaterial]. Colliders can sample the surface of the terrain
+/// with [Terrain::height_at] and [Terrain::normal_at].
+///
+/// ```
+/// # use glam::UVec2;
+/// # use glam::Vec2;
+/// # use pulse::render::terrain::Heightmap;
+/// # use pulse::render::terrain::Terrain;
+/// # use pulse::LocalTransform;
+/// # use pulse::Scene;
+/// let hills = Heightmap::from_fn(UVec2::splat(129), |texel| {
+///     (texel.x as f32 * 0.1).sin() * (texel.y as f32 * 0.1).cos() * 0.5 + 0.5
+/// })
+/// .unwrap();
+///
+/// let mut scene = Scene::new();
+/// scene.spawn_with((
+///     Terrain {
+///         size: Vec2::splat(256.0),
+///         height: 20.0,
+///         ..Terrain::new(hills)
+///     },
+///     LocalTransform::IDENTITY,
+/// ));
+/// ```
+#[derive(Clone, Debug, PartialEq, Component)]
+pub struct Terrain {
+    /// Heights of the terrain.
+    pub heightmap: Heightmap,
+    /// Size of the terrain along the X and Z axes.
+    pub size: Vec2,
+    /// Height of the terrain where the heightmap is 1.
+    pub height: f32,
+    /// Number of quads along each side of a chunk at full detail.
+    pub chunk_quads: u32,
+    /// Number of levels of detail, including the full detail.
+    pub lod_levels: u32,
+    /// Distance from the camera up to which chunks are at full detail.
+    pub lod_distance: f32,
+    /// Material of the chunks.
+    pub material: Material,
+}
+
+impl Terrain {
+    /// Returns a terrain of the heightmap 100 units wide and 10 units high, with 32 quads per
+    /// chunk and 4 levels of detail from 25 units away, shaded by the default material.
+    pub fn new(heightmap: Heightmap) -> Self {
+        Self {
+            heightmap,
+            size: Vec2::splat(100.0),
+            height: 10.0,
+            chunk_quads: 32,
+            lod_levels: 4,
+            lod_distance: 25.0,
+            material: Material::default(),
+        }
+    }
+
+    /// Returns the number of chunks along the X and Z axes.
+    pub fn chunks(&self) -> UVec2 {
+        let quads = self.quads();
+        ((self.heightmap.size() - 1).max(UVec2::ONE) + quads - 1) / quads
+    }
+
+    /// Returns the height of the surface at the position along the X and Z axes relative to the
+    /// node, clamped to the edges of the terrain.
+    pub fn height_at(&self, position: Vec2) -> f32 {
+        let grid = (self.chunks() * self.quads()).as_vec2();
+        let texels = (self.heightmap.size() - 1).max(UVec2::ONE).as_vec2();
+        let uv = position / self.size + 0.5;
+        self.heightmap.sample(uv * grid / texels) * self.height
+    }
+
+    /// Returns the normal of the surface at the position along the X and Z axes relative to the
+    /// node, from the slope of the heights a texel of the heightmap around it.
+    pub fn normal_at(&self, position: Vec2) -> Vec3 {
+        let step = self.spacing();
+        let dx = self.height_at(position + Vec2::new(step.x, 0.0))
+            - self.height_at(position - Vec2::new(step.x, 0.0));
+        let dz = self.height_at(position + Vec2::new(0.0, step.y))
+            - self.height_at(position - Vec2::new(0.0, step.y));
+        Vec3::new(-dx / (2.0 * step.x), 1.0, -dz / (2.0 * step.y)).normalize()
+    }
+
+    /// Returns the level of detail of a chunk at the distance from the nearest camera.
+    pub fn lod(&self, distance: f32) -> u32 {
+        let coarsest = (self.lod_levels.max(1) - 1).min(self.quads().trailing_zeros());
+        let level = if distance < self.lod_distance {
+            0
+        } else {
+            (distance / self.lod_distance).log2() as u32 + 1
+        };
+        level.min(coarsest)
+    }
+
+    /// Returns the number of quads along each side of a chunk at full detail.
+    fn quads(&self) -> u32 {
+        self.chunk_quads.max(1).next_power_of_two()
+    }
+
+    /// Returns the size of a quad at full detail along the X and Z axes.
+    fn spacing(&self) -> Vec2 {
+        self.size / (self.chunks() * self.quads()).as_vec2()
+    }
+
+    /// Returns the position relative to the node and the coordinates from 0 to 1 across the
+    /// terrain of the vertex of the full detail grid.
+    fn vertex(&self, vertex: UVec2) -> (Vec3, Vec2) {
+        let uv = vertex.as_vec2() / (self.chunks() * self.quads()).as_vec2();
+        let position = (uv - 0.5) * self.size;
+        (
+            Vec3::new(position.x, self.height_at(position), position.y),
+            uv,
+        )
+    }
+
+    /// Returns the bounds of the chunk's surface at full detail.
+    fn chunk_bounds(&self, coords: UVec2) -> Aabb {
+        let quads = self.quads();
+        let start = coords * quads;
+        Aabb::from_points(
+            (0..=quads)
+                .flat_map(|z| (0..=quads).map(move |x| self.vertex(start + UVec2::new(x, z)).0)),
+        )
+        .unwrap()
+    }
+
+    /// Returns the mesh of the chunk at the level of detail, with a skirt as deep as the heights
+    /// of the chunk vary around its edges.
+    fn chunk_mesh(&self, coords: UVec2, lod: u32) -> Mesh {
+        let step = 1 << lod;
+        let quads = self.quads() / step;
+        let start = coords * self.quads();
+        let bounds = self.chunk_bounds(coords);
+        let depth = bounds.max.y - bounds.min.y + self.spacing().min_element();
+
+        let mut data = MeshData::default();
+        for z in 0..=quads {
+            for x in 0..=quads {
+                let (position, uv) = self.vertex(start + UVec2::new(x, z) * step);
+                data.positions.push(position);
+                data.normals
+                    .push(self.normal_at(Vec2::new(position.x, position.z)));
+                data.uvs.push(uv);
+            }
+        }
+        let index = |x: u32, z: u32| z * (quads + 1) + x;
+        for z in 0..quads {
+            for x in 0..quads {
+                let (top_left, top_right) = (index(x, z), index(x + 1, z));
+                let (bottom_left, bottom_right) = (index(x, z + 1), index(x + 1, z + 1));
+                data.indices.extend([
+                    bottom_left,
+                    bottom_right,
+                    top_right,
+                    bottom_left,
+                    top_right,
+                    top_left,
+                ]);
+            }
+        }
+
+        // The edges clockwise seen from above, so that the skirts face away from the chunk.
+        let edges = (0..quads)
+            .map(|x| (index(x, 0), index(x + 1, 0)))
+            .chain((0..quads).map(|z| (index(quads, z), index(quads, z + 1))))
+            .chain((0..quads).map(|x| (index(quads - x, quads), index(quads - x - 1, quads))))
+            .chain((0..quads).map(|z| (index(0, quads - z), index(0, quads - z - 1))))
+            .collect::<Vec<_>>();
+        for (a, b) in edges {
+            let skirt = data.positions.len() as u32;
+            for vertex in [a, b] {
+                let position = data.positions[vertex as usize] - Vec3::Y * depth;
+                data.positions.push(position);
+                data.normals.push(data.normals[vertex as usize]);
+                data.uvs.push(data.uvs[vertex as usize]);
+            }
+            data.indices.extend([a, b, skirt + 1, a, skirt + 1, skirt]);
+        }
+
+        Mesh::new(data).unwrap()
+    }
+}
+
+/// # Terrain Chunk
+///
+/// Component of the child nodes spawned for the chunks of a [Terrain], with the coordinates of the
+/// chunk along the X and Z axes and the level of detail of its mesh.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Component)]
+pub struct TerrainChunk {
+    /// Coordinates of the chunk from the corner of the terrain at the smallest coordinates.
+    pub coords: UVec2,
+    /// Level of detail of the chunk's mesh, from 0 for full detail.
+    pub lod: u32,
+}
+
+/// Component of the nodes with a [Terrain] with the nodes of its chunks and their meshes at each
+/// level of detail built so far.
+#[derive(Clone, Debug, PartialEq, Component)]
+pub(crate) struct TerrainChunks {
+    terrain: Terrain,
+    chunks: Vec<Chunk>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Chunk {
+    node: Node,
+    coords: UVec2,
+    bounds: Aabb,
+    lod: Option<u32>,
+    meshes: Vec<Option<Mesh>>,
+}
+
+impl TerrainChunks {
+    /// Spawns a child of the node with the terrain's material for each chunk of the terrain. The
+    /// chunks have no mesh until their level of detail is selected.
+    pub(crate) fn spawn(scene: &mut Scene, node: Node, terrain: &Terrain) -> Self {
+        let count = terrain.chunks();
+        let chunks = (0..count.y)
+            .flat_map(|z| (0..count.x).map(move |x| UVec2::new(x, z)))
+            .map(|coords| {
+                let chunk = scene.spawn_with((
+                    TerrainChunk { coords, lod: 0 },
+                    terrain.material.clone(),
+                    LocalTransform::IDENTITY,
+                ));
+                scene.set_parent(chunk, node);
+                Chunk {
+                    node: chunk,
+                    coords,
+                    bounds: terrain.chunk_bounds(coords),
+                    lod: None,
+                    meshes: vec![None; terrain.lod_levels.max(1) as usize],
+                }
+            })
+            .collect();
+        Self {
+            terrain: terrain.clone(),
+            chunks,
+        }
+    }
+
+    /// Returns the terrain the chunks were spawned for.
+    pub(crate) fn terrain(&self) -> &Terrain {
+        &self.terrain
+    }
+
+    /// Returns the nodes of the chunks.
+    pub(crate) fn nodes(&self) -> impl '_ + Iterator<Item = Node> {
+        self.chunks.iter().map(|chunk| chunk.node)
+    }
+
+    /// Selects the level of detail of each chunk from the distance of its bounds transformed by
+    /// the matrix to the nearest of the positions, and returns the chunks whose level changed with
+    /// their component and mesh.
+    pub(crate) fn select_lods(
+        &mut self,
+        matrix: &Mat4,
+        cameras: &[Vec3],
+    ) -> Vec<(Node, TerrainChunk, Mesh)> {
+        let terrain = &self.terrain;
+        self.chunks
+            .iter_mut()
+            .filter_map(|chunk| {
+                let bounds = chunk.bounds.transformed(matrix);
+                let distance = cameras
+                    .iter()
+                    .map(|camera| camera.clamp(bounds.min, bounds.max).distance(*camera))
+                    .fold(f32::INFINITY, f32::min);
+                let lod = terrain.lod(distance);
+                if chunk.lod == Some(lod) {
+                    return None;
+                }
+
+                chunk.lod = Some(lod);
+                let mesh = chunk.meshes[lod as usize]
+                    .get_or_insert_with(|| terrain.chunk_mesh(chunk.coords, lod))
+                    .clone();
+                let component = TerrainChunk {
+                    coords: chunk.coords,
+                    lod,
+                };
+                Some((chunk.node, component, mesh))
+            })
+            .collect()
+    }
+}
+
+/// # Splat Material
+///
+/// Terrain material blending four layers by the weights in the red, green, blue, and alpha
+/// channels of a linear splat map stretched over the whole terrain. Each layer is the color of its
+/// texture tiled `tiling` times across the terrain, tinted by its color. Converted into a
+/// [ShaderMaterial] lit like a dielectric standard material. Without a splat map, the terrain is
+/// covered by the first layer.
+///
+/// ```
+/// # use pulse::render::material::Material;
+/// # use pulse::render::terrain::SplatLayer;
+/// # use pulse::render::terrain::SplatMaterial;
+/// # use pulse::render::Color;
+/// let grass = SplatLayer {
+///     color: Color::rgb(0.2, 0.5, 0.1),
+///     ..SplatLayer::default()
+/// };
+/// let material = Material::from(SplatMaterial {
+///     layers: [grass, SplatLayer::default(), SplatLayer::default(), SplatLayer::default()],
+///     ..SplatMaterial::default()
+/// });
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct SplatMaterial {
+    /// Weights of the layers, blending the first layer if `None`.
+    pub splat_map: Option<Image>,
+    /// Layers blended by the splat map.
+    pub layers: [SplatLayer; 4],
+    /// Number of times the layers' textures repeat across the terrain.
+    pub tiling: f32,
+}
+
+impl Default for SplatMaterial {
+    fn default() -> Self {
+        Self {
+            splat_map: None,
+            layers: Default::default(),
+            tiling: 16.0,
+        }
+    }
+}
+
+impl From<SplatMaterial> for ShaderMaterial {
+    fn from(splat: SplatMaterial) -> Self {
+        let shader = SPLAT_SHADER
+            .get_or_init(|| MaterialShader::new(include_str!("shaders/terrain.wgsl")))
+            .clone();
+        let first = || Image::from_pixel([255, 0, 0, 0], ColorSpace::Linear);
+        let mut uniforms = vec![("tiling".to_string(), UniformValue::Float(splat.tiling))];
+        let mut textures = vec![(
+            "splat".to_string(),
+            Some(splat.splat_map.unwrap_or_else(first)),
+        )];
+        for (index, layer) in splat.layers.iter().enumerate() {
+            uniforms.push((format!("layer_{index}"), UniformValue::Color(layer.color)));
+            textures.push((format!("layer_{index}"), layer.texture.clone()));
+        }
+        let roughness = splat.layers.map(|layer| layer.roughness);
+        uniforms.push((
+            "roughness".to_string(),
+            UniformValue::Vec4(Vec4::from_array(roughness)),
+        ));
+        Self {
+            uniforms,
+            textures,
+            ..Self::new(shader)
+        }
+    }
+}
+
+impl From<SplatMaterial> for Material {
+    fn from(splat: SplatMaterial) -> Self {
+        Self::custom(splat.into())
+    }
+}
+
+/// Layer of a [SplatMaterial].
+#[derive(Clone, Debug, PartialEq)]
+pub struct SplatLayer {
+    /// Color multiplying the texture's color.
+    pub color: Color,
+    /// Texture tiled across the terrain, white if `None`.
+    pub texture: Option<Image>,
+    /// Roughness of the layer's surface.
+    pub roughness: f32,
+}
+
+impl Default for SplatLayer {
+    fn default() -> Self {
+        Self {
+            color: Color::WHITE,
+            texture: None,
+            roughness: 0.9,
+        }
+    }
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::light::AmbientLight;
+    use crate::render::tests::render_updated;
+    use crate::render::tonemap::Tonemapping;
+    use crate::Camera;
+
+    fn ramp() -> Terrain {
+        let heightmap = Heightmap::from_fn(UVec2::splat(5), |texel| texel.x as f32 * 0.25).unwrap();
+        Terrain {
+            size: Vec2::splat(8.0),
+            height: 4.0,
+            chunk_quads: 2,
+            ..Terrain::new(heightmap)
+        }
+    }
+
+    #[test]
+    fn terrain_samples_heights_and_normals() {
+        let terrain = ramp();
+
+        assert_eq!(terrain.chunks(), UVec2::splat(2));
+        assert_eq!(terrain.height_at(Vec2::new(-4.0, 0.0)), 0.0);
+        assert_eq!(terrain.height_at(Vec2::new(1.0, 3.0)), 2.5);
+        assert_eq!(terrain.height_at(Vec2::new(10.0, 0.0)), 4.0);
+        let normal = terrain.normal_at(Vec2::ZERO);
+        assert!(normal.abs_diff_eq(Vec3::new(-0.5, 1.0, 0.0).normalize(), 1e-6));
+    }
+
+    #[test]
+    fn chunk_meshes_halve_vertices_with_each_lod() {
+        let terrain = Terrain {
+            chunk_quads: 4,
+            lod_levels: 3,
+            lod_distance: 10.0,
+            ..ramp()
+        };
+        let full = terrain.chunk_mesh(UVec2::ZERO, 0);
+        let coarse = terrain.chunk_mesh(UVec2::ZERO, 1);
+
+        // The grid's vertices followed by two for each quad of the skirt around the edges.
+        assert_eq!(full.data().positions.len(), 25 + 32);
+        assert_eq!(coarse.data().positions.len(), 9 + 16);
+        assert_eq!(coarse.data().positions[4], Vec3::new(0.0, 2.0, 0.0));
+        let bottom = full.data().positions[25..]
+            .iter()
+            .map(|position| position.y)
+            .fold(f32::INFINITY, f32::min);
+        assert!(bottom < -4.0);
+
+        assert_eq!(terrain.lod(5.0), 0);
+        assert_eq!(terrain.lod(15.0), 1);
+        assert_eq!(terrain.lod(30.0), 2);
+        assert_eq!(terrain.lod(1000.0), 2);
+    }
+
+    #[test]
+    fn render_blends_splat_layers() {
+        let mut scene = Scene::new();
+        scene.insert_resource(AmbientLight {
+            intensity: 1.0,
+            ..AmbientLight::default()
+        });
+        let splat_map = Image::new(
+            UVec2::new(2, 1),
+            [[255, 0, 0, 0], [0, 255, 0, 0]].concat(),
+            ColorSpace::Linear,
+        );
+        let layer = |color| SplatLayer {
+            color,
+            ..SplatLayer::default()
+        };
+        let material = Material::from(SplatMaterial {
+            splat_map,
+            layers: [
+                layer(Color::rgb(1.0, 0.0, 0.0)),
+                layer(Color::rgb(0.0, 0.0, 1.0)),
+                layer(Color::BLACK),
+                layer(Color::BLACK),
+            ],
+            ..SplatMaterial::default()
+        });
+        let flat = Heightmap::new(UVec2::splat(2), vec![0.0; 4]).unwrap();
+        scene.spawn_with((
+            Terrain {
+                size: Vec2::splat(2.0),
+                material,
+                ..Terrain::new(flat)
+            },
+            LocalTransform::IDENTITY,
+        ));
+        scene.spawn_with((
+            Camera {
+                tonemapping: Tonemapping::None,
+                ..Camera::orthographic(2.0, 1.0, 0.1, 10.0)
+            },
+            LocalTransform::from_position(Vec3::Y * 5.0).looking_at(Vec3::ZERO, Vec3::NEG_Z),
+        ));
+
+        let Some(pixels) = render_updated(&mut scene, 1) else {
+            return;
+        };
+        let pixel = |x: usize| &pixels[(32 * 64 + x) * 4..][..3];
+
+        assert!(pixel(16)[0] > 150 && pixel(16)[2] < 32, "{:?}", pixel(16));
+        assert!(pixel(48)[2] > 150 && pixel(48)[0] < 32, "{:?}", pixel(48));
+    }
+}
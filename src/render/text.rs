@@ -0,0 +1,471 @@
+//! # Text
+//!
+//! Text laid out with a TrueType or OpenType [Font] and drawn by the
+//! [crate::render::graph::SPRITE] node like sprites. Glyphs are rasterized on the CPU into a
+//! [GlyphAtlas] shared by all texts.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+use ab_glyph::Font as _;
+use ab_glyph::FontArc;
+use ab_glyph::GlyphId;
+use ab_glyph::PxScale;
+use ab_glyph::ScaleFont;
+use glam::Mat4;
+use glam::UVec2;
+use glam::Vec2;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::assets::AssetLoader;
+use crate::assets::LoadContext;
+use crate::render::image::ColorSpace;
+use crate::render::image::Image;
+use crate::render::Color;
+use crate::Aabb;
+use crate::Component;
+use crate::Reflect;
+
+/// Initial width and height of the glyph atlas in pixels. The atlas grows in height as needed.
+const ATLAS_SIZE: u32 = 256;
+
+/// Maximum height of the glyph atlas in pixels, the texture size all devices support.
+const MAX_ATLAS_HEIGHT: u32 = 8192;
+
+/// Empty pixels around each glyph in the atlas, so filtering doesn't bleed between glyphs.
+const GLYPH_PADDING: u32 = 1;
+
+/// # Font
+///
+/// TrueType or OpenType font. The data is shared between clones of the font, and fonts are equal
+/// if they share the same data.
+///
+/// ```no_run
+/// # use pulse::render::text::Font;
+/// let font = Font::from_bytes(std::fs::read("font.ttf").unwrap()).unwrap();
+/// ```
+#[derive(Clone, Debug)]
+pub struct Font {
+    font: Arc<FontArc>,
+}
+
+impl Font {
+    /// Returns the font in the bytes of a TrueType or OpenType file.
+    ///
+    /// # Errors
+    ///
+    /// Returns [FontError::InvalidFont] if the bytes aren't a valid font.
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Self, FontError> {
+        let font = FontArc::try_from_vec(bytes).map_err(|_| FontError::InvalidFont)?;
+        Ok(Self {
+            font: Arc::new(font),
+        })
+    }
+
+    /// Returns an identifier for the font's data, valid while the font exists.
+    pub(crate) fn id(&self) -> usize {
+        Arc::as_ptr(&self.font) as usize
+    }
+}
+
+impl PartialEq for Font {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.font, &other.font)
+    }
+}
+
+/// # Font Loader
+///
+/// [AssetLoader] of [Font]s from TrueType and OpenType files.
+pub struct FontLoader;
+
+impl AssetLoader for FontLoader {
+    type Asset = Font;
+
+    fn extensions(&self) -> &[&str] {
+        &["ttf", "otf"]
+    }
+
+    fn load(
+        &self,
+        context: &LoadContext,
+    ) -> Result<Font, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(Font::from_bytes(context.bytes().to_vec())?)
+    }
+}
+
+/// Error returned when loading a [Font] fails.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FontError {
+    /// The bytes aren't a valid TrueType or OpenType font.
+    InvalidFont,
+}
+
+impl fmt::Display for FontError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidFont => write!(f, "invalid font"),
+        }
+    }
+}
+
+impl std::error::Error for FontError {}
+
+/// # Text Alignment
+///
+/// Horizontal alignment of the lines of a [Text].
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Reflect, Serialize, Deserialize)]
+pub enum TextAlignment {
+    /// Lines start at the left edge.
+    #[default]
+    Left,
+    /// Lines are centered.
+    Center,
+    /// Lines end at the right edge.
+    Right,
+}
+
+/// # Text
+///
+/// Text drawn in the XY plane of the node's [crate::WorldTransform], facing +Z, when the node is
+/// visible to a camera. Lines break at `\n`, and at spaces to fit into the maximum width, if any.
+/// Glyphs are rasterized at the font size in pixels, so texts are sharpest when one world unit is
+/// one pixel, like with an orthographic [crate::Camera] whose height is the window's height.
+///
+/// The node's [Aabb] is computed from the text by [crate::systems::compute_text_bounds].
+///
+/// ```no_run
+/// # use pulse::render::text::Font;
+/// # use pulse::render::text::Text;
+/// # use pulse::render::text::TextAlignment;
+/// # use pulse::Scene;
+/// let font = Font::from_bytes(std::fs::read("font.ttf").unwrap()).unwrap();
+///
+/// let mut scene = Scene::new();
+/// scene.spawn_with(Text {
+///     alignment: TextAlignment::Center,
+///     max_width: Some(200.0),
+///     ..Text::new("Hello, world!", font, 24.0)
+/// });
+/// ```
+#[derive(Clone, Debug, PartialEq, Component)]
+pub struct Text {
+    /// Characters of the text.
+    pub text: String,
+    /// Font the text is drawn with.
+    pub font: Font,
+    /// Font size in world units, the height from the lowest descender to the highest ascender.
+    pub size: f32,
+    /// Color of the glyphs.
+    pub color: Color,
+    /// Horizontal alignment of the lines.
+    pub alignment: TextAlignment,
+    /// Width at which lines break at spaces, if any. Words wider than it aren't broken.
+    pub max_width: Option<f32>,
+    /// Point of the text's bounds at the node's origin, from (0, 0) at the bottom-left to (1, 1)
+    /// at the top-right corner.
+    pub anchor: Vec2,
+}
+
+impl Text {
+    /// Returns white, left aligned text with the font size, whose top-left corner is at the
+    /// node's origin.
+    pub fn new(text: impl Into<String>, font: Font, size: f32) -> Self {
+        Self {
+            text: text.into(),
+            font,
+            size,
+            color: Color::WHITE,
+            alignment: TextAlignment::Left,
+            max_width: None,
+            anchor: Vec2::new(0.0, 1.0),
+        }
+    }
+
+    /// Returns the width and height of the laid out text in world units.
+    pub fn size(&self) -> Vec2 {
+        self.layout().size
+    }
+
+    /// Returns the bounds of the laid out text.
+    pub fn aabb(&self) -> Aabb {
+        let size = self.size().extend(0.0);
+        let min = -self.anchor.extend(0.0) * size;
+        Aabb::new(min, min + size)
+    }
+
+    /// Returns the glyphs of the text positioned relative to the node's origin.
+    pub(crate) fn layout(&self) -> TextLayout {
+        let font = self.font.font.as_scaled(PxScale::from(self.size.max(0.0)));
+        let space = font.h_advance(font.glyph_id(' '));
+        let line_height = font.height() + font.line_gap();
+
+        // Glyphs of each line at their offsets from the line's start, and the line's width.
+        let mut lines = Vec::<(Vec<(GlyphId, f32)>, f32)>::new();
+        for paragraph in self.text.split('\n') {
+            let mut line = Vec::new();
+            let mut width = 0.0;
+            for word in paragraph.split(' ') {
+                let mut word_glyphs = Vec::new();
+                let mut advance = 0.0;
+                let mut previous = None;
+                for character in word.chars() {
+                    let glyph = font.glyph_id(character);
+                    if let Some(previous) = previous {
+                        advance += font.kern(previous, glyph);
+                    }
+                    word_glyphs.push((glyph, advance));
+                    advance += font.h_advance(glyph);
+                    previous = Some(glyph);
+                }
+
+                let mut start = if line.is_empty() { 0.0 } else { width + space };
+                if !line.is_empty()
+                    && self
+                        .max_width
+                        .is_some_and(|max_width| start + advance > max_width)
+                {
+                    lines.push((std::mem::take(&mut line), width));
+                    start = 0.0;
+                }
+                line.extend(word_glyphs.into_iter().map(|(glyph, x)| (glyph, start + x)));
+                width = start + advance;
+            }
+            lines.push((line, width));
+        }
+
+        let width = self
+            .max_width
+            .unwrap_or_else(|| lines.iter().map(|(_, width)| *width).fold(0.0, f32::max));
+        let size = Vec2::new(width, lines.len() as f32 * line_height);
+        let origin = Vec2::new(-self.anchor.x * size.x, (1.0 - self.anchor.y) * size.y);
+        let glyphs = lines
+            .into_iter()
+            .enumerate()
+            .flat_map(|(index, (line, line_width))| {
+                let x = match self.alignment {
+                    TextAlignment::Left => 0.0,
+                    TextAlignment::Center => (width - line_width) * 0.5,
+                    TextAlignment::Right => width - line_width,
+                };
+                let baseline = font.ascent() + index as f32 * line_height;
+                line.into_iter()
+                    .map(move |(glyph, offset)| (glyph, origin + Vec2::new(x + offset, -baseline)))
+            })
+            .collect();
+
+        TextLayout { size, glyphs }
+    }
+}
+
+/// Glyphs of a [Text] and the size of their bounds.
+pub(crate) struct TextLayout {
+    pub(crate) size: Vec2,
+    /// Glyphs and their origins on the baseline, with Y pointing up.
+    pub(crate) glyphs: Vec<(GlyphId, Vec2)>,
+}
+
+/// Glyph in the [GlyphAtlas].
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) struct AtlasGlyph {
+    /// Top-left corner of the glyph in the atlas in pixels.
+    position: UVec2,
+    /// Size of the glyph in pixels.
+    size: UVec2,
+    /// Offset of the glyph's bottom-left corner from its origin in pixels, with Y pointing up.
+    offset: Vec2,
+}
+
+/// Key of a glyph in the [GlyphAtlas], the font's id, the glyph, and the size in pixels.
+type GlyphKey = (usize, GlyphId, u32);
+
+/// Glyphs rasterized into the pixels of an image, white with the glyph's coverage as alpha.
+/// Glyphs are packed into rows, and the atlas grows in height when a glyph doesn't fit anymore.
+pub(crate) struct GlyphAtlas {
+    size: UVec2,
+    pixels: Vec<u8>,
+    /// Position of the next glyph in the current row, and the row's height.
+    cursor: UVec2,
+    row_height: u32,
+    /// Glyphs rasterized so far, `None` for glyphs without an outline or that didn't fit.
+    glyphs: HashMap<GlyphKey, Option<AtlasGlyph>>,
+    /// Image of the pixels, recreated when glyphs are added.
+    image: Option<Image>,
+}
+
+impl GlyphAtlas {
+    pub(crate) fn new() -> Self {
+        Self {
+            size: UVec2::splat(ATLAS_SIZE),
+            pixels: vec![0; (ATLAS_SIZE * ATLAS_SIZE * 4) as usize],
+            cursor: UVec2::ZERO,
+            row_height: 0,
+            glyphs: HashMap::new(),
+            image: None,
+        }
+    }
+
+    /// Rasterizes the glyph of the font at the size in pixels if it isn't in the atlas yet, and
+    /// returns it unless it has no outline or doesn't fit.
+    pub(crate) fn glyph(&mut self, font: &Font, glyph: GlyphId, size: u32) -> Option<AtlasGlyph> {
+        let key = (font.id(), glyph, size);
+        if let Some(glyph) = self.glyphs.get(&key) {
+            return *glyph;
+        }
+
+        let outlined = font
+            .font
+            .outline_glyph(glyph.with_scale(PxScale::from(size as f32)));
+        let atlas_glyph = outlined.and_then(|outlined| {
+            let bounds = outlined.px_bounds();
+            let glyph_size = UVec2::new(bounds.width() as u32, bounds.height() as u32);
+            let position = self.allocate(glyph_size)?;
+            outlined.draw(|x, y, coverage| {
+                let index = ((position.y + y) * self.size.x + position.x + x) as usize * 4;
+                self.pixels[index..index + 4].copy_from_slice(&[
+                    255,
+                    255,
+                    255,
+                    (coverage * 255.0).round() as u8,
+                ]);
+            });
+            self.image = None;
+            Some(AtlasGlyph {
+                position,
+                size: glyph_size,
+                offset: Vec2::new(bounds.min.x, -bounds.max.y),
+            })
+        });
+        self.glyphs.insert(key, atlas_glyph);
+        atlas_glyph
+    }
+
+    /// Returns the position of a free rectangle of the size, growing the atlas if needed, or
+    /// `None` if it doesn't fit.
+    fn allocate(&mut self, size: UVec2) -> Option<UVec2> {
+        let padded = size + GLYPH_PADDING;
+        if padded.x > self.size.x {
+            return None;
+        }
+        if self.cursor.x + padded.x > self.size.x {
+            self.cursor = UVec2::new(0, self.cursor.y + self.row_height);
+            self.row_height = 0;
+        }
+        while self.cursor.y + padded.y > self.size.y {
+            if self.size.y * 2 > MAX_ATLAS_HEIGHT {
+                return None;
+            }
+            self.size.y *= 2;
+            self.pixels
+                .resize((self.size.x * self.size.y * 4) as usize, 0);
+        }
+
+        let position = self.cursor;
+        self.cursor.x += padded.x;
+        self.row_height = self.row_height.max(padded.y);
+        Some(position)
+    }
+
+    /// Returns the image of the atlas' pixels.
+    pub(crate) fn image(&mut self) -> &Image {
+        self.image.get_or_insert_with(|| {
+            Image::new(self.size, self.pixels.clone(), ColorSpace::Linear).unwrap()
+        })
+    }
+
+    /// Returns the texture coordinates of the glyph's bottom-left corner, and the offset from it
+    /// to the top-right corner.
+    pub(crate) fn uv_rect(&self, glyph: &AtlasGlyph) -> [f32; 4] {
+        let size = self.size.as_vec2();
+        let position = glyph.position.as_vec2() / size;
+        let extent = glyph.size.as_vec2() / size;
+        [position.x, position.y + extent.y, extent.x, -extent.y]
+    }
+}
+
+impl AtlasGlyph {
+    /// Returns the matrix mapping the unit square to the glyph drawn at the origin, scaled from
+    /// the size it was rasterized at.
+    pub(crate) fn matrix(&self, origin: Vec2, scale: f32) -> Mat4 {
+        let min = origin + self.offset * scale;
+        Mat4::from_translation(min.extend(0.0))
+            * Mat4::from_scale((self.size.as_vec2() * scale).extend(1.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn font() -> Font {
+        Font::from_bytes(include_bytes!("../../assets/fonts/Cantarell-Regular.ttf").to_vec())
+            .unwrap()
+    }
+
+    #[test]
+    fn from_bytes_rejects_invalid_fonts() {
+        assert_eq!(Font::from_bytes(vec![1, 2, 3]), Err(FontError::InvalidFont));
+    }
+
+    #[test]
+    fn layout_wraps_and_aligns_lines() {
+        let text = Text::new("ab cd\nefg", font(), 20.0);
+        let layout = text.layout();
+        let one_line = Text::new("ab", font(), 20.0).size();
+
+        assert_eq!(layout.glyphs.len(), 7);
+        assert_eq!(layout.size.y, one_line.y * 2.0);
+        assert_eq!(layout.glyphs[0].1.x, 0.0);
+        assert!(layout.glyphs[0].1.y < 0.0 && layout.glyphs[0].1.y > -20.0);
+
+        let wrapped = Text {
+            max_width: Some(one_line.x + 1.0),
+            alignment: TextAlignment::Right,
+            ..text
+        };
+        let layout = wrapped.layout();
+
+        assert_eq!(layout.size, Vec2::new(one_line.x + 1.0, one_line.y * 3.0));
+        // "cd" starts the second line, right aligned.
+        assert!(layout.glyphs[2].1.x > 0.0);
+        assert_eq!(layout.glyphs[2].1.y, layout.glyphs[0].1.y - one_line.y);
+    }
+
+    #[test]
+    fn aabb_is_placed_by_anchor() {
+        let text = Text {
+            anchor: Vec2::splat(0.5),
+            ..Text::new("pulse", font(), 16.0)
+        };
+        let size = text.size();
+
+        assert!(size.x > 0.0 && size.y >= 16.0);
+        assert_eq!(
+            text.aabb(),
+            Aabb::new(-size.extend(0.0) * 0.5, size.extend(0.0) * 0.5)
+        );
+    }
+
+    #[test]
+    fn atlas_caches_and_packs_glyphs() {
+        let font = font();
+        let mut atlas = GlyphAtlas::new();
+        let a = atlas.glyph(&font, font.font.glyph_id('a'), 32).unwrap();
+        let b = atlas.glyph(&font, font.font.glyph_id('b'), 32).unwrap();
+
+        assert_eq!(atlas.glyph(&font, font.font.glyph_id('a'), 32), Some(a));
+        assert_eq!(atlas.glyph(&font, font.font.glyph_id(' '), 32), None);
+        assert!(b.position.x >= a.position.x + a.size.x);
+        let image = atlas.image().clone();
+        assert!(image.pixels().chunks(4).any(|pixel| pixel[3] == 255));
+        assert_eq!(atlas.image(), &image);
+
+        for size in 33..80 {
+            atlas.glyph(&font, font.font.glyph_id('W'), size).unwrap();
+        }
+        assert!(atlas.size.y > ATLAS_SIZE);
+        assert_ne!(atlas.image(), &image);
+    }
+}
@@ -0,0 +1,582 @@
+//! # Tonemap
+//!
+//! Meshes are drawn into a high dynamic range target, which the
+//! [crate::render::graph::TONEMAP] node maps to the range of the render target with the
+//! [Exposure] and [Tonemapping] of the first camera.
+
+use std::collections::HashMap;
+
+use bytemuck::Pod;
+use bytemuck::Zeroable;
+use glam::UVec2;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::render::graph::RenderContext;
+use crate::render::graph::RenderNode;
+use crate::Camera;
+use crate::Reflect;
+use crate::Scene;
+use crate::Time;
+
+/// Format of the high dynamic range target.
+pub(crate) const HDR_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+/// Format of the luminance and exposure textures of auto exposure.
+const LUMINANCE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::R16Float;
+
+/// Size of the luminance texture averaged by auto exposure.
+const LUMINANCE_SIZE: u32 = 256;
+
+/// # Tonemapping
+///
+/// Operator mapping the exposed light of a [Camera] to the range of the render target.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Reflect, Serialize, Deserialize)]
+pub enum Tonemapping {
+    /// Clamps the light, so bright colors saturate.
+    None,
+    /// Reinhard's operator, compressing bright colors but washing them out.
+    Reinhard,
+    /// Fit of the filmic ACES curve, with more contrast and saturation.
+    #[default]
+    Aces,
+}
+
+/// # Exposure
+///
+/// Factor the light seen by a [Camera] is multiplied with before [Tonemapping], given as an
+/// exposure value (EV) in stops: increasing it by one halves the brightness.
+///
+/// ```
+/// # use pulse::render::tonemap::Exposure;
+/// # use pulse::render::tonemap::Tonemapping;
+/// # use pulse::Camera;
+/// let camera = Camera {
+///     exposure: Exposure::auto(),
+///     tonemapping: Tonemapping::Reinhard,
+///     ..Camera::perspective(1.0, 16.0 / 9.0, 0.1, 100.0)
+/// };
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq, Reflect, Serialize, Deserialize)]
+pub enum Exposure {
+    /// Fixed exposure value.
+    Manual {
+        /// Exposure value, zero to keep the light as is.
+        ev: f32,
+    },
+    /// Exposure value adapting to the average luminance of the rendered image, so it appears as
+    /// middle gray.
+    Auto {
+        /// Exposure value subtracted from the adapted one, positive to brighten the image.
+        compensation: f32,
+        /// Minimum exposure value, limiting the brightening of dark images.
+        min_ev: f32,
+        /// Maximum exposure value, limiting the darkening of bright images.
+        max_ev: f32,
+        /// Rate of the adaptation per second. Higher rates adapt faster.
+        speed: f32,
+    },
+}
+
+impl Exposure {
+    /// Returns auto exposure without compensation, ranging from -8 to 8 EV, adapting at a rate
+    /// of 2 per second.
+    pub const fn auto() -> Self {
+        Self::Auto {
+            compensation: 0.0,
+            min_ev: -8.0,
+            max_ev: 8.0,
+            speed: 2.0,
+        }
+    }
+}
+
+impl Default for Exposure {
+    fn default() -> Self {
+        Self::Manual { ev: 0.0 }
+    }
+}
+
+/// Render resource with the high dynamic range target the meshes are drawn into, with the size of
+/// the render target.
+pub(crate) struct HdrTarget {
+    size: UVec2,
+    pub(crate) texture: wgpu::Texture,
+    pub(crate) view: wgpu::TextureView,
+}
+
+impl HdrTarget {
+    /// Inserts a target with the size of the context's target into the context unless it has
+    /// one already.
+    pub(crate) fn prepare(context: &mut RenderContext) {
+        let size = context.target_size;
+        if context
+            .resource::<Self>()
+            .is_some_and(|target| target.size == size)
+        {
+            return;
+        }
+
+        let texture = context.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("hdr"),
+            size: wgpu::Extent3d {
+                width: size.x.max(1),
+                height: size.y.max(1),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: HDR_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                | wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        context.insert_resource(Self {
+            size,
+            texture,
+            view,
+        });
+    }
+}
+
+/// Uniforms of the tonemapping shader.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+struct Params {
+    ev: f32,
+    compensation: f32,
+    min_ev: f32,
+    max_ev: f32,
+    adaptation: f32,
+    auto_exposure: u32,
+    tonemapping: u32,
+    _padding: u32,
+}
+
+impl Params {
+    fn new(camera: Option<&Camera>, adaptation: f32) -> Self {
+        let mut params = Self::zeroed();
+        params.adaptation = adaptation;
+        let Some(camera) = camera else {
+            params.tonemapping = Tonemapping::default() as u32;
+            return params;
+        };
+
+        params.tonemapping = camera.tonemapping as u32;
+        match camera.exposure {
+            Exposure::Manual { ev } => params.ev = ev,
+            Exposure::Auto {
+                compensation,
+                min_ev,
+                max_ev,
+                ..
+            } => {
+                params.compensation = compensation;
+                params.min_ev = min_ev;
+                params.max_ev = max_ev.max(min_ev);
+                params.auto_exposure = 1;
+            }
+        }
+        params
+    }
+}
+
+/// Render node applying the exposure and tonemapping of the first camera to the [HdrTarget] and
+/// writing the result to the render target.
+pub(crate) struct TonemapPass {
+    sample_layout: wgpu::BindGroupLayout,
+    params_layout: wgpu::BindGroupLayout,
+    shader: wgpu::ShaderModule,
+    luminance: wgpu::RenderPipeline,
+    downsample: wgpu::RenderPipeline,
+    adapt: wgpu::RenderPipeline,
+    /// Tonemapping pipelines for the formats of the render targets.
+    tonemap: HashMap<wgpu::TextureFormat, wgpu::RenderPipeline>,
+    sampler: wgpu::Sampler,
+    params: wgpu::Buffer,
+    /// Views of the mips of the luminance texture.
+    luminance_mips: Vec<wgpu::TextureView>,
+}
+
+/// Render resource with the exposure of the render target's first camera.
+struct Exposures {
+    /// Exposure values of the previous and the current frame, swapped every frame.
+    views: [wgpu::TextureView; 2],
+    /// Whether the previous frame adapted the exposure, so the current frame can continue.
+    adapted: bool,
+}
+
+impl Exposures {
+    /// Inserts the exposures into the context unless it has them already.
+    fn prepare(context: &mut RenderContext) {
+        if context.resource::<Self>().is_some() {
+            return;
+        }
+
+        let exposure = || {
+            context
+                .device
+                .create_texture(&wgpu::TextureDescriptor {
+                    label: Some("exposure"),
+                    size: wgpu::Extent3d::default(),
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D2,
+                    format: LUMINANCE_FORMAT,
+                    usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                        | wgpu::TextureUsages::TEXTURE_BINDING,
+                    view_formats: &[],
+                })
+                .create_view(&wgpu::TextureViewDescriptor::default())
+        };
+        let views = [exposure(), exposure()];
+        context.insert_resource(Self {
+            views,
+            adapted: false,
+        });
+    }
+}
+
+impl TonemapPass {
+    pub(crate) fn new(device: &wgpu::Device) -> Self {
+        let shader = device.create_shader_module(wgpu::include_wgsl!("shaders/tonemap.wgsl"));
+        let texture = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        };
+        let sample_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("tonemap sample"),
+            entries: &[
+                texture(1),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+        let params_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("tonemap params"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(
+                            std::mem::size_of::<Params>() as u64
+                        ),
+                    },
+                    count: None,
+                },
+                texture(1),
+                texture(3),
+            ],
+        });
+
+        let luminance_texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("luminance"),
+            size: wgpu::Extent3d {
+                width: LUMINANCE_SIZE,
+                height: LUMINANCE_SIZE,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: LUMINANCE_SIZE.ilog2() + 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: LUMINANCE_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        let luminance_mips = (0..luminance_texture.mip_level_count())
+            .map(|mip| {
+                luminance_texture.create_view(&wgpu::TextureViewDescriptor {
+                    base_mip_level: mip,
+                    mip_level_count: Some(1),
+                    ..wgpu::TextureViewDescriptor::default()
+                })
+            })
+            .collect();
+
+        Self {
+            luminance: pipeline(
+                device,
+                &shader,
+                &sample_layout,
+                "fs_luminance",
+                LUMINANCE_FORMAT,
+            ),
+            downsample: pipeline(
+                device,
+                &shader,
+                &sample_layout,
+                "fs_downsample",
+                LUMINANCE_FORMAT,
+            ),
+            adapt: pipeline(
+                device,
+                &shader,
+                &params_layout,
+                "fs_adapt",
+                LUMINANCE_FORMAT,
+            ),
+            tonemap: HashMap::new(),
+            sample_layout,
+            params_layout,
+            shader,
+            sampler: device.create_sampler(&wgpu::SamplerDescriptor {
+                label: Some("tonemap"),
+                mag_filter: wgpu::FilterMode::Linear,
+                min_filter: wgpu::FilterMode::Linear,
+                ..wgpu::SamplerDescriptor::default()
+            }),
+            params: device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("tonemap params"),
+                size: std::mem::size_of::<Params>() as u64,
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }),
+            luminance_mips,
+        }
+    }
+
+    fn sample_bind_group(
+        &self,
+        device: &wgpu::Device,
+        view: &wgpu::TextureView,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("tonemap sample"),
+            layout: &self.sample_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler),
+                },
+            ],
+        })
+    }
+
+    fn params_bind_group(
+        &self,
+        device: &wgpu::Device,
+        source: &wgpu::TextureView,
+        exposure: &wgpu::TextureView,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("tonemap params"),
+            layout: &self.params_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: self.params.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(source),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(exposure),
+                },
+            ],
+        })
+    }
+}
+
+impl RenderNode for TonemapPass {
+    fn run(&mut self, context: &mut RenderContext, scene: &Scene) {
+        if context.resource::<HdrTarget>().is_none() {
+            return;
+        }
+        let device = context.device;
+
+        Exposures::prepare(context);
+        let exposures = context.resources.resource_mut::<Exposures>().unwrap();
+        let camera = context
+            .cameras
+            .first()
+            .and_then(|node| scene.get::<Camera>(*node));
+        let auto_exposure =
+            camera.is_some_and(|camera| matches!(camera.exposure, Exposure::Auto { .. }));
+        let adaptation = match camera.map(|camera| camera.exposure) {
+            Some(Exposure::Auto { speed, .. }) if exposures.adapted => {
+                let delta = scene
+                    .get_resource::<Time>()
+                    .map_or(0.0, |time| time.unscaled_delta().as_secs_f32());
+                1.0 - (-speed.max(0.0) * delta).exp()
+            }
+            _ => 1.0,
+        };
+        let params = Params::new(camera, adaptation);
+        context
+            .queue
+            .write_buffer(&self.params, 0, bytemuck::bytes_of(&params));
+
+        exposures.adapted = auto_exposure;
+        if auto_exposure {
+            exposures.views.swap(0, 1);
+        }
+
+        let hdr = context.resources.resource::<HdrTarget>().unwrap();
+        let exposures = &context.resources.resource::<Exposures>().unwrap().views;
+        if auto_exposure {
+            let bind_group = self.sample_bind_group(device, &hdr.view);
+            draw(
+                context.encoder,
+                &self.luminance,
+                &bind_group,
+                &self.luminance_mips[0],
+            );
+            for (previous, target) in self.luminance_mips.iter().zip(&self.luminance_mips[1..]) {
+                let bind_group = self.sample_bind_group(device, previous);
+                draw(context.encoder, &self.downsample, &bind_group, target);
+            }
+            let average = self.luminance_mips.last().unwrap();
+            let bind_group = self.params_bind_group(device, average, &exposures[0]);
+            draw(context.encoder, &self.adapt, &bind_group, &exposures[1]);
+        }
+
+        let bind_group = self.params_bind_group(device, &hdr.view, &exposures[1]);
+        let tonemap = self
+            .tonemap
+            .entry(context.target_format)
+            .or_insert_with(|| {
+                pipeline(
+                    device,
+                    &self.shader,
+                    &self.params_layout,
+                    "fs_tonemap",
+                    context.target_format,
+                )
+            });
+        draw(context.encoder, tonemap, &bind_group, context.target);
+        let draw_calls = if auto_exposure {
+            self.luminance_mips.len() as u32 + 2
+        } else {
+            1
+        };
+        context.count_draws(draw_calls, u64::from(draw_calls));
+    }
+}
+
+fn pipeline(
+    device: &wgpu::Device,
+    shader: &wgpu::ShaderModule,
+    layout: &wgpu::BindGroupLayout,
+    entry_point: &str,
+    format: wgpu::TextureFormat,
+) -> wgpu::RenderPipeline {
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("tonemap"),
+        bind_group_layouts: &[layout],
+        push_constant_ranges: &[],
+    });
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some(entry_point),
+        layout: Some(&pipeline_layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: "vs_main",
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+            buffers: &[],
+        },
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState::default(),
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point,
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+            targets: &[Some(format.into())],
+        }),
+        multiview: None,
+        cache: None,
+    })
+}
+
+/// Records a pass drawing a triangle covering the target.
+fn draw(
+    encoder: &mut wgpu::CommandEncoder,
+    pipeline: &wgpu::RenderPipeline,
+    bind_group: &wgpu::BindGroup,
+    target: &wgpu::TextureView,
+) {
+    let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: Some("tonemap"),
+        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+            view: target,
+            resolve_target: None,
+            ops: wgpu::Operations {
+                load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                store: wgpu::StoreOp::Store,
+            },
+        })],
+        depth_stencil_attachment: None,
+        timestamp_writes: None,
+        occlusion_query_set: None,
+    });
+    pass.set_pipeline(pipeline);
+    pass.set_bind_group(0, bind_group, &[]);
+    pass.draw(0..3, 0..1);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::ClearColor;
+    use crate::render::Color;
+
+    /// Renders a scene cleared to the gray with a camera with the exposure and tonemapping, and
+    /// returns the red component of a pixel, or `None` if there's no adapter.
+    fn render_gray(gray: f32, exposure: Exposure, tonemapping: Tonemapping) -> Option<u8> {
+        let mut scene = Scene::new();
+        scene.insert_resource(ClearColor(Color::rgb(gray, gray, gray)));
+        scene.spawn_with(Camera {
+            exposure,
+            tonemapping,
+            ..Camera::perspective(1.0, 1.0, 0.1, 10.0)
+        });
+
+        crate::render::tests::render(&scene).map(|pixels| pixels[0])
+    }
+
+    #[test]
+    fn render_maps_exposed_light() {
+        let manual = |ev| Exposure::Manual { ev };
+        let Some(none) = render_gray(1.0, manual(1.0), Tonemapping::None) else {
+            return;
+        };
+
+        assert_eq!(none, 128);
+        assert_eq!(
+            render_gray(1.0, manual(0.0), Tonemapping::Reinhard),
+            Some(128)
+        );
+        assert_eq!(render_gray(1.0, manual(0.0), Tonemapping::Aces), Some(205));
+    }
+
+    #[test]
+    fn render_adapts_exposure_to_middle_gray() {
+        let Some(gray) = render_gray(0.72, Exposure::auto(), Tonemapping::None) else {
+            return;
+        };
+
+        assert!(gray.abs_diff(46) <= 1);
+    }
+}
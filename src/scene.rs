@@ -1,18 +1,58 @@
 use std::any::Any;
 use std::any::TypeId;
-use std::cell::Ref;
-use std::cell::RefCell;
 use std::collections::BTreeMap;
-use std::sync::atomic::AtomicUsize;
-use std::sync::atomic::Ordering;
+use std::hash::Hash;
+use std::hash::Hasher;
+use std::ops::Deref;
+use std::ops::DerefMut;
 
 use nohash::IntMap;
 use nohash::IntSet;
 
-static ALLOCATOR: AtomicUsize = AtomicUsize::new(1);
+use crate::scene::events::EventBuffer;
+use crate::scene::filter::QueryFilter;
+use crate::scene::merge::NodeRemap;
+use crate::scene::reflect::ReflectRegistry;
+use crate::scene::relationship::RelationshipTable;
+use crate::scene::serialize::Registry;
+use crate::scene::stats::vec_memory;
+use crate::scene::stats::ComponentStats;
+use crate::Aabb;
+use crate::BoundingSphere;
+use crate::Camera;
+use crate::LocalTransform;
+use crate::Name;
+use crate::Prefab;
+use crate::Visibility;
+
+pub mod access;
+pub mod commands;
+mod events;
+pub mod filter;
+pub mod hierarchy;
+pub mod merge;
+#[cfg(feature = "rayon")]
+pub mod parallel;
+pub mod prefab;
+mod reflect;
+pub mod relationship;
+mod serialize;
+pub mod snapshot;
+pub mod stats;
+#[cfg(any(debug_assertions, feature = "validate"))]
+pub mod validate;
 
 /// # Component
-pub trait Component: 'static + Clone + PartialEq {}
+///
+/// Value attached to nodes. Components are shared between the threads of systems run in parallel,
+/// so they must be [Send] and [Sync].
+pub trait Component: 'static + Clone + PartialEq + Send + Sync {}
+
+/// # Tag
+///
+/// Marker without any data that can be attached to nodes. Tags are stored as a set of nodes rather
+/// than a component table.
+pub trait Tag: 'static {}
 
 /// # Component Event
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
@@ -25,92 +65,191 @@ pub enum ComponentEvent {
     Removed(Node),
 }
 
+/// # Node Event
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum NodeEvent {
+    /// Node was spawned.
+    Spawned(Node),
+    /// Node was despawned.
+    Despawned(Node),
+}
+
 /// # Node
-#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+///
+/// Handle to a node in a [Scene]. Handles are made of an index and a generation, so a handle to a
+/// despawned node never refers to a node spawned later in its place.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
 pub struct Node {
-    id: usize,
+    index: u32,
+    generation: u32,
 }
 
 impl Node {
-    fn new() -> Self {
-        Self {
-            id: ALLOCATOR.fetch_add(1, Ordering::Relaxed),
-        }
+    const fn new(index: u32, generation: u32) -> Self {
+        Self { index, generation }
+    }
+
+    /// Returns the index of the node within its scene.
+    pub const fn index(&self) -> u32 {
+        self.index
+    }
+
+    /// Returns the generation of the node's index.
+    pub const fn generation(&self) -> u32 {
+        self.generation
+    }
+}
+
+impl Hash for Node {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_u64((u64::from(self.generation) << 32) | u64::from(self.index));
     }
 }
 
 impl nohash::IsEnabled for Node {}
 
-trait DynamicComponentTable {
+trait DynamicComponent {
+    fn component_type_id(&self) -> TypeId;
+
+    fn add_to(&self, scene: &mut Scene, node: Node);
+
+    fn clone_box(&self) -> Box<dyn DynamicComponent>;
+}
+
+impl<T: Component> DynamicComponent for T {
+    fn component_type_id(&self) -> TypeId {
+        TypeId::of::<T>()
+    }
+
+    fn add_to(&self, scene: &mut Scene, node: Node) {
+        scene.set_or_add(node, self.clone());
+    }
+
+    fn clone_box(&self) -> Box<dyn DynamicComponent> {
+        Box::new(self.clone())
+    }
+}
+
+trait DynamicComponentTable: Send + Sync {
     fn as_any(&self) -> &dyn Any;
 
     fn as_any_mut(&mut self) -> &mut dyn Any;
 
+    fn type_name(&self) -> &'static str;
+
+    fn stats(&self) -> ComponentStats;
+
+    fn get_dynamic(&self, node: Node) -> Option<Box<dyn DynamicComponent>>;
+
+    fn merge_into(self: Box<Self>, scene: &mut Scene, remap: &NodeRemap);
+
+    fn clone_table(&self) -> Box<dyn DynamicComponentTable>;
+
+    fn empty_table(&self) -> Box<dyn DynamicComponentTable>;
+
+    fn restore(&mut self, other: &dyn DynamicComponentTable);
+
+    #[cfg(any(debug_assertions, feature = "validate"))]
+    fn validate(&self, scene: &Scene, errors: &mut Vec<validate::ValidationError>);
+
     fn remove(&mut self, node: Node);
 
+    fn clear(&mut self);
+
     fn clear_events(&mut self);
+
+    fn advance_events(&mut self);
+}
+
+/// Change ticks of a component value.
+#[derive(Copy, Clone, Debug)]
+struct ComponentTicks {
+    added: u32,
+    changed: u32,
 }
 
+/// Sparse set of component values. The values are packed in a dense array for linear iteration
+/// and looked up through a sparse array indexed by the node index.
 struct ComponentTable<T> {
-    node_indexes: IntMap<Node, usize>,
+    sparse: Vec<Option<u32>>,
+    nodes: Vec<Node>,
     items: Vec<T>,
-    events: Vec<ComponentEvent>,
+    ticks: Vec<ComponentTicks>,
+    events: EventBuffer<ComponentEvent>,
 }
 
 impl<T: Component> ComponentTable<T> {
     fn new() -> Self {
         Self {
-            node_indexes: IntMap::default(),
+            sparse: Vec::new(),
+            nodes: Vec::new(),
             items: Vec::new(),
-            events: Vec::new(),
+            ticks: Vec::new(),
+            events: EventBuffer::new(),
         }
     }
 
-    fn add(&mut self, node: Node, value: T) {
-        if !self.node_indexes.contains_key(&node) {
-            let index = self.items.len();
-            self.node_indexes.insert(node, index);
+    fn index(&self, node: Node) -> Option<usize> {
+        let index = (*self.sparse.get(node.index as usize)?)? as usize;
+        (self.nodes[index] == node).then_some(index)
+    }
+
+    fn add(&mut self, node: Node, value: T, tick: u32) {
+        let sparse_index = node.index as usize;
+        if self.sparse.len() <= sparse_index {
+            self.sparse.resize(sparse_index + 1, None);
+        }
+
+        if self.sparse[sparse_index].is_none() {
+            self.sparse[sparse_index] = Some(self.items.len() as u32);
+            self.nodes.push(node);
             self.items.push(value);
+            self.ticks.push(ComponentTicks {
+                added: tick,
+                changed: tick,
+            });
             self.events.push(ComponentEvent::Added(node));
         }
     }
 
+    fn reserve(&mut self, additional: usize) {
+        self.nodes.reserve(additional);
+        self.items.reserve(additional);
+        self.ticks.reserve(additional);
+        self.events.reserve(additional);
+    }
+
     fn get(&self, node: Node) -> Option<&T> {
-        self.node_indexes
-            .get(&node)
-            .map(|index| &self.items[*index])
+        self.index(node).map(|index| &self.items[index])
     }
 
-    fn set(&mut self, node: Node, value: T) {
-        if let Some(index) = self.node_indexes.get(&node) {
-            if self.items[*index] != value {
-                self.items[*index] = value;
-                self.events.push(ComponentEvent::Modified(node));
-            }
+    fn set(&mut self, node: Node, value: T, tick: u32) {
+        if let Some(index) = self.index(node) {
+            self.items[index] = value;
+            self.mark_modified(index, tick);
         }
     }
 
+    fn mark_modified(&mut self, index: usize, tick: u32) {
+        self.ticks[index].changed = tick;
+        self.events
+            .push(ComponentEvent::Modified(self.nodes[index]));
+    }
+
     fn remove(&mut self, node: Node) {
-        if let Some(index) = self.node_indexes.remove(&node) {
+        if let Some(index) = self.index(node) {
             self.events.push(ComponentEvent::Removed(node));
+            self.sparse[node.index as usize] = None;
+            self.nodes.swap_remove(index);
             self.items.swap_remove(index);
+            self.ticks.swap_remove(index);
 
-            let moved_index = self.items.len();
-            if moved_index != index {
-                for node_index in &mut self.node_indexes.values_mut() {
-                    if *node_index == moved_index {
-                        *node_index = index;
-                        break;
-                    }
-                }
+            if let Some(moved_node) = self.nodes.get(index) {
+                self.sparse[moved_node.index as usize] = Some(index as u32);
             }
         }
     }
 
-    fn events(&self) -> &[ComponentEvent] {
-        &self.events
-    }
-
     fn clear_events(&mut self) {
         self.events.clear();
     }
@@ -125,34 +264,181 @@ impl<T: Component> DynamicComponentTable for ComponentTable<T> {
         self
     }
 
+    fn type_name(&self) -> &'static str {
+        std::any::type_name::<T>()
+    }
+
+    fn stats(&self) -> ComponentStats {
+        ComponentStats {
+            type_id: TypeId::of::<T>(),
+            type_name: self.type_name(),
+            count: self.items.len(),
+            memory: vec_memory(&self.sparse)
+                + vec_memory(&self.nodes)
+                + vec_memory(&self.items)
+                + vec_memory(&self.ticks)
+                + vec_memory(&self.events),
+            events: self.events.len(),
+        }
+    }
+
+    fn get_dynamic(&self, node: Node) -> Option<Box<dyn DynamicComponent>> {
+        self.get(node)
+            .map(|value| Box::new(value.clone()) as Box<dyn DynamicComponent>)
+    }
+
+    fn merge_into(self: Box<Self>, scene: &mut Scene, remap: &NodeRemap) {
+        for (node, value) in self.nodes.into_iter().zip(self.items) {
+            if let Some(node) = remap.get(node) {
+                scene.add(node, value);
+            }
+        }
+    }
+
+    fn clone_table(&self) -> Box<dyn DynamicComponentTable> {
+        Box::new(Self {
+            sparse: self.sparse.clone(),
+            nodes: self.nodes.clone(),
+            items: self.items.clone(),
+            ticks: self.ticks.clone(),
+            events: EventBuffer::new(),
+        })
+    }
+
+    fn empty_table(&self) -> Box<dyn DynamicComponentTable> {
+        Box::new(Self::new())
+    }
+
+    fn restore(&mut self, other: &dyn DynamicComponentTable) {
+        let other = other.as_any().downcast_ref::<Self>().unwrap();
+        self.sparse.clone_from(&other.sparse);
+        self.nodes.clone_from(&other.nodes);
+        self.items.clone_from(&other.items);
+        self.ticks.clone_from(&other.ticks);
+        self.events.clear();
+    }
+
+    #[cfg(any(debug_assertions, feature = "validate"))]
+    fn validate(&self, scene: &Scene, errors: &mut Vec<validate::ValidationError>) {
+        let type_name = self.type_name();
+        let corrupt = self.nodes.len() != self.items.len()
+            || self.nodes.len() != self.ticks.len()
+            || self.sparse.iter().flatten().count() != self.nodes.len();
+
+        for (index, node) in self.nodes.iter().enumerate() {
+            if corrupt
+                || self.sparse.get(node.index as usize).copied().flatten() != Some(index as u32)
+            {
+                errors.push(validate::ValidationError::CorruptComponentIndex {
+                    type_name,
+                    node: *node,
+                });
+            } else if !scene.contains(*node) {
+                errors.push(validate::ValidationError::DanglingComponent {
+                    type_name,
+                    node: *node,
+                });
+            }
+        }
+    }
+
     fn remove(&mut self, node: Node) {
         self.remove(node);
     }
 
+    fn clear(&mut self) {
+        self.events
+            .extend(self.nodes.iter().map(|node| ComponentEvent::Removed(*node)));
+        self.sparse = Vec::new();
+        self.nodes = Vec::new();
+        self.items = Vec::new();
+        self.ticks = Vec::new();
+    }
+
     fn clear_events(&mut self) {
         self.clear_events();
     }
+
+    fn advance_events(&mut self) {
+        self.events.advance();
+    }
+}
+
+#[derive(Clone, Default)]
+struct TagTable {
+    nodes: IntSet<Node>,
+    events: EventBuffer<ComponentEvent>,
+}
+
+impl TagTable {
+    fn insert(&mut self, node: Node) {
+        if self.nodes.insert(node) {
+            self.events.push(ComponentEvent::Added(node));
+        }
+    }
+
+    fn remove(&mut self, node: Node) {
+        if self.nodes.remove(&node) {
+            self.events.push(ComponentEvent::Removed(node));
+        }
+    }
 }
 
 /// # Scene
 pub struct Scene {
+    generations: Vec<u32>,
+    free_indexes: Vec<u32>,
     nodes: IntSet<Node>,
     parents: IntMap<Node, Node>,
     children: IntMap<Node, Vec<Node>>,
-    component_indexes: RefCell<BTreeMap<TypeId, usize>>,
-    component_tables: RefCell<Vec<Box<dyn DynamicComponentTable>>>,
+    parent_ticks: IntMap<Node, u32>,
+    node_events: EventBuffer<NodeEvent>,
+    change_tick: u32,
+    component_indexes: BTreeMap<TypeId, usize>,
+    component_tables: Vec<Box<dyn DynamicComponentTable>>,
+    tag_tables: BTreeMap<TypeId, TagTable>,
+    relationship_tables: BTreeMap<TypeId, RelationshipTable>,
+    resources: BTreeMap<TypeId, Box<dyn Any + Send + Sync>>,
+    registry: Registry,
+    reflect_registry: ReflectRegistry,
 }
 
 impl Scene {
-    /// Returns an empty scene.
+    /// Returns an empty scene with the built-in components registered for serialization and
+    /// reflection.
     pub fn new() -> Self {
-        Self {
+        let mut scene = Self {
+            generations: Vec::new(),
+            free_indexes: Vec::new(),
             nodes: IntSet::default(),
             parents: IntMap::default(),
             children: IntMap::default(),
-            component_indexes: RefCell::new(BTreeMap::new()),
-            component_tables: RefCell::new(Vec::new()),
-        }
+            parent_ticks: IntMap::default(),
+            node_events: EventBuffer::new(),
+            change_tick: 1,
+            component_indexes: BTreeMap::new(),
+            component_tables: Vec::new(),
+            tag_tables: BTreeMap::new(),
+            relationship_tables: BTreeMap::new(),
+            resources: BTreeMap::new(),
+            registry: Registry::new(),
+            reflect_registry: ReflectRegistry::new(),
+        };
+
+        scene.register::<Aabb>("Aabb");
+        scene.register::<BoundingSphere>("BoundingSphere");
+        scene.register::<Camera>("Camera");
+        scene.register::<LocalTransform>("LocalTransform");
+        scene.register::<Name>("Name");
+        scene.register::<Visibility>("Visibility");
+        scene.register_reflect::<Aabb>("Aabb");
+        scene.register_reflect::<BoundingSphere>("BoundingSphere");
+        scene.register_reflect::<Camera>("Camera");
+        scene.register_reflect::<LocalTransform>("LocalTransform");
+        scene.register_reflect::<Name>("Name");
+        scene.register_reflect::<Visibility>("Visibility");
+
+        scene
     }
 
     /// Returns true if the scene contains the given node.
@@ -160,44 +446,160 @@ impl Scene {
         self.nodes.contains(&node)
     }
 
-    /// Creates a new node and adds it to the scene.
+    /// Creates a new node and adds it to the scene. Indexes of despawned nodes are reused with a
+    /// new generation.
     pub fn spawn(&mut self) -> Node {
-        let node = Node::new();
+        let node = match self.free_indexes.pop() {
+            Some(index) => Node::new(index, self.generations[index as usize]),
+            None => {
+                let index = u32::try_from(self.generations.len()).expect("too many nodes");
+                self.generations.push(0);
+                Node::new(index, 0)
+            }
+        };
+
         self.nodes.insert(node);
+        self.node_events.push(NodeEvent::Spawned(node));
+        node
+    }
+
+    /// Creates the given number of nodes and adds them to the scene. Storage for the nodes is
+    /// reserved up front, so this is faster than calling [Scene::spawn] in a loop.
+    pub fn spawn_batch(&mut self, count: usize) -> Vec<Node> {
+        let reused = count.min(self.free_indexes.len());
+        let new = count - reused;
+        // The last new index is checked before any free index is taken, matching the limit of
+        // [Scene::spawn] and leaving the scene unchanged if there are too many nodes.
+        let first = self.generations.len();
+        let last = u32::try_from((first + new).saturating_sub(1)).expect("too many nodes");
+        self.generations.reserve(new);
+        self.nodes.reserve(count);
+        self.node_events.reserve(count);
+
+        let mut nodes = Vec::with_capacity(count);
+        for index in self
+            .free_indexes
+            .drain(self.free_indexes.len() - reused..)
+            .rev()
+        {
+            nodes.push(Node::new(index, self.generations[index as usize]));
+        }
+
+        self.generations.resize(first + new, 0);
+        nodes.extend(
+            (first..=last as usize)
+                .take(new)
+                .map(|index| Node::new(index as u32, 0)),
+        );
+
+        self.nodes.extend(nodes.iter().copied());
+        self.node_events
+            .extend(nodes.iter().copied().map(NodeEvent::Spawned));
+        nodes
+    }
+
+    /// Creates a new node with the components of the bundle and adds it to the scene.
+    ///
+    /// ```
+    /// # use pulse::LocalTransform;
+    /// # use pulse::Name;
+    /// # use pulse::Scene;
+    /// # use pulse::Visibility;
+    /// let mut scene = Scene::new();
+    /// let node = scene.spawn_with((LocalTransform::IDENTITY, Visibility::Visible, Name::new("Player")));
+    /// ```
+    pub fn spawn_with<B: Bundle>(&mut self, bundle: B) -> Node {
+        let node = self.spawn();
+        bundle.add_to(self, node);
         node
     }
 
+    /// Adds the components of the bundle to the node.
+    pub fn add_bundle<B: Bundle>(&mut self, node: Node, bundle: B) {
+        bundle.add_to(self, node);
+    }
+
+    /// Deep-copies the node, its components and tags, and its descendants, and returns the copy.
+    /// The copy has the same parent as the original node. Returns [None] if the scene doesn't
+    /// contain the node.
+    pub fn duplicate(&mut self, node: Node) -> Option<Node> {
+        let copy = Prefab::from_node(self, node)?.instantiate(self);
+        if let Some(parent) = self.get_parent(node) {
+            self.set_parent(copy, parent);
+        }
+
+        Some(copy)
+    }
+
     /// Removes the given node from the scene.
     pub fn despawn(&mut self, node: Node) {
         if self.contains(node) {
-            Self::despawn_internal(
-                &mut self.nodes,
-                &mut self.parents,
-                &mut self.children,
-                &mut self.component_tables.borrow_mut(),
-                node,
-            );
             self.remove_parent(node);
+            self.despawn_internal(node);
         }
     }
 
-    fn despawn_internal(
-        nodes: &mut IntSet<Node>,
-        parents: &mut IntMap<Node, Node>,
-        children: &mut IntMap<Node, Vec<Node>>,
-        component_tables: &mut Vec<Box<dyn DynamicComponentTable>>,
-        node: Node,
-    ) {
-        if nodes.remove(&node) {
-            for child in children.remove(&node).into_iter().flatten() {
-                Self::despawn_internal(nodes, parents, children, component_tables, child);
-            }
+    fn despawn_internal(&mut self, node: Node) {
+        let mut stack = vec![node];
+        while let Some(node) = stack.pop() {
+            if self.nodes.remove(&node) {
+                let generation = &mut self.generations[node.index as usize];
+                *generation = generation.wrapping_add(1);
+                self.free_indexes.push(node.index);
+                self.node_events.push(NodeEvent::Despawned(node));
+
+                stack.extend(self.children.remove(&node).into_iter().flatten());
+
+                for table in &mut self.component_tables {
+                    table.remove(node);
+                }
+
+                for table in self.tag_tables.values_mut() {
+                    table.remove(node);
+                }
 
-            for table in component_tables {
-                table.remove(node);
+                for table in self.relationship_tables.values_mut() {
+                    table.remove_node(node);
+                }
+
+                self.parents.remove(&node);
+                self.parent_ticks.remove(&node);
             }
+        }
+    }
+
+    /// Despawns all of the nodes and releases the component storage. Emits the despawned node
+    /// events and the removed component and tag events. Resources and component registrations are
+    /// kept.
+    pub fn clear(&mut self) {
+        let mut nodes = self.nodes.drain().collect::<Vec<_>>();
+        nodes.sort();
+
+        for node in nodes {
+            let generation = &mut self.generations[node.index as usize];
+            *generation = generation.wrapping_add(1);
+            self.free_indexes.push(node.index);
+            self.node_events.push(NodeEvent::Despawned(node));
+        }
+
+        self.parents.clear();
+        self.children.clear();
+        self.parent_ticks.clear();
+
+        for table in &mut self.component_tables {
+            table.clear();
+        }
+
+        for table in self.tag_tables.values_mut() {
+            let mut nodes = table.nodes.drain().collect::<Vec<_>>();
+            nodes.sort();
+            table
+                .events
+                .extend(nodes.into_iter().map(ComponentEvent::Removed));
+        }
 
-            parents.remove(&node);
+        for table in self.relationship_tables.values_mut() {
+            table.clear();
         }
     }
 
@@ -213,28 +615,41 @@ impl Scene {
             return;
         }
 
-        let mut root = Some(parent);
-        while root.is_some() {
-            if root.unwrap() == node {
-                return;
-            }
-
-            root = self.get_parent(root.unwrap());
+        // A node without children can't be an ancestor of the parent.
+        let has_children = self
+            .get_children(node)
+            .is_some_and(|children| !children.is_empty());
+        if node == parent || has_children && self.ancestors(parent).any(|ancestor| ancestor == node)
+        {
+            return;
         }
 
         self.remove_parent(node);
         self.parents.insert(node, parent);
+        self.parent_ticks.insert(node, self.change_tick);
 
-        if !self.children.contains_key(&parent) {
-            self.children.insert(parent, Vec::new());
-        }
+        self.children.entry(parent).or_default().push(node);
+    }
 
-        self.children.get_mut(&parent).unwrap().push(node);
+    /// Returns true if the parent of the node was set or removed after the given tick.
+    pub fn is_parent_changed(&self, node: Node, since: u32) -> bool {
+        self.parent_ticks
+            .get(&node)
+            .is_some_and(|tick| *tick > since)
+    }
+
+    /// Returns the nodes whose parent was set or removed after the given tick.
+    pub fn parent_changed(&self, since: u32) -> impl '_ + Iterator<Item = Node> {
+        self.parent_ticks
+            .iter()
+            .filter(move |(_, tick)| **tick > since)
+            .map(|(node, _)| *node)
     }
 
     /// Removes the parent node for the given node.
     pub fn remove_parent(&mut self, node: Node) {
         if let Some(parent) = self.parents.remove(&node) {
+            self.parent_ticks.insert(node, self.change_tick);
             if let Some(children) = self.children.get_mut(&parent) {
                 let mut i = 0;
                 while i < children.len() {
@@ -261,195 +676,593 @@ impl Scene {
         self.children.get(&node).map(Vec::as_slice)
     }
 
-    /// Adds the component to the node.
-    pub fn add<T: Component>(&self, node: Node, value: T) {
-        let component_index = match self.component_index::<T>() {
-            Some(index) => index,
-            None => {
-                let index = self.component_tables.borrow().len();
-                self.component_indexes
-                    .borrow_mut()
-                    .insert(TypeId::of::<T>(), index);
-                self.component_tables
-                    .borrow_mut()
-                    .push(Box::new(ComponentTable::<T>::new()));
-
-                index
-            }
-        };
+    /// Adds the component to the node. Does nothing if the scene doesn't contain the node.
+    pub fn add<T: Component>(&mut self, node: Node, value: T) {
+        if self.contains(node) {
+            let tick = self.change_tick;
+            self.table_or_insert::<T>().add(node, value, tick);
+        }
+    }
 
-        self.component_tables.borrow_mut()[component_index]
+    /// Adds the components to the nodes. Storage for the components is reserved up front, so this
+    /// is faster than calling [Scene::add] in a loop. Nodes the scene doesn't contain are skipped.
+    pub fn add_batch<T: Component>(&mut self, values: impl IntoIterator<Item = (Node, T)>) {
+        let values = values.into_iter();
+        let tick = self.change_tick;
+        self.table_or_insert::<T>().reserve(values.size_hint().0);
+        let table = self.component_tables[self.component_indexes[&TypeId::of::<T>()]]
             .as_any_mut()
             .downcast_mut::<ComponentTable<T>>()
-            .unwrap()
-            .add(node, value);
+            .unwrap();
+
+        for (node, value) in values {
+            if self.nodes.contains(&node) {
+                table.add(node, value, tick);
+            }
+        }
     }
 
     /// Returns the component value for the given node.
-    pub fn get<T: Component>(&self, node: Node) -> Option<T> {
-        if let Some(component_index) = self.component_index::<T>() {
-            self.component_tables.borrow()[component_index]
-                .as_any()
-                .downcast_ref::<ComponentTable<T>>()
-                .unwrap()
-                .get(node)
-                .cloned()
-        } else {
-            None
+    pub fn get<T: Component>(&self, node: Node) -> Option<&T> {
+        self.table::<T>()?.get(node)
+    }
+
+    /// Returns a mutable reference to the component value for the given node. The component is
+    /// marked as modified when the returned reference is dropped if it was mutably dereferenced.
+    pub fn get_mut<T: Component>(&mut self, node: Node) -> Option<ComponentMut<'_, T>> {
+        let tick = self.change_tick;
+        let table = self.table_mut::<T>()?;
+        let index = table.index(node)?;
+
+        Some(ComponentMut {
+            table,
+            index,
+            tick,
+            modified: false,
+        })
+    }
+
+    /// Sets the component value for the given node and marks it as modified, without comparing it
+    /// to the current value. Use [Scene::set_if_changed] to skip values equal to the current one.
+    pub fn set<T: Component>(&mut self, node: Node, value: T) {
+        let tick = self.change_tick;
+        if let Some(table) = self.table_mut::<T>() {
+            table.set(node, value, tick);
         }
     }
 
-    /// Sets the component value for the given node.
-    pub fn set<T: Component>(&self, node: Node, value: T) {
-        if let Some(component_index) = self.component_index::<T>() {
-            self.component_tables.borrow_mut()[component_index]
-                .as_any_mut()
-                .downcast_mut::<ComponentTable<T>>()
-                .unwrap()
-                .set(node, value);
+    /// Sets the component value for the given node if it's different from the current value, so
+    /// equal values aren't marked as modified. Compares the values, which may be costly for large
+    /// components.
+    pub fn set_if_changed<T: Component>(&mut self, node: Node, value: T) {
+        if self.get::<T>(node).is_some_and(|current| *current != value) {
+            self.set(node, value);
         }
     }
 
     /// Sets the component value for the given node or adds the component.
-    pub fn set_or_add<T: Component>(&self, node: Node, value: T) {
-        self.add(node, value.clone());
-        self.set(node, value);
-    }
+    pub fn set_or_add<T: Component>(&mut self, node: Node, value: T) {
+        if !self.contains(node) {
+            return;
+        }
 
-    /// Removes the component from the given node.
-    pub fn remove<T: Component>(&self, node: Node) {
-        if let Some(component_index) = self.component_index::<T>() {
-            self.component_tables.borrow_mut()[component_index]
-                .as_any_mut()
-                .downcast_mut::<ComponentTable<T>>()
-                .unwrap()
-                .remove(node);
-        }
-    }
-
-    /// Returns the component events for the given component.
-    pub fn events<T: Component>(&self) -> Ref<[ComponentEvent]> {
-        if let Some(component_index) = self.component_index::<T>() {
-            Ref::map(self.component_tables.borrow(), |table| {
-                table[component_index]
-                    .as_any()
-                    .downcast_ref::<ComponentTable<T>>()
-                    .unwrap()
-                    .events()
-            })
+        let tick = self.change_tick;
+        let table = self.table_or_insert::<T>();
+        if table.get(node).is_some() {
+            table.set(node, value, tick);
         } else {
-            Ref::map(self.component_tables.borrow(), |_| &[])
+            table.add(node, value, tick);
         }
     }
 
-    /// Clears the component events for all the components.
-    pub fn clear_events(&self) {
-        for table in self.component_tables.borrow_mut().iter_mut() {
-            table.clear_events();
+    /// Removes the component from the given node.
+    pub fn remove<T: Component>(&mut self, node: Node) {
+        if let Some(table) = self.table_mut::<T>() {
+            table.remove(node);
         }
     }
 
-    fn component_index<T: Component>(&self) -> Option<usize> {
-        self.component_indexes
-            .borrow()
-            .get(&TypeId::of::<T>())
-            .copied()
+    /// Returns the first node with the given [Name].
+    pub fn find_by_name(&self, name: &str) -> Option<Node> {
+        self.find_all_by_name(name).next()
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use std::ops::Deref;
+    /// Returns all of the nodes with the given [Name], in the order of [Scene::query].
+    pub fn find_all_by_name<'a>(&'a self, name: &'a str) -> impl 'a + Iterator<Item = Node> {
+        self.query::<(Name,)>()
+            .filter(move |(_, node_name)| node_name.as_str() == name)
+            .map(|(node, _)| node)
+    }
 
-    use super::*;
+    /// Returns the nodes with all of the queried components along with references to the
+    /// component values. The nodes are returned in the storage order of the first queried
+    /// component, which is the order it was added to them until it's removed from a node and the
+    /// last node takes its place.
+    ///
+    /// ```
+    /// # use pulse::LocalTransform;
+    /// # use pulse::Scene;
+    /// # use pulse::Visibility;
+    /// let mut scene = Scene::new();
+    /// let node = scene.spawn();
+    /// scene.add(node, LocalTransform::IDENTITY);
+    /// scene.add(node, Visibility::Visible);
+    ///
+    /// for (node, transform, visibility) in scene.query::<(LocalTransform, Visibility)>() {
+    ///     println!("{node:?}: {transform:?} {visibility:?}");
+    /// }
+    /// ```
+    pub fn query<Q: Query>(&self) -> impl Iterator<Item = Q::Item<'_>> {
+        Q::fetch(self)
+    }
 
-    impl Component for u32 {}
+    /// Returns the type ids of all of the tags of the given node.
+    fn get_all_tags(&self, node: Node) -> Vec<TypeId> {
+        self.tag_tables
+            .iter()
+            .filter(|(_, table)| table.nodes.contains(&node))
+            .map(|(type_id, _)| *type_id)
+            .collect()
+    }
 
-    #[test]
-    fn spawn_contains_returns_true() {
-        let mut scene = Scene::new();
+    /// Adds the tag with the given type id to the node.
+    fn tag_dynamic(&mut self, type_id: TypeId, node: Node) {
+        if self.contains(node) {
+            self.tag_tables.entry(type_id).or_default().insert(node);
+        }
+    }
 
-        let node = scene.spawn();
+    /// Returns copies of all of the components of the given node.
+    fn get_all_dynamic(&self, node: Node) -> Vec<Box<dyn DynamicComponent>> {
+        self.component_tables
+            .iter()
+            .filter_map(|table| table.get_dynamic(node))
+            .collect()
+    }
 
-        assert!(scene.contains(node));
+    /// Returns the current change tick. Components added or changed from now on are stamped with
+    /// this tick until [Scene::advance_change_tick] is called.
+    pub fn change_tick(&self) -> u32 {
+        self.change_tick
     }
 
-    #[test]
-    fn spawn_get_parent_returns_none() {
-        let mut scene = Scene::new();
+    /// Advances the change tick and returns the previous tick. Pass the returned tick to
+    /// [Scene::is_added] or [Scene::is_changed] later to find the components added or changed
+    /// since this call. Events recorded before the previous call are dropped, so events that are
+    /// never cleared are kept for one tick after the one they were recorded in.
+    ///
+    /// ```
+    /// # use pulse::Scene;
+    /// # use pulse::Visibility;
+    /// let mut scene = Scene::new();
+    /// let node = scene.spawn_with(Visibility::Visible);
+    /// let since = scene.advance_change_tick();
+    /// assert!(!scene.is_changed::<Visibility>(node, since));
+    ///
+    /// scene.set(node, Visibility::Invisible);
+    /// assert!(scene.is_changed::<Visibility>(node, since));
+    /// ```
+    pub fn advance_change_tick(&mut self) -> u32 {
+        self.node_events.advance();
+        for table in &mut self.component_tables {
+            table.advance_events();
+        }
+        for table in self.tag_tables.values_mut() {
+            table.events.advance();
+        }
 
-        let node = scene.spawn();
+        let tick = self.change_tick;
+        self.change_tick = tick.wrapping_add(1);
+        tick
+    }
 
-        assert_eq!(scene.get_parent(node), None);
+    /// Returns true if the component was added to the node after the given tick.
+    pub fn is_added<T: Component>(&self, node: Node, since: u32) -> bool {
+        self.ticks::<T>(node)
+            .is_some_and(|ticks| ticks.added > since)
     }
 
-    #[test]
-    fn spawn_get_children_returns_none() {
-        let mut scene = Scene::new();
+    /// Returns true if the component was added to or changed for the node after the given tick.
+    pub fn is_changed<T: Component>(&self, node: Node, since: u32) -> bool {
+        self.ticks::<T>(node)
+            .is_some_and(|ticks| ticks.changed > since)
+    }
 
-        let node = scene.spawn();
+    /// Returns the nodes whose component was added or changed after the given tick.
+    pub fn changed<T: Component>(&self, since: u32) -> impl '_ + Iterator<Item = Node> {
+        self.table::<T>().into_iter().flat_map(move |table| {
+            table
+                .nodes
+                .iter()
+                .zip(&table.ticks)
+                .filter(move |(_, ticks)| ticks.changed > since)
+                .map(|(node, _)| *node)
+        })
+    }
 
-        assert_eq!(scene.get_children(node), None);
+    fn ticks<T: Component>(&self, node: Node) -> Option<ComponentTicks> {
+        let table = self.table::<T>()?;
+        table.index(node).map(|index| table.ticks[index])
     }
 
-    #[test]
-    fn spawn_get_returns_none() {
-        let mut scene = Scene::new();
+    /// Returns the component events for the given component, recorded since they were last
+    /// cleared, but at most since the change tick before the current one.
+    pub fn events<T: Component>(&self) -> &[ComponentEvent] {
+        self.table::<T>()
+            .map(|table| table.events.as_slice())
+            .unwrap_or(&[])
+    }
 
-        let node = scene.spawn();
+    /// Adds the tag to the node.
+    pub fn tag<T: Tag>(&mut self, node: Node) {
+        if self.contains(node) {
+            self.tag_tables
+                .entry(TypeId::of::<T>())
+                .or_default()
+                .insert(node);
+        }
+    }
 
-        assert_eq!(scene.get::<u32>(node), None);
+    /// Removes the tag from the node.
+    pub fn untag<T: Tag>(&mut self, node: Node) {
+        if let Some(table) = self.tag_tables.get_mut(&TypeId::of::<T>()) {
+            table.remove(node);
+        }
     }
 
-    #[test]
-    fn despawn_contains_returns_false() {
-        let mut scene = Scene::new();
-        let node = scene.spawn();
+    /// Returns true if the node has the tag.
+    pub fn has_tag<T: Tag>(&self, node: Node) -> bool {
+        self.tag_tables
+            .get(&TypeId::of::<T>())
+            .is_some_and(|table| table.nodes.contains(&node))
+    }
 
-        scene.despawn(node);
+    /// Returns the nodes with the tag sorted by node.
+    pub fn tagged<T: Tag>(&self) -> Vec<Node> {
+        let mut nodes = self
+            .tag_tables
+            .get(&TypeId::of::<T>())
+            .map(|table| table.nodes.iter().copied().collect::<Vec<_>>())
+            .unwrap_or_default();
+        nodes.sort();
+        nodes
+    }
 
-        assert!(!scene.contains(node));
+    /// Returns the added and removed events for the given tag.
+    pub fn tag_events<T: Tag>(&self) -> &[ComponentEvent] {
+        self.tag_tables
+            .get(&TypeId::of::<T>())
+            .map(|table| table.events.as_slice())
+            .unwrap_or(&[])
     }
 
-    #[test]
-    fn despawn_get_parent_returns_none() {
-        let mut scene = Scene::new();
-        let parent = scene.spawn();
-        let node = scene.spawn();
-        scene.set_parent(node, parent);
+    /// Inserts the resource into the scene, replacing and returning the existing resource of the
+    /// same type.
+    pub fn insert_resource<T: 'static + Send + Sync>(&mut self, resource: T) -> Option<T> {
+        self.resources
+            .insert(TypeId::of::<T>(), Box::new(resource))
+            .map(|resource| *resource.downcast::<T>().unwrap())
+    }
 
-        scene.despawn(node);
+    /// Returns true if the scene contains a resource of the given type.
+    pub fn contains_resource<T: 'static + Send + Sync>(&self) -> bool {
+        self.resources.contains_key(&TypeId::of::<T>())
+    }
 
-        assert_eq!(scene.get_parent(node), None);
+    /// Returns the resource of the given type.
+    pub fn get_resource<T: 'static + Send + Sync>(&self) -> Option<&T> {
+        self.resources
+            .get(&TypeId::of::<T>())
+            .map(|resource| resource.downcast_ref::<T>().unwrap())
     }
 
-    #[test]
-    fn despawn_get_children_returns_none() {
-        let mut scene = Scene::new();
-        let parent = scene.spawn();
-        let node = scene.spawn();
-        scene.set_parent(node, parent);
+    /// Returns a mutable reference to the resource of the given type.
+    pub fn get_resource_mut<T: 'static + Send + Sync>(&mut self) -> Option<&mut T> {
+        self.resources
+            .get_mut(&TypeId::of::<T>())
+            .map(|resource| resource.downcast_mut::<T>().unwrap())
+    }
 
-        scene.despawn(node);
+    /// Removes and returns the resource of the given type.
+    pub fn remove_resource<T: 'static + Send + Sync>(&mut self) -> Option<T> {
+        self.resources
+            .remove(&TypeId::of::<T>())
+            .map(|resource| *resource.downcast::<T>().unwrap())
+    }
 
-        assert_eq!(scene.get_children(node), None);
+    /// Returns the spawned and despawned node events.
+    pub fn node_events(&self) -> &[NodeEvent] {
+        &self.node_events
     }
 
-    #[test]
-    fn despawn_parent_contains_returns_false() {
-        let mut scene = Scene::new();
-        let parent = scene.spawn();
-        let node = scene.spawn();
-        scene.set_parent(node, parent);
+    /// Clears the node events and the component and tag events for all the components and tags.
+    pub fn clear_events(&mut self) {
+        self.node_events.clear();
 
-        scene.despawn(parent);
+        for table in &mut self.component_tables {
+            table.clear_events();
+        }
 
-        assert!(!scene.contains(node));
+        for table in self.tag_tables.values_mut() {
+            table.events.clear();
+        }
     }
 
-    #[test]
-    fn despawn_parent_get_parent_returns_none() {
-        let mut scene = Scene::new();
+    fn table<T: Component>(&self) -> Option<&ComponentTable<T>> {
+        let component_index = self.component_indexes.get(&TypeId::of::<T>())?;
+        self.component_tables[*component_index]
+            .as_any()
+            .downcast_ref::<ComponentTable<T>>()
+    }
+
+    fn table_mut<T: Component>(&mut self) -> Option<&mut ComponentTable<T>> {
+        let component_index = self.component_indexes.get(&TypeId::of::<T>())?;
+        self.component_tables[*component_index]
+            .as_any_mut()
+            .downcast_mut::<ComponentTable<T>>()
+    }
+
+    fn table_or_insert<T: Component>(&mut self) -> &mut ComponentTable<T> {
+        let component_index = *self
+            .component_indexes
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| {
+                self.component_tables
+                    .push(Box::new(ComponentTable::<T>::new()));
+                self.component_tables.len() - 1
+            });
+
+        self.component_tables[component_index]
+            .as_any_mut()
+            .downcast_mut::<ComponentTable<T>>()
+            .unwrap()
+    }
+}
+
+/// # Component Mut
+///
+/// Mutable reference to a component value returned by [Scene::get_mut]. Emits a
+/// [ComponentEvent::Modified] event and stamps the change tick when dropped if the value was
+/// mutably dereferenced.
+pub struct ComponentMut<'a, T: Component> {
+    table: &'a mut ComponentTable<T>,
+    index: usize,
+    tick: u32,
+    modified: bool,
+}
+
+impl<T: Component> Deref for ComponentMut<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.table.items[self.index]
+    }
+}
+
+impl<T: Component> DerefMut for ComponentMut<'_, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.modified = true;
+        &mut self.table.items[self.index]
+    }
+}
+
+impl<T: Component> Drop for ComponentMut<'_, T> {
+    fn drop(&mut self) {
+        if self.modified {
+            self.table.mark_modified(self.index, self.tick);
+        }
+    }
+}
+
+/// # Bundle
+///
+/// Set of components added to a node together by [Scene::spawn_with] or [Scene::add_bundle].
+/// Implemented for every component, for tuples of up to twelve bundles, and for structs deriving
+/// `Bundle`.
+pub trait Bundle: 'static {
+    /// Adds the components of the bundle to the node.
+    fn add_to(self, scene: &mut Scene, node: Node);
+}
+
+impl<T: Component> Bundle for T {
+    fn add_to(self, scene: &mut Scene, node: Node) {
+        scene.add(node, self);
+    }
+}
+
+macro_rules! impl_bundle {
+    ($($name:ident),*) => {
+        impl<$($name: Bundle),*> Bundle for ($($name,)*) {
+            #[allow(non_snake_case)]
+            fn add_to(self, scene: &mut Scene, node: Node) {
+                let ($($name,)*) = self;
+                $($name.add_to(scene, node);)*
+            }
+        }
+    };
+}
+
+impl_bundle!(A);
+impl_bundle!(A, B);
+impl_bundle!(A, B, C);
+impl_bundle!(A, B, C, D);
+impl_bundle!(A, B, C, D, E);
+impl_bundle!(A, B, C, D, E, F);
+impl_bundle!(A, B, C, D, E, F, G);
+impl_bundle!(A, B, C, D, E, F, G, H);
+impl_bundle!(A, B, C, D, E, F, G, H, I);
+impl_bundle!(A, B, C, D, E, F, G, H, I, J);
+impl_bundle!(A, B, C, D, E, F, G, H, I, J, K);
+impl_bundle!(A, B, C, D, E, F, G, H, I, J, K, L);
+
+/// # Query
+///
+/// Set of components fetched together by [Scene::query]. Implemented for tuples of up to eight
+/// components.
+pub trait Query {
+    /// Item returned for each node with all of the queried components.
+    type Item<'a>;
+
+    /// Returns the items for all of the nodes with all of the queried components.
+    fn fetch(scene: &Scene) -> impl Iterator<Item = Self::Item<'_>>;
+
+    /// Returns the items for all of the nodes with all of the queried components that match the
+    /// filter.
+    fn fetch_filtered<F: QueryFilter>(scene: &Scene) -> impl Iterator<Item = Self::Item<'_>>;
+}
+
+macro_rules! impl_query {
+    ($first:ident $(, $rest:ident)*) => {
+        impl<$first: Component $(, $rest: Component)*> Query for ($first, $($rest,)*) {
+            type Item<'a> = (Node, &'a $first $(, &'a $rest)*);
+
+            fn fetch(scene: &Scene) -> impl Iterator<Item = Self::Item<'_>> {
+                Self::fetch_filtered::<()>(scene)
+            }
+
+            // The tables are looked up once, so each node of the first table only indexes the
+            // sparse arrays of the others.
+            #[allow(non_snake_case)]
+            fn fetch_filtered<Filter: QueryFilter>(
+                scene: &Scene,
+            ) -> impl Iterator<Item = Self::Item<'_>> {
+                let tables = (|| Some((scene.table::<$first>()?, $(scene.table::<$rest>()?,)*)))();
+
+                tables.into_iter().flat_map(move |($first, $($rest,)*)| {
+                    $first
+                        .nodes
+                        .iter()
+                        .zip(&$first.items)
+                        .filter(move |(node, _)| Filter::matches(scene, **node))
+                        .filter_map(move |(node, first)| {
+                            Some((*node, first, $($rest.get(*node)?,)*))
+                        })
+                })
+            }
+        }
+    };
+}
+
+impl_query!(A);
+impl_query!(A, B);
+impl_query!(A, B, C);
+impl_query!(A, B, C, D);
+impl_query!(A, B, C, D, E);
+impl_query!(A, B, C, D, E, F);
+impl_query!(A, B, C, D, E, F, G);
+impl_query!(A, B, C, D, E, F, G, H);
+
+impl Default for Scene {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    impl Component for u32 {}
+
+    impl Component for bool {}
+
+    #[test]
+    fn spawn_contains_returns_true() {
+        let mut scene = Scene::new();
+
+        let node = scene.spawn();
+
+        assert!(scene.contains(node));
+    }
+
+    #[test]
+    fn spawn_get_parent_returns_none() {
+        let mut scene = Scene::new();
+
+        let node = scene.spawn();
+
+        assert_eq!(scene.get_parent(node), None);
+    }
+
+    #[test]
+    fn spawn_get_children_returns_none() {
+        let mut scene = Scene::new();
+
+        let node = scene.spawn();
+
+        assert_eq!(scene.get_children(node), None);
+    }
+
+    #[test]
+    fn spawn_get_returns_none() {
+        let mut scene = Scene::new();
+
+        let node = scene.spawn();
+
+        assert_eq!(scene.get::<u32>(node), None);
+    }
+
+    #[test]
+    fn despawn_contains_returns_false() {
+        let mut scene = Scene::new();
+        let node = scene.spawn();
+
+        scene.despawn(node);
+
+        assert!(!scene.contains(node));
+    }
+
+    #[test]
+    fn despawn_get_parent_returns_none() {
+        let mut scene = Scene::new();
+        let parent = scene.spawn();
+        let node = scene.spawn();
+        scene.set_parent(node, parent);
+
+        scene.despawn(node);
+
+        assert_eq!(scene.get_parent(node), None);
+    }
+
+    #[test]
+    fn despawn_get_children_returns_none() {
+        let mut scene = Scene::new();
+        let parent = scene.spawn();
+        let node = scene.spawn();
+        scene.set_parent(node, parent);
+
+        scene.despawn(node);
+
+        assert_eq!(scene.get_children(node), None);
+    }
+
+    #[test]
+    fn despawn_parent_get_children_excludes_node() {
+        let mut scene = Scene::new();
+        let parent = scene.spawn();
+        let node = scene.spawn();
+        scene.set_parent(node, parent);
+
+        scene.despawn(node);
+
+        assert_eq!(scene.get_children(parent), Some([].as_slice()));
+    }
+
+    #[test]
+    fn despawn_parent_contains_returns_false() {
+        let mut scene = Scene::new();
+        let parent = scene.spawn();
+        let node = scene.spawn();
+        scene.set_parent(node, parent);
+
+        scene.despawn(parent);
+
+        assert!(!scene.contains(node));
+    }
+
+    #[test]
+    fn despawn_parent_get_parent_returns_none() {
+        let mut scene = Scene::new();
         let parent = scene.spawn();
         let node = scene.spawn();
         scene.set_parent(node, parent);
@@ -483,6 +1296,22 @@ mod tests {
         assert_eq!(scene.get::<u32>(node), None);
     }
 
+    #[test]
+    fn despawn_deep_hierarchy_contains_returns_false() {
+        let mut scene = Scene::new();
+        let root = scene.spawn();
+        let mut nodes = vec![root];
+        for _ in 0..100_000 {
+            let node = scene.spawn();
+            scene.set_parent(node, *nodes.last().unwrap());
+            nodes.push(node);
+        }
+
+        scene.despawn(root);
+
+        assert!(nodes.iter().all(|node| !scene.contains(*node)));
+    }
+
     #[test]
     fn set_parent_get_parent_returns_parent() {
         let mut scene = Scene::new();
@@ -563,7 +1392,21 @@ mod tests {
 
         scene.add(node, value);
 
-        assert_eq!(scene.get::<u32>(node), Some(value));
+        assert_eq!(scene.get::<u32>(node), Some(&value));
+    }
+
+    #[test]
+    fn add_despawned_node_get_returns_none() {
+        let mut scene = Scene::new();
+        let node = scene.spawn();
+        scene.despawn(node);
+        let new_node = scene.spawn();
+
+        scene.add(node, 17u32);
+        scene.add(new_node, 192u32);
+
+        assert_eq!(scene.get::<u32>(node), None);
+        assert_eq!(scene.get::<u32>(new_node), Some(&192));
     }
 
     #[test]
@@ -574,10 +1417,7 @@ mod tests {
 
         scene.add(node, value);
 
-        assert_eq!(
-            scene.events::<u32>().deref(),
-            &[ComponentEvent::Added(node)]
-        );
+        assert_eq!(scene.events::<u32>(), &[ComponentEvent::Added(node)]);
     }
 
     #[test]
@@ -590,7 +1430,7 @@ mod tests {
         let new_value = 192u32;
         scene.set(node, new_value);
 
-        assert_eq!(scene.get::<u32>(node), Some(new_value));
+        assert_eq!(scene.get::<u32>(node), Some(&new_value));
     }
 
     #[test]
@@ -604,24 +1444,146 @@ mod tests {
         scene.set(node, new_value);
 
         assert_eq!(
-            scene.events::<u32>().deref(),
+            scene.events::<u32>(),
             &[ComponentEvent::Added(node), ComponentEvent::Modified(node)]
         );
     }
 
     #[test]
-    fn set_existing_value_events_does_not_return_modified_event() {
+    fn set_existing_value_events_returns_modified_event() {
         let mut scene = Scene::new();
         let node = scene.spawn();
         let value = 17u32;
         scene.add(node, value);
+        let since = scene.advance_change_tick();
 
         scene.set(node, value);
 
         assert_eq!(
-            scene.events::<u32>().deref(),
-            &[ComponentEvent::Added(node)]
+            scene.events::<u32>(),
+            &[ComponentEvent::Added(node), ComponentEvent::Modified(node)]
         );
+        assert!(scene.is_changed::<u32>(node, since));
+    }
+
+    #[test]
+    fn set_if_changed_existing_value_events_does_not_return_modified_event() {
+        let mut scene = Scene::new();
+        let node = scene.spawn();
+        let value = 17u32;
+        scene.add(node, value);
+        let since = scene.advance_change_tick();
+
+        scene.set_if_changed(node, value);
+        assert_eq!(scene.events::<u32>(), &[ComponentEvent::Added(node)]);
+        assert!(!scene.is_changed::<u32>(node, since));
+
+        scene.set_if_changed(node, 192u32);
+        assert_eq!(scene.get::<u32>(node), Some(&192));
+        assert!(scene.is_changed::<u32>(node, since));
+    }
+
+    #[test]
+    fn advance_change_tick_drops_events_of_older_ticks() {
+        let mut scene = Scene::new();
+        let node = scene.spawn_with(17u32);
+        scene.tag::<Selected>(node);
+        scene.advance_change_tick();
+        scene.set(node, 192u32);
+        assert_eq!(scene.node_events(), &[NodeEvent::Spawned(node)]);
+
+        scene.advance_change_tick();
+        assert!(scene.node_events().is_empty());
+        assert!(scene.tag_events::<Selected>().is_empty());
+        assert_eq!(scene.events::<u32>(), &[ComponentEvent::Modified(node)]);
+
+        scene.advance_change_tick();
+        assert!(scene.events::<u32>().is_empty());
+    }
+
+    #[test]
+    fn spawn_batch_contains_returns_true() {
+        let mut scene = Scene::new();
+        let despawned = scene.spawn();
+        scene.despawn(despawned);
+
+        let nodes = scene.spawn_batch(3);
+
+        assert_eq!(nodes.len(), 3);
+        assert!(nodes.iter().all(|node| scene.contains(*node)));
+        assert!(!scene.contains(despawned));
+        assert_eq!(nodes[0].index(), despawned.index());
+        assert_ne!(scene.spawn(), nodes[2]);
+    }
+
+    #[test]
+    fn add_batch_get_returns_values() {
+        let mut scene = Scene::new();
+        let nodes = scene.spawn_batch(3);
+        scene.despawn(nodes[2]);
+
+        scene.add_batch(nodes.iter().map(|node| (*node, node.index())));
+
+        assert_eq!(scene.get::<u32>(nodes[0]), Some(&0));
+        assert_eq!(scene.get::<u32>(nodes[1]), Some(&1));
+        assert_eq!(scene.get::<u32>(nodes[2]), None);
+        assert_eq!(
+            scene.events::<u32>(),
+            &[
+                ComponentEvent::Added(nodes[0]),
+                ComponentEvent::Added(nodes[1])
+            ]
+        );
+    }
+
+    #[test]
+    fn is_added_returns_true_after_tick() {
+        let mut scene = Scene::new();
+        let node = scene.spawn();
+        let since = scene.advance_change_tick();
+
+        scene.add(node, 17u32);
+
+        assert!(scene.is_added::<u32>(node, since));
+        assert!(scene.is_changed::<u32>(node, since));
+        let since = scene.advance_change_tick();
+        assert!(!scene.is_added::<u32>(node, since));
+    }
+
+    #[test]
+    fn is_changed_set_returns_true_after_tick() {
+        let mut scene = Scene::new();
+        let node = scene.spawn_with(17u32);
+        let since = scene.advance_change_tick();
+        assert!(!scene.is_changed::<u32>(node, since));
+
+        scene.set(node, 192u32);
+
+        assert!(scene.is_changed::<u32>(node, since));
+        assert!(!scene.is_added::<u32>(node, since));
+    }
+
+    #[test]
+    fn is_changed_get_mut_returns_true_only_when_mutated() {
+        let mut scene = Scene::new();
+        let node = scene.spawn_with(17u32);
+        let since = scene.advance_change_tick();
+
+        assert_eq!(scene.get_mut::<u32>(node).as_deref(), Some(&17));
+        assert!(!scene.is_changed::<u32>(node, since));
+
+        *scene.get_mut::<u32>(node).unwrap() = 192;
+        assert!(scene.is_changed::<u32>(node, since));
+    }
+
+    #[test]
+    fn is_changed_removed_component_returns_false() {
+        let mut scene = Scene::new();
+        let node = scene.spawn_with(17u32);
+
+        scene.remove::<u32>(node);
+
+        assert!(!scene.is_changed::<u32>(node, 0));
     }
 
     #[test]
@@ -646,7 +1608,7 @@ mod tests {
         scene.remove::<u32>(node);
 
         assert_eq!(
-            scene.events::<u32>().deref(),
+            scene.events::<u32>(),
             &[ComponentEvent::Added(node), ComponentEvent::Removed(node)]
         );
     }
@@ -660,6 +1622,468 @@ mod tests {
 
         scene.clear_events();
 
-        assert_eq!(scene.events::<u32>().deref(), &[]);
+        assert!(scene.events::<u32>().is_empty());
+    }
+
+    #[test]
+    fn query_returns_nodes_with_all_components() {
+        let mut scene = Scene::new();
+        let node = scene.spawn();
+        let other = scene.spawn();
+        scene.add(node, 17u32);
+        scene.add(node, true);
+        scene.add(other, 192u32);
+
+        let items = scene.query::<(u32, bool)>().collect::<Vec<_>>();
+
+        assert_eq!(items, [(node, &17u32, &true)]);
+    }
+
+    #[test]
+    fn query_after_remove_returns_remaining_nodes() {
+        let mut scene = Scene::new();
+        let first = scene.spawn();
+        let second = scene.spawn();
+        let third = scene.spawn();
+        scene.add(first, 1u32);
+        scene.add(second, 2u32);
+        scene.add(third, 3u32);
+
+        scene.remove::<u32>(first);
+
+        let items = scene.query::<(u32,)>().collect::<Vec<_>>();
+
+        assert_eq!(items, [(third, &3u32), (second, &2u32)]);
+    }
+
+    #[test]
+    fn get_mut_get_returns_new_value() {
+        let mut scene = Scene::new();
+        let node = scene.spawn();
+        scene.add(node, 17u32);
+
+        *scene.get_mut::<u32>(node).unwrap() = 192;
+
+        assert_eq!(scene.get::<u32>(node), Some(&192));
+    }
+
+    #[test]
+    fn get_mut_events_returns_modified_event() {
+        let mut scene = Scene::new();
+        let node = scene.spawn();
+        scene.add(node, 17u32);
+
+        *scene.get_mut::<u32>(node).unwrap() += 1;
+
+        assert_eq!(
+            scene.events::<u32>(),
+            &[ComponentEvent::Added(node), ComponentEvent::Modified(node)]
+        );
+    }
+
+    #[test]
+    fn get_mut_read_only_events_does_not_return_modified_event() {
+        let mut scene = Scene::new();
+        let node = scene.spawn();
+        scene.add(node, 17u32);
+
+        assert_eq!(*scene.get_mut::<u32>(node).unwrap(), 17);
+
+        assert_eq!(scene.events::<u32>(), &[ComponentEvent::Added(node)]);
+    }
+
+    #[test]
+    fn get_mut_missing_component_returns_none() {
+        let mut scene = Scene::new();
+        let node = scene.spawn();
+
+        assert!(scene.get_mut::<u32>(node).is_none());
+    }
+
+    #[test]
+    fn spawn_in_different_scenes_returns_equal_nodes() {
+        let mut scene = Scene::new();
+        let mut other = Scene::new();
+
+        assert_eq!(scene.spawn(), other.spawn());
+    }
+
+    #[test]
+    fn stale_node_contains_returns_false() {
+        let mut scene = Scene::new();
+        let node = scene.spawn();
+        let stale = Node::new(node.index(), node.generation() + 1);
+
+        assert!(!scene.contains(stale));
+    }
+
+    #[test]
+    fn despawn_spawn_reuses_index() {
+        let mut scene = Scene::new();
+        let node = scene.spawn();
+
+        scene.despawn(node);
+        let new_node = scene.spawn();
+
+        assert_eq!(new_node.index(), node.index());
+        assert_ne!(new_node.generation(), node.generation());
+    }
+
+    #[test]
+    fn despawn_spawn_contains_despawned_returns_false() {
+        let mut scene = Scene::new();
+        let node = scene.spawn();
+        scene.add(node, 17u32);
+
+        scene.despawn(node);
+        let new_node = scene.spawn();
+        scene.add(new_node, 192u32);
+
+        assert!(!scene.contains(node));
+        assert_eq!(scene.get::<u32>(node), None);
+    }
+
+    #[test]
+    fn save_load_restores_hierarchy_and_components() {
+        let mut scene = Scene::new();
+        scene.register::<u32>("u32");
+        let parent = scene.spawn();
+        let node = scene.spawn();
+        scene.set_parent(node, parent);
+        scene.add(parent, 17u32);
+        scene.add(node, LocalTransform::from_position(glam::Vec3::X));
+        scene.add(node, true);
+
+        let source = scene.save().unwrap();
+        let mut loaded = Scene::new();
+        loaded.register::<u32>("u32");
+        let nodes = loaded.load(&source).unwrap();
+
+        assert_eq!(nodes.len(), 2);
+        assert_eq!(loaded.get_parent(nodes[1]), Some(nodes[0]));
+        assert_eq!(loaded.get::<u32>(nodes[0]), Some(&17));
+        assert_eq!(
+            loaded.get::<LocalTransform>(nodes[1]),
+            Some(&LocalTransform::from_position(glam::Vec3::X))
+        );
+        assert_eq!(loaded.get::<bool>(nodes[1]), None);
+    }
+
+    #[test]
+    fn serialize_deserialize_json_restores_components() {
+        let mut scene = Scene::new();
+        let node = scene.spawn();
+        scene.add(node, Visibility::Invisible);
+
+        let mut output = Vec::new();
+        scene
+            .serialize(&mut serde_json::Serializer::new(&mut output))
+            .unwrap();
+        let mut loaded = Scene::new();
+        let nodes = loaded
+            .deserialize(&mut serde_json::Deserializer::from_slice(&output))
+            .unwrap();
+
+        assert_eq!(
+            loaded.get::<Visibility>(nodes[0]),
+            Some(&Visibility::Invisible)
+        );
+    }
+
+    #[test]
+    fn load_unregistered_component_returns_error() {
+        let mut scene = Scene::new();
+        scene.register::<u32>("u32");
+        let node = scene.spawn();
+        scene.add(node, 17u32);
+
+        let source = scene.save().unwrap();
+
+        assert!(Scene::new().load(&source).is_err());
+    }
+
+    #[test]
+    fn load_failure_despawns_loaded_nodes() {
+        let mut scene = Scene::new();
+        let existing = scene.spawn();
+
+        let unregistered =
+            r#"(nodes: [(parent: None, components: {}), (components: {"unknown": 1})])"#;
+        let invalid_parent =
+            "(nodes: [(parent: None, components: {}), (parent: Some(5), components: {})])";
+        assert!(scene.load(unregistered).is_err());
+        assert!(scene.load(invalid_parent).is_err());
+
+        assert_eq!(scene.get_root_nodes().collect::<Vec<_>>(), [existing]);
+    }
+
+    #[test]
+    fn find_by_name_returns_first_named_node() {
+        let mut scene = Scene::new();
+        let first = scene.spawn();
+        let second = scene.spawn();
+        scene.add(first, Name::new("Player"));
+        scene.add(second, Name::new("Player"));
+
+        assert_eq!(scene.find_by_name("Player"), Some(first));
+        assert_eq!(scene.find_by_name("Enemy"), None);
+    }
+
+    #[test]
+    fn find_all_by_name_returns_named_nodes() {
+        let mut scene = Scene::new();
+        let first = scene.spawn();
+        let other = scene.spawn();
+        let second = scene.spawn();
+        scene.add(first, Name::new("Tree"));
+        scene.add(other, Name::new("Rock"));
+        scene.add(second, Name::new("Tree"));
+
+        let nodes = scene.find_all_by_name("Tree").collect::<Vec<_>>();
+
+        assert_eq!(nodes, [first, second]);
+    }
+
+    #[test]
+    fn derive_component_add_get_returns_value() {
+        #[derive(Clone, Debug, PartialEq, crate::Component)]
+        struct Health<T>(T);
+
+        let mut scene = Scene::new();
+        let node = scene.spawn();
+
+        scene.add(node, Health(17u32));
+
+        assert_eq!(scene.get::<Health<u32>>(node), Some(&Health(17)));
+    }
+
+    struct Selected;
+
+    impl Tag for Selected {}
+
+    #[test]
+    fn tag_has_tag_returns_true() {
+        let mut scene = Scene::new();
+        let node = scene.spawn();
+
+        scene.tag::<Selected>(node);
+
+        assert!(scene.has_tag::<Selected>(node));
+        assert_eq!(scene.tagged::<Selected>(), [node]);
+    }
+
+    #[test]
+    fn untag_has_tag_returns_false() {
+        let mut scene = Scene::new();
+        let node = scene.spawn();
+        scene.tag::<Selected>(node);
+
+        scene.untag::<Selected>(node);
+
+        assert!(!scene.has_tag::<Selected>(node));
+    }
+
+    #[test]
+    fn tag_twice_events_returns_single_added_event() {
+        let mut scene = Scene::new();
+        let node = scene.spawn();
+
+        scene.tag::<Selected>(node);
+        scene.tag::<Selected>(node);
+
+        assert_eq!(
+            scene.tag_events::<Selected>(),
+            &[ComponentEvent::Added(node)]
+        );
+    }
+
+    #[test]
+    fn despawn_tag_events_returns_removed_event() {
+        let mut scene = Scene::new();
+        let node = scene.spawn();
+        scene.tag::<Selected>(node);
+
+        scene.despawn(node);
+
+        assert!(!scene.has_tag::<Selected>(node));
+        assert_eq!(
+            scene.tag_events::<Selected>(),
+            &[ComponentEvent::Added(node), ComponentEvent::Removed(node)]
+        );
+    }
+
+    #[test]
+    fn insert_resource_get_resource_returns_resource() {
+        let mut scene = Scene::new();
+
+        scene.insert_resource(17u32);
+
+        assert!(scene.contains_resource::<u32>());
+        assert_eq!(scene.get_resource::<u32>(), Some(&17));
+    }
+
+    #[test]
+    fn insert_resource_existing_returns_previous_resource() {
+        let mut scene = Scene::new();
+        scene.insert_resource(17u32);
+
+        assert_eq!(scene.insert_resource(192u32), Some(17));
+        assert_eq!(scene.get_resource::<u32>(), Some(&192));
+    }
+
+    #[test]
+    fn get_resource_mut_get_resource_returns_new_value() {
+        let mut scene = Scene::new();
+        scene.insert_resource(17u32);
+
+        *scene.get_resource_mut::<u32>().unwrap() = 192;
+
+        assert_eq!(scene.get_resource::<u32>(), Some(&192));
+    }
+
+    #[test]
+    fn remove_resource_get_resource_returns_none() {
+        let mut scene = Scene::new();
+        scene.insert_resource(17u32);
+
+        assert_eq!(scene.remove_resource::<u32>(), Some(17));
+        assert_eq!(scene.get_resource::<u32>(), None);
+    }
+
+    #[test]
+    fn spawn_with_tuple_get_returns_values() {
+        let mut scene = Scene::new();
+
+        let node = scene.spawn_with((17u32, true));
+
+        assert_eq!(scene.get::<u32>(node), Some(&17));
+        assert_eq!(scene.get::<bool>(node), Some(&true));
+    }
+
+    #[test]
+    fn spawn_with_derived_bundle_get_returns_values() {
+        #[derive(crate::Bundle)]
+        struct Unit {
+            health: u32,
+            extra: (bool, Name),
+        }
+
+        let mut scene = Scene::new();
+
+        let node = scene.spawn_with(Unit {
+            health: 17,
+            extra: (true, Name::new("Unit")),
+        });
+
+        assert_eq!(scene.get::<u32>(node), Some(&17));
+        assert_eq!(scene.get::<bool>(node), Some(&true));
+        assert_eq!(scene.find_by_name("Unit"), Some(node));
+    }
+
+    #[test]
+    fn spawn_node_events_returns_spawned_event() {
+        let mut scene = Scene::new();
+
+        let node = scene.spawn();
+
+        assert_eq!(scene.node_events(), &[NodeEvent::Spawned(node)]);
+    }
+
+    #[test]
+    fn despawn_parent_node_events_returns_despawned_events() {
+        let mut scene = Scene::new();
+        let parent = scene.spawn();
+        let node = scene.spawn();
+        scene.set_parent(node, parent);
+        scene.clear_events();
+
+        scene.despawn(parent);
+
+        assert_eq!(
+            scene.node_events(),
+            &[NodeEvent::Despawned(parent), NodeEvent::Despawned(node)]
+        );
+    }
+
+    #[test]
+    fn clear_events_node_events_returns_empty() {
+        let mut scene = Scene::new();
+        scene.spawn();
+
+        scene.clear_events();
+
+        assert!(scene.node_events().is_empty());
+    }
+
+    #[test]
+    fn duplicate_copies_components_tags_and_descendants() {
+        let mut scene = Scene::new();
+        let parent = scene.spawn();
+        let node = scene.spawn_with(17u32);
+        let child = scene.spawn_with(true);
+        scene.set_parent(node, parent);
+        scene.set_parent(child, node);
+        scene.tag::<Selected>(node);
+
+        let copy = scene.duplicate(node).unwrap();
+
+        assert_ne!(copy, node);
+        assert_eq!(scene.get::<u32>(copy), Some(&17));
+        assert!(scene.has_tag::<Selected>(copy));
+        assert_eq!(scene.get_parent(copy), Some(parent));
+        assert_eq!(scene.get_children(parent), Some([node, copy].as_slice()));
+        let copy_child = scene.get_children(copy).unwrap()[0];
+        assert_ne!(copy_child, child);
+        assert_eq!(scene.get::<bool>(copy_child), Some(&true));
+    }
+
+    #[test]
+    fn duplicate_despawned_node_returns_none() {
+        let mut scene = Scene::new();
+        let node = scene.spawn();
+        scene.despawn(node);
+
+        assert_eq!(scene.duplicate(node), None);
+    }
+
+    #[test]
+    fn clear_removes_nodes_and_emits_events() {
+        let mut scene = Scene::new();
+        let parent = scene.spawn_with(17u32);
+        let node = scene.spawn();
+        scene.set_parent(node, parent);
+        scene.tag::<Selected>(node);
+        scene.insert_resource(true);
+        scene.clear_events();
+
+        scene.clear();
+
+        assert!(!scene.contains(parent));
+        assert!(!scene.contains(node));
+        assert_eq!(scene.get_root_nodes().count(), 0);
+        assert_eq!(scene.get::<u32>(parent), None);
+        assert!(!scene.has_tag::<Selected>(node));
+        assert_eq!(scene.get_resource::<bool>(), Some(&true));
+        assert_eq!(
+            scene.node_events(),
+            &[NodeEvent::Despawned(parent), NodeEvent::Despawned(node)]
+        );
+        assert_eq!(scene.events::<u32>(), &[ComponentEvent::Removed(parent)]);
+        assert_eq!(
+            scene.tag_events::<Selected>(),
+            &[ComponentEvent::Removed(node)]
+        );
+    }
+
+    #[test]
+    fn clear_spawn_does_not_alias_cleared_nodes() {
+        let mut scene = Scene::new();
+        let node = scene.spawn_with(17u32);
+
+        scene.clear();
+        let new_node = scene.spawn();
+
+        assert_ne!(new_node, node);
+        assert!(!scene.contains(node));
     }
 }
@@ -0,0 +1,168 @@
+use std::any::TypeId;
+
+use crate::scene::ComponentTable;
+use crate::scene::DynamicComponentTable;
+use crate::Component;
+use crate::ComponentMut;
+use crate::Node;
+use crate::Scene;
+
+/// Returns the index of the component table of the given type, inserting an empty table if the
+/// scene has none yet.
+pub(crate) type TableIndex = fn(&mut Scene) -> usize;
+
+/// # Component Writes
+///
+/// Component tables a system has exclusive access to while it runs, possibly in parallel with other
+/// systems. See [crate::systems::SystemConfig::writes]. The tables are taken out of the scene while
+/// the system runs, so the written components must be accessed through this rather than the scene.
+pub struct ComponentWrites {
+    tables: Vec<(TypeId, usize, Box<dyn DynamicComponentTable>)>,
+    tick: u32,
+}
+
+impl ComponentWrites {
+    /// Returns the value of the component for the given node.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the system didn't declare that it writes the component.
+    pub fn get<T: Component>(&self, node: Node) -> Option<&T> {
+        self.table::<T>().get(node)
+    }
+
+    /// Returns a mutable reference to the value of the component for the given node.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the system didn't declare that it writes the component.
+    pub fn get_mut<T: Component>(&mut self, node: Node) -> Option<ComponentMut<'_, T>> {
+        let tick = self.tick;
+        let table = self.table_mut::<T>();
+        table.index(node).map(|index| ComponentMut {
+            table,
+            index,
+            tick,
+            modified: false,
+        })
+    }
+
+    /// Returns all of the nodes with the component along with references to the component values.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the system didn't declare that it writes the component.
+    pub fn iter<T: Component>(&self) -> impl Iterator<Item = (Node, &T)> {
+        let table = self.table::<T>();
+        table.nodes.iter().copied().zip(&table.items)
+    }
+
+    /// Returns all of the nodes with the component along with mutable references to the component
+    /// values. Every component value is marked as modified.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the system didn't declare that it writes the component.
+    pub fn iter_mut<T: Component>(&mut self) -> impl Iterator<Item = (Node, &mut T)> {
+        let tick = self.tick;
+        let table = self.table_mut::<T>();
+        for index in 0..table.nodes.len() {
+            table.mark_modified(index, tick);
+        }
+
+        table.nodes.iter().copied().zip(&mut table.items)
+    }
+
+    fn table<T: Component>(&self) -> &ComponentTable<T> {
+        self.tables
+            .iter()
+            .find(|(type_id, _, _)| *type_id == TypeId::of::<T>())
+            .and_then(|(_, _, table)| table.as_any().downcast_ref())
+            .unwrap_or_else(|| undeclared::<T>())
+    }
+
+    fn table_mut<T: Component>(&mut self) -> &mut ComponentTable<T> {
+        self.tables
+            .iter_mut()
+            .find(|(type_id, _, _)| *type_id == TypeId::of::<T>())
+            .and_then(|(_, _, table)| table.as_any_mut().downcast_mut())
+            .unwrap_or_else(|| undeclared::<T>())
+    }
+}
+
+fn undeclared<T>() -> ! {
+    panic!(
+        "system doesn't declare that it writes `{}`",
+        std::any::type_name::<T>()
+    )
+}
+
+impl Scene {
+    /// Returns the index of the component table of the given type, inserting an empty table if
+    /// the scene has none yet.
+    pub(crate) fn table_index<T: Component>(&mut self) -> usize {
+        self.table_or_insert::<T>();
+        self.component_indexes[&TypeId::of::<T>()]
+    }
+
+    /// Takes the component tables out of the scene, leaving empty tables in their place until
+    /// they're returned by [Scene::return_writes].
+    pub(crate) fn take_writes(&mut self, writes: &[(TypeId, TableIndex)]) -> ComponentWrites {
+        let tables = writes
+            .iter()
+            .map(|(type_id, table_index)| {
+                let index = table_index(self);
+                let empty = self.component_tables[index].empty_table();
+                let table = std::mem::replace(&mut self.component_tables[index], empty);
+                (*type_id, index, table)
+            })
+            .collect();
+
+        ComponentWrites {
+            tables,
+            tick: self.change_tick,
+        }
+    }
+
+    /// Returns the component tables taken by [Scene::take_writes] to the scene.
+    pub(crate) fn return_writes(&mut self, writes: ComponentWrites) {
+        for (_, index, table) in writes.tables {
+            self.component_tables[index] = table;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ComponentEvent;
+
+    #[test]
+    fn take_writes_moves_tables_until_returned() {
+        let mut scene = Scene::new();
+        let node = scene.spawn_with((17u32, true));
+        scene.clear_events();
+
+        let mut writes =
+            scene.take_writes(&[(TypeId::of::<u32>(), Scene::table_index::<u32> as TableIndex)]);
+        *writes.get_mut::<u32>(node).unwrap() += 1;
+
+        assert_eq!(scene.get::<u32>(node), None);
+        assert_eq!(scene.get::<bool>(node), Some(&true));
+
+        scene.return_writes(writes);
+
+        assert_eq!(scene.get::<u32>(node), Some(&18));
+        assert_eq!(scene.events::<u32>(), &[ComponentEvent::Modified(node)]);
+    }
+
+    #[test]
+    #[should_panic(expected = "doesn't declare")]
+    fn get_undeclared_component_panics() {
+        let mut scene = Scene::new();
+        let node = scene.spawn_with(17u32);
+
+        let writes = scene.take_writes(&[]);
+        writes.get::<u32>(node);
+    }
+}
@@ -0,0 +1,254 @@
+use crate::Bundle;
+use crate::Component;
+use crate::Node;
+use crate::Scene;
+
+type Command = Box<dyn FnOnce(&mut Scene, &mut Vec<Node>)>;
+
+/// # Commands
+///
+/// Buffer of structural changes recorded while the scene is borrowed (e.g. while iterating a query
+/// or events) and applied later with [Scene::apply].
+///
+/// ```
+/// # use pulse::Commands;
+/// # use pulse::LocalTransform;
+/// # use pulse::Scene;
+/// # use pulse::Visibility;
+/// let mut scene = Scene::new();
+/// let node = scene.spawn_with(Visibility::Invisible);
+///
+/// let mut commands = Commands::new();
+/// for (node, visibility) in scene.query::<(Visibility,)>() {
+///     if *visibility == Visibility::Invisible {
+///         commands.despawn(node);
+///     }
+/// }
+///
+/// scene.apply(commands);
+/// assert!(!scene.contains(node));
+/// ```
+#[derive(Default)]
+pub struct Commands {
+    commands: Vec<Command>,
+    spawned: usize,
+}
+
+/// # Command Node
+///
+/// Node targeted by a command, either an existing node converted with [From], or a node spawned
+/// earlier in the same [Commands] buffer returned by [Commands::spawn].
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct CommandNode(Target);
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum Target {
+    /// Existing node in the scene.
+    Existing(Node),
+    /// Node spawned by the command buffer, identified by the order it was spawned in.
+    Spawned(usize),
+}
+
+impl CommandNode {
+    /// Returns the node, or none if it was spawned by another buffer that spawned more nodes.
+    fn resolve(self, spawned: &[Node]) -> Option<Node> {
+        match self.0 {
+            Target::Existing(node) => Some(node),
+            Target::Spawned(index) => spawned.get(index).copied(),
+        }
+    }
+}
+
+impl From<Node> for CommandNode {
+    fn from(node: Node) -> Self {
+        Self(Target::Existing(node))
+    }
+}
+
+impl Commands {
+    /// Returns an empty command buffer.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of recorded commands.
+    pub fn len(&self) -> usize {
+        self.commands.len()
+    }
+
+    /// Returns true if no commands have been recorded.
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    /// Records spawning a new node and returns a handle that later commands can target.
+    pub fn spawn(&mut self) -> CommandNode {
+        let node = CommandNode(Target::Spawned(self.spawned));
+        self.spawned += 1;
+        self.push(|scene, spawned| spawned.push(scene.spawn()));
+        node
+    }
+
+    /// Records spawning a new node with the components of the bundle.
+    pub fn spawn_with<B: Bundle>(&mut self, bundle: B) -> CommandNode {
+        let node = self.spawn();
+        self.add(node, bundle);
+        node
+    }
+
+    /// Records despawning the node.
+    pub fn despawn(&mut self, node: impl Into<CommandNode>) {
+        let node = node.into();
+        self.push(move |scene, spawned| {
+            if let Some(node) = node.resolve(spawned) {
+                scene.despawn(node);
+            }
+        });
+    }
+
+    /// Records adding the components of the bundle to the node.
+    pub fn add<B: Bundle>(&mut self, node: impl Into<CommandNode>, bundle: B) {
+        let node = node.into();
+        self.push(move |scene, spawned| {
+            if let Some(node) = node.resolve(spawned).filter(|&node| scene.contains(node)) {
+                scene.add_bundle(node, bundle);
+            }
+        });
+    }
+
+    /// Records setting the component value of the node or adding the component.
+    pub fn set_or_add<T: Component>(&mut self, node: impl Into<CommandNode>, value: T) {
+        let node = node.into();
+        self.push(move |scene, spawned| {
+            if let Some(node) = node.resolve(spawned).filter(|&node| scene.contains(node)) {
+                scene.set_or_add(node, value);
+            }
+        });
+    }
+
+    /// Records removing the component from the node.
+    pub fn remove<T: Component>(&mut self, node: impl Into<CommandNode>) {
+        let node = node.into();
+        self.push(move |scene, spawned| {
+            if let Some(node) = node.resolve(spawned) {
+                scene.remove::<T>(node);
+            }
+        });
+    }
+
+    /// Records setting the parent of the node.
+    pub fn set_parent(&mut self, node: impl Into<CommandNode>, parent: impl Into<CommandNode>) {
+        let node = node.into();
+        let parent = parent.into();
+        self.push(move |scene, spawned| {
+            if let (Some(node), Some(parent)) = (node.resolve(spawned), parent.resolve(spawned)) {
+                scene.set_parent(node, parent);
+            }
+        });
+    }
+
+    /// Records removing the parent of the node.
+    pub fn remove_parent(&mut self, node: impl Into<CommandNode>) {
+        let node = node.into();
+        self.push(move |scene, spawned| {
+            if let Some(node) = node.resolve(spawned) {
+                scene.remove_parent(node);
+            }
+        });
+    }
+
+    /// Records an arbitrary change to the scene.
+    pub fn run(&mut self, command: impl 'static + FnOnce(&mut Scene)) {
+        self.push(move |scene, _| command(scene));
+    }
+
+    fn push(&mut self, command: impl 'static + FnOnce(&mut Scene, &mut Vec<Node>)) {
+        self.commands.push(Box::new(command));
+    }
+}
+
+impl Scene {
+    /// Applies the recorded commands in order and returns the nodes spawned by the commands.
+    pub fn apply(&mut self, commands: Commands) -> Vec<Node> {
+        let mut spawned = Vec::with_capacity(commands.spawned);
+        for command in commands.commands {
+            command(self, &mut spawned);
+        }
+
+        spawned
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn apply_spawn_with_set_parent_builds_hierarchy() {
+        let mut scene = Scene::new();
+        let parent = scene.spawn();
+        let mut commands = Commands::new();
+
+        let child = commands.spawn_with(17u32);
+        commands.set_parent(child, parent);
+        let spawned = scene.apply(commands);
+
+        assert_eq!(spawned.len(), 1);
+        assert_eq!(scene.get::<u32>(spawned[0]), Some(&17));
+        assert_eq!(scene.get_parent(spawned[0]), Some(parent));
+    }
+
+    #[test]
+    fn apply_remove_and_despawn_while_iterating_query() {
+        let mut scene = Scene::new();
+        let first = scene.spawn_with(1u32);
+        let second = scene.spawn_with((2u32, true));
+        let mut commands = Commands::new();
+
+        for (node, value) in scene.query::<(u32,)>() {
+            if *value == 1 {
+                commands.despawn(node);
+            } else {
+                commands.remove::<bool>(node);
+            }
+        }
+        scene.apply(commands);
+
+        assert!(!scene.contains(first));
+        assert_eq!(scene.get::<bool>(second), None);
+    }
+
+    #[test]
+    fn apply_skips_nodes_spawned_by_other_buffers() {
+        let mut scene = Scene::new();
+        let parent = scene.spawn();
+        let mut other = Commands::new();
+        other.spawn();
+        let foreign = other.spawn();
+        let mut commands = Commands::new();
+
+        commands.spawn();
+        commands.add(foreign, 17u32);
+        commands.set_parent(foreign, parent);
+        commands.despawn(foreign);
+        let spawned = scene.apply(commands);
+
+        assert_eq!(spawned.len(), 1);
+        assert!(scene.contains(parent));
+        assert_eq!(scene.query::<(u32,)>().count(), 0);
+        assert_eq!(scene.get_children(parent), None);
+    }
+
+    #[test]
+    fn apply_add_to_despawned_node_does_not_add_component() {
+        let mut scene = Scene::new();
+        let node = scene.spawn();
+        let mut commands = Commands::new();
+
+        commands.despawn(node);
+        commands.add(node, 17u32);
+        scene.apply(commands);
+
+        assert_eq!(scene.get::<u32>(node), None);
+    }
+}
@@ -0,0 +1,78 @@
+use std::ops::Deref;
+
+/// Events recorded since the change tick before the current one. Older events are dropped when
+/// the change tick advances, so the buffer stays bounded even if the events are never cleared.
+#[derive(Clone, Debug)]
+pub(super) struct EventBuffer<E> {
+    events: Vec<E>,
+    /// Number of events recorded before the last [EventBuffer::advance].
+    previous: usize,
+}
+
+impl<E> EventBuffer<E> {
+    pub(super) const fn new() -> Self {
+        Self {
+            events: Vec::new(),
+            previous: 0,
+        }
+    }
+
+    pub(super) fn push(&mut self, event: E) {
+        self.events.push(event);
+    }
+
+    pub(super) fn reserve(&mut self, additional: usize) {
+        self.events.reserve(additional);
+    }
+
+    pub(super) fn clear(&mut self) {
+        self.events.clear();
+        self.previous = 0;
+    }
+
+    /// Drops the events recorded before the previous advance and keeps the ones recorded since.
+    pub(super) fn advance(&mut self) {
+        self.events.drain(..self.previous);
+        self.previous = self.events.len();
+    }
+}
+
+impl<E> Default for EventBuffer<E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<E> Deref for EventBuffer<E> {
+    type Target = Vec<E>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.events
+    }
+}
+
+impl<E> Extend<E> for EventBuffer<E> {
+    fn extend<I: IntoIterator<Item = E>>(&mut self, iter: I) {
+        self.events.extend(iter);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn advance_keeps_events_of_one_tick() {
+        let mut events = EventBuffer::new();
+        events.push(1);
+        events.advance();
+        events.push(2);
+        assert_eq!(events.as_slice(), &[1, 2]);
+
+        events.advance();
+        assert_eq!(events.as_slice(), &[2]);
+
+        events.advance();
+        assert!(events.is_empty());
+    }
+}
@@ -0,0 +1,167 @@
+use std::marker::PhantomData;
+
+use crate::Component;
+use crate::Node;
+use crate::Query;
+use crate::Scene;
+
+/// # Query Filter
+///
+/// Condition on a node checked by [Scene::query_filtered] without fetching any component values.
+/// Implemented for [With], [Without], [Added], [Changed], and tuples of up to eight filters, which
+/// match when all of their filters match.
+pub trait QueryFilter {
+    /// Returns true if the node matches the filter.
+    fn matches(scene: &Scene, node: Node) -> bool;
+}
+
+/// # With
+///
+/// Filter matching nodes with the component.
+pub struct With<T>(PhantomData<T>);
+
+impl<T: Component> QueryFilter for With<T> {
+    fn matches(scene: &Scene, node: Node) -> bool {
+        scene.get::<T>(node).is_some()
+    }
+}
+
+/// # Without
+///
+/// Filter matching nodes without the component.
+pub struct Without<T>(PhantomData<T>);
+
+impl<T: Component> QueryFilter for Without<T> {
+    fn matches(scene: &Scene, node: Node) -> bool {
+        scene.get::<T>(node).is_none()
+    }
+}
+
+/// # Added
+///
+/// Filter matching nodes whose component was added during the current change tick, i.e. since the
+/// last call to [Scene::advance_change_tick].
+pub struct Added<T>(PhantomData<T>);
+
+impl<T: Component> QueryFilter for Added<T> {
+    fn matches(scene: &Scene, node: Node) -> bool {
+        scene.is_added::<T>(node, scene.change_tick().wrapping_sub(1))
+    }
+}
+
+/// # Changed
+///
+/// Filter matching nodes whose component was added or changed during the current change tick, i.e.
+/// since the last call to [Scene::advance_change_tick].
+pub struct Changed<T>(PhantomData<T>);
+
+impl<T: Component> QueryFilter for Changed<T> {
+    fn matches(scene: &Scene, node: Node) -> bool {
+        scene.is_changed::<T>(node, scene.change_tick().wrapping_sub(1))
+    }
+}
+
+impl QueryFilter for () {
+    fn matches(_scene: &Scene, _node: Node) -> bool {
+        true
+    }
+}
+
+macro_rules! impl_query_filter {
+    ($($name:ident),*) => {
+        impl<$($name: QueryFilter),*> QueryFilter for ($($name,)*) {
+            fn matches(scene: &Scene, node: Node) -> bool {
+                $($name::matches(scene, node))&&*
+            }
+        }
+    };
+}
+
+impl_query_filter!(A);
+impl_query_filter!(A, B);
+impl_query_filter!(A, B, C);
+impl_query_filter!(A, B, C, D);
+impl_query_filter!(A, B, C, D, E);
+impl_query_filter!(A, B, C, D, E, F);
+impl_query_filter!(A, B, C, D, E, F, G);
+impl_query_filter!(A, B, C, D, E, F, G, H);
+
+impl Scene {
+    /// Returns the nodes with all of the queried components that match the filter along with
+    /// references to the component values.
+    ///
+    /// ```
+    /// # use pulse::Changed;
+    /// # use pulse::LocalTransform;
+    /// # use pulse::Scene;
+    /// # use pulse::Visibility;
+    /// let mut scene = Scene::new();
+    /// let node = scene.spawn_with((LocalTransform::IDENTITY, Visibility::Visible));
+    /// scene.spawn_with(LocalTransform::IDENTITY);
+    /// scene.advance_change_tick();
+    /// scene.set(node, Visibility::Invisible);
+    ///
+    /// let changed = scene.query_filtered::<(LocalTransform,), (Changed<Visibility>,)>();
+    /// assert_eq!(changed.map(|(node, _)| node).collect::<Vec<_>>(), [node]);
+    /// ```
+    pub fn query_filtered<Q: Query, F: QueryFilter>(&self) -> impl Iterator<Item = Q::Item<'_>> {
+        Q::fetch_filtered::<F>(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn query_filtered_with_without_returns_matching_nodes() {
+        let mut scene = Scene::new();
+        let first = scene.spawn_with((17u32, true));
+        let second = scene.spawn_with(192u32);
+
+        let with = scene
+            .query_filtered::<(u32,), With<bool>>()
+            .map(|(node, _)| node)
+            .collect::<Vec<_>>();
+        let without = scene
+            .query_filtered::<(u32,), Without<bool>>()
+            .map(|(node, _)| node)
+            .collect::<Vec<_>>();
+
+        assert_eq!(with, [first]);
+        assert_eq!(without, [second]);
+    }
+
+    #[test]
+    fn query_filtered_added_returns_nodes_added_this_tick() {
+        let mut scene = Scene::new();
+        scene.spawn_with(17u32);
+        scene.advance_change_tick();
+        let node = scene.spawn_with(192u32);
+
+        let added = scene
+            .query_filtered::<(u32,), Added<u32>>()
+            .map(|(node, _)| node)
+            .collect::<Vec<_>>();
+
+        assert_eq!(added, [node]);
+    }
+
+    #[test]
+    fn query_filtered_changed_returns_nodes_changed_this_tick() {
+        let mut scene = Scene::new();
+        let first = scene.spawn_with(17u32);
+        let second = scene.spawn_with(192u32);
+        scene.advance_change_tick();
+
+        *scene.get_mut::<u32>(second).unwrap() += 1;
+
+        let changed = scene
+            .query_filtered::<(u32,), (Changed<u32>, Without<bool>)>()
+            .map(|(node, _)| node)
+            .collect::<Vec<_>>();
+
+        assert_eq!(changed, [second]);
+        assert!(!scene.is_changed::<u32>(first, scene.change_tick() - 1));
+    }
+}
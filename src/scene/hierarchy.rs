@@ -0,0 +1,222 @@
+use std::collections::VecDeque;
+
+use crate::Node;
+use crate::Scene;
+
+/// # Descendants
+///
+/// Depth-first iterator over the descendants of a node returned by [Scene::descendants].
+pub struct Descendants<'a> {
+    scene: &'a Scene,
+    stack: Vec<Node>,
+}
+
+impl Iterator for Descendants<'_> {
+    type Item = Node;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.stack.pop()?;
+        self.stack
+            .extend(self.scene.get_children(node).into_iter().flatten().rev());
+        Some(node)
+    }
+}
+
+/// # Descendants Breadth First
+///
+/// Breadth-first iterator over the descendants of a node returned by
+/// [Scene::descendants_breadth_first].
+pub struct DescendantsBreadthFirst<'a> {
+    scene: &'a Scene,
+    queue: VecDeque<Node>,
+}
+
+impl Iterator for DescendantsBreadthFirst<'_> {
+    type Item = Node;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.queue.pop_front()?;
+        self.queue
+            .extend(self.scene.get_children(node).into_iter().flatten());
+        Some(node)
+    }
+}
+
+/// # Ancestors
+///
+/// Iterator over the ancestors of a node, from its parent to its root, returned by
+/// [Scene::ancestors].
+pub struct Ancestors<'a> {
+    scene: &'a Scene,
+    node: Option<Node>,
+}
+
+impl Iterator for Ancestors<'_> {
+    type Item = Node;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.node = self.scene.get_parent(self.node?);
+        self.node
+    }
+}
+
+impl Scene {
+    /// Returns a depth-first (pre-order) iterator over the descendants of the node, not including
+    /// the node itself.
+    pub fn descendants(&self, node: Node) -> Descendants<'_> {
+        Descendants {
+            scene: self,
+            stack: self
+                .get_children(node)
+                .into_iter()
+                .flatten()
+                .rev()
+                .copied()
+                .collect(),
+        }
+    }
+
+    /// Returns a breadth-first iterator over the descendants of the node, not including the node
+    /// itself.
+    pub fn descendants_breadth_first(&self, node: Node) -> DescendantsBreadthFirst<'_> {
+        DescendantsBreadthFirst {
+            scene: self,
+            queue: self
+                .get_children(node)
+                .into_iter()
+                .flatten()
+                .copied()
+                .collect(),
+        }
+    }
+
+    /// Sets the parent of the child and inserts it at the given index among the parent's children.
+    /// The index is clamped to the number of children. Keeps the existing parent under the same
+    /// conditions as [Scene::set_parent].
+    pub fn insert_child_at(&mut self, parent: Node, child: Node, index: usize) {
+        self.set_parent(child, parent);
+        self.move_child(parent, child, index);
+    }
+
+    /// Moves the child to the given index among the parent's children. The index is clamped to the
+    /// last child. Does nothing if the node isn't a child of the parent.
+    pub fn move_child(&mut self, parent: Node, child: Node, index: usize) {
+        if let Some(children) = self.children.get_mut(&parent) {
+            if let Some(current) = children.iter().position(|node| *node == child) {
+                children.remove(current);
+                children.insert(index.min(children.len()), child);
+            }
+        }
+    }
+
+    /// Returns the index of the node among its parent's children.
+    pub fn child_index(&self, node: Node) -> Option<usize> {
+        let parent = self.get_parent(node)?;
+        self.get_children(parent)?
+            .iter()
+            .position(|child| *child == node)
+    }
+
+    /// Returns an iterator over the ancestors of the node from its parent up to its root.
+    pub fn ancestors(&self, node: Node) -> Ancestors<'_> {
+        Ancestors {
+            scene: self,
+            node: Some(node),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds the hierarchy `root -> (a -> (c, d), b)`.
+    fn hierarchy(scene: &mut Scene) -> [Node; 5] {
+        let [root, a, b, c, d] = [(); 5].map(|_| scene.spawn());
+        scene.set_parent(a, root);
+        scene.set_parent(b, root);
+        scene.set_parent(c, a);
+        scene.set_parent(d, a);
+        [root, a, b, c, d]
+    }
+
+    #[test]
+    fn descendants_returns_depth_first_order() {
+        let mut scene = Scene::new();
+        let [root, a, b, c, d] = hierarchy(&mut scene);
+
+        let nodes = scene.descendants(root).collect::<Vec<_>>();
+
+        assert_eq!(nodes, [a, c, d, b]);
+    }
+
+    #[test]
+    fn descendants_breadth_first_returns_breadth_first_order() {
+        let mut scene = Scene::new();
+        let [root, a, b, c, d] = hierarchy(&mut scene);
+
+        let nodes = scene.descendants_breadth_first(root).collect::<Vec<_>>();
+
+        assert_eq!(nodes, [a, b, c, d]);
+    }
+
+    #[test]
+    fn ancestors_returns_parent_to_root() {
+        let mut scene = Scene::new();
+        let [root, a, _, _, d] = hierarchy(&mut scene);
+
+        let nodes = scene.ancestors(d).collect::<Vec<_>>();
+
+        assert_eq!(nodes, [a, root]);
+    }
+
+    #[test]
+    fn ancestors_root_returns_empty() {
+        let mut scene = Scene::new();
+        let [root, ..] = hierarchy(&mut scene);
+
+        assert_eq!(scene.ancestors(root).next(), None);
+    }
+
+    #[test]
+    fn insert_child_at_get_children_returns_inserted_order() {
+        let mut scene = Scene::new();
+        let [root, a, b, ..] = hierarchy(&mut scene);
+        let node = scene.spawn();
+
+        scene.insert_child_at(root, node, 1);
+
+        assert_eq!(scene.get_children(root), Some([a, node, b].as_slice()));
+        assert_eq!(scene.child_index(node), Some(1));
+    }
+
+    #[test]
+    fn move_child_get_children_returns_new_order() {
+        let mut scene = Scene::new();
+        let [root, a, b, ..] = hierarchy(&mut scene);
+
+        scene.move_child(root, a, 10);
+
+        assert_eq!(scene.get_children(root), Some([b, a].as_slice()));
+        assert_eq!(scene.child_index(a), Some(1));
+    }
+
+    #[test]
+    fn move_child_other_parent_does_nothing() {
+        let mut scene = Scene::new();
+        let [root, a, b, c, d] = hierarchy(&mut scene);
+
+        scene.move_child(root, c, 0);
+
+        assert_eq!(scene.get_children(root), Some([a, b].as_slice()));
+        assert_eq!(scene.get_children(a), Some([c, d].as_slice()));
+    }
+
+    #[test]
+    fn child_index_root_returns_none() {
+        let mut scene = Scene::new();
+        let [root, ..] = hierarchy(&mut scene);
+
+        assert_eq!(scene.child_index(root), None);
+    }
+}
@@ -0,0 +1,112 @@
+use nohash::IntMap;
+
+use crate::Node;
+use crate::Scene;
+
+/// # Node Remap
+///
+/// Mapping from the nodes of a merged scene to their new nodes returned by [Scene::merge].
+#[derive(Clone, Debug, Default)]
+pub struct NodeRemap {
+    nodes: IntMap<Node, Node>,
+}
+
+impl NodeRemap {
+    /// Returns the new node for the given node of the merged scene.
+    pub fn get(&self, node: Node) -> Option<Node> {
+        self.nodes.get(&node).copied()
+    }
+
+    /// Returns the number of remapped nodes.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Returns true if no nodes were remapped.
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Returns an iterator over the old and new node pairs.
+    pub fn iter(&self) -> impl '_ + Iterator<Item = (Node, Node)> {
+        self.nodes.iter().map(|(old, new)| (*old, *new))
+    }
+}
+
+impl Scene {
+    /// Moves all of the nodes, hierarchy, components, and tags of the other scene into this scene
+    /// and returns the mapping from the other scene's nodes to the new nodes. Resources of the
+    /// other scene are dropped.
+    pub fn merge(&mut self, mut other: Scene) -> NodeRemap {
+        let mut nodes = other.nodes.iter().copied().collect::<Vec<_>>();
+        nodes.sort();
+
+        let mut remap = NodeRemap::default();
+        for node in &nodes {
+            remap.nodes.insert(*node, self.spawn());
+        }
+
+        let mut roots = other.get_root_nodes().collect::<Vec<_>>();
+        roots.sort();
+        for root in roots {
+            for node in other.descendants(root) {
+                let parent = other.get_parent(node).unwrap();
+                self.set_parent(remap.nodes[&node], remap.nodes[&parent]);
+            }
+        }
+
+        for table in other.component_tables.drain(..) {
+            table.merge_into(self, &remap);
+        }
+
+        for (type_id, table) in other.tag_tables {
+            for node in table.nodes {
+                self.tag_dynamic(type_id, remap.nodes[&node]);
+            }
+        }
+
+        for (type_id, table) in &other.relationship_tables {
+            for (source, target) in table.links() {
+                self.relate_dynamic(*type_id, remap.nodes[&source], remap.nodes[&target]);
+            }
+        }
+
+        remap
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merge_moves_nodes_hierarchy_and_components() {
+        let mut scene = Scene::new();
+        let existing = scene.spawn_with(1u32);
+        let mut other = Scene::new();
+        let parent = other.spawn_with(17u32);
+        let first = other.spawn();
+        let second = other.spawn_with(true);
+        other.set_parent(second, parent);
+        other.set_parent(first, parent);
+
+        let remap = scene.merge(other);
+
+        assert_eq!(remap.len(), 3);
+        let parent = remap.get(parent).unwrap();
+        let first = remap.get(first).unwrap();
+        let second = remap.get(second).unwrap();
+        assert_eq!(scene.get::<u32>(existing), Some(&1));
+        assert_eq!(scene.get::<u32>(parent), Some(&17));
+        assert_eq!(scene.get::<bool>(second), Some(&true));
+        assert_eq!(scene.get_children(parent), Some([second, first].as_slice()));
+    }
+
+    #[test]
+    fn merge_empty_scene_returns_empty_remap() {
+        let mut scene = Scene::new();
+        scene.spawn();
+
+        assert!(scene.merge(Scene::new()).is_empty());
+    }
+}
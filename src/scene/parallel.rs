@@ -0,0 +1,139 @@
+use rayon::prelude::*;
+
+use crate::Component;
+use crate::Node;
+use crate::Query;
+use crate::Scene;
+
+/// # Par Query
+///
+/// Set of components fetched together in parallel by [Scene::par_query]. Implemented for tuples of
+/// up to eight components.
+pub trait ParQuery: Query {
+    /// Returns the items for all of the nodes with all of the queried components as a parallel
+    /// iterator.
+    fn par_fetch(scene: &Scene) -> impl ParallelIterator<Item = Self::Item<'_>>;
+}
+
+macro_rules! impl_par_query {
+    ($first:ident $(, $rest:ident)*) => {
+        impl<$first: Component $(, $rest: Component)*> ParQuery for ($first, $($rest,)*) {
+            #[allow(non_snake_case)]
+            fn par_fetch(scene: &Scene) -> impl ParallelIterator<Item = Self::Item<'_>> {
+                let tables = (|| Some((scene.table::<$first>()?, $(scene.table::<$rest>()?,)*)))();
+
+                tables
+                    .into_par_iter()
+                    .flat_map(|($first, $($rest,)*)| {
+                        $first
+                            .nodes
+                            .par_iter()
+                            .zip(&$first.items)
+                            .filter_map(move |(node, first)| {
+                                Some((*node, first, $($rest.get(*node)?,)*))
+                            })
+                    })
+            }
+        }
+    };
+}
+
+impl_par_query!(A);
+impl_par_query!(A, B);
+impl_par_query!(A, B, C);
+impl_par_query!(A, B, C, D);
+impl_par_query!(A, B, C, D, E);
+impl_par_query!(A, B, C, D, E, F);
+impl_par_query!(A, B, C, D, E, F, G);
+impl_par_query!(A, B, C, D, E, F, G, H);
+
+impl Scene {
+    /// Returns the nodes with all of the queried components along with references to the
+    /// component values as a parallel iterator. The component tables are split across the threads
+    /// of the rayon thread pool.
+    ///
+    /// ```
+    /// # use pulse::LocalTransform;
+    /// # use pulse::Scene;
+    /// # use rayon::prelude::*;
+    /// let mut scene = Scene::new();
+    /// let node = scene.spawn_with(LocalTransform::IDENTITY);
+    ///
+    /// let count = scene.par_query::<(LocalTransform,)>().count();
+    /// assert_eq!(count, 1);
+    /// ```
+    pub fn par_query<Q: ParQuery>(&self) -> impl ParallelIterator<Item = Q::Item<'_>>
+    where
+        for<'a> Q::Item<'a>: Send,
+    {
+        Q::par_fetch(self)
+    }
+
+    /// Returns all of the nodes with the component along with mutable references to the component
+    /// values as a parallel iterator. Every component value is marked as modified since each
+    /// thread has exclusive access to its share of the values.
+    pub fn par_query_mut<T: Component>(
+        &mut self,
+    ) -> impl IndexedParallelIterator<Item = (Node, &mut T)> {
+        let tick = self.change_tick;
+        let table = self.table_mut::<T>();
+        let (nodes, items) = match table {
+            Some(table) => {
+                for index in 0..table.nodes.len() {
+                    table.mark_modified(index, tick);
+                }
+                (table.nodes.as_slice(), table.items.as_mut_slice())
+            }
+            None => (&[][..], &mut [][..]),
+        };
+
+        nodes.par_iter().copied().zip(items.par_iter_mut())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ComponentEvent;
+
+    #[test]
+    fn par_query_returns_nodes_with_all_components() {
+        let mut scene = Scene::new();
+        let nodes = (0..100u32)
+            .map(|i| scene.spawn_with((i, i % 2 == 0)))
+            .collect::<Vec<_>>();
+        scene.remove::<bool>(nodes[0]);
+
+        let mut items = scene
+            .par_query::<(u32, bool)>()
+            .map(|(node, value, flag)| (node, *value, *flag))
+            .collect::<Vec<_>>();
+        items.sort();
+
+        assert_eq!(items.len(), 99);
+        assert_eq!(items[0], (nodes[1], 1, false));
+    }
+
+    #[test]
+    fn par_query_missing_component_returns_nothing() {
+        let mut scene = Scene::new();
+        scene.spawn_with(17u32);
+
+        assert_eq!(scene.par_query::<(u32, bool)>().count(), 0);
+    }
+
+    #[test]
+    fn par_query_mut_modifies_values_and_emits_events() {
+        let mut scene = Scene::new();
+        let nodes = (0..100u32).map(|i| scene.spawn_with(i)).collect::<Vec<_>>();
+        scene.clear_events();
+
+        scene
+            .par_query_mut::<u32>()
+            .for_each(|(_, value)| *value *= 2);
+
+        assert_eq!(scene.get::<u32>(nodes[17]), Some(&34));
+        assert_eq!(scene.events::<u32>().len(), 100);
+        assert_eq!(scene.events::<u32>()[0], ComponentEvent::Modified(nodes[0]));
+    }
+}
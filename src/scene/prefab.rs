@@ -0,0 +1,219 @@
+use std::any::TypeId;
+
+use crate::scene::DynamicComponent;
+use crate::Component;
+use crate::Node;
+use crate::Scene;
+
+/// # Prefab
+///
+/// Reusable template of a node subtree that can be instantiated into any scene multiple times.
+/// Prefab nodes are indexed in depth-first order starting with the root node at index `0`.
+pub struct Prefab {
+    nodes: Vec<PrefabNode>,
+}
+
+struct PrefabNode {
+    parent: Option<usize>,
+    components: Vec<Box<dyn DynamicComponent>>,
+    tags: Vec<TypeId>,
+}
+
+impl Prefab {
+    /// Returns a prefab capturing the components, tags, and hierarchy of the given node and its
+    /// descendants. Returns [None] if the scene doesn't contain the node.
+    pub fn from_node(scene: &Scene, node: Node) -> Option<Self> {
+        if !scene.contains(node) {
+            return None;
+        }
+
+        let mut nodes = Vec::new();
+        let mut stack = vec![(node, None)];
+        while let Some((node, parent)) = stack.pop() {
+            let index = nodes.len();
+            nodes.push(PrefabNode {
+                parent,
+                components: scene.get_all_dynamic(node),
+                tags: scene.get_all_tags(node),
+            });
+
+            for child in scene.get_children(node).into_iter().flatten().rev() {
+                stack.push((*child, Some(index)));
+            }
+        }
+
+        Some(Self { nodes })
+    }
+
+    /// Returns the number of nodes in the prefab.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Returns true if the prefab has no nodes.
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Instantiates the prefab into the scene and returns the new root node.
+    pub fn instantiate(&self, scene: &mut Scene) -> Node {
+        self.instantiate_with(scene, &PrefabOverrides::new())
+    }
+
+    /// Instantiates the prefab into the scene with the given component overrides and returns the
+    /// new root node.
+    pub fn instantiate_with(&self, scene: &mut Scene, overrides: &PrefabOverrides) -> Node {
+        let mut instances: Vec<Node> = Vec::with_capacity(self.nodes.len());
+        for (index, prefab_node) in self.nodes.iter().enumerate() {
+            let node = scene.spawn();
+            if let Some(parent) = prefab_node.parent {
+                scene.set_parent(node, instances[parent]);
+            }
+
+            let node_overrides = overrides
+                .components
+                .iter()
+                .filter(|(override_index, _)| *override_index == index)
+                .map(|(_, value)| value)
+                .collect::<Vec<_>>();
+
+            for component in &prefab_node.components {
+                let component = node_overrides
+                    .iter()
+                    .find(|value| value.component_type_id() == component.component_type_id())
+                    .copied()
+                    .unwrap_or(component);
+                component.add_to(scene, node);
+            }
+
+            for value in node_overrides {
+                if !prefab_node
+                    .components
+                    .iter()
+                    .any(|component| component.component_type_id() == value.component_type_id())
+                {
+                    value.add_to(scene, node);
+                }
+            }
+
+            for tag in &prefab_node.tags {
+                scene.tag_dynamic(*tag, node);
+            }
+
+            instances.push(node);
+        }
+
+        instances[0]
+    }
+}
+
+impl Clone for Prefab {
+    fn clone(&self) -> Self {
+        Self {
+            nodes: self
+                .nodes
+                .iter()
+                .map(|node| PrefabNode {
+                    parent: node.parent,
+                    components: node
+                        .components
+                        .iter()
+                        .map(|component| component.clone_box())
+                        .collect(),
+                    tags: node.tags.clone(),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// # Prefab Overrides
+///
+/// Component values that replace or extend the components of a [Prefab] when instantiated.
+#[derive(Default)]
+pub struct PrefabOverrides {
+    components: Vec<(usize, Box<dyn DynamicComponent>)>,
+}
+
+impl PrefabOverrides {
+    /// Returns an empty set of overrides.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Overrides the component value for the prefab node at the given index.
+    pub fn with<T: Component>(mut self, index: usize, value: T) -> Self {
+        self.components.retain(|(i, component)| {
+            *i != index || component.component_type_id() != value.component_type_id()
+        });
+        self.components.push((index, Box::new(value)));
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_node_despawned_node_returns_none() {
+        let mut scene = Scene::new();
+        let node = scene.spawn();
+        scene.despawn(node);
+
+        assert!(Prefab::from_node(&scene, node).is_none());
+    }
+
+    #[test]
+    fn instantiate_copies_components_and_hierarchy() {
+        let mut scene = Scene::new();
+        let root = scene.spawn();
+        let child = scene.spawn();
+        scene.set_parent(child, root);
+        scene.add(root, 17u32);
+        scene.add(child, true);
+        let prefab = Prefab::from_node(&scene, root).unwrap();
+
+        let instance = prefab.instantiate(&mut scene);
+
+        assert_ne!(instance, root);
+        assert_eq!(scene.get::<u32>(instance), Some(&17));
+        let children = scene.get_children(instance).unwrap();
+        assert_eq!(children.len(), 1);
+        assert_eq!(scene.get::<bool>(children[0]), Some(&true));
+    }
+
+    #[test]
+    fn instantiate_into_other_scene_copies_components() {
+        let mut scene = Scene::new();
+        let root = scene.spawn();
+        scene.add(root, 17u32);
+        let prefab = Prefab::from_node(&scene, root).unwrap();
+
+        let mut other = Scene::new();
+        let first = prefab.instantiate(&mut other);
+        let second = prefab.instantiate(&mut other);
+
+        assert_ne!(first, second);
+        assert_eq!(other.get::<u32>(first), Some(&17));
+        assert_eq!(other.get::<u32>(second), Some(&17));
+    }
+
+    #[test]
+    fn instantiate_with_overrides_replaces_and_adds_components() {
+        let mut scene = Scene::new();
+        let root = scene.spawn();
+        let child = scene.spawn();
+        scene.set_parent(child, root);
+        scene.add(root, 17u32);
+        let prefab = Prefab::from_node(&scene, root).unwrap();
+
+        let overrides = PrefabOverrides::new().with(0, 192u32).with(1, false);
+        let instance = prefab.instantiate_with(&mut scene, &overrides);
+
+        assert_eq!(scene.get::<u32>(instance), Some(&192));
+        let child = scene.get_children(instance).unwrap()[0];
+        assert_eq!(scene.get::<bool>(child), Some(&false));
+        assert_eq!(scene.get::<u32>(root), Some(&17));
+    }
+}
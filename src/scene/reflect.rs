@@ -0,0 +1,123 @@
+use std::collections::BTreeMap;
+
+use crate::Component;
+use crate::Node;
+use crate::Reflect;
+use crate::Scene;
+
+/// Reflection registrations keyed by component name.
+pub(super) type ReflectRegistry = BTreeMap<&'static str, ReflectRegistration>;
+
+/// Reflection accessors for a registered component type.
+#[derive(Copy, Clone)]
+pub(super) struct ReflectRegistration {
+    get: fn(&Scene, Node) -> Option<&dyn Reflect>,
+    get_mut: fn(&mut Scene, Node) -> Option<&mut dyn Reflect>,
+}
+
+impl ReflectRegistration {
+    fn new<T: Component + Reflect>() -> Self {
+        Self {
+            get: |scene, node| scene.get::<T>(node).map(|value| value as &dyn Reflect),
+            get_mut: |scene, node| {
+                let tick = scene.change_tick;
+                let table = scene.table_mut::<T>()?;
+                let index = table.index(node)?;
+                table.mark_modified(index, tick);
+                Some(&mut table.items[index] as &mut dyn Reflect)
+            },
+        }
+    }
+}
+
+impl Scene {
+    /// Registers the component under the given name for reflected access.
+    pub fn register_reflect<T: Component + Reflect>(&mut self, name: &'static str) {
+        self.reflect_registry
+            .insert(name, ReflectRegistration::new::<T>());
+    }
+
+    /// Returns the names of the components registered for reflected access.
+    pub fn reflect_names(&self) -> impl '_ + Iterator<Item = &'static str> {
+        self.reflect_registry.keys().copied()
+    }
+
+    /// Returns the names of the registered components of the given node.
+    pub fn reflect_components(&self, node: Node) -> Vec<&'static str> {
+        self.reflect_registry
+            .iter()
+            .filter(|(_, registration)| (registration.get)(self, node).is_some())
+            .map(|(name, _)| *name)
+            .collect()
+    }
+
+    /// Returns the value at the given path of the node, where the path starts with the registered
+    /// component name followed by dot-separated field names, e.g. `"LocalTransform.position.x"`.
+    pub fn reflect(&self, node: Node, path: &str) -> Option<&dyn Reflect> {
+        let (name, fields) = path.split_once('.').unwrap_or((path, ""));
+        let registration = self.reflect_registry.get(name)?;
+        (registration.get)(self, node)?.path(fields)
+    }
+
+    /// Returns a mutable reference to the value at the given path of the node. See
+    /// [Scene::reflect] for the path format. The component is marked as modified when the value
+    /// is found.
+    pub fn reflect_mut(&mut self, node: Node, path: &str) -> Option<&mut dyn Reflect> {
+        let (name, fields) = path.split_once('.').unwrap_or((path, ""));
+        let registration = *self.reflect_registry.get(name)?;
+        (registration.get_mut)(self, node)?.path_mut(fields)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec3;
+
+    use super::*;
+    use crate::ComponentEvent;
+    use crate::LocalTransform;
+    use crate::Visibility;
+
+    #[test]
+    fn reflect_returns_component_field() {
+        let mut scene = Scene::new();
+        let node = scene.spawn_with(LocalTransform::from_position(Vec3::X));
+
+        let x = scene
+            .reflect(node, "LocalTransform.position.x")
+            .and_then(|x| x.downcast_ref::<f32>());
+
+        assert_eq!(x, Some(&1.0));
+        assert!(scene.reflect(node, "Visibility").is_none());
+    }
+
+    #[test]
+    fn reflect_mut_modifies_component_and_emits_event() {
+        let mut scene = Scene::new();
+        let node = scene.spawn_with(Visibility::Visible);
+        scene.clear_events();
+
+        let applied = scene
+            .reflect_mut(node, "Visibility")
+            .unwrap()
+            .apply(&Visibility::Invisible);
+
+        assert!(applied);
+        assert_eq!(scene.get::<Visibility>(node), Some(&Visibility::Invisible));
+        assert_eq!(
+            scene.events::<Visibility>(),
+            &[ComponentEvent::Modified(node)]
+        );
+    }
+
+    #[test]
+    fn reflect_components_returns_registered_component_names() {
+        let mut scene = Scene::new();
+        let node = scene.spawn_with((Visibility::Visible, LocalTransform::IDENTITY, 17u32));
+
+        assert_eq!(
+            scene.reflect_components(node),
+            ["LocalTransform", "Visibility"]
+        );
+    }
+}
@@ -0,0 +1,240 @@
+use std::any::TypeId;
+
+use nohash::IntMap;
+
+use crate::Node;
+use crate::Scene;
+
+/// # Relationship
+///
+/// Kind of directed link from a source node to target nodes, e.g. `AttachedTo` or `OwnedBy`.
+/// Relationships are removed automatically when either of their nodes is despawned.
+pub trait Relationship: 'static {}
+
+/// Source-to-target and target-to-source links of a single relationship type.
+#[derive(Clone, Default)]
+pub(super) struct RelationshipTable {
+    targets: IntMap<Node, Vec<Node>>,
+    sources: IntMap<Node, Vec<Node>>,
+}
+
+impl RelationshipTable {
+    fn insert(&mut self, source: Node, target: Node) {
+        let targets = self.targets.entry(source).or_default();
+        if !targets.contains(&target) {
+            targets.push(target);
+            self.sources.entry(target).or_default().push(source);
+        }
+    }
+
+    fn remove(&mut self, source: Node, target: Node) {
+        if remove_link(&mut self.targets, source, target) {
+            remove_link(&mut self.sources, target, source);
+        }
+    }
+
+    /// Removes all of the relationships from and to the node.
+    pub(super) fn remove_node(&mut self, node: Node) {
+        for target in self.targets.remove(&node).into_iter().flatten() {
+            remove_link(&mut self.sources, target, node);
+        }
+
+        for source in self.sources.remove(&node).into_iter().flatten() {
+            remove_link(&mut self.targets, source, node);
+        }
+    }
+
+    /// Returns the source and target pairs of the relationships.
+    pub(super) fn links(&self) -> impl '_ + Iterator<Item = (Node, Node)> {
+        self.targets
+            .iter()
+            .flat_map(|(source, targets)| targets.iter().map(|target| (*source, *target)))
+    }
+
+    pub(super) fn clear(&mut self) {
+        self.targets.clear();
+        self.sources.clear();
+    }
+}
+
+/// Removes the value from the list of the key and removes the list when it becomes empty. Returns
+/// true if the value was removed.
+fn remove_link(links: &mut IntMap<Node, Vec<Node>>, key: Node, value: Node) -> bool {
+    let Some(values) = links.get_mut(&key) else {
+        return false;
+    };
+
+    let Some(index) = values.iter().position(|node| *node == value) else {
+        return false;
+    };
+
+    values.remove(index);
+    if values.is_empty() {
+        links.remove(&key);
+    }
+
+    true
+}
+
+impl Scene {
+    /// Relates the source node to the target node. Does nothing if the scene doesn't contain
+    /// either node or if the nodes are already related.
+    ///
+    /// ```
+    /// # use pulse::Relationship;
+    /// # use pulse::Scene;
+    /// #[derive(Relationship)]
+    /// struct AttachedTo;
+    ///
+    /// let mut scene = Scene::new();
+    /// let sword = scene.spawn();
+    /// let hand = scene.spawn();
+    /// scene.relate::<AttachedTo>(sword, hand);
+    ///
+    /// assert_eq!(scene.related::<AttachedTo>(sword), [hand]);
+    /// scene.despawn(hand);
+    /// assert!(scene.related::<AttachedTo>(sword).is_empty());
+    /// ```
+    pub fn relate<R: Relationship>(&mut self, source: Node, target: Node) {
+        if self.contains(source) && self.contains(target) {
+            self.relationship_tables
+                .entry(TypeId::of::<R>())
+                .or_default()
+                .insert(source, target);
+        }
+    }
+
+    /// Removes the relationship from the source node to the target node.
+    pub fn unrelate<R: Relationship>(&mut self, source: Node, target: Node) {
+        if let Some(table) = self.relationship_tables.get_mut(&TypeId::of::<R>()) {
+            table.remove(source, target);
+        }
+    }
+
+    /// Removes all of the relationships of the given type from and to the node.
+    pub fn unrelate_all<R: Relationship>(&mut self, node: Node) {
+        if let Some(table) = self.relationship_tables.get_mut(&TypeId::of::<R>()) {
+            table.remove_node(node);
+        }
+    }
+
+    /// Returns true if the source node is related to the target node.
+    pub fn is_related<R: Relationship>(&self, source: Node, target: Node) -> bool {
+        self.related::<R>(source).contains(&target)
+    }
+
+    /// Returns the target nodes the source node is related to in the order they were related.
+    pub fn related<R: Relationship>(&self, source: Node) -> &[Node] {
+        self.relationship_tables
+            .get(&TypeId::of::<R>())
+            .and_then(|table| table.targets.get(&source))
+            .map(Vec::as_slice)
+            .unwrap_or(&[])
+    }
+
+    /// Returns the source nodes related to the target node in the order they were related.
+    pub fn related_from<R: Relationship>(&self, target: Node) -> &[Node] {
+        self.relationship_tables
+            .get(&TypeId::of::<R>())
+            .and_then(|table| table.sources.get(&target))
+            .map(Vec::as_slice)
+            .unwrap_or(&[])
+    }
+
+    /// Relates the nodes with the relationship with the given type id.
+    pub(super) fn relate_dynamic(&mut self, type_id: TypeId, source: Node, target: Node) {
+        if self.contains(source) && self.contains(target) {
+            self.relationship_tables
+                .entry(type_id)
+                .or_default()
+                .insert(source, target);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct AttachedTo;
+
+    impl Relationship for AttachedTo {}
+
+    struct OwnedBy;
+
+    impl Relationship for OwnedBy {}
+
+    #[test]
+    fn relate_related_returns_targets() {
+        let mut scene = Scene::new();
+        let source = scene.spawn();
+        let first = scene.spawn();
+        let second = scene.spawn();
+
+        scene.relate::<AttachedTo>(source, first);
+        scene.relate::<AttachedTo>(source, second);
+        scene.relate::<AttachedTo>(source, first);
+
+        assert_eq!(scene.related::<AttachedTo>(source), [first, second]);
+        assert_eq!(scene.related_from::<AttachedTo>(first), [source]);
+        assert!(scene.is_related::<AttachedTo>(source, second));
+        assert!(scene.related::<OwnedBy>(source).is_empty());
+    }
+
+    #[test]
+    fn unrelate_removes_relationship() {
+        let mut scene = Scene::new();
+        let source = scene.spawn();
+        let target = scene.spawn();
+        scene.relate::<AttachedTo>(source, target);
+        scene.relate::<OwnedBy>(source, target);
+
+        scene.unrelate::<AttachedTo>(source, target);
+
+        assert!(!scene.is_related::<AttachedTo>(source, target));
+        assert!(scene.related_from::<AttachedTo>(target).is_empty());
+        assert!(scene.is_related::<OwnedBy>(source, target));
+    }
+
+    #[test]
+    fn despawn_removes_relationships_from_and_to_node() {
+        let mut scene = Scene::new();
+        let source = scene.spawn();
+        let target = scene.spawn();
+        let other = scene.spawn();
+        scene.relate::<AttachedTo>(source, target);
+        scene.relate::<AttachedTo>(other, source);
+
+        scene.despawn(source);
+
+        assert!(scene.related_from::<AttachedTo>(target).is_empty());
+        assert!(scene.related::<AttachedTo>(other).is_empty());
+    }
+
+    #[test]
+    fn relate_despawned_node_does_nothing() {
+        let mut scene = Scene::new();
+        let source = scene.spawn();
+        let target = scene.spawn();
+        scene.despawn(target);
+
+        scene.relate::<AttachedTo>(source, target);
+
+        assert!(scene.related::<AttachedTo>(source).is_empty());
+    }
+
+    #[test]
+    fn merge_moves_relationships() {
+        let mut scene = Scene::new();
+        let mut other = Scene::new();
+        let source = other.spawn();
+        let target = other.spawn();
+        other.relate::<AttachedTo>(source, target);
+
+        let remap = scene.merge(other);
+
+        let source = remap.get(source).unwrap();
+        let target = remap.get(target).unwrap();
+        assert_eq!(scene.related::<AttachedTo>(source), [target]);
+    }
+}
@@ -0,0 +1,400 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use nohash::IntMap;
+use serde::de::DeserializeOwned;
+use serde::de::DeserializeSeed;
+use serde::de::Error as _;
+use serde::de::IgnoredAny;
+use serde::de::MapAccess;
+use serde::de::SeqAccess;
+use serde::de::Visitor;
+use serde::ser::SerializeMap;
+use serde::ser::SerializeSeq;
+use serde::ser::SerializeStruct;
+use serde::Deserializer;
+use serde::Serialize;
+use serde::Serializer;
+
+use crate::Component;
+use crate::Node;
+use crate::Scene;
+
+/// Component registrations keyed by name.
+pub(super) type Registry = BTreeMap<&'static str, ComponentRegistration>;
+
+/// Serialization functions for a registered component type.
+#[derive(Copy, Clone)]
+pub(super) struct ComponentRegistration {
+    serialize: fn(&Scene, Node) -> Option<&dyn erased_serde::Serialize>,
+    deserialize:
+        fn(&mut dyn erased_serde::Deserializer, &mut Scene, Node) -> erased_serde::Result<()>,
+}
+
+impl ComponentRegistration {
+    fn new<T: Component + Serialize + DeserializeOwned>() -> Self {
+        Self {
+            serialize: |scene, node| {
+                scene
+                    .get::<T>(node)
+                    .map(|value| value as &dyn erased_serde::Serialize)
+            },
+            deserialize: |deserializer, scene, node| {
+                let value = erased_serde::deserialize::<T>(deserializer)?;
+                scene.set_or_add(node, value);
+                Ok(())
+            },
+        }
+    }
+}
+
+impl Scene {
+    /// Registers the component under the given name so it is included when the scene is saved or
+    /// loaded. Components that aren't registered are skipped when saving.
+    pub fn register<T: Component + Serialize + DeserializeOwned>(&mut self, name: &'static str) {
+        self.registry
+            .insert(name, ComponentRegistration::new::<T>());
+    }
+
+    /// Serializes the nodes, hierarchy, and registered components of the scene with the given
+    /// serializer.
+    pub fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut nodes = Vec::new();
+        let mut stack = self.get_root_nodes().collect::<Vec<_>>();
+        stack.sort();
+        stack.reverse();
+
+        while let Some(node) = stack.pop() {
+            nodes.push(node);
+            stack.extend(self.get_children(node).into_iter().flatten().rev());
+        }
+
+        let node_indexes = nodes
+            .iter()
+            .enumerate()
+            .map(|(index, node)| (*node, index))
+            .collect::<IntMap<_, _>>();
+
+        SerializeScene {
+            scene: self,
+            nodes: &nodes,
+            node_indexes: &node_indexes,
+        }
+        .serialize(serializer)
+    }
+
+    /// Deserializes nodes previously written by [Scene::serialize] into the scene and returns the
+    /// new nodes in the order they were serialized. On error, the nodes deserialized so far are
+    /// despawned again.
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        &mut self,
+        deserializer: D,
+    ) -> Result<Vec<Node>, D::Error> {
+        let mut spawned = Vec::new();
+        let result = SceneSeed {
+            scene: self,
+            spawned: &mut spawned,
+        }
+        .deserialize(deserializer);
+        self.despawn_on_error(result, spawned)
+    }
+
+    /// Saves the scene as a RON string.
+    pub fn save(&self) -> Result<String, ron::Error> {
+        let mut output = String::new();
+        let mut serializer =
+            ron::Serializer::new(&mut output, Some(ron::ser::PrettyConfig::default()))?;
+        self.serialize(&mut serializer)?;
+        Ok(output)
+    }
+
+    /// Loads the nodes from a RON string written by [Scene::save] into the scene and returns the
+    /// new nodes. On error, the nodes loaded so far are despawned again.
+    pub fn load(&mut self, source: &str) -> Result<Vec<Node>, ron::error::SpannedError> {
+        let mut spawned = Vec::new();
+        let seed = SceneSeed {
+            scene: self,
+            spawned: &mut spawned,
+        };
+        let result = ron::Options::default().from_str_seed(source, seed);
+        self.despawn_on_error(result, spawned)
+    }
+
+    /// Despawns the spawned nodes if the result is an error, so a failed load leaves no
+    /// half-loaded nodes behind.
+    fn despawn_on_error<T, E>(&mut self, result: Result<T, E>, spawned: Vec<Node>) -> Result<T, E> {
+        if result.is_err() {
+            for node in spawned {
+                self.despawn(node);
+            }
+        }
+        result
+    }
+}
+
+struct SerializeScene<'a> {
+    scene: &'a Scene,
+    nodes: &'a [Node],
+    node_indexes: &'a IntMap<Node, usize>,
+}
+
+impl Serialize for SerializeScene<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("Scene", 1)?;
+        state.serialize_field("nodes", &SerializeNodes(self))?;
+        state.end()
+    }
+}
+
+struct SerializeNodes<'a>(&'a SerializeScene<'a>);
+
+impl Serialize for SerializeNodes<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(self.0.nodes.len()))?;
+        for node in self.0.nodes {
+            seq.serialize_element(&SerializeNode {
+                scene: self.0,
+                node: *node,
+            })?;
+        }
+        seq.end()
+    }
+}
+
+struct SerializeNode<'a> {
+    scene: &'a SerializeScene<'a>,
+    node: Node,
+}
+
+impl Serialize for SerializeNode<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let parent = self
+            .scene
+            .scene
+            .get_parent(self.node)
+            .and_then(|parent| self.scene.node_indexes.get(&parent).copied());
+
+        let mut state = serializer.serialize_struct("Node", 2)?;
+        state.serialize_field("parent", &parent)?;
+        state.serialize_field("components", &SerializeComponents(self))?;
+        state.end()
+    }
+}
+
+struct SerializeComponents<'a>(&'a SerializeNode<'a>);
+
+impl Serialize for SerializeComponents<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let scene = self.0.scene.scene;
+        let components = scene
+            .registry
+            .iter()
+            .filter_map(|(name, registration)| {
+                (registration.serialize)(scene, self.0.node).map(|value| (*name, value))
+            })
+            .collect::<Vec<_>>();
+
+        let mut map = serializer.serialize_map(Some(components.len()))?;
+        for (name, value) in &components {
+            map.serialize_entry(name, value)?;
+        }
+        map.end()
+    }
+}
+
+struct SceneSeed<'a> {
+    scene: &'a mut Scene,
+    /// Every node spawned so far, even by a node that failed to deserialize.
+    spawned: &'a mut Vec<Node>,
+}
+
+impl<'de> DeserializeSeed<'de> for SceneSeed<'_> {
+    type Value = Vec<Node>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_struct("Scene", &["nodes"], self)
+    }
+}
+
+impl<'de> Visitor<'de> for SceneSeed<'_> {
+    type Value = Vec<Node>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a scene")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut nodes = None;
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "nodes" => {
+                    nodes = Some(map.next_value_seed(NodesSeed {
+                        scene: &mut *self.scene,
+                        spawned: &mut *self.spawned,
+                    })?)
+                }
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+
+        let nodes: Vec<(Node, Option<usize>)> =
+            nodes.ok_or_else(|| A::Error::missing_field("nodes"))?;
+
+        for (node, parent) in &nodes {
+            if let Some(parent) = parent {
+                let (parent, _) = nodes
+                    .get(*parent)
+                    .ok_or_else(|| A::Error::custom(format!("invalid parent index {parent}")))?;
+                self.scene.set_parent(*node, *parent);
+            }
+        }
+
+        Ok(nodes.into_iter().map(|(node, _)| node).collect())
+    }
+}
+
+struct NodesSeed<'a> {
+    scene: &'a mut Scene,
+    spawned: &'a mut Vec<Node>,
+}
+
+impl<'de> DeserializeSeed<'de> for NodesSeed<'_> {
+    type Value = Vec<(Node, Option<usize>)>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de> Visitor<'de> for NodesSeed<'_> {
+    type Value = Vec<(Node, Option<usize>)>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a sequence of nodes")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut nodes = Vec::new();
+        while let Some(node) = seq.next_element_seed(NodeSeed {
+            scene: &mut *self.scene,
+            spawned: &mut *self.spawned,
+        })? {
+            nodes.push(node);
+        }
+
+        Ok(nodes)
+    }
+}
+
+struct NodeSeed<'a> {
+    scene: &'a mut Scene,
+    spawned: &'a mut Vec<Node>,
+}
+
+impl<'de> DeserializeSeed<'de> for NodeSeed<'_> {
+    type Value = (Node, Option<usize>);
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        let node = self.scene.spawn();
+        self.spawned.push(node);
+        let parent = deserializer.deserialize_struct(
+            "Node",
+            &["parent", "components"],
+            NodeVisitor {
+                scene: &mut *self.scene,
+                node,
+            },
+        )?;
+
+        Ok((node, parent))
+    }
+}
+
+struct NodeVisitor<'a> {
+    scene: &'a mut Scene,
+    node: Node,
+}
+
+impl<'de> Visitor<'de> for NodeVisitor<'_> {
+    type Value = Option<usize>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a node")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut parent = None;
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "parent" => parent = map.next_value::<Option<usize>>()?,
+                "components" => map.next_value_seed(ComponentsSeed {
+                    scene: &mut *self.scene,
+                    node: self.node,
+                })?,
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+
+        Ok(parent)
+    }
+}
+
+struct ComponentsSeed<'a> {
+    scene: &'a mut Scene,
+    node: Node,
+}
+
+impl<'de> DeserializeSeed<'de> for ComponentsSeed<'_> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de> Visitor<'de> for ComponentsSeed<'_> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a map of components")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        while let Some(name) = map.next_key::<String>()? {
+            let registration = self
+                .scene
+                .registry
+                .get(name.as_str())
+                .copied()
+                .ok_or_else(|| A::Error::custom(format!("unregistered component `{name}`")))?;
+
+            map.next_value_seed(ComponentSeed {
+                registration,
+                scene: &mut *self.scene,
+                node: self.node,
+            })?;
+        }
+
+        Ok(())
+    }
+}
+
+struct ComponentSeed<'a> {
+    registration: ComponentRegistration,
+    scene: &'a mut Scene,
+    node: Node,
+}
+
+impl<'de> DeserializeSeed<'de> for ComponentSeed<'_> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        let mut deserializer = <dyn erased_serde::Deserializer>::erase(deserializer);
+        (self.registration.deserialize)(&mut deserializer, self.scene, self.node)
+            .map_err(D::Error::custom)
+    }
+}
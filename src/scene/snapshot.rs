@@ -0,0 +1,219 @@
+use std::any::TypeId;
+use std::collections::BTreeMap;
+
+use nohash::IntMap;
+use nohash::IntSet;
+
+use crate::scene::events::EventBuffer;
+use crate::scene::relationship::RelationshipTable;
+use crate::scene::DynamicComponentTable;
+use crate::scene::TagTable;
+use crate::Node;
+use crate::Scene;
+
+/// # Scene Snapshot
+///
+/// Copy of the nodes, hierarchy, components, tags, and relationships of a scene returned by
+/// [Scene::snapshot] that can be restored later with [Scene::restore]. Resources aren't captured.
+pub struct SceneSnapshot {
+    generations: Vec<u32>,
+    nodes: IntSet<Node>,
+    parents: IntMap<Node, Node>,
+    children: IntMap<Node, Vec<Node>>,
+    component_tables: BTreeMap<TypeId, Box<dyn DynamicComponentTable>>,
+    tag_tables: BTreeMap<TypeId, TagTable>,
+    relationship_tables: BTreeMap<TypeId, RelationshipTable>,
+}
+
+impl SceneSnapshot {
+    /// Returns the number of nodes in the snapshot.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Returns true if the snapshot has no nodes.
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Returns true if the snapshot contains the given node.
+    pub fn contains(&self, node: Node) -> bool {
+        self.nodes.contains(&node)
+    }
+}
+
+impl Clone for SceneSnapshot {
+    fn clone(&self) -> Self {
+        Self {
+            generations: self.generations.clone(),
+            nodes: self.nodes.clone(),
+            parents: self.parents.clone(),
+            children: self.children.clone(),
+            component_tables: self
+                .component_tables
+                .iter()
+                .map(|(type_id, table)| (*type_id, table.clone_table()))
+                .collect(),
+            tag_tables: self.tag_tables.clone(),
+            relationship_tables: self.relationship_tables.clone(),
+        }
+    }
+}
+
+impl Scene {
+    /// Returns a copy of the nodes, hierarchy, components, tags, and relationships of the scene.
+    /// Component tables are copied as whole arrays rather than node by node.
+    pub fn snapshot(&self) -> SceneSnapshot {
+        SceneSnapshot {
+            generations: self.generations.clone(),
+            nodes: self.nodes.clone(),
+            parents: self.parents.clone(),
+            children: self.children.clone(),
+            component_tables: self
+                .component_indexes
+                .iter()
+                .map(|(type_id, index)| (*type_id, self.component_tables[*index].clone_table()))
+                .collect(),
+            tag_tables: self
+                .tag_tables
+                .iter()
+                .map(|(type_id, table)| {
+                    let table = TagTable {
+                        nodes: table.nodes.clone(),
+                        events: EventBuffer::new(),
+                    };
+                    (*type_id, table)
+                })
+                .collect(),
+            relationship_tables: self.relationship_tables.clone(),
+        }
+    }
+
+    /// Replaces the nodes, hierarchy, components, tags, and relationships of the scene with the
+    /// ones captured by the snapshot, reusing the existing allocations where possible. Indexes
+    /// that are free after the restore get a new generation, so nodes spawned later don't alias
+    /// handles to nodes spawned after the snapshot was taken. Pending events are cleared;
+    /// resources and registrations are kept.
+    pub fn restore(&mut self, snapshot: &SceneSnapshot) {
+        let len = self.generations.len().max(snapshot.generations.len());
+        self.generations.resize(len, 0);
+        self.free_indexes.clear();
+        for index in 0..len {
+            let snapshot_generation = snapshot.generations.get(index).copied().unwrap_or(0);
+            let generation = &mut self.generations[index];
+            if snapshot.contains(Node::new(index as u32, snapshot_generation)) {
+                *generation = snapshot_generation;
+            } else {
+                let alive = self.nodes.contains(&Node::new(index as u32, *generation));
+                *generation = (*generation)
+                    .max(snapshot_generation)
+                    .wrapping_add(u32::from(alive));
+                self.free_indexes.push(index as u32);
+            }
+        }
+        self.free_indexes.reverse();
+
+        self.nodes.clone_from(&snapshot.nodes);
+        self.parents.clone_from(&snapshot.parents);
+        self.children.clone_from(&snapshot.children);
+        self.parent_ticks.clear();
+        self.node_events.clear();
+
+        for (type_id, index) in &self.component_indexes {
+            let table = &mut self.component_tables[*index];
+            match snapshot.component_tables.get(type_id) {
+                Some(snapshot_table) => table.restore(snapshot_table.as_ref()),
+                None => {
+                    table.clear();
+                    table.clear_events();
+                }
+            }
+        }
+
+        for (type_id, snapshot_table) in &snapshot.component_tables {
+            if !self.component_indexes.contains_key(type_id) {
+                self.component_indexes
+                    .insert(*type_id, self.component_tables.len());
+                self.component_tables.push(snapshot_table.clone_table());
+            }
+        }
+
+        self.tag_tables.clone_from(&snapshot.tag_tables);
+        self.relationship_tables
+            .clone_from(&snapshot.relationship_tables);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scene::relationship::Relationship;
+
+    struct AttachedTo;
+
+    impl Relationship for AttachedTo {}
+
+    #[test]
+    fn restore_returns_scene_to_snapshot() {
+        let mut scene = Scene::new();
+        let parent = scene.spawn_with(17u32);
+        let child = scene.spawn_with(true);
+        scene.set_parent(child, parent);
+        scene.relate::<AttachedTo>(child, parent);
+        let snapshot = scene.snapshot();
+
+        scene.set(parent, 192u32);
+        scene.remove::<bool>(child);
+        scene.despawn(child);
+        let spawned = scene.spawn_with(1u32);
+        scene.restore(&snapshot);
+
+        assert!(scene.contains(child));
+        assert!(!scene.contains(spawned));
+        assert_eq!(scene.get::<u32>(parent), Some(&17));
+        assert_eq!(scene.get::<bool>(child), Some(&true));
+        assert_eq!(scene.get::<u32>(spawned), None);
+        assert_eq!(scene.get_parent(child), Some(parent));
+        assert_eq!(scene.related::<AttachedTo>(child), [parent]);
+        assert!(scene.node_events().is_empty());
+    }
+
+    #[test]
+    fn restore_spawn_does_not_alias_nodes_spawned_after_snapshot() {
+        let mut scene = Scene::new();
+        let snapshot = scene.snapshot();
+        let spawned = scene.spawn();
+
+        scene.restore(&snapshot);
+        let node = scene.spawn();
+
+        assert_ne!(node, spawned);
+        assert!(!scene.contains(spawned));
+    }
+
+    #[test]
+    fn restore_clears_components_added_after_snapshot() {
+        let mut scene = Scene::new();
+        let node = scene.spawn();
+        let snapshot = scene.snapshot();
+        scene.add(node, 17u32);
+
+        scene.restore(&snapshot);
+
+        assert_eq!(scene.get::<u32>(node), None);
+        assert!(scene.events::<u32>().is_empty());
+    }
+
+    #[test]
+    fn restore_into_other_scene_copies_components() {
+        let mut scene = Scene::new();
+        let node = scene.spawn_with(17u32);
+        let snapshot = scene.snapshot();
+
+        let mut other = Scene::new();
+        other.restore(&snapshot);
+
+        assert_eq!(other.get::<u32>(node), Some(&17));
+        assert_eq!(snapshot.len(), 1);
+    }
+}
@@ -0,0 +1,179 @@
+use std::any::TypeId;
+use std::mem::size_of;
+
+use crate::Scene;
+
+/// # Scene Stats
+///
+/// Snapshot of the size of a scene returned by [Scene::stats].
+#[derive(Clone, Debug)]
+pub struct SceneStats {
+    /// Number of nodes in the scene.
+    pub nodes: usize,
+    /// Number of node events waiting to be cleared.
+    pub node_events: usize,
+    /// Stats for each component type that has been added to the scene, sorted by type name.
+    pub components: Vec<ComponentStats>,
+    /// Stats for each tag type that has been added to the scene.
+    pub tags: Vec<TagStats>,
+    /// Number of resources in the scene.
+    pub resources: usize,
+}
+
+impl SceneStats {
+    /// Returns the total number of component values in the scene.
+    pub fn component_count(&self) -> usize {
+        self.components.iter().map(|stats| stats.count).sum()
+    }
+
+    /// Returns the total memory in bytes allocated by the component tables.
+    pub fn component_memory(&self) -> usize {
+        self.components.iter().map(|stats| stats.memory).sum()
+    }
+
+    /// Returns the total number of node, component, and tag events waiting to be cleared.
+    pub fn event_backlog(&self) -> usize {
+        self.node_events
+            + self
+                .components
+                .iter()
+                .map(|stats| stats.events)
+                .sum::<usize>()
+            + self.tags.iter().map(|stats| stats.events).sum::<usize>()
+    }
+}
+
+/// # Component Stats
+///
+/// Size of the storage of a single component type.
+#[derive(Copy, Clone, Debug)]
+pub struct ComponentStats {
+    /// Type id of the component.
+    pub type_id: TypeId,
+    /// Type name of the component.
+    pub type_name: &'static str,
+    /// Number of nodes with the component.
+    pub count: usize,
+    /// Memory in bytes allocated by the component table. Memory owned by the component values
+    /// themselves, e.g. the contents of a [String], isn't included.
+    pub memory: usize,
+    /// Number of component events waiting to be cleared.
+    pub events: usize,
+}
+
+/// # Tag Stats
+///
+/// Size of the storage of a single tag type.
+#[derive(Copy, Clone, Debug)]
+pub struct TagStats {
+    /// Type id of the tag.
+    pub type_id: TypeId,
+    /// Number of nodes with the tag.
+    pub count: usize,
+    /// Number of tag events waiting to be cleared.
+    pub events: usize,
+}
+
+impl Scene {
+    /// Returns the number of nodes, the per-type component and tag counts, memory usage, and
+    /// event backlog sizes of the scene.
+    pub fn stats(&self) -> SceneStats {
+        let mut components = self
+            .component_tables
+            .iter()
+            .map(|table| table.stats())
+            .collect::<Vec<_>>();
+        components.sort_by_key(|stats| stats.type_name);
+
+        let tags = self
+            .tag_tables
+            .iter()
+            .map(|(type_id, table)| TagStats {
+                type_id: *type_id,
+                count: table.nodes.len(),
+                events: table.events.len(),
+            })
+            .collect();
+
+        SceneStats {
+            nodes: self.nodes.len(),
+            node_events: self.node_events.len(),
+            components,
+            tags,
+            resources: self.resources.len(),
+        }
+    }
+
+    /// Returns the type ids and type names of all of the component types that have been added to
+    /// the scene.
+    pub fn component_types(&self) -> impl '_ + Iterator<Item = (TypeId, &'static str)> {
+        self.component_indexes
+            .iter()
+            .map(|(type_id, index)| (*type_id, self.component_tables[*index].type_name()))
+    }
+
+    /// Returns the names of the components registered for serialization.
+    pub fn registered_names(&self) -> impl '_ + Iterator<Item = &'static str> {
+        self.registry.keys().copied()
+    }
+}
+
+/// Returns the memory in bytes allocated by the vector.
+pub(super) fn vec_memory<T>(vec: &Vec<T>) -> usize {
+    vec.capacity() * size_of::<T>()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Selected;
+
+    impl crate::Tag for Selected {}
+
+    #[test]
+    fn stats_returns_counts_and_event_backlog() {
+        let mut scene = Scene::new();
+        let node = scene.spawn_with((17u32, true));
+        scene.spawn_with(192u32);
+        scene.tag::<Selected>(node);
+        scene.insert_resource(1.0f32);
+
+        let stats = scene.stats();
+
+        assert_eq!(stats.nodes, 2);
+        assert_eq!(stats.node_events, 2);
+        assert_eq!(stats.component_count(), 3);
+        assert_eq!(stats.event_backlog(), 6);
+        assert_eq!(stats.resources, 1);
+        let u32_stats = stats
+            .components
+            .iter()
+            .find(|stats| stats.type_id == TypeId::of::<u32>())
+            .unwrap();
+        assert_eq!(u32_stats.count, 2);
+        assert!(u32_stats.memory >= 2 * size_of::<u32>());
+        assert_eq!(stats.tags[0].count, 1);
+    }
+
+    #[test]
+    fn stats_after_clear_events_returns_empty_backlog() {
+        let mut scene = Scene::new();
+        scene.spawn_with(17u32);
+        scene.clear_events();
+
+        assert_eq!(scene.stats().event_backlog(), 0);
+    }
+
+    #[test]
+    fn component_types_returns_added_component_types() {
+        let mut scene = Scene::new();
+        scene.spawn_with(17u32);
+
+        assert_eq!(
+            scene.component_types().collect::<Vec<_>>(),
+            [(TypeId::of::<u32>(), "u32")]
+        );
+        assert!(scene.registered_names().any(|name| name == "Name"));
+    }
+}
@@ -0,0 +1,263 @@
+use std::fmt;
+
+use nohash::IntSet;
+
+use crate::Node;
+use crate::Scene;
+
+/// # Validation Report
+///
+/// Integrity problems found by [Scene::validate].
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ValidationReport {
+    /// Problems found in the scene.
+    pub errors: Vec<ValidationError>,
+}
+
+impl ValidationReport {
+    /// Returns true if no problems were found.
+    pub fn is_ok(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+impl fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.errors.is_empty() {
+            return f.write_str("scene is valid");
+        }
+
+        writeln!(f, "scene has {} problems:", self.errors.len())?;
+        for error in &self.errors {
+            writeln!(f, "- {error}")?;
+        }
+        Ok(())
+    }
+}
+
+/// # Validation Error
+///
+/// Single integrity problem found by [Scene::validate].
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ValidationError {
+    /// Node's generation doesn't match the current generation of its index.
+    StaleGeneration(Node),
+    /// Index of a live node is in the free list.
+    FreeIndexInUse(Node),
+    /// Hierarchy refers to a node that isn't in the scene.
+    DespawnedInHierarchy(Node),
+    /// Node has a parent that doesn't list it as a child.
+    MissingChild {
+        /// Parent node.
+        parent: Node,
+        /// Child node.
+        child: Node,
+    },
+    /// Node is listed as a child of a node that isn't its parent.
+    MissingParent {
+        /// Parent node.
+        parent: Node,
+        /// Child node.
+        child: Node,
+    },
+    /// Node is its own ancestor.
+    Cycle(Node),
+    /// Component table has a value for a node that isn't in the scene.
+    DanglingComponent {
+        /// Type name of the component.
+        type_name: &'static str,
+        /// Node of the value.
+        node: Node,
+    },
+    /// Component table's sparse index doesn't point at the node's value.
+    CorruptComponentIndex {
+        /// Type name of the component.
+        type_name: &'static str,
+        /// Node of the value.
+        node: Node,
+    },
+    /// Tag table has a node that isn't in the scene.
+    DanglingTag(Node),
+    /// Relationship refers to a node that isn't in the scene.
+    DanglingRelationship(Node),
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::StaleGeneration(node) => write!(f, "{node:?} has a stale generation"),
+            Self::FreeIndexInUse(node) => write!(f, "index of {node:?} is in the free list"),
+            Self::DespawnedInHierarchy(node) => {
+                write!(f, "hierarchy refers to despawned {node:?}")
+            }
+            Self::MissingChild { parent, child } => {
+                write!(f, "{child:?} isn't a child of its parent {parent:?}")
+            }
+            Self::MissingParent { parent, child } => {
+                write!(
+                    f,
+                    "{child:?} is a child of {parent:?} but has another parent"
+                )
+            }
+            Self::Cycle(node) => write!(f, "{node:?} is its own ancestor"),
+            Self::DanglingComponent { type_name, node } => {
+                write!(f, "{type_name} component of despawned {node:?}")
+            }
+            Self::CorruptComponentIndex { type_name, node } => {
+                write!(f, "{type_name} component index of {node:?} is corrupt")
+            }
+            Self::DanglingTag(node) => write!(f, "tag of despawned {node:?}"),
+            Self::DanglingRelationship(node) => {
+                write!(f, "relationship of despawned {node:?}")
+            }
+        }
+    }
+}
+
+impl Scene {
+    /// Checks that the nodes, hierarchy, component tables, tags, and relationships of the scene
+    /// are consistent with each other and returns the problems found. Only available in debug
+    /// builds or with the `validate` feature.
+    pub fn validate(&self) -> ValidationReport {
+        let mut errors = Vec::new();
+
+        let free_indexes = self.free_indexes.iter().copied().collect::<IntSet<_>>();
+        let mut nodes = self.nodes.iter().copied().collect::<Vec<_>>();
+        nodes.sort();
+        for node in &nodes {
+            if self.generations.get(node.index as usize) != Some(&node.generation) {
+                errors.push(ValidationError::StaleGeneration(*node));
+            }
+
+            if free_indexes.contains(&node.index) {
+                errors.push(ValidationError::FreeIndexInUse(*node));
+            }
+        }
+
+        let mut parents = self.parents.iter().collect::<Vec<_>>();
+        parents.sort();
+        for (child, parent) in parents {
+            for node in [child, parent] {
+                if !self.contains(*node) {
+                    errors.push(ValidationError::DespawnedInHierarchy(*node));
+                }
+            }
+
+            if !self
+                .get_children(*parent)
+                .is_some_and(|children| children.contains(child))
+            {
+                errors.push(ValidationError::MissingChild {
+                    parent: *parent,
+                    child: *child,
+                });
+            }
+        }
+
+        let mut children = self.children.iter().collect::<Vec<_>>();
+        children.sort();
+        for (parent, children) in children {
+            for child in children {
+                if self.get_parent(*child) != Some(*parent) {
+                    errors.push(ValidationError::MissingParent {
+                        parent: *parent,
+                        child: *child,
+                    });
+                }
+            }
+        }
+
+        for node in &nodes {
+            let mut ancestor = self.get_parent(*node);
+            let mut depth = 0;
+            while let Some(parent) = ancestor {
+                if parent == *node || depth > self.parents.len() {
+                    errors.push(ValidationError::Cycle(*node));
+                    break;
+                }
+
+                ancestor = self.get_parent(parent);
+                depth += 1;
+            }
+        }
+
+        for table in &self.component_tables {
+            table.validate(self, &mut errors);
+        }
+
+        for table in self.tag_tables.values() {
+            let mut nodes = table.nodes.iter().copied().collect::<Vec<_>>();
+            nodes.sort();
+            errors.extend(
+                nodes
+                    .into_iter()
+                    .filter(|node| !self.contains(*node))
+                    .map(ValidationError::DanglingTag),
+            );
+        }
+
+        for table in self.relationship_tables.values() {
+            for (source, target) in table.links() {
+                for node in [source, target] {
+                    if !self.contains(node) {
+                        errors.push(ValidationError::DanglingRelationship(node));
+                    }
+                }
+            }
+        }
+
+        ValidationReport { errors }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validate_consistent_scene_returns_ok() {
+        let mut scene = Scene::new();
+        let parent = scene.spawn_with(17u32);
+        let child = scene.spawn_with((192u32, true));
+        scene.set_parent(child, parent);
+        let despawned = scene.spawn_with(1u32);
+        scene.despawn(despawned);
+        scene.remove::<u32>(parent);
+
+        assert!(scene.validate().is_ok());
+    }
+
+    #[test]
+    fn validate_corrupt_hierarchy_returns_errors() {
+        let mut scene = Scene::new();
+        let parent = scene.spawn();
+        let child = scene.spawn();
+        scene.set_parent(child, parent);
+        scene.children.clear();
+        scene.parents.insert(parent, child);
+
+        let report = scene.validate();
+
+        assert!(report
+            .errors
+            .contains(&ValidationError::MissingChild { parent, child }));
+        assert!(report.errors.contains(&ValidationError::Cycle(child)));
+    }
+
+    #[test]
+    fn validate_corrupt_component_table_returns_errors() {
+        let mut scene = Scene::new();
+        let node = scene.spawn_with(17u32);
+        scene.nodes.remove(&node);
+
+        let report = scene.validate();
+
+        assert_eq!(
+            report.errors,
+            [ValidationError::DanglingComponent {
+                type_name: "u32",
+                node
+            }]
+        );
+    }
+}
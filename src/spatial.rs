@@ -0,0 +1,420 @@
+//! # Spatial
+
+use glam::Vec3;
+use nohash::IntMap;
+
+use crate::Aabb;
+use crate::BoundingSphere;
+use crate::ComponentEvent;
+use crate::Node;
+use crate::NodeEvent;
+use crate::Scene;
+use crate::WorldBounds;
+
+/// # Spatial Index
+///
+/// Bounding volume hierarchy over the [WorldBounds] of the nodes in a scene for raycasts and
+/// overlap queries. Insert it as a scene resource to have it updated by
+/// [crate::systems::update_spatial_index], or call [SpatialIndex::update] directly.
+///
+/// ```
+/// # use glam::Vec3;
+/// # use pulse::spatial::SpatialIndex;
+/// # use pulse::systems;
+/// # use pulse::Aabb;
+/// # use pulse::Scene;
+/// let mut scene = Scene::new();
+/// let node = scene.spawn_with(Aabb::new(Vec3::splat(-1.0), Vec3::splat(1.0)));
+/// systems::compute_world_bounds(&mut scene);
+///
+/// let mut index = SpatialIndex::new();
+/// index.update(&scene);
+///
+/// let hit = index.raycast(Vec3::new(0.0, 0.0, 5.0), Vec3::NEG_Z).unwrap();
+/// assert_eq!(hit.node, node);
+/// assert_eq!(hit.distance, 4.0);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct SpatialIndex {
+    entries: Vec<Entry>,
+    free_entries: Vec<usize>,
+    root: Option<usize>,
+    leaves: IntMap<Node, usize>,
+    tick: u32,
+}
+
+#[derive(Clone, Debug)]
+struct Entry {
+    aabb: Aabb,
+    parent: Option<usize>,
+    kind: EntryKind,
+}
+
+#[derive(Copy, Clone, Debug)]
+enum EntryKind {
+    Leaf(Node),
+    Branch(usize, usize),
+}
+
+/// # Raycast Hit
+///
+/// Node hit by [SpatialIndex::raycast].
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RaycastHit {
+    /// Node whose bounds were hit.
+    pub node: Node,
+    /// Distance from the origin of the ray to where it enters the bounds of the node.
+    pub distance: f32,
+}
+
+impl SpatialIndex {
+    /// Returns an empty index.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of nodes in the index.
+    pub fn len(&self) -> usize {
+        self.leaves.len()
+    }
+
+    /// Returns true if the index has no nodes.
+    pub fn is_empty(&self) -> bool {
+        self.leaves.is_empty()
+    }
+
+    /// Returns true if the index contains the node.
+    pub fn contains(&self, node: Node) -> bool {
+        self.leaves.contains_key(&node)
+    }
+
+    /// Removes all of the nodes from the index, so the next update inserts all of the nodes of the
+    /// scene again.
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Inserts, moves, or removes the nodes whose [WorldBounds] changed since the previous update.
+    /// Removals are detected from the scene's events, so the index must be updated before the
+    /// events are cleared.
+    pub fn update(&mut self, scene: &Scene) {
+        for node in scene.changed::<WorldBounds>(self.tick) {
+            let aabb = scene.get::<WorldBounds>(node).unwrap().aabb;
+            self.insert(node, aabb);
+        }
+
+        let removed = scene
+            .events::<WorldBounds>()
+            .iter()
+            .filter_map(|event| match event {
+                ComponentEvent::Removed(node) => Some(*node),
+                ComponentEvent::Added(_) | ComponentEvent::Modified(_) => None,
+            });
+        let despawned = scene.node_events().iter().filter_map(|event| match event {
+            NodeEvent::Despawned(node) => Some(*node),
+            NodeEvent::Spawned(_) => None,
+        });
+        for node in removed.chain(despawned) {
+            if scene.get::<WorldBounds>(node).is_none() {
+                self.remove(node);
+            }
+        }
+
+        self.tick = scene.change_tick().wrapping_sub(1);
+    }
+
+    /// Inserts the node with the given bounds, or moves it if it's already in the index.
+    pub fn insert(&mut self, node: Node, aabb: Aabb) {
+        if let Some(leaf) = self.leaves.get(&node).copied() {
+            if self.entries[leaf].aabb == aabb {
+                return;
+            }
+            self.remove_leaf(leaf);
+        }
+
+        let leaf = self.allocate(Entry {
+            aabb,
+            parent: None,
+            kind: EntryKind::Leaf(node),
+        });
+        self.leaves.insert(node, leaf);
+        self.insert_leaf(leaf);
+    }
+
+    /// Removes the node from the index.
+    pub fn remove(&mut self, node: Node) {
+        if let Some(leaf) = self.leaves.remove(&node) {
+            self.remove_leaf(leaf);
+        }
+    }
+
+    /// Returns the closest node whose bounds are hit by the ray, if any. The direction doesn't
+    /// need to be normalized.
+    pub fn raycast(&self, origin: Vec3, direction: Vec3) -> Option<RaycastHit> {
+        let direction = direction.try_normalize()?;
+        let inverse_direction = direction.recip();
+
+        let mut closest = None::<RaycastHit>;
+        let mut stack = self.root.into_iter().collect::<Vec<_>>();
+        while let Some(index) = stack.pop() {
+            let entry = &self.entries[index];
+            let Some(distance) = ray_distance(&entry.aabb, origin, inverse_direction) else {
+                continue;
+            };
+            if closest.is_some_and(|hit| hit.distance <= distance) {
+                continue;
+            }
+
+            match entry.kind {
+                EntryKind::Leaf(node) => closest = Some(RaycastHit { node, distance }),
+                EntryKind::Branch(left, right) => stack.extend([left, right]),
+            }
+        }
+
+        closest
+    }
+
+    /// Returns the nodes whose bounds overlap the box.
+    pub fn query_aabb(&self, aabb: &Aabb) -> Vec<Node> {
+        self.query(
+            |bounds| bounds.intersects(aabb),
+            |bounds| bounds.intersects(aabb),
+        )
+    }
+
+    /// Returns the nodes whose bounds overlap the sphere.
+    pub fn query_sphere(&self, sphere: &BoundingSphere) -> Vec<Node> {
+        let sphere_aabb = sphere.aabb();
+        self.query(
+            |bounds| bounds.intersects(&sphere_aabb),
+            |bounds| {
+                let closest = sphere.center.clamp(bounds.min, bounds.max);
+                sphere.contains_point(closest)
+            },
+        )
+    }
+
+    fn query(
+        &self,
+        branch_overlaps: impl Fn(&Aabb) -> bool,
+        leaf_overlaps: impl Fn(&Aabb) -> bool,
+    ) -> Vec<Node> {
+        let mut nodes = Vec::new();
+        let mut stack = self.root.into_iter().collect::<Vec<_>>();
+        while let Some(index) = stack.pop() {
+            let entry = &self.entries[index];
+            match entry.kind {
+                EntryKind::Leaf(node) => {
+                    if leaf_overlaps(&entry.aabb) {
+                        nodes.push(node);
+                    }
+                }
+                EntryKind::Branch(left, right) => {
+                    if branch_overlaps(&entry.aabb) {
+                        stack.extend([left, right]);
+                    }
+                }
+            }
+        }
+
+        nodes.sort();
+        nodes
+    }
+
+    fn allocate(&mut self, entry: Entry) -> usize {
+        match self.free_entries.pop() {
+            Some(index) => {
+                self.entries[index] = entry;
+                index
+            }
+            None => {
+                self.entries.push(entry);
+                self.entries.len() - 1
+            }
+        }
+    }
+
+    /// Inserts the leaf next to the entry whose bounds grow the least, descending greedily from the
+    /// root.
+    fn insert_leaf(&mut self, leaf: usize) {
+        let Some(mut sibling) = self.root else {
+            self.root = Some(leaf);
+            return;
+        };
+
+        let aabb = self.entries[leaf].aabb;
+        while let EntryKind::Branch(left, right) = self.entries[sibling].kind {
+            let growth = |index: usize| {
+                let bounds = &self.entries[index].aabb;
+                area(&bounds.union(&aabb)) - area(bounds)
+            };
+            sibling = if growth(left) <= growth(right) {
+                left
+            } else {
+                right
+            };
+        }
+
+        let parent = self.entries[sibling].parent;
+        let branch = self.allocate(Entry {
+            aabb: self.entries[sibling].aabb.union(&aabb),
+            parent,
+            kind: EntryKind::Branch(sibling, leaf),
+        });
+        self.entries[sibling].parent = Some(branch);
+        self.entries[leaf].parent = Some(branch);
+
+        match parent {
+            Some(parent) => {
+                self.replace_child(parent, sibling, branch);
+                self.refit(parent);
+            }
+            None => self.root = Some(branch),
+        }
+    }
+
+    /// Removes the leaf and replaces its parent branch with its sibling.
+    fn remove_leaf(&mut self, leaf: usize) {
+        self.free_entries.push(leaf);
+
+        let Some(parent) = self.entries[leaf].parent else {
+            self.root = None;
+            return;
+        };
+
+        let EntryKind::Branch(left, right) = self.entries[parent].kind else {
+            unreachable!("parent of a spatial index entry is a leaf");
+        };
+        let sibling = if left == leaf { right } else { left };
+        let grandparent = self.entries[parent].parent;
+        self.entries[sibling].parent = grandparent;
+        self.free_entries.push(parent);
+
+        match grandparent {
+            Some(grandparent) => {
+                self.replace_child(grandparent, parent, sibling);
+                self.refit(grandparent);
+            }
+            None => self.root = Some(sibling),
+        }
+    }
+
+    fn replace_child(&mut self, branch: usize, child: usize, replacement: usize) {
+        if let EntryKind::Branch(left, right) = &mut self.entries[branch].kind {
+            if *left == child {
+                *left = replacement;
+            } else {
+                *right = replacement;
+            }
+        }
+    }
+
+    /// Recomputes the bounds of the branch and its ancestors from their children.
+    fn refit(&mut self, branch: usize) {
+        let mut current = Some(branch);
+        while let Some(index) = current {
+            if let EntryKind::Branch(left, right) = self.entries[index].kind {
+                self.entries[index].aabb = self.entries[left].aabb.union(&self.entries[right].aabb);
+            }
+            current = self.entries[index].parent;
+        }
+    }
+}
+
+/// Returns half of the surface area of the box.
+fn area(aabb: &Aabb) -> f32 {
+    let size = aabb.max - aabb.min;
+    size.x * size.y + size.y * size.z + size.z * size.x
+}
+
+/// Returns the distance along the ray to where it enters the box, or zero if the origin is inside
+/// the box.
+fn ray_distance(aabb: &Aabb, origin: Vec3, inverse_direction: Vec3) -> Option<f32> {
+    let first = (aabb.min - origin) * inverse_direction;
+    let second = (aabb.max - origin) * inverse_direction;
+    let enter = first.min(second).max_element().max(0.0);
+    let exit = first.max(second).min_element();
+    (enter <= exit).then_some(enter)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LocalTransform;
+
+    fn unit_box(center: Vec3) -> Aabb {
+        Aabb::from_center_half_extents(center, Vec3::splat(0.5))
+    }
+
+    #[test]
+    fn raycast_returns_closest_hit() {
+        let mut scene = Scene::new();
+        let mut index = SpatialIndex::new();
+        let nodes = (0..8)
+            .map(|i| {
+                let center = Vec3::new(i as f32 * 2.0, 0.0, 0.0);
+                let node = scene.spawn();
+                index.insert(node, unit_box(center));
+                node
+            })
+            .collect::<Vec<_>>();
+
+        let hit = index.raycast(Vec3::new(20.0, 0.0, 0.0), Vec3::NEG_X);
+        let miss = index.raycast(Vec3::new(20.0, 2.0, 0.0), Vec3::NEG_X);
+
+        assert_eq!(
+            hit,
+            Some(RaycastHit {
+                node: nodes[7],
+                distance: 5.5
+            })
+        );
+        assert_eq!(miss, None);
+    }
+
+    #[test]
+    fn query_aabb_and_sphere_return_overlapping_nodes() {
+        let mut scene = Scene::new();
+        let mut index = SpatialIndex::new();
+        let nodes = (0..8)
+            .map(|i| {
+                let node = scene.spawn();
+                index.insert(node, unit_box(Vec3::new(0.0, i as f32 * 2.0, 0.0)));
+                node
+            })
+            .collect::<Vec<_>>();
+
+        let boxed = index.query_aabb(&Aabb::new(
+            Vec3::new(0.0, 1.5, 0.0),
+            Vec3::new(0.0, 4.0, 0.0),
+        ));
+        let sphered = index.query_sphere(&BoundingSphere::new(Vec3::new(0.0, 9.0, 0.0), 0.6));
+
+        assert_eq!(boxed, &nodes[1..3]);
+        assert_eq!(sphered, &nodes[4..6]);
+    }
+
+    #[test]
+    fn update_tracks_moved_and_despawned_nodes() {
+        let mut scene = Scene::new();
+        let moving = scene.spawn_with((LocalTransform::IDENTITY, unit_box(Vec3::ZERO)));
+        let despawned = scene.spawn_with((LocalTransform::IDENTITY, unit_box(Vec3::ZERO)));
+        crate::systems::compute_world_transform(&mut scene);
+        crate::systems::compute_world_bounds(&mut scene);
+        let mut index = SpatialIndex::new();
+        index.update(&scene);
+        scene.advance_change_tick();
+        scene.clear_events();
+
+        scene.set(moving, LocalTransform::from_position(Vec3::X * 10.0));
+        scene.despawn(despawned);
+        crate::systems::compute_world_transform(&mut scene);
+        crate::systems::compute_world_bounds(&mut scene);
+        index.update(&scene);
+
+        assert_eq!(index.len(), 1);
+        assert!(!index.contains(despawned));
+        assert_eq!(index.query_aabb(&unit_box(Vec3::X * 10.0)), [moving]);
+        assert!(index.query_aabb(&unit_box(Vec3::ZERO)).is_empty());
+    }
+}
@@ -0,0 +1,282 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::Scene;
+
+/// # States
+///
+/// Scene resource holding a stack of user-defined application states of type `S`, e.g. a main
+/// menu, the game, and a pause menu pushed on top of the game. Registered with
+/// [crate::AppBuilder::add_state], which applies the requested transitions at the end of every
+/// frame and calls the hooks added with [crate::AppBuilder::on_enter] and friends. Systems are
+/// limited to states with [States::run_if_current] and [States::run_if_contains].
+///
+/// ```
+/// # use pulse::AppBuilder;
+/// # use pulse::Scene;
+/// # use pulse::States;
+/// # use pulse::systems::Stage;
+/// #[derive(Clone, Debug, PartialEq)]
+/// enum GameState {
+///     MainMenu,
+///     InGame,
+///     Paused,
+/// }
+///
+/// let mut app = AppBuilder::new();
+/// app.add_state(GameState::MainMenu)
+///     .on_enter(GameState::InGame, |scene| {
+///         // Spawn the level.
+///     });
+/// app.add_system(Stage::Update, |scene: &mut Scene| {
+///     // Move the player.
+/// })
+/// .run_if(States::run_if_current(GameState::InGame));
+/// ```
+#[derive(Clone, Debug)]
+pub struct States<S> {
+    stack: Vec<S>,
+    pending: Vec<Transition<S>>,
+}
+
+/// Transition requested through [States] and applied at the end of the frame.
+#[derive(Clone, Debug)]
+enum Transition<S> {
+    Push(S),
+    Pop,
+    Set(S),
+}
+
+impl<S: 'static + Clone + PartialEq + Send + Sync> States<S> {
+    /// Returns the states with the initial state pushed at the end of the first frame, or when
+    /// the application starts with [crate::AppBuilder::add_state].
+    pub fn new(initial: S) -> Self {
+        Self {
+            stack: Vec::new(),
+            pending: vec![Transition::Push(initial)],
+        }
+    }
+
+    /// Returns the state on top of the stack, or `None` if the stack is empty.
+    pub fn current(&self) -> Option<&S> {
+        self.stack.last()
+    }
+
+    /// Returns the stack of states from the bottom to the top.
+    pub fn stack(&self) -> &[S] {
+        &self.stack
+    }
+
+    /// Returns true if the state is on top of the stack.
+    pub fn is_current(&self, state: &S) -> bool {
+        self.current() == Some(state)
+    }
+
+    /// Returns true if the state is anywhere on the stack, e.g. the game below a pause menu.
+    pub fn contains(&self, state: &S) -> bool {
+        self.stack.contains(state)
+    }
+
+    /// Requests the state to be pushed on top of the stack, pausing the current state.
+    pub fn push(&mut self, state: S) {
+        self.pending.push(Transition::Push(state));
+    }
+
+    /// Requests the state on top of the stack to be popped, resuming the state below it.
+    pub fn pop(&mut self) {
+        self.pending.push(Transition::Pop);
+    }
+
+    /// Requests the state on top of the stack to be replaced with the state.
+    pub fn set(&mut self, state: S) {
+        self.pending.push(Transition::Set(state));
+    }
+
+    /// Returns a run criterion for systems that run only while the state is on top of the stack.
+    /// See [crate::systems::SystemConfig::run_if].
+    pub fn run_if_current(state: S) -> impl FnMut(&Scene) -> bool {
+        move |scene| {
+            scene
+                .get_resource::<Self>()
+                .is_some_and(|states| states.is_current(&state))
+        }
+    }
+
+    /// Returns a run criterion for systems that run while the state is anywhere on the stack.
+    /// See [crate::systems::SystemConfig::run_if].
+    pub fn run_if_contains(state: S) -> impl FnMut(&Scene) -> bool {
+        move |scene| {
+            scene
+                .get_resource::<Self>()
+                .is_some_and(|states| states.contains(&state))
+        }
+    }
+}
+
+type StateHook = Box<dyn FnMut(&mut Scene)>;
+
+/// Hooks of the states of type `S`, shared between the [crate::AppBuilder] and the transition
+/// hook it runs at the end of every frame.
+pub(crate) type SharedStateHooks<S> = Rc<RefCell<StateHooks<S>>>;
+
+/// Hooks called when states of type `S` are transitioned.
+pub(crate) struct StateHooks<S> {
+    pub(crate) enter: Vec<(S, StateHook)>,
+    pub(crate) exit: Vec<(S, StateHook)>,
+    pub(crate) pause: Vec<(S, StateHook)>,
+    pub(crate) resume: Vec<(S, StateHook)>,
+}
+
+impl<S: 'static + Clone + PartialEq + Send + Sync> StateHooks<S> {
+    pub(crate) fn new() -> Self {
+        Self {
+            enter: Vec::new(),
+            exit: Vec::new(),
+            pause: Vec::new(),
+            resume: Vec::new(),
+        }
+    }
+
+    /// Applies the transitions requested through the scene's [States] resource, including the
+    /// ones requested by the hooks, calling the hooks of the states along the way.
+    pub(crate) fn apply_transitions(&mut self, scene: &mut Scene) {
+        loop {
+            let Some(states) = scene.get_resource_mut::<States<S>>() else {
+                return;
+            };
+            if states.pending.is_empty() {
+                return;
+            }
+
+            for transition in std::mem::take(&mut states.pending) {
+                match transition {
+                    Transition::Push(state) => self.push(scene, state),
+                    Transition::Pop => self.pop(scene),
+                    Transition::Set(state) => self.set(scene, state),
+                }
+            }
+        }
+    }
+
+    fn push(&mut self, scene: &mut Scene, state: S) {
+        let states = scene.get_resource_mut::<States<S>>().unwrap();
+        let paused = states.current().cloned();
+        states.stack.push(state.clone());
+
+        if let Some(paused) = paused {
+            run(&mut self.pause, &paused, scene);
+        }
+        run(&mut self.enter, &state, scene);
+    }
+
+    fn set(&mut self, scene: &mut Scene, state: S) {
+        let states = scene.get_resource_mut::<States<S>>().unwrap();
+        let exited = states.stack.pop();
+        states.stack.push(state.clone());
+
+        if let Some(exited) = exited {
+            run(&mut self.exit, &exited, scene);
+        }
+        run(&mut self.enter, &state, scene);
+    }
+
+    fn pop(&mut self, scene: &mut Scene) {
+        let states = scene.get_resource_mut::<States<S>>().unwrap();
+        let Some(exited) = states.stack.pop() else {
+            return;
+        };
+        let resumed = states.current().cloned();
+
+        run(&mut self.exit, &exited, scene);
+        if let Some(resumed) = resumed {
+            run(&mut self.resume, &resumed, scene);
+        }
+    }
+}
+
+fn run<S: PartialEq>(hooks: &mut [(S, StateHook)], state: &S, scene: &mut Scene) {
+    for (_, hook) in hooks.iter_mut().filter(|(other, _)| other == state) {
+        hook(scene);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Copy, Clone, Debug, PartialEq)]
+    enum GameState {
+        Menu,
+        Game,
+        Paused,
+    }
+
+    fn log(name: &'static str) -> StateHook {
+        Box::new(move |scene| scene.get_resource_mut::<Vec<&str>>().unwrap().push(name))
+    }
+
+    fn scene() -> (Scene, StateHooks<GameState>) {
+        let mut scene = Scene::new();
+        scene.insert_resource(Vec::<&str>::new());
+        scene.insert_resource(States::new(GameState::Menu));
+
+        let mut hooks = StateHooks::new();
+        hooks.enter.push((GameState::Menu, log("enter menu")));
+        hooks.exit.push((GameState::Menu, log("exit menu")));
+        hooks.enter.push((GameState::Game, log("enter game")));
+        hooks.pause.push((GameState::Game, log("pause game")));
+        hooks.resume.push((GameState::Game, log("resume game")));
+        hooks.enter.push((GameState::Paused, log("enter paused")));
+        hooks.exit.push((GameState::Paused, log("exit paused")));
+        hooks.apply_transitions(&mut scene);
+
+        (scene, hooks)
+    }
+
+    #[test]
+    fn push_and_pop_pause_and_resume_state_below() {
+        let (mut scene, mut hooks) = scene();
+        let states = scene.get_resource_mut::<States<GameState>>().unwrap();
+        states.set(GameState::Game);
+        states.push(GameState::Paused);
+        hooks.apply_transitions(&mut scene);
+
+        let states = scene.get_resource::<States<GameState>>().unwrap();
+        assert_eq!(states.stack(), &[GameState::Game, GameState::Paused]);
+        assert!(states.is_current(&GameState::Paused));
+        assert!(states.contains(&GameState::Game));
+
+        scene.get_resource_mut::<States<GameState>>().unwrap().pop();
+        hooks.apply_transitions(&mut scene);
+
+        assert_eq!(
+            scene.get_resource::<Vec<&str>>().unwrap(),
+            &[
+                "enter menu",
+                "exit menu",
+                "enter game",
+                "pause game",
+                "enter paused",
+                "exit paused",
+                "resume game",
+            ]
+        );
+    }
+
+    #[test]
+    fn run_if_current_checks_top_of_stack() {
+        let (mut scene, mut hooks) = scene();
+        let mut in_menu = States::run_if_current(GameState::Menu);
+        let mut in_game = States::run_if_contains(GameState::Game);
+        assert!(in_menu(&scene));
+        assert!(!in_game(&scene));
+
+        let states = scene.get_resource_mut::<States<GameState>>().unwrap();
+        states.set(GameState::Game);
+        states.push(GameState::Paused);
+        hooks.apply_transitions(&mut scene);
+
+        assert!(!in_menu(&scene));
+        assert!(in_game(&scene));
+    }
+}
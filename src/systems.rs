@@ -1,64 +1,1446 @@
 //! # Systems
 
 use glam::Mat4;
+use glam::UVec2;
+use nohash::IntMap;
+use nohash::IntSet;
 
+use crate::animation::AnimationPlayer;
+use crate::assets::Asset;
+use crate::assets::AssetServer;
+use crate::assets::Assets;
+use crate::assets::Handle;
 use crate::components::WorldTransform;
+use crate::occlusion;
+use crate::render::light::world_transform;
+use crate::render::mesh::Mesh;
+use crate::render::probe::CaptureCamera;
+use crate::render::probe::ProbeCapture;
+use crate::render::probe::ReflectionProbe;
+use crate::render::skin::JointPalette;
+use crate::render::skin::SkinnedMesh;
+use crate::render::sprite::Sprite;
+use crate::render::target::RenderTarget;
+use crate::render::terrain::Terrain;
+use crate::render::terrain::TerrainChunk;
+use crate::render::terrain::TerrainChunks;
+use crate::render::text::Text;
+use crate::spatial::SpatialIndex;
+use crate::Aabb;
+use crate::BoundingSphere;
+use crate::Camera;
+use crate::Component;
+use crate::ComponentEvent;
 use crate::ComputedVisibility;
+use crate::HierarchyBounds;
 use crate::LocalTransform;
 use crate::Node;
+use crate::NodeEvent;
+use crate::PreviousWorldTransform;
+use crate::ScalingMode;
 use crate::Scene;
+use crate::Time;
 use crate::Visibility;
+use crate::VisibleNodes;
+use crate::Window;
+use crate::With;
+use crate::Without;
+use crate::WorldBounds;
+
+pub use crate::systems::schedule::ExclusiveSystem;
+pub use crate::systems::schedule::IntoSystem;
+pub use crate::systems::schedule::ParallelSystem;
+pub use crate::systems::schedule::Schedule;
+pub use crate::systems::schedule::ScheduleError;
+pub use crate::systems::schedule::System;
+pub use crate::systems::schedule::SystemConfig;
+pub use crate::systems::stages::Stage;
+pub use crate::systems::stages::Stages;
+
+mod schedule;
+mod stages;
+
+/// Label of [compute_visibility] in [Schedule::with_builtin_systems].
+pub const VISIBILITY: &str = "pulse::visibility";
+
+/// Label of [compute_world_transform] in [Schedule::with_builtin_systems].
+pub const TRANSFORM: &str = "pulse::transform";
+
+/// Label of [compute_mesh_bounds] in [Schedule::with_builtin_systems].
+pub const MESH_BOUNDS: &str = "pulse::mesh_bounds";
+
+/// Label of [compute_sprite_bounds] in [Schedule::with_builtin_systems].
+pub const SPRITE_BOUNDS: &str = "pulse::sprite_bounds";
+
+/// Label of [compute_text_bounds] in [Schedule::with_builtin_systems].
+pub const TEXT_BOUNDS: &str = "pulse::text_bounds";
+
+/// Label of [compute_world_bounds] in [Schedule::with_builtin_systems].
+pub const BOUNDS: &str = "pulse::bounds";
+
+/// Label of [fit_cameras] in [Schedule::with_builtin_systems].
+pub const FIT_CAMERAS: &str = "pulse::fit_cameras";
+
+/// Label of [cull_cameras] in [Schedule::with_builtin_systems].
+pub const CULL: &str = "pulse::cull";
+
+/// Label of [update_spatial_index] in [Schedule::with_builtin_systems].
+pub const SPATIAL: &str = "pulse::spatial";
+
+/// Label of [compute_joint_palettes] in [Schedule::with_builtin_systems].
+pub const SKIN: &str = "pulse::skin";
+
+/// Label of [capture_reflection_probes] in [Schedule::with_builtin_systems].
+pub const PROBES: &str = "pulse::probes";
+
+/// Label of [update_terrain] in [Schedule::with_builtin_systems].
+pub const TERRAIN: &str = "pulse::terrain";
+
+/// Label of [update_previous_transforms] in the [Stage::FixedUpdate] schedule of
+/// [Stages::with_builtin_systems].
+pub const PREVIOUS_TRANSFORM: &str = "pulse::previous_transform";
+
+/// Label of the system calling [interpolate_transforms] in the [Stage::Render] schedule of
+/// [Stages::with_builtin_systems].
+pub const INTERPOLATE_TRANSFORM: &str = "pulse::interpolate_transform";
+
+/// Label of [update_assets] in the [Stage::Update] schedule of [Stages::with_builtin_systems].
+pub const ASSETS: &str = "pulse::assets";
+
+/// Label of [animate] in the [Stage::Update] schedule of [Stages::with_builtin_systems].
+pub const ANIMATION: &str = "pulse::animation";
+
+/// Change tick up to which [compute_visibility] has propagated the visibilities.
+struct VisibilityTick(u32);
 
 /// Computes the visibility for all of the nodes in the scene.
-pub fn compute_visibility(scene: &Scene) {
-    for node in scene.get_root_nodes() {
-        compute_visibility_internal(scene, node, ComputedVisibility::Visible);
+///
+/// Only nodes that were spawned, whose [Visibility] or parent changed since the previous call, or
+/// whose [Visibility] was removed since the events were last cleared are recomputed. Their
+/// descendants are only visited while the computed visibility differs from the previous one.
+pub fn compute_visibility(scene: &mut Scene) {
+    let since = scene
+        .get_resource::<VisibilityTick>()
+        .map_or(0, |tick| tick.0);
+
+    let spawned = scene.node_events().iter().filter_map(|event| match event {
+        NodeEvent::Spawned(node) => Some(*node),
+        NodeEvent::Despawned(_) => None,
+    });
+    let removed = scene
+        .events::<Visibility>()
+        .iter()
+        .filter_map(|event| match event {
+            ComponentEvent::Removed(node) => Some(*node),
+            ComponentEvent::Added(_) | ComponentEvent::Modified(_) => None,
+        });
+    let mut dirty = scene
+        .changed::<Visibility>(since)
+        .chain(scene.parent_changed(since))
+        .chain(spawned)
+        .chain(removed)
+        .filter(|node| scene.contains(*node))
+        .map(|node| (scene.ancestors(node).count(), node))
+        .collect::<Vec<_>>();
+    dirty.sort();
+    dirty.dedup();
+
+    for (_, node) in dirty {
+        let parent_visibility = scene
+            .get_parent(node)
+            .and_then(|parent| scene.get::<ComputedVisibility>(parent).copied())
+            .unwrap_or(ComputedVisibility::Visible);
+        propagate_visibility(scene, node, parent_visibility);
     }
+
+    let tick = scene.change_tick().wrapping_sub(1);
+    scene.insert_resource(VisibilityTick(tick));
 }
 
-fn compute_visibility_internal(scene: &Scene, node: Node, parent_visibility: ComputedVisibility) {
-    let visibility = match scene.get::<Visibility>(node) {
-        Some(Visibility::Inherit) => parent_visibility,
-        Some(Visibility::Visible) => ComputedVisibility::Visible,
-        Some(Visibility::Invisible) => ComputedVisibility::Invisible,
-        None => parent_visibility,
-    };
+fn propagate_visibility(scene: &mut Scene, node: Node, parent_visibility: ComputedVisibility) {
+    let mut stack = vec![(node, parent_visibility)];
+    while let Some((node, parent_visibility)) = stack.pop() {
+        let visibility = match scene.get::<Visibility>(node) {
+            Some(Visibility::Inherit) => parent_visibility,
+            Some(Visibility::Visible) => ComputedVisibility::Visible,
+            Some(Visibility::Invisible) => ComputedVisibility::Invisible,
+            None => parent_visibility,
+        };
+
+        if scene.get::<ComputedVisibility>(node) == Some(&visibility) {
+            continue;
+        }
 
-    scene.set_or_add(node, visibility);
+        scene.set_or_add(node, visibility);
 
-    for node in scene.get_children(node).into_iter().flatten().copied() {
-        compute_visibility_internal(scene, node, visibility);
+        for child in scene.get_children(node).into_iter().flatten().copied() {
+            stack.push((child, visibility));
+        }
     }
 }
 
+/// Change tick up to which [compute_world_transform] has propagated the world transforms.
+struct WorldTransformTick(u32);
+
 /// Computes the world transform for all of the nodes in the scene with a [LocalTransform]
-/// component.
-pub fn compute_world_transform(scene: &Scene) {
-    for node in scene.get_root_nodes() {
-        compute_world_transform_internal(scene, node, WorldTransform::IDENTITY);
+/// component. Nodes without a [LocalTransform] pass their parent's world transform through to
+/// their children unchanged, so they can be used to group transformed nodes.
+///
+/// Only the subtrees of nodes whose [LocalTransform] or parent changed since the previous call
+/// are recomputed, so static parts of the scene cost almost nothing. Nodes with a
+/// [PreviousWorldTransform] are always recomputed, since [interpolate_transforms] may have
+/// replaced their world transform.
+pub fn compute_world_transform(scene: &mut Scene) {
+    let since = scene
+        .get_resource::<WorldTransformTick>()
+        .map_or(0, |tick| tick.0);
+
+    let mut dirty = scene
+        .changed::<LocalTransform>(since)
+        .chain(scene.parent_changed(since))
+        .chain(
+            scene
+                .query_filtered::<(WorldTransform,), Without<LocalTransform>>()
+                .map(|(node, _)| node),
+        )
+        .chain(
+            scene
+                .query_filtered::<(PreviousWorldTransform,), With<LocalTransform>>()
+                .map(|(node, _)| node),
+        )
+        .map(|node| (scene.ancestors(node).count(), node))
+        .collect::<Vec<_>>();
+    dirty.sort();
+
+    let mut visited = IntSet::default();
+    for (_, node) in dirty {
+        if visited.contains(&node) {
+            continue;
+        }
+
+        let parent_transform = scene
+            .ancestors(node)
+            .find_map(|ancestor| scene.get::<WorldTransform>(ancestor).copied())
+            .unwrap_or(WorldTransform::IDENTITY);
+        propagate_world_transform(scene, node, parent_transform, &mut visited);
+    }
+
+    let tick = scene.change_tick().wrapping_sub(1);
+    scene.insert_resource(WorldTransformTick(tick));
+}
+
+/// Computes the world transforms and stores them in the [PreviousWorldTransform] components, so
+/// they can be interpolated after the next fixed update. Runs at the start of every fixed update
+/// in [Stages::with_builtin_systems].
+pub fn update_previous_transforms(scene: &mut Scene) {
+    compute_world_transform(scene);
+
+    let transforms = scene
+        .query::<(PreviousWorldTransform, WorldTransform)>()
+        .map(|(node, _, transform)| (node, PreviousWorldTransform::new(transform.matrix)))
+        .collect::<Vec<_>>();
+    for (node, transform) in transforms {
+        scene.set_if_changed(node, transform);
     }
 }
 
-fn compute_world_transform_internal(scene: &Scene, node: Node, parent_transform: WorldTransform) {
-    let transform = match scene.get::<LocalTransform>(node) {
-        Some(transform) => {
-            let transform = WorldTransform::new(
-                parent_transform.matrix
-                    * Mat4::from_scale_rotation_translation(
-                        transform.scale,
-                        transform.rotation,
-                        transform.position,
-                    ),
-            );
+/// Replaces the world transforms of nodes with a [PreviousWorldTransform] with the transform
+/// blended between the previous and the current fixed update, where `alpha` is the fraction of the
+/// fixed timestep elapsed since the current one, e.g. [Stages::overstep_fraction]. Must run after
+/// [compute_world_transform].
+pub fn interpolate_transforms(scene: &mut Scene, alpha: f32) {
+    let alpha = alpha.clamp(0.0, 1.0);
+    let transforms = scene
+        .query::<(PreviousWorldTransform, WorldTransform)>()
+        .map(|(node, previous, transform)| {
+            let previous = WorldTransform::new(previous.matrix);
+            (node, previous.lerp(transform, alpha))
+        })
+        .collect::<Vec<_>>();
+    for (node, transform) in transforms {
+        scene.set_if_changed(node, transform);
+    }
+}
+
+/// Calls [interpolate_transforms] with the overstep fraction of the scene's [Time] resource.
+pub(crate) fn interpolate_transforms_with_time(scene: &mut Scene) {
+    let alpha = scene
+        .get_resource::<Time>()
+        .map_or(1.0, |time| time.overstep_fraction());
+    interpolate_transforms(scene, alpha);
+}
+
+/// Change tick up to which [compute_mesh_bounds] has computed the bounds.
+struct MeshBoundsTick(u32);
+
+/// Sets the [Aabb] of the nodes whose [Mesh] was added or changed since the previous call to the
+/// bounds of the mesh.
+pub fn compute_mesh_bounds(scene: &mut Scene) {
+    let since = scene
+        .get_resource::<MeshBoundsTick>()
+        .map_or(0, |tick| tick.0);
+
+    let changed = scene
+        .changed::<Mesh>(since)
+        .map(|node| (node, scene.get::<Mesh>(node).and_then(Mesh::aabb)))
+        .collect::<Vec<_>>();
+    for (node, aabb) in changed {
+        set_if_changed(scene, node, aabb);
+    }
+
+    let tick = scene.change_tick().wrapping_sub(1);
+    scene.insert_resource(MeshBoundsTick(tick));
+}
+
+/// Change tick up to which [compute_sprite_bounds] has computed the bounds.
+struct SpriteBoundsTick(u32);
+
+/// Sets the [Aabb] of the nodes whose [Sprite] was added or changed since the previous call to
+/// the bounds of the sprite.
+pub fn compute_sprite_bounds(scene: &mut Scene) {
+    let since = scene
+        .get_resource::<SpriteBoundsTick>()
+        .map_or(0, |tick| tick.0);
+
+    let changed = scene
+        .changed::<Sprite>(since)
+        .map(|node| (node, scene.get::<Sprite>(node).map(Sprite::aabb)))
+        .collect::<Vec<_>>();
+    for (node, aabb) in changed {
+        set_if_changed(scene, node, aabb);
+    }
+
+    let tick = scene.change_tick().wrapping_sub(1);
+    scene.insert_resource(SpriteBoundsTick(tick));
+}
+
+/// Change tick up to which [compute_text_bounds] has computed the bounds.
+struct TextBoundsTick(u32);
+
+/// Sets the [Aabb] of the nodes whose [Text] was added or changed since the previous call to the
+/// bounds of the laid out text.
+pub fn compute_text_bounds(scene: &mut Scene) {
+    let since = scene
+        .get_resource::<TextBoundsTick>()
+        .map_or(0, |tick| tick.0);
+
+    let changed = scene
+        .changed::<Text>(since)
+        .map(|node| (node, scene.get::<Text>(node).map(Text::aabb)))
+        .collect::<Vec<_>>();
+    for (node, aabb) in changed {
+        set_if_changed(scene, node, aabb);
+    }
+
+    let tick = scene.change_tick().wrapping_sub(1);
+    scene.insert_resource(TextBoundsTick(tick));
+}
+
+/// Change tick up to which [compute_world_bounds] has computed the bounds.
+struct WorldBoundsTick(u32);
+
+/// Computes the [WorldBounds] of all of the nodes in the scene with an [Aabb] or
+/// [BoundingSphere] component by transforming them with their [WorldTransform], and the
+/// [HierarchyBounds] of those nodes and their ancestors by combining the bounds of their
+/// descendants. Nodes without a [WorldTransform] use the world transform of their closest
+/// ancestor that has one.
+///
+/// The bounds are only recomputed if a transform, bounds component, or parent changed since the
+/// previous call or a node was despawned since the events were last cleared.
+pub fn compute_world_bounds(scene: &mut Scene) {
+    let since = scene
+        .get_resource::<WorldBoundsTick>()
+        .map_or(0, |tick| tick.0);
+
+    let removed = |events: &[ComponentEvent]| {
+        events
+            .iter()
+            .any(|event| matches!(event, ComponentEvent::Removed(_)))
+    };
+    let dirty = scene.changed::<WorldTransform>(since).next().is_some()
+        || scene.changed::<Aabb>(since).next().is_some()
+        || scene.changed::<BoundingSphere>(since).next().is_some()
+        || scene.parent_changed(since).next().is_some()
+        || removed(scene.events::<WorldTransform>())
+        || removed(scene.events::<Aabb>())
+        || removed(scene.events::<BoundingSphere>())
+        || scene
+            .node_events()
+            .iter()
+            .any(|event| matches!(event, NodeEvent::Despawned(_)));
+
+    if dirty {
+        update_world_bounds(scene);
+    }
+
+    let tick = scene.change_tick().wrapping_sub(1);
+    scene.insert_resource(WorldBoundsTick(tick));
+}
 
-            scene.set_or_add(node, transform);
+fn update_world_bounds(scene: &mut Scene) {
+    let mut order = Vec::new();
+    let mut stack = scene
+        .get_root_nodes()
+        .map(|node| (node, Mat4::IDENTITY))
+        .collect::<Vec<_>>();
+    while let Some((node, parent_matrix)) = stack.pop() {
+        let matrix = scene
+            .get::<WorldTransform>(node)
+            .map_or(parent_matrix, |transform| transform.matrix);
+        order.push((node, matrix));
 
-            transform
+        for child in scene.get_children(node).into_iter().flatten().copied() {
+            stack.push((child, matrix));
         }
-        None => WorldTransform::IDENTITY,
+    }
+
+    let mut hierarchy_bounds = IntMap::<Node, Aabb>::default();
+    for (node, matrix) in order.into_iter().rev() {
+        let bounds = match (
+            scene.get::<Aabb>(node).copied(),
+            scene.get::<BoundingSphere>(node).copied(),
+        ) {
+            (Some(aabb), Some(sphere)) => Some(WorldBounds {
+                aabb: aabb.transformed(&matrix),
+                sphere: sphere.transformed(&matrix),
+            }),
+            (Some(aabb), None) => Some(WorldBounds {
+                aabb: aabb.transformed(&matrix),
+                sphere: BoundingSphere::from_aabb(&aabb).transformed(&matrix),
+            }),
+            (None, Some(sphere)) => {
+                let sphere = sphere.transformed(&matrix);
+                Some(WorldBounds {
+                    aabb: sphere.aabb(),
+                    sphere,
+                })
+            }
+            (None, None) => None,
+        };
+
+        let aabb = scene
+            .get_children(node)
+            .into_iter()
+            .flatten()
+            .filter_map(|child| hierarchy_bounds.get(child))
+            .fold(bounds.map(|bounds| bounds.aabb), |aabb, child| {
+                Some(aabb.map_or(*child, |aabb| aabb.union(child)))
+            });
+        if let Some(aabb) = aabb {
+            hierarchy_bounds.insert(node, aabb);
+        }
+
+        set_if_changed(scene, node, bounds);
+        set_if_changed(scene, node, aabb.map(|aabb| HierarchyBounds { aabb }));
+    }
+}
+
+/// Computes the [VisibleNodes] of the camera node, the nodes with [WorldBounds] that are
+/// [ComputedVisibility::Visible] and at least partially inside the camera's frustum. Subtrees
+/// whose [HierarchyBounds] are outside of the frustum are skipped, and nodes hidden behind the
+/// [crate::occlusion::Occluder]s in the frustum are removed, see [crate::occlusion]. Does nothing
+/// if the node has no [Camera].
+pub fn cull_frustum(scene: &mut Scene, camera_node: Node) {
+    let Some(camera) = scene.get::<Camera>(camera_node) else {
+        return;
+    };
+    let transform = scene
+        .get::<WorldTransform>(camera_node)
+        .copied()
+        .unwrap_or(WorldTransform::IDENTITY);
+    let frustum = camera.frustum(&transform);
+
+    let mut visible = Vec::new();
+    let mut stack = scene.get_root_nodes().collect::<Vec<_>>();
+    while let Some(node) = stack.pop() {
+        if !scene
+            .get::<HierarchyBounds>(node)
+            .is_some_and(|bounds| frustum.intersects_aabb(&bounds.aabb))
+        {
+            continue;
+        }
+
+        let inside = scene.get::<WorldBounds>(node).is_some_and(|bounds| {
+            frustum.intersects_sphere(&bounds.sphere) && frustum.intersects_aabb(&bounds.aabb)
+        });
+        if inside && scene.get::<ComputedVisibility>(node) == Some(&ComputedVisibility::Visible) {
+            visible.push(node);
+        }
+
+        stack.extend(scene.get_children(node).into_iter().flatten().copied());
+    }
+
+    visible.sort();
+    occlusion::cull_occluded(
+        scene,
+        &camera.view_projection_matrix(&transform),
+        &mut visible,
+    );
+    set_if_changed(scene, camera_node, Some(VisibleNodes(visible)));
+}
+
+/// Sets the aspect ratio of the scene's [Camera]s with a [ScalingMode::FixedVertical] to the one
+/// of their render target, the image of their [RenderTarget] or the [Window], and of the cameras
+/// with a [ScalingMode::PixelPerfect] to the one of their virtual resolution. Cameras whose target
+/// has no pixels are left unchanged.
+pub fn fit_cameras(scene: &mut Scene) {
+    let window = scene.get_resource::<Window>().map(Window::size);
+    let fitted = scene
+        .query::<(Camera,)>()
+        .filter_map(|(node, camera)| {
+            let size = match camera.scaling {
+                ScalingMode::Stretch | ScalingMode::Letterbox => return None,
+                ScalingMode::FixedVertical => match scene.get::<RenderTarget>(node) {
+                    Some(RenderTarget(image)) => image.size(),
+                    None => window?,
+                },
+                ScalingMode::PixelPerfect { width, height } => UVec2::new(width, height),
+            };
+            let aspect_ratio = size.x as f32 / size.y as f32;
+            (size.min_element() > 0 && camera.aspect_ratio != aspect_ratio).then_some((
+                node,
+                Camera {
+                    aspect_ratio,
+                    ..*camera
+                },
+            ))
+        })
+        .collect::<Vec<_>>();
+    for (node, camera) in fitted {
+        scene.set(node, camera);
+    }
+}
+
+/// Calls [cull_frustum] for all of the nodes in the scene with a [Camera] component.
+pub fn cull_cameras(scene: &mut Scene) {
+    let cameras = scene
+        .query::<(Camera,)>()
+        .map(|(node, _)| node)
+        .collect::<Vec<_>>();
+    for camera in cameras {
+        cull_frustum(scene, camera);
+    }
+}
+
+/// Updates the scene's [SpatialIndex] resource, if any, from the changed [WorldBounds]. See
+/// [SpatialIndex::update].
+pub fn update_spatial_index(scene: &mut Scene) {
+    if let Some(mut index) = scene.remove_resource::<SpatialIndex>() {
+        index.update(scene);
+        scene.insert_resource(index);
+    }
+}
+
+/// Computes the [JointPalette] of all of the nodes in the scene with a [SkinnedMesh] component
+/// from the [WorldTransform]s of their joints, and removes the palettes of nodes whose
+/// [SkinnedMesh] was removed. Palettes are only modified if a joint moved relative to the node.
+pub fn compute_joint_palettes(scene: &mut Scene) {
+    let palettes = scene
+        .query::<(SkinnedMesh,)>()
+        .map(|(node, skinned)| {
+            let transform = world_transform(scene, node);
+            (node, Some(JointPalette::new(scene, &transform, skinned)))
+        })
+        .chain(
+            scene
+                .query_filtered::<(JointPalette,), Without<SkinnedMesh>>()
+                .map(|(node, _)| (node, None)),
+        )
+        .collect::<Vec<_>>();
+    for (node, palette) in palettes {
+        set_if_changed(scene, node, palette);
+    }
+}
+
+/// Captures the scene around the [ReflectionProbe]s without a [ProbeCapture], adding the capture
+/// and a camera rendering each of its faces from the probe's translation. The cameras are
+/// despawned in the next call, once they rendered a frame, and their capture is marked as ready.
+pub fn capture_reflection_probes(scene: &mut Scene) {
+    let cameras = scene
+        .query::<(CaptureCamera,)>()
+        .map(|(node, camera)| (node, camera.0))
+        .collect::<Vec<_>>();
+    for (camera, probe) in cameras {
+        scene.despawn(camera);
+        if let Some(mut capture) = scene.get_mut::<ProbeCapture>(probe) {
+            capture.complete();
+        }
+    }
+
+    let probes = scene
+        .query::<(ReflectionProbe,)>()
+        .filter(|(node, _)| scene.get::<ProbeCapture>(*node).is_none())
+        .map(|(node, probe)| (node, *probe))
+        .collect::<Vec<_>>();
+    for (node, probe) in probes {
+        let Some(capture) = ProbeCapture::new(&probe) else {
+            continue;
+        };
+        let position = world_transform(scene, node).translation();
+        for (camera, transform, target) in capture.cameras(&probe, position) {
+            scene.spawn_with((camera, transform, target, CaptureCamera(node)));
+        }
+        scene.add(node, capture);
+    }
+}
+
+/// Spawns the chunks of the scene's [Terrain]s as children of their nodes, respawning them when a
+/// terrain is modified and despawning them when it's removed, and sets the mesh of each chunk to
+/// the level of detail of its distance to the nearest [Camera]. The cameras' positions are the
+/// ones of their last computed [WorldTransform]s. Terrains copied with their chunks, e.g. by
+/// [Scene::duplicate] or a [crate::Prefab], respawn their own chunks.
+pub fn update_terrain(scene: &mut Scene) {
+    let cameras = scene
+        .query::<(Camera,)>()
+        .map(|(node, _)| world_transform(scene, node).translation())
+        .collect::<Vec<_>>();
+    let removed = scene
+        .query_filtered::<(TerrainChunks,), Without<Terrain>>()
+        .map(|(node, _)| node)
+        .collect::<Vec<_>>();
+    for node in removed {
+        despawn_terrain_chunks(scene, node);
+        scene.remove::<TerrainChunks>(node);
+    }
+
+    let terrains = scene
+        .query::<(Terrain,)>()
+        .map(|(node, terrain)| (node, terrain.clone()))
+        .collect::<Vec<_>>();
+    for (node, terrain) in terrains {
+        // Chunks copied from another terrain still refer to the other terrain's chunk nodes.
+        let current = scene.get::<TerrainChunks>(node).is_some_and(|chunks| {
+            *chunks.terrain() == terrain
+                && chunks
+                    .nodes()
+                    .all(|chunk| scene.contains(chunk) && scene.get_parent(chunk) == Some(node))
+        });
+        if !current {
+            despawn_terrain_chunks(scene, node);
+            let chunks = TerrainChunks::spawn(scene, node, &terrain);
+            scene.set_or_add(node, chunks);
+        }
+
+        let matrix = world_transform(scene, node).matrix;
+        let changed = scene
+            .get_mut::<TerrainChunks>(node)
+            .unwrap()
+            .select_lods(&matrix, &cameras);
+        for (chunk, component, mesh) in changed {
+            scene.set(chunk, component);
+            scene.set_or_add(chunk, mesh);
+        }
+    }
+}
+
+/// Despawns the children of the terrain's node with a [TerrainChunk], which includes the chunks
+/// copied along with the terrain.
+fn despawn_terrain_chunks(scene: &mut Scene, node: Node) {
+    let chunks = scene
+        .get_children(node)
+        .into_iter()
+        .flatten()
+        .copied()
+        .filter(|child| scene.get::<TerrainChunk>(*child).is_some())
+        .collect::<Vec<_>>();
+    for chunk in chunks {
+        scene.despawn(chunk);
+    }
+}
+
+/// Sets, adds, or removes the component, skipping values equal to the current one.
+fn set_if_changed<T: Component>(scene: &mut Scene, node: Node, value: Option<T>) {
+    match value {
+        Some(value) if scene.get::<T>(node) != Some(&value) => scene.set_or_add(node, value),
+        Some(_) => {}
+        None if scene.get::<T>(node).is_some() => scene.remove::<T>(node),
+        None => {}
+    }
+}
+
+fn propagate_world_transform(
+    scene: &mut Scene,
+    node: Node,
+    parent_transform: WorldTransform,
+    visited: &mut IntSet<Node>,
+) {
+    let mut stack = vec![(node, parent_transform)];
+    while let Some((node, parent_transform)) = stack.pop() {
+        visited.insert(node);
+
+        let transform = match scene.get::<LocalTransform>(node) {
+            Some(transform) => {
+                let transform = WorldTransform::new(parent_transform.matrix * transform.matrix());
+
+                if scene.get::<WorldTransform>(node) != Some(&transform) {
+                    scene.set_or_add(node, transform);
+                }
+
+                transform
+            }
+            None => {
+                scene.remove::<WorldTransform>(node);
+                parent_transform
+            }
+        };
+
+        for child in scene.get_children(node).into_iter().flatten().copied() {
+            stack.push((child, transform));
+        }
+    }
+}
+
+/// Adds the assets the scene's [AssetServer] finished loading to their [crate::assets::Assets]
+/// resources, sends their [crate::assets::AssetEvent]s, and removes the loaded assets whose
+/// strong handles were all dropped.
+pub fn update_assets(scene: &mut Scene) {
+    let Some(server) = scene.get_resource_mut::<AssetServer>() else {
+        return;
+    };
+
+    let (loaded, cleanups) = server.take_loaded();
+    for asset in loaded {
+        asset.apply(scene);
+    }
+    for cleanup in cleanups {
+        cleanup(scene);
+    }
+}
+
+/// Sets the `T` component of the nodes with a [Handle] to an asset of type `T` to the asset in the
+/// scene's [Assets] resource, e.g. so nodes update in place when their asset is reloaded. Nodes
+/// whose asset isn't loaded keep their component.
+pub fn update_asset_components<T: Asset + Component>(scene: &mut Scene) {
+    let Some(assets) = scene.get_resource::<Assets<T>>() else {
+        return;
     };
 
-    for node in scene.get_children(node).into_iter().flatten().copied() {
-        compute_world_transform_internal(scene, node, transform);
+    let updates = scene
+        .query::<(Handle<T>,)>()
+        .filter_map(|(node, handle)| {
+            let asset = assets.get(handle)?;
+            (scene.get::<T>(node) != Some(asset)).then(|| (node, asset.clone()))
+        })
+        .collect::<Vec<_>>();
+    for (node, asset) in updates {
+        scene.set_or_add(node, asset);
+    }
+}
+
+/// Advances the [AnimationPlayer]s by the delta time of the scene's [Time] resource and applies
+/// their clips to the clips' targets.
+pub fn animate(scene: &mut Scene) {
+    let delta = scene
+        .get_resource::<Time>()
+        .map_or(0.0, Time::delta_seconds);
+
+    let players = scene
+        .query::<(AnimationPlayer,)>()
+        .map(|(node, player)| (node, player.clone()))
+        .collect::<Vec<_>>();
+    for (node, mut player) in players {
+        player.advance(delta);
+        player.clip.apply(scene, player.time);
+        set_if_changed(scene, node, Some(player));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec2;
+    use glam::Vec3;
+
+    use super::*;
+    use crate::occlusion::Occluder;
+    use crate::occlusion::OcclusionSettings;
+    use crate::render::skin::Skeleton;
+    use crate::render::terrain::Heightmap;
+    use crate::Name;
+    use crate::Prefab;
+
+    fn world_position(scene: &Scene, node: Node) -> Option<Vec3> {
+        scene
+            .get::<WorldTransform>(node)
+            .map(|transform| transform.matrix.w_axis.truncate())
+    }
+
+    #[test]
+    fn compute_visibility_inherits_parent_visibility() {
+        let mut scene = Scene::new();
+        let parent = scene.spawn_with(Visibility::Invisible);
+        let child = scene.spawn_with(Visibility::Inherit);
+        let grandchild = scene.spawn_with(Visibility::Visible);
+        let root = scene.spawn();
+        scene.set_parent(child, parent);
+        scene.set_parent(grandchild, child);
+
+        compute_visibility(&mut scene);
+
+        assert_eq!(
+            scene.get::<ComputedVisibility>(child),
+            Some(&ComputedVisibility::Invisible)
+        );
+        assert_eq!(
+            scene.get::<ComputedVisibility>(grandchild),
+            Some(&ComputedVisibility::Visible)
+        );
+        assert_eq!(
+            scene.get::<ComputedVisibility>(root),
+            Some(&ComputedVisibility::Visible)
+        );
+    }
+
+    #[test]
+    fn compute_visibility_changed_visibility_modifies_changed_nodes() {
+        let mut scene = Scene::new();
+        let parent = scene.spawn_with(Visibility::Visible);
+        let child = scene.spawn();
+        let other = scene.spawn_with(Visibility::Visible);
+        scene.set_parent(child, parent);
+        compute_visibility(&mut scene);
+        scene.advance_change_tick();
+        scene.clear_events();
+
+        scene.set(parent, Visibility::Invisible);
+        scene.set(other, Visibility::Inherit);
+        compute_visibility(&mut scene);
+
+        assert_eq!(
+            scene.events::<ComputedVisibility>(),
+            &[
+                ComponentEvent::Modified(parent),
+                ComponentEvent::Modified(child)
+            ]
+        );
+    }
+
+    #[test]
+    fn compute_visibility_removed_visibility_inherits_parent_visibility() {
+        let mut scene = Scene::new();
+        let parent = scene.spawn_with(Visibility::Invisible);
+        let child = scene.spawn_with(Visibility::Visible);
+        scene.set_parent(child, parent);
+        compute_visibility(&mut scene);
+        scene.advance_change_tick();
+        scene.clear_events();
+
+        scene.remove::<Visibility>(child);
+        compute_visibility(&mut scene);
+
+        assert_eq!(
+            scene.get::<ComputedVisibility>(child),
+            Some(&ComputedVisibility::Invisible)
+        );
+    }
+
+    #[test]
+    fn compute_world_transform_combines_parent_transforms() {
+        let mut scene = Scene::new();
+        let parent = scene.spawn_with(LocalTransform::from_position(Vec3::X));
+        let child = scene.spawn_with(LocalTransform::from_position(Vec3::Y));
+        scene.set_parent(child, parent);
+
+        compute_world_transform(&mut scene);
+
+        assert_eq!(world_position(&scene, parent), Some(Vec3::X));
+        assert_eq!(
+            world_position(&scene, child),
+            Some(Vec3::new(1.0, 1.0, 0.0))
+        );
+    }
+
+    #[test]
+    fn compute_world_transform_passes_through_nodes_without_local_transform() {
+        let mut scene = Scene::new();
+        let root = scene.spawn_with(LocalTransform::from_position(Vec3::X));
+        let group = scene.spawn();
+        let child = scene.spawn_with(LocalTransform::from_position(Vec3::Y));
+        scene.set_parent(group, root);
+        scene.set_parent(child, group);
+
+        compute_world_transform(&mut scene);
+
+        assert_eq!(world_position(&scene, group), None);
+        assert_eq!(
+            world_position(&scene, child),
+            Some(Vec3::new(1.0, 1.0, 0.0))
+        );
+    }
+
+    #[test]
+    fn compute_world_transform_root_without_local_transform_uses_identity() {
+        let mut scene = Scene::new();
+        let root = scene.spawn();
+        let group = scene.spawn();
+        let child = scene.spawn_with(LocalTransform::from_position(Vec3::Z));
+        scene.set_parent(group, root);
+        scene.set_parent(child, group);
+
+        compute_world_transform(&mut scene);
+
+        assert_eq!(world_position(&scene, child), Some(Vec3::Z));
+    }
+
+    #[test]
+    fn compute_world_transform_removed_local_transform_removes_world_transform() {
+        let mut scene = Scene::new();
+        let parent = scene.spawn_with(LocalTransform::from_position(Vec3::X));
+        let child = scene.spawn_with(LocalTransform::from_position(Vec3::Y));
+        scene.set_parent(child, parent);
+        compute_world_transform(&mut scene);
+
+        scene.remove::<LocalTransform>(parent);
+        compute_world_transform(&mut scene);
+
+        assert_eq!(world_position(&scene, parent), None);
+        assert_eq!(world_position(&scene, child), Some(Vec3::Y));
+    }
+
+    #[test]
+    fn compute_world_transform_recomputes_changed_subtrees() {
+        let mut scene = Scene::new();
+        let parent = scene.spawn_with(LocalTransform::from_position(Vec3::X));
+        let child = scene.spawn_with(LocalTransform::from_position(Vec3::Y));
+        let other = scene.spawn_with(LocalTransform::from_position(Vec3::Z));
+        scene.set_parent(child, parent);
+        compute_world_transform(&mut scene);
+        scene.advance_change_tick();
+        scene.clear_events();
+
+        scene.get_mut::<LocalTransform>(parent).unwrap().position = Vec3::NEG_X;
+        compute_world_transform(&mut scene);
+
+        assert_eq!(
+            world_position(&scene, child),
+            Some(Vec3::new(-1.0, 1.0, 0.0))
+        );
+        assert_eq!(
+            scene.events::<WorldTransform>(),
+            &[
+                crate::ComponentEvent::Modified(parent),
+                crate::ComponentEvent::Modified(child)
+            ]
+        );
+        assert_eq!(world_position(&scene, other), Some(Vec3::Z));
+    }
+
+    #[test]
+    fn compute_world_transform_reparented_node_uses_new_parent() {
+        let mut scene = Scene::new();
+        let first = scene.spawn_with(LocalTransform::from_position(Vec3::X));
+        let second = scene.spawn_with(LocalTransform::from_position(Vec3::Y));
+        let group = scene.spawn();
+        let child = scene.spawn_with(LocalTransform::from_position(Vec3::Z));
+        scene.set_parent(group, first);
+        scene.set_parent(child, group);
+        compute_world_transform(&mut scene);
+        scene.advance_change_tick();
+
+        scene.set_parent(group, second);
+        compute_world_transform(&mut scene);
+
+        assert_eq!(
+            world_position(&scene, child),
+            Some(Vec3::new(0.0, 1.0, 1.0))
+        );
+    }
+
+    #[test]
+    fn compute_world_transform_unchanged_scene_skips_nodes() {
+        let mut scene = Scene::new();
+        let node = scene.spawn_with(LocalTransform::from_position(Vec3::X));
+        compute_world_transform(&mut scene);
+        scene.advance_change_tick();
+        compute_world_transform(&mut scene);
+        scene.advance_change_tick();
+
+        scene.set(node, WorldTransform::IDENTITY);
+        compute_world_transform(&mut scene);
+
+        assert_eq!(world_position(&scene, node), Some(Vec3::ZERO));
+    }
+
+    #[test]
+    fn compute_mesh_bounds_sets_aabb_of_changed_meshes() {
+        let mut scene = Scene::new();
+        let node = scene.spawn_with(Mesh::cube(2.0));
+        compute_mesh_bounds(&mut scene);
+        assert_eq!(
+            scene.get::<Aabb>(node),
+            Some(&Aabb::new(Vec3::splat(-1.0), Vec3::ONE))
+        );
+
+        scene.advance_change_tick();
+        scene.set(node, Mesh::plane(4.0));
+        compute_mesh_bounds(&mut scene);
+
+        assert_eq!(
+            scene.get::<Aabb>(node),
+            Some(&Aabb::new(
+                Vec3::new(-2.0, 0.0, -2.0),
+                Vec3::new(2.0, 0.0, 2.0)
+            ))
+        );
+    }
+
+    #[test]
+    fn compute_sprite_bounds_sets_aabb_of_changed_sprites() {
+        let mut scene = Scene::new();
+        let node = scene.spawn_with(Sprite::default());
+        compute_sprite_bounds(&mut scene);
+        assert_eq!(
+            scene.get::<Aabb>(node),
+            Some(&Aabb::new(
+                Vec3::new(-0.5, -0.5, 0.0),
+                Vec3::new(0.5, 0.5, 0.0)
+            ))
+        );
+
+        scene.advance_change_tick();
+        scene.set(
+            node,
+            Sprite {
+                anchor: Vec2::ZERO,
+                custom_size: Some(Vec2::new(-2.0, 4.0)),
+                ..Sprite::default()
+            },
+        );
+        compute_sprite_bounds(&mut scene);
+
+        assert_eq!(
+            scene.get::<Aabb>(node),
+            Some(&Aabb::new(
+                Vec3::new(-2.0, 0.0, 0.0),
+                Vec3::new(0.0, 4.0, 0.0)
+            ))
+        );
+    }
+
+    #[test]
+    fn compute_text_bounds_sets_aabb_of_changed_texts() {
+        let font = include_bytes!("../assets/fonts/Cantarell-Regular.ttf");
+        let font = crate::render::text::Font::from_bytes(font.to_vec()).unwrap();
+        let mut scene = Scene::new();
+        let node = scene.spawn_with(Text::new("a", font.clone(), 16.0));
+        compute_text_bounds(&mut scene);
+        let aabb = *scene.get::<Aabb>(node).unwrap();
+        assert_eq!(aabb.max.y, 0.0);
+
+        scene.advance_change_tick();
+        scene.set(node, Text::new("aaaa", font, 16.0));
+        compute_text_bounds(&mut scene);
+
+        assert!(scene.get::<Aabb>(node).unwrap().max.x > aabb.max.x);
+    }
+
+    #[test]
+    fn compute_world_bounds_transforms_and_combines_bounds() {
+        let mut scene = Scene::new();
+        let parent = scene.spawn_with((
+            LocalTransform::from_position(Vec3::X),
+            Aabb::new(Vec3::ZERO, Vec3::ONE),
+        ));
+        let group = scene.spawn();
+        let child = scene.spawn_with((
+            LocalTransform::from_position(Vec3::Y),
+            BoundingSphere::new(Vec3::ZERO, 0.5),
+        ));
+        scene.set_parent(group, parent);
+        scene.set_parent(child, group);
+        compute_world_transform(&mut scene);
+
+        compute_world_bounds(&mut scene);
+
+        let child_bounds = scene.get::<WorldBounds>(child).unwrap();
+        assert_eq!(
+            child_bounds.aabb,
+            Aabb::new(Vec3::new(0.5, 0.5, -0.5), Vec3::new(1.5, 1.5, 0.5))
+        );
+        assert_eq!(
+            scene
+                .get::<HierarchyBounds>(group)
+                .map(|bounds| bounds.aabb),
+            Some(child_bounds.aabb)
+        );
+        assert_eq!(
+            scene
+                .get::<HierarchyBounds>(parent)
+                .map(|bounds| bounds.aabb),
+            Some(Aabb::new(
+                Vec3::new(0.5, 0.0, -0.5),
+                Vec3::new(2.0, 1.5, 1.0)
+            ))
+        );
+        assert_eq!(scene.get::<WorldBounds>(group), None);
+    }
+
+    #[test]
+    fn compute_world_bounds_despawned_child_shrinks_hierarchy_bounds() {
+        let mut scene = Scene::new();
+        let parent = scene.spawn_with(Aabb::new(Vec3::ZERO, Vec3::ONE));
+        let child = scene.spawn_with(Aabb::new(Vec3::ZERO, Vec3::splat(2.0)));
+        scene.set_parent(child, parent);
+        compute_world_bounds(&mut scene);
+        scene.advance_change_tick();
+        scene.clear_events();
+
+        scene.despawn(child);
+        compute_world_bounds(&mut scene);
+
+        assert_eq!(
+            scene
+                .get::<HierarchyBounds>(parent)
+                .map(|bounds| bounds.aabb),
+            Some(Aabb::new(Vec3::ZERO, Vec3::ONE))
+        );
+    }
+
+    #[test]
+    fn compute_world_bounds_unchanged_scene_skips_nodes() {
+        let mut scene = Scene::new();
+        scene.spawn_with(Aabb::new(Vec3::ZERO, Vec3::ONE));
+        compute_world_bounds(&mut scene);
+        scene.advance_change_tick();
+        scene.clear_events();
+
+        compute_world_bounds(&mut scene);
+
+        assert!(scene.events::<WorldBounds>().is_empty());
+        assert!(scene.events::<HierarchyBounds>().is_empty());
+    }
+
+    #[test]
+    fn cull_frustum_returns_visible_nodes_inside_frustum() {
+        let mut scene = Scene::new();
+        let camera = scene.spawn_with((
+            LocalTransform::IDENTITY,
+            Camera::perspective(std::f32::consts::FRAC_PI_2, 1.0, 0.1, 100.0),
+        ));
+        let bounds = Aabb::new(Vec3::splat(-0.5), Vec3::splat(0.5));
+        let parent = scene.spawn_with((LocalTransform::from_position(Vec3::NEG_Z * 5.0), bounds));
+        let hidden = scene.spawn_with((LocalTransform::IDENTITY, bounds, Visibility::Invisible));
+        let behind = scene.spawn_with((LocalTransform::from_position(Vec3::Z * 10.0), bounds));
+        scene.spawn_with((LocalTransform::from_position(Vec3::X * 50.0), bounds));
+        scene.set_parent(hidden, parent);
+        scene.set_parent(behind, parent);
+        compute_visibility(&mut scene);
+        compute_world_transform(&mut scene);
+        compute_world_bounds(&mut scene);
+
+        cull_frustum(&mut scene, camera);
+
+        assert_eq!(
+            scene.get::<VisibleNodes>(camera),
+            Some(&VisibleNodes(vec![parent]))
+        );
+    }
+
+    #[test]
+    fn cull_frustum_removes_nodes_behind_occluders() {
+        let mut scene = Scene::new();
+        let camera = scene.spawn_with((
+            LocalTransform::IDENTITY,
+            Camera::perspective(std::f32::consts::FRAC_PI_2, 1.0, 0.1, 100.0),
+        ));
+        let wall = scene.spawn_with((
+            LocalTransform::from_position(Vec3::NEG_Z * 5.0),
+            Mesh::cube(4.0),
+            Occluder,
+        ));
+        let bounds = Aabb::new(Vec3::splat(-0.5), Vec3::splat(0.5));
+        let hidden = scene.spawn_with((LocalTransform::from_position(Vec3::NEG_Z * 15.0), bounds));
+        let beside = scene.spawn_with((
+            LocalTransform::from_position(Vec3::new(12.0, 0.0, -15.0)),
+            bounds,
+        ));
+        compute_visibility(&mut scene);
+        compute_world_transform(&mut scene);
+        compute_mesh_bounds(&mut scene);
+        compute_world_bounds(&mut scene);
+
+        cull_frustum(&mut scene, camera);
+
+        assert_eq!(
+            scene.get::<VisibleNodes>(camera),
+            Some(&VisibleNodes(vec![wall, beside]))
+        );
+
+        scene.insert_resource(OcclusionSettings {
+            enabled: false,
+            ..OcclusionSettings::default()
+        });
+        cull_frustum(&mut scene, camera);
+
+        assert_eq!(
+            scene.get::<VisibleNodes>(camera),
+            Some(&VisibleNodes(vec![wall, hidden, beside]))
+        );
+    }
+
+    #[test]
+    fn capture_reflection_probes_renders_faces_for_one_frame() {
+        let mut scene = Scene::new();
+        let probe = scene.spawn_with((
+            ReflectionProbe::default(),
+            LocalTransform::from_position(Vec3::Y),
+        ));
+        compute_world_transform(&mut scene);
+
+        capture_reflection_probes(&mut scene);
+
+        let capture = scene.get::<ProbeCapture>(probe).unwrap().clone();
+        let cameras = scene
+            .query::<(CaptureCamera,)>()
+            .map(|(node, camera)| {
+                let transform = scene.get::<WorldTransform>(node).unwrap();
+                let RenderTarget(face) = scene.get::<RenderTarget>(node).unwrap();
+                assert_eq!(transform.translation(), Vec3::Y);
+                assert!(scene.get::<Camera>(node).is_some());
+                (camera.0, face.clone())
+            })
+            .collect::<Vec<_>>();
+        assert!(!capture.is_ready());
+        assert_eq!(
+            cameras,
+            capture
+                .faces()
+                .iter()
+                .map(|face| (probe, face.clone()))
+                .collect::<Vec<_>>()
+        );
+
+        capture_reflection_probes(&mut scene);
+
+        assert!(scene.get::<ProbeCapture>(probe).unwrap().is_ready());
+        assert_eq!(scene.query::<(CaptureCamera,)>().count(), 0);
+
+        scene.remove::<ProbeCapture>(probe);
+        capture_reflection_probes(&mut scene);
+
+        assert!(!scene.get::<ProbeCapture>(probe).unwrap().is_ready());
+        assert_eq!(scene.query::<(CaptureCamera,)>().count(), 6);
+    }
+
+    #[test]
+    fn update_terrain_selects_chunk_lods_from_cameras() {
+        let mut scene = Scene::new();
+        let flat = Heightmap::new(UVec2::splat(9), vec![0.0; 81]).unwrap();
+        let terrain = Terrain {
+            size: Vec2::splat(16.0),
+            chunk_quads: 4,
+            lod_levels: 3,
+            lod_distance: 4.0,
+            ..Terrain::new(flat)
+        };
+        let node = scene.spawn_with((terrain.clone(), LocalTransform::IDENTITY));
+        let camera = scene.spawn_with((
+            Camera::perspective(1.0, 1.0, 0.1, 100.0),
+            LocalTransform::from_position(Vec3::new(-4.0, 1.0, -4.0)),
+        ));
+        compute_world_transform(&mut scene);
+        let lods = |scene: &Scene| {
+            let mut lods = scene
+                .get_children(node)
+                .unwrap()
+                .iter()
+                .map(|chunk| {
+                    assert!(scene.get::<Mesh>(*chunk).is_some());
+                    let chunk = scene.get::<TerrainChunk>(*chunk).unwrap();
+                    (chunk.coords.to_array(), chunk.lod)
+                })
+                .collect::<Vec<_>>();
+            lods.sort();
+            lods
+        };
+
+        update_terrain(&mut scene);
+
+        assert_eq!(
+            lods(&scene),
+            [([0, 0], 0), ([0, 1], 1), ([1, 0], 1), ([1, 1], 1)]
+        );
+
+        scene.set(camera, LocalTransform::from_position(Vec3::splat(100.0)));
+        compute_world_transform(&mut scene);
+        update_terrain(&mut scene);
+
+        assert!(lods(&scene).iter().all(|(_, lod)| *lod == 2));
+
+        let chunks = scene.get_children(node).unwrap().to_vec();
+        scene.set(
+            node,
+            Terrain {
+                height: 2.0,
+                ..terrain
+            },
+        );
+        update_terrain(&mut scene);
+
+        assert_eq!(scene.get_children(node).unwrap().len(), 4);
+        assert!(chunks.iter().all(|chunk| !scene.contains(*chunk)));
+
+        scene.remove::<Terrain>(node);
+        update_terrain(&mut scene);
+
+        assert!(scene.get_children(node).is_none_or(<[Node]>::is_empty));
+        assert_eq!(scene.query::<(TerrainChunk,)>().count(), 0);
+    }
+
+    #[test]
+    fn update_terrain_respawns_chunks_of_copied_terrains() {
+        let mut scene = Scene::new();
+        let flat = Heightmap::new(UVec2::splat(9), vec![0.0; 81]).unwrap();
+        let terrain = Terrain {
+            size: Vec2::splat(16.0),
+            chunk_quads: 4,
+            lod_levels: 3,
+            lod_distance: 4.0,
+            ..Terrain::new(flat)
+        };
+        let node = scene.spawn_with((terrain, LocalTransform::IDENTITY));
+        let camera = scene.spawn_with((
+            Camera::perspective(1.0, 1.0, 0.1, 100.0),
+            LocalTransform::from_position(Vec3::new(-4.0, 1.0, -4.0)),
+        ));
+        compute_world_transform(&mut scene);
+        update_terrain(&mut scene);
+        let chunks = scene.get_children(node).unwrap().to_vec();
+
+        let duplicate = scene.duplicate(node).unwrap();
+        let instance = Prefab::from_node(&scene, node)
+            .unwrap()
+            .instantiate(&mut scene);
+        compute_world_transform(&mut scene);
+        update_terrain(&mut scene);
+
+        assert_eq!(scene.get_children(node).unwrap(), chunks);
+        for copy in [duplicate, instance] {
+            let children = scene.get_children(copy).unwrap().to_vec();
+            assert_eq!(children.len(), 4);
+            assert!(children.iter().all(|chunk| !chunks.contains(chunk)));
+            let mut nodes = scene.get::<TerrainChunks>(copy).unwrap().nodes();
+            assert!(nodes.all(|chunk| children.contains(&chunk)));
+        }
+
+        // The copies select the levels of detail of their own chunks.
+        scene.set(camera, LocalTransform::from_position(Vec3::splat(100.0)));
+        compute_world_transform(&mut scene);
+        update_terrain(&mut scene);
+        for terrain in [node, duplicate, instance] {
+            let children = scene.get_children(terrain).unwrap();
+            assert!(children
+                .iter()
+                .all(|chunk| scene.get::<TerrainChunk>(*chunk).unwrap().lod == 2));
+        }
+    }
+
+    #[test]
+    fn update_asset_components_follows_handles() {
+        let mut scene = Scene::new();
+        let mut names = Assets::new();
+        let handle = names.add(Name::new("first"));
+        let removed = names.add(Name::new("removed"));
+        names.remove(&removed);
+        scene.insert_resource(names);
+        let node = scene.spawn_with((handle.clone(),));
+        let unloaded = scene.spawn_with((removed, Name::new("kept")));
+
+        update_asset_components::<Name>(&mut scene);
+        assert_eq!(scene.get::<Name>(node), Some(&Name::new("first")));
+        assert_eq!(scene.get::<Name>(unloaded), Some(&Name::new("kept")));
+
+        let names = scene.get_resource_mut::<Assets<Name>>().unwrap();
+        names.insert(&handle, Name::new("second"));
+        update_asset_components::<Name>(&mut scene);
+        assert_eq!(scene.get::<Name>(node), Some(&Name::new("second")));
+    }
+
+    #[test]
+    fn interpolate_transforms_blends_previous_and_current() {
+        let mut scene = Scene::new();
+        let node = scene.spawn_with((LocalTransform::IDENTITY, PreviousWorldTransform::IDENTITY));
+        update_previous_transforms(&mut scene);
+        scene.set(node, LocalTransform::from_position(Vec3::X));
+        compute_world_transform(&mut scene);
+
+        interpolate_transforms(&mut scene, 0.25);
+
+        assert_eq!(
+            world_position(&scene, node),
+            Some(Vec3::new(0.25, 0.0, 0.0))
+        );
+    }
+
+    #[test]
+    fn compute_world_transform_restores_interpolated_transforms() {
+        let mut scene = Scene::new();
+        let parent = scene.spawn_with((LocalTransform::IDENTITY, PreviousWorldTransform::IDENTITY));
+        let child = scene.spawn_with(LocalTransform::from_position(Vec3::Y));
+        scene.set_parent(child, parent);
+        update_previous_transforms(&mut scene);
+        scene.set(parent, LocalTransform::from_position(Vec3::X));
+        compute_world_transform(&mut scene);
+        interpolate_transforms(&mut scene, 0.5);
+        scene.advance_change_tick();
+
+        compute_world_transform(&mut scene);
+
+        assert_eq!(world_position(&scene, parent), Some(Vec3::X));
+        assert_eq!(
+            world_position(&scene, child),
+            Some(Vec3::new(1.0, 1.0, 0.0))
+        );
+    }
+
+    #[test]
+    fn compute_joint_palettes_moves_joints_relative_to_mesh() {
+        let mut scene = Scene::new();
+        let mesh = scene.spawn_with(LocalTransform::from_position(Vec3::X));
+        let joint = scene.spawn_with(LocalTransform::from_position(Vec3::Y));
+        compute_world_transform(&mut scene);
+        let skeleton = Skeleton::from_pose(&scene, mesh, vec![joint]);
+        scene.add(mesh, SkinnedMesh { skeleton });
+        scene.set(
+            joint,
+            LocalTransform::from_position(Vec3::new(0.0, 3.0, 0.0)),
+        );
+        compute_world_transform(&mut scene);
+
+        compute_joint_palettes(&mut scene);
+
+        assert_eq!(
+            scene.get::<JointPalette>(mesh),
+            Some(&JointPalette(vec![Mat4::from_translation(Vec3::new(
+                0.0, 2.0, 0.0
+            ))]))
+        );
+
+        scene.remove::<SkinnedMesh>(mesh);
+        compute_joint_palettes(&mut scene);
+
+        assert_eq!(scene.get::<JointPalette>(mesh), None);
+    }
+
+    #[test]
+    fn fit_cameras_follows_window_and_virtual_resolution() {
+        let mut scene = Scene::new();
+        scene.insert_resource(Window::new(UVec2::new(800, 400), 1.0));
+        let camera = |scaling| Camera {
+            scaling,
+            ..Camera::orthographic(10.0, 1.0, -1.0, 1.0)
+        };
+        let stretched = scene.spawn_with(camera(ScalingMode::Stretch));
+        let fixed = scene.spawn_with(camera(ScalingMode::FixedVertical));
+        let pixel_perfect = scene.spawn_with(camera(ScalingMode::PixelPerfect {
+            width: 30,
+            height: 10,
+        }));
+
+        fit_cameras(&mut scene);
+
+        let aspect_ratio = |node| scene.get::<Camera>(node).unwrap().aspect_ratio;
+        assert_eq!(aspect_ratio(stretched), 1.0);
+        assert_eq!(aspect_ratio(fixed), 2.0);
+        assert_eq!(aspect_ratio(pixel_perfect), 3.0);
     }
 }
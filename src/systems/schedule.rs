@@ -0,0 +1,779 @@
+use std::any::TypeId;
+use std::collections::VecDeque;
+use std::fmt;
+
+use crate::scene::access::TableIndex;
+use crate::systems;
+use crate::Component;
+use crate::ComponentWrites;
+use crate::Scene;
+
+/// # Schedule
+///
+/// Ordered set of systems run together on a scene. Systems are run in the order they were added
+/// unless reordered by their `before` and `after` constraints. Systems can be skipped by disabling
+/// one of their labels or by run criteria.
+///
+/// Systems taking `&mut Scene` run on their own. Consecutive systems taking `&Scene` that don't
+/// conflict in the components they declare with [SystemConfig::reads] and [SystemConfig::writes]
+/// are run together, in parallel on the rayon thread pool with the `rayon` feature.
+///
+/// ```
+/// # use pulse::systems;
+/// # use pulse::systems::Schedule;
+/// # use pulse::Scene;
+/// let mut schedule = Schedule::new();
+/// schedule
+///     .add_system(systems::compute_world_transform)
+///     .label(systems::TRANSFORM);
+/// schedule
+///     .add_system(systems::compute_visibility)
+///     .label(systems::VISIBILITY)
+///     .before(systems::TRANSFORM);
+///
+/// let mut scene = Scene::new();
+/// schedule.run(&mut scene);
+/// ```
+#[derive(Default)]
+pub struct Schedule {
+    systems: Vec<SystemEntry>,
+    batches: Option<Vec<Vec<usize>>>,
+    disabled_labels: Vec<&'static str>,
+}
+
+struct SystemEntry {
+    system: System,
+    name: &'static str,
+    labels: Vec<&'static str>,
+    before: Vec<&'static str>,
+    after: Vec<&'static str>,
+    run_criteria: Vec<RunCriterion>,
+    access: Access,
+}
+
+/// Components a system declared it accesses. Systems without declarations may read any
+/// component.
+#[derive(Default)]
+struct Access {
+    declared: bool,
+    reads: Vec<TypeId>,
+    writes: Vec<(TypeId, TableIndex)>,
+}
+
+impl Access {
+    fn writes(&self, type_id: TypeId) -> bool {
+        self.writes.iter().any(|(write, _)| *write == type_id)
+    }
+
+    fn accesses(&self, type_id: TypeId) -> bool {
+        !self.declared || self.reads.contains(&type_id) || self.writes(type_id)
+    }
+
+    fn conflicts(&self, other: &Self) -> bool {
+        self.writes
+            .iter()
+            .any(|(type_id, _)| other.accesses(*type_id))
+            || other
+                .writes
+                .iter()
+                .any(|(type_id, _)| self.accesses(*type_id))
+    }
+}
+
+type RunCriterion = Box<dyn FnMut(&Scene) -> bool>;
+
+impl Schedule {
+    /// Returns an empty schedule.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns a schedule with the built-in systems, [systems::update_terrain] labelled
+    /// [systems::TERRAIN] and [systems::compute_visibility] labelled [systems::VISIBILITY]
+    /// followed by [systems::compute_world_transform] labelled [systems::TRANSFORM],
+    /// [systems::compute_mesh_bounds] labelled [systems::MESH_BOUNDS],
+    /// [systems::compute_sprite_bounds] labelled [systems::SPRITE_BOUNDS],
+    /// [systems::compute_text_bounds] labelled [systems::TEXT_BOUNDS],
+    /// [systems::compute_world_bounds] labelled [systems::BOUNDS], [systems::fit_cameras]
+    /// labelled [systems::FIT_CAMERAS], [systems::cull_cameras] labelled [systems::CULL],
+    /// [systems::update_spatial_index] labelled [systems::SPATIAL],
+    /// [systems::compute_joint_palettes] labelled [systems::SKIN], and
+    /// [systems::capture_reflection_probes] labelled [systems::PROBES].
+    pub fn with_builtin_systems() -> Self {
+        let mut schedule = Self::new();
+        schedule
+            .add_system(systems::compute_visibility)
+            .label(systems::VISIBILITY);
+        schedule
+            .add_system(systems::compute_world_transform)
+            .label(systems::TRANSFORM)
+            .after(systems::VISIBILITY);
+        schedule
+            .add_system(systems::compute_mesh_bounds)
+            .label(systems::MESH_BOUNDS)
+            .after(systems::TRANSFORM);
+        schedule
+            .add_system(systems::compute_sprite_bounds)
+            .label(systems::SPRITE_BOUNDS)
+            .after(systems::MESH_BOUNDS);
+        schedule
+            .add_system(systems::compute_text_bounds)
+            .label(systems::TEXT_BOUNDS)
+            .after(systems::SPRITE_BOUNDS);
+        schedule
+            .add_system(systems::compute_world_bounds)
+            .label(systems::BOUNDS)
+            .after(systems::TRANSFORM)
+            .after(systems::MESH_BOUNDS)
+            .after(systems::SPRITE_BOUNDS)
+            .after(systems::TEXT_BOUNDS);
+        schedule
+            .add_system(systems::fit_cameras)
+            .label(systems::FIT_CAMERAS)
+            .after(systems::BOUNDS);
+        schedule
+            .add_system(systems::cull_cameras)
+            .label(systems::CULL)
+            .after(systems::BOUNDS)
+            .after(systems::FIT_CAMERAS);
+        schedule
+            .add_system(systems::update_spatial_index)
+            .label(systems::SPATIAL)
+            .after(systems::BOUNDS);
+        schedule
+            .add_system(systems::compute_joint_palettes)
+            .label(systems::SKIN)
+            .after(systems::BOUNDS);
+        schedule
+            .add_system(systems::capture_reflection_probes)
+            .label(systems::PROBES)
+            .after(systems::BOUNDS)
+            .before(systems::FIT_CAMERAS);
+        schedule
+            .add_system(systems::update_terrain)
+            .label(systems::TERRAIN)
+            .before(systems::VISIBILITY);
+        schedule
+    }
+
+    /// Returns the number of systems in the schedule.
+    pub fn len(&self) -> usize {
+        self.systems.len()
+    }
+
+    /// Returns true if the schedule has no systems.
+    pub fn is_empty(&self) -> bool {
+        self.systems.is_empty()
+    }
+
+    /// Adds the system to the schedule and returns a builder for its labels and ordering
+    /// constraints. Systems are functions or closures taking either `&Scene` or `&mut Scene`.
+    pub fn add_system<M>(&mut self, system: impl IntoSystem<M>) -> SystemConfig<'_> {
+        self.batches = None;
+        let name = std::any::type_name_of_val(&system);
+        self.systems.push(SystemEntry {
+            system: system.into_system(),
+            name,
+            labels: Vec::new(),
+            before: Vec::new(),
+            after: Vec::new(),
+            run_criteria: Vec::new(),
+            access: Access::default(),
+        });
+
+        SystemConfig {
+            entry: self.systems.last_mut().unwrap(),
+        }
+    }
+
+    /// Enables or disables the systems with the given label. Disabled systems keep their place in
+    /// the order but are skipped when the schedule is run.
+    ///
+    /// ```
+    /// # use pulse::systems::Schedule;
+    /// # use pulse::Scene;
+    /// let mut schedule = Schedule::new();
+    /// schedule
+    ///     .add_system(|_: &mut Scene| panic!("physics ran"))
+    ///     .label("physics");
+    ///
+    /// schedule.set_enabled("physics", false);
+    /// schedule.run(&mut Scene::new());
+    /// assert!(!schedule.is_enabled("physics"));
+    /// ```
+    pub fn set_enabled(&mut self, label: &'static str, enabled: bool) {
+        if enabled {
+            self.disabled_labels.retain(|disabled| *disabled != label);
+        } else if !self.disabled_labels.contains(&label) {
+            self.disabled_labels.push(label);
+        }
+    }
+
+    /// Returns false if the systems with the given label are disabled.
+    pub fn is_enabled(&self, label: &'static str) -> bool {
+        !self.disabled_labels.contains(&label)
+    }
+
+    /// Returns the names of the systems in the order they will be run.
+    pub fn system_names(&mut self) -> Result<Vec<&'static str>, ScheduleError> {
+        self.build()?;
+        Ok(self
+            .batches
+            .iter()
+            .flatten()
+            .flatten()
+            .map(|index| self.systems[*index].name)
+            .collect())
+    }
+
+    /// Returns the names of the systems grouped into the batches that are run together.
+    pub fn system_batches(&mut self) -> Result<Vec<Vec<&'static str>>, ScheduleError> {
+        self.build()?;
+        Ok(self
+            .batches
+            .iter()
+            .flatten()
+            .map(|batch| {
+                batch
+                    .iter()
+                    .map(|index| self.systems[*index].name)
+                    .collect()
+            })
+            .collect())
+    }
+
+    /// Resolves the order of the systems from their constraints and groups them into batches
+    /// that can run together. The batches are cached until a system is added.
+    pub fn build(&mut self) -> Result<(), ScheduleError> {
+        if self.batches.is_none() {
+            let (order, successors) = self.sort()?;
+            self.batches = Some(self.batch(order, &successors));
+        }
+
+        Ok(())
+    }
+
+    /// Runs all of the systems on the scene in order, skipping systems with a disabled label or a
+    /// run criterion returning false.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the constraints of the systems can't be satisfied. Call [Schedule::build] to
+    /// check the constraints without running the systems.
+    pub fn run(&mut self, scene: &mut Scene) {
+        if let Err(error) = self.build() {
+            panic!("{error}");
+        }
+
+        for batch in self.batches.iter().flatten() {
+            let mut runnable = Vec::with_capacity(batch.len());
+            for index in batch {
+                let entry = &mut self.systems[*index];
+                if entry
+                    .labels
+                    .iter()
+                    .any(|label| self.disabled_labels.contains(label))
+                {
+                    continue;
+                }
+
+                if entry
+                    .run_criteria
+                    .iter_mut()
+                    .all(|criterion| criterion(scene))
+                {
+                    runnable.push(*index);
+                }
+            }
+
+            run_batch(&mut self.systems, &runnable, scene);
+        }
+    }
+
+    /// Groups the consecutive systems in the order into batches of systems without conflicting
+    /// component access or ordering constraints between them.
+    fn batch(&self, order: Vec<usize>, successors: &[Vec<usize>]) -> Vec<Vec<usize>> {
+        let mut batches = Vec::<Vec<usize>>::new();
+        for index in order {
+            let entry = &self.systems[index];
+            let joins = batches.last().is_some_and(|batch| {
+                batch.iter().all(|other| {
+                    let other_entry = &self.systems[*other];
+                    matches!(entry.system, System::Parallel(_))
+                        && matches!(other_entry.system, System::Parallel(_))
+                        && !entry.access.conflicts(&other_entry.access)
+                        && !successors[*other].contains(&index)
+                })
+            });
+
+            match batches.last_mut() {
+                Some(batch) if joins => batch.push(index),
+                _ => batches.push(vec![index]),
+            }
+        }
+
+        batches
+    }
+
+    /// Sorts the systems topologically, keeping the insertion order between unconstrained
+    /// systems. Returns the order and the direct successors of each system.
+    fn sort(&self) -> Result<(Vec<usize>, Vec<Vec<usize>>), ScheduleError> {
+        let labelled = |label: &'static str| -> Result<Vec<usize>, ScheduleError> {
+            let indexes = self
+                .systems
+                .iter()
+                .enumerate()
+                .filter(|(_, entry)| entry.labels.contains(&label))
+                .map(|(index, _)| index)
+                .collect::<Vec<_>>();
+
+            if indexes.is_empty() {
+                Err(ScheduleError::UnknownLabel(label))
+            } else {
+                Ok(indexes)
+            }
+        };
+
+        let mut successors = vec![Vec::new(); self.systems.len()];
+        let mut predecessor_counts = vec![0; self.systems.len()];
+        for (index, entry) in self.systems.iter().enumerate() {
+            for label in &entry.before {
+                for successor in labelled(label)? {
+                    successors[index].push(successor);
+                    predecessor_counts[successor] += 1;
+                }
+            }
+
+            for label in &entry.after {
+                for predecessor in labelled(label)? {
+                    successors[predecessor].push(index);
+                    predecessor_counts[index] += 1;
+                }
+            }
+        }
+
+        let mut ready = (0..self.systems.len())
+            .filter(|index| predecessor_counts[*index] == 0)
+            .collect::<VecDeque<_>>();
+        let mut order = Vec::with_capacity(self.systems.len());
+        while let Some(index) = ready.pop_front() {
+            order.push(index);
+
+            for successor in &successors[index] {
+                predecessor_counts[*successor] -= 1;
+                if predecessor_counts[*successor] == 0 {
+                    let position = ready.partition_point(|index| index < successor);
+                    ready.insert(position, *successor);
+                }
+            }
+        }
+
+        if order.len() < self.systems.len() {
+            let index = (0..self.systems.len())
+                .find(|index| !order.contains(index))
+                .unwrap();
+            return Err(ScheduleError::Cycle(self.systems[index].name));
+        }
+
+        Ok((order, successors))
+    }
+}
+
+/// Runs the systems of a batch, in parallel with the `rayon` feature.
+fn run_batch(systems: &mut [SystemEntry], batch: &[usize], scene: &mut Scene) {
+    let mut jobs = Vec::with_capacity(batch.len());
+    for (index, entry) in systems.iter_mut().enumerate() {
+        if !batch.contains(&index) {
+            continue;
+        }
+
+        match &mut entry.system {
+            System::Exclusive(system) => system(scene),
+            System::Parallel(system) => {
+                let writes = scene.take_writes(&entry.access.writes);
+                jobs.push((system, writes));
+            }
+        }
+    }
+
+    #[cfg(feature = "rayon")]
+    if jobs.len() > 1 {
+        let scene = &*scene;
+        rayon::scope(|scope| {
+            for (system, writes) in &mut jobs {
+                scope.spawn(move |_| system(scene, writes));
+            }
+        });
+    } else {
+        for (system, writes) in &mut jobs {
+            system(scene, writes);
+        }
+    }
+
+    #[cfg(not(feature = "rayon"))]
+    for (system, writes) in &mut jobs {
+        system(scene, writes);
+    }
+
+    for (_, writes) in jobs {
+        scene.return_writes(writes);
+    }
+}
+
+/// # System Config
+///
+/// Builder for the labels and ordering constraints of a system returned by
+/// [Schedule::add_system].
+pub struct SystemConfig<'a> {
+    entry: &'a mut SystemEntry,
+}
+
+impl SystemConfig<'_> {
+    /// Adds the label to the system. Multiple systems can share a label.
+    pub fn label(self, label: &'static str) -> Self {
+        self.entry.labels.push(label);
+        self
+    }
+
+    /// Runs the system before the systems with the given label.
+    pub fn before(self, label: &'static str) -> Self {
+        self.entry.before.push(label);
+        self
+    }
+
+    /// Runs the system after the systems with the given label.
+    pub fn after(self, label: &'static str) -> Self {
+        self.entry.after.push(label);
+        self
+    }
+
+    /// Only runs the system when the criterion returns true. Criteria are checked in the order
+    /// they were added every time the schedule is run, until one returns false.
+    ///
+    /// ```
+    /// # use pulse::systems::Schedule;
+    /// # use pulse::Scene;
+    /// struct Paused(bool);
+    ///
+    /// let mut schedule = Schedule::new();
+    /// schedule
+    ///     .add_system(|_: &mut Scene| panic!("physics ran"))
+    ///     .run_if(|scene: &Scene| scene.get_resource::<Paused>().is_some_and(|paused| !paused.0));
+    ///
+    /// let mut scene = Scene::new();
+    /// scene.insert_resource(Paused(true));
+    /// schedule.run(&mut scene);
+    /// ```
+    pub fn run_if(self, criterion: impl 'static + FnMut(&Scene) -> bool) -> Self {
+        self.entry.run_criteria.push(Box::new(criterion));
+        self
+    }
+
+    /// Declares that the system reads the component. Systems taking `&Scene` that declare their
+    /// access may only read the declared components, and in return run in parallel with systems
+    /// writing other components.
+    pub fn reads<T: Component>(self) -> Self {
+        self.entry.access.declared = true;
+        self.entry.access.reads.push(TypeId::of::<T>());
+        self
+    }
+
+    /// Declares that the system writes the component. The component is accessed through the
+    /// [ComponentWrites] of systems taking `&Scene` and `&mut ComponentWrites`, and is missing from
+    /// the scene while the system runs.
+    ///
+    /// ```
+    /// # use pulse::systems::Schedule;
+    /// # use pulse::ComponentWrites;
+    /// # use pulse::LocalTransform;
+    /// # use pulse::Scene;
+    /// # use pulse::Visibility;
+    /// let mut schedule = Schedule::new();
+    /// schedule
+    ///     .add_system(|_: &Scene, writes: &mut ComponentWrites| {
+    ///         for (_, transform) in writes.iter_mut::<LocalTransform>() {
+    ///             transform.position.y -= 1.0;
+    ///         }
+    ///     })
+    ///     .writes::<LocalTransform>();
+    /// schedule
+    ///     .add_system(|_: &Scene, writes: &mut ComponentWrites| {
+    ///         for (_, visibility) in writes.iter_mut::<Visibility>() {
+    ///             *visibility = Visibility::Visible;
+    ///         }
+    ///     })
+    ///     .writes::<Visibility>();
+    ///
+    /// assert_eq!(schedule.system_batches().unwrap().len(), 1);
+    /// ```
+    pub fn writes<T: Component>(self) -> Self {
+        self.entry.access.declared = true;
+        if !self.entry.access.writes(TypeId::of::<T>()) {
+            self.entry
+                .access
+                .writes
+                .push((TypeId::of::<T>(), Scene::table_index::<T>));
+        }
+        self
+    }
+}
+
+/// # System
+///
+/// System converted by [IntoSystem] for a [Schedule].
+pub enum System {
+    /// System with exclusive access to the scene, run on its own.
+    Exclusive(ExclusiveSystem),
+    /// System with shared access to the scene and exclusive access to the components it declares
+    /// with [SystemConfig::writes], run together with systems it doesn't conflict with.
+    Parallel(ParallelSystem),
+}
+
+/// Boxed system with exclusive access to the scene.
+pub type ExclusiveSystem = Box<dyn FnMut(&mut Scene)>;
+
+/// Boxed system with shared access to the scene and exclusive access to the written components.
+pub type ParallelSystem = Box<dyn FnMut(&Scene, &mut ComponentWrites) + Send>;
+
+/// # Into System
+///
+/// Conversion of functions and closures into systems. Implemented for `FnMut(&mut Scene)`,
+/// `FnMut(&Scene)`, and `FnMut(&Scene, &mut ComponentWrites)`.
+pub trait IntoSystem<M> {
+    /// Returns the system.
+    fn into_system(self) -> System;
+}
+
+/// Marker for systems taking `&mut Scene`.
+#[doc(hidden)]
+pub struct SceneMut;
+
+/// Marker for systems taking `&Scene`.
+#[doc(hidden)]
+pub struct SceneRef;
+
+/// Marker for systems taking `&Scene` and `&mut ComponentWrites`.
+#[doc(hidden)]
+pub struct SceneWrites;
+
+impl<F: 'static + FnMut(&mut Scene)> IntoSystem<SceneMut> for F {
+    fn into_system(self) -> System {
+        System::Exclusive(Box::new(self))
+    }
+}
+
+impl<F: 'static + Send + FnMut(&Scene)> IntoSystem<SceneRef> for F {
+    fn into_system(mut self) -> System {
+        System::Parallel(Box::new(move |scene, _| self(scene)))
+    }
+}
+
+impl<F: 'static + Send + FnMut(&Scene, &mut ComponentWrites)> IntoSystem<SceneWrites> for F {
+    fn into_system(self) -> System {
+        System::Parallel(Box::new(self))
+    }
+}
+
+/// # Schedule Error
+///
+/// Error returned when the ordering constraints of a [Schedule] can't be satisfied.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ScheduleError {
+    /// A `before` or `after` constraint refers to a label no system has.
+    UnknownLabel(&'static str),
+    /// The constraints of the named system form a cycle.
+    Cycle(&'static str),
+}
+
+impl fmt::Display for ScheduleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownLabel(label) => write!(f, "no system has the label `{label}`"),
+            Self::Cycle(name) => write!(f, "ordering constraints of `{name}` form a cycle"),
+        }
+    }
+}
+
+impl std::error::Error for ScheduleError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn push(value: u32) -> impl FnMut(&mut Scene) {
+        move |scene| {
+            let mut values = scene.remove_resource::<Vec<u32>>().unwrap_or_default();
+            values.push(value);
+            scene.insert_resource(values);
+        }
+    }
+
+    fn run(schedule: &mut Schedule) -> Vec<u32> {
+        let mut scene = Scene::new();
+        schedule.run(&mut scene);
+        scene.remove_resource().unwrap_or_default()
+    }
+
+    #[test]
+    fn run_unconstrained_systems_runs_in_insertion_order() {
+        let mut schedule = Schedule::new();
+        schedule.add_system(push(1));
+        schedule.add_system(push(2));
+        schedule.add_system(push(3));
+
+        assert_eq!(run(&mut schedule), [1, 2, 3]);
+    }
+
+    #[test]
+    fn run_constrained_systems_runs_in_constraint_order() {
+        let mut schedule = Schedule::new();
+        schedule.add_system(push(1)).label("first").after("second");
+        schedule.add_system(push(2)).label("second");
+        schedule.add_system(push(3)).before("second");
+
+        assert_eq!(run(&mut schedule), [3, 2, 1]);
+    }
+
+    #[test]
+    fn run_read_only_system_reads_scene() {
+        let mut schedule = Schedule::new();
+        schedule.add_system(push(1)).label("push");
+        schedule
+            .add_system(|scene: &Scene| {
+                assert_eq!(scene.get_resource::<Vec<u32>>(), Some(&vec![1]));
+            })
+            .after("push");
+
+        assert_eq!(run(&mut schedule), [1]);
+    }
+
+    #[test]
+    fn run_disabled_label_skips_systems() {
+        let mut schedule = Schedule::new();
+        schedule.add_system(push(1)).label("first");
+        schedule.add_system(push(2)).label("second").after("first");
+        schedule.set_enabled("first", false);
+
+        assert_eq!(run(&mut schedule), [2]);
+
+        schedule.set_enabled("first", true);
+
+        assert_eq!(run(&mut schedule), [1, 2]);
+    }
+
+    #[test]
+    fn run_run_criteria_gate_systems() {
+        let mut schedule = Schedule::new();
+        schedule.add_system(push(1)).label("push");
+        schedule
+            .add_system(push(2))
+            .after("push")
+            .run_if(|scene: &Scene| scene.get_resource::<Vec<u32>>().is_some())
+            .run_if(|scene: &Scene| scene.get_resource::<Vec<u32>>().unwrap().len() > 1);
+        schedule.add_system(push(3)).run_if(|_: &Scene| true);
+
+        assert_eq!(run(&mut schedule), [1, 3]);
+    }
+
+    fn write(value: u32) -> impl FnMut(&Scene, &mut ComponentWrites) {
+        move |_, writes| {
+            for (_, item) in writes.iter_mut::<u32>() {
+                *item += value;
+            }
+        }
+    }
+
+    #[test]
+    fn build_non_conflicting_systems_share_batch() {
+        let mut schedule = Schedule::new();
+        schedule.add_system(write(1)).writes::<u32>();
+        schedule.add_system(|_: &Scene| {}).reads::<bool>();
+        schedule.add_system(|_: &Scene| {}).reads::<u32>();
+        schedule.add_system(|_: &Scene| {});
+        schedule.add_system(push(1));
+
+        schedule.build().unwrap();
+
+        assert_eq!(
+            schedule.batches,
+            Some(vec![vec![0, 1], vec![2, 3], vec![4]])
+        );
+    }
+
+    #[test]
+    fn build_ordering_constraints_split_batches() {
+        let mut schedule = Schedule::new();
+        schedule.add_system(write(1)).writes::<u32>().label("first");
+        schedule
+            .add_system(|_: &Scene, _: &mut ComponentWrites| {})
+            .writes::<bool>()
+            .after("first");
+
+        schedule.build().unwrap();
+
+        assert_eq!(schedule.batches, Some(vec![vec![0], vec![1]]));
+    }
+
+    #[test]
+    fn run_parallel_systems_write_components() {
+        let mut schedule = Schedule::new();
+        schedule.add_system(write(1)).writes::<u32>();
+        schedule
+            .add_system(|_: &Scene, writes: &mut ComponentWrites| {
+                for (_, item) in writes.iter_mut::<bool>() {
+                    *item = !*item;
+                }
+            })
+            .writes::<bool>();
+        schedule.add_system(write(10)).writes::<u32>();
+        let mut scene = Scene::new();
+        let node = scene.spawn_with((17u32, false));
+
+        schedule.run(&mut scene);
+
+        assert_eq!(scene.get::<u32>(node), Some(&28));
+        assert_eq!(scene.get::<bool>(node), Some(&true));
+    }
+
+    #[test]
+    fn build_cycle_returns_error() {
+        let mut schedule = Schedule::new();
+        schedule.add_system(push(1)).label("first").after("second");
+        schedule.add_system(push(2)).label("second").after("first");
+
+        assert!(matches!(schedule.build(), Err(ScheduleError::Cycle(_))));
+    }
+
+    #[test]
+    fn build_unknown_label_returns_error() {
+        let mut schedule = Schedule::new();
+        schedule.add_system(push(1)).after("missing");
+
+        assert_eq!(
+            schedule.build(),
+            Err(ScheduleError::UnknownLabel("missing"))
+        );
+    }
+
+    #[test]
+    fn with_builtin_systems_runs_visibility_before_transform() {
+        let mut schedule = Schedule::with_builtin_systems();
+        schedule.add_system(push(1)).before(systems::VISIBILITY);
+
+        let names = schedule.system_names().unwrap();
+
+        assert_eq!(names.len(), 13);
+        assert!(names[0].ends_with("update_terrain"));
+        assert!(names[2].ends_with("compute_visibility"));
+        assert!(names[3].ends_with("compute_world_transform"));
+        assert!(names[4].ends_with("compute_mesh_bounds"));
+        assert!(names[5].ends_with("compute_sprite_bounds"));
+        assert!(names[6].ends_with("compute_text_bounds"));
+        assert!(names[7].ends_with("compute_world_bounds"));
+        assert!(names[8].ends_with("update_spatial_index"));
+        assert!(names[9].ends_with("compute_joint_palettes"));
+        assert!(names[10].ends_with("capture_reflection_probes"));
+        assert!(names[11].ends_with("fit_cameras"));
+        assert!(names[12].ends_with("cull_cameras"));
+    }
+}
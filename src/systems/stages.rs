@@ -0,0 +1,306 @@
+use std::time::Duration;
+
+use crate::systems;
+use crate::systems::IntoSystem;
+use crate::systems::Schedule;
+use crate::systems::SystemConfig;
+use crate::Scene;
+use crate::Time;
+
+/// # Stage
+///
+/// Stage of a frame run by [Stages].
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum Stage {
+    /// Runs zero or more times per frame at a fixed frequency, e.g. for physics and deterministic
+    /// gameplay.
+    FixedUpdate,
+    /// Runs once per frame after the fixed updates.
+    Update,
+    /// Runs once per frame after the update, e.g. to prepare the scene for rendering.
+    Render,
+}
+
+/// # Stages
+///
+/// [Schedule]s for each [Stage] of a frame. The [Stage::FixedUpdate] schedule is run once for
+/// every fixed timestep that elapsed since the previous frame, carrying the remainder over to
+/// the next frame.
+///
+/// ```
+/// # use std::time::Duration;
+/// # use pulse::systems::Stage;
+/// # use pulse::systems::Stages;
+/// # use pulse::Scene;
+/// let mut stages = Stages::new().with_fixed_timestep(Duration::from_millis(20));
+/// stages.add_system(Stage::FixedUpdate, |scene: &mut Scene| {
+///     // Step the physics simulation.
+/// });
+///
+/// let mut scene = Scene::new();
+/// stages.run(&mut scene, Duration::from_millis(50));
+/// assert_eq!(stages.overstep(), Duration::from_millis(10));
+/// ```
+pub struct Stages {
+    fixed_update: Schedule,
+    update: Schedule,
+    render: Schedule,
+    fixed_timestep: Duration,
+    max_fixed_steps: u32,
+    accumulator: Duration,
+}
+
+impl Stages {
+    /// Default fixed timestep of 1/60th of a second.
+    pub const DEFAULT_FIXED_TIMESTEP: Duration = Duration::from_nanos(16_666_667);
+
+    /// Returns empty stages with the default fixed timestep.
+    pub fn new() -> Self {
+        Self {
+            fixed_update: Schedule::new(),
+            update: Schedule::new(),
+            render: Schedule::new(),
+            fixed_timestep: Self::DEFAULT_FIXED_TIMESTEP,
+            max_fixed_steps: 8,
+            accumulator: Duration::ZERO,
+        }
+    }
+
+    /// Returns stages with the built-in systems. [systems::update_previous_transforms] labelled
+    /// [systems::PREVIOUS_TRANSFORM] starts the [Stage::FixedUpdate] schedule, and
+    /// [systems::update_assets] labelled [systems::ASSETS] followed by [systems::animate] labelled
+    /// [systems::ANIMATION] start the [Stage::Update] schedule. The
+    /// [Stage::Render] schedule starts with the systems of [Schedule::with_builtin_systems]
+    /// with [systems::interpolate_transforms] labelled [systems::INTERPOLATE_TRANSFORM] between
+    /// the transform and bounds systems, which uses the overstep fraction of the scene's [Time]
+    /// resource.
+    pub fn with_builtin_systems() -> Self {
+        let mut stages = Self {
+            render: Schedule::with_builtin_systems(),
+            ..Self::new()
+        };
+        stages
+            .add_system(Stage::FixedUpdate, systems::update_previous_transforms)
+            .label(systems::PREVIOUS_TRANSFORM);
+        stages
+            .add_system(Stage::Update, systems::update_assets)
+            .label(systems::ASSETS);
+        stages
+            .add_system(Stage::Update, systems::animate)
+            .label(systems::ANIMATION)
+            .after(systems::ASSETS);
+        stages
+            .add_system(Stage::Render, systems::interpolate_transforms_with_time)
+            .label(systems::INTERPOLATE_TRANSFORM)
+            .after(systems::TRANSFORM)
+            .before(systems::BOUNDS);
+        stages
+    }
+
+    /// Sets the fixed timestep of the [Stage::FixedUpdate] schedule.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the timestep is zero.
+    pub fn with_fixed_timestep(mut self, fixed_timestep: Duration) -> Self {
+        self.set_fixed_timestep(fixed_timestep);
+        self
+    }
+
+    /// Sets the maximum number of fixed updates run per frame. Time beyond that is dropped so a
+    /// slow frame doesn't cause ever slower frames.
+    pub fn with_max_fixed_steps(mut self, max_fixed_steps: u32) -> Self {
+        self.max_fixed_steps = max_fixed_steps;
+        self
+    }
+
+    /// Returns the fixed timestep of the [Stage::FixedUpdate] schedule.
+    pub fn fixed_timestep(&self) -> Duration {
+        self.fixed_timestep
+    }
+
+    /// Sets the fixed timestep of the [Stage::FixedUpdate] schedule.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the timestep is zero.
+    pub fn set_fixed_timestep(&mut self, fixed_timestep: Duration) {
+        assert!(!fixed_timestep.is_zero(), "fixed timestep must not be zero");
+        self.fixed_timestep = fixed_timestep;
+    }
+
+    /// Returns the time elapsed since the last fixed update that hasn't been simulated yet.
+    pub fn overstep(&self) -> Duration {
+        self.accumulator
+    }
+
+    /// Returns the elapsed time since the last fixed update as a fraction of the fixed timestep,
+    /// e.g. for interpolating between fixed updates.
+    pub fn overstep_fraction(&self) -> f32 {
+        self.accumulator.as_secs_f32() / self.fixed_timestep.as_secs_f32()
+    }
+
+    /// Returns the schedule of the stage.
+    pub fn schedule(&self, stage: Stage) -> &Schedule {
+        match stage {
+            Stage::FixedUpdate => &self.fixed_update,
+            Stage::Update => &self.update,
+            Stage::Render => &self.render,
+        }
+    }
+
+    /// Returns a mutable reference to the schedule of the stage.
+    pub fn schedule_mut(&mut self, stage: Stage) -> &mut Schedule {
+        match stage {
+            Stage::FixedUpdate => &mut self.fixed_update,
+            Stage::Update => &mut self.update,
+            Stage::Render => &mut self.render,
+        }
+    }
+
+    /// Enables or disables the systems with the given label in all of the stages. See
+    /// [Schedule::set_enabled].
+    pub fn set_enabled(&mut self, label: &'static str, enabled: bool) {
+        self.fixed_update.set_enabled(label, enabled);
+        self.update.set_enabled(label, enabled);
+        self.render.set_enabled(label, enabled);
+    }
+
+    /// Adds the system to the schedule of the stage. See [Schedule::add_system].
+    pub fn add_system<M>(&mut self, stage: Stage, system: impl IntoSystem<M>) -> SystemConfig<'_> {
+        self.schedule_mut(stage).add_system(system)
+    }
+
+    /// Resolves the order of the systems of all of the stages. See [Schedule::build].
+    pub fn build(&mut self) -> Result<(), systems::ScheduleError> {
+        self.fixed_update.build()?;
+        self.update.build()?;
+        self.render.build()
+    }
+
+    /// Runs the stages for a frame that took the given time. Returns the number of fixed updates
+    /// that were run. Updates the overstep fraction of the scene's [Time] resource, if any, before
+    /// running the [Stage::Update] schedule.
+    pub fn run(&mut self, scene: &mut Scene, delta: Duration) -> u32 {
+        self.accumulator += delta;
+
+        let mut steps = 0;
+        while self.accumulator >= self.fixed_timestep {
+            if steps == self.max_fixed_steps {
+                self.accumulator = Duration::from_nanos(
+                    (self.accumulator.as_nanos() % self.fixed_timestep.as_nanos()) as u64,
+                );
+                break;
+            }
+
+            self.fixed_update.run(scene);
+            self.accumulator -= self.fixed_timestep;
+            steps += 1;
+        }
+
+        let overstep_fraction = self.overstep_fraction();
+        if let Some(time) = scene.get_resource_mut::<Time>() {
+            time.set_overstep_fraction(overstep_fraction);
+        }
+
+        self.update.run(scene);
+        self.render.run(scene);
+        steps
+    }
+}
+
+impl Default for Stages {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec3;
+
+    use super::*;
+    use crate::LocalTransform;
+    use crate::PreviousWorldTransform;
+    use crate::WorldTransform;
+
+    fn count(scene: &mut Scene) {
+        *scene.get_resource_mut::<u32>().unwrap() += 1;
+    }
+
+    fn stages() -> (Stages, Scene) {
+        let mut stages = Stages::new().with_fixed_timestep(Duration::from_millis(10));
+        stages.add_system(Stage::FixedUpdate, count);
+        let mut scene = Scene::new();
+        scene.insert_resource(0u32);
+        (stages, scene)
+    }
+
+    #[test]
+    fn run_accumulates_time_between_frames() {
+        let (mut stages, mut scene) = stages();
+
+        assert_eq!(stages.run(&mut scene, Duration::from_millis(6)), 0);
+        assert_eq!(stages.run(&mut scene, Duration::from_millis(6)), 1);
+        assert_eq!(stages.run(&mut scene, Duration::from_millis(25)), 2);
+
+        assert_eq!(scene.get_resource::<u32>(), Some(&3));
+        assert_eq!(stages.overstep(), Duration::from_millis(7));
+    }
+
+    #[test]
+    fn run_slow_frame_drops_steps_beyond_max() {
+        let (stages, mut scene) = stages();
+        let mut stages = stages.with_max_fixed_steps(3);
+
+        assert_eq!(stages.run(&mut scene, Duration::from_millis(105)), 3);
+        assert_eq!(stages.overstep(), Duration::from_millis(5));
+    }
+
+    #[test]
+    fn run_runs_stages_in_order() {
+        let mut stages = Stages::new().with_fixed_timestep(Duration::from_millis(10));
+        for (stage, value) in [
+            (Stage::Render, 3),
+            (Stage::Update, 2),
+            (Stage::FixedUpdate, 1),
+        ] {
+            stages.add_system(stage, move |scene: &mut Scene| {
+                let mut values = scene.remove_resource::<Vec<u32>>().unwrap_or_default();
+                values.push(value);
+                scene.insert_resource(values);
+            });
+        }
+        let mut scene = Scene::new();
+
+        stages.run(&mut scene, Duration::from_millis(10));
+
+        assert_eq!(scene.get_resource::<Vec<u32>>(), Some(&vec![1, 2, 3]));
+    }
+
+    #[test]
+    fn run_builtin_systems_interpolates_fixed_movement() {
+        let mut stages =
+            Stages::with_builtin_systems().with_fixed_timestep(Duration::from_millis(10));
+        stages.add_system(Stage::FixedUpdate, |scene: &mut Scene| {
+            let nodes = scene
+                .query::<(LocalTransform,)>()
+                .map(|(node, _)| node)
+                .collect::<Vec<_>>();
+            for node in nodes {
+                scene.get_mut::<LocalTransform>(node).unwrap().position.x += 1.0;
+            }
+        });
+        let mut scene = Scene::new();
+        scene.insert_resource(Time::new());
+        let node = scene.spawn_with((LocalTransform::IDENTITY, PreviousWorldTransform::IDENTITY));
+        let position = |scene: &Scene| scene.get::<WorldTransform>(node).unwrap().translation();
+
+        stages.run(&mut scene, Duration::from_millis(15));
+        assert!(position(&scene).abs_diff_eq(Vec3::new(0.5, 0.0, 0.0), 1e-6));
+
+        scene.advance_change_tick();
+        stages.run(&mut scene, Duration::from_millis(4));
+        assert!(position(&scene).abs_diff_eq(Vec3::new(0.9, 0.0, 0.0), 1e-6));
+    }
+}
@@ -0,0 +1,243 @@
+use std::time::Duration;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) use std::time::Instant;
+#[cfg(target_arch = "wasm32")]
+pub(crate) use web_time::Instant;
+
+/// # Time
+///
+/// Frame timing maintained by the application runner as a [crate::Scene] resource. Use
+/// [Time::delta_seconds] for framerate-independent movement.
+///
+/// ```
+/// # use std::time::Duration;
+/// # use pulse::Time;
+/// let mut time = Time::new();
+/// time.set_time_scale(0.5);
+/// time.update(Duration::from_millis(20));
+///
+/// assert_eq!(time.delta(), Duration::from_millis(10));
+/// assert_eq!(time.unscaled_delta(), Duration::from_millis(20));
+/// assert_eq!(time.frame_count(), 1);
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Time {
+    delta: Duration,
+    elapsed: Duration,
+    unscaled_delta: Duration,
+    unscaled_elapsed: Duration,
+    frame_count: u64,
+    time_scale: f32,
+    overstep_fraction: f32,
+}
+
+impl Time {
+    /// Returns the time before the first frame with a time scale of `1.0`.
+    pub const fn new() -> Self {
+        Self {
+            delta: Duration::ZERO,
+            elapsed: Duration::ZERO,
+            unscaled_delta: Duration::ZERO,
+            unscaled_elapsed: Duration::ZERO,
+            frame_count: 0,
+            time_scale: 1.0,
+            overstep_fraction: 0.0,
+        }
+    }
+
+    /// Advances the time by a frame that took the given real time.
+    pub fn update(&mut self, unscaled_delta: Duration) {
+        self.unscaled_delta = unscaled_delta;
+        self.unscaled_elapsed += unscaled_delta;
+        self.delta = if self.time_scale == 1.0 {
+            unscaled_delta
+        } else {
+            unscaled_delta.mul_f64(f64::from(self.time_scale))
+        };
+        self.elapsed += self.delta;
+        self.frame_count += 1;
+    }
+
+    /// Returns the scaled time the last frame took.
+    pub fn delta(&self) -> Duration {
+        self.delta
+    }
+
+    /// Returns the scaled time the last frame took in seconds.
+    pub fn delta_seconds(&self) -> f32 {
+        self.delta.as_secs_f32()
+    }
+
+    /// Returns the scaled time elapsed since the first frame.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// Returns the scaled time elapsed since the first frame in seconds.
+    pub fn elapsed_seconds(&self) -> f64 {
+        self.elapsed.as_secs_f64()
+    }
+
+    /// Returns the real time the last frame took, ignoring the time scale.
+    pub fn unscaled_delta(&self) -> Duration {
+        self.unscaled_delta
+    }
+
+    /// Returns the real time elapsed since the first frame, ignoring the time scale.
+    pub fn unscaled_elapsed(&self) -> Duration {
+        self.unscaled_elapsed
+    }
+
+    /// Returns the number of frames since the application started.
+    pub fn frame_count(&self) -> u64 {
+        self.frame_count
+    }
+
+    /// Returns the factor the real time is multiplied by, e.g. `0.5` for slow motion or `0.0` to
+    /// pause.
+    pub fn time_scale(&self) -> f32 {
+        self.time_scale
+    }
+
+    /// Sets the factor the real time is multiplied by from the next frame on. Negative values are
+    /// treated as `0.0`.
+    pub fn set_time_scale(&mut self, time_scale: f32) {
+        self.time_scale = time_scale.max(0.0);
+    }
+
+    /// Returns the time elapsed since the last fixed update as a fraction of the fixed timestep,
+    /// as of the last [crate::systems::Stages::run].
+    pub fn overstep_fraction(&self) -> f32 {
+        self.overstep_fraction
+    }
+
+    /// Sets the time elapsed since the last fixed update as a fraction of the fixed timestep.
+    pub(crate) fn set_overstep_fraction(&mut self, overstep_fraction: f32) {
+        self.overstep_fraction = overstep_fraction;
+    }
+}
+
+impl Default for Time {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// # Frame Pacing
+///
+/// Limit on how often the application runner runs frames. The runner reads it from the scene
+/// resource every frame, so it can be inserted before [crate::Application::run] to configure it at
+/// startup or changed at runtime. Defaults to [FramePacing::Vsync] when there's no resource.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum FramePacing {
+    /// Frames are run as fast as possible.
+    Uncapped,
+    /// Frames are limited to the refresh rate of the window's display, or 60 per second if it's
+    /// unknown. On the web, frames are paced by `requestAnimationFrame` instead.
+    #[default]
+    Vsync,
+    /// Frames are limited to the target rate.
+    Limited {
+        /// Target number of frames per second.
+        fps: f64,
+        /// How the runner waits for the next frame.
+        wait: FrameWait,
+    },
+}
+
+impl FramePacing {
+    /// Refresh rate assumed for [FramePacing::Vsync] when the display's is unknown.
+    pub const DEFAULT_REFRESH_RATE: f64 = 60.0;
+
+    /// Returns the pacing limited to the target number of frames per second, sleeping between
+    /// frames.
+    pub fn limited(fps: f64) -> Self {
+        Self::Limited {
+            fps,
+            wait: FrameWait::Sleep,
+        }
+    }
+
+    /// Returns the minimum duration of a frame for a display with the given refresh rate, or
+    /// `None` if the frames are uncapped.
+    pub fn frame_duration(&self, refresh_rate: Option<f64>) -> Option<Duration> {
+        let fps = match *self {
+            Self::Uncapped => return None,
+            Self::Vsync => refresh_rate.unwrap_or(Self::DEFAULT_REFRESH_RATE),
+            Self::Limited { fps, .. } => fps,
+        };
+
+        (fps > 0.0).then(|| Duration::from_secs_f64(1.0 / fps))
+    }
+}
+
+/// # Frame Wait
+///
+/// How the runner waits for the next frame with [FramePacing::Limited].
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum FrameWait {
+    /// The event loop sleeps until the next frame. Cheap, but frames may start late by the
+    /// operating system's timer resolution.
+    #[default]
+    Sleep,
+    /// The event loop sleeps until shortly before the next frame, then spins until the frame is
+    /// due. Frame times are more precise at the cost of some CPU time.
+    Spin,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn update_accumulates_elapsed_time() {
+        let mut time = Time::new();
+
+        time.update(Duration::from_millis(10));
+        time.update(Duration::from_millis(30));
+
+        assert_eq!(time.delta(), Duration::from_millis(30));
+        assert_eq!(time.elapsed(), Duration::from_millis(40));
+        assert_eq!(time.frame_count(), 2);
+    }
+
+    #[test]
+    fn update_paused_does_not_advance_scaled_time() {
+        let mut time = Time::new();
+        time.set_time_scale(0.0);
+
+        time.update(Duration::from_millis(10));
+
+        assert_eq!(time.delta_seconds(), 0.0);
+        assert_eq!(time.elapsed(), Duration::ZERO);
+        assert_eq!(time.unscaled_elapsed(), Duration::from_millis(10));
+    }
+
+    #[test]
+    fn set_time_scale_negative_clamps_to_zero() {
+        let mut time = Time::new();
+
+        time.set_time_scale(-1.0);
+
+        assert_eq!(time.time_scale(), 0.0);
+    }
+
+    #[test]
+    fn frame_duration_uses_refresh_rate_for_vsync() {
+        assert_eq!(FramePacing::Uncapped.frame_duration(Some(144.0)), None);
+        assert_eq!(
+            FramePacing::Vsync.frame_duration(Some(50.0)),
+            Some(Duration::from_millis(20))
+        );
+        assert_eq!(
+            FramePacing::Vsync.frame_duration(None),
+            Some(Duration::from_secs_f64(1.0 / 60.0))
+        );
+        assert_eq!(
+            FramePacing::limited(25.0).frame_duration(Some(144.0)),
+            Some(Duration::from_millis(40))
+        );
+        assert_eq!(FramePacing::limited(0.0).frame_duration(None), None);
+    }
+}
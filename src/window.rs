@@ -0,0 +1,538 @@
+use glam::IVec2;
+use glam::UVec2;
+use glam::Vec2;
+
+use crate::Event;
+
+/// # Window
+///
+/// State of the application window maintained by the application runner as a [crate::Scene]
+/// resource. The state is updated from the [Event::Resized], [Event::ScaleFactorChanged], and
+/// [Event::Focused] events before they're passed to [crate::Application::handle_event].
+///
+/// ```
+/// # use glam::UVec2;
+/// # use glam::Vec2;
+/// # use pulse::Event;
+/// # use pulse::Window;
+/// let mut window = Window::new(UVec2::new(800, 600), 1.0);
+/// window.handle_event(&Event::ScaleFactorChanged { scale_factor: 2.0 });
+/// window.handle_event(&Event::Resized {
+///     width: 1600,
+///     height: 1200,
+/// });
+///
+/// assert_eq!(window.size(), UVec2::new(1600, 1200));
+/// assert_eq!(window.logical_size(), Vec2::new(800.0, 600.0));
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Window {
+    size: UVec2,
+    scale_factor: f64,
+    mode: WindowMode,
+    monitors: Vec<Monitor>,
+    current_monitor: Option<usize>,
+    cursor: Cursor,
+    cursor_position_request: Option<Vec2>,
+    ime_allowed: bool,
+    ime_cursor_area_request: Option<(Vec2, Vec2)>,
+    focused: bool,
+    icon_request: Option<Option<WindowIcon>>,
+    attention_request: Option<Option<UserAttention>>,
+    minimized_request: Option<bool>,
+    maximized_request: Option<bool>,
+}
+
+impl Window {
+    /// Returns the state of a window with the given inner size in physical pixels and scale
+    /// factor.
+    pub fn new(size: UVec2, scale_factor: f64) -> Self {
+        Self {
+            size,
+            scale_factor,
+            mode: WindowMode::Windowed,
+            monitors: Vec::new(),
+            current_monitor: None,
+            cursor: Cursor::default(),
+            cursor_position_request: None,
+            ime_allowed: false,
+            ime_cursor_area_request: None,
+            focused: true,
+            icon_request: None,
+            attention_request: None,
+            minimized_request: None,
+            maximized_request: None,
+        }
+    }
+
+    /// Returns the inner size of the window in physical pixels.
+    pub fn size(&self) -> UVec2 {
+        self.size
+    }
+
+    /// Returns the inner width of the window in physical pixels.
+    pub fn width(&self) -> u32 {
+        self.size.x
+    }
+
+    /// Returns the inner height of the window in physical pixels.
+    pub fn height(&self) -> u32 {
+        self.size.y
+    }
+
+    /// Returns the inner size of the window in logical pixels, i.e. the physical size divided by
+    /// the scale factor.
+    pub fn logical_size(&self) -> Vec2 {
+        self.size.as_vec2() / self.scale_factor as f32
+    }
+
+    /// Returns the ratio of physical to logical pixels, e.g. 2 on high DPI displays.
+    pub fn scale_factor(&self) -> f64 {
+        self.scale_factor
+    }
+
+    /// Returns the width of the window divided by its height, or 1 if the window is minimized.
+    pub fn aspect_ratio(&self) -> f32 {
+        if self.size.x == 0 || self.size.y == 0 {
+            1.0
+        } else {
+            self.size.x as f32 / self.size.y as f32
+        }
+    }
+
+    /// Returns the mode of the window.
+    pub fn mode(&self) -> WindowMode {
+        self.mode
+    }
+
+    /// Sets the mode of the window, e.g. to switch to fullscreen from a settings menu. The mode is
+    /// applied by the runner at the end of the frame.
+    pub fn set_mode(&mut self, mode: WindowMode) {
+        self.mode = mode;
+    }
+
+    /// Returns the available monitors.
+    pub fn monitors(&self) -> &[Monitor] {
+        &self.monitors
+    }
+
+    /// Returns the index of the monitor the window is on within [Window::monitors], or `None` if
+    /// it's unknown.
+    pub fn current_monitor(&self) -> Option<usize> {
+        self.current_monitor
+    }
+
+    /// Sets the available monitors and the index of the one the window is on.
+    pub(crate) fn set_monitors(&mut self, monitors: Vec<Monitor>, current_monitor: Option<usize>) {
+        self.monitors = monitors;
+        self.current_monitor = current_monitor;
+    }
+
+    /// Returns the cursor settings of the window.
+    pub fn cursor(&self) -> Cursor {
+        self.cursor
+    }
+
+    /// Sets how the cursor is grabbed by the window. Use [CursorGrab::Locked] along with
+    /// [Event::MouseMotion] for first-person camera controls. The runner falls back to the other
+    /// grab mode if the platform doesn't support the requested one.
+    pub fn set_cursor_grab(&mut self, grab: CursorGrab) {
+        self.cursor.grab = grab;
+    }
+
+    /// Sets whether the cursor is visible while it's over the window.
+    pub fn set_cursor_visible(&mut self, visible: bool) {
+        self.cursor.visible = visible;
+    }
+
+    /// Sets the icon of the cursor while it's over the window.
+    pub fn set_cursor_icon(&mut self, icon: CursorIcon) {
+        self.cursor.icon = icon;
+    }
+
+    /// Requests the cursor to be moved to the position in physical pixels relative to the top-left
+    /// corner of the window. The cursor is moved by the runner at the end of the frame.
+    pub fn set_cursor_position(&mut self, position: Vec2) {
+        self.cursor_position_request = Some(position);
+    }
+
+    /// Returns the cursor position requested through [Window::set_cursor_position] since the
+    /// previous call.
+    pub(crate) fn take_cursor_position_request(&mut self) -> Option<Vec2> {
+        self.cursor_position_request.take()
+    }
+
+    /// Returns true if the window accepts IME input.
+    pub fn is_ime_allowed(&self) -> bool {
+        self.ime_allowed
+    }
+
+    /// Sets whether the window accepts IME input, e.g. while a text field is focused. IME is
+    /// disallowed by default, as it may intercept key presses meant for gameplay.
+    pub fn set_ime_allowed(&mut self, allowed: bool) {
+        self.ime_allowed = allowed;
+    }
+
+    /// Requests the IME candidate window to be placed near the area in physical pixels relative to
+    /// the top-left corner of the window, e.g. the focused text field's cursor.
+    pub fn set_ime_cursor_area(&mut self, position: Vec2, size: Vec2) {
+        self.ime_cursor_area_request = Some((position, size));
+    }
+
+    /// Returns the IME cursor area requested through [Window::set_ime_cursor_area] since the
+    /// previous call.
+    pub(crate) fn take_ime_cursor_area_request(&mut self) -> Option<(Vec2, Vec2)> {
+        self.ime_cursor_area_request.take()
+    }
+
+    /// Returns true if the window has keyboard focus.
+    pub fn is_focused(&self) -> bool {
+        self.focused
+    }
+
+    /// Requests the window's icon in the title bar and taskbar to be set, or reset to the platform
+    /// default with `None`. The icon is set by the runner at the end of the frame.
+    pub fn set_icon(&mut self, icon: Option<WindowIcon>) {
+        self.icon_request = Some(icon);
+    }
+
+    /// Returns the icon requested through [Window::set_icon] since the previous call.
+    pub(crate) fn take_icon_request(&mut self) -> Option<Option<WindowIcon>> {
+        self.icon_request.take()
+    }
+
+    /// Requests the user's attention, e.g. by flashing the taskbar entry while the window isn't
+    /// focused, or cancels a previous request with `None`.
+    pub fn request_attention(&mut self, attention: Option<UserAttention>) {
+        self.attention_request = Some(attention);
+    }
+
+    /// Returns the attention requested through [Window::request_attention] since the previous
+    /// call.
+    pub(crate) fn take_attention_request(&mut self) -> Option<Option<UserAttention>> {
+        self.attention_request.take()
+    }
+
+    /// Requests the window to be minimized, or restored from being minimized.
+    pub fn set_minimized(&mut self, minimized: bool) {
+        self.minimized_request = Some(minimized);
+    }
+
+    /// Returns the minimized state requested through [Window::set_minimized] since the previous
+    /// call.
+    pub(crate) fn take_minimized_request(&mut self) -> Option<bool> {
+        self.minimized_request.take()
+    }
+
+    /// Requests the window to be maximized, or restored from being maximized.
+    pub fn set_maximized(&mut self, maximized: bool) {
+        self.maximized_request = Some(maximized);
+    }
+
+    /// Returns the maximized state requested through [Window::set_maximized] since the previous
+    /// call.
+    pub(crate) fn take_maximized_request(&mut self) -> Option<bool> {
+        self.maximized_request.take()
+    }
+
+    /// Updates the state from the event.
+    pub fn handle_event(&mut self, event: &Event) {
+        match *event {
+            Event::Resized { width, height } => self.size = UVec2::new(width, height),
+            Event::ScaleFactorChanged { scale_factor } => self.scale_factor = scale_factor,
+            Event::Focused(focused) => self.focused = focused,
+            _ => {}
+        }
+    }
+}
+
+impl Default for Window {
+    fn default() -> Self {
+        Self::new(UVec2::ZERO, 1.0)
+    }
+}
+
+/// # Window Icon
+///
+/// Image shown for the window in the title bar and taskbar. See [Window::set_icon].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct WindowIcon {
+    /// Pixels in 8-bit RGBA row by row from the top-left corner.
+    pub rgba: Vec<u8>,
+    /// Width of the image in pixels.
+    pub width: u32,
+    /// Height of the image in pixels.
+    pub height: u32,
+}
+
+impl WindowIcon {
+    /// Returns the icon for the pixels in 8-bit RGBA, or `None` if the number of pixels doesn't
+    /// match the size.
+    pub fn from_rgba(rgba: Vec<u8>, width: u32, height: u32) -> Option<Self> {
+        (rgba.len() as u64 == u64::from(width) * u64::from(height) * 4).then_some(Self {
+            rgba,
+            width,
+            height,
+        })
+    }
+
+    pub(crate) fn to_winit(&self) -> Option<winit::window::Icon> {
+        winit::window::Icon::from_rgba(self.rgba.clone(), self.width, self.height).ok()
+    }
+}
+
+/// # User Attention
+///
+/// Urgency of a [Window::request_attention] request.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum UserAttention {
+    /// Keeps notifying the user until the window is focused, e.g. by flashing the taskbar entry.
+    Critical,
+    /// Notifies the user once, e.g. by flashing the taskbar entry briefly.
+    Informational,
+}
+
+impl UserAttention {
+    pub(crate) fn to_winit(self) -> winit::window::UserAttentionType {
+        match self {
+            UserAttention::Critical => winit::window::UserAttentionType::Critical,
+            UserAttention::Informational => winit::window::UserAttentionType::Informational,
+        }
+    }
+}
+
+/// # Window Mode
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum WindowMode {
+    /// Window with decorations that can be moved and resized.
+    Windowed,
+    /// Window without decorations covering the whole monitor at the monitor's current video mode.
+    BorderlessFullscreen {
+        /// Index of the monitor within [Window::monitors], or `None` for the current monitor.
+        monitor: Option<usize>,
+    },
+    /// Window with exclusive access to the monitor, switching it to the video mode.
+    Fullscreen {
+        /// Index of the monitor within [Window::monitors].
+        monitor: usize,
+        /// One of the monitor's [Monitor::video_modes].
+        video_mode: VideoMode,
+    },
+}
+
+/// # Monitor
+///
+/// Monitor available to the application window. See [Window::monitors].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Monitor {
+    /// Human-readable name of the monitor, or `None` if the monitor doesn't exist anymore.
+    pub name: Option<String>,
+    /// Position of the monitor's top-left corner in physical pixels on the desktop.
+    pub position: IVec2,
+    /// Current resolution of the monitor in physical pixels.
+    pub size: UVec2,
+    /// Ratio of physical to logical pixels of the monitor.
+    pub scale_factor: f64,
+    /// Current refresh rate of the monitor in hertz, or `None` if it's unknown.
+    pub refresh_rate: Option<f64>,
+    /// Video modes the monitor supports in exclusive fullscreen.
+    pub video_modes: Vec<VideoMode>,
+}
+
+impl Monitor {
+    pub(crate) fn from_winit(monitor: &winit::monitor::MonitorHandle) -> Self {
+        let position = monitor.position();
+        let size = monitor.size();
+        Self {
+            name: monitor.name(),
+            position: IVec2::new(position.x, position.y),
+            size: UVec2::new(size.width, size.height),
+            scale_factor: monitor.scale_factor(),
+            refresh_rate: monitor
+                .refresh_rate_millihertz()
+                .map(|millihertz| f64::from(millihertz) / 1000.0),
+            video_modes: monitor
+                .video_modes()
+                .map(|video_mode| VideoMode::from_winit(&video_mode))
+                .collect(),
+        }
+    }
+}
+
+/// # Video Mode
+///
+/// Resolution and refresh rate of a monitor in exclusive fullscreen.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct VideoMode {
+    /// Resolution in physical pixels.
+    pub size: UVec2,
+    /// Number of bits per pixel.
+    pub bit_depth: u16,
+    /// Refresh rate in hertz.
+    pub refresh_rate: f64,
+}
+
+impl VideoMode {
+    pub(crate) fn from_winit(video_mode: &winit::monitor::VideoMode) -> Self {
+        let size = video_mode.size();
+        Self {
+            size: UVec2::new(size.width, size.height),
+            bit_depth: video_mode.bit_depth(),
+            refresh_rate: f64::from(video_mode.refresh_rate_millihertz()) / 1000.0,
+        }
+    }
+}
+
+/// # Cursor
+///
+/// Cursor settings of the application window. See [Window::cursor].
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Cursor {
+    /// How the cursor is grabbed by the window.
+    pub grab: CursorGrab,
+    /// Whether the cursor is visible while it's over the window.
+    pub visible: bool,
+    /// Icon of the cursor while it's over the window.
+    pub icon: CursorIcon,
+}
+
+impl Default for Cursor {
+    fn default() -> Self {
+        Self {
+            grab: CursorGrab::None,
+            visible: true,
+            icon: CursorIcon::Default,
+        }
+    }
+}
+
+/// # Cursor Grab
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum CursorGrab {
+    /// Cursor moves freely.
+    None,
+    /// Cursor is confined to the window area.
+    Confined,
+    /// Cursor is locked in place, e.g. for first-person camera controls.
+    Locked,
+}
+
+impl CursorGrab {
+    pub(crate) fn to_winit(self) -> winit::window::CursorGrabMode {
+        match self {
+            CursorGrab::None => winit::window::CursorGrabMode::None,
+            CursorGrab::Confined => winit::window::CursorGrabMode::Confined,
+            CursorGrab::Locked => winit::window::CursorGrabMode::Locked,
+        }
+    }
+}
+
+/// # Cursor Icon
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum CursorIcon {
+    /// Platform-dependent default cursor, usually an arrow.
+    Default,
+    /// Pointing hand, e.g. over links.
+    Pointer,
+    /// Text selection caret.
+    Text,
+    /// Crosshair.
+    Crosshair,
+    /// Something can be moved.
+    Move,
+    /// Something can be grabbed.
+    Grab,
+    /// Something is being grabbed.
+    Grabbing,
+    /// Requested action isn't allowed.
+    NotAllowed,
+    /// Application is busy and can't be interacted with.
+    Wait,
+    /// Application is busy but can still be interacted with.
+    Progress,
+    /// Help is available.
+    Help,
+    /// Horizontal resize.
+    EwResize,
+    /// Vertical resize.
+    NsResize,
+    /// Diagonal resize from the bottom-left to the top-right corner.
+    NeswResize,
+    /// Diagonal resize from the top-left to the bottom-right corner.
+    NwseResize,
+    /// Zoom in.
+    ZoomIn,
+    /// Zoom out.
+    ZoomOut,
+}
+
+impl CursorIcon {
+    pub(crate) fn to_winit(self) -> winit::window::CursorIcon {
+        match self {
+            CursorIcon::Default => winit::window::CursorIcon::Default,
+            CursorIcon::Pointer => winit::window::CursorIcon::Pointer,
+            CursorIcon::Text => winit::window::CursorIcon::Text,
+            CursorIcon::Crosshair => winit::window::CursorIcon::Crosshair,
+            CursorIcon::Move => winit::window::CursorIcon::Move,
+            CursorIcon::Grab => winit::window::CursorIcon::Grab,
+            CursorIcon::Grabbing => winit::window::CursorIcon::Grabbing,
+            CursorIcon::NotAllowed => winit::window::CursorIcon::NotAllowed,
+            CursorIcon::Wait => winit::window::CursorIcon::Wait,
+            CursorIcon::Progress => winit::window::CursorIcon::Progress,
+            CursorIcon::Help => winit::window::CursorIcon::Help,
+            CursorIcon::EwResize => winit::window::CursorIcon::EwResize,
+            CursorIcon::NsResize => winit::window::CursorIcon::NsResize,
+            CursorIcon::NeswResize => winit::window::CursorIcon::NeswResize,
+            CursorIcon::NwseResize => winit::window::CursorIcon::NwseResize,
+            CursorIcon::ZoomIn => winit::window::CursorIcon::ZoomIn,
+            CursorIcon::ZoomOut => winit::window::CursorIcon::ZoomOut,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn aspect_ratio_of_minimized_window_is_one() {
+        let mut window = Window::new(UVec2::new(1920, 1080), 1.0);
+        assert_eq!(window.aspect_ratio(), 1920.0 / 1080.0);
+
+        window.handle_event(&Event::Resized {
+            width: 0,
+            height: 0,
+        });
+
+        assert_eq!(window.aspect_ratio(), 1.0);
+    }
+
+    #[test]
+    fn take_cursor_position_request_returns_latest_request_once() {
+        let mut window = Window::default();
+        window.set_cursor_position(Vec2::new(10.0, 20.0));
+        window.set_cursor_position(Vec2::new(30.0, 40.0));
+
+        assert_eq!(
+            window.take_cursor_position_request(),
+            Some(Vec2::new(30.0, 40.0))
+        );
+        assert_eq!(window.take_cursor_position_request(), None);
+    }
+
+    #[test]
+    fn focused_follows_focus_events() {
+        let mut window = Window::default();
+        assert!(window.is_focused());
+
+        window.handle_event(&Event::Focused(false));
+
+        assert!(!window.is_focused());
+    }
+
+    #[test]
+    fn window_icon_from_rgba_checks_size() {
+        assert!(WindowIcon::from_rgba(vec![0; 2 * 2 * 4], 2, 2).is_some());
+        assert!(WindowIcon::from_rgba(vec![0; 3], 2, 2).is_none());
+    }
+}